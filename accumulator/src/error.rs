@@ -15,6 +15,19 @@ pub enum ProvingError {
         /// The number of leaves
         count: usize,
     },
+    /// Generated proof path has the wrong length
+    #[error(transparent)]
+    ProofLength(#[from] ProofLengthError),
+}
+
+/// A proof path has the wrong number of elements
+#[derive(Debug, PartialEq, Eq, Clone, Copy, thiserror::Error)]
+#[error("Proof path has {got} elements. Expected {expected}")]
+pub struct ProofLengthError {
+    /// Required number of elements
+    pub expected: usize,
+    /// Number of elements found
+    pub got: usize,
 }
 
 /// Tree Errors
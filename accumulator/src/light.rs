@@ -162,10 +162,8 @@ pub mod arrays {
                     None => return Err(serde::de::Error::invalid_length(N, &self)),
                 }
             }
-            match data.try_into() {
-                Ok(arr) => Ok(arr),
-                Err(_) => unreachable!(),
-            }
+            data.try_into()
+                .map_err(|data: Vec<T>| serde::de::Error::invalid_length(data.len(), &self))
         }
     }
 
@@ -182,6 +180,9 @@ pub mod arrays {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{ProvingError, Tree};
+    use ethers::utils::hash_message;
+
     #[test]
     fn it_calculates_the_initial_root() {
         assert_eq!(
@@ -192,28 +193,54 @@ mod test {
         );
     }
 
-    //     use ethers::utils::hash_message;
-    //     use super::*;
-    //     use crate::test_utils;
-    //     #[test]
-    //     fn it_computes_branch_roots() {
-    //         let test_cases = test_utils::load_merkle_test_json();
-    //         for test_case in test_cases.iter() {
-    //             let mut tree = IncrementalMerkle::default();
-    //             // insert the leaves
-    //             for leaf in test_case.leaves.iter() {
-    //                 let hashed_leaf = hash_message(leaf);
-    //                 tree.ingest(hashed_leaf);
-    //             }
-    //             // assert the tree has the proper leaf count
-    //             assert_eq!(tree.count(), test_case.leaves.len());
-    //             // assert the tree generates the proper root
-    //             let root = tree.root(); // root is type H256
-    //             assert_eq!(root, test_case.expected_root);
-    //             for n in 0..test_case.leaves.len() {
-    //                 // check that the tree can verify the proof for this leaf
-    //                 assert!(tree.verify(&test_case.proofs[n]));
-    //             }
-    //         }
-    //     }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct MerkleTestCase {
+        test_name: String,
+        leaves: Vec<String>,
+        proofs: Vec<Proof<32>>,
+        expected_root: H256,
+    }
+
+    /// Trees and proofs captured from the Solidity `MerkleLib`
+    fn contract_fixtures() -> Vec<MerkleTestCase> {
+        serde_json::from_str(include_str!("../../fixtures/merkle.json")).unwrap()
+    }
+
+    #[test]
+    fn it_matches_the_contract_fixtures() {
+        for case in contract_fixtures() {
+            let leaves: Vec<H256> = case.leaves.iter().map(hash_message).collect();
+            let light = LightMerkle::<32>::from_leaves(&leaves);
+            let full = Tree::<32>::from_leaves(&leaves);
+
+            assert_eq!(light.count(), leaves.len(), "{}", case.test_name);
+            assert_eq!(light.root(), case.expected_root, "{}", case.test_name);
+            assert_eq!(full.root(), case.expected_root, "{}", case.test_name);
+
+            for expected in case.proofs.iter() {
+                let proof = full.prove(expected.index).unwrap();
+                assert_eq!(&proof, expected, "{}", case.test_name);
+                assert!(light.verify(&proof), "{}", case.test_name);
+            }
+            // leaves not ingested yet have no proof
+            assert!(matches!(
+                full.prove(leaves.len()),
+                Err(ProvingError::ZeroProof { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn its_zero_hashes_match_the_contract() {
+        let cases = contract_fixtures();
+        let one_leaf = cases
+            .iter()
+            .find(|case| case.leaves.len() == 1)
+            .expect("no single leaf fixture");
+
+        // The siblings of the only leaf are the empty subtrees of each level
+        let path = one_leaf.proofs[0].path;
+        assert_eq!(&path[..], &ZERO_HASHES[..32]);
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{merkle_root_from_branch, MerkleProof};
+use crate::{merkle_root_from_branch, MerkleProof, ProofLengthError};
 use ethers::prelude::H256;
 
 /// A merkle proof object. The leaf, its path to the root, and its index in the
@@ -15,8 +15,20 @@ pub struct Proof<const N: usize> {
     pub path: [H256; N],
 }
 
+impl<const N: usize> Proof<N> {
+    /// Instantiate a proof from a path slice. Fails unless the path has
+    /// exactly `N` elements.
+    pub fn from_slice(leaf: H256, index: usize, path: &[H256]) -> Result<Self, ProofLengthError> {
+        let path = path.try_into().map_err(|_| ProofLengthError {
+            expected: N,
+            got: path.len(),
+        })?;
+        Ok(Self { leaf, index, path })
+    }
+}
+
 mod const_array_serde {
-    use super::H256;
+    use super::{ProofLengthError, H256};
     use serde::{ser::SerializeSeq, Deserialize, Deserializer, Serializer};
 
     pub fn serialize<S, const N: usize>(item: &[H256; N], serializer: S) -> Result<S::Ok, S::Error>
@@ -35,17 +47,12 @@ mod const_array_serde {
         D: Deserializer<'de>,
     {
         let v: Vec<H256> = Deserialize::deserialize(d)?;
-        if v.len() != N {
-            Err(serde::de::Error::custom(format!(
-                "Expected a sequence with {} elements. Got {} elements",
-                N,
-                v.len()
-            )))
-        } else {
-            let mut h: [H256; N] = [Default::default(); N];
-            h.copy_from_slice(&v[..N]);
-            Ok(h)
-        }
+        v.as_slice().try_into().map_err(|_| {
+            serde::de::Error::custom(ProofLengthError {
+                expected: N,
+                got: v.len(),
+            })
+        })
     }
 }
 
@@ -70,10 +70,7 @@ impl<const N: usize> Tree<N> {
         }
 
         let (leaf, nodes) = self.tree.generate_proof(index, N);
-        debug_assert_eq!(nodes.len(), N);
-        let mut path = [H256::default(); N];
-        path.copy_from_slice(&nodes[..N]);
-        Ok(Proof { leaf, index, path })
+        Ok(Proof::from_slice(leaf, index, &nodes)?)
     }
 }
 
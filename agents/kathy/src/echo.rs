@@ -0,0 +1,419 @@
+//! Round-trip echo probes
+//!
+//! Kathy dispatches an echo request from her home to the echo xApp on a
+//! remote. The echo xApp answers each request by dispatching a reply back to
+//! the echo xApp on kathy's home network. Kathy measures the latency of each
+//! leg from the replicas' message status.
+
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use color_eyre::{eyre::bail, Result};
+use ethers::{core::types::H256, signers::Signer};
+use nomad_base::{ChainSetup, Homes, Replicas, Settings};
+use nomad_core::{CanonicalH256, FromSignerConf, Home, MessageStatus, NomadMessage, Replica};
+use nomad_ethereum::EthereumSigners;
+use nomad_xyz_configuration::{agent::kathy::EchoConfig, ethereum, TxSubmitterConf};
+use tracing::{info, warn};
+
+/// Type byte of an echo request body
+pub(crate) const ECHO_REQUEST: u8 = 1;
+
+/// Type byte of an echo reply body
+pub(crate) const ECHO_REPLY: u8 = 2;
+
+/// Echo bodies use the raw form of the bridge message codec: a leading type
+/// byte followed by fixed-width fields, here the 32-byte request id. The echo
+/// xApp replies with the request's body, with the type byte set to
+/// [`ECHO_REPLY`].
+pub(crate) const ECHO_BODY_LEN: usize = 33;
+
+/// Most candidate nonces checked per leg. Other traffic dispatched between
+/// the nonce reads widens the range.
+const MAX_CANDIDATES: u32 = 64;
+
+/// Encode an echo body
+pub(crate) fn echo_body(kind: u8, request_id: H256) -> Vec<u8> {
+    let mut body = Vec::with_capacity(ECHO_BODY_LEN);
+    body.push(kind);
+    body.extend_from_slice(request_id.as_bytes());
+    body
+}
+
+/// Leaves of a message at each nonce in `nonces`. The message with the
+/// matching nonce is the only one of these that can exist.
+pub(crate) fn candidate_leaves(
+    origin: u32,
+    sender: H256,
+    nonces: Range<u32>,
+    destination: u32,
+    recipient: H256,
+    body: &[u8],
+) -> Vec<H256> {
+    let end = nonces.end.min(nonces.start.saturating_add(MAX_CANDIDATES));
+    (nonces.start..end)
+        .map(|nonce| {
+            NomadMessage {
+                origin,
+                sender,
+                nonce,
+                destination,
+                recipient,
+                body: body.to_vec(),
+            }
+            .to_leaf()
+        })
+        .collect()
+}
+
+/// Progress of an echo probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EchoOutcome {
+    /// Still waiting on a leg
+    Pending,
+    /// The request was processed on the remote
+    OutboundDelivered(Duration),
+    /// The reply was processed on the home network
+    ReturnDelivered(Duration),
+    /// The request was not processed on the remote within the deadline
+    MissingOutbound,
+    /// The request was processed, but the reply was not processed within the
+    /// deadline
+    MissingReturn,
+}
+
+/// Per-leg echo metrics of a single route
+#[derive(Debug, Clone)]
+pub struct EchoMetrics {
+    /// Seconds from dispatch of a request to its processing on the remote
+    pub(crate) outbound_latency: prometheus::Histogram,
+    /// Seconds from processing of a request to processing of its reply
+    pub(crate) return_latency: prometheus::Histogram,
+    /// Requests not processed on the remote by the deadline
+    pub(crate) missing_outbound: prometheus::IntCounter,
+    /// Replies not processed on the home by the deadline
+    pub(crate) missing_return: prometheus::IntCounter,
+}
+
+impl EchoMetrics {
+    /// Record an outcome. Returns whether the probe is still in flight.
+    pub(crate) fn record(&self, outcome: EchoOutcome) -> bool {
+        match outcome {
+            EchoOutcome::Pending => return true,
+            EchoOutcome::OutboundDelivered(latency) => {
+                self.outbound_latency.observe(latency.as_secs_f64());
+                return true;
+            }
+            EchoOutcome::ReturnDelivered(latency) => {
+                self.return_latency.observe(latency.as_secs_f64())
+            }
+            EchoOutcome::MissingOutbound => self.missing_outbound.inc(),
+            EchoOutcome::MissingReturn => self.missing_return.inc(),
+        }
+        false
+    }
+}
+
+/// A single echo request in flight
+#[derive(Debug, Clone)]
+pub(crate) struct EchoProbe {
+    request_id: H256,
+    dispatched_at: Instant,
+    /// Candidate leaves of the request
+    outbound: Vec<H256>,
+    /// Remote home's nonce to the home network at dispatch. The reply nonce
+    /// is at least this.
+    return_from: u32,
+    outbound_at: Option<Instant>,
+    /// Candidate leaves of the reply, known once the request is processed
+    returns: Vec<H256>,
+}
+
+impl EchoProbe {
+    /// Whether a leg has run past the deadline. The outbound leg is timed
+    /// from dispatch, the return leg from processing of the request.
+    pub(crate) fn overdue(&self, now: Instant, deadline: Duration) -> Option<EchoOutcome> {
+        match self.outbound_at {
+            None if now.duration_since(self.dispatched_at) > deadline => {
+                Some(EchoOutcome::MissingOutbound)
+            }
+            Some(at) if now.duration_since(at) > deadline => Some(EchoOutcome::MissingReturn),
+            _ => None,
+        }
+    }
+}
+
+/// Echo route from kathy's home to a single remote and back
+#[derive(Debug)]
+pub struct EchoRoute {
+    home_domain: u32,
+    remote_domain: u32,
+    /// Kathy's transaction signer, the sender of requests
+    sender: H256,
+    /// Echo xApp on the remote
+    remote_recipient: H256,
+    /// Echo xApp on kathy's home network
+    home_recipient: H256,
+    /// Home on the remote, dispatching replies
+    remote_home: Homes,
+    /// Replica of the remote home on kathy's home network
+    return_replica: Replicas,
+    deadline: Duration,
+}
+
+impl EchoRoute {
+    /// Build the echo route to `remote`
+    pub(crate) async fn from_settings(
+        config: &EchoConfig,
+        settings: &Settings,
+        remote: &str,
+    ) -> Result<Self> {
+        let home = &settings.home;
+        let (remote_recipient, home_recipient) = match (
+            config.recipients.get(remote),
+            config.recipients.get(&home.name),
+        ) {
+            (Some(remote), Some(home)) => (*remote, *home),
+            _ => bail!("Echo requires recipients for {} and {}", remote, home.name),
+        };
+        let returns = match config.returns.get(remote) {
+            Some(returns) => returns,
+            None => bail!("Echo requires return contracts for {}", remote),
+        };
+        let remote_setup = match settings.replicas.get(remote) {
+            Some(setup) => setup,
+            None => bail!("No chain setup for echo remote {}", remote),
+        };
+
+        // Requests are matched by leaf, which commits to the sender
+        let sender = match settings.get_submitter_conf(&home.name) {
+            Some(TxSubmitterConf::Ethereum(ethereum::TxSubmitterConf::Local(conf))) => {
+                EthereumSigners::try_from_signer_conf(&conf)
+                    .await?
+                    .address()
+            }
+            _ => bail!("Echo requires a local signer for {}", home.name),
+        };
+
+        let remote_home = ChainSetup {
+            address: Some(returns.home),
+            ..remote_setup.clone()
+        }
+        .try_into_home(settings.get_submitter_conf(remote), None, None)
+        .await?;
+        let return_replica = ChainSetup {
+            name: remote.to_owned(),
+            address: Some(returns.replica),
+            ..home.clone()
+        }
+        .try_into_replica(settings.get_submitter_conf(&home.name), None)
+        .await?;
+
+        Ok(Self {
+            home_domain: home.domain,
+            remote_domain: remote_setup.domain,
+            sender: sender.into(),
+            remote_recipient: remote_recipient.into(),
+            home_recipient: home_recipient.into(),
+            remote_home,
+            return_replica,
+            deadline: Duration::from_secs(config.deadline_seconds),
+        })
+    }
+
+    /// Echo xApp on the remote
+    pub(crate) fn recipient(&self) -> H256 {
+        self.remote_recipient
+    }
+
+    /// Read the remote home's nonce ahead of dispatching a request
+    pub(crate) async fn return_nonce(&self) -> Result<u32> {
+        Ok(self.remote_home.nonces(self.home_domain).await?)
+    }
+
+    /// Start tracking a request dispatched at one of `nonces`
+    pub(crate) fn probe(
+        &self,
+        request_id: H256,
+        nonces: Range<u32>,
+        return_from: u32,
+    ) -> EchoProbe {
+        EchoProbe {
+            request_id,
+            dispatched_at: Instant::now(),
+            outbound: candidate_leaves(
+                self.home_domain,
+                self.sender,
+                nonces,
+                self.remote_domain,
+                self.remote_recipient,
+                &echo_body(ECHO_REQUEST, request_id),
+            ),
+            return_from,
+            outbound_at: None,
+            returns: vec![],
+        }
+    }
+
+    /// Advance a probe. `replica` is the replica of kathy's home on the
+    /// remote.
+    pub(crate) async fn poll<R: Replica>(
+        &self,
+        probe: &mut EchoProbe,
+        replica: &R,
+    ) -> Result<EchoOutcome> {
+        let now = Instant::now();
+
+        if probe.outbound_at.is_none() {
+            for leaf in probe.outbound.iter() {
+                if let MessageStatus::Processed = replica.message_status(*leaf).await? {
+                    probe.outbound_at = Some(now);
+
+                    // The reply is dispatched when the request is processed
+                    let return_to = self.remote_home.nonces(self.home_domain).await?;
+                    probe.returns = candidate_leaves(
+                        self.remote_domain,
+                        self.remote_recipient,
+                        probe.return_from..return_to,
+                        self.home_domain,
+                        self.home_recipient,
+                        &echo_body(ECHO_REPLY, probe.request_id),
+                    );
+
+                    info!(
+                        request_id = %CanonicalH256(probe.request_id),
+                        leaf = %CanonicalH256(*leaf),
+                        "Echo request processed on remote"
+                    );
+                    return Ok(EchoOutcome::OutboundDelivered(
+                        now.duration_since(probe.dispatched_at),
+                    ));
+                }
+            }
+        } else {
+            for leaf in probe.returns.iter() {
+                if let MessageStatus::Processed = self.return_replica.message_status(*leaf).await? {
+                    info!(
+                        request_id = %CanonicalH256(probe.request_id),
+                        leaf = %CanonicalH256(*leaf),
+                        "Echo reply processed on home"
+                    );
+                    let outbound_at = probe.outbound_at.expect("checked");
+                    return Ok(EchoOutcome::ReturnDelivered(
+                        now.duration_since(outbound_at),
+                    ));
+                }
+            }
+        }
+
+        match probe.overdue(now, self.deadline) {
+            Some(outcome) => {
+                warn!(
+                    request_id = %CanonicalH256(probe.request_id),
+                    outcome = ?outcome,
+                    deadline = self.deadline.as_secs(),
+                    "Echo leg missed its deadline"
+                );
+                Ok(outcome)
+            }
+            None => Ok(EchoOutcome::Pending),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_encodes_echo_bodies() {
+        let request_id = H256::repeat_byte(0xab);
+        let request = echo_body(ECHO_REQUEST, request_id);
+        let reply = echo_body(ECHO_REPLY, request_id);
+
+        assert_eq!(request.len(), ECHO_BODY_LEN);
+        assert_eq!(request[0], ECHO_REQUEST);
+        assert_eq!(&request[1..], request_id.as_bytes());
+
+        // A reply differs from its request only in the type byte
+        assert_eq!(reply[0], ECHO_REPLY);
+        assert_eq!(request[1..], reply[1..]);
+    }
+
+    #[test]
+    fn it_matches_the_reply_leaf_among_candidates() {
+        let request_id = H256::repeat_byte(1);
+        let body = echo_body(ECHO_REPLY, request_id);
+        let reply = NomadMessage {
+            origin: 2000,
+            sender: H256::repeat_byte(2),
+            nonce: 7,
+            destination: 1000,
+            recipient: H256::repeat_byte(3),
+            body: body.clone(),
+        };
+
+        let candidates = candidate_leaves(
+            2000,
+            H256::repeat_byte(2),
+            5..9,
+            1000,
+            H256::repeat_byte(3),
+            &body,
+        );
+        assert_eq!(candidates.len(), 4);
+        assert_eq!(candidates[2], reply.to_leaf());
+
+        // A different request id never matches
+        let other = candidate_leaves(
+            2000,
+            H256::repeat_byte(2),
+            5..9,
+            1000,
+            H256::repeat_byte(3),
+            &echo_body(ECHO_REPLY, H256::repeat_byte(9)),
+        );
+        assert!(!other.contains(&reply.to_leaf()));
+
+        assert_eq!(
+            candidate_leaves(0, H256::zero(), 0..1000, 0, H256::zero(), &body).len(),
+            MAX_CANDIDATES as usize
+        );
+    }
+
+    #[test]
+    fn it_reports_missing_legs_distinctly() {
+        let deadline = Duration::from_secs(60);
+        let start = Instant::now();
+        let mut probe = EchoProbe {
+            request_id: H256::zero(),
+            dispatched_at: start,
+            outbound: vec![],
+            return_from: 0,
+            outbound_at: None,
+            returns: vec![],
+        };
+
+        assert_eq!(
+            probe.overdue(start + Duration::from_secs(30), deadline),
+            None
+        );
+        assert_eq!(
+            probe.overdue(start + Duration::from_secs(61), deadline),
+            Some(EchoOutcome::MissingOutbound)
+        );
+
+        // The return leg is timed from processing of the request
+        probe.outbound_at = Some(start + Duration::from_secs(50));
+        assert_eq!(
+            probe.overdue(start + Duration::from_secs(61), deadline),
+            None
+        );
+        assert_eq!(
+            probe.overdue(start + Duration::from_secs(111), deadline),
+            Some(EchoOutcome::MissingReturn)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use color_eyre::Result;
 
@@ -6,24 +6,39 @@ use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use tokio::{sync::Mutex, task::JoinHandle, time::sleep};
 use tracing::instrument::Instrumented;
-use tracing::{info, Instrument};
+use tracing::{info, warn, Instrument};
 
 use ethers::core::types::H256;
-use nomad_base::{decl_agent, decl_channel, AgentCore, CachingHome, CachingReplica, NomadAgent};
-use nomad_core::{Common, Home, Message, Replica};
+use nomad_base::{
+    decl_agent, decl_channel, AgentCore, CachingHome, CachingReplica, ChainCommunicationError,
+    NomadAgent,
+};
+use nomad_core::{
+    Address32, CanonicalH256, ChecksumAddress, Common, DispatchOutcome, Home, Message, Replica,
+};
 use nomad_xyz_configuration::agent::kathy::ChatGenConfig;
 
+use crate::echo::{echo_body, EchoMetrics, EchoRoute, ECHO_REQUEST};
 use crate::settings::KathySettings as Settings;
 
 decl_agent!(Kathy {
     interval: u64,
     generator: ChatGenerator,
+    echo_routes: HashMap<String, Arc<EchoRoute>>,
     home_lock: Arc<Mutex<()>>,
     messages_dispatched: prometheus::IntCounterVec,
+    home_failed: prometheus::IntGaugeVec,
+    echo_latency: prometheus::HistogramVec,
+    echo_missing: prometheus::IntCounterVec,
 });
 
 impl Kathy {
-    pub fn new(interval: u64, generator: ChatGenerator, core: AgentCore) -> Self {
+    pub fn new(
+        interval: u64,
+        generator: ChatGenerator,
+        echo_routes: HashMap<String, Arc<EchoRoute>>,
+        core: AgentCore,
+    ) -> Self {
         let messages_dispatched = core
             .metrics
             .new_int_counter(
@@ -33,20 +48,165 @@ impl Kathy {
             )
             .expect("failed to register messages_dispatched_count metric");
 
+        let home_failed = core
+            .metrics
+            .new_int_gauge_vec(
+                "home_failed",
+                "Set to 1 while sending is paused because the home is in failed state.",
+                &["home", "replica", "agent"],
+            )
+            .expect("failed to register home_failed metric");
+
+        let echo_latency = core
+            .metrics
+            .new_histogram(
+                "echo_leg_latency_seconds",
+                "Seconds for an echo leg to be processed, by leg (outbound or return).",
+                &["home", "replica", "leg", "agent"],
+                &[
+                    60.0, 300.0, 600.0, 1200.0, 1800.0, 2400.0, 3600.0, 5400.0, 7200.0, 10800.0,
+                ],
+            )
+            .expect("failed to register echo_leg_latency_seconds metric");
+
+        let echo_missing = core
+            .metrics
+            .new_int_counter(
+                "echo_missing_count",
+                "Number of echo legs not processed by the deadline, by leg (outbound or return).",
+                &["home", "replica", "leg", "agent"],
+            )
+            .expect("failed to register echo_missing_count metric");
+
         Self {
             interval,
             generator,
+            echo_routes,
             core,
             home_lock: Arc::new(Mutex::new(())),
             messages_dispatched,
+            home_failed,
+            echo_latency,
+            echo_missing,
         }
     }
 }
 
+impl Kathy {
+    fn echo_metrics(&self, replica: &str) -> EchoMetrics {
+        let home = self.home();
+        let outbound = [home.name(), replica, "outbound", Self::AGENT_NAME];
+        let ret = [home.name(), replica, "return", Self::AGENT_NAME];
+
+        EchoMetrics {
+            outbound_latency: self.echo_latency.with_label_values(&outbound),
+            return_latency: self.echo_latency.with_label_values(&ret),
+            missing_outbound: self.echo_missing.with_label_values(&outbound),
+            missing_return: self.echo_missing.with_label_values(&ret),
+        }
+    }
+}
+
+/// Dispatch a message, pausing while the home is in failed state. Sending
+/// resumes with the same message once the home recovers. Returns where the
+/// message landed in the home's tree.
+async fn dispatch_or_pause(
+    home: &CachingHome,
+    message: &Message,
+    home_failed: &prometheus::IntGauge,
+    interval: u64,
+) -> Result<DispatchOutcome> {
+    let outcome = loop {
+        match home.dispatch_tracked(message).await {
+            Ok(outcome) => break outcome,
+            Err(ChainCommunicationError::HomeFailed(name)) => {
+                if home_failed.get() == 0 {
+                    warn!(
+                        home = name.as_str(),
+                        "Home {} is in failed state. Pausing sending until it recovers.", name
+                    );
+                    home_failed.set(1);
+                }
+                sleep(Duration::from_secs(interval)).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    if home_failed.get() != 0 {
+        info!(
+            home = home.name(),
+            "Home {} recovered from failed state. Resuming sending.",
+            home.name()
+        );
+        home_failed.set(0);
+    }
+
+    Ok(outcome)
+}
+
+/// Send echo requests to the echo xApp of a remote and track both legs of
+/// each round trip
+#[allow(clippy::too_many_arguments)]
+async fn run_echo(
+    home: Arc<CachingHome>,
+    replica: Arc<CachingReplica>,
+    route: Arc<EchoRoute>,
+    metrics: EchoMetrics,
+    home_lock: Arc<Mutex<()>>,
+    messages_dispatched: prometheus::IntCounter,
+    home_failed: prometheus::IntGauge,
+    interval: u64,
+) -> Result<()> {
+    let destination = replica.local_domain();
+    let mut probes = vec![];
+
+    loop {
+        let request_id = H256::random();
+        let message = Message {
+            destination,
+            recipient: route.recipient(),
+            body: echo_body(ECHO_REQUEST, request_id),
+        };
+
+        // Nonces are read under the lock so the request's nonce is bounded
+        let guard = home_lock.lock().await;
+        let return_from = route.return_nonce().await?;
+        let first = home.nonces(destination).await?;
+        let outcome = dispatch_or_pause(&home, &message, &home_failed, interval).await?;
+        let last = home.nonces(destination).await?.max(first + 1);
+        drop(guard);
+
+        messages_dispatched.inc();
+        info!(
+            target: "outgoing_messages",
+            request_id = %CanonicalH256(request_id),
+            destination,
+            leaf_index = outcome.leaf_index,
+            message_hash = ?outcome.message_hash,
+            "Dispatched echo request"
+        );
+        probes.push(route.probe(request_id, first..last, return_from));
+
+        let mut in_flight = vec![];
+        for mut probe in probes.into_iter() {
+            let outcome = route.poll(&mut probe, replica.as_ref()).await?;
+            if metrics.record(outcome) {
+                in_flight.push(probe);
+            }
+        }
+        probes = in_flight;
+
+        sleep(Duration::from_secs(interval)).await;
+    }
+}
+
 decl_channel!(Kathy {
     home_lock: Arc<Mutex<()>>,
     generator: ChatGenerator,
+    echo: Option<(Arc<EchoRoute>, EchoMetrics)>,
     messages_dispatched: prometheus::IntCounter,
+    home_failed: prometheus::IntGauge,
     interval: u64,
 });
 
@@ -59,9 +219,18 @@ impl NomadAgent for Kathy {
     type Channel = KathyChannel;
 
     async fn from_settings(settings: Settings) -> Result<Self> {
+        let mut echo_routes = HashMap::new();
+        if let Some(echo) = settings.agent.echo.as_ref() {
+            for remote in settings.base.replicas.keys() {
+                let route = EchoRoute::from_settings(echo, &settings.base, remote).await?;
+                echo_routes.insert(remote.to_owned(), Arc::new(route));
+            }
+        }
+
         Ok(Self::new(
             settings.agent.interval,
             settings.agent.chat.into(),
+            echo_routes,
             settings.base.try_into_core(Self::AGENT_NAME).await?,
         ))
     }
@@ -71,11 +240,20 @@ impl NomadAgent for Kathy {
             base: self.channel_base(replica),
             home_lock: self.home_lock.clone(),
             generator: self.generator.clone(),
+            echo: self
+                .echo_routes
+                .get(replica)
+                .map(|route| (route.clone(), self.echo_metrics(replica))),
             messages_dispatched: self.messages_dispatched.with_label_values(&[
                 self.home().name(),
                 replica,
                 Self::AGENT_NAME,
             ]),
+            home_failed: self.home_failed.with_label_values(&[
+                self.home().name(),
+                replica,
+                Self::AGENT_NAME,
+            ]),
             interval: self.interval,
         }
     }
@@ -83,11 +261,26 @@ impl NomadAgent for Kathy {
     #[tracing::instrument]
     fn run(channel: Self::Channel) -> Instrumented<JoinHandle<Result<()>>> {
         tokio::spawn(async move {
+            if let Some((route, metrics)) = channel.echo.clone() {
+                return run_echo(
+                    channel.home(),
+                    channel.replica(),
+                    route,
+                    metrics,
+                    channel.home_lock,
+                    channel.messages_dispatched,
+                    channel.home_failed,
+                    channel.interval,
+                )
+                .await;
+            }
+
             let home = channel.home();
             let destination = channel.replica().local_domain();
             let mut generator = channel.generator;
             let home_lock = channel.home_lock;
             let messages_dispatched = channel.messages_dispatched;
+            let home_failed = channel.home_failed;
             let interval = channel.interval;
 
             loop {
@@ -110,9 +303,16 @@ impl NomadAgent for Kathy {
                         );
 
                         let guard = home_lock.lock().await;
-                        home.dispatch(&message).await?;
+                        let outcome =
+                            dispatch_or_pause(&home, &message, &home_failed, interval).await?;
 
                         messages_dispatched.inc();
+                        info!(
+                            target: "outgoing_messages",
+                            leaf_index = outcome.leaf_index,
+                            message_hash = ?outcome.message_hash,
+                            "Dispatched message"
+                        );
 
                         drop(guard);
                     }
@@ -143,6 +343,9 @@ pub enum ChatGenerator {
     Random {
         length: usize,
     },
+    Empty {
+        recipient: H256,
+    },
     Default,
 }
 
@@ -155,19 +358,36 @@ impl Default for ChatGenerator {
 impl From<ChatGenConfig> for ChatGenerator {
     fn from(conf: ChatGenConfig) -> ChatGenerator {
         match conf {
-            ChatGenConfig::Static { recipient, message } => {
-                ChatGenerator::Static { recipient, message }
-            }
+            ChatGenConfig::Static { recipient, message } => ChatGenerator::Static {
+                recipient: checked_recipient(recipient),
+                message,
+            },
             ChatGenConfig::OrderedList { messages } => ChatGenerator::OrderedList {
                 messages,
                 counter: 0,
             },
             ChatGenConfig::Random { length } => ChatGenerator::Random { length },
+            ChatGenConfig::Empty { recipient } => ChatGenerator::Empty {
+                recipient: checked_recipient(recipient),
+            },
             ChatGenConfig::Default => ChatGenerator::Default,
         }
     }
 }
 
+/// The configured recipient, warning if it looks like an EVM address padded
+/// on the wrong side
+fn checked_recipient(recipient: Address32) -> H256 {
+    if let Some(address) = recipient.right_padded_evm() {
+        warn!(
+            recipient = %recipient,
+            intended = %ChecksumAddress(address),
+            "Chat recipient looks like an EVM address padded on the wrong side. Configure the address itself or its left-padded form",
+        );
+    }
+    recipient.into()
+}
+
 impl ChatGenerator {
     fn rand_string(length: usize) -> String {
         thread_rng()
@@ -189,6 +409,7 @@ impl ChatGenerator {
                 counter: _,
             } => Default::default(),
             ChatGenerator::Random { length: _ } => H256::random(),
+            ChatGenerator::Empty { recipient } => *recipient,
         }
     }
 
@@ -212,6 +433,127 @@ impl ChatGenerator {
                 Some(msg)
             }
             ChatGenerator::Random { length } => Some(Self::rand_string(*length).into()),
+            ChatGenerator::Empty { recipient: _ } => Some(vec![]),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use nomad_base::{
+        chains::PageSettings, ContractSync, ContractSyncMetrics, CoreMetrics, HomeIndexers,
+        IndexSettings, NomadDB,
+    };
+    use nomad_core::{utils::destination_and_nonce, State, TxOutcome};
+    use nomad_test::mocks::{MockHomeContract, MockIndexer};
+    use nomad_test::test_utils;
+
+    #[test]
+    fn it_generates_empty_bodies() {
+        let recipient = H256::repeat_byte(0x22);
+        let mut generator: ChatGenerator = ChatGenConfig::Empty {
+            recipient: recipient.into(),
+        }
+        .into();
+
+        for _ in 0..3 {
+            assert_eq!(generator.gen_recipient(), recipient);
+            assert_eq!(generator.gen_chat(), Some(vec![]));
+        }
+    }
+
+    #[tokio::test]
+    async fn it_pauses_and_resumes_sending_on_failed_home() {
+        test_utils::run_test_db(|db| async move {
+            let failed = Arc::new(AtomicBool::new(true));
+
+            let mut mock_home = MockHomeContract::new();
+            {
+                let failed = failed.clone();
+                mock_home.expect__name().return_const("home_1".to_owned());
+                mock_home.expect__state().times(..).returning(move || {
+                    if failed.load(Ordering::SeqCst) {
+                        Ok(State::Failed)
+                    } else {
+                        Ok(State::Active)
+                    }
+                });
+                // Dispatch is only attempted once the home recovers
+                mock_home
+                    .expect__dispatch_tracked()
+                    .times(1)
+                    .returning(|_| {
+                        Ok(DispatchOutcome {
+                            tx: TxOutcome {
+                                txid: H256::zero(),
+                                gas_used: None,
+                                block_number: None,
+                            },
+                            leaf_index: 7,
+                            message_hash: H256::repeat_byte(1),
+                            destination_and_nonce: destination_and_nonce(2000, 3),
+                        })
+                    });
+            }
+
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "kathy_test",
+                    "home",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .expect("could not make metrics"),
+            );
+            let nomad_db = NomadDB::new("home_1", db);
+            let home_indexer: Arc<HomeIndexers> = Arc::new(MockIndexer::new().into());
+            let home_sync = ContractSync::new(
+                "kathy".to_owned(),
+                "home_1".to_owned(),
+                "".to_owned(),
+                nomad_db.clone(),
+                home_indexer,
+                IndexSettings::default(),
+                PageSettings::default(),
+                Default::default(),
+                ContractSyncMetrics::new(metrics),
+            );
+            let home: Arc<CachingHome> =
+                CachingHome::new(mock_home.into(), home_sync, nomad_db).into();
+
+            let home_failed = prometheus::IntGauge::new("home_failed", "home failed").unwrap();
+            let message = Message {
+                destination: 2000,
+                recipient: H256::zero(),
+                body: vec![],
+            };
+
+            let handle = {
+                let home_failed = home_failed.clone();
+                tokio::spawn(
+                    async move { dispatch_or_pause(&home, &message, &home_failed, 1).await },
+                )
+            };
+
+            // Failed home pauses sending
+            sleep(Duration::from_millis(100)).await;
+            assert_eq!(home_failed.get(), 1);
+
+            // Recovered home resumes sending
+            failed.store(false, Ordering::SeqCst);
+            let outcome = tokio::time::timeout(Duration::from_secs(5), handle)
+                .await
+                .expect("sending did not resume")
+                .unwrap()
+                .unwrap();
+            assert_eq!(home_failed.get(), 0);
+            assert_eq!(outcome.leaf_index, 7);
+            assert_eq!(outcome.message_hash, H256::repeat_byte(1));
+        })
+        .await
+    }
+}
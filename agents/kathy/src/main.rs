@@ -4,19 +4,25 @@
 #![warn(missing_docs)]
 #![warn(unused_extern_crates)]
 
+mod echo;
 mod kathy;
 mod settings;
 
 use crate::{kathy::Kathy, settings::KathySettings as Settings};
 use color_eyre::Result;
-use nomad_base::NomadAgent;
+use nomad_base::{FatalError, NomadAgent};
 
 use tracing::info_span;
 use tracing_subscriber::prelude::*;
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<()> {
+async fn main() {
+    nomad_base::exit_with_summary(Kathy::AGENT_NAME, run().await)
+}
+
+async fn run() -> Result<()> {
     color_eyre::install()?;
+    nomad_base::dump_schema_if_requested();
 
     // sets the subscriber for this scope only
     let _bootup_guard = tracing_subscriber::FmtSubscriber::builder()
@@ -27,8 +33,12 @@ async fn main() -> Result<()> {
     let span = info_span!("KathyBootup");
     let _span = span.enter();
 
-    let settings = Settings::new().await?;
-    let agent = Kathy::from_settings(settings).await?;
+    let settings = Settings::new()
+        .await
+        .map_err(|e| FatalError::config("settings", e))?;
+    let agent = Kathy::from_settings(settings)
+        .await
+        .map_err(|e| FatalError::classify_startup(e, "setup"))?;
 
     drop(_span);
     drop(span);
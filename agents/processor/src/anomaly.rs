@@ -0,0 +1,331 @@
+//! Dispatch rate anomaly detection per corridor. Purely observational: it
+//! reads indexed messages and never touches processing.
+
+use color_eyre::Result;
+use ethers::prelude::H256;
+use nomad_base::{CachingReplica, NomadDB};
+use nomad_core::{
+    db::DbError, CanonicalH256, CommittedMessage, Common, Decode, Encode, NomadError, Replica,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryFrom,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{info_span, instrument::Instrumented, warn, Instrument};
+
+static RATE_BASELINE: &str = "dispatch_rate_baseline_";
+
+/// Seconds of dispatches counted per window
+const RATE_WINDOW_SECONDS: u64 = 300;
+/// Weight of the latest window in the baseline
+const BASELINE_WEIGHT: f64 = 0.05;
+/// Windows observed before deviations are flagged
+const WARMUP_WINDOWS: u64 = 12;
+/// Window counts reported with an alert
+const RECENT_WINDOWS: usize = 12;
+/// Senders reported with an alert
+const TOP_SENDERS: usize = 5;
+
+/// Exponentially weighted baseline of a corridor's dispatches per window,
+/// and the next destination nonce to count. Persisted, so restarts keep what
+/// was learned.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct RateBaseline {
+    /// Weighted dispatches per window
+    pub(crate) rate: f64,
+    /// Windows observed
+    pub(crate) windows: u64,
+    /// Next destination nonce to count
+    pub(crate) next_nonce: u32,
+}
+
+impl RateBaseline {
+    /// Load the baseline of a destination domain
+    pub(crate) fn load(db: &NomadDB, destination: u32) -> Result<Self, DbError> {
+        Ok(db
+            .retrieve_keyed_decodable(RATE_BASELINE, &destination)?
+            .unwrap_or_default())
+    }
+
+    /// Persist the baseline of a destination domain
+    pub(crate) fn store(&self, db: &NomadDB, destination: u32) -> Result<(), DbError> {
+        db.store_keyed_encodable(RATE_BASELINE, &destination, self)
+    }
+
+    /// Fold a window's dispatch count into the baseline. Returns the count's
+    /// multiple of the previous baseline if it exceeds `threshold` once
+    /// warmed up. The baseline is floored at one dispatch per window, so
+    /// quiet corridors do not alert on their first messages. Anomalous
+    /// windows are folded in too: sustained adoption raises the baseline
+    /// and stops alerting.
+    pub(crate) fn observe(&mut self, count: u64, threshold: f64) -> Option<f64> {
+        let multiple = count as f64 / self.rate.max(1.0);
+        let anomalous = self.windows >= WARMUP_WINDOWS && multiple > threshold;
+
+        self.rate = if self.windows == 0 {
+            count as f64
+        } else {
+            BASELINE_WEIGHT * count as f64 + (1.0 - BASELINE_WEIGHT) * self.rate
+        };
+        self.windows += 1;
+
+        anomalous.then(|| multiple)
+    }
+}
+
+impl Encode for RateBaseline {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = self.rate.to_bits().write_to(writer)?;
+        written += self.windows.write_to(writer)?;
+        written += self.next_nonce.write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for RateBaseline {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        Ok(Self {
+            rate: f64::from_bits(u64::read_from(reader)?),
+            windows: u64::read_from(reader)?,
+            next_nonce: u32::read_from(reader)?,
+        })
+    }
+}
+
+/// Dispatches to a destination since a nonce
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Dispatches {
+    /// Number of messages
+    pub(crate) count: u64,
+    /// Messages by sender
+    pub(crate) senders: HashMap<H256, u64>,
+    /// Next destination nonce to count
+    pub(crate) next_nonce: u32,
+}
+
+impl Dispatches {
+    /// Count indexed messages to `destination` from nonce `from`
+    pub(crate) fn count(db: &NomadDB, destination: u32, from: u32) -> Result<Self> {
+        let mut dispatches = Self {
+            next_nonce: from,
+            ..Default::default()
+        };
+        while let Some(raw) = db.message_by_nonce(destination, dispatches.next_nonce)? {
+            let message = CommittedMessage::try_from(raw)?;
+            dispatches.count += 1;
+            *dispatches
+                .senders
+                .entry(message.message.sender)
+                .or_default() += 1;
+            dispatches.next_nonce += 1;
+        }
+        Ok(dispatches)
+    }
+
+    /// The `n` senders with the most messages, most first
+    pub(crate) fn top_senders(&self, n: usize) -> Vec<(H256, u64)> {
+        let mut senders: Vec<_> = self.senders.iter().map(|(s, c)| (*s, *c)).collect();
+        senders.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        senders.truncate(n);
+        senders
+    }
+}
+
+/// Watches one corridor's dispatch rate against its baseline, and warns and
+/// sets the anomaly gauge when a window exceeds `threshold` times it
+#[derive(Debug)]
+pub(crate) struct RateMonitor {
+    pub(crate) replica: Arc<CachingReplica>,
+    pub(crate) db: NomadDB,
+    pub(crate) threshold: f64,
+    pub(crate) anomaly: prometheus::IntGauge,
+}
+
+impl RateMonitor {
+    /// Count the dispatches of the window just ended and check them against
+    /// the baseline. The first window after a start is only counted: it
+    /// holds whatever was dispatched while the agent was down.
+    fn check(
+        &self,
+        baseline: &mut RateBaseline,
+        recent: &mut VecDeque<u64>,
+        observe: bool,
+    ) -> Result<()> {
+        let destination = self.replica.local_domain();
+        let dispatches = Dispatches::count(&self.db, destination, baseline.next_nonce)?;
+        baseline.next_nonce = dispatches.next_nonce;
+
+        if observe {
+            if recent.len() == RECENT_WINDOWS {
+                recent.pop_front();
+            }
+            recent.push_back(dispatches.count);
+
+            let expected = baseline.rate;
+            let anomaly = baseline.observe(dispatches.count, self.threshold);
+            self.anomaly.set(anomaly.is_some() as i64);
+            if let Some(multiple) = anomaly {
+                let top_senders: Vec<_> = dispatches
+                    .top_senders(TOP_SENDERS)
+                    .into_iter()
+                    .map(|(sender, count)| format!("{}: {}", CanonicalH256(sender), count))
+                    .collect();
+                warn!(
+                    replica = self.replica.name(),
+                    count = dispatches.count,
+                    baseline = expected,
+                    multiple,
+                    recent_counts = ?recent,
+                    top_senders = ?top_senders,
+                    window_seconds = RATE_WINDOW_SECONDS,
+                    "Dispatch rate to {} is {:.1}x its baseline",
+                    self.replica.name(),
+                    multiple,
+                );
+            }
+        }
+
+        Ok(baseline.store(&self.db, destination)?)
+    }
+
+    /// Spawn the monitor. Failures are logged and never stop processing.
+    pub(crate) fn spawn(self) -> Instrumented<JoinHandle<Result<()>>> {
+        let span = info_span!("RateMonitor", replica = self.replica.name());
+        tokio::spawn(async move {
+            let mut baseline = RateBaseline::load(&self.db, self.replica.local_domain())?;
+            let mut recent = VecDeque::with_capacity(RECENT_WINDOWS);
+            let mut observe = false;
+            loop {
+                if let Err(error) = self.check(&mut baseline, &mut recent, observe) {
+                    warn!(
+                        replica = self.replica.name(),
+                        error = %error,
+                        "Failed to check dispatch rate"
+                    );
+                }
+                observe = true;
+                sleep(Duration::from_secs(RATE_WINDOW_SECONDS)).await;
+            }
+        })
+        .instrument(span)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nomad_core::{NomadMessage, RawCommittedMessage};
+    use nomad_test::test_utils;
+
+    const THRESHOLD: f64 = 20.0;
+
+    fn drive(baseline: &mut RateBaseline, counts: impl IntoIterator<Item = u64>) -> Vec<usize> {
+        counts
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, count)| baseline.observe(count, THRESHOLD).map(|_| i))
+            .collect()
+    }
+
+    #[test]
+    fn it_flags_spikes_over_a_steady_rate() {
+        let mut baseline = RateBaseline::default();
+        // A noisy corridor around 10 dispatches per window
+        let steady = (0..100).map(|i| 5 + (i * 7) % 11);
+        assert!(drive(&mut baseline, steady).is_empty());
+        assert!((baseline.rate - 10.0).abs() < 3.0);
+
+        // A 100x spike is flagged with its multiple, a 3x one is not
+        let mut unchanged = baseline;
+        assert!(unchanged.observe(30, THRESHOLD).is_none());
+        let multiple = baseline.observe(1000, THRESHOLD).unwrap();
+        assert!(multiple > 50.0);
+    }
+
+    #[test]
+    fn it_does_not_flag_during_warmup_or_on_quiet_corridors() {
+        let mut baseline = RateBaseline::default();
+        // Spikes while learning are not flagged
+        assert!(drive(&mut baseline, [0, 0, 500, 0, 0]).is_empty());
+
+        // A corridor that sees nothing alerts on a burst, not on a trickle
+        let mut baseline = RateBaseline::default();
+        assert!(drive(&mut baseline, std::iter::repeat(0).take(50)).is_empty());
+        assert_eq!(drive(&mut baseline, [3, 0, 15, 0, 25]), vec![4]);
+    }
+
+    #[test]
+    fn it_adapts_to_sustained_adoption() {
+        let mut baseline = RateBaseline::default();
+        drive(&mut baseline, std::iter::repeat(10).take(50));
+
+        // A step up to 300 per window alerts at first, then becomes the norm
+        assert_eq!(
+            drive(&mut baseline, std::iter::repeat(300).take(200)),
+            vec![0]
+        );
+        assert!((baseline.rate - 300.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn it_survives_boundary_counts() {
+        let mut baseline = RateBaseline::default();
+        drive(&mut baseline, std::iter::repeat(0).take(20));
+        assert!(baseline.observe(u64::MAX, THRESHOLD).is_some());
+        assert!(baseline.rate.is_finite());
+    }
+
+    #[tokio::test]
+    async fn it_persists_baselines_and_counts_senders() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let (a, b) = (H256::repeat_byte(0xa), H256::repeat_byte(0xb));
+
+            for (nonce, sender) in [a, b, a, a].iter().enumerate() {
+                let message = NomadMessage {
+                    origin: 1000,
+                    sender: *sender,
+                    nonce: nonce as u32,
+                    destination: 2000,
+                    recipient: H256::zero(),
+                    body: vec![],
+                };
+                db.store_latest_message(&RawCommittedMessage {
+                    leaf_index: nonce as u32,
+                    committed_root: H256::zero(),
+                    message: message.to_vec(),
+                    timestamp: None,
+                })
+                .unwrap();
+            }
+
+            let dispatches = Dispatches::count(&db, 2000, 1).unwrap();
+            assert_eq!(dispatches.count, 3);
+            assert_eq!(dispatches.next_nonce, 4);
+            assert_eq!(dispatches.top_senders(1), vec![(a, 2)]);
+            assert_eq!(dispatches.top_senders(5), vec![(a, 2), (b, 1)]);
+            assert_eq!(Dispatches::count(&db, 2000, 4).unwrap().count, 0);
+
+            assert_eq!(RateBaseline::load(&db, 2000).unwrap(), Default::default());
+            let mut baseline = RateBaseline {
+                next_nonce: dispatches.next_nonce,
+                ..Default::default()
+            };
+            drive(&mut baseline, [4, 8, 6]);
+            baseline.store(&db, 2000).unwrap();
+            assert_eq!(RateBaseline::load(&db, 2000).unwrap(), baseline);
+            assert_eq!(RateBaseline::load(&db, 3000).unwrap(), Default::default());
+        })
+        .await
+    }
+}
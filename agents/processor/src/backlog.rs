@@ -0,0 +1,293 @@
+use color_eyre::Result;
+use nomad_base::{CachingReplica, CoreMetrics, NomadDB};
+use nomad_core::{Common, Replica, UpdateMeta};
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{info_span, instrument::Instrumented, warn, Instrument};
+
+use crate::processor::CURRENT_NONCE;
+
+/// Number of updates to walk back from the replica's committed root when
+/// looking for an acceptable root
+const ROOT_LOOKBACK: usize = 16;
+/// Number of cursor samples used to estimate throughput
+const THROUGHPUT_SAMPLES: usize = 30;
+
+/// A corridor's processing backlog
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Backlog {
+    /// Messages covered by an acceptable root on the replica and not yet
+    /// processed
+    pub(crate) pending: u64,
+    /// Age of the oldest pending message, from the timestamp of the update
+    /// that covered it
+    pub(crate) oldest_pending_age_seconds: Option<u64>,
+    /// Pending messages divided by recent throughput. `None` if nothing
+    /// was processed recently
+    pub(crate) estimated_clear_seconds: Option<u64>,
+}
+
+/// Recent processing throughput, from samples of the processor's nonce
+/// cursor
+#[derive(Debug, Default)]
+pub(crate) struct Throughput {
+    samples: VecDeque<(u64, u32)>,
+}
+
+impl Throughput {
+    /// Record the cursor at unix time `at`
+    pub(crate) fn record(&mut self, at: u64, cursor: u32) {
+        if self.samples.len() == THROUGHPUT_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((at, cursor));
+    }
+
+    /// Messages per second over the sampled window
+    pub(crate) fn per_second(&self) -> Option<f64> {
+        let (first_at, first) = self.samples.front()?;
+        let (last_at, last) = self.samples.back()?;
+        if last_at <= first_at || last <= first {
+            return None;
+        }
+        Some((last - first) as f64 / (last_at - first_at) as f64)
+    }
+
+    /// Seconds to clear `pending` messages at the current throughput
+    pub(crate) fn clear_seconds(&self, pending: u64) -> Option<u64> {
+        if pending == 0 {
+            return Some(0);
+        }
+        self.per_second()
+            .map(|rate| (pending as f64 / rate).ceil() as u64)
+    }
+}
+
+/// Count messages to `domain` from the processor's cursor onwards whose
+/// covering update landed on the home at or before `acceptable_block`.
+/// Returns the count and the timestamp of the oldest pending message's
+/// covering update.
+pub(crate) fn pending_messages(
+    db: &NomadDB,
+    domain: u32,
+    cursor: u32,
+    acceptable_block: u64,
+) -> Result<(u64, Option<u64>)> {
+    let mut pending = 0;
+    let mut oldest = None;
+
+    for nonce in cursor.. {
+        let message = match db.message_by_nonce(domain, nonce)? {
+            Some(message) => message,
+            None => break,
+        };
+        let covering = match db.update_by_previous_root(message.committed_root)? {
+            Some(update) => update.update.new_root,
+            None => break,
+        };
+        let meta = match db.retrieve_update_metadata(covering)? {
+            Some(meta) if meta.block_number <= acceptable_block => meta,
+            _ => break,
+        };
+
+        if pending == 0 {
+            oldest = meta.timestamp;
+        }
+        pending += 1;
+    }
+
+    Ok((pending, oldest))
+}
+
+/// Periodically computes the processing backlog of one home <> replica
+/// corridor and exports it as metrics and in `/status`
+#[derive(Debug)]
+pub(crate) struct BacklogMonitor {
+    pub(crate) replica: Arc<CachingReplica>,
+    pub(crate) db: NomadDB,
+    pub(crate) interval: u64,
+    pub(crate) metrics: Arc<CoreMetrics>,
+    pub(crate) pending: prometheus::IntGauge,
+    pub(crate) oldest_age: prometheus::IntGauge,
+    pub(crate) clear_time: prometheus::IntGauge,
+}
+
+impl BacklogMonitor {
+    /// Metadata of the latest root that is acceptable on the replica
+    async fn latest_acceptable(&self) -> Result<Option<UpdateMeta>> {
+        let mut root = self.replica.committed_root().await?;
+        for _ in 0..ROOT_LOOKBACK {
+            if root.is_zero() {
+                return Ok(None);
+            }
+            if self.replica.acceptable_root(root).await? {
+                return Ok(self.db.retrieve_update_metadata(root)?);
+            }
+            root = match self.db.update_by_new_root(root)? {
+                Some(update) => update.update.previous_root,
+                None => return Ok(None),
+            };
+        }
+        Ok(None)
+    }
+
+    async fn compute(&self, throughput: &mut Throughput) -> Result<Backlog> {
+        let domain = self.replica.local_domain();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let cursor = self
+            .db
+            .retrieve_keyed_decodable(CURRENT_NONCE, &domain)?
+            .map(|n: u32| n + 1)
+            .unwrap_or_default();
+        throughput.record(now, cursor);
+
+        let (pending, oldest) = match self.latest_acceptable().await? {
+            Some(meta) => pending_messages(&self.db, domain, cursor, meta.block_number)?,
+            None => (0, None),
+        };
+
+        Ok(Backlog {
+            pending,
+            oldest_pending_age_seconds: oldest.map(|at| now.saturating_sub(at)),
+            estimated_clear_seconds: throughput.clear_seconds(pending),
+        })
+    }
+
+    /// Spawn the monitor. Unknown values are exported as -1.
+    pub(crate) fn spawn(self) -> Instrumented<JoinHandle<Result<()>>> {
+        let span = info_span!("BacklogMonitor", replica = self.replica.name());
+        tokio::spawn(async move {
+            let mut throughput = Throughput::default();
+            loop {
+                match self.compute(&mut throughput).await {
+                    Ok(backlog) => {
+                        self.pending.set(backlog.pending as i64);
+                        self.oldest_age.set(
+                            backlog
+                                .oldest_pending_age_seconds
+                                .map_or(-1, |age| age as i64),
+                        );
+                        self.clear_time.set(
+                            backlog
+                                .estimated_clear_seconds
+                                .map_or(-1, |secs| secs as i64),
+                        );
+                        self.metrics.set_status(
+                            "backlog",
+                            self.replica.name(),
+                            serde_json::to_value(backlog)?,
+                        );
+                    }
+                    Err(error) => warn!(
+                        replica = self.replica.name(),
+                        error = %error,
+                        "Failed to compute processing backlog"
+                    ),
+                }
+                sleep(Duration::from_secs(self.interval)).await;
+            }
+        })
+        .instrument(span)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::core::types::H256;
+    use nomad_core::{Encode, NomadMessage, RawCommittedMessage, SignedUpdateWithMeta, Update};
+    use nomad_test::test_utils;
+
+    #[test]
+    fn it_estimates_clear_time_from_recent_throughput() {
+        let mut throughput = Throughput::default();
+        assert_eq!(throughput.clear_seconds(0), Some(0));
+        assert_eq!(throughput.clear_seconds(10), None);
+
+        throughput.record(1000, 5);
+        throughput.record(1010, 5);
+        assert_eq!(throughput.clear_seconds(10), None);
+
+        throughput.record(1020, 15);
+        assert_eq!(throughput.per_second(), Some(0.5));
+        assert_eq!(throughput.clear_seconds(10), Some(20));
+        assert_eq!(throughput.clear_seconds(3), Some(6));
+
+        // old samples fall out of the window
+        for i in 0..THROUGHPUT_SAMPLES as u64 {
+            throughput.record(1030 + i * 10, 15);
+        }
+        assert_eq!(throughput.per_second(), None);
+    }
+
+    #[tokio::test]
+    async fn it_counts_messages_covered_by_acceptable_roots() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let signer: ethers::signers::LocalWallet = "1".repeat(64).parse().unwrap();
+
+            // Three updates at blocks 10, 20 and 30 each cover two messages
+            // to domain 2000. A message to domain 3000 is interleaved.
+            let roots: Vec<H256> = (0..4).map(H256::repeat_byte).collect();
+            let mut nonces = [0u32; 2];
+            for (i, window) in roots.windows(2).enumerate() {
+                let update = Update {
+                    home_domain: 1000,
+                    previous_root: window[0],
+                    new_root: window[1],
+                }
+                .sign_with(&signer)
+                .await
+                .unwrap();
+                db.store_updates_and_meta(&[SignedUpdateWithMeta {
+                    signed_update: update,
+                    metadata: UpdateMeta {
+                        block_number: 10 * (i as u64 + 1),
+                        timestamp: Some(100 * (i as u64 + 1)),
+                    },
+                }])
+                .unwrap();
+
+                for destination in [2000, 2000, 3000] {
+                    let index = if destination == 2000 { 0 } else { 1 };
+                    let message = NomadMessage {
+                        origin: 1000,
+                        sender: H256::zero(),
+                        nonce: nonces[index],
+                        destination,
+                        recipient: H256::zero(),
+                        body: vec![],
+                    };
+                    nonces[index] += 1;
+                    db.store_latest_message(&RawCommittedMessage {
+                        leaf_index: db
+                            .retrieve_latest_leaf_index()
+                            .unwrap()
+                            .map_or(0, |i| i + 1),
+                        committed_root: window[0],
+                        message: message.to_vec(),
+                        timestamp: None,
+                    })
+                    .unwrap();
+                }
+            }
+
+            // Nothing is acceptable before the first update
+            assert_eq!(pending_messages(&db, 2000, 0, 5).unwrap(), (0, None));
+            // Two roots acceptable, nothing processed
+            assert_eq!(pending_messages(&db, 2000, 0, 20).unwrap(), (4, Some(100)));
+            // The first three messages are processed
+            assert_eq!(pending_messages(&db, 2000, 3, 20).unwrap(), (1, Some(200)));
+            // All roots acceptable
+            assert_eq!(pending_messages(&db, 2000, 3, 30).unwrap(), (3, Some(200)));
+            assert_eq!(pending_messages(&db, 3000, 0, 30).unwrap(), (3, Some(100)));
+            assert_eq!(pending_messages(&db, 2000, 6, 30).unwrap(), (0, None));
+        })
+        .await
+    }
+}
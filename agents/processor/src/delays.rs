@@ -0,0 +1,208 @@
+use color_eyre::Result;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{info, info_span, instrument::Instrumented, warn, Instrument};
+
+/// Extra processing delays past the optimistic window, by destination
+/// network. Delays from the config are overridden by entries in the delay
+/// file, which is re-read while the processor runs.
+#[derive(Debug)]
+pub(crate) struct CorridorDelays {
+    configured: HashMap<String, u64>,
+    path: Option<PathBuf>,
+    current: RwLock<HashMap<String, u64>>,
+}
+
+impl CorridorDelays {
+    /// Instantiate from the configured delays and optional delay file
+    pub(crate) fn new(configured: HashMap<String, u64>, path: Option<PathBuf>) -> Self {
+        Self {
+            current: RwLock::new(configured.clone()),
+            configured,
+            path,
+        }
+    }
+
+    /// Whether delays are reloaded from a file
+    pub(crate) fn watches_file(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Current extra delay in seconds for messages to `destination`
+    pub(crate) fn extra_delay(&self, destination: &str) -> u64 {
+        self.current
+            .read()
+            .expect("poisoned")
+            .get(destination)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Re-read the delay file. Returns true if the delays changed. A
+    /// missing file leaves only the configured delays.
+    pub(crate) fn reload(&self) -> Result<bool> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+
+        let mut delays = self.configured.clone();
+        if path.exists() {
+            let file: HashMap<String, u64> = serde_json::from_slice(&std::fs::read(path)?)?;
+            delays.extend(file);
+        }
+
+        let mut current = self.current.write().expect("poisoned");
+        if *current == delays {
+            return Ok(false);
+        }
+        info!(path = ?path, delays = ?delays, "Reloaded extra processing delays");
+        *current = delays;
+        Ok(true)
+    }
+
+    /// Spawn a task re-reading the delay file every `interval` seconds. A
+    /// malformed file is logged and the previous delays are kept.
+    pub(crate) fn spawn(self: Arc<Self>, interval: u64) -> Instrumented<JoinHandle<Result<()>>> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(error) = self.reload() {
+                    warn!(
+                        path = ?self.path,
+                        error = %error,
+                        "Failed to reload extra processing delays, keeping previous delays"
+                    );
+                }
+                sleep(Duration::from_secs(interval)).await;
+            }
+        })
+        .instrument(info_span!("CorridorDelays"))
+    }
+}
+
+/// When a root's optimistic window ends on a replica, classified from its
+/// `confirmAt`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Confirmation {
+    /// The replica has not accepted an update to the root
+    Unknown,
+    /// The window ends at this timestamp
+    At(u64),
+    /// The window ends beyond the horizon, which no sane `optimisticSeconds`
+    /// or `confirmAt` reaches. Messages under the root are never processable
+    /// and are not scheduled.
+    Never,
+}
+
+impl Confirmation {
+    /// Classify a replica's `confirmAt`, converted with `saturating_u64`,
+    /// against chain time `now` and a horizon in seconds
+    pub(crate) fn classify(confirm_at: u64, now: u64, horizon: u64) -> Self {
+        if confirm_at == 0 {
+            Confirmation::Unknown
+        } else if confirm_at > now.saturating_add(horizon) {
+            Confirmation::Never
+        } else {
+            Confirmation::At(confirm_at)
+        }
+    }
+}
+
+/// Timestamp after which a message under a root confirmed at `confirm_at`
+/// may be processed
+pub(crate) fn ready_at(confirm_at: u64, extra_delay: u64) -> u64 {
+    confirm_at.saturating_add(extra_delay)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::core::types::U256;
+    use nomad_core::utils::saturating_u64;
+
+    const HORIZON: u64 = 10 * 365 * 24 * 60 * 60;
+
+    #[test]
+    fn it_classifies_extreme_confirmations() {
+        let now = 1_700_000_000;
+        let classify =
+            |confirm_at: U256| Confirmation::classify(saturating_u64(confirm_at), now, HORIZON);
+
+        assert_eq!(classify(U256::zero()), Confirmation::Unknown);
+        // replicas confirm their initial root at 1
+        assert_eq!(classify(U256::one()), Confirmation::At(1));
+        assert_eq!(
+            classify(U256::from(now + 1800)),
+            Confirmation::At(now + 1800)
+        );
+        assert_eq!(
+            classify(U256::from(now + HORIZON)),
+            Confirmation::At(now + HORIZON)
+        );
+        assert_eq!(classify(U256::from(now + HORIZON + 1)), Confirmation::Never);
+        assert_eq!(classify(U256::from(u64::MAX)), Confirmation::Never);
+        assert_eq!(classify(U256::from(u64::MAX) + 1), Confirmation::Never);
+        assert_eq!(classify(U256::MAX), Confirmation::Never);
+
+        // `optimisticSeconds` near U256::MAX saturates rather than wrapping
+        // `confirmAt` into the past
+        let confirm_at = U256::from(now).saturating_add(U256::MAX - 5);
+        assert_eq!(classify(confirm_at), Confirmation::Never);
+
+        // a clock or horizon at the extremes does not overflow
+        assert_eq!(
+            Confirmation::classify(u64::MAX, u64::MAX, u64::MAX),
+            Confirmation::At(u64::MAX)
+        );
+        assert_eq!(Confirmation::classify(1, 0, 0), Confirmation::Never);
+    }
+
+    #[test]
+    fn it_saturates_ready_times() {
+        assert_eq!(ready_at(saturating_u64(U256::MAX), 3600), u64::MAX);
+        assert_eq!(ready_at(1, 0), 1);
+        assert_eq!(ready_at(0, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn it_recomputes_schedules_on_reload() {
+        let path = std::env::temp_dir().join("processor-corridor-delays-test.json");
+        let _ = std::fs::remove_file(&path);
+
+        let delays = CorridorDelays::new(
+            HashMap::from([("goerli".to_owned(), 60)]),
+            Some(path.clone()),
+        );
+        assert!(!delays.reload().unwrap());
+
+        // the root confirmed at 1000 and the message is past its base window
+        let confirm_at = 1000;
+        let now = 1100;
+        assert!(ready_at(confirm_at, delays.extra_delay("goerli")) <= now);
+        assert_eq!(ready_at(confirm_at, delays.extra_delay("rinkeby")), 1000);
+
+        // raising the delay holds the message again
+        std::fs::write(&path, r#"{"goerli": 3600, "rinkeby": 10}"#).unwrap();
+        assert!(delays.reload().unwrap());
+        assert!(!delays.reload().unwrap());
+        assert_eq!(ready_at(confirm_at, delays.extra_delay("goerli")), 4600);
+        assert_eq!(ready_at(confirm_at, delays.extra_delay("rinkeby")), 1010);
+        assert!(ready_at(confirm_at, delays.extra_delay("goerli")) > now);
+
+        // a malformed file keeps the previous delays
+        std::fs::write(&path, "not json").unwrap();
+        assert!(delays.reload().is_err());
+        assert_eq!(delays.extra_delay("goerli"), 3600);
+
+        // removing the file falls back to the configured delays
+        std::fs::remove_file(&path).unwrap();
+        assert!(delays.reload().unwrap());
+        assert_eq!(ready_at(confirm_at, delays.extra_delay("goerli")), 1060);
+        assert_eq!(delays.extra_delay("rinkeby"), 0);
+    }
+}
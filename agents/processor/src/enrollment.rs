@@ -0,0 +1,302 @@
+//! Enrollment of a corridor's destination replica in the destination's
+//! XAppConnectionManager. xApps ignore messages from unenrolled replicas, so
+//! processing into one only spends gas.
+
+use color_eyre::Result;
+use ethers::prelude::H256;
+use nomad_base::{ConnectionManagers, CoreMetrics, CorridorSwitches, NomadDB};
+use nomad_core::{ConnectionManager, ReplicaEnrollment};
+use serde::Serialize;
+use std::{sync::Arc, time::Duration};
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{error, info, info_span, instrument::Instrumented, warn, Instrument};
+
+/// Last block indexed for enrollment events, by destination domain
+static ENROLLMENT_INDEXED_THROUGH: &str = "enrollment_indexed_through_";
+/// Latest enrollment event of the destination replica, by destination domain
+static LATEST_ENROLLMENT: &str = "latest_replica_enrollment_";
+/// Last observed enrollment state, by destination domain
+static ENROLLED: &str = "replica_enrolled_";
+
+/// `/status` section listing replica enrollments
+const STATUS_SECTION: &str = "enrollment";
+/// Reason corridors are paused with while their replica is unenrolled
+const UNENROLLED_REASON: &str = "replica unenrolled";
+
+/// Enrollment of a replica as reported by its XAppConnectionManager
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Enrollment {
+    /// Whether `isReplica` holds and `replicaToDomain` is the home domain
+    pub(crate) enrolled: bool,
+    /// Remote domain the replica is enrolled for, 0 if none
+    pub(crate) replica_domain: u32,
+    /// Latest indexed enrollment event of the replica
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) last_event: Option<ReplicaEnrollment>,
+}
+
+/// Periodically checks that a corridor's destination replica is enrolled,
+/// flips the enrolled gauge, alerts on transitions and, if set, pauses the
+/// corridor while the replica is unenrolled
+#[derive(Debug)]
+pub(crate) struct EnrollmentMonitor {
+    /// Destination network name
+    pub(crate) network: String,
+    /// Destination domain
+    pub(crate) domain: u32,
+    /// Home domain the replica should be enrolled for
+    pub(crate) home_domain: u32,
+    /// Replica address on the destination
+    pub(crate) replica: H256,
+    /// XAppConnectionManager on the destination
+    pub(crate) manager: Arc<ConnectionManagers>,
+    pub(crate) db: NomadDB,
+    /// Block to start indexing enrollment events at
+    pub(crate) from: u32,
+    /// Blocks indexed per request
+    pub(crate) page_size: u32,
+    pub(crate) interval: u64,
+    pub(crate) pause_unenrolled: bool,
+    pub(crate) corridors: Arc<CorridorSwitches>,
+    pub(crate) metrics: Arc<CoreMetrics>,
+    /// Set to 1 while the replica is enrolled
+    pub(crate) enrolled: prometheus::IntGauge,
+}
+
+impl EnrollmentMonitor {
+    /// Index enrollment events up to the chain tip, keeping the latest event
+    /// of the replica
+    async fn index(&self) -> Result<Option<ReplicaEnrollment>> {
+        let mut latest: Option<ReplicaEnrollment> = self
+            .db
+            .retrieve_keyed_decodable(LATEST_ENROLLMENT, &self.domain)?;
+        let mut from = self
+            .db
+            .retrieve_keyed_decodable(ENROLLMENT_INDEXED_THROUGH, &self.domain)?
+            .map_or(self.from, |through: u32| through + 1);
+        let tip = self.manager.get_block_number().await?;
+
+        while from <= tip {
+            let to = tip.min(from.saturating_add(self.page_size.max(1) - 1));
+            let events = self.manager.fetch_replica_enrollments(from, to).await?;
+            if let Some(event) = events
+                .into_iter()
+                .filter(|event| event.replica == self.replica)
+                .last()
+            {
+                self.db
+                    .store_keyed_encodable(LATEST_ENROLLMENT, &self.domain, &event)?;
+                latest = Some(event);
+            }
+            self.db
+                .store_keyed_encodable(ENROLLMENT_INDEXED_THROUGH, &self.domain, &to)?;
+            from = to + 1;
+        }
+
+        Ok(latest)
+    }
+
+    /// Check the replica's enrollment, update the gauge and `/status`, alert
+    /// on a transition and pause or resume the corridor
+    pub(crate) async fn check(&self) -> Result<Enrollment> {
+        let last_event = self.index().await?;
+        let is_replica = self.manager.is_replica(self.replica.into()).await?;
+        let replica_domain = self.manager.replica_to_domain(self.replica.into()).await?;
+        let enrollment = Enrollment {
+            enrolled: is_replica && replica_domain == self.home_domain,
+            replica_domain,
+            last_event,
+        };
+
+        self.enrolled.set(enrollment.enrolled as i64);
+        self.metrics.set_status(
+            STATUS_SECTION,
+            &self.network,
+            serde_json::to_value(&enrollment).expect("!serialize"),
+        );
+
+        let previous: Option<bool> = self.db.retrieve_keyed_decodable(ENROLLED, &self.domain)?;
+        match (previous, enrollment.enrolled) {
+            (None | Some(true), false) => error!(
+                network = self.network.as_str(),
+                replica = ?self.replica,
+                replica_domain,
+                block_number = ?last_event.map(|e| e.block_number),
+                timestamp = ?last_event.and_then(|e| e.timestamp),
+                "Replica on {} is not enrolled for this home. xApps will ignore its messages",
+                self.network,
+            ),
+            (Some(false), true) => info!(
+                network = self.network.as_str(),
+                replica = ?self.replica,
+                block_number = ?last_event.map(|e| e.block_number),
+                timestamp = ?last_event.and_then(|e| e.timestamp),
+                "Replica on {} is enrolled again",
+                self.network,
+            ),
+            _ => {}
+        }
+        self.db
+            .store_keyed_encodable(ENROLLED, &self.domain, &enrollment.enrolled)?;
+
+        if self.pause_unenrolled {
+            let state = self.corridors.state(&self.network);
+            let paused_by_us = state
+                .as_ref()
+                .map_or(false, |s| s.reason.as_deref() == Some(UNENROLLED_REASON));
+            if !enrollment.enrolled && self.corridors.is_enabled(&self.network) {
+                self.corridors.pause(&self.network, UNENROLLED_REASON);
+            } else if enrollment.enrolled && paused_by_us {
+                // Corridors paused by an operator stay paused
+                self.corridors.resume(&self.network);
+            }
+        }
+
+        Ok(enrollment)
+    }
+
+    /// Spawn the monitor. Failures are logged and only stop the monitor's
+    /// current check.
+    pub(crate) fn spawn(self) -> Instrumented<JoinHandle<Result<()>>> {
+        let span = info_span!("EnrollmentMonitor", network = self.network.as_str());
+        tokio::spawn(async move {
+            loop {
+                if let Err(error) = self.check().await {
+                    warn!(
+                        network = self.network.as_str(),
+                        error = %error,
+                        "Failed to check replica enrollment"
+                    );
+                }
+                sleep(Duration::from_secs(self.interval)).await;
+            }
+        })
+        .instrument(span)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nomad_core::NomadIdentifier;
+    use nomad_test::{mocks::MockConnectionManagerContract, test_utils};
+    use std::sync::Mutex;
+
+    fn event(replica: H256, enrolled: bool, block_number: u64) -> ReplicaEnrollment {
+        ReplicaEnrollment {
+            domain: 1000,
+            replica,
+            enrolled,
+            block_number,
+            timestamp: Some(block_number * 10),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_tracks_enrollment_and_pauses_unenrolled_corridors() {
+        test_utils::run_test_db(|db| async move {
+            let replica = H256::repeat_byte(0x11);
+            let other = H256::repeat_byte(0x22);
+            // Whether the replica is enrolled, and the chain tip
+            let state = Arc::new(Mutex::new((true, 100u32)));
+
+            let mut manager = MockConnectionManagerContract::new();
+            {
+                let state = state.clone();
+                manager
+                    .expect__get_block_number()
+                    .returning(move || Ok(state.lock().unwrap().1));
+            }
+            {
+                let state = state.clone();
+                manager
+                    .expect__is_replica()
+                    .withf(move |address: &NomadIdentifier| H256::from(*address) == replica)
+                    .returning(move |_| Ok(state.lock().unwrap().0));
+            }
+            {
+                let state = state.clone();
+                manager
+                    .expect__replica_to_domain()
+                    .returning(move |_| Ok(if state.lock().unwrap().0 { 1000 } else { 0 }));
+            }
+            manager
+                .expect__fetch_replica_enrollments()
+                .returning(move |from, to| {
+                    Ok([
+                        event(replica, true, 5),
+                        event(other, false, 60),
+                        event(replica, false, 150),
+                    ]
+                    .into_iter()
+                    .filter(|e| (from as u64..=to as u64).contains(&e.block_number))
+                    .collect())
+                });
+
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "processor_test",
+                    "home_1",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .unwrap(),
+            );
+            let corridors = Arc::new(
+                CorridorSwitches::new(
+                    "home_1",
+                    "processor",
+                    vec!["replica_1".to_owned()],
+                    &Default::default(),
+                    metrics.clone(),
+                )
+                .unwrap(),
+            );
+            let monitor = EnrollmentMonitor {
+                network: "replica_1".to_owned(),
+                domain: 2000,
+                home_domain: 1000,
+                replica,
+                manager: Arc::new(manager.into()),
+                db: NomadDB::new("home_1", db),
+                from: 0,
+                page_size: 40,
+                interval: 1,
+                pause_unenrolled: true,
+                corridors: corridors.clone(),
+                metrics: metrics.clone(),
+                enrolled: prometheus::IntGauge::new("enrolled", "enrolled").unwrap(),
+            };
+
+            let enrollment = monitor.check().await.unwrap();
+            assert!(enrollment.enrolled);
+            assert_eq!(enrollment.last_event, Some(event(replica, true, 5)));
+            assert_eq!(monitor.enrolled.get(), 1);
+            assert_eq!(
+                metrics.status()["enrollment"]["replica_1"]["enrolled"],
+                true
+            );
+
+            // The replica is unenrolled at block 150
+            *state.lock().unwrap() = (false, 200);
+            let enrollment = monitor.check().await.unwrap();
+            assert!(!enrollment.enrolled);
+            assert_eq!(enrollment.replica_domain, 0);
+            assert_eq!(enrollment.last_event, Some(event(replica, false, 150)));
+            assert_eq!(monitor.enrolled.get(), 0);
+            assert!(!corridors.is_enabled("replica_1"));
+
+            // Re-enrollment resumes the corridor
+            state.lock().unwrap().0 = true;
+            assert!(monitor.check().await.unwrap().enrolled);
+            assert!(corridors.is_enabled("replica_1"));
+
+            // An operator's pause is left alone
+            corridors.pause("replica_1", "bridge upgrade");
+            monitor.check().await.unwrap();
+            assert!(!corridors.is_enabled("replica_1"));
+        })
+        .await
+    }
+}
@@ -0,0 +1,60 @@
+use color_eyre::Result;
+use nomad_base::{NomadDB, RECIPIENT_GAS_RETENTION_DAYS};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{info_span, instrument::Instrumented, warn, Instrument};
+
+/// Recipients exported as metrics. Bounds the label values of the gauge.
+const TOP_RECIPIENTS: usize = 20;
+/// Days of recipient gas totals summed for the metrics
+const METRIC_WINDOW_DAYS: u64 = 7;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Periodically exports the gas used processing messages to the recipients
+/// that used the most over the last week, and prunes totals past their
+/// retention
+#[derive(Debug)]
+pub(crate) struct RecipientGasMonitor {
+    pub(crate) db: NomadDB,
+    pub(crate) interval: u64,
+    pub(crate) agent: String,
+    pub(crate) gas_used: prometheus::IntGaugeVec,
+}
+
+impl RecipientGasMonitor {
+    fn refresh(&self) -> Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        self.db.prune_recipient_gas(
+            now.saturating_sub(RECIPIENT_GAS_RETENTION_DAYS * SECONDS_PER_DAY),
+        )?;
+        let top = self
+            .db
+            .recipient_gas_since(now.saturating_sub(METRIC_WINDOW_DAYS * SECONDS_PER_DAY))?;
+
+        // recipients falling out of the top lose their series
+        self.gas_used.reset();
+        for (destination, recipient, totals) in top.iter().take(TOP_RECIPIENTS) {
+            self.gas_used
+                .with_label_values(&[
+                    &destination.to_string(),
+                    &format!("{:?}", recipient),
+                    &self.agent,
+                ])
+                .set(totals.gas_used as i64);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn spawn(self) -> Instrumented<JoinHandle<Result<()>>> {
+        let span = info_span!("RecipientGasMonitor");
+        tokio::spawn(async move {
+            loop {
+                if let Err(error) = self.refresh() {
+                    warn!(error = %error, "Failed to export recipient gas usage");
+                }
+                sleep(Duration::from_secs(self.interval)).await;
+            }
+        })
+        .instrument(span)
+    }
+}
@@ -0,0 +1,86 @@
+use nomad_core::{
+    batch_hash, CanonicalH256, CommittedMessage, Decode, GovernanceMessage, NomadError,
+};
+use nomad_ethereum::describe_governance_call;
+use tracing::{info, warn};
+
+/// Log what a message to the governance router will execute, so operators
+/// can review it before the optimistic window expires
+pub(crate) fn log_governance_message(message: &CommittedMessage) {
+    match describe_governance_body(&message.message.body) {
+        Ok(lines) => {
+            for line in lines {
+                info!(
+                    leaf_index = message.leaf_index,
+                    origin = message.message.origin,
+                    nonce = message.message.nonce,
+                    "Governance message: {}",
+                    line
+                );
+            }
+        }
+        Err(e) => warn!(
+            leaf_index = message.leaf_index,
+            error = %e,
+            "Failed to decode governance message"
+        ),
+    }
+}
+
+/// Describe what a governance message body will execute, one line per item
+pub(crate) fn describe_governance_body(body: &[u8]) -> Result<Vec<String>, NomadError> {
+    let message = GovernanceMessage::read_from(&mut &body[..])?;
+
+    Ok(match message {
+        GovernanceMessage::Batch(calls) => {
+            let mut lines = vec![format!(
+                "batch of {} calls with hash {}",
+                calls.len(),
+                CanonicalH256(batch_hash(&calls))
+            )];
+            lines.extend(
+                calls
+                    .iter()
+                    .enumerate()
+                    .map(|(i, call)| format!("call {} {}", i, describe_governance_call(call))),
+            );
+            lines
+        }
+        GovernanceMessage::BatchHash(hash) => vec![format!(
+            "batch with hash {}. Calls are supplied at execution",
+            CanonicalH256(hash)
+        )],
+        GovernanceMessage::TransferGovernor { domain, governor } => vec![format!(
+            "transfer governor to {} on domain {}",
+            CanonicalH256(governor),
+            domain
+        )],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    fn fixture_message(field: &str) -> Vec<u8> {
+        let fixture = std::fs::read_to_string("../../fixtures/governance_batch.json").unwrap();
+        let fixture: Value = serde_json::from_str(&fixture).unwrap();
+        ethers::utils::hex::decode(fixture[field].as_str().unwrap().trim_start_matches("0x"))
+            .unwrap()
+    }
+
+    #[test]
+    fn it_describes_governance_batches() {
+        let lines = describe_governance_body(&fixture_message("inlineBatchMessage")).unwrap();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("batch of 3 calls"), "{}", lines[0]);
+        assert!(lines[1].contains("OwnerEnrollReplica"), "{}", lines[1]);
+        assert!(lines[3].contains("raw 0xb49c53a7"), "{}", lines[3]);
+
+        let lines = describe_governance_body(&fixture_message("batchMessage")).unwrap();
+        assert_eq!(lines.len(), 1);
+
+        assert!(describe_governance_body(&[]).is_err());
+    }
+}
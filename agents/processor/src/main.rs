@@ -8,22 +8,37 @@
 #![warn(missing_docs)]
 #![warn(unused_extern_crates)]
 
+mod anomaly;
+mod backlog;
+mod delays;
+mod enrollment;
+mod gas;
+mod governance;
 mod processor;
 mod prover_sync;
 mod push;
+mod reconcile;
+mod sender_queue;
 mod settings;
+mod stale;
 
 use color_eyre::Result;
+use std::{fs::File, io::BufReader};
 use tracing::info_span;
 
 use crate::{processor::Processor, settings::ProcessorSettings as Settings};
-use nomad_base::NomadAgent;
+use nomad_base::{FatalError, NomadAgent, TreeSnapshot};
 
 use tracing_subscriber::prelude::*;
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<()> {
+async fn main() {
+    nomad_base::exit_with_summary(Processor::AGENT_NAME, run().await)
+}
+
+async fn run() -> Result<()> {
     color_eyre::install()?;
+    nomad_base::dump_schema_if_requested();
 
     // sets the subscriber for this scope only
     let _bootup_guard = tracing_subscriber::FmtSubscriber::builder()
@@ -34,8 +49,25 @@ async fn main() -> Result<()> {
     let span = info_span!("ProcessorBootup");
     let _span = span.enter();
 
-    let settings = Settings::new().await?;
-    let agent = Processor::from_settings(settings).await?;
+    let settings = Settings::new()
+        .await
+        .map_err(|e| FatalError::config("settings", e))?;
+    let agent = Processor::from_settings(settings)
+        .await
+        .map_err(|e| FatalError::classify_startup(e, "setup"))?;
+    agent.verify_db_if_requested()?;
+
+    if let Some(path) = nomad_base::bootstrap_path_if_requested() {
+        let snapshot = File::open(path)
+            .map_err(Into::into)
+            .and_then(|file| TreeSnapshot::read_from(&mut BufReader::new(file)))
+            .map_err(|e| FatalError::config("bootstrap", e))?;
+        agent
+            .home()
+            .bootstrap_from(snapshot)
+            .await
+            .map_err(|e| FatalError::classify_startup(e, "bootstrap"))?;
+    }
 
     drop(_span);
     drop(span);
@@ -2,55 +2,121 @@ use async_trait::async_trait;
 use color_eyre::{eyre::bail, Result};
 use ethers::prelude::H256;
 use futures_util::future::select_all;
-use nomad_xyz_configuration::S3Config;
+use nomad_xyz_configuration::{
+    agent::processor::{ProcessingOrder, ReconciliationLookback},
+    S3Config,
+};
 use std::{
     collections::{HashMap, HashSet},
-    sync::Arc,
-    time::Duration,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use tokio::{sync::RwLock, task::JoinHandle, time::sleep};
+use tokio::{sync::RwLock, task::JoinHandle};
 use tracing::{
     debug, error, info, info_span, instrument, instrument::Instrumented, warn, Instrument,
 };
 
 use nomad_base::{
-    cancel_task, decl_agent, decl_channel, AgentCore, CachingHome, CachingReplica,
-    ChainCommunicationError, NomadAgent, NomadDB, ProcessorError,
+    cancel_task, chains::PageSettings, decl_agent, decl_channel, serve_admin, AgentCore,
+    AuditEvent, AuditId, CachingHome, CachingReplica, ChainCommunicationError, ClockSkew,
+    ClockSkewMonitor, ConnectionManagers, CoreMetrics, CorridorIntCounter, CorridorIntGauge,
+    CorridorSwitches, NomadAgent, NomadDB, ProcessingLeg, ProcessingRoot, ProcessorError, Retry,
 };
 use nomad_core::{
     accumulator::{MerkleProof, NomadProof},
-    CommittedMessage, Common, Home, HomeEvents, MessageStatus,
+    capture_submission,
+    utils::saturating_u64,
+    Address32, CanonicalH256, CommittedMessage, Common, Home, HomeEvents, MessageStatus,
+    RootProvenance, TxOutcome,
 };
 
-use crate::{prover_sync::ProverSync, push::Pusher, settings::ProcessorSettings as Settings};
+use crate::{
+    anomaly::RateMonitor,
+    backlog::BacklogMonitor,
+    delays::{self, Confirmation, CorridorDelays},
+    enrollment::EnrollmentMonitor,
+    gas::RecipientGasMonitor,
+    governance::log_governance_message,
+    prover_sync::ProverSync,
+    push::Pusher,
+    reconcile::{self, ReconcileWindow},
+    sender_queue::{HeadAction, SenderQueues},
+    settings::ProcessorSettings as Settings,
+    stale::{self, StaleHolds},
+};
 
 const AGENT_NAME: &str = "processor";
-static CURRENT_NONCE: &str = "current_nonce_";
+pub(crate) static CURRENT_NONCE: &str = "current_nonce_";
+
+/// Default seconds of clock skew against a replica chain to alert on
+const DEFAULT_CLOCK_SKEW_ALERT_SECONDS: u64 = 30;
+/// Default seconds of safety margin on skew-corrected chain time
+const DEFAULT_CLOCK_SKEW_MARGIN_SECONDS: u64 = 5;
+/// Default seconds past chain time beyond which a `confirmAt` is never
+/// reached: 10 years
+const DEFAULT_CONFIRM_HORIZON_SECONDS: u64 = 10 * 365 * 24 * 60 * 60;
+/// Default multiple of a corridor's baseline dispatch rate to alert on
+const DEFAULT_DISPATCH_RATE_ALERT_MULTIPLE: f64 = 20.0;
+/// Default messages before the last processed message inspected again on
+/// startup
+const DEFAULT_RESUME_REWIND: u32 = 5;
+/// Time a root found not attested is taken as such before it is looked up
+/// again
+const PROVENANCE_RECHECK: Duration = Duration::from_secs(60);
 
 enum Flow {
     Advance,
     Repeat,
 }
 
+/// What became of a reverted `proveAndProcess` after falling back
+enum Fallback {
+    /// Processed already, or submitted with `process`
+    Submitted,
+    /// Not provable yet. Retried.
+    NotProvable,
+    /// `process` needs more gas than the cap. Retried on restart.
+    OverGasCap,
+}
+
 /// The replica processor is responsible for polling messages and waiting until they validate
 /// before proving/processing them.
 #[derive(Debug)]
 pub(crate) struct Replica {
     interval: u64,
+    retry: Retry,
     replica: Arc<CachingReplica>,
     home: Arc<CachingHome>,
     db: NomadDB,
     allowed: Option<Arc<HashSet<H256>>>,
     denied: Option<Arc<HashSet<H256>>>,
+    ordering: ProcessingOrder,
+    released: Arc<HashSet<H256>>,
+    delays: Arc<CorridorDelays>,
+    clock_skew: ClockSkew,
+    confirm_horizon: u64,
+    corridors: Arc<CorridorSwitches>,
+    governance_router: Option<H256>,
+    attested_roots_only: bool,
+    provenances: Mutex<HashMap<H256, (Option<RootProvenance>, Instant)>>,
+    provenance_recheck: Duration,
+    reconciliation_lookback: Option<ReconciliationLookback>,
+    full_reconcile: bool,
+    max_message_age: Option<u64>,
+    resume_rewind: u32,
+    stale: Arc<StaleHolds>,
     next_message_nonce: prometheus::IntGauge,
+    processed_failed: prometheus::IntCounter,
+    never_processable: prometheus::IntGauge,
 }
 
 impl std::fmt::Display for Replica {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "ReplicaProcessor: {{ home: {}, replica: {}, allowed: {:?}, denied: {:?} }}",
-            self.home, self.replica, self.allowed, self.denied
+            "ReplicaProcessor: {{ home: {}, replica: {}, allowed: {:?}, denied: {:?}, ordering: {:?} }}",
+            self.home, self.replica, self.allowed, self.denied, self.ordering
         )
     }
 }
@@ -72,11 +138,7 @@ impl Replica {
                 //      - If not, wait and poll again
                 // 4. Check if the proof is valid under the replica
                 // 5. Submit the proof to the replica
-                let mut next_message_nonce: u32 = self
-                    .db
-                    .retrieve_keyed_decodable(CURRENT_NONCE, &replica_domain)?
-                    .map(|n: u32| n + 1)
-                    .unwrap_or_default();
+                let mut next_message_nonce = self.resume_nonce(replica_domain)?;
 
                 self.next_message_nonce.set(next_message_nonce as i64);
 
@@ -90,7 +152,34 @@ impl Replica {
                     next_message_nonce
                 );
 
+                self.reconcile(replica_domain).await?;
+                self.retry_gas_capped(replica_domain).await?;
+
+                let mut last_drain: Option<Instant> = None;
+
                 loop {
+                    // A paused corridor is left alone until it is resumed
+                    if !self.corridors.is_enabled(self.replica.name()) {
+                        debug!(
+                            replica_domain,
+                            replica = self.replica.name(),
+                            "Corridor is paused. Not processing."
+                        );
+                        if !self.retry.sleep(1).await {
+                            return Ok(());
+                        }
+                        continue;
+                    }
+
+                    // Held messages are retried at most once per interval
+                    if last_drain.map_or(true, |t| t.elapsed().as_secs() >= self.interval) {
+                        if self.ordering == ProcessingOrder::PerSenderNonce {
+                            self.drain_sender_queues(replica_domain).await?;
+                        }
+                        self.drain_stale_holds().await?;
+                        last_drain = Some(Instant::now());
+                    }
+
                     let seq_span = tracing::trace_span!(
                         "ReplicaProcessor",
                         name = self.replica.name(),
@@ -122,7 +211,9 @@ impl Replica {
                                 next_message_nonce,
                                 replica_domain,
                             );
-                            sleep(Duration::from_secs(self.interval)).await
+                            if !self.retry.sleep(1).await {
+                                return Ok(());
+                            }
                         }
                         Err(e) => {
                             error!("fatal error in processor::Replica: {}", e);
@@ -135,6 +226,29 @@ impl Replica {
         )
     }
 
+    /// Nonce to resume inspecting messages to `domain` at: past both the
+    /// last nonce advanced past and the latest message confirmed processed,
+    /// rewound by `resume_rewind` messages in case the home reorganized
+    /// them. Messages inspected again that are already processed are
+    /// skipped.
+    fn resume_nonce(&self, domain: u32) -> Result<u32> {
+        let advanced = self
+            .db
+            .retrieve_keyed_decodable(CURRENT_NONCE, &domain)?
+            .map(|n: u32| n + 1)
+            .unwrap_or_default();
+
+        let processed = match self.db.latest_processed_leaf_for_destination(domain)? {
+            Some(leaf_index) => match self.db.message_by_leaf_index(leaf_index)? {
+                Some(raw) => CommittedMessage::try_from(raw)?.message.nonce + 1,
+                None => 0,
+            },
+            None => 0,
+        };
+
+        Ok(advanced.max(processed).saturating_sub(self.resume_rewind))
+    }
+
     /// Attempt to process a message.
     ///
     /// Postcondition: ```match retval? {
@@ -143,6 +257,10 @@ impl Replica {
     /// }```
     ///
     /// In case of error: send help?
+    ///
+    /// CancellationSafe: queue writes follow the last await, and holding a
+    /// message is idempotent. A message attempted before a cancellation is
+    /// held on the retry of its nonce.
     #[instrument(err, skip(self), fields(self = %self))]
     async fn try_msg_by_domain_and_nonce(&self, domain: u32, nonce: u32) -> Result<Flow> {
         use nomad_core::Replica;
@@ -162,7 +280,7 @@ impl Replica {
         // if we have an allow list, filter senders not on it
         if let Some(false) = self.allowed.as_ref().map(|set| set.contains(&sender)) {
             info!(
-                sender = ?sender,
+                sender = %Address32(sender).readable(),
                 domain = domain,
                 nonce = nonce,
                 "Skipping message because sender not on allow list."
@@ -173,7 +291,7 @@ impl Replica {
         // if we have a deny list, filter senders on it
         if let Some(true) = self.denied.as_ref().map(|set| set.contains(&sender)) {
             info!(
-                sender = ?sender,
+                sender = %Address32(sender).readable(),
                 domain = domain,
                 nonce = nonce,
                 "Skipping message because sender on deny list."
@@ -181,17 +299,123 @@ impl Replica {
             return Ok(Flow::Advance);
         }
 
-        let proof = match self.db.proof_by_leaf_index(message.leaf_index) {
-            Ok(Some(p)) => p,
-            Ok(None) => {
+        // hold messages behind an earlier unprocessed message of the sender
+        let per_sender = self.ordering == ProcessingOrder::PerSenderNonce;
+        if per_sender {
+            let mut queues = SenderQueues::load(&self.db, domain)?;
+            if queues.is_blocked(&sender) {
+                queues.hold(sender, nonce);
+                queues.store(&self.db, domain)?;
+                info!(
+                    sender = %Address32(sender).readable(),
+                    domain = domain,
+                    nonce = nonce,
+                    head = ?queues.head(&sender),
+                    held = queues.len(),
+                    "Holding message behind an unprocessed earlier message of its sender."
+                );
+                return Ok(Flow::Advance);
+            }
+        }
+
+        // hold messages older than the maximum age until they are released
+        if self.held_for_age(&message)? {
+            if per_sender {
+                let mut queues = SenderQueues::load(&self.db, domain)?;
+                queues.hold(sender, nonce);
+                queues.store(&self.db, domain)?;
+            }
+            return Ok(Flow::Advance);
+        }
+
+        let proof = match self.proof_for(&message)? {
+            Some(p) => p,
+            None => return Ok(Flow::Repeat),
+        };
+
+        if Some(message.message.recipient) == self.governance_router {
+            log_governance_message(&message);
+        }
+
+        loop {
+            let state = self
+                .replica
+                .processing_state(proof.root(), message.to_leaf())
+                .await?;
+            if state.root_acceptable {
+                break;
+            }
+            if self.never_processable(&message, proof.root(), state.confirm_at)? {
+                return Ok(Flow::Repeat);
+            }
+            info!(
+                leaf_hash = ?message.to_leaf(),
+                leaf_index = message.leaf_index,
+                "Proof under {root} not yet valid here, waiting until Replica confirms",
+                root = proof.root(),
+            );
+            if !self.retry.sleep(1).await {
+                return Ok(Flow::Repeat);
+            }
+        }
+        self.never_processable.set(0);
+
+        if !self.wait_for_extra_delay(&message, proof.root()).await? {
+            return Ok(Flow::Repeat);
+        }
+
+        let provenance = self.provenance(proof.root()).await;
+        if !self.admits(&message, proof.root(), provenance) {
+            return Ok(Flow::Repeat);
+        }
+
+        info!(
+            leaf_hash = ?message.to_leaf(),
+            leaf_index = message.leaf_index,
+            "Dispatching a message for processing {}:{}",
+            domain,
+            nonce
+        );
+
+        let leaf = message.to_leaf();
+        if !self.process(message, proof, provenance).await? {
+            return Ok(Flow::Repeat);
+        }
+        if self.stale.get(leaf).is_some() {
+            self.stale.remove(leaf)?;
+        }
+
+        // a message that did not reach `Processed` blocks its sender
+        if per_sender && !self.is_processed(leaf).await? {
+            let mut queues = SenderQueues::load(&self.db, domain)?;
+            queues.hold(sender, nonce);
+            queues.store(&self.db, domain)?;
+            warn!(
+                sender = %Address32(sender).readable(),
+                domain = domain,
+                nonce = nonce,
+                leaf = %CanonicalH256(leaf),
+                "Message was not processed. Holding later messages of its sender until it is processed or released."
+            );
+        }
+
+        Ok(Flow::Advance)
+    }
+
+    /// Retrieve the proof of a message, checking it against the message's
+    /// leaf. Returns `None` if the proof is not yet available. Malformed
+    /// proof records are quarantined and also reported as not available.
+    fn proof_for(&self, message: &CommittedMessage) -> Result<Option<NomadProof>> {
+        let proof = match self.db.proof_or_quarantine(message.leaf_index)? {
+            Some(p) => p,
+            None => {
                 info!(
                     leaf_hash = ?message.to_leaf(),
                     leaf_index = message.leaf_index,
                     "Proof not yet found"
                 );
-                return Ok(Flow::Repeat);
+                return Ok(None);
             }
-            Err(e) => bail!(e),
         };
 
         if proof.leaf != message.to_leaf() {
@@ -202,65 +426,407 @@ impl Replica {
             });
         }
 
-        while !self.replica.acceptable_root(proof.root()).await? {
-            info!(
+        Ok(Some(proof))
+    }
+
+    /// Whether messages under `root` can never be processed, because the
+    /// replica's `confirmAt` for it lies beyond the horizon. Such a root is
+    /// not waited on; it is alerted on and rechecked on the next attempt, in
+    /// case the replica's owner corrects it.
+    fn never_processable(
+        &self,
+        message: &CommittedMessage,
+        root: H256,
+        confirm_at: u64,
+    ) -> Result<bool> {
+        let now = self.clock_skew.chain_now_from_host()?;
+        let never =
+            Confirmation::classify(confirm_at, now, self.confirm_horizon) == Confirmation::Never;
+        self.never_processable.set(never as i64);
+        if never {
+            error!(
                 leaf_hash = ?message.to_leaf(),
                 leaf_index = message.leaf_index,
-                "Proof under {root} not yet valid here, waiting until Replica confirms",
-                root = proof.root(),
+                root = ?root,
+                confirm_at,
+                horizon = self.confirm_horizon,
+                "Replica confirms root beyond the horizon. Its messages are never processable. Check the replica's optimisticSeconds and confirmAt."
             );
-            sleep(Duration::from_secs(self.interval)).await;
         }
+        Ok(never)
+    }
 
-        info!(
+    /// How the replica came to accept `root`, on corridors restricted to
+    /// attested roots. `None` on other corridors, whose policy does not need
+    /// it, and if the lookup fails. An update attesting to a root is never
+    /// undone, so attested roots are not looked up again. Other roots are
+    /// looked up again after `provenance_recheck`.
+    async fn provenance(&self, root: H256) -> Option<RootProvenance> {
+        use nomad_core::Replica;
+
+        if !self.attested_roots_only {
+            return None;
+        }
+        if let Some((provenance, checked)) = self.provenances.lock().expect("poisoned").get(&root) {
+            if provenance.map_or(false, |p| p.is_attested())
+                || checked.elapsed() < self.provenance_recheck
+            {
+                return *provenance;
+            }
+        }
+        match self.replica.root_provenance(root).await {
+            Ok(provenance) => {
+                self.provenances
+                    .lock()
+                    .expect("poisoned")
+                    .insert(root, (provenance, Instant::now()));
+                provenance
+            }
+            Err(e) => {
+                warn!(
+                    root = ?root,
+                    error = %e,
+                    "Failed to look up how the replica accepted a root. Not processing under it until known."
+                );
+                None
+            }
+        }
+    }
+
+    /// Whether messages may be processed under `root`, given how the replica
+    /// came to accept it. Corridors restricted to attested roots wait for an
+    /// update attesting to any other root.
+    fn admits(
+        &self,
+        message: &CommittedMessage,
+        root: H256,
+        provenance: Option<RootProvenance>,
+    ) -> bool {
+        if !self.attested_roots_only || provenance.map_or(false, |p| p.is_attested()) {
+            return true;
+        }
+        warn!(
             leaf_hash = ?message.to_leaf(),
             leaf_index = message.leaf_index,
-            "Dispatching a message for processing {}:{}",
-            domain,
-            nonce
+            root = ?root,
+            provenance = ?provenance,
+            "Root was not attested by the updater. Corridor only processes under attested roots, waiting for an update to the root."
         );
+        false
+    }
+
+    /// Timestamp after which messages under `root` may be processed, or
+    /// `None` if the corridor has no extra delay. The delay is looked up on
+    /// each call, so reloaded delays apply to messages already past the
+    /// optimistic window.
+    async fn ready_at(&self, root: H256) -> Result<Option<u64>> {
+        use nomad_core::Replica;
 
-        self.process(message, proof).await?;
+        let extra_delay = self.delays.extra_delay(self.replica.name());
+        if extra_delay == 0 {
+            return Ok(None);
+        }
+        let confirm_at = self.replica.confirm_at(root).await?;
+        Ok(Some(delays::ready_at(confirm_at, extra_delay)))
+    }
 
-        Ok(Flow::Advance)
+    /// Wait out the corridor's extra delay past the optimistic window. Time
+    /// is the replica chain's, estimated from the host clock less its skew.
+    /// Returns false if shutdown interrupted the wait.
+    async fn wait_for_extra_delay(&self, message: &CommittedMessage, root: H256) -> Result<bool> {
+        while let Some(ready_at) = self.ready_at(root).await? {
+            let now = self.clock_skew.chain_now_from_host()?;
+            if now >= ready_at {
+                break;
+            }
+            info!(
+                leaf_hash = ?message.to_leaf(),
+                leaf_index = message.leaf_index,
+                ready_at,
+                extra_delay = self.delays.extra_delay(self.replica.name()),
+                "Holding message past the optimistic window for the corridor's extra delay"
+            );
+            let delay = Duration::from_secs(self.interval.min(ready_at - now));
+            if !self.retry.wait(delay).await {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    async fn is_processed(&self, leaf: H256) -> Result<bool> {
+        use nomad_core::Replica;
+
+        Ok(matches!(
+            self.replica.message_status(leaf).await?,
+            MessageStatus::Processed
+        ))
+    }
+
+    /// Work through the per-sender queues of held messages. A queue's head is
+    /// popped once it is processed on the replica or released by the
+    /// operator, and the next held message is then processed. A head that
+    /// was attempted and did not process is never retried automatically.
+    ///
+    /// CancellationSafe: each pop is stored as it is made, and the queues
+    /// are only written from this replica's task.
+    async fn drain_sender_queues(&self, domain: u32) -> Result<()> {
+        use nomad_core::Replica;
+
+        let mut queues = SenderQueues::load(&self.db, domain)?;
+
+        for sender in queues.senders() {
+            while let Some(nonce) = queues.head(&sender) {
+                let message = match self.db.message_by_nonce(domain, nonce)? {
+                    Some(raw) => CommittedMessage::try_from(raw)?,
+                    None => break,
+                };
+                let leaf = message.to_leaf();
+
+                let status = self.replica.message_status(leaf).await?;
+                let attempted = self.attempted(&message).await?;
+                let released = self.released.contains(&leaf);
+
+                match HeadAction::decide(status, attempted, released) {
+                    HeadAction::Pop => {
+                        if released {
+                            warn!(
+                                sender = %Address32(sender).readable(),
+                                domain = domain,
+                                nonce = nonce,
+                                leaf = %CanonicalH256(leaf),
+                                "Operator released message. Processing later messages of its sender."
+                            );
+                        }
+                        queues.pop(&sender);
+                        queues.store(&self.db, domain)?;
+                    }
+                    HeadAction::Process => {
+                        if self.held_for_age(&message)? {
+                            break;
+                        }
+                        if !self.process_if_ready(message).await? {
+                            break;
+                        }
+                        if !self.is_processed(leaf).await? {
+                            break;
+                        }
+                    }
+                    HeadAction::Wait => {
+                        debug!(
+                            sender = %Address32(sender).readable(),
+                            domain = domain,
+                            nonce = nonce,
+                            leaf = %CanonicalH256(leaf),
+                            "Sender is held behind an unprocessed message"
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether `message` waits for an operator to release it, for being older
+    /// than the maximum message age by its dispatch timestamp. A stale
+    /// message is held on its first check. With a maximum age, messages of
+    /// unknown age are held too.
+    fn held_for_age(&self, message: &CommittedMessage) -> Result<bool> {
+        if let Some(hold) = self.stale.get(message.to_leaf()) {
+            return Ok(!hold.released);
+        }
+        if self.max_message_age.is_none() {
+            return Ok(false);
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        if let Some(dispatched_at) = message.timestamp {
+            if !stale::is_stale(dispatched_at, now, self.max_message_age) {
+                return Ok(false);
+            }
+        }
+        self.stale
+            .hold(self.replica.name(), message, message.timestamp, now)?;
+        Ok(true)
+    }
+
+    /// Process a held `message` if its proof is available and the replica
+    /// admits processing under the proof's root now. Does not wait. Returns
+    /// false if the message is not ready.
+    async fn process_if_ready(&self, message: CommittedMessage) -> Result<bool> {
+        use nomad_core::Replica;
+
+        let proof = match self.proof_for(&message)? {
+            Some(p) => p,
+            None => return Ok(false),
+        };
+        if !self.replica.acceptable_root(proof.root()).await? {
+            return Ok(false);
+        }
+        if let Some(ready_at) = self.ready_at(proof.root()).await? {
+            if self.clock_skew.chain_now_from_host()? < ready_at {
+                return Ok(false);
+            }
+        }
+        let provenance = self.provenance(proof.root()).await;
+        if !self.admits(&message, proof.root(), provenance) {
+            return Ok(false);
+        }
+
+        info!(
+            sender = %Address32(message.message.sender).readable(),
+            domain = message.message.destination,
+            nonce = message.message.nonce,
+            "Dispatching held message for processing"
+        );
+        self.process(message, proof, provenance).await
+    }
+
+    /// Process the messages released from their hold for age, and drop the
+    /// holds of messages processed since, or attempted after their release.
+    /// Under per-sender ordering, a released message is processed from its
+    /// sender's queue instead, so it does not overtake its sender's earlier
+    /// messages.
+    async fn drain_stale_holds(&self) -> Result<()> {
+        for hold in self.stale.of_replica(self.replica.name()) {
+            let message = match self.db.message_by_leaf(hold.leaf)? {
+                Some(raw) => CommittedMessage::try_from(raw)?,
+                None => continue,
+            };
+            if hold.released && self.ordering != ProcessingOrder::PerSenderNonce {
+                self.process_if_ready(message.clone()).await?;
+            }
+            if self.is_processed(hold.leaf).await?
+                || (hold.released && self.attempted(&message).await?)
+            {
+                self.stale.remove(hold.leaf)?;
+            }
+        }
+        Ok(())
     }
 
     #[instrument(err, level = "info", skip(self), fields(self = %self, domain = message.message.destination, nonce = message.message.nonce, leaf_index = message.leaf_index, leaf = ?message.message.to_leaf()))]
     /// Dispatch a message for processing. If the message is already proven, process only.
-    async fn process(&self, message: CommittedMessage, proof: NomadProof) -> Result<()> {
+    /// If `proveAndProcess` reverts, falls back to `process` when the message
+    /// turns out to be proven already. The provenance of the proof's root is recorded with the decision.
+    ///
+    /// The root and the message's status are checked at one block first.
+    /// Returns false without dispatching if the replica did not accept the
+    /// root at that block, as when the eligibility check was answered by a
+    /// node ahead of the others, and if `proveAndProcess` reverted on a
+    /// message not provable yet.
+    ///
+    /// A message needing more gas than the chain's cap is alerted on and
+    /// recorded over the cap instead of attempted, so it does not halt
+    /// processing and is retried on restart, once the cap may be raised.
+    ///
+    /// CancellationSafe: the outcome and the attempt are recorded together
+    /// after the last await. A submission cancelled before they are recorded
+    /// is found processed on chain on the retry, and recorded then.
+    async fn process(
+        &self,
+        message: CommittedMessage,
+        proof: NomadProof,
+        provenance: Option<RootProvenance>,
+    ) -> Result<bool> {
         use nomad_core::Replica;
 
         // First check locally to see if we've tried before
-        if self.db.previously_attempted(&message)? {
+        if self.attempted(&message).await? {
             info!("Message already attempted");
-            return Ok(());
+            return Ok(true);
+        }
+        if self.db.gas_capped(&message)? {
+            debug!("Message was skipped over the gas cap. Retried on restart.");
+            return Ok(true);
         }
 
         // Then check on-chain status
-        let status = self.replica.message_status(message.to_leaf()).await?;
+        let state = self
+            .replica
+            .processing_state(proof.root(), message.to_leaf())
+            .await?;
+        let status = state.status;
 
         // shortcut here to DRY up later function
         if let MessageStatus::Processed = status {
+            self.record_process_outcome(&message, None).await?;
             self.db.set_previously_attempted(&message)?;
-            return Ok(());
+            return Ok(true);
+        }
+
+        if !state.root_acceptable {
+            warn!(
+                root = ?proof.root(),
+                "Replica does not accept the proof's root at the block its status was read at. Not dispatching until reads agree."
+            );
+            return Ok(false);
+        }
+
+        let decision = self.db.audit(AuditEvent::ProcessMessage {
+            replica: self.replica.name().to_owned(),
+            leaf: message.to_leaf(),
+            leaf_index: message.leaf_index,
+            nonce: message.message.nonce,
+            proof_root: proof.root(),
+            prove: matches!(status, MessageStatus::None),
+            provenance,
+        })?;
+        if let Some(provenance) = provenance {
+            self.db.store_processing_root(
+                &message,
+                &ProcessingRoot {
+                    root: proof.root(),
+                    provenance,
+                },
+            )?;
         }
 
         // We don't care if the prove/process succeeds. We just want it to be
         // dispatched to the chain. We'll still log warnings if they fail
-        let fut = match status {
-            MessageStatus::None => self.replica.prove_and_process(message.as_ref(), &proof),
-            MessageStatus::Proven(_) => self.replica.process(message.as_ref()),
+        let (fut, leg) = match status {
+            MessageStatus::None => (
+                self.replica.prove_and_process(message.as_ref(), &proof),
+                ProcessingLeg::ProveAndProcess,
+            ),
+            MessageStatus::Proven(_) => (
+                self.replica.process(message.as_ref()),
+                ProcessingLeg::Process,
+            ),
             _ => unreachable!(),
         };
         info!("Submitting message for processing");
-        let result = fut.await;
+        let (result, payload) = capture_submission(fut).await;
+        self.db.audit_submission(
+            decision,
+            self.replica.name(),
+            &result,
+            payload.as_ref(),
+            |tx| tx.txid,
+        )?;
 
         // handle reverts specifically by logging and ignoring.
         // Other errors are bubbled up
         match result {
-            Ok(_) => {}
+            Ok(outcome) => {
+                self.record_gas(&message, leg, &outcome)?;
+                self.record_process_outcome(&message, outcome.block_number)
+                    .await?
+            }
             Err(ChainCommunicationError::TxNotExecuted(txid)) => {
                 warn!(txid = ?txid, "Error in processing. May indicate an internal revert of the handler.");
+                if let ProcessingLeg::ProveAndProcess = leg {
+                    match self.fall_back_to_process(&message, decision).await? {
+                        Fallback::Submitted => {}
+                        Fallback::NotProvable => return Ok(false),
+                        Fallback::OverGasCap => return Ok(true),
+                    }
+                }
+            }
+            Err(e @ ChainCommunicationError::GasLimitExceeded { .. }) => {
+                self.skip_over_gas_cap(&message, &e)?;
+                return Ok(true);
             }
             Err(e) => {
                 bail!(e)
@@ -268,6 +834,246 @@ impl Replica {
         }
         // Store that we've attempted processing
         self.db.set_previously_attempted(&message)?;
+        Ok(true)
+    }
+
+    /// Recover from a reverted `proveAndProcess` by checking the message
+    /// status again. A message proven in the meantime, e.g. by another
+    /// processor, is processed alone. A message not provable yet is not
+    /// recorded attempted and is retried.
+    async fn fall_back_to_process(
+        &self,
+        message: &CommittedMessage,
+        decision: AuditId,
+    ) -> Result<Fallback> {
+        use nomad_core::Replica;
+
+        match self.replica.message_status(message.to_leaf()).await? {
+            MessageStatus::None => {
+                warn!("Message not provable yet after proveAndProcess reverted. Will retry.");
+                Ok(Fallback::NotProvable)
+            }
+            MessageStatus::Processed => {
+                self.record_process_outcome(message, None).await?;
+                Ok(Fallback::Submitted)
+            }
+            MessageStatus::Proven(_) => {
+                info!("Message already proven. Falling back to process.");
+                let (result, payload) =
+                    capture_submission(self.replica.process(message.as_ref())).await;
+                self.db.audit_submission(
+                    decision,
+                    self.replica.name(),
+                    &result,
+                    payload.as_ref(),
+                    |tx| tx.txid,
+                )?;
+                match result {
+                    Ok(outcome) => {
+                        self.record_gas(message, ProcessingLeg::Process, &outcome)?;
+                        self.record_process_outcome(message, outcome.block_number)
+                            .await?
+                    }
+                    Err(ChainCommunicationError::TxNotExecuted(txid)) => {
+                        warn!(txid = ?txid, "Error in processing. May indicate an internal revert of the handler.");
+                    }
+                    Err(e @ ChainCommunicationError::GasLimitExceeded { .. }) => {
+                        self.skip_over_gas_cap(message, &e)?;
+                        return Ok(Fallback::OverGasCap);
+                    }
+                    Err(e) => {
+                        bail!(e)
+                    }
+                }
+                Ok(Fallback::Submitted)
+            }
+        }
+    }
+
+    /// Alert on a message whose submission needs more gas than the chain's
+    /// cap, and record it skipped. The estimate will not fall on a retry, so
+    /// it is only retried on restart, when a raised cap takes effect.
+    fn skip_over_gas_cap(
+        &self,
+        message: &CommittedMessage,
+        error: &ChainCommunicationError,
+    ) -> Result<()> {
+        error!(
+            error = %error,
+            leaf_index = message.leaf_index,
+            "Message needs more gas than the configured cap. Skipping it until restart. Raise maxGasLimit or process it manually."
+        );
+        self.db.set_gas_capped(message)?;
+        Ok(())
+    }
+
+    /// Retry the messages to `domain` skipped for needing more gas than the
+    /// cap, which may have been raised since. A message not ready to be
+    /// processed stays recorded for the next restart.
+    async fn retry_gas_capped(&self, domain: u32) -> Result<()> {
+        for leaf_index in self.db.gas_capped_leaf_indexes(domain)? {
+            let message = match self.db.message_by_leaf_index(leaf_index)? {
+                Some(raw) => CommittedMessage::try_from(raw)?,
+                None => continue,
+            };
+            info!(
+                leaf_index,
+                leaf = ?message.to_leaf(),
+                "Retrying message skipped over the gas cap"
+            );
+            self.db.clear_gas_capped(&message)?;
+            if !self.process_if_ready(message.clone()).await? {
+                self.db.set_gas_capped(&message)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reconcile the records of messages to this replica in the startup
+    /// window with their status on the replica. A message processed while
+    /// the agent was down is recorded attempted, with its outcome.
+    async fn reconcile(&self, domain: u32) -> Result<()> {
+        use nomad_core::Replica;
+
+        let started = Instant::now();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let window = match ReconcileWindow::new(
+            &self.db,
+            domain,
+            self.reconciliation_lookback,
+            self.full_reconcile,
+            now,
+        )? {
+            Some(window) => window,
+            None => return Ok(()),
+        };
+
+        let mut reconciled = 0u32;
+        for leaf_index in window.start..=window.end {
+            let message = match self.db.message_by_leaf_index(leaf_index)? {
+                Some(raw) => CommittedMessage::try_from(raw)?,
+                None => continue,
+            };
+            if message.message.destination != domain {
+                continue;
+            }
+            self.reconcile_message(&message).await?;
+            reconciled += 1;
+        }
+        window.store(&self.db, domain)?;
+
+        info!(
+            replica = self.replica.name(),
+            start = window.start,
+            end = window.end,
+            reconciled,
+            full = self.full_reconcile,
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "Reconciled message records with the replica"
+        );
+        Ok(())
+    }
+
+    /// Record `message` attempted, with its outcome, if it is processed on
+    /// the replica and was not attempted by this processor
+    async fn reconcile_message(&self, message: &CommittedMessage) -> Result<()> {
+        let leaf = message.to_leaf();
+        let attempted = self.db.previously_attempted(message)?;
+        match (self.is_processed(leaf).await?, attempted) {
+            (true, false) => {
+                self.record_process_outcome(message, None).await?;
+                self.db.set_previously_attempted(message)?;
+            }
+            (false, true) => warn!(
+                leaf = ?leaf,
+                leaf_index = message.leaf_index,
+                nonce = message.message.nonce,
+                "Message recorded attempted is not processed on the replica"
+            ),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Whether `message` was attempted before. A message older than every
+    /// startup reconciliation is reconciled on its first access.
+    async fn attempted(&self, message: &CommittedMessage) -> Result<bool> {
+        if reconcile::needs_lazy_reconciliation(&self.db, message)? {
+            self.reconcile_message(message).await?;
+            reconcile::set_lazily_reconciled(&self.db, message)?;
+        }
+        Ok(self.db.previously_attempted(message)?)
+    }
+
+    /// Add the gas used by a leg of processing `message` to the totals of
+    /// its recipient
+    fn record_gas(
+        &self,
+        message: &CommittedMessage,
+        leg: ProcessingLeg,
+        outcome: &TxOutcome,
+    ) -> Result<()> {
+        if let Some(gas_used) = outcome.gas_used {
+            self.db.record_recipient_gas(
+                message.message.destination,
+                message.message.recipient,
+                message.leaf_index,
+                leg,
+                saturating_u64(gas_used),
+                SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Check the `Process` event of a processed message and record the
+    /// message as `ProcessedFailed` if its handler reverted. The replica marks
+    /// these messages processed regardless, so they cannot be re-submitted
+    /// and must be remediated manually by the recipient xApp. The event is
+    /// looked up in `block` if we processed the message.
+    async fn record_process_outcome(
+        &self,
+        message: &CommittedMessage,
+        block: Option<u64>,
+    ) -> Result<()> {
+        use nomad_core::Replica;
+
+        let leaf = message.to_leaf();
+        // The outcome is informational. Failing to look it up does not fail
+        // the message.
+        let success = match self.replica.process_success(leaf, block).await {
+            Ok(success) => success,
+            Err(e) => {
+                warn!(leaf = ?leaf, error = %e, "Failed to look up the outcome of a processed message");
+                None
+            }
+        };
+        match success {
+            Some(false) => {
+                error!(
+                    target: "processed_failed",
+                    leaf = ?leaf,
+                    leaf_index = message.leaf_index,
+                    origin = message.message.origin,
+                    destination = message.message.destination,
+                    nonce = message.message.nonce,
+                    sender = %Address32(message.message.sender).readable(),
+                    recipient = %Address32(message.message.recipient).readable(),
+                    "Message was processed but its handler reverted. Needs manual remediation by the recipient xApp."
+                );
+                self.db.set_processed_failed(message)?;
+                self.processed_failed.inc();
+            }
+            Some(true) => {}
+            None => warn!(
+                leaf = ?leaf,
+                leaf_index = message.leaf_index,
+                "Outcome of processed message not found. Its Process event could not be looked up."
+            ),
+        }
+        self.db
+            .store_latest_processed_leaf(message.message.destination, message.leaf_index)?;
+
         Ok(())
     }
 }
@@ -280,47 +1086,401 @@ decl_agent!(
         allowed: Option<Arc<HashSet<H256>>>,
         denied: Option<Arc<HashSet<H256>>>,
         subsidized_remotes: HashSet<String>,
-        next_message_nonces: prometheus::IntGaugeVec,
+        next_message_nonces: CorridorIntGauge,
+        processed_failed_messages: CorridorIntCounter,
+        backlog_pending: CorridorIntGauge,
+        backlog_oldest_age: CorridorIntGauge,
+        backlog_clear_time: CorridorIntGauge,
         config: Option<S3Config>,
+        ordering: ProcessingOrder,
+        released: Arc<HashSet<H256>>,
+        delays: Arc<CorridorDelays>,
+        clock_skew_seconds: prometheus::IntGaugeVec,
+        clock_skew_alert: prometheus::IntGaugeVec,
+        clock_skew_alert_seconds: u64,
+        clock_skew_margin_seconds: u64,
+        confirm_horizon_seconds: u64,
+        never_processable_roots: CorridorIntGauge,
+        dispatch_rate_alert_multiple: f64,
+        dispatch_rate_anomalies: CorridorIntGauge,
+        corridors: Arc<CorridorSwitches>,
+        admin_port: Option<u16>,
+        attested_roots_only: HashSet<String>,
+        reconciliation_lookback: Option<ReconciliationLookback>,
+        full_reconcile: bool,
+        connection_managers: HashMap<String, Arc<ConnectionManagers>>,
+        pause_unenrolled: bool,
+        replica_enrolled: CorridorIntGauge,
+        max_message_age: Option<u64>,
+        resume_rewind: u32,
+        stale: Arc<StaleHolds>,
+        recipient_gas: prometheus::IntGaugeVec,
     }
 );
 
-impl Processor {
-    /// Instantiate a new processor
-    pub fn new(
-        interval: u64,
-        core: AgentCore,
-        allowed: Option<HashSet<H256>>,
-        denied: Option<HashSet<H256>>,
-        subsidized_remotes: HashSet<String>,
-        config: Option<S3Config>,
-    ) -> Self {
-        let next_message_nonces = core
-            .metrics
-            .new_int_gauge_vec(
-                "next_message_nonce",
-                "Index of the next message to inspect",
-                &["home", "replica", "agent"],
-            )
-            .expect("processor metric already registered -- should have be a singleton");
-
-        Self {
-            interval,
-            core,
-            replica_tasks: Default::default(),
-            allowed: allowed.map(Arc::new),
-            denied: denied.map(Arc::new),
-            next_message_nonces,
-            subsidized_remotes,
-            config,
-        }
-    }
+/// Processor options, resolved from its settings with defaults applied
+#[derive(Debug)]
+pub struct ProcessorConfig {
+    /// Seconds between polls of each replica
+    pub interval: u64,
+    /// Allowed senders and recipients
+    pub allowed: Option<HashSet<H256>>,
+    /// Denied senders and recipients
+    pub denied: Option<HashSet<H256>>,
+    /// Remotes whose messages are processed at the processor's expense
+    pub subsidized_remotes: HashSet<String>,
+    /// S3 bucket to push proofs to
+    pub s3: Option<S3Config>,
+    /// Order messages are processed in
+    pub ordering: ProcessingOrder,
+    /// Leaves released from their sender's queue
+    pub released: HashSet<H256>,
+    /// Extra seconds to wait before processing, by replica
+    pub extra_delay_seconds: HashMap<String, u64>,
+    /// File extra delays are reloaded from
+    pub extra_delay_path: Option<PathBuf>,
+    /// Seconds of clock skew against a replica chain to alert on
+    pub clock_skew_alert_seconds: u64,
+    /// Seconds of safety margin on skew-corrected chain time
+    pub clock_skew_margin_seconds: u64,
+    /// Seconds past chain time beyond which a `confirmAt` is never reached
+    pub confirm_horizon_seconds: u64,
+    /// Multiple of a corridor's baseline dispatch rate to alert on
+    pub dispatch_rate_alert_multiple: f64,
+    /// Corridors paused on startup, with the reason
+    pub paused: HashMap<String, String>,
+    /// Port of the admin server
+    pub admin_port: Option<u16>,
+    /// Replicas only processing messages under roots attested on the home
+    pub attested_roots_only: HashSet<String>,
+    /// How far back processed messages are reconciled
+    pub reconciliation_lookback: Option<ReconciliationLookback>,
+    /// Reconcile the whole history on startup
+    pub full_reconcile: bool,
+    /// Connection managers, by network
+    pub connection_managers: HashMap<String, Arc<ConnectionManagers>>,
+    /// Pause corridors whose replica is unenrolled
+    pub pause_unenrolled: bool,
+    /// Seconds after dispatch past which messages are held
+    pub max_message_age: Option<u64>,
+    /// Messages before the last processed message inspected again on
+    /// startup
+    pub resume_rewind: u32,
+}
+
+impl ProcessorConfig {
+    /// Resolve the processor options from its settings, building the
+    /// connection managers it monitors enrollment with
+    pub async fn from_settings(settings: Settings) -> Self {
+        // we filter this so that the agent doesn't think it should subsidize
+        // remotes it is unaware of
+        let subsidized_remotes = settings
+            .agent
+            .subsidized_remotes
+            .iter()
+            .filter(|r| settings.base.replicas.contains_key(*r))
+            .cloned()
+            .collect();
+
+        // Connection managers are read only. Without a submitter for their
+        // network, one cannot be built and enrollment is not monitored
+        let mut connection_managers = HashMap::new();
+        for (network, setup) in settings.base.managers.iter().flatten() {
+            let submitter_conf = match settings.base.get_submitter_conf(network) {
+                Some(conf) => conf,
+                None => {
+                    warn!(
+                        network = network.as_str(),
+                        "No transaction submitter for {}. Not monitoring replica enrollment.",
+                        network
+                    );
+                    continue;
+                }
+            };
+            let gas = settings
+                .base
+                .gas
+                .get(network)
+                .map(|c| c.core.connection_manager);
+            match setup
+                .try_into_connection_manager(Some(submitter_conf), gas)
+                .await
+            {
+                Ok(manager) => {
+                    connection_managers.insert(network.to_owned(), Arc::new(manager));
+                }
+                Err(e) => warn!(
+                    network = network.as_str(),
+                    error = %e,
+                    "Invalid XCM setup. Not monitoring replica enrollment."
+                ),
+            }
+        }
+
+        let agent = settings.agent;
+        Self {
+            interval: agent.interval,
+            allowed: agent.allowed,
+            denied: agent.denied,
+            subsidized_remotes,
+            s3: agent.s3,
+            ordering: agent.ordering,
+            released: agent.released,
+            extra_delay_seconds: agent.extra_delay_seconds,
+            extra_delay_path: agent.extra_delay_path,
+            clock_skew_alert_seconds: agent
+                .clock_skew_alert_seconds
+                .unwrap_or(DEFAULT_CLOCK_SKEW_ALERT_SECONDS),
+            clock_skew_margin_seconds: agent
+                .clock_skew_margin_seconds
+                .unwrap_or(DEFAULT_CLOCK_SKEW_MARGIN_SECONDS),
+            confirm_horizon_seconds: agent
+                .confirm_horizon_seconds
+                .unwrap_or(DEFAULT_CONFIRM_HORIZON_SECONDS),
+            dispatch_rate_alert_multiple: agent
+                .dispatch_rate_alert_multiple
+                .unwrap_or(DEFAULT_DISPATCH_RATE_ALERT_MULTIPLE),
+            paused: agent.paused,
+            admin_port: agent.admin_port,
+            attested_roots_only: agent.attested_roots_only,
+            reconciliation_lookback: agent.reconciliation_lookback,
+            full_reconcile: std::env::args().any(|arg| arg == "--full-reconcile"),
+            connection_managers,
+            pause_unenrolled: agent.pause_unenrolled,
+            max_message_age: agent.max_message_age,
+            resume_rewind: agent.resume_rewind.unwrap_or(DEFAULT_RESUME_REWIND),
+        }
+    }
+}
+
+impl Processor {
+    /// Instantiate a new processor, loading its corridor states and held
+    /// messages from the db
+    pub fn new(core: AgentCore, config: ProcessorConfig) -> Result<Self> {
+        let ProcessorConfig {
+            interval,
+            allowed,
+            denied,
+            subsidized_remotes,
+            s3,
+            ordering,
+            released,
+            extra_delay_seconds,
+            extra_delay_path,
+            clock_skew_alert_seconds,
+            clock_skew_margin_seconds,
+            confirm_horizon_seconds,
+            dispatch_rate_alert_multiple,
+            paused,
+            admin_port,
+            attested_roots_only,
+            reconciliation_lookback,
+            full_reconcile,
+            connection_managers,
+            pause_unenrolled,
+            max_message_age,
+            resume_rewind,
+        } = config;
+
+        let next_message_nonces = core
+            .metrics
+            .corridor_int_gauge("next_message_nonce", "Index of the next message to inspect")
+            .expect("processor metric already registered -- should have be a singleton");
+
+        let processed_failed_messages = core
+            .metrics
+            .corridor_int_counter(
+                "processed_failed_messages",
+                "Number of messages processed with a reverted handler (Process success=false)",
+            )
+            .expect("processor metric already registered -- should have be a singleton");
+
+        let backlog_pending = core
+            .metrics
+            .corridor_int_gauge(
+                "backlog_pending_messages",
+                "Number of messages covered by an acceptable root on the replica and not yet processed",
+            )
+            .expect("processor metric already registered -- should have be a singleton");
+
+        let backlog_oldest_age = core
+            .metrics
+            .corridor_int_gauge(
+                "backlog_oldest_pending_age_seconds",
+                "Age of the oldest pending message by the timestamp of its covering update. -1 if unknown",
+            )
+            .expect("processor metric already registered -- should have be a singleton");
+
+        let backlog_clear_time = core
+            .metrics
+            .corridor_int_gauge(
+                "backlog_estimated_clear_seconds",
+                "Estimated seconds to process pending messages at recent throughput. -1 if unknown",
+            )
+            .expect("processor metric already registered -- should have be a singleton");
+
+        let clock_skew_seconds = core
+            .metrics
+            .new_int_gauge_vec(
+                "clock_skew_seconds",
+                "Median seconds the host clock is ahead of the network's latest block timestamps",
+                &["network", "agent"],
+            )
+            .expect("processor metric already registered -- should have be a singleton");
+
+        let clock_skew_alert = core
+            .metrics
+            .new_int_gauge_vec(
+                "clock_skew_alert",
+                "1 if the host clock skew against the network exceeds the alert threshold",
+                &["network", "agent"],
+            )
+            .expect("processor metric already registered -- should have be a singleton");
+
+        let never_processable_roots = core
+            .metrics
+            .corridor_int_gauge(
+                "never_processable_root",
+                "1 if the next message is under a root the replica confirms beyond the horizon",
+            )
+            .expect("processor metric already registered -- should have be a singleton");
+
+        let dispatch_rate_anomalies = core
+            .metrics
+            .corridor_int_gauge(
+                "dispatch_rate_anomaly",
+                "1 if the last window of dispatches to the replica exceeded the alert multiple of its baseline",
+            )
+            .expect("processor metric already registered -- should have be a singleton");
+
+        let replica_enrolled = core
+            .metrics
+            .corridor_int_gauge(
+                "replica_enrolled",
+                "1 if the replica is enrolled for the home in its chain's XAppConnectionManager",
+            )
+            .expect("processor metric already registered -- should have be a singleton");
+
+        let recipient_gas = core
+            .metrics
+            .new_int_gauge_vec(
+                "recipient_gas_used",
+                "Gas used processing messages to the top recipients by gas over the last 7 days",
+                &["destination", "recipient", "agent"],
+            )
+            .expect("processor metric already registered -- should have be a singleton");
+
+        // Configured corridors export their series from startup. Corridors
+        // appearing later get theirs on first use.
+        let replicas = || core.replicas.keys().map(String::as_str);
+        for gauge in [
+            &next_message_nonces,
+            &backlog_pending,
+            &never_processable_roots,
+            &dispatch_rate_anomalies,
+            &replica_enrolled,
+        ] {
+            gauge.prewarm(replicas());
+        }
+        processed_failed_messages.prewarm(replicas());
+        for replica in replicas() {
+            backlog_oldest_age.get(replica).set(-1);
+            backlog_clear_time.get(replica).set(-1);
+        }
+
+        let corridors = Arc::new(
+            CorridorSwitches::new(
+                core.home.name(),
+                AGENT_NAME,
+                core.replicas.keys().cloned(),
+                &paused,
+                core.metrics.clone(),
+            )
+            .expect("processor metric already registered -- should have be a singleton")
+            .persisted(NomadDB::new(core.home.name(), core.db.clone()))?,
+        );
+
+        let stale = Arc::new(StaleHolds::new(
+            NomadDB::new(core.home.name(), core.db.clone()),
+            core.metrics.clone(),
+        )?);
+        if max_message_age.is_some() && (admin_port.is_none() || admin_token().is_none()) {
+            warn!("Messages older than the maximum message age are held, but no admin port and token are set to release them");
+        }
+
+        Ok(Self {
+            interval,
+            core,
+            replica_tasks: Default::default(),
+            allowed: allowed.map(Arc::new),
+            denied: denied.map(Arc::new),
+            next_message_nonces,
+            processed_failed_messages,
+            backlog_pending,
+            backlog_oldest_age,
+            backlog_clear_time,
+            subsidized_remotes,
+            config: s3,
+            ordering,
+            released: Arc::new(released),
+            delays: Arc::new(CorridorDelays::new(extra_delay_seconds, extra_delay_path)),
+            clock_skew_seconds,
+            clock_skew_alert,
+            clock_skew_alert_seconds,
+            clock_skew_margin_seconds,
+            confirm_horizon_seconds,
+            never_processable_roots,
+            dispatch_rate_alert_multiple,
+            dispatch_rate_anomalies,
+            corridors,
+            admin_port,
+            attested_roots_only,
+            reconciliation_lookback,
+            full_reconcile,
+            connection_managers,
+            pause_unenrolled,
+            replica_enrolled,
+            max_message_age,
+            resume_rewind,
+            stale,
+            recipient_gas,
+        })
+    }
 }
 
 decl_channel!(Processor {
     next_message_nonce: prometheus::IntGauge,
+    processed_failed: prometheus::IntCounter,
+    backlog_pending: prometheus::IntGauge,
+    backlog_oldest_age: prometheus::IntGauge,
+    backlog_clear_time: prometheus::IntGauge,
+    metrics: Arc<CoreMetrics>,
     allowed: Option<Arc<HashSet<H256>>>,
     denied: Option<Arc<HashSet<H256>>>,
+    ordering: ProcessingOrder,
+    released: Arc<HashSet<H256>>,
+    delays: Arc<CorridorDelays>,
+    clock_skew_seconds: prometheus::IntGauge,
+    clock_skew_alert: prometheus::IntGauge,
+    clock_skew_alert_seconds: u64,
+    clock_skew_margin_seconds: u64,
+    confirm_horizon_seconds: u64,
+    never_processable: prometheus::IntGauge,
+    dispatch_rate_alert_multiple: f64,
+    dispatch_rate_anomaly: prometheus::IntGauge,
+    corridors: Arc<CorridorSwitches>,
+    governance_router: Option<H256>,
+    attested_roots_only: bool,
+    reconciliation_lookback: Option<ReconciliationLookback>,
+    full_reconcile: bool,
+    connection_manager: Option<Arc<ConnectionManagers>>,
+    manager_page_settings: PageSettings,
+    replica_address: Option<H256>,
+    pause_unenrolled: bool,
+    replica_enrolled: prometheus::IntGauge,
+    max_message_age: Option<u64>,
+    resume_rewind: u32,
+    stale: Arc<StaleHolds>,
     interval: u64,
 });
 
@@ -337,52 +1497,181 @@ impl NomadAgent for Processor {
     where
         Self: Sized,
     {
-        // we filter this so that the agent doesn't think it should subsidize
-        // remotes it is unaware of
-        let subsidized_remotes = settings
-            .agent
-            .subsidized_remotes
-            .iter()
-            .filter(|r| settings.base.replicas.contains_key(*r))
-            .cloned()
-            .collect();
-        Ok(Self::new(
-            settings.agent.interval,
-            settings.as_ref().try_into_core(AGENT_NAME).await?,
-            settings.agent.allowed,
-            settings.agent.denied,
-            subsidized_remotes,
-            settings.agent.s3,
-        ))
+        let core = settings.as_ref().try_into_core(AGENT_NAME).await?;
+        Self::new(core, ProcessorConfig::from_settings(settings).await)
     }
 
     fn build_channel(&self, replica: &str) -> Self::Channel {
         Self::Channel {
             base: self.channel_base(replica),
-            next_message_nonce: self.next_message_nonces.with_label_values(&[
-                self.home().name(),
-                replica,
-                Self::AGENT_NAME,
-            ]),
+            next_message_nonce: self.next_message_nonces.get(replica),
+            processed_failed: self.processed_failed_messages.get(replica),
+            backlog_pending: self.backlog_pending.get(replica),
+            backlog_oldest_age: self.backlog_oldest_age.get(replica),
+            backlog_clear_time: self.backlog_clear_time.get(replica),
+            metrics: self.metrics(),
             allowed: self.allowed.clone(),
             denied: self.denied.clone(),
+            ordering: self.ordering,
+            released: self.released.clone(),
+            delays: self.delays.clone(),
+            clock_skew_seconds: self
+                .clock_skew_seconds
+                .with_label_values(&[replica, Self::AGENT_NAME]),
+            clock_skew_alert: self
+                .clock_skew_alert
+                .with_label_values(&[replica, Self::AGENT_NAME]),
+            clock_skew_alert_seconds: self.clock_skew_alert_seconds,
+            clock_skew_margin_seconds: self.clock_skew_margin_seconds,
+            confirm_horizon_seconds: self.confirm_horizon_seconds,
+            never_processable: self.never_processable_roots.get(replica),
+            dispatch_rate_alert_multiple: self.dispatch_rate_alert_multiple,
+            dispatch_rate_anomaly: self.dispatch_rate_anomalies.get(replica),
+            corridors: self.corridors.clone(),
+            governance_router: self
+                .as_ref()
+                .settings
+                .governance_routers
+                .get(replica)
+                .map(|router| (*router).into()),
+            attested_roots_only: self.attested_roots_only.contains(replica),
+            reconciliation_lookback: self.reconciliation_lookback,
+            full_reconcile: self.full_reconcile,
+            connection_manager: self.connection_managers.get(replica).cloned(),
+            manager_page_settings: self
+                .as_ref()
+                .settings
+                .managers
+                .as_ref()
+                .and_then(|managers| managers.get(replica))
+                .map(|setup| setup.page_settings.clone())
+                .unwrap_or_default(),
+            replica_address: self
+                .as_ref()
+                .settings
+                .replicas
+                .get(replica)
+                .and_then(|setup| setup.address)
+                .map(Into::into),
+            pause_unenrolled: self.pause_unenrolled,
+            replica_enrolled: self.replica_enrolled.get(replica),
+            max_message_age: self.max_message_age,
+            resume_rewind: self.resume_rewind,
+            stale: self.stale.clone(),
             interval: self.interval,
         }
     }
 
+    fn corridors(&self) -> Option<Arc<CorridorSwitches>> {
+        Some(self.corridors.clone())
+    }
+
+    fn run_admin_server(&self) -> Option<Instrumented<JoinHandle<Result<()>>>> {
+        use warp::Filter;
+
+        let port = self.admin_port?;
+        let routes = self
+            .corridors
+            .clone()
+            .routes()
+            .or(self.stale.clone().routes());
+        serve_admin(AGENT_NAME, routes, port)
+    }
+
     fn run(channel: Self::Channel) -> Instrumented<JoinHandle<Result<()>>> {
         tokio::spawn(async move {
-            Replica {
+            let backlog_task = BacklogMonitor {
+                replica: channel.replica(),
+                db: channel.db(),
+                interval: channel.interval,
+                metrics: channel.metrics.clone(),
+                pending: channel.backlog_pending.clone(),
+                oldest_age: channel.backlog_oldest_age.clone(),
+                clear_time: channel.backlog_clear_time.clone(),
+            }
+            .spawn();
+
+            let rate_task = RateMonitor {
+                replica: channel.replica(),
+                db: channel.db(),
+                threshold: channel.dispatch_rate_alert_multiple,
+                anomaly: channel.dispatch_rate_anomaly.clone(),
+            }
+            .spawn();
+
+            let clock_skew = ClockSkew::new(channel.clock_skew_margin_seconds);
+            let clock_skew_task = ClockSkewMonitor {
+                network: channel.replica().name().to_owned(),
+                indexer: channel.replica().indexer(),
+                skew: clock_skew.clone(),
+                alert_seconds: channel.clock_skew_alert_seconds,
+                interval: channel.interval,
+                skew_gauge: channel.clock_skew_seconds.clone(),
+                alert_gauge: channel.clock_skew_alert.clone(),
+            }
+            .spawn();
+
+            let enrollment_task = channel
+                .connection_manager
+                .clone()
+                .zip(channel.replica_address)
+                .map(|(manager, replica_address)| {
+                    EnrollmentMonitor {
+                        network: channel.replica().name().to_owned(),
+                        domain: channel.replica().local_domain(),
+                        home_domain: channel.home().local_domain(),
+                        replica: replica_address,
+                        manager,
+                        db: channel.db(),
+                        from: channel.manager_page_settings.from,
+                        page_size: channel.manager_page_settings.page_size,
+                        interval: channel.interval,
+                        pause_unenrolled: channel.pause_unenrolled,
+                        corridors: channel.corridors.clone(),
+                        metrics: channel.metrics.clone(),
+                        enrolled: channel.replica_enrolled.clone(),
+                    }
+                    .spawn()
+                });
+
+            let result = Replica {
                 interval: channel.interval,
+                retry: Retry::fixed(Duration::from_secs(channel.interval))
+                    .with_cancellation(channel.cancellation()),
                 replica: channel.replica(),
                 home: channel.home(),
                 db: channel.db(),
                 allowed: channel.allowed,
                 denied: channel.denied,
+                ordering: channel.ordering,
+                released: channel.released,
+                delays: channel.delays,
+                clock_skew,
+                confirm_horizon: channel.confirm_horizon_seconds,
+                corridors: channel.corridors,
+                governance_router: channel.governance_router,
+                attested_roots_only: channel.attested_roots_only,
+                provenances: Default::default(),
+                provenance_recheck: PROVENANCE_RECHECK,
+                reconciliation_lookback: channel.reconciliation_lookback,
+                full_reconcile: channel.full_reconcile,
+                max_message_age: channel.max_message_age,
+                resume_rewind: channel.resume_rewind,
+                stale: channel.stale,
                 next_message_nonce: channel.next_message_nonce,
+                processed_failed: channel.processed_failed,
+                never_processable: channel.never_processable,
             }
             .main()
-            .await?
+            .await?;
+
+            cancel_task!(backlog_task);
+            cancel_task!(rate_task);
+            cancel_task!(clock_skew_task);
+            if let Some(enrollment_task) = enrollment_task {
+                cancel_task!(enrollment_task);
+            }
+            result
         })
         .in_current_span()
     }
@@ -399,6 +1688,7 @@ impl NomadAgent for Processor {
             // tree sync
             info!("Starting ProverSync");
             let db = NomadDB::new(self.home().name(), self.db());
+            db.cache_key_space(CURRENT_NONCE);
             let sync = ProverSync::from_disk(db.clone());
             let prover_sync_task = sync.spawn();
 
@@ -434,9 +1724,34 @@ impl NomadAgent for Processor {
                 tasks.push(pusher.spawn())
             }
 
+            if self.delays.watches_file() {
+                info!("Starting extra delay reload task");
+                tasks.push(self.delays.clone().spawn(self.interval));
+            }
+
+            if let Some(top_up_task) = self.top_up_task() {
+                tasks.push(top_up_task);
+            }
+            tasks.push(self.signer_claims_task());
+
+            tasks.push(
+                RecipientGasMonitor {
+                    db: db.clone(),
+                    interval: self.interval,
+                    agent: AGENT_NAME.to_owned(),
+                    gas_used: self.recipient_gas.clone(),
+                }
+                .spawn(),
+            );
+
+            if let Some(admin_task) = self.run_admin_server() {
+                tasks.push(admin_task);
+            }
+
             // find the first task to shut down. Then cancel all others
             debug!(tasks = tasks.len(), "Selecting across Processor tasks");
             let (res, _, remaining) = select_all(tasks).await;
+            self.cancellation().cancel();
             for task in remaining.into_iter() {
                 cancel_task!(task);
             }
@@ -446,3 +1761,1523 @@ impl NomadAgent for Processor {
         .instrument(info_span!("Processor::run_all"))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::core::types::H160;
+    use nomad_base::{
+        chains::PageSettings, CommonIndexers, ContractSync, ContractSyncMetrics, HomeIndexers,
+        IndexSettings,
+    };
+    use nomad_core::{
+        accumulator::NomadTree, db::DB, Encode, NomadMessage, RawCommittedMessage, TxOutcome,
+    };
+    use nomad_test::mocks::{MockHomeContract, MockIndexer, MockReplicaContract};
+    use nomad_test::test_utils;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn replica_processor(
+        db: DB,
+        home_db: &NomadDB,
+        mock_home: MockHomeContract,
+        mock_replica: MockReplicaContract,
+        ordering: ProcessingOrder,
+    ) -> Replica {
+        let metrics = Arc::new(
+            CoreMetrics::new(
+                "processor_test",
+                "home_1",
+                None,
+                Arc::new(prometheus::Registry::new()),
+            )
+            .expect("could not make metrics"),
+        );
+        let sync_metrics = ContractSyncMetrics::new(metrics.clone());
+        let home_indexer: Arc<HomeIndexers> = Arc::new(MockIndexer::new().into());
+        let replica_indexer: Arc<CommonIndexers> = Arc::new(MockIndexer::new().into());
+        let replica_db = NomadDB::new("replica_1", db);
+
+        let home_sync = ContractSync::new(
+            AGENT_NAME.to_owned(),
+            "home_1".to_owned(),
+            "".to_owned(),
+            home_db.clone(),
+            home_indexer,
+            IndexSettings::default(),
+            PageSettings::default(),
+            Default::default(),
+            sync_metrics.clone(),
+        );
+        let replica_sync = ContractSync::new(
+            AGENT_NAME.to_owned(),
+            "home_1".to_owned(),
+            "replica_1".to_owned(),
+            replica_db.clone(),
+            replica_indexer,
+            IndexSettings::default(),
+            PageSettings::default(),
+            Default::default(),
+            sync_metrics,
+        );
+
+        Replica {
+            interval: 1,
+            retry: Retry::fixed(Duration::from_secs(1)),
+            replica: Arc::new(CachingReplica::new(
+                mock_replica.into(),
+                replica_sync,
+                replica_db,
+            )),
+            home: Arc::new(CachingHome::new(
+                mock_home.into(),
+                home_sync,
+                home_db.clone(),
+            )),
+            db: home_db.clone(),
+            allowed: None,
+            denied: None,
+            ordering,
+            released: Default::default(),
+            delays: Arc::new(CorridorDelays::new(Default::default(), None)),
+            clock_skew: ClockSkew::new(0),
+            confirm_horizon: DEFAULT_CONFIRM_HORIZON_SECONDS,
+            corridors: Arc::new(
+                CorridorSwitches::new(
+                    "home_1",
+                    AGENT_NAME,
+                    vec!["replica_1".to_owned()],
+                    &Default::default(),
+                    metrics.clone(),
+                )
+                .unwrap(),
+            ),
+            governance_router: None,
+            attested_roots_only: false,
+            provenances: Default::default(),
+            provenance_recheck: Duration::ZERO,
+            reconciliation_lookback: None,
+            full_reconcile: false,
+            max_message_age: None,
+            resume_rewind: 0,
+            stale: Arc::new(StaleHolds::new(home_db.clone(), metrics.clone()).unwrap()),
+            next_message_nonce: prometheus::IntGauge::new("nonce", "nonce").unwrap(),
+            processed_failed: prometheus::IntCounter::new("failed", "failed").unwrap(),
+            never_processable: prometheus::IntGauge::new("never", "never").unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_processes_empty_body_messages() {
+        test_utils::run_test_db(|db| async move {
+            let message = NomadMessage {
+                origin: 1000,
+                sender: H160::repeat_byte(0x11).into(),
+                nonce: 1,
+                destination: 2000,
+                recipient: H160::repeat_byte(0x22).into(),
+                body: vec![],
+            };
+            // Leaf of the same message computed by `Message.sol`
+            let leaf: H256 = "0x12ad2f0b362a4c917093c565e4d2e28219a9d654ce97ccffc557da38f98d17b5"
+                .parse()
+                .unwrap();
+            assert_eq!(message.to_vec().len(), 76);
+            assert_eq!(
+                NomadMessage::from_raw(&message.to_vec()).unwrap().body,
+                vec![]
+            );
+            assert_eq!(message.to_leaf(), leaf);
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            let proof = NomadTree::from_leaves(&[leaf]).prove(0).unwrap();
+            home_db
+                .store_messages(&[RawCommittedMessage {
+                    leaf_index: 0,
+                    committed_root: NomadTree::initial_root(),
+                    message: message.to_vec(),
+                    timestamp: None,
+                }])
+                .unwrap();
+            home_db.store_proof(0, &proof).unwrap();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            let mut mock_replica = MockReplicaContract::new();
+            {
+                let root = proof.root();
+                mock_replica
+                    .expect__name()
+                    .return_const("replica_1".to_owned());
+                mock_replica
+                    .expect__acceptable_root()
+                    .withf(move |r: &H256| *r == root)
+                    .returning(|_| Ok(true));
+                mock_replica.expect__confirm_at().returning(|_| Ok(1));
+                mock_replica
+                    .expect__root_provenance()
+                    .returning(|_| Ok(Some(RootProvenance::Update)));
+                mock_replica
+                    .expect__message_status()
+                    .withf(move |l: &H256| *l == leaf)
+                    .returning(|_| Ok(MessageStatus::None));
+                // The empty body is submitted as is
+                mock_replica
+                    .expect__prove_and_process()
+                    .withf(move |m: &NomadMessage, p: &NomadProof| {
+                        m.body.is_empty() && m.to_leaf() == leaf && p.leaf == leaf
+                    })
+                    .times(1)
+                    .returning(|_, _| {
+                        Ok(TxOutcome {
+                            txid: H256::zero(),
+                            gas_used: None,
+                            block_number: None,
+                        })
+                    });
+                mock_replica
+                    .expect__process_success()
+                    .returning(|_, _| Ok(Some(true)));
+            }
+
+            let mut replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+            replica.attested_roots_only = true;
+
+            let flow = replica.try_msg_by_domain_and_nonce(2000, 1).await.unwrap();
+            assert!(matches!(flow, Flow::Advance));
+
+            let committed =
+                CommittedMessage::try_from(home_db.message_by_nonce(2000, 1).unwrap().unwrap())
+                    .unwrap();
+            assert!(home_db.previously_attempted(&committed).unwrap());
+            assert!(!home_db.processed_failed(leaf).unwrap());
+            assert_eq!(
+                home_db.processing_root(leaf).unwrap(),
+                Some(ProcessingRoot {
+                    root: proof.root(),
+                    provenance: RootProvenance::Update,
+                })
+            );
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_holds_stale_messages_until_released() {
+        test_utils::run_test_db(|db| async move {
+            let message = NomadMessage {
+                origin: 1000,
+                sender: H160::repeat_byte(0x11).into(),
+                nonce: 1,
+                destination: 2000,
+                recipient: H160::repeat_byte(0x22).into(),
+                body: vec![1, 2, 3],
+            };
+            let leaf = message.to_leaf();
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            let proof = NomadTree::from_leaves(&[leaf]).prove(0).unwrap();
+            home_db
+                .store_messages(&[RawCommittedMessage {
+                    leaf_index: 0,
+                    committed_root: NomadTree::initial_root(),
+                    message: message.to_vec(),
+                    // dispatched in 1970
+                    timestamp: Some(1),
+                }])
+                .unwrap();
+            home_db.store_proof(0, &proof).unwrap();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            // Nothing is submitted until the message is released
+            let released = Arc::new(AtomicBool::new(false));
+            let mut mock_replica = MockReplicaContract::new();
+            {
+                let released = released.clone();
+                mock_replica
+                    .expect__name()
+                    .return_const("replica_1".to_owned());
+                mock_replica
+                    .expect__acceptable_root()
+                    .returning(|_| Ok(true));
+                mock_replica.expect__confirm_at().returning(|_| Ok(1));
+                mock_replica
+                    .expect__root_provenance()
+                    .returning(|_| Ok(Some(RootProvenance::Update)));
+                mock_replica
+                    .expect__message_status()
+                    .returning(|_| Ok(MessageStatus::None));
+                mock_replica
+                    .expect__prove_and_process()
+                    .withf(move |m: &NomadMessage, _: &NomadProof| {
+                        assert!(released.load(Ordering::SeqCst));
+                        m.to_leaf() == leaf
+                    })
+                    .times(1)
+                    .returning(|_, _| {
+                        Ok(TxOutcome {
+                            txid: H256::zero(),
+                            gas_used: None,
+                            block_number: None,
+                        })
+                    });
+                mock_replica
+                    .expect__process_success()
+                    .returning(|_, _| Ok(Some(true)));
+            }
+
+            let mut replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+            replica.max_message_age = Some(24 * 60 * 60);
+
+            let flow = replica.try_msg_by_domain_and_nonce(2000, 1).await.unwrap();
+            assert!(matches!(flow, Flow::Advance));
+            let hold = replica.stale.get(leaf).unwrap();
+            assert_eq!(hold.dispatched_at, Some(1));
+            assert!(!hold.released);
+
+            // an unreleased hold is left alone
+            replica.drain_stale_holds().await.unwrap();
+            assert!(replica.stale.get(leaf).is_some());
+
+            released.store(true, Ordering::SeqCst);
+            replica.stale.release(leaf).unwrap();
+            replica.drain_stale_holds().await.unwrap();
+
+            let committed =
+                CommittedMessage::try_from(home_db.message_by_nonce(2000, 1).unwrap().unwrap())
+                    .unwrap();
+            assert!(home_db.previously_attempted(&committed).unwrap());
+            assert_eq!(replica.stale.get(leaf), None);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_holds_messages_of_unknown_age() {
+        test_utils::run_test_db(|db| async move {
+            let message = NomadMessage {
+                origin: 1000,
+                sender: H160::repeat_byte(0x11).into(),
+                nonce: 1,
+                destination: 2000,
+                recipient: H160::repeat_byte(0x22).into(),
+                body: vec![1, 2, 3],
+            };
+            let leaf = message.to_leaf();
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            home_db
+                .store_messages(&[RawCommittedMessage {
+                    leaf_index: 0,
+                    committed_root: NomadTree::initial_root(),
+                    message: message.to_vec(),
+                    timestamp: None,
+                }])
+                .unwrap();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+            let mut mock_replica = MockReplicaContract::new();
+            mock_replica
+                .expect__name()
+                .return_const("replica_1".to_owned());
+            mock_replica.expect__prove_and_process().never();
+
+            let mut replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+
+            // without a maximum age, age does not matter
+            assert!(!replica
+                .held_for_age(
+                    &CommittedMessage::try_from(
+                        home_db.message_by_nonce(2000, 1).unwrap().unwrap()
+                    )
+                    .unwrap()
+                )
+                .unwrap());
+
+            replica.max_message_age = Some(24 * 60 * 60);
+            let flow = replica.try_msg_by_domain_and_nonce(2000, 1).await.unwrap();
+            assert!(matches!(flow, Flow::Advance));
+            let hold = replica.stale.get(leaf).unwrap();
+            assert_eq!(hold.dispatched_at, None);
+            assert!(!hold.released);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_requires_attested_roots_on_strict_corridors() {
+        test_utils::run_test_db(|db| async move {
+            let message = NomadMessage {
+                origin: 1000,
+                sender: H160::repeat_byte(0x11).into(),
+                nonce: 1,
+                destination: 2000,
+                recipient: H160::repeat_byte(0x22).into(),
+                body: vec![1, 2, 3],
+            };
+            let leaf = message.to_leaf();
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            let proof = NomadTree::from_leaves(&[leaf]).prove(0).unwrap();
+            home_db
+                .store_messages(&[RawCommittedMessage {
+                    leaf_index: 0,
+                    committed_root: NomadTree::initial_root(),
+                    message: message.to_vec(),
+                    timestamp: None,
+                }])
+                .unwrap();
+            home_db.store_proof(0, &proof).unwrap();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            // Governance confirmed the root with `setConfirmation`, then an
+            // update attested to it
+            let attested = Arc::new(AtomicBool::new(false));
+            let mut mock_replica = MockReplicaContract::new();
+            {
+                let attested = attested.clone();
+                mock_replica
+                    .expect__name()
+                    .return_const("replica_1".to_owned());
+                mock_replica
+                    .expect__acceptable_root()
+                    .returning(|_| Ok(true));
+                mock_replica.expect__confirm_at().returning(|_| Ok(1));
+                mock_replica.expect__root_provenance().returning(move |_| {
+                    if attested.load(Ordering::SeqCst) {
+                        Ok(Some(RootProvenance::Update))
+                    } else {
+                        Ok(Some(RootProvenance::Governance))
+                    }
+                });
+                mock_replica
+                    .expect__message_status()
+                    .returning(|_| Ok(MessageStatus::None));
+                mock_replica
+                    .expect__prove_and_process()
+                    .times(1)
+                    .returning(|_, _| {
+                        Ok(TxOutcome {
+                            txid: H256::zero(),
+                            gas_used: None,
+                            block_number: None,
+                        })
+                    });
+                mock_replica
+                    .expect__process_success()
+                    .returning(|_, _| Ok(Some(true)));
+            }
+
+            let mut replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+            replica.attested_roots_only = true;
+            let committed =
+                CommittedMessage::try_from(home_db.message_by_nonce(2000, 1).unwrap().unwrap())
+                    .unwrap();
+
+            // The governance-injected root is not processed under
+            let flow = replica.try_msg_by_domain_and_nonce(2000, 1).await.unwrap();
+            assert!(matches!(flow, Flow::Repeat));
+            assert!(!home_db.previously_attempted(&committed).unwrap());
+            assert_eq!(home_db.processing_root(leaf).unwrap(), None);
+
+            // Once attested, the message is processed and the provenance
+            // recorded with it
+            attested.store(true, Ordering::SeqCst);
+            let flow = replica.try_msg_by_domain_and_nonce(2000, 1).await.unwrap();
+            assert!(matches!(flow, Flow::Advance));
+            assert!(home_db.previously_attempted(&committed).unwrap());
+            assert_eq!(
+                home_db.processing_root(leaf).unwrap(),
+                Some(ProcessingRoot {
+                    root: proof.root(),
+                    provenance: RootProvenance::Update,
+                })
+            );
+            let provenances: Vec<_> = home_db
+                .audit_records(nomad_base::AuditId(0))
+                .filter_map(|record| match record.unwrap().event {
+                    AuditEvent::ProcessMessage { provenance, .. } => Some(provenance),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(provenances, vec![Some(RootProvenance::Update)]);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_does_not_wait_on_roots_confirmed_beyond_the_horizon() {
+        test_utils::run_test_db(|db| async move {
+            let message = NomadMessage {
+                origin: 1000,
+                sender: H160::repeat_byte(0x11).into(),
+                nonce: 1,
+                destination: 2000,
+                recipient: H160::repeat_byte(0x22).into(),
+                body: vec![1, 2, 3],
+            };
+            let leaf = message.to_leaf();
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            let proof = NomadTree::from_leaves(&[leaf]).prove(0).unwrap();
+            home_db
+                .store_messages(&[RawCommittedMessage {
+                    leaf_index: 0,
+                    committed_root: NomadTree::initial_root(),
+                    message: message.to_vec(),
+                    timestamp: None,
+                }])
+                .unwrap();
+            home_db.store_proof(0, &proof).unwrap();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            // `optimisticSeconds` near U256::MAX: the root is confirmed at the
+            // saturated `confirmAt`
+            let mut mock_replica = MockReplicaContract::new();
+            mock_replica
+                .expect__name()
+                .return_const("replica_1".to_owned());
+            mock_replica
+                .expect__acceptable_root()
+                .returning(|_| Ok(false));
+            mock_replica
+                .expect__message_status()
+                .returning(|_| Ok(MessageStatus::None));
+            mock_replica
+                .expect__confirm_at()
+                .times(1)
+                .returning(|_| Ok(u64::MAX));
+            mock_replica.expect__prove_and_process().never();
+
+            let replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+
+            let flow = replica.try_msg_by_domain_and_nonce(2000, 1).await.unwrap();
+            assert!(matches!(flow, Flow::Repeat));
+            assert_eq!(replica.never_processable.get(), 1);
+        })
+        .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_interrupts_waiting_for_confirmation() {
+        test_utils::run_test_db(|db| async move {
+            let message = NomadMessage {
+                origin: 1000,
+                sender: H160::repeat_byte(0x11).into(),
+                nonce: 1,
+                destination: 2000,
+                recipient: H160::repeat_byte(0x22).into(),
+                body: vec![1, 2, 3],
+            };
+            let leaf = message.to_leaf();
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            let proof = NomadTree::from_leaves(&[leaf]).prove(0).unwrap();
+            home_db
+                .store_messages(&[RawCommittedMessage {
+                    leaf_index: 0,
+                    committed_root: NomadTree::initial_root(),
+                    message: message.to_vec(),
+                    timestamp: None,
+                }])
+                .unwrap();
+            home_db.store_proof(0, &proof).unwrap();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            // The root confirms in an hour
+            let confirm_at = ClockSkew::new(0).chain_now_from_host().unwrap() + 3600;
+            let mut mock_replica = MockReplicaContract::new();
+            mock_replica
+                .expect__name()
+                .return_const("replica_1".to_owned());
+            mock_replica
+                .expect__acceptable_root()
+                .returning(|_| Ok(false));
+            mock_replica
+                .expect__message_status()
+                .returning(|_| Ok(MessageStatus::None));
+            mock_replica
+                .expect__confirm_at()
+                .returning(move |_| Ok(confirm_at));
+            mock_replica.expect__prove_and_process().never();
+
+            let mut replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+            let cancellation = nomad_base::CancellationToken::default();
+            replica.retry =
+                Retry::fixed(Duration::from_secs(600)).with_cancellation(cancellation.clone());
+
+            let start = tokio::time::Instant::now();
+            let (flow, _) = tokio::join!(replica.try_msg_by_domain_and_nonce(2000, 1), async {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                cancellation.cancel();
+            });
+
+            // The wait ends at shutdown rather than at the next poll
+            assert!(matches!(flow.unwrap(), Flow::Repeat));
+            assert_eq!(start.elapsed(), Duration::from_secs(1));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_recovers_from_cancelled_processing() {
+        test_utils::run_test_db(|db| async move {
+            let message = NomadMessage {
+                origin: 1000,
+                sender: H160::repeat_byte(0x11).into(),
+                nonce: 1,
+                destination: 2000,
+                recipient: H160::repeat_byte(0x22).into(),
+                body: vec![1, 2, 3],
+            };
+            let leaf = message.to_leaf();
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            let proof = NomadTree::from_leaves(&[leaf]).prove(0).unwrap();
+            home_db
+                .store_messages(&[RawCommittedMessage {
+                    leaf_index: 0,
+                    committed_root: NomadTree::initial_root(),
+                    message: message.to_vec(),
+                    timestamp: None,
+                }])
+                .unwrap();
+            home_db.store_proof(0, &proof).unwrap();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            // The message is processed on chain once submitted, and its
+            // handler reverts
+            let submitted = Arc::new(AtomicBool::new(false));
+            let mut mock_replica = MockReplicaContract::new();
+            {
+                let submitted_status = submitted.clone();
+                let submitted_tx = submitted.clone();
+                mock_replica
+                    .expect__name()
+                    .return_const("replica_1".to_owned());
+                mock_replica
+                    .expect__acceptable_root()
+                    .returning(|_| Ok(true));
+                mock_replica.expect__confirm_at().returning(|_| Ok(1));
+                mock_replica
+                    .expect__root_provenance()
+                    .returning(|_| Ok(Some(RootProvenance::Governance)));
+                mock_replica.expect__message_status().returning(move |_| {
+                    if submitted_status.load(Ordering::SeqCst) {
+                        Ok(MessageStatus::Processed)
+                    } else {
+                        Ok(MessageStatus::None)
+                    }
+                });
+                mock_replica
+                    .expect__prove_and_process()
+                    .times(1)
+                    .returning(move |_, _| {
+                        submitted_tx.store(true, Ordering::SeqCst);
+                        Ok(TxOutcome {
+                            txid: H256::zero(),
+                            gas_used: None,
+                            block_number: None,
+                        })
+                    });
+                mock_replica
+                    .expect__process_success()
+                    .returning(|_, _| Ok(Some(false)));
+            }
+
+            let replica = &replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::PerSenderNonce,
+            );
+            let committed =
+                CommittedMessage::try_from(home_db.message_by_nonce(2000, 1).unwrap().unwrap())
+                    .unwrap();
+
+            // Retry after every cancellation, as a restarted processor would
+            test_utils::cancel_at_each_await(
+                || replica.try_msg_by_domain_and_nonce(2000, 1),
+                |polls| {
+                    assert_eq!(
+                        home_db.previously_attempted(&committed).unwrap(),
+                        home_db.processed_failed(leaf).unwrap(),
+                        "cancelled after {}",
+                        polls
+                    );
+                },
+            );
+
+            // Submitted once, with its outcome recorded and its sender not
+            // held
+            assert!(submitted.load(Ordering::SeqCst));
+            assert!(home_db.previously_attempted(&committed).unwrap());
+            assert!(home_db.processed_failed(leaf).unwrap());
+            assert_eq!(SenderQueues::load(&home_db, 2000).unwrap().len(), 0);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_submits_messages_by_their_status() {
+        test_utils::run_test_db(|db| async move {
+            // Unknown, proven and processed on the replica
+            let messages: Vec<_> = (1..=3)
+                .map(|nonce| NomadMessage {
+                    origin: 1000,
+                    sender: H160::repeat_byte(0x11).into(),
+                    nonce,
+                    destination: 2000,
+                    recipient: H160::repeat_byte(0x22).into(),
+                    body: vec![nonce as u8],
+                })
+                .collect();
+            let leaves: Vec<H256> = messages.iter().map(NomadMessage::to_leaf).collect();
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            let tree = NomadTree::from_leaves(&leaves);
+            for (leaf_index, message) in messages.iter().enumerate() {
+                home_db
+                    .store_messages(&[RawCommittedMessage {
+                        leaf_index: leaf_index as u32,
+                        committed_root: NomadTree::initial_root(),
+                        message: message.to_vec(),
+                        timestamp: None,
+                    }])
+                    .unwrap();
+                home_db
+                    .store_proof(leaf_index as u32, &tree.prove(leaf_index).unwrap())
+                    .unwrap();
+            }
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            let mut mock_replica = MockReplicaContract::new();
+            {
+                let root = tree.prove(0).unwrap().root();
+                let (unknown, proven) = (leaves[0], leaves[1]);
+                mock_replica
+                    .expect__name()
+                    .return_const("replica_1".to_owned());
+                mock_replica
+                    .expect__acceptable_root()
+                    .returning(|_| Ok(true));
+                mock_replica
+                    .expect__root_provenance()
+                    .returning(|_| Ok(Some(RootProvenance::Update)));
+                mock_replica
+                    .expect__message_status()
+                    .returning(move |leaf| {
+                        Ok(if leaf == unknown {
+                            MessageStatus::None
+                        } else if leaf == proven {
+                            MessageStatus::Proven(root)
+                        } else {
+                            MessageStatus::Processed
+                        })
+                    });
+                // Proven with the unknown message only, and no gas spent on
+                // the processed one
+                mock_replica
+                    .expect__prove_and_process()
+                    .withf(move |m: &NomadMessage, _: &NomadProof| m.to_leaf() == unknown)
+                    .times(1)
+                    .returning(|_, _| {
+                        Ok(TxOutcome {
+                            txid: H256::repeat_byte(1),
+                            gas_used: None,
+                            block_number: None,
+                        })
+                    });
+                mock_replica
+                    .expect__process()
+                    .withf(move |m: &NomadMessage| m.to_leaf() == proven)
+                    .times(1)
+                    .returning(|_| {
+                        Ok(TxOutcome {
+                            txid: H256::repeat_byte(2),
+                            gas_used: None,
+                            block_number: None,
+                        })
+                    });
+                mock_replica
+                    .expect__process_success()
+                    .returning(|_, _| Ok(Some(true)));
+            }
+
+            let replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+            for nonce in 1..=3 {
+                let flow = replica
+                    .try_msg_by_domain_and_nonce(2000, nonce)
+                    .await
+                    .unwrap();
+                assert!(matches!(flow, Flow::Advance));
+
+                let committed = CommittedMessage::try_from(
+                    home_db.message_by_nonce(2000, nonce).unwrap().unwrap(),
+                )
+                .unwrap();
+                assert!(home_db.previously_attempted(&committed).unwrap());
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_holds_a_sender_behind_its_reverted_head_until_released() {
+        test_utils::run_test_db(|db| async move {
+            // Nonces 0..4 alternate between senders A and B
+            let (a, b) = (H160::repeat_byte(0xa), H160::repeat_byte(0xb));
+            let messages: Vec<_> = (0..4)
+                .map(|nonce| NomadMessage {
+                    origin: 1000,
+                    sender: if nonce % 2 == 0 { a } else { b }.into(),
+                    nonce,
+                    destination: 2000,
+                    recipient: H160::repeat_byte(0x22).into(),
+                    body: vec![nonce as u8],
+                })
+                .collect();
+            let leaves: Vec<H256> = messages.iter().map(NomadMessage::to_leaf).collect();
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            let tree = NomadTree::from_leaves(&leaves);
+            for (leaf_index, message) in messages.iter().enumerate() {
+                home_db
+                    .store_messages(&[RawCommittedMessage {
+                        leaf_index: leaf_index as u32,
+                        committed_root: NomadTree::initial_root(),
+                        message: message.to_vec(),
+                        timestamp: None,
+                    }])
+                    .unwrap();
+                home_db
+                    .store_proof(leaf_index as u32, &tree.prove(leaf_index).unwrap())
+                    .unwrap();
+            }
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            // A's head is proven and `process` reverts, so it never reaches
+            // `Processed`. Every other message is processed once submitted.
+            let head = leaves[0];
+            let processed = Arc::new(Mutex::new(Vec::new()));
+            let mut mock_replica = MockReplicaContract::new();
+            {
+                let root = tree.prove(0).unwrap().root();
+                let status_processed = processed.clone();
+                let submit_processed = processed.clone();
+                mock_replica
+                    .expect__name()
+                    .return_const("replica_1".to_owned());
+                mock_replica
+                    .expect__acceptable_root()
+                    .returning(|_| Ok(true));
+                mock_replica.expect__confirm_at().returning(|_| Ok(1));
+                mock_replica
+                    .expect__root_provenance()
+                    .returning(|_| Ok(Some(RootProvenance::Update)));
+                mock_replica
+                    .expect__message_status()
+                    .returning(move |leaf| {
+                        Ok(if leaf == head {
+                            MessageStatus::Proven(root)
+                        } else if status_processed.lock().unwrap().contains(&leaf) {
+                            MessageStatus::Processed
+                        } else {
+                            MessageStatus::None
+                        })
+                    });
+                mock_replica
+                    .expect__process()
+                    .withf(move |m: &NomadMessage| m.to_leaf() == head)
+                    .times(1)
+                    .returning(|_| Err(nomad_test::MockError::TxNotExecuted(H256::repeat_byte(1))));
+                mock_replica
+                    .expect__prove_and_process()
+                    .times(3)
+                    .returning(move |m, _| {
+                        submit_processed.lock().unwrap().push(m.to_leaf());
+                        Ok(TxOutcome {
+                            txid: H256::repeat_byte(2),
+                            gas_used: None,
+                            block_number: None,
+                        })
+                    });
+                mock_replica
+                    .expect__process_success()
+                    .returning(|_, _| Ok(Some(true)));
+            }
+
+            let mut replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::PerSenderNonce,
+            );
+            for nonce in 0..4 {
+                let flow = replica
+                    .try_msg_by_domain_and_nonce(2000, nonce)
+                    .await
+                    .unwrap();
+                assert!(matches!(flow, Flow::Advance));
+            }
+
+            // A's later message is held behind its head. B's are processed.
+            assert_eq!(*processed.lock().unwrap(), vec![leaves[1], leaves[3]]);
+            let queues = SenderQueues::load(&home_db, 2000).unwrap();
+            assert_eq!(queues.senders(), vec![H256::from(a)]);
+            assert_eq!(queues.len(), 2);
+
+            // The reverted head is not retried by draining
+            replica.drain_sender_queues(2000).await.unwrap();
+            assert_eq!(processed.lock().unwrap().len(), 2);
+            assert_eq!(SenderQueues::load(&home_db, 2000).unwrap(), queues);
+
+            // Releasing the head processes A's held message
+            replica.released = Arc::new(HashSet::from([head]));
+            replica.drain_sender_queues(2000).await.unwrap();
+            assert_eq!(
+                *processed.lock().unwrap(),
+                vec![leaves[1], leaves[3], leaves[2]]
+            );
+            assert_eq!(SenderQueues::load(&home_db, 2000).unwrap().len(), 0);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_falls_back_to_process_for_messages_proven_meanwhile() {
+        test_utils::run_test_db(|db| async move {
+            let message = NomadMessage {
+                origin: 1000,
+                sender: H160::repeat_byte(0x11).into(),
+                nonce: 1,
+                destination: 2000,
+                recipient: H160::repeat_byte(0x22).into(),
+                body: vec![1, 2, 3],
+            };
+            let leaf = message.to_leaf();
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            let proof = NomadTree::from_leaves(&[leaf]).prove(0).unwrap();
+            home_db
+                .store_messages(&[RawCommittedMessage {
+                    leaf_index: 0,
+                    committed_root: NomadTree::initial_root(),
+                    message: message.to_vec(),
+                    timestamp: None,
+                }])
+                .unwrap();
+            home_db.store_proof(0, &proof).unwrap();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            // Another processor proves the message first, so
+            // `proveAndProcess` reverts on the already proven leaf
+            let reverted = Arc::new(AtomicBool::new(false));
+            let mut mock_replica = MockReplicaContract::new();
+            {
+                let root = proof.root();
+                let reverted_status = reverted.clone();
+                let reverted_tx = reverted.clone();
+                mock_replica
+                    .expect__name()
+                    .return_const("replica_1".to_owned());
+                mock_replica
+                    .expect__acceptable_root()
+                    .returning(|_| Ok(true));
+                mock_replica.expect__confirm_at().returning(|_| Ok(1));
+                mock_replica
+                    .expect__root_provenance()
+                    .returning(|_| Ok(Some(RootProvenance::Update)));
+                mock_replica.expect__message_status().returning(move |_| {
+                    if reverted_status.load(Ordering::SeqCst) {
+                        Ok(MessageStatus::Proven(root))
+                    } else {
+                        Ok(MessageStatus::None)
+                    }
+                });
+                mock_replica
+                    .expect__prove_and_process()
+                    .times(1)
+                    .returning(move |_, _| {
+                        reverted_tx.store(true, Ordering::SeqCst);
+                        Err(nomad_test::MockError::TxNotExecuted(H256::repeat_byte(1)))
+                    });
+                mock_replica
+                    .expect__process()
+                    .withf(move |m: &NomadMessage| m.to_leaf() == leaf)
+                    .times(1)
+                    .returning(|_| {
+                        Ok(TxOutcome {
+                            txid: H256::repeat_byte(2),
+                            gas_used: None,
+                            block_number: None,
+                        })
+                    });
+                mock_replica
+                    .expect__process_success()
+                    .returning(|_, _| Ok(Some(true)));
+            }
+
+            let replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+
+            let flow = replica.try_msg_by_domain_and_nonce(2000, 1).await.unwrap();
+            assert!(matches!(flow, Flow::Advance));
+
+            let committed =
+                CommittedMessage::try_from(home_db.message_by_nonce(2000, 1).unwrap().unwrap())
+                    .unwrap();
+            assert!(home_db.previously_attempted(&committed).unwrap());
+            assert!(!home_db.processed_failed(leaf).unwrap());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_retries_messages_not_yet_provable_after_a_revert() {
+        test_utils::run_test_db(|db| async move {
+            let message = NomadMessage {
+                origin: 1000,
+                sender: H160::repeat_byte(0x11).into(),
+                nonce: 1,
+                destination: 2000,
+                recipient: H160::repeat_byte(0x22).into(),
+                body: vec![1, 2, 3],
+            };
+            let leaf = message.to_leaf();
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            let proof = NomadTree::from_leaves(&[leaf]).prove(0).unwrap();
+            home_db
+                .store_messages(&[RawCommittedMessage {
+                    leaf_index: 0,
+                    committed_root: NomadTree::initial_root(),
+                    message: message.to_vec(),
+                    timestamp: None,
+                }])
+                .unwrap();
+            home_db.store_proof(0, &proof).unwrap();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            // `proveAndProcess` reverts and the message stays unproven
+            let mut mock_replica = MockReplicaContract::new();
+            {
+                mock_replica
+                    .expect__name()
+                    .return_const("replica_1".to_owned());
+                mock_replica
+                    .expect__acceptable_root()
+                    .returning(|_| Ok(true));
+                mock_replica.expect__confirm_at().returning(|_| Ok(1));
+                mock_replica
+                    .expect__root_provenance()
+                    .returning(|_| Ok(Some(RootProvenance::Update)));
+                mock_replica
+                    .expect__message_status()
+                    .returning(|_| Ok(MessageStatus::None));
+                mock_replica
+                    .expect__prove_and_process()
+                    .times(2)
+                    .returning(|_, _| {
+                        Err(nomad_test::MockError::TxNotExecuted(H256::repeat_byte(1)))
+                    });
+                mock_replica.expect__process().never();
+            }
+
+            let replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+            let committed =
+                CommittedMessage::try_from(home_db.message_by_nonce(2000, 1).unwrap().unwrap())
+                    .unwrap();
+
+            // Not recorded attempted, so the next pass submits it again
+            for _ in 0..2 {
+                replica.try_msg_by_domain_and_nonce(2000, 1).await.unwrap();
+                assert!(!home_db.previously_attempted(&committed).unwrap());
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_skips_messages_over_the_gas_limit_cap() {
+        test_utils::run_test_db(|db| async move {
+            let message = NomadMessage {
+                origin: 1000,
+                sender: H160::repeat_byte(0x11).into(),
+                nonce: 1,
+                destination: 2000,
+                recipient: H160::repeat_byte(0x22).into(),
+                body: vec![1, 2, 3],
+            };
+            let leaf = message.to_leaf();
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            let proof = NomadTree::from_leaves(&[leaf]).prove(0).unwrap();
+            home_db
+                .store_messages(&[RawCommittedMessage {
+                    leaf_index: 0,
+                    committed_root: NomadTree::initial_root(),
+                    message: message.to_vec(),
+                    timestamp: None,
+                }])
+                .unwrap();
+            home_db.store_proof(0, &proof).unwrap();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            // The adjusted estimate of `proveAndProcess` exceeds the cap
+            let mut mock_replica = MockReplicaContract::new();
+            {
+                mock_replica
+                    .expect__name()
+                    .return_const("replica_1".to_owned());
+                mock_replica
+                    .expect__acceptable_root()
+                    .returning(|_| Ok(true));
+                mock_replica.expect__confirm_at().returning(|_| Ok(1));
+                mock_replica
+                    .expect__root_provenance()
+                    .returning(|_| Ok(Some(RootProvenance::Update)));
+                mock_replica
+                    .expect__message_status()
+                    .returning(|_| Ok(MessageStatus::None));
+                mock_replica
+                    .expect__prove_and_process()
+                    .times(1)
+                    .returning(|_, _| {
+                        Err(nomad_test::MockError::GasLimitExceeded {
+                            limit: 3_000_000,
+                            cap: 2_000_000,
+                        })
+                    });
+                mock_replica.expect__process().never();
+            }
+
+            let replica = replica_processor(
+                db.clone(),
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+
+            // Skipped rather than halting the processor, and not resubmitted
+            // until restart
+            let committed =
+                CommittedMessage::try_from(home_db.message_by_nonce(2000, 1).unwrap().unwrap())
+                    .unwrap();
+            for _ in 0..2 {
+                let flow = replica.try_msg_by_domain_and_nonce(2000, 1).await.unwrap();
+                assert!(matches!(flow, Flow::Advance));
+            }
+            assert!(!home_db.previously_attempted(&committed).unwrap());
+            assert!(home_db.gas_capped(&committed).unwrap());
+            assert_eq!(home_db.gas_capped_leaf_indexes(2000).unwrap(), vec![0]);
+            drop(replica);
+
+            // The cap was raised before a restart, which retries the message
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+            let mut mock_replica = MockReplicaContract::new();
+            {
+                mock_replica
+                    .expect__name()
+                    .return_const("replica_1".to_owned());
+                mock_replica
+                    .expect__acceptable_root()
+                    .returning(|_| Ok(true));
+                mock_replica.expect__confirm_at().returning(|_| Ok(1));
+                mock_replica
+                    .expect__root_provenance()
+                    .returning(|_| Ok(Some(RootProvenance::Update)));
+                mock_replica
+                    .expect__message_status()
+                    .returning(|_| Ok(MessageStatus::None));
+                mock_replica
+                    .expect__prove_and_process()
+                    .times(1)
+                    .returning(|_, _| {
+                        Ok(TxOutcome {
+                            txid: H256::zero(),
+                            gas_used: None,
+                            block_number: None,
+                        })
+                    });
+                mock_replica
+                    .expect__process_success()
+                    .returning(|_, _| Ok(Some(true)));
+            }
+
+            let replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+            replica.retry_gas_capped(2000).await.unwrap();
+            assert!(home_db.previously_attempted(&committed).unwrap());
+            assert!(!home_db.gas_capped(&committed).unwrap());
+            assert!(home_db.gas_capped_leaf_indexes(2000).unwrap().is_empty());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_reconciles_messages_processed_while_down() {
+        test_utils::run_test_db(|db| async move {
+            let home_db = NomadDB::new("home_1", db.clone());
+            let messages: Vec<_> = (0..3)
+                .map(|nonce| NomadMessage {
+                    origin: 1000,
+                    sender: H160::repeat_byte(0x11).into(),
+                    nonce,
+                    destination: 2000,
+                    recipient: H160::repeat_byte(0x22).into(),
+                    body: vec![],
+                })
+                .collect();
+            home_db
+                .store_messages(
+                    &messages
+                        .iter()
+                        .enumerate()
+                        .map(|(leaf_index, message)| RawCommittedMessage {
+                            leaf_index: leaf_index as u32,
+                            committed_root: NomadTree::initial_root(),
+                            message: message.to_vec(),
+                            timestamp: None,
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .unwrap();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            // Every message was processed on the replica while down
+            let mut mock_replica = MockReplicaContract::new();
+            mock_replica
+                .expect__name()
+                .return_const("replica_1".to_owned());
+            mock_replica
+                .expect__message_status()
+                .returning(|_| Ok(MessageStatus::Processed));
+            mock_replica
+                .expect__process_success()
+                .returning(|_, _| Ok(Some(true)));
+
+            let mut replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+            replica.reconciliation_lookback = Some(ReconciliationLookback::Leaves(1));
+
+            let committed = |nonce: u32| {
+                CommittedMessage::try_from(home_db.message_by_nonce(2000, nonce).unwrap().unwrap())
+                    .unwrap()
+            };
+
+            // Only the lookback is reconciled at startup
+            replica.reconcile(2000).await.unwrap();
+            assert!(home_db.previously_attempted(&committed(2)).unwrap());
+            assert!(!home_db.previously_attempted(&committed(0)).unwrap());
+
+            // Older messages are reconciled on access
+            assert!(replica.attempted(&committed(0)).await.unwrap());
+            assert!(home_db.previously_attempted(&committed(0)).unwrap());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_processes_the_first_message_under_the_initialization_root() {
+        test_utils::run_test_db(|db| async move {
+            let message = NomadMessage {
+                origin: 1000,
+                sender: H160::repeat_byte(0x11).into(),
+                nonce: 0,
+                destination: 2000,
+                recipient: H160::repeat_byte(0x22).into(),
+                body: vec![1],
+            };
+            let leaf = message.to_leaf();
+
+            let home_db = NomadDB::new("home_1", db.clone());
+            let proof = NomadTree::from_leaves(&[leaf]).prove(0).unwrap();
+            home_db
+                .store_messages(&[RawCommittedMessage {
+                    leaf_index: 0,
+                    committed_root: NomadTree::initial_root(),
+                    message: message.to_vec(),
+                    timestamp: None,
+                }])
+                .unwrap();
+            home_db.store_proof(0, &proof).unwrap();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+
+            // The replica was deployed after the home's first update, and
+            // initialized with the root committing to the first message. No
+            // update on the replica attests to it.
+            let mut mock_replica = MockReplicaContract::new();
+            mock_replica
+                .expect__name()
+                .return_const("replica_1".to_owned());
+            mock_replica
+                .expect__acceptable_root()
+                .returning(|_| Ok(true));
+            mock_replica.expect__confirm_at().returning(|_| Ok(1));
+            // Only looked up on corridors restricted to attested roots
+            mock_replica.expect__root_provenance().never();
+            mock_replica
+                .expect__message_status()
+                .returning(|_| Ok(MessageStatus::None));
+            mock_replica
+                .expect__prove_and_process()
+                .times(1)
+                .returning(|_, _| {
+                    Ok(TxOutcome {
+                        txid: H256::zero(),
+                        gas_used: None,
+                        block_number: None,
+                    })
+                });
+            mock_replica
+                .expect__process_success()
+                .returning(|_, _| Ok(Some(true)));
+
+            let replica = replica_processor(
+                db,
+                &home_db,
+                mock_home,
+                mock_replica,
+                ProcessingOrder::Global,
+            );
+
+            let flow = replica.try_msg_by_domain_and_nonce(2000, 0).await.unwrap();
+            assert!(matches!(flow, Flow::Advance));
+            assert_eq!(home_db.processing_root(leaf).unwrap(), None);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_resumes_near_the_latest_processed_message_after_a_restart() {
+        test_utils::run_test_db(|db| async move {
+            let home_db = NomadDB::new("home_1", db.clone());
+            let messages: Vec<_> = (0..10)
+                .map(|nonce| NomadMessage {
+                    origin: 1000,
+                    sender: H160::repeat_byte(0x11).into(),
+                    nonce,
+                    destination: 2000,
+                    recipient: H160::repeat_byte(0x22).into(),
+                    body: vec![nonce as u8],
+                })
+                .collect();
+            let leaves: Vec<H256> = messages.iter().map(NomadMessage::to_leaf).collect();
+            let tree = NomadTree::from_leaves(&leaves);
+            home_db
+                .store_messages(
+                    &messages
+                        .iter()
+                        .enumerate()
+                        .map(|(leaf_index, message)| RawCommittedMessage {
+                            leaf_index: leaf_index as u32,
+                            committed_root: NomadTree::initial_root(),
+                            message: message.to_vec(),
+                            timestamp: None,
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .unwrap();
+            for leaf_index in 0..leaves.len() {
+                home_db
+                    .store_proof(leaf_index as u32, &tree.prove(leaf_index).unwrap())
+                    .unwrap();
+            }
+
+            // Messages are processed on the replica once submitted
+            let processed = Arc::new(std::sync::Mutex::new(HashSet::new()));
+            let replica_mock = |submissions: usize| {
+                let mut mock_replica = MockReplicaContract::new();
+                mock_replica
+                    .expect__name()
+                    .return_const("replica_1".to_owned());
+                mock_replica
+                    .expect__acceptable_root()
+                    .returning(|_| Ok(true));
+                mock_replica.expect__confirm_at().returning(|_| Ok(1));
+                mock_replica
+                    .expect__root_provenance()
+                    .returning(|_| Ok(Some(RootProvenance::Update)));
+                let status = processed.clone();
+                mock_replica
+                    .expect__message_status()
+                    .returning(move |leaf| {
+                        Ok(if status.lock().unwrap().contains(&leaf) {
+                            MessageStatus::Processed
+                        } else {
+                            MessageStatus::None
+                        })
+                    });
+                let submitted = processed.clone();
+                mock_replica
+                    .expect__prove_and_process()
+                    .times(submissions)
+                    .returning(move |message: &NomadMessage, _| {
+                        submitted.lock().unwrap().insert(message.to_leaf());
+                        Ok(TxOutcome {
+                            txid: H256::zero(),
+                            gas_used: None,
+                            block_number: None,
+                        })
+                    });
+                mock_replica
+                    .expect__process_success()
+                    .returning(|_, _| Ok(Some(true)));
+                mock_replica
+            };
+            let home_mock = || {
+                let mut mock_home = MockHomeContract::new();
+                mock_home.expect__name().return_const("home_1".to_owned());
+                mock_home
+            };
+
+            // The first run processes the first 7 messages, advancing as the
+            // main loop does, then stops
+            let first = replica_processor(
+                db.clone(),
+                &home_db,
+                home_mock(),
+                replica_mock(7),
+                ProcessingOrder::Global,
+            );
+            let mut nonce = first.resume_nonce(2000).unwrap();
+            assert_eq!(nonce, 0);
+            while nonce < 7 {
+                let flow = first
+                    .try_msg_by_domain_and_nonce(2000, nonce)
+                    .await
+                    .unwrap();
+                assert!(matches!(flow, Flow::Advance));
+                home_db
+                    .store_keyed_encodable(CURRENT_NONCE, &2000u32, &nonce)
+                    .unwrap();
+                nonce += 1;
+            }
+            assert_eq!(
+                home_db.latest_processed_leaf_for_destination(2000).unwrap(),
+                Some(6)
+            );
+            drop(first);
+
+            // The restarted processor resumes a rewind before the next
+            // message, and skips the processed messages it inspects again
+            let mut second = replica_processor(
+                db,
+                &home_db,
+                home_mock(),
+                replica_mock(3),
+                ProcessingOrder::Global,
+            );
+            second.resume_rewind = 2;
+            let mut nonce = second.resume_nonce(2000).unwrap();
+            assert_eq!(nonce, 5);
+            while nonce < 10 {
+                let flow = second
+                    .try_msg_by_domain_and_nonce(2000, nonce)
+                    .await
+                    .unwrap();
+                assert!(matches!(flow, Flow::Advance));
+                nonce += 1;
+            }
+            assert_eq!(
+                home_db.latest_processed_leaf_for_destination(2000).unwrap(),
+                Some(9)
+            );
+        })
+        .await
+    }
+}
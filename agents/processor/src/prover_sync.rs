@@ -4,6 +4,7 @@ use nomad_base::{ChainCommunicationError, NomadDB};
 use nomad_core::{
     accumulator::{Merkle, NomadTree, ProvingError},
     db::DbError,
+    CanonicalH256,
 };
 use std::{fmt::Display, time::Duration};
 use tokio::{
@@ -134,7 +135,7 @@ impl ProverSync {
         for i in 0..sync.prover.count() as u32 {
             match (
                 sync.db.leaf_by_leaf_index(i).expect("db error"),
-                sync.db.proof_by_leaf_index(i).expect("db error"),
+                sync.db.proof_or_quarantine(i).expect("db error"),
             ) {
                 (Some(_), None) => sync.store_proof(i).expect("db error"),
                 (None, _) => break,
@@ -169,7 +170,7 @@ impl ProverSync {
                 leaf = ?leaf,
                 "Ingesting leaf at index {}. Leaf: {}.",
                 tree_size,
-                leaf
+                CanonicalH256(leaf)
             );
             self.prover.ingest(leaf).expect("!tree_full");
         }
@@ -181,6 +182,10 @@ impl ProverSync {
     /// local merkle tree with all leaves between local root and
     /// new root. Use short interval for bootup syncing and longer
     /// interval for regular polling.
+    ///
+    /// CancellationSafe: proofs and the latest committed root are stored
+    /// after the tree has caught up, with no await in between. The in-memory
+    /// tree is rebuilt from the db on restart.
     pub fn spawn(mut self) -> Instrumented<JoinHandle<Result<()>>> {
         let span = info_span!("ProverSync", self = %self);
         tokio::spawn(async move {
@@ -217,7 +222,7 @@ impl ProverSync {
 
                     // Ensure there is a proof in the db for all leaves
                     for idx in pre_update_size..self.prover.count() {
-                        if self.db.proof_by_leaf_index(idx as u32)?.is_none() {
+                        if self.db.proof_or_quarantine(idx as u32)?.is_none() {
                             self.store_proof(idx as u32)?;
                         }
                     }
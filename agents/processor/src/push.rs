@@ -113,7 +113,7 @@ impl Pusher {
         tokio::spawn(async move {
             let mut index = 0;
             loop {
-                let proof = self.db.proof_by_leaf_index(index)?;
+                let proof = self.db.proof_or_quarantine(index)?;
                 match proof {
                     Some(proof) => {
                         let message = self
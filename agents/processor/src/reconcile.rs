@@ -0,0 +1,290 @@
+//! Bounds of the startup reconciliation of the processor's message records
+//! against the replicas' message statuses. Startups reconcile the messages
+//! added since the last reconciliation plus a lookback of recent history.
+//! Older history that was never reconciled is reconciled message by message
+//! when first accessed.
+
+use nomad_base::NomadDB;
+use nomad_core::{db::DbError, CommittedMessage};
+use nomad_xyz_configuration::agent::processor::ReconciliationLookback;
+
+/// Last leaf index reconciled at startup, by destination domain
+static RECONCILED_THROUGH: &str = "reconciled_through_";
+/// Leaf index below which messages were never reconciled at startup, by
+/// destination domain
+static RECONCILE_LAZILY_BELOW: &str = "reconcile_lazily_below_";
+/// Messages below the lazy bound reconciled on first access
+static LAZILY_RECONCILED: &str = "lazily_reconciled_";
+
+/// Leaves reconciled at startup, `start` through `end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ReconcileWindow {
+    /// First leaf index
+    pub(crate) start: u32,
+    /// Last leaf index
+    pub(crate) end: u32,
+}
+
+impl ReconcileWindow {
+    /// The window to reconcile messages to `destination` in: the leaves added
+    /// since the last reconciliation and those within `lookback` of the
+    /// latest leaf. All leaves if `full`, or if there is no lookback and
+    /// no reconciliation yet. `None` if there is nothing to reconcile.
+    pub(crate) fn new(
+        db: &NomadDB,
+        destination: u32,
+        lookback: Option<ReconciliationLookback>,
+        full: bool,
+        now: u64,
+    ) -> Result<Option<Self>, DbError> {
+        let end = match db.retrieve_latest_leaf_index()? {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+        let unreconciled: Option<u32> = db
+            .retrieve_keyed_decodable::<_, u32>(RECONCILED_THROUGH, &destination)?
+            .map(|through| through + 1);
+
+        let start = match (full, lookback, unreconciled) {
+            (true, _, _) => 0,
+            (false, None, unreconciled) => unreconciled.unwrap_or_default(),
+            (false, Some(lookback), unreconciled) => {
+                let recent = lookback_start(db, lookback, end, now)?;
+                unreconciled.map_or(recent, |unreconciled| unreconciled.min(recent))
+            }
+        };
+
+        Ok((start <= end).then(|| Self { start, end }))
+    }
+
+    /// Record the window as reconciled. A first window not starting at the
+    /// first leaf leaves the history below it to be reconciled lazily.
+    pub(crate) fn store(&self, db: &NomadDB, destination: u32) -> Result<(), DbError> {
+        let first = db
+            .retrieve_keyed_decodable::<_, u32>(RECONCILED_THROUGH, &destination)?
+            .is_none();
+        if self.start == 0 {
+            db.store_keyed_encodable(RECONCILE_LAZILY_BELOW, &destination, &0u32)?;
+        } else if first {
+            db.store_keyed_encodable(RECONCILE_LAZILY_BELOW, &destination, &self.start)?;
+        }
+        db.store_keyed_encodable(RECONCILED_THROUGH, &destination, &self.end)
+    }
+}
+
+/// First leaf index within `lookback` of the latest leaf `end`
+fn lookback_start(
+    db: &NomadDB,
+    lookback: ReconciliationLookback,
+    end: u32,
+    now: u64,
+) -> Result<u32, DbError> {
+    match lookback {
+        ReconciliationLookback::Leaves(leaves) => Ok((end + 1).saturating_sub(leaves)),
+        ReconciliationLookback::Seconds(seconds) => {
+            let cutoff = now.saturating_sub(seconds);
+            for leaf_index in (0..=end).rev() {
+                if matches!(committed_at(db, leaf_index)?, Some(at) if at < cutoff) {
+                    return Ok(leaf_index + 1);
+                }
+            }
+            Ok(0)
+        }
+    }
+}
+
+/// Timestamp of the update committing to the message at `leaf_index`, if
+/// it is indexed and has one
+fn committed_at(db: &NomadDB, leaf_index: u32) -> Result<Option<u64>, DbError> {
+    let message = match db.message_by_leaf_index(leaf_index)? {
+        Some(message) => message,
+        None => return Ok(None),
+    };
+    let update = match db.update_by_previous_root(message.committed_root)? {
+        Some(update) => update,
+        None => return Ok(None),
+    };
+    Ok(db
+        .retrieve_update_metadata(update.update.new_root)?
+        .and_then(|meta| meta.timestamp))
+}
+
+/// Whether `message` is older than every startup reconciliation and was not
+/// reconciled on an earlier access
+pub(crate) fn needs_lazy_reconciliation(
+    db: &NomadDB,
+    message: &CommittedMessage,
+) -> Result<bool, DbError> {
+    let below: u32 = db
+        .retrieve_keyed_decodable(RECONCILE_LAZILY_BELOW, &message.message.destination)?
+        .unwrap_or_default();
+    if message.leaf_index >= below {
+        return Ok(false);
+    }
+    Ok(db
+        .retrieve_keyed_decodable::<_, bool>(LAZILY_RECONCILED, &message.to_leaf())?
+        .is_none())
+}
+
+/// Record that `message` was reconciled on access
+pub(crate) fn set_lazily_reconciled(
+    db: &NomadDB,
+    message: &CommittedMessage,
+) -> Result<(), DbError> {
+    db.store_keyed_encodable(LAZILY_RECONCILED, &message.to_leaf(), &true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::core::types::{Signature, H256};
+    use nomad_core::{
+        Encode, NomadMessage, RawCommittedMessage, SignedUpdate, SignedUpdateWithMeta, Update,
+        UpdateMeta,
+    };
+    use nomad_test::test_utils;
+    use std::convert::TryInto;
+
+    /// Store messages to domain 2000 at `leaves`, each committed by its own
+    /// update at timestamp `1000 * (leaf_index + 1)`
+    fn store_history(db: &NomadDB, leaves: std::ops::Range<u32>) {
+        let root = |i: u32| H256::from_low_u64_be(i as u64 + 1);
+        for leaf_index in leaves {
+            let message = NomadMessage {
+                origin: 1000,
+                sender: H256::repeat_byte(1),
+                nonce: leaf_index,
+                destination: 2000,
+                recipient: H256::repeat_byte(2),
+                body: vec![],
+            };
+            db.store_latest_message(&RawCommittedMessage {
+                leaf_index,
+                committed_root: root(leaf_index),
+                message: message.to_vec(),
+                timestamp: None,
+            })
+            .unwrap();
+            db.store_updates_and_meta(&[SignedUpdateWithMeta {
+                signed_update: SignedUpdate {
+                    update: Update {
+                        home_domain: 1000,
+                        previous_root: root(leaf_index),
+                        new_root: root(leaf_index + 1),
+                    },
+                    signature: Signature {
+                        r: Default::default(),
+                        s: Default::default(),
+                        v: 27,
+                    }
+                    .try_into()
+                    .unwrap(),
+                },
+                metadata: UpdateMeta {
+                    block_number: leaf_index as u64,
+                    timestamp: Some(1000 * (leaf_index as u64 + 1)),
+                },
+            }])
+            .unwrap();
+        }
+    }
+
+    fn window(start: u32, end: u32) -> Option<ReconcileWindow> {
+        Some(ReconcileWindow { start, end })
+    }
+
+    #[tokio::test]
+    async fn it_bounds_reconciliation_by_the_lookback() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            assert_eq!(
+                ReconcileWindow::new(&db, 2000, None, false, 0).unwrap(),
+                None
+            );
+            store_history(&db, 0..100);
+
+            // Without a lookback, the first startup reconciles everything
+            assert_eq!(
+                ReconcileWindow::new(&db, 2000, None, false, 0).unwrap(),
+                window(0, 99)
+            );
+            let leaves = Some(ReconciliationLookback::Leaves(10));
+            assert_eq!(
+                ReconcileWindow::new(&db, 2000, leaves, false, 0).unwrap(),
+                window(90, 99)
+            );
+            // Leaves committed at or after 95_000
+            let seconds = Some(ReconciliationLookback::Seconds(5_000));
+            assert_eq!(
+                ReconcileWindow::new(&db, 2000, seconds, false, 100_000).unwrap(),
+                window(94, 99)
+            );
+            assert_eq!(
+                ReconcileWindow::new(&db, 2000, leaves, true, 0).unwrap(),
+                window(0, 99)
+            );
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_covers_the_delta_and_reconciles_older_history_lazily() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            store_history(&db, 0..100);
+            let message = |leaf_index: u32| -> CommittedMessage {
+                db.message_by_leaf_index(leaf_index)
+                    .unwrap()
+                    .unwrap()
+                    .try_into()
+                    .unwrap()
+            };
+            let leaves = Some(ReconciliationLookback::Leaves(10));
+
+            let first = ReconcileWindow::new(&db, 2000, leaves, false, 0)
+                .unwrap()
+                .unwrap();
+            first.store(&db, 2000).unwrap();
+            assert!(needs_lazy_reconciliation(&db, &message(89)).unwrap());
+            assert!(!needs_lazy_reconciliation(&db, &message(90)).unwrap());
+            set_lazily_reconciled(&db, &message(89)).unwrap();
+            assert!(!needs_lazy_reconciliation(&db, &message(89)).unwrap());
+            assert!(needs_lazy_reconciliation(&db, &message(5)).unwrap());
+
+            // 50 leaves later, the next startup covers the delta, which is
+            // longer than the lookback
+            store_history(&db, 100..150);
+            let second = ReconcileWindow::new(&db, 2000, leaves, false, 0)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                second,
+                ReconcileWindow {
+                    start: 100,
+                    end: 149
+                }
+            );
+            second.store(&db, 2000).unwrap();
+            assert!(needs_lazy_reconciliation(&db, &message(5)).unwrap());
+
+            // Shortly after, the lookback covers more than the delta
+            store_history(&db, 150..152);
+            assert_eq!(
+                ReconcileWindow::new(&db, 2000, leaves, false, 0).unwrap(),
+                window(142, 151)
+            );
+
+            // A full reconciliation leaves nothing to reconcile lazily
+            let full = ReconcileWindow::new(&db, 2000, leaves, true, 0)
+                .unwrap()
+                .unwrap();
+            full.store(&db, 2000).unwrap();
+            assert!(!needs_lazy_reconciliation(&db, &message(5)).unwrap());
+            assert_eq!(
+                ReconcileWindow::new(&db, 2000, None, false, 0).unwrap(),
+                None
+            );
+        })
+        .await
+    }
+}
@@ -0,0 +1,210 @@
+//! Per-sender message queues for `PerSenderNonce` processing order
+
+use ethers::prelude::H256;
+use std::collections::{BTreeMap, VecDeque};
+
+use nomad_base::NomadDB;
+use nomad_core::{db::DbError, Decode, Encode, MessageStatus, NomadError};
+
+static SENDER_QUEUES: &str = "sender_queues_";
+
+/// What to do with the head of a sender's queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HeadAction {
+    /// The head is processed or released by the operator. Pop it.
+    Pop,
+    /// The head has not been attempted yet. Process it.
+    Process,
+    /// The head was attempted and is not processed. Hold the sender until
+    /// the head is processed or released.
+    Wait,
+}
+
+impl HeadAction {
+    /// Decide on the head of a queue from its status on the replica, whether
+    /// it was attempted before and whether the operator released it
+    pub(crate) fn decide(status: MessageStatus, attempted: bool, released: bool) -> Self {
+        match (status, attempted, released) {
+            (MessageStatus::Processed, _, _) | (_, _, true) => HeadAction::Pop,
+            (_, false, false) => HeadAction::Process,
+            (_, true, false) => HeadAction::Wait,
+        }
+    }
+}
+
+/// Messages held back per sender, by destination nonce. The head of a queue is
+/// the earliest message of the sender that has not reached `Processed` on the
+/// replica. Every later message of that sender waits behind it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct SenderQueues {
+    queues: BTreeMap<H256, VecDeque<u32>>,
+}
+
+impl SenderQueues {
+    /// Load the queues of a destination domain
+    pub(crate) fn load(db: &NomadDB, destination: u32) -> Result<Self, DbError> {
+        Ok(db
+            .retrieve_keyed_decodable(SENDER_QUEUES, &destination)?
+            .unwrap_or_default())
+    }
+
+    /// Persist the queues of a destination domain
+    pub(crate) fn store(&self, db: &NomadDB, destination: u32) -> Result<(), DbError> {
+        db.store_keyed_encodable(SENDER_QUEUES, &destination, self)
+    }
+
+    /// Whether later messages from `sender` must wait
+    pub(crate) fn is_blocked(&self, sender: &H256) -> bool {
+        self.queues.contains_key(sender)
+    }
+
+    /// Append a message to the sender's queue. The first message held for a
+    /// sender becomes the head of its queue. Holding a message twice is a
+    /// no-op, so a message re-read after a restart keeps its place.
+    pub(crate) fn hold(&mut self, sender: H256, nonce: u32) {
+        let queue = self.queues.entry(sender).or_default();
+        if !queue.contains(&nonce) {
+            queue.push_back(nonce);
+        }
+    }
+
+    /// The earliest unprocessed message of the sender
+    pub(crate) fn head(&self, sender: &H256) -> Option<u32> {
+        self.queues
+            .get(sender)
+            .and_then(|queue| queue.front().copied())
+    }
+
+    /// Remove the head of the sender's queue, unblocking the sender once its
+    /// queue is empty
+    pub(crate) fn pop(&mut self, sender: &H256) -> Option<u32> {
+        let queue = self.queues.get_mut(sender)?;
+        let head = queue.pop_front();
+        if queue.is_empty() {
+            self.queues.remove(sender);
+        }
+        head
+    }
+
+    /// Senders with held messages
+    pub(crate) fn senders(&self) -> Vec<H256> {
+        self.queues.keys().copied().collect()
+    }
+
+    /// Total number of held messages
+    pub(crate) fn len(&self) -> usize {
+        self.queues.values().map(VecDeque::len).sum()
+    }
+}
+
+impl Encode for SenderQueues {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = (self.queues.len() as u32).write_to(writer)?;
+        for (sender, queue) in self.queues.iter() {
+            written += sender.write_to(writer)?;
+            written += (queue.len() as u32).write_to(writer)?;
+            for nonce in queue.iter() {
+                written += nonce.write_to(writer)?;
+            }
+        }
+        Ok(written)
+    }
+}
+
+impl Decode for SenderQueues {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let mut queues = BTreeMap::new();
+        for _ in 0..u32::read_from(reader)? {
+            let sender = H256::read_from(reader)?;
+            let len = u32::read_from(reader)?;
+            let queue = (0..len)
+                .map(|_| u32::read_from(reader))
+                .collect::<Result<VecDeque<_>, _>>()?;
+            queues.insert(sender, queue);
+        }
+        Ok(Self { queues })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nomad_test::test_utils;
+
+    #[tokio::test]
+    async fn it_holds_interleaved_senders_independently() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let (a, b) = (H256::repeat_byte(0xa), H256::repeat_byte(0xb));
+
+            // Nonces 0..6 alternate between senders. A's first message fails
+            // to process and blocks A. B is never blocked.
+            let mut queues = SenderQueues::default();
+            queues.hold(a, 0);
+            queues.hold(a, 0);
+            for nonce in 1..6 {
+                let sender = if nonce % 2 == 0 { a } else { b };
+                if queues.is_blocked(&sender) {
+                    queues.hold(sender, nonce);
+                }
+            }
+            assert!(!queues.is_blocked(&b));
+            assert_eq!(queues.len(), 3);
+            queues.store(&db, 1000).unwrap();
+
+            // Held messages survive a restart, in nonce order
+            let mut queues = SenderQueues::load(&db, 1000).unwrap();
+            assert_eq!(queues.senders(), vec![a]);
+            assert_eq!(queues.pop(&a), Some(0));
+            assert_eq!(queues.pop(&a), Some(2));
+            assert_eq!(queues.pop(&a), Some(4));
+            assert!(!queues.is_blocked(&a));
+
+            // Queues are per destination
+            assert_eq!(SenderQueues::load(&db, 2000).unwrap(), Default::default());
+        })
+        .await
+    }
+
+    #[test]
+    fn it_releases_a_poisoned_head_only_on_override() {
+        let a = H256::repeat_byte(0xa);
+        let mut queues = SenderQueues::default();
+        queues.hold(a, 0);
+        queues.hold(a, 2);
+
+        // Head 0 was attempted and reverted. It blocks the sender until it is
+        // processed on the replica or released by the operator.
+        let proven = MessageStatus::Proven(H256::zero());
+        assert_eq!(
+            HeadAction::decide(MessageStatus::None, true, false),
+            HeadAction::Wait
+        );
+        assert_eq!(HeadAction::decide(proven, true, false), HeadAction::Wait);
+        assert_eq!(
+            HeadAction::decide(MessageStatus::None, true, true),
+            HeadAction::Pop
+        );
+        assert_eq!(queues.pop(&a), Some(0));
+
+        // The next held message is processed in nonce order
+        assert_eq!(queues.head(&a), Some(2));
+        assert_eq!(
+            HeadAction::decide(MessageStatus::None, false, false),
+            HeadAction::Process
+        );
+        assert_eq!(
+            HeadAction::decide(MessageStatus::Processed, true, false),
+            HeadAction::Pop
+        );
+        assert_eq!(queues.pop(&a), Some(2));
+        assert!(!queues.is_blocked(&a));
+    }
+}
@@ -0,0 +1,463 @@
+//! Messages held for being older than the processor's maximum message age.
+//! A held message is not processed until an operator releases it.
+
+use color_eyre::Result;
+use ethers::prelude::H256;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::ErrorKind,
+    sync::{Arc, RwLock},
+};
+use tracing::{info, warn};
+
+use nomad_base::{CoreMetrics, CorridorIntGauge, NomadDB};
+use nomad_core::{
+    h256_serde, CanonicalH256, CommittedMessage, Decode, Encode, MessageRef, NomadError,
+};
+
+/// Held messages, by leaf
+static STALE_HELD: &str = "stale_held_";
+/// `/status` section listing held messages
+const STATUS_SECTION: &str = "held_messages";
+
+/// Whether a message dispatched at `dispatched_at` is older than `max_age`
+/// at `now`. Nothing is stale without a maximum age.
+pub(crate) fn is_stale(dispatched_at: u64, now: u64, max_age: Option<u64>) -> bool {
+    max_age.map_or(false, |max_age| now.saturating_sub(dispatched_at) > max_age)
+}
+
+/// A message held for its age
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StaleHold {
+    /// Replica the message is destined for
+    pub(crate) replica: String,
+    /// Leaf of the message
+    #[serde(with = "h256_serde")]
+    pub(crate) leaf: H256,
+    /// Leaf index of the message
+    pub(crate) leaf_index: u32,
+    /// Nonce of the message on its destination
+    pub(crate) nonce: u32,
+    /// Timestamp of the block the message was dispatched in, if it was
+    /// indexed with one
+    pub(crate) dispatched_at: Option<u64>,
+    /// When the processor held the message
+    pub(crate) held_at: u64,
+    /// Whether an operator released the message for processing
+    pub(crate) released: bool,
+}
+
+impl Encode for StaleHold {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        writer.write_all(&json)?;
+        Ok(json.len())
+    }
+}
+
+impl Decode for StaleHold {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let mut json = vec![];
+        reader.read_to_end(&mut json)?;
+        serde_json::from_slice(&json).map_err(|e| {
+            NomadError::IoError(std::io::Error::new(ErrorKind::InvalidData, e.to_string()))
+        })
+    }
+}
+
+/// Messages of all replicas held for their age. Holds are stored in the
+/// db, so they survive restarts, and are released through the admin
+/// endpoint. Every hold is listed in `/status` and counted per replica in
+/// the `held_stale_messages` gauge until the message is processed.
+#[derive(Debug)]
+pub(crate) struct StaleHolds {
+    db: NomadDB,
+    holds: RwLock<BTreeMap<H256, StaleHold>>,
+    held: CorridorIntGauge,
+    metrics: Arc<CoreMetrics>,
+}
+
+impl StaleHolds {
+    /// Load the holds stored in the home's db
+    pub(crate) fn new(db: NomadDB, metrics: Arc<CoreMetrics>) -> Result<Self> {
+        let held = metrics.corridor_int_gauge(
+            "held_stale_messages",
+            "Number of messages held for being older than the maximum message age, awaiting release",
+        )?;
+
+        let holds = Self {
+            db,
+            holds: Default::default(),
+            held,
+            metrics,
+        };
+        let stored = holds
+            .db
+            .iterate_from::<StaleHold>(STALE_HELD, H256::zero())
+            .collect::<Result<Vec<_>, _>>()?;
+        for hold in stored {
+            holds.set(hold);
+        }
+
+        Ok(holds)
+    }
+
+    /// The hold on `leaf`, if any
+    pub(crate) fn get(&self, leaf: H256) -> Option<StaleHold> {
+        self.holds.read().expect("poisoned").get(&leaf).cloned()
+    }
+
+    /// Holds of messages to `replica`
+    pub(crate) fn of_replica(&self, replica: &str) -> Vec<StaleHold> {
+        self.holds
+            .read()
+            .expect("poisoned")
+            .values()
+            .filter(|hold| hold.replica == replica)
+            .cloned()
+            .collect()
+    }
+
+    /// Hold `message` to `replica`, dispatched at `dispatched_at` if known.
+    /// Holding a held message keeps its existing hold.
+    pub(crate) fn hold(
+        &self,
+        replica: &str,
+        message: &CommittedMessage,
+        dispatched_at: Option<u64>,
+        now: u64,
+    ) -> Result<StaleHold> {
+        if let Some(hold) = self.get(message.to_leaf()) {
+            return Ok(hold);
+        }
+        let hold = StaleHold {
+            replica: replica.to_owned(),
+            leaf: message.to_leaf(),
+            leaf_index: message.leaf_index,
+            nonce: message.message.nonce,
+            dispatched_at,
+            held_at: now,
+            released: false,
+        };
+        match dispatched_at {
+            Some(dispatched_at) => warn!(
+                replica,
+                leaf = %CanonicalH256(hold.leaf),
+                leaf_index = hold.leaf_index,
+                nonce = hold.nonce,
+                age = now.saturating_sub(dispatched_at),
+                "Message is older than the maximum message age. Holding it until it is released."
+            ),
+            None => warn!(
+                replica,
+                leaf = %CanonicalH256(hold.leaf),
+                leaf_index = hold.leaf_index,
+                nonce = hold.nonce,
+                "Message age is unknown. Holding it until it is released."
+            ),
+        }
+        self.db
+            .store_keyed_encodable(STALE_HELD, &hold.leaf, &hold)?;
+        self.set(hold.clone());
+        Ok(hold)
+    }
+
+    /// Release the hold on `leaf`. Returns `None` if the message is not held.
+    pub(crate) fn release(&self, leaf: H256) -> Result<Option<StaleHold>> {
+        let mut hold = match self.get(leaf) {
+            Some(hold) => hold,
+            None => return Ok(None),
+        };
+        hold.released = true;
+        info!(
+            replica = hold.replica.as_str(),
+            leaf = %CanonicalH256(leaf),
+            nonce = hold.nonce,
+            "Operator released message held for its age"
+        );
+        self.db.store_keyed_encodable(STALE_HELD, &leaf, &hold)?;
+        self.set(hold.clone());
+        Ok(Some(hold))
+    }
+
+    /// Leaf of `message`, looking ids up in the home's db. `None` if no
+    /// message has the id.
+    fn leaf_of(&self, message: MessageRef) -> Result<Option<H256>> {
+        match message {
+            MessageRef::Leaf(leaf) => Ok(Some(leaf)),
+            MessageRef::Id(id) => Ok(self.db.message_by_id(id)?.map(|raw| raw.leaf())),
+        }
+    }
+
+    /// Drop the hold on `leaf` once its message is processed or attempted
+    pub(crate) fn remove(&self, leaf: H256) -> Result<()> {
+        self.db.delete_keyed(STALE_HELD, &leaf)?;
+        let removed = self.holds.write().expect("poisoned").remove(&leaf);
+        if let Some(hold) = removed {
+            self.metrics
+                .remove_status(STATUS_SECTION, &CanonicalH256(leaf).to_string());
+            self.count(&hold.replica);
+        }
+        Ok(())
+    }
+
+    /// Store the hold in memory and replace its `/status` entry
+    fn set(&self, hold: StaleHold) {
+        self.metrics.set_status(
+            STATUS_SECTION,
+            &CanonicalH256(hold.leaf).to_string(),
+            serde_json::to_value(&hold).expect("!serialize"),
+        );
+        let replica = hold.replica.clone();
+        self.holds
+            .write()
+            .expect("poisoned")
+            .insert(hold.leaf, hold);
+        self.count(&replica);
+    }
+
+    /// Set the gauge of `replica` to its number of unreleased holds
+    fn count(&self, replica: &str) {
+        let held = self
+            .of_replica(replica)
+            .iter()
+            .filter(|hold| !hold.released)
+            .count();
+        self.held.get(replica).set(held as i64);
+    }
+
+    /// Admin routes. `GET /messages/held` lists the held messages and
+    /// `POST /messages/<leaf or id>/release` releases one.
+    pub(crate) fn routes(
+        self: Arc<Self>,
+    ) -> impl warp::Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        use warp::{http::StatusCode, Filter};
+
+        let listing = self.clone();
+        let list = warp::get()
+            .and(warp::path!("messages" / "held"))
+            .map(move || {
+                let holds: Vec<_> = listing
+                    .holds
+                    .read()
+                    .expect("poisoned")
+                    .values()
+                    .cloned()
+                    .collect();
+                warp::reply::with_status(warp::reply::json(&holds), StatusCode::OK)
+            });
+
+        let release = warp::post()
+            .and(warp::path!("messages" / String / "release"))
+            .map(move |message: String| {
+                let parsed = match message.parse::<MessageRef>() {
+                    Ok(parsed) => parsed,
+                    Err(e) => {
+                        return warp::reply::with_status(
+                            warp::reply::json(&e.to_string()),
+                            StatusCode::BAD_REQUEST,
+                        )
+                    }
+                };
+                let released = self.leaf_of(parsed).and_then(|leaf| match leaf {
+                    Some(leaf) => self.release(leaf),
+                    None => Ok(None),
+                });
+                match released {
+                    Ok(Some(hold)) => {
+                        warp::reply::with_status(warp::reply::json(&hold), StatusCode::OK)
+                    }
+                    Ok(None) => warp::reply::with_status(
+                        warp::reply::json(&format!("no held message {}", message)),
+                        StatusCode::NOT_FOUND,
+                    ),
+                    Err(e) => warp::reply::with_status(
+                        warp::reply::json(&e.to_string()),
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                    ),
+                }
+            });
+
+        list.or(release)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nomad_core::{NomadMessage, RawCommittedMessage};
+    use nomad_test::test_utils;
+
+    fn metrics() -> Arc<CoreMetrics> {
+        Arc::new(
+            CoreMetrics::new(
+                "processor",
+                "home_1",
+                None,
+                Arc::new(prometheus::Registry::new()),
+            )
+            .expect("could not make metrics"),
+        )
+    }
+
+    fn message(nonce: u32) -> CommittedMessage {
+        CommittedMessage {
+            leaf_index: nonce,
+            committed_root: H256::zero(),
+            message: NomadMessage {
+                origin: 1000,
+                sender: H256::repeat_byte(1),
+                nonce,
+                destination: 2000,
+                recipient: H256::repeat_byte(2),
+                body: vec![],
+            },
+            timestamp: Some(1_000),
+        }
+    }
+
+    fn held(holds: &StaleHolds) -> i64 {
+        holds.held.get("replica_1").get()
+    }
+
+    #[test]
+    fn it_holds_messages_older_than_the_maximum_age() {
+        assert!(!is_stale(1_000, 2_000, Some(1_000)));
+        assert!(is_stale(1_000, 2_001, Some(1_000)));
+        assert!(!is_stale(1_000, 2_001, None));
+        // a dispatch timestamp ahead of the host clock is not stale
+        assert!(!is_stale(3_000, 2_001, Some(1_000)));
+    }
+
+    #[tokio::test]
+    async fn it_persists_and_releases_holds() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let holds = StaleHolds::new(db.clone(), metrics()).unwrap();
+            let message = message(7);
+            let leaf = message.to_leaf();
+
+            let hold = holds
+                .hold("replica_1", &message, Some(1_000), 90_000)
+                .unwrap();
+            assert_eq!(hold.held_at, 90_000);
+            // holding again keeps the first hold
+            assert_eq!(
+                holds
+                    .hold("replica_1", &message, Some(1_000), 95_000)
+                    .unwrap(),
+                hold
+            );
+            assert_eq!(held(&holds), 1);
+            let key = CanonicalH256(leaf).to_string();
+            assert_eq!(
+                holds.metrics.status()["held_messages"][&key]["released"],
+                false
+            );
+
+            // holds survive a restart
+            let holds = StaleHolds::new(db.clone(), metrics()).unwrap();
+            assert_eq!(holds.get(leaf), Some(hold));
+            assert_eq!(held(&holds), 1);
+
+            assert!(holds.release(leaf).unwrap().unwrap().released);
+            assert_eq!(held(&holds), 0);
+            assert_eq!(holds.of_replica("replica_1").len(), 1);
+            assert!(holds.release(H256::repeat_byte(9)).unwrap().is_none());
+
+            holds.remove(leaf).unwrap();
+            assert_eq!(holds.get(leaf), None);
+            assert!(holds.metrics.status()["held_messages"]
+                .as_object()
+                .unwrap()
+                .is_empty());
+            let holds = StaleHolds::new(db, metrics()).unwrap();
+            assert!(holds.of_replica("replica_1").is_empty());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_releases_through_the_admin_endpoint() {
+        test_utils::run_test_db(|db| async move {
+            let holds = Arc::new(StaleHolds::new(NomadDB::new("home_1", db), metrics()).unwrap());
+            let message = message(7);
+            holds
+                .hold("replica_1", &message, Some(1_000), 90_000)
+                .unwrap();
+            let routes = holds.clone().routes();
+
+            let response = warp::test::request()
+                .method("GET")
+                .path("/messages/held")
+                .reply(&routes)
+                .await;
+            assert_eq!(response.status(), 200);
+            let listed: Vec<StaleHold> = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(listed.len(), 1);
+
+            let response = warp::test::request()
+                .method("POST")
+                .path(&format!(
+                    "/messages/{}/release",
+                    CanonicalH256(message.to_leaf())
+                ))
+                .reply(&routes)
+                .await;
+            assert_eq!(response.status(), 200);
+            assert_eq!(held(&holds), 0);
+
+            let response = warp::test::request()
+                .method("POST")
+                .path(&format!(
+                    "/messages/{}/release",
+                    CanonicalH256(H256::repeat_byte(9))
+                ))
+                .reply(&routes)
+                .await;
+            assert_eq!(response.status(), 404);
+
+            // by id, once the home's db has indexed the message
+            let indexed = message(8);
+            holds
+                .hold("replica_1", &indexed, Some(1_000), 90_000)
+                .unwrap();
+            holds
+                .db
+                .store_raw_committed_message(&RawCommittedMessage {
+                    leaf_index: indexed.leaf_index,
+                    committed_root: indexed.committed_root,
+                    message: indexed.message.to_vec(),
+                    timestamp: indexed.timestamp,
+                })
+                .unwrap();
+            for (id, status) in [("2000-8", 404), ("1000-9", 404), ("1000-8", 200)] {
+                let response = warp::test::request()
+                    .method("POST")
+                    .path(&format!("/messages/{}/release", id))
+                    .reply(&routes)
+                    .await;
+                assert_eq!(response.status(), status, "{}", id);
+            }
+            assert_eq!(held(&holds), 0);
+
+            let response = warp::test::request()
+                .method("POST")
+                .path("/messages/not-a-leaf/release")
+                .reply(&routes)
+                .await;
+            assert_eq!(response.status(), 400);
+        })
+        .await
+    }
+}
@@ -0,0 +1,241 @@
+use color_eyre::Result;
+use ethers::core::types::H256;
+use nomad_base::NomadDB;
+use nomad_core::CanonicalH256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{info, info_span, instrument::Instrumented, warn, Instrument};
+
+/// Default seconds after which an unmatched home update is warned about
+pub(crate) const DEFAULT_UNMATCHED_WARN_SECONDS: u64 = 3600;
+
+/// Time from a home update's inclusion to its inclusion on the replica
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RelayLatency {
+    /// New root of the relayed update
+    pub(crate) new_root: H256,
+    /// Replica block timestamp minus home block timestamp
+    pub(crate) seconds: u64,
+    /// Whether the update reached the replica without a submission by this
+    /// agent
+    pub(crate) third_party: bool,
+}
+
+/// Result of one reconciliation pass
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Reconciliation {
+    /// Updates newly matched on the replica
+    pub(crate) latencies: Vec<RelayLatency>,
+    /// Oldest home update not yet seen on the replica, and its age in
+    /// seconds, if older than the warning threshold
+    pub(crate) stale: Option<(H256, u64)>,
+}
+
+/// Joins home updates, replica updates and this agent's relay submissions
+/// to measure relay latency on one corridor.
+///
+/// Home updates are walked from a cursor root and matched to replica updates
+/// by root pair. Home updates skipped by a batched relay are never matched
+/// and are passed over once a later update is.
+#[derive(Debug)]
+pub(crate) struct RelayReconciler {
+    pub(crate) home_db: NomadDB,
+    pub(crate) replica_db: NomadDB,
+    pub(crate) replica: String,
+    pub(crate) cursor: H256,
+    pub(crate) warn_after: u64,
+}
+
+impl RelayReconciler {
+    /// Match home updates past the cursor against the replica at unix time
+    /// `now`
+    pub(crate) fn step(&mut self, now: u64) -> Result<Reconciliation> {
+        let mut reconciliation = Reconciliation::default();
+        let mut oldest_unmatched: Option<(H256, Option<u64>)> = None;
+
+        let mut root = self.cursor;
+        while let Some(update) = self.home_db.update_by_previous_root(root)? {
+            let previous_root = update.update.previous_root;
+            let new_root = update.update.new_root;
+            root = new_root;
+
+            let on_replica = self
+                .replica_db
+                .update_by_new_root(new_root)?
+                .filter(|u| u.update.previous_root == previous_root)
+                .is_some();
+            let home_timestamp = self
+                .home_db
+                .retrieve_update_metadata(new_root)?
+                .and_then(|meta| meta.timestamp);
+
+            if !on_replica {
+                if oldest_unmatched.is_none() {
+                    oldest_unmatched = Some((new_root, home_timestamp));
+                }
+                continue;
+            }
+
+            let replica_timestamp = self
+                .replica_db
+                .retrieve_update_metadata(new_root)?
+                .and_then(|meta| meta.timestamp);
+
+            // Timestamps are best effort. A match without them still
+            // advances the cursor.
+            if let (Some(home), Some(replica)) = (home_timestamp, replica_timestamp) {
+                let third_party = self
+                    .home_db
+                    .retrieve_relay_submission(&self.replica, new_root)?
+                    .is_none();
+                reconciliation.latencies.push(RelayLatency {
+                    new_root,
+                    seconds: replica.saturating_sub(home),
+                    third_party,
+                });
+            }
+
+            self.cursor = new_root;
+            oldest_unmatched = None;
+        }
+
+        reconciliation.stale = oldest_unmatched
+            .and_then(|(root, timestamp)| Some((root, now.saturating_sub(timestamp?))))
+            .filter(|(_, age)| *age > self.warn_after);
+
+        Ok(reconciliation)
+    }
+
+    /// Spawn the reconciler, observing latencies into `local` and
+    /// `third_party`
+    pub(crate) fn spawn(
+        mut self,
+        interval: u64,
+        local: prometheus::Histogram,
+        third_party: prometheus::Histogram,
+    ) -> Instrumented<JoinHandle<Result<()>>> {
+        let span = info_span!("RelayReconciler", replica = self.replica.as_str());
+        tokio::spawn(async move {
+            loop {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                let reconciliation = self.step(now)?;
+
+                for latency in reconciliation.latencies {
+                    if latency.third_party {
+                        third_party.observe(latency.seconds as f64);
+                    } else {
+                        local.observe(latency.seconds as f64);
+                    }
+                    info!(
+                        replica = self.replica.as_str(),
+                        new_root = %CanonicalH256(latency.new_root),
+                        seconds = latency.seconds,
+                        third_party = latency.third_party,
+                        "Update reached replica",
+                    );
+                }
+
+                if let Some((root, age)) = reconciliation.stale {
+                    warn!(
+                        replica = self.replica.as_str(),
+                        new_root = %CanonicalH256(root),
+                        age_seconds = age,
+                        "Home update not seen on replica {} after {} seconds",
+                        self.replica,
+                        age,
+                    );
+                }
+
+                sleep(Duration::from_secs(interval)).await;
+            }
+        })
+        .instrument(span)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::signers::LocalWallet;
+    use nomad_core::{SignedUpdate, SignedUpdateWithMeta, Update, UpdateMeta};
+    use nomad_test::test_utils;
+
+    async fn store(db: &NomadDB, signer: &LocalWallet, roots: (u8, u8), timestamp: u64) {
+        let signed_update: SignedUpdate = Update {
+            home_domain: 1000,
+            previous_root: H256::repeat_byte(roots.0),
+            new_root: H256::repeat_byte(roots.1),
+        }
+        .sign_with(signer)
+        .await
+        .unwrap();
+        db.store_updates_and_meta(&[SignedUpdateWithMeta {
+            signed_update,
+            metadata: UpdateMeta {
+                block_number: timestamp,
+                timestamp: Some(timestamp),
+            },
+        }])
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_matches_home_updates_on_the_replica() {
+        test_utils::run_test_db(|db| async move {
+            let signer: LocalWallet = "1".repeat(64).parse().unwrap();
+            let home_db = NomadDB::new("home_1", db.clone());
+            let replica_db = NomadDB::new("replica_1", db);
+
+            // Home: r0 -> r1 -> r2 -> r3 -> r4
+            for (i, t) in [(0, 100), (1, 200), (2, 300), (3, 400)] {
+                store(&home_db, &signer, (i, i + 1), t).await;
+            }
+            // We relayed r0 -> r1. Someone else relayed r2 -> r3 in a batch
+            // skipping r1 -> r2. r3 -> r4 has not arrived.
+            store(&replica_db, &signer, (0, 1), 130).await;
+            home_db
+                .store_relay_submission("replica_1", H256::repeat_byte(1), 110)
+                .unwrap();
+            store(&replica_db, &signer, (2, 3), 460).await;
+
+            let mut reconciler = RelayReconciler {
+                home_db,
+                replica_db: replica_db.clone(),
+                replica: "replica_1".to_owned(),
+                cursor: H256::repeat_byte(0),
+                warn_after: 600,
+            };
+
+            let reconciliation = reconciler.step(500).unwrap();
+            assert_eq!(
+                reconciliation.latencies,
+                vec![
+                    RelayLatency {
+                        new_root: H256::repeat_byte(1),
+                        seconds: 30,
+                        third_party: false,
+                    },
+                    RelayLatency {
+                        new_root: H256::repeat_byte(3),
+                        seconds: 160,
+                        third_party: true,
+                    },
+                ]
+            );
+            assert_eq!(reconciliation.stale, None);
+            assert_eq!(reconciler.cursor, H256::repeat_byte(3));
+
+            // r3 -> r4 goes stale, and is not observed twice once it lands
+            let reconciliation = reconciler.step(1100).unwrap();
+            assert!(reconciliation.latencies.is_empty());
+            assert_eq!(reconciliation.stale, Some((H256::repeat_byte(4), 700)));
+
+            store(&replica_db, &signer, (3, 4), 1150).await;
+            let reconciliation = reconciler.step(1200).unwrap();
+            assert_eq!(reconciliation.latencies.len(), 1);
+            assert_eq!(reconciliation.latencies[0].seconds, 750);
+            assert!(reconciler.step(1300).unwrap().latencies.is_empty());
+        })
+        .await
+    }
+}
@@ -1,21 +1,41 @@
 use async_trait::async_trait;
 use color_eyre::{eyre::ensure, Result};
-use std::{sync::Arc, time::Duration};
-use tokio::{sync::Mutex, task::JoinHandle, time::sleep};
-use tracing::{info, instrument::Instrumented, Instrument};
+use ethers::core::types::H256;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{sync::Mutex, task::JoinHandle};
+use tracing::{debug, info, instrument::Instrumented, warn, Instrument};
 
-use nomad_base::{decl_agent, decl_channel, AgentCore, CachingHome, CachingReplica, NomadAgent};
-use nomad_core::{Common, CommonEvents};
+use nomad_base::{
+    cancel_task, decl_agent, decl_channel, serve_admin, AgentCore, AuditEvent, CachingHome,
+    CachingReplica, CorridorSwitches, NomadAgent, NomadDB, Retry,
+};
+use nomad_core::{
+    capture_submission, is_genesis_root, CanonicalH256, Common, CommonEvents, SignedUpdate,
+    UpdateChain,
+};
 
-use crate::settings::RelayerSettings as Settings;
+use crate::{
+    latency::{RelayReconciler, DEFAULT_UNMATCHED_WARN_SECONDS},
+    settings::RelayerSettings as Settings,
+};
+
+/// Maximum number of updates a batched replica may be caught up by at once
+const MAX_CATCH_UP_UPDATES: usize = 256;
 
 #[derive(Debug)]
 struct UpdatePoller {
-    interval: u64,
+    retry: Retry,
     home: Arc<CachingHome>,
     replica: Arc<CachingReplica>,
+    db: NomadDB,
+    batched: bool,
     semaphore: Mutex<()>,
     updates_relayed_count: prometheus::IntCounter,
+    corridors: Arc<CorridorSwitches>,
 }
 
 impl std::fmt::Display for UpdatePoller {
@@ -32,16 +52,195 @@ impl UpdatePoller {
     fn new(
         home: Arc<CachingHome>,
         replica: Arc<CachingReplica>,
-        interval: u64,
+        db: NomadDB,
+        batched: bool,
+        retry: Retry,
         updates_relayed_count: prometheus::IntCounter,
+        corridors: Arc<CorridorSwitches>,
     ) -> Self {
         Self {
             home,
             replica,
-            interval,
+            db,
+            batched,
+            retry,
             semaphore: Mutex::new(()),
             updates_relayed_count,
+            corridors,
+        }
+    }
+
+    /// Select a verified chain of updates leading from the replica's root to
+    /// the home's root. Only the latest update of the chain is relayed to a
+    /// batched replica, so the shortcut is refused if any intermediate update
+    /// is missing, does not chain, or is not signed by the updater.
+    async fn catch_up_chain(&self, old_root: H256) -> Result<Option<UpdateChain>> {
+        let target = self.home.committed_root().await?;
+        if target == old_root {
+            return Ok(None);
+        }
+
+        let updates = match self
+            .db
+            .update_chain_between(old_root, target, MAX_CATCH_UP_UPDATES)?
+        {
+            Some(updates) if updates.len() > 1 => updates,
+            Some(_) => return Ok(None),
+            None => {
+                warn!(
+                    replica = self.replica.name(),
+                    old_root = ?old_root,
+                    target = ?target,
+                    "Missing update between replica root and home root. Not skipping intermediate updates."
+                );
+                return Ok(None);
+            }
+        };
+
+        let updater = self.home.updater().await?.into();
+        match UpdateChain::verify(old_root, updates, updater) {
+            Ok(chain) => Ok(Some(chain)),
+            Err(e) => {
+                warn!(
+                    replica = self.replica.name(),
+                    old_root = ?old_root,
+                    target = ?target,
+                    error = %e,
+                    "Intermediate updates failed verification. Not skipping intermediate updates."
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Pick the root relaying starts from when the replica has no relay
+    /// cursor, as after the DB was wiped: the replica's committed root. Home
+    /// updates up to the one producing it are already on the replica, so they
+    /// are never relayed again, however far the home index has to catch up.
+    /// A fresh replica is at its genesis root, the root it was initialized
+    /// with, and its first update is the home update building on that root.
+    async fn bootstrap(&self) -> Result<()> {
+        let replica = self.replica.name();
+        if let Some(cursor) = self.db.retrieve_relay_cursor(replica)? {
+            info!(
+                replica,
+                cursor = ?cursor,
+                "Resuming relay to replica {} from root {}",
+                replica,
+                CanonicalH256(cursor)
+            );
+            return Ok(());
+        }
+
+        let committed_root = self.replica.committed_root().await?;
+        let initialization_root = self
+            .replica
+            .db()
+            .genesis_root_advance()?
+            .map(|advance| advance.root);
+        if is_genesis_root(committed_root, initialization_root) {
+            info!(
+                replica,
+                committed_root = ?committed_root,
+                "No relay cursor for replica {}. Starting with its first update, from its genesis root {}",
+                replica,
+                CanonicalH256(committed_root)
+            );
+            self.db.store_relay_cursor(replica, committed_root)?;
+            return Ok(());
         }
+
+        match self.db.update_by_new_root(committed_root)? {
+            Some(update) => info!(
+                replica,
+                committed_root = ?committed_root,
+                previous_root = ?update.update.previous_root,
+                "No relay cursor for replica {}. Starting after the home update to its committed root {}",
+                replica,
+                CanonicalH256(committed_root)
+            ),
+            None => info!(
+                replica,
+                committed_root = ?committed_root,
+                "No relay cursor for replica {}. Starting at its committed root {}, once the home index reaches it",
+                replica,
+                CanonicalH256(committed_root)
+            ),
+        }
+
+        self.db.store_relay_cursor(replica, committed_root)?;
+        Ok(())
+    }
+
+    /// Submit an update to the replica unless a submission is in flight or
+    /// the replica has moved past `expected_root`, the root the update was
+    /// selected for. `skipped` are the new roots of the intermediate updates
+    /// a batched relay skips. Returns `true` if the update was submitted.
+    ///
+    /// CancellationSafe: the submission is recorded right after the
+    /// transaction returns. If cancelled before, the update is relayed again,
+    /// which the replica rejects.
+    async fn relay(
+        &self,
+        signed_update: &SignedUpdate,
+        expected_root: H256,
+        skipped: Vec<H256>,
+    ) -> Result<bool> {
+        // Attempt to acquire lock for submitting tx
+        let lock = self.semaphore.try_lock();
+        if lock.is_err() {
+            return Ok(false); // tx in flight. just do nothing
+        }
+
+        // Waiting on the home index may take long, e.g. while it catches up
+        // after the DB was wiped. An update the replica moved past would only
+        // revert.
+        let committed_root = self.replica.committed_root().await?;
+        if committed_root != expected_root {
+            info!(
+                replica = self.replica.name(),
+                expected_root = ?expected_root,
+                committed_root = ?committed_root,
+                new_root = ?signed_update.update.new_root,
+                "Replica moved past the update's root. Not relaying."
+            );
+            return Ok(false);
+        }
+
+        let decision = self.db.audit(AuditEvent::RelayUpdate {
+            replica: self.replica.name().to_owned(),
+            previous_root: expected_root,
+            new_root: signed_update.update.new_root,
+            skipped,
+        })?;
+
+        // Relay update and increment counters if tx successful
+        let (result, payload) = capture_submission(self.replica.update(signed_update)).await;
+        self.db.audit_submission(
+            decision,
+            self.replica.name(),
+            &result,
+            payload.as_ref(),
+            |tx| tx.txid,
+        )?;
+        match result {
+            Ok(_) => {
+                self.updates_relayed_count.inc();
+                let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                self.db.store_relay_submission(
+                    self.replica.name(),
+                    signed_update.update.new_root,
+                    now,
+                )?;
+            }
+            Err(e) => {
+                drop(lock.unwrap());
+                return Err(e.into());
+            }
+        };
+
+        // lock dropped here
+        Ok(true)
     }
 
     #[tracing::instrument(err, skip(self), fields(self = %self))]
@@ -51,8 +250,38 @@ impl UpdatePoller {
         info!(
             "Replica {} latest root is: {}",
             self.replica.name(),
-            old_root
+            CanonicalH256(old_root)
         );
+        self.db.store_relay_cursor(self.replica.name(), old_root)?;
+
+        if self.batched {
+            if let Some(chain) = self.catch_up_chain(old_root).await? {
+                let latest = chain.latest();
+                info!(
+                    replica = self.replica.name(),
+                    skipped = ?chain
+                        .intermediate()
+                        .iter()
+                        .map(|u| u.update.new_root)
+                        .collect::<Vec<_>>(),
+                    "Batched update for replica {}. Root {} to {}, skipping {} intermediate updates",
+                    self.replica.name(),
+                    CanonicalH256(chain.start()),
+                    CanonicalH256(latest.update.new_root),
+                    chain.intermediate().len(),
+                );
+
+                let skipped = chain
+                    .intermediate()
+                    .iter()
+                    .map(|u| u.update.new_root)
+                    .collect();
+                if self.relay(latest, chain.start(), skipped).await? {
+                    self.db.store_relay_record(self.replica.name(), &chain)?;
+                }
+                return Ok(());
+            }
+        }
 
         // Check for first signed update building off of the replica's current root
         let signed_update_opt = self.home.signed_update_by_old_root(old_root).await?;
@@ -63,31 +292,16 @@ impl UpdatePoller {
             info!(
                 "Update for replica {}. Root {} to {}",
                 self.replica.name(),
-                &signed_update.update.previous_root,
-                &signed_update.update.new_root,
+                CanonicalH256(signed_update.update.previous_root),
+                CanonicalH256(signed_update.update.new_root),
             );
 
-            // Attempt to acquire lock for submitting tx
-            let lock = self.semaphore.try_lock();
-            if lock.is_err() {
-                return Ok(()); // tx in flight. just do nothing
-            }
-
-            // Relay update and increment counters if tx successful
-            match self.replica.update(&signed_update).await {
-                Ok(_) => self.updates_relayed_count.inc(),
-                Err(e) => {
-                    drop(lock.unwrap());
-                    return Err(e.into());
-                }
-            };
-
-            // lock dropped here
+            self.relay(&signed_update, old_root, vec![]).await?;
         } else {
             info!(
                 "No update. Current root for replica {} is {}",
                 self.replica.name(),
-                old_root
+                CanonicalH256(old_root)
             );
         }
 
@@ -96,9 +310,21 @@ impl UpdatePoller {
 
     fn spawn(self) -> JoinHandle<Result<()>> {
         tokio::spawn(async move {
+            self.bootstrap().await?;
+
             loop {
-                self.poll_and_relay_update().await?;
-                sleep(Duration::from_secs(self.interval)).await;
+                // A paused corridor is left alone until it is resumed
+                if self.corridors.is_enabled(self.replica.name()) {
+                    self.poll_and_relay_update().await?;
+                } else {
+                    debug!(
+                        replica = self.replica.name(),
+                        "Corridor is paused. Not relaying."
+                    );
+                }
+                if !self.retry.sleep(1).await {
+                    return Ok(());
+                }
             }
         })
     }
@@ -106,13 +332,25 @@ impl UpdatePoller {
 
 decl_agent!(Relayer {
     updates_relayed_counts: prometheus::IntCounterVec,
+    relay_latencies: prometheus::HistogramVec,
     interval: u64,
+    batched_replicas: HashSet<String>,
+    unmatched_warn_seconds: u64,
+    corridors: Arc<CorridorSwitches>,
+    admin_port: Option<u16>,
 });
 
 #[allow(clippy::unit_arg)]
 impl Relayer {
     /// Instantiate a new relayer
-    pub fn new(interval: u64, core: AgentCore) -> Self {
+    pub fn new(
+        interval: u64,
+        batched_replicas: HashSet<String>,
+        unmatched_warn_seconds: u64,
+        paused: HashMap<String, String>,
+        admin_port: Option<u16>,
+        core: AgentCore,
+    ) -> Self {
         let updates_relayed_counts = core
             .metrics
             .new_int_counter(
@@ -122,17 +360,52 @@ impl Relayer {
             )
             .expect("processor metric already registered -- should have be a singleton");
 
+        let relay_latencies = core
+            .metrics
+            .new_histogram(
+                "relay_latency_seconds",
+                "Seconds from an update's inclusion on the home to its inclusion on the replica, by whether this agent relayed it",
+                &["home", "replica", "relayed_by", "agent"],
+                &[
+                    15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0, 1800.0, 3600.0, 7200.0,
+                ],
+            )
+            .expect("failed to register relay_latency_seconds metric");
+
+        let corridors = Arc::new(
+            CorridorSwitches::new(
+                core.home.name(),
+                "relayer",
+                core.replicas.keys().cloned(),
+                &paused,
+                core.metrics.clone(),
+            )
+            .expect("failed to register corridor_enabled_info metric")
+            .persisted(NomadDB::new(core.home.name(), core.db.clone()))
+            .expect("could not load corridor states"),
+        );
+
         Self {
             interval,
             core,
             updates_relayed_counts,
+            relay_latencies,
+            batched_replicas,
+            unmatched_warn_seconds,
+            corridors,
+            admin_port,
         }
     }
 }
 
 decl_channel!(Relayer {
     updates_relayed_count: prometheus::IntCounter,
+    local_relay_latency: prometheus::Histogram,
+    third_party_relay_latency: prometheus::Histogram,
     interval: u64,
+    batched: bool,
+    unmatched_warn_seconds: u64,
+    corridors: Arc<CorridorSwitches>,
 });
 
 #[async_trait]
@@ -150,6 +423,13 @@ impl NomadAgent for Relayer {
     {
         Ok(Self::new(
             settings.agent.interval,
+            settings.agent.batched_replicas.clone(),
+            settings
+                .agent
+                .unmatched_update_warn_seconds
+                .unwrap_or(DEFAULT_UNMATCHED_WARN_SECONDS),
+            settings.agent.paused.clone(),
+            settings.agent.admin_port,
             settings.as_ref().try_into_core("relayer").await?,
         ))
     }
@@ -162,10 +442,34 @@ impl NomadAgent for Relayer {
                 replica,
                 Self::AGENT_NAME,
             ]),
+            local_relay_latency: self.relay_latencies.with_label_values(&[
+                self.home().name(),
+                replica,
+                "local",
+                Self::AGENT_NAME,
+            ]),
+            third_party_relay_latency: self.relay_latencies.with_label_values(&[
+                self.home().name(),
+                replica,
+                "third_party",
+                Self::AGENT_NAME,
+            ]),
             interval: self.interval,
+            batched: self.batched_replicas.contains(replica),
+            unmatched_warn_seconds: self.unmatched_warn_seconds,
+            corridors: self.corridors.clone(),
         }
     }
 
+    fn run_admin_server(&self) -> Option<Instrumented<JoinHandle<Result<()>>>> {
+        let port = self.admin_port?;
+        serve_admin("relayer", self.corridors.clone().routes(), port)
+    }
+
+    fn corridors(&self) -> Option<Arc<CorridorSwitches>> {
+        Some(self.corridors.clone())
+    }
+
     #[tracing::instrument]
     fn run(channel: Self::Channel) -> Instrumented<JoinHandle<Result<()>>> {
         tokio::spawn(async move {
@@ -174,18 +478,43 @@ impl NomadAgent for Relayer {
 
             ensure!(
                 home_updater == replica_updater,
-                "Home and replica updaters do not match. Home: {:x}. Replica: {:x}.",
-                home_updater,
-                replica_updater
+                "Home and replica updaters do not match. Home: {}. Replica: {}.",
+                CanonicalH256(home_updater),
+                CanonicalH256(replica_updater)
+            );
+
+            // Index replica updates to match them against home updates
+            // and our own submissions
+            let cursor = channel.replica().committed_root().await?;
+            let replica_sync_task = channel.replica().sync();
+            let reconcile_task = RelayReconciler {
+                home_db: channel.db(),
+                replica_db: channel.replica().db(),
+                replica: channel.replica().name().to_owned(),
+                cursor,
+                warn_after: channel.unmatched_warn_seconds,
+            }
+            .spawn(
+                channel.interval,
+                channel.local_relay_latency.clone(),
+                channel.third_party_relay_latency.clone(),
             );
 
             let update_poller = UpdatePoller::new(
                 channel.home(),
                 channel.replica(),
-                channel.interval,
+                channel.db(),
+                channel.batched,
+                Retry::fixed(Duration::from_secs(channel.interval))
+                    .with_cancellation(channel.cancellation()),
                 channel.updates_relayed_count,
+                channel.corridors,
             );
-            update_poller.spawn().await?
+            let result = update_poller.spawn().await?;
+
+            cancel_task!(reconcile_task);
+            cancel_task!(replica_sync_task);
+            result
         })
         .in_current_span()
     }
@@ -194,10 +523,12 @@ impl NomadAgent for Relayer {
 #[cfg(test)]
 mod test {
     use ethers::prelude::H256;
+    use ethers::signers::{LocalWallet, Signer};
     use nomad_base::{
-        chains::PageSettings, CommonIndexers, ContractSync, ContractSyncMetrics, CoreMetrics,
-        HomeIndexers, IndexSettings, NomadDB,
+        chains::PageSettings, ChainSetupBuilder, CommonIndexers, ContractSync, ContractSyncMetrics,
+        CoreMetrics, HomeIndexers, IndexSettings, NomadDB, SettingsBuilder,
     };
+    use nomad_core::{RootAdvance, RootProvenance, TxOutcome, Update};
     use nomad_test::mocks::{MockHomeContract, MockIndexer, MockReplicaContract};
     use nomad_test::test_utils;
     use std::collections::HashMap;
@@ -224,7 +555,13 @@ mod test {
             let sync_metrics = ContractSyncMetrics::new(metrics.clone());
 
             // Setting home
-            let settings = nomad_base::Settings::default();
+            let settings = SettingsBuilder::new(
+                AGENT_NAME,
+                ChainSetupBuilder::new("home_1", 1000).build().unwrap(),
+            )
+            .replica(ChainSetupBuilder::new(channel_name, 2000).build().unwrap())
+            .build()
+            .unwrap();
             let home_indexer: Arc<HomeIndexers> = Arc::new(MockIndexer::new().into());
             let home_db = NomadDB::new("home_1", db.clone());
             let mut home_mock = MockHomeContract::new();
@@ -260,7 +597,7 @@ mod test {
                 replica_mock
                     .expect__committed_root()
                     .times(..)
-                    .returning(|| Err(nomad_test::mocks::MockError));
+                    .returning(|| Err(nomad_test::mocks::MockError::Failed));
             }
 
             let replica_indexer: Arc<CommonIndexers> = Arc::new(MockIndexer::new().into());
@@ -294,9 +631,10 @@ mod test {
                 metrics,
                 indexer: IndexSettings::default(),
                 settings,
+                cancellation: Default::default(),
             };
 
-            let agent = Relayer::new(2, core);
+            let agent = Relayer::new(2, Default::default(), 3600, Default::default(), None, core);
 
             // Sanity check that we indeed throw an error when calling run NOT
             // run_report_error
@@ -321,4 +659,441 @@ mod test {
         })
         .await
     }
+
+    async fn sign_chain(signer: &LocalWallet, roots: &[H256]) -> Vec<SignedUpdate> {
+        let mut updates = vec![];
+        for window in roots.windows(2) {
+            updates.push(
+                Update {
+                    home_domain: 1,
+                    previous_root: window[0],
+                    new_root: window[1],
+                }
+                .sign_with(signer)
+                .await
+                .expect("!sign"),
+            );
+        }
+        updates
+    }
+
+    /// Poller for a batched replica at `replica_root`, expecting the home at
+    /// `home_root` and exactly one update `expected` to be submitted
+    fn batched_poller(
+        db: nomad_core::db::DB,
+        updater: H256,
+        home_root: H256,
+        replica_root: H256,
+        expected: (H256, H256),
+    ) -> UpdatePoller {
+        poller(
+            db,
+            updater,
+            home_root,
+            Arc::new(std::sync::Mutex::new(replica_root)),
+            &[expected],
+            true,
+        )
+    }
+
+    /// Poller for a replica whose committed root is read from
+    /// `replica_root`, expecting the home at `home_root` and exactly the
+    /// updates `expected` to be submitted, once each
+    fn poller(
+        db: nomad_core::db::DB,
+        updater: H256,
+        home_root: H256,
+        replica_root: Arc<std::sync::Mutex<H256>>,
+        expected: &[(H256, H256)],
+        batched: bool,
+    ) -> UpdatePoller {
+        let metrics = Arc::new(
+            CoreMetrics::new(
+                "relayer_test",
+                "home",
+                None,
+                Arc::new(prometheus::Registry::new()),
+            )
+            .expect("could not make metrics"),
+        );
+        let sync_metrics = ContractSyncMetrics::new(metrics.clone());
+
+        let home_db = NomadDB::new("home_1", db.clone());
+        let mut home_mock = MockHomeContract::new();
+        home_mock.expect__name().return_const("home_1".to_owned());
+        home_mock
+            .expect__updater()
+            .times(..)
+            .returning(move || Ok(updater));
+        home_mock
+            .expect__committed_root()
+            .times(..)
+            .returning(move || Ok(home_root));
+        let home_indexer: Arc<HomeIndexers> = Arc::new(MockIndexer::new().into());
+        let home_sync = ContractSync::new(
+            AGENT_NAME.to_owned(),
+            "home_1".to_owned(),
+            "replica_1".to_owned(),
+            home_db.clone(),
+            home_indexer,
+            IndexSettings::default(),
+            PageSettings::default(),
+            Default::default(),
+            sync_metrics.clone(),
+        );
+        let home = Arc::new(CachingHome::new(
+            home_mock.into(),
+            home_sync,
+            home_db.clone(),
+        ));
+
+        let replica_db = NomadDB::new("replica_1", db);
+        let mut replica_mock = MockReplicaContract::new();
+        replica_mock
+            .expect__name()
+            .return_const("replica_1".to_owned());
+        replica_mock
+            .expect__committed_root()
+            .times(..)
+            .returning(move || Ok(*replica_root.lock().unwrap()));
+        for &expected in expected {
+            replica_mock
+                .expect__update()
+                .withf(move |u: &SignedUpdate| {
+                    (u.update.previous_root, u.update.new_root) == expected
+                })
+                .times(1)
+                .returning(|_| {
+                    Ok(TxOutcome {
+                        txid: H256::zero(),
+                        gas_used: None,
+                        block_number: None,
+                    })
+                });
+        }
+        let replica_indexer: Arc<CommonIndexers> = Arc::new(MockIndexer::new().into());
+        let replica_sync = ContractSync::new(
+            AGENT_NAME.to_owned(),
+            "home_1".to_owned(),
+            "replica_1".to_owned(),
+            replica_db.clone(),
+            replica_indexer,
+            IndexSettings::default(),
+            PageSettings::default(),
+            Default::default(),
+            sync_metrics,
+        );
+        let replica = Arc::new(CachingReplica::new(
+            replica_mock.into(),
+            replica_sync,
+            replica_db,
+        ));
+
+        let counter = prometheus::IntCounter::new("updates_relayed", "test").unwrap();
+        let corridors = CorridorSwitches::new(
+            "home_1",
+            AGENT_NAME,
+            vec!["replica_1".to_owned()],
+            &Default::default(),
+            metrics,
+        )
+        .unwrap();
+        UpdatePoller::new(
+            home,
+            replica,
+            home_db,
+            batched,
+            Retry::fixed(Duration::from_secs(1)),
+            counter,
+            Arc::new(corridors),
+        )
+    }
+
+    fn test_signer() -> LocalWallet {
+        "1111111111111111111111111111111111111111111111111111111111111111"
+            .parse()
+            .unwrap()
+    }
+
+    fn roots() -> Vec<H256> {
+        (0..4).map(H256::repeat_byte).collect()
+    }
+
+    #[tokio::test]
+    async fn batched_replica_skips_verified_intermediate_updates() {
+        test_utils::run_test_db(|db| async move {
+            let signer = test_signer();
+            let roots = roots();
+            let updates = sign_chain(&signer, &roots).await;
+
+            let poller = batched_poller(
+                db,
+                signer.address().into(),
+                roots[3],
+                roots[0],
+                (roots[2], roots[3]),
+            );
+            for update in updates.iter() {
+                poller.db.store_update(update).unwrap();
+            }
+
+            poller.poll_and_relay_update().await.unwrap();
+
+            let record = poller
+                .db
+                .retrieve_relay_record("replica_1", roots[3])
+                .unwrap()
+                .expect("!relay record");
+            assert_eq!(record.updates().cloned().collect::<Vec<_>>(), updates);
+            assert_eq!(record.start(), roots[0]);
+            assert_eq!(record.updater(), signer.address());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn batched_replica_falls_back_on_missing_link() {
+        test_utils::run_test_db(|db| async move {
+            let signer = test_signer();
+            let roots = roots();
+            let updates = sign_chain(&signer, &roots).await;
+
+            let poller = batched_poller(
+                db,
+                signer.address().into(),
+                roots[3],
+                roots[0],
+                (roots[0], roots[1]),
+            );
+            // r1 -> r2 is missing
+            poller.db.store_update(&updates[0]).unwrap();
+            poller.db.store_update(&updates[2]).unwrap();
+
+            poller.poll_and_relay_update().await.unwrap();
+
+            assert!(poller
+                .db
+                .retrieve_relay_record("replica_1", roots[3])
+                .unwrap()
+                .is_none());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn batched_replica_falls_back_on_forged_link() {
+        test_utils::run_test_db(|db| async move {
+            let signer = test_signer();
+            let forger: LocalWallet =
+                "2222222222222222222222222222222222222222222222222222222222222222"
+                    .parse()
+                    .unwrap();
+            let roots = roots();
+            let mut updates = sign_chain(&signer, &roots).await;
+            updates[1] = sign_chain(&forger, &roots[1..3]).await.remove(0);
+
+            let poller = batched_poller(
+                db,
+                signer.address().into(),
+                roots[3],
+                roots[0],
+                (roots[0], roots[1]),
+            );
+            for update in updates.iter() {
+                poller.db.store_update(update).unwrap();
+            }
+
+            poller.poll_and_relay_update().await.unwrap();
+
+            assert!(poller
+                .db
+                .retrieve_relay_record("replica_1", roots[3])
+                .unwrap()
+                .is_none());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn bootstrap_after_db_loss_skips_relayed_updates() {
+        test_utils::run_test_db(|db| async move {
+            let signer = test_signer();
+            let roots: Vec<H256> = (0..6).map(H256::repeat_byte).collect();
+            let updates = sign_chain(&signer, &roots).await;
+
+            // Updates up to r3 were relayed before the DB was wiped. The
+            // fresh DB has re-indexed the home's history but has no cursor.
+            let replica_root = Arc::new(std::sync::Mutex::new(roots[3]));
+            let poller = poller(
+                db,
+                signer.address().into(),
+                roots[5],
+                replica_root.clone(),
+                &[(roots[3], roots[4]), (roots[4], roots[5])],
+                false,
+            );
+            for update in updates.iter() {
+                poller.db.store_update(update).unwrap();
+            }
+            assert!(poller
+                .db
+                .retrieve_relay_cursor("replica_1")
+                .unwrap()
+                .is_none());
+
+            poller.bootstrap().await.unwrap();
+            assert_eq!(
+                poller.db.retrieve_relay_cursor("replica_1").unwrap(),
+                Some(roots[3])
+            );
+
+            // Only the updates past the replica's root are submitted
+            poller.poll_and_relay_update().await.unwrap();
+            *replica_root.lock().unwrap() = roots[4];
+            poller.poll_and_relay_update().await.unwrap();
+            *replica_root.lock().unwrap() = roots[5];
+            assert_eq!(
+                poller.db.retrieve_relay_cursor("replica_1").unwrap(),
+                Some(roots[4])
+            );
+
+            // A restart resumes from the cursor
+            poller.bootstrap().await.unwrap();
+            assert_eq!(
+                poller.db.retrieve_relay_cursor("replica_1").unwrap(),
+                Some(roots[4])
+            );
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn updates_superseded_while_home_index_catches_up_are_not_relayed() {
+        test_utils::run_test_db(|db| async move {
+            let signer = test_signer();
+            let roots: Vec<H256> = (0..6).map(H256::repeat_byte).collect();
+            let updates = sign_chain(&signer, &roots).await;
+
+            // The wiped DB has only re-indexed the home up to r2
+            let replica_root = Arc::new(std::sync::Mutex::new(roots[3]));
+            let poller = Arc::new(poller(
+                db,
+                signer.address().into(),
+                roots[5],
+                replica_root.clone(),
+                &[],
+                false,
+            ));
+            for update in updates[..2].iter() {
+                poller.db.store_update(update).unwrap();
+            }
+            poller.bootstrap().await.unwrap();
+
+            let poll = tokio::spawn({
+                let poller = poller.clone();
+                async move { poller.poll_and_relay_update().await }
+            });
+
+            // Another relayer moves the replica on while the index catches
+            // up past the root the poll is waiting on
+            sleep(Duration::from_millis(100)).await;
+            *replica_root.lock().unwrap() = roots[5];
+            for update in updates[2..].iter() {
+                poller.db.store_update(update).unwrap();
+            }
+
+            // No submission expected: any would fail the mock
+            tokio::time::timeout(Duration::from_secs(5), poll)
+                .await
+                .expect("poll did not finish")
+                .unwrap()
+                .unwrap();
+            assert!(poller
+                .db
+                .retrieve_relay_submission("replica_1", roots[4])
+                .unwrap()
+                .is_none());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn first_update_on_a_fresh_replica_is_relayed() {
+        test_utils::run_test_db(|db| async move {
+            let signer = test_signer();
+            let roots = roots();
+            let updates = sign_chain(&signer, &roots[..2]).await;
+
+            // The replica was deployed before the home's first update
+            let poller = poller(
+                db,
+                signer.address().into(),
+                roots[1],
+                Arc::new(std::sync::Mutex::new(roots[0])),
+                &[(roots[0], roots[1])],
+                false,
+            );
+            poller.db.store_update(&updates[0]).unwrap();
+
+            poller.bootstrap().await.unwrap();
+            assert_eq!(
+                poller.db.retrieve_relay_cursor("replica_1").unwrap(),
+                Some(roots[0])
+            );
+            poller.poll_and_relay_update().await.unwrap();
+            assert!(poller
+                .db
+                .retrieve_relay_submission("replica_1", roots[1])
+                .unwrap()
+                .is_some());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn first_update_on_a_replica_initialized_past_genesis_is_relayed() {
+        test_utils::run_test_db(|db| async move {
+            let signer = test_signer();
+            let roots = roots();
+            let updates = sign_chain(&signer, &roots).await;
+
+            // The replica was deployed once the home reached r2, and
+            // initialized with it
+            let poller = poller(
+                db,
+                signer.address().into(),
+                roots[3],
+                Arc::new(std::sync::Mutex::new(roots[2])),
+                &[(roots[2], roots[3])],
+                false,
+            );
+            for update in updates.iter() {
+                poller.db.store_update(update).unwrap();
+            }
+            poller
+                .replica
+                .db()
+                .store_root_advances(&[RootAdvance {
+                    root: roots[2],
+                    provenance: RootProvenance::Genesis,
+                    block_number: 1,
+                    timestamp: None,
+                    tx_hash: H256::repeat_byte(9),
+                }])
+                .unwrap();
+
+            poller.bootstrap().await.unwrap();
+            assert_eq!(
+                poller.db.retrieve_relay_cursor("replica_1").unwrap(),
+                Some(roots[2])
+            );
+            poller.poll_and_relay_update().await.unwrap();
+            assert!(poller
+                .db
+                .retrieve_relay_submission("replica_1", roots[3])
+                .unwrap()
+                .is_some());
+        })
+        .await
+    }
 }
@@ -0,0 +1,803 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use color_eyre::{eyre::eyre, Result};
+use nomad_base::{CachingHome, NomadDB};
+use nomad_core::Common;
+use rusoto_core::{Region, RusotoError};
+use rusoto_dynamodb::{
+    AttributeValue, DynamoDb, DynamoDbClient, GetItemInput, PutItemError, PutItemInput,
+};
+use tokio::{
+    sync::RwLock,
+    task::JoinHandle,
+    time::{sleep, timeout, Instant},
+};
+use tracing::{info, info_span, instrument::Instrumented, warn, Instrument};
+
+/// Shared flag gating attestation signing. The producer holds a read guard
+/// for the duration of a sign-and-store, so taking the write guard waits for
+/// any in-flight attestation to finish.
+pub(crate) type SigningPermit = Arc<RwLock<bool>>;
+
+/// Heartbeats before a standby may take over at which a holder that could
+/// not renew its lease stops signing. Covers a round timing out plus the
+/// heartbeat sleep after it.
+pub(crate) const SIGNING_MARGIN_HEARTBEATS: u64 = 4;
+
+/// Leader lease record shared by the updater instances
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Lease {
+    /// Instance id of the lease holder
+    holder: String,
+    /// Unix timestamp of the holder's latest heartbeat
+    heartbeat: u64,
+    /// Set when the holder released the lease through a handoff
+    handoff: bool,
+    /// Incremented on every write. Writes are conditional on it.
+    version: u64,
+}
+
+/// Storage shared by all updater instances of a home, holding their lease.
+/// It must be visible to every instance and support a conditional write.
+#[async_trait]
+pub(crate) trait LeaseStore: std::fmt::Debug + Send + Sync {
+    /// Read the current lease, if any
+    async fn retrieve(&self) -> Result<Option<Lease>>;
+
+    /// Store `lease` only if the stored lease is still at version
+    /// `expected`, or if there is none when `expected` is `None`. Returns
+    /// whether `lease` was stored.
+    async fn compare_and_swap(&self, expected: Option<u64>, lease: &Lease) -> Result<bool>;
+}
+
+/// Lease stored in a DynamoDB table keyed by `lease_key`, one item per home
+pub(crate) struct DynamoLeaseStore {
+    client: DynamoDbClient,
+    table: String,
+    key: String,
+}
+
+impl std::fmt::Debug for DynamoLeaseStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynamoLeaseStore")
+            .field("table", &self.table)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+impl DynamoLeaseStore {
+    /// Instantiate a store for the lease of `home` in `table`. The region is
+    /// read from the AWS environment.
+    pub(crate) async fn new(table: &str, home: &str) -> Self {
+        let region: Region = Default::default(); // loads from aws env
+        let client =
+            DynamoDbClient::new_with_client(nomad_core::aws::get_client().await.clone(), region);
+        Self {
+            client,
+            table: table.to_owned(),
+            key: home.to_owned(),
+        }
+    }
+
+    fn string(value: &str) -> AttributeValue {
+        AttributeValue {
+            s: Some(value.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn number(value: u64) -> AttributeValue {
+        AttributeValue {
+            n: Some(value.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn key(&self) -> HashMap<String, AttributeValue> {
+        HashMap::from([("lease_key".to_owned(), Self::string(&self.key))])
+    }
+
+    fn to_item(&self, lease: &Lease) -> HashMap<String, AttributeValue> {
+        let mut item = self.key();
+        item.insert("holder".to_owned(), Self::string(&lease.holder));
+        item.insert("heartbeat".to_owned(), Self::number(lease.heartbeat));
+        item.insert(
+            "handoff".to_owned(),
+            AttributeValue {
+                bool: Some(lease.handoff),
+                ..Default::default()
+            },
+        );
+        item.insert("version".to_owned(), Self::number(lease.version));
+        item
+    }
+
+    fn from_item(item: &HashMap<String, AttributeValue>) -> Result<Lease> {
+        let attribute = |name: &str| {
+            item.get(name)
+                .ok_or_else(|| eyre!("updater lease item is missing {}", name))
+        };
+        let number = |name: &str| -> Result<u64> {
+            attribute(name)?
+                .n
+                .as_deref()
+                .ok_or_else(|| eyre!("updater lease {} is not a number", name))?
+                .parse::<u64>()
+                .map_err(|e| eyre!("invalid updater lease {}: {}", name, e))
+        };
+
+        Ok(Lease {
+            holder: attribute("holder")?
+                .s
+                .clone()
+                .ok_or_else(|| eyre!("updater lease holder is not a string"))?,
+            heartbeat: number("heartbeat")?,
+            handoff: attribute("handoff")?
+                .bool
+                .ok_or_else(|| eyre!("updater lease handoff is not a bool"))?,
+            version: number("version")?,
+        })
+    }
+}
+
+#[async_trait]
+impl LeaseStore for DynamoLeaseStore {
+    async fn retrieve(&self) -> Result<Option<Lease>> {
+        let output = self
+            .client
+            .get_item(GetItemInput {
+                table_name: self.table.clone(),
+                key: self.key(),
+                consistent_read: Some(true),
+                ..Default::default()
+            })
+            .await?;
+
+        output.item.as_ref().map(Self::from_item).transpose()
+    }
+
+    async fn compare_and_swap(&self, expected: Option<u64>, lease: &Lease) -> Result<bool> {
+        let (condition, values) = match expected {
+            None => ("attribute_not_exists(lease_key)", None),
+            Some(version) => (
+                "version = :expected",
+                Some(HashMap::from([(
+                    ":expected".to_owned(),
+                    Self::number(version),
+                )])),
+            ),
+        };
+
+        let result = self
+            .client
+            .put_item(PutItemInput {
+                table_name: self.table.clone(),
+                item: self.to_item(lease),
+                condition_expression: Some(condition.to_owned()),
+                expression_attribute_values: values,
+                ..Default::default()
+            })
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(RusotoError::Service(PutItemError::ConditionalCheckFailed(_))) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Active/standby lease for an updater instance.
+///
+/// The lease holder signs, everyone else only syncs. An active instance
+/// gives the lease up through an explicit handoff: it stops signing, waits
+/// for in-flight attestations and releases the lease with a handoff marker.
+/// A standby only acquires a released lease once its local view matches the
+/// on-chain committed root. Taking over a lease whose heartbeat went stale is
+/// a last resort, after `takeover_seconds`.
+///
+/// Every write is conditional on the version of the lease it was decided
+/// on, so of several instances racing for the lease only one acquires it,
+/// and a holder that was taken over stops signing on its next heartbeat.
+///
+/// Store calls time out after a heartbeat. A holder that has not renewed
+/// its lease for `takeover_seconds` less `SIGNING_MARGIN_HEARTBEATS`
+/// heartbeats stops signing, whether or not the store answers, before a
+/// standby could take the lease over.
+#[derive(Debug, Clone)]
+pub(crate) struct UpdaterLease {
+    store: Arc<dyn LeaseStore>,
+    db: NomadDB,
+    home: Arc<CachingHome>,
+    instance_id: String,
+    heartbeat_seconds: u64,
+    takeover_seconds: u64,
+    signing: SigningPermit,
+    handoff_requested: Arc<AtomicBool>,
+    /// Start of the round that stored our latest heartbeat
+    renewed_at: Arc<Mutex<Option<Instant>>>,
+}
+
+impl UpdaterLease {
+    pub(crate) fn new(
+        store: Arc<dyn LeaseStore>,
+        db: NomadDB,
+        home: Arc<CachingHome>,
+        instance_id: String,
+        heartbeat_seconds: u64,
+        takeover_seconds: u64,
+    ) -> Self {
+        Self {
+            store,
+            db,
+            home,
+            instance_id,
+            heartbeat_seconds,
+            takeover_seconds,
+            signing: Default::default(),
+            handoff_requested: Default::default(),
+            renewed_at: Default::default(),
+        }
+    }
+
+    /// Permit gating attestation signing for this instance
+    pub(crate) fn signing(&self) -> SigningPermit {
+        self.signing.clone()
+    }
+
+    /// Ask this instance to hand its lease off on the next heartbeat
+    pub(crate) fn request_handoff(&self) {
+        self.handoff_requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Admin routes of the lease. `POST /handoff` requests a handoff.
+    pub(crate) fn routes(
+        &self,
+    ) -> impl warp::Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        use warp::Filter;
+
+        let lease = self.clone();
+        warp::post().and(warp::path!("handoff")).map(move || {
+            lease.request_handoff();
+            warp::reply::with_status("handoff requested", warp::http::StatusCode::ACCEPTED)
+        })
+    }
+
+    fn store_timeout(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_seconds)
+    }
+
+    /// Read the current lease, failing if the store does not answer within
+    /// a heartbeat
+    async fn retrieve_lease(&self) -> Result<Option<Lease>> {
+        timeout(self.store_timeout(), self.store.retrieve())
+            .await
+            .map_err(|_| eyre!("timed out reading the updater lease"))?
+    }
+
+    /// Store our lease in place of the one at version `expected`. Returns
+    /// whether it was stored, failing if the store does not answer within a
+    /// heartbeat.
+    async fn swap_lease(
+        &self,
+        expected: Option<u64>,
+        heartbeat: u64,
+        handoff: bool,
+    ) -> Result<bool> {
+        let lease = Lease {
+            holder: self.instance_id.clone(),
+            heartbeat,
+            handoff,
+            version: expected.map_or(0, |version| version + 1),
+        };
+        timeout(
+            self.store_timeout(),
+            self.store.compare_and_swap(expected, &lease),
+        )
+        .await
+        .map_err(|_| eyre!("timed out writing the updater lease"))?
+    }
+
+    async fn set_signing(&self, enabled: bool) {
+        let mut signing = self.signing.write().await;
+        if *signing != enabled {
+            info!(
+                instance_id = self.instance_id.as_str(),
+                enabled, "Updater signing enabled: {}", enabled
+            );
+            *signing = enabled;
+        }
+    }
+
+    /// Stop signing if our lease was last renewed too long ago. A standby
+    /// may take over `takeover_seconds` after our last heartbeat, so we stop
+    /// `SIGNING_MARGIN_HEARTBEATS` heartbeats before that.
+    async fn expire_signing(&self) {
+        let deadline = Duration::from_secs(
+            self.takeover_seconds
+                .saturating_sub(SIGNING_MARGIN_HEARTBEATS * self.heartbeat_seconds),
+        );
+        let renewed_at = *self.renewed_at.lock().expect("!lock");
+        if renewed_at.map_or(false, |at| at.elapsed() < deadline) || !*self.signing.read().await {
+            return;
+        }
+
+        warn!(
+            instance_id = self.instance_id.as_str(),
+            "Updater lease was not renewed in time. Stopping signing."
+        );
+        self.set_signing(false).await;
+    }
+
+    /// Check that there is no attestation gap: the local view of the latest
+    /// root must match the on-chain committed root.
+    async fn verify_no_gap(&self) -> Result<bool> {
+        let local = self.db.retrieve_latest_root()?.unwrap_or_default();
+        let on_chain = self.home.committed_root().await?;
+
+        if local != on_chain {
+            warn!(
+                instance_id = self.instance_id.as_str(),
+                local = ?local,
+                on_chain = ?on_chain,
+                "Local latest root does not match on-chain committed root. Not acquiring updater lease yet."
+            );
+        }
+
+        Ok(local == on_chain)
+    }
+
+    /// Acquire the lease in place of the one at version `expected`, in the
+    /// round that started at `started`
+    async fn try_acquire(
+        &self,
+        expected: Option<u64>,
+        now: u64,
+        started: Instant,
+        reason: &str,
+    ) -> Result<()> {
+        if !self.verify_no_gap().await? {
+            return Ok(());
+        }
+
+        if !self.swap_lease(expected, now, false).await? {
+            info!(
+                instance_id = self.instance_id.as_str(),
+                reason, "Another instance acquired the updater lease first"
+            );
+            return Ok(());
+        }
+
+        info!(
+            instance_id = self.instance_id.as_str(),
+            reason, "Acquired updater lease: {}", reason
+        );
+        *self.renewed_at.lock().expect("!lock") = Some(started);
+        self.set_signing(true).await;
+        Ok(())
+    }
+
+    /// Run a single round of the lease protocol at time `now`
+    ///
+    /// CancellationSafe: a handoff request is cleared only once the released
+    /// lease is stored, so a round cancelled while waiting for in-flight
+    /// attestations hands off on the next one.
+    pub(crate) async fn step(&self, now: u64) -> Result<()> {
+        let started = Instant::now();
+        let lease = match self.retrieve_lease().await? {
+            Some(lease) => lease,
+            None => {
+                return self
+                    .try_acquire(None, now, started, "no existing lease")
+                    .await
+            }
+        };
+
+        let ours = lease.holder == self.instance_id;
+        let stale = now.saturating_sub(lease.heartbeat) > self.takeover_seconds;
+        let expected = Some(lease.version);
+
+        match (ours, lease.handoff) {
+            (true, false) => {
+                if self.handoff_requested.load(Ordering::SeqCst) {
+                    // Taking the write guard waits for in-flight attestations
+                    self.set_signing(false).await;
+                    if self.swap_lease(expected, now, true).await? {
+                        info!(
+                            instance_id = self.instance_id.as_str(),
+                            "Released updater lease for handoff"
+                        );
+                    }
+                    // Either released, or no longer ours to hand off
+                    self.handoff_requested.store(false, Ordering::SeqCst);
+                } else if self.swap_lease(expected, now, false).await? {
+                    *self.renewed_at.lock().expect("!lock") = Some(started);
+                    self.set_signing(true).await;
+                } else {
+                    warn!(
+                        instance_id = self.instance_id.as_str(),
+                        "Updater lease changed since it was read. Stopping signing."
+                    );
+                    self.set_signing(false).await;
+                }
+            }
+            (true, true) => {
+                self.set_signing(false).await;
+                if stale {
+                    self.try_acquire(expected, now, started, "handoff was not picked up")
+                        .await?;
+                }
+            }
+            (false, true) => {
+                self.set_signing(false).await;
+                self.try_acquire(expected, now, started, "handoff").await?;
+            }
+            (false, false) => {
+                self.set_signing(false).await;
+                if stale {
+                    warn!(
+                        instance_id = self.instance_id.as_str(),
+                        holder = lease.holder.as_str(),
+                        last_heartbeat = lease.heartbeat,
+                        "Updater lease heartbeat is stale. Attempting takeover."
+                    );
+                    self.try_acquire(expected, now, started, "stale heartbeat")
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Spawn the lease heartbeat task
+    pub(crate) fn spawn(self) -> Instrumented<JoinHandle<Result<()>>> {
+        let span = info_span!("UpdaterLease", instance_id = self.instance_id.as_str());
+
+        tokio::spawn(async move {
+            let heartbeat = Duration::from_secs(self.heartbeat_seconds);
+            loop {
+                // A round makes at most two store calls, each timing out
+                // after a heartbeat
+                match timeout(2 * heartbeat, self.step(unix_now())).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => warn!(
+                        instance_id = self.instance_id.as_str(),
+                        error = %e,
+                        "Updater lease round failed. Retrying on the next heartbeat."
+                    ),
+                    Err(_) => warn!(
+                        instance_id = self.instance_id.as_str(),
+                        "Updater lease round timed out. Retrying on the next heartbeat."
+                    ),
+                }
+                self.expire_signing().await;
+
+                sleep(heartbeat).await;
+            }
+        })
+        .instrument(span)
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("!timestamp")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ethers::core::types::H256;
+    use ethers::signers::LocalWallet;
+    use nomad_base::{
+        chains::PageSettings, ContractSync, ContractSyncMetrics, CoreMetrics, HomeIndexers,
+        IndexSettings,
+    };
+    use nomad_core::Update;
+    use nomad_test::mocks::{MockHomeContract, MockIndexer};
+    use nomad_test::test_utils;
+    use std::sync::Mutex;
+
+    const HEARTBEAT: u64 = 10;
+    const TAKEOVER: u64 = 300;
+
+    /// In-memory lease shared by the instances of a test. Reads yield, so
+    /// instances stepped concurrently interleave between read and write.
+    #[derive(Debug, Default)]
+    struct MemoryLeaseStore(Mutex<Option<Lease>>);
+
+    #[async_trait]
+    impl LeaseStore for MemoryLeaseStore {
+        async fn retrieve(&self) -> Result<Option<Lease>> {
+            let lease = self.0.lock().unwrap().clone();
+            tokio::task::yield_now().await;
+            Ok(lease)
+        }
+
+        async fn compare_and_swap(&self, expected: Option<u64>, lease: &Lease) -> Result<bool> {
+            let mut stored = self.0.lock().unwrap();
+            if stored.as_ref().map(|lease| lease.version) != expected {
+                return Ok(false);
+            }
+            *stored = Some(lease.clone());
+            Ok(true)
+        }
+    }
+
+    /// Lease store that stops answering once `stalled` is set
+    #[derive(Debug, Default)]
+    struct StallingLeaseStore {
+        lease: MemoryLeaseStore,
+        stalled: AtomicBool,
+    }
+
+    impl StallingLeaseStore {
+        async fn stall_if_set(&self) {
+            if self.stalled.load(Ordering::SeqCst) {
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+
+    #[async_trait]
+    impl LeaseStore for StallingLeaseStore {
+        async fn retrieve(&self) -> Result<Option<Lease>> {
+            self.stall_if_set().await;
+            self.lease.retrieve().await
+        }
+
+        async fn compare_and_swap(&self, expected: Option<u64>, lease: &Lease) -> Result<bool> {
+            self.stall_if_set().await;
+            self.lease.compare_and_swap(expected, lease).await
+        }
+    }
+
+    fn updater_instance<S: LeaseStore + 'static>(
+        store: &Arc<S>,
+        db: &NomadDB,
+        instance_id: &str,
+        committed_root: H256,
+    ) -> UpdaterLease {
+        let metrics = Arc::new(
+            CoreMetrics::new(
+                "updater_lease_test",
+                "home",
+                None,
+                Arc::new(prometheus::Registry::new()),
+            )
+            .expect("could not make metrics"),
+        );
+
+        let mut mock_home = MockHomeContract::new();
+        mock_home.expect__name().return_const("home_1".to_owned());
+        mock_home
+            .expect__committed_root()
+            .times(..)
+            .returning(move || Ok(committed_root));
+
+        let home_indexer: Arc<HomeIndexers> = Arc::new(MockIndexer::new().into());
+        let home_sync = ContractSync::new(
+            "updater".to_owned(),
+            "home_1".to_owned(),
+            "".to_owned(),
+            db.clone(),
+            home_indexer,
+            IndexSettings::default(),
+            PageSettings::default(),
+            Default::default(),
+            ContractSyncMetrics::new(metrics),
+        );
+        let home: Arc<CachingHome> =
+            CachingHome::new(mock_home.into(), home_sync, db.clone()).into();
+
+        UpdaterLease::new(
+            store.clone(),
+            db.clone(),
+            home,
+            instance_id.to_owned(),
+            HEARTBEAT,
+            TAKEOVER,
+        )
+    }
+
+    #[tokio::test]
+    async fn lease_item_roundtrips() {
+        let store = DynamoLeaseStore {
+            client: DynamoDbClient::new(Region::UsEast1),
+            table: "updater-leases".to_owned(),
+            key: "home_1".to_owned(),
+        };
+        let lease = Lease {
+            holder: "updater-a".to_owned(),
+            heartbeat: 1234,
+            handoff: true,
+            version: 7,
+        };
+
+        let item = store.to_item(&lease);
+        assert_eq!(item["lease_key"].s.as_deref(), Some("home_1"));
+        assert_eq!(DynamoLeaseStore::from_item(&item).unwrap(), lease);
+
+        let mut missing = item;
+        missing.remove("version");
+        assert!(DynamoLeaseStore::from_item(&missing).is_err());
+    }
+
+    #[tokio::test]
+    async fn it_hands_off_between_active_and_standby() {
+        test_utils::run_test_db(|db| async move {
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+
+            let committed_root = H256::repeat_byte(1);
+            let update = Update {
+                home_domain: 1,
+                previous_root: H256::zero(),
+                new_root: committed_root,
+            }
+            .sign_with(&signer)
+            .await
+            .expect("!sign");
+
+            let nomad_db = NomadDB::new("home_1", db);
+            nomad_db.store_latest_update(&update).unwrap();
+
+            let store = Arc::new(MemoryLeaseStore::default());
+            let active = updater_instance(&store, &nomad_db, "updater-a", committed_root);
+            let standby = updater_instance(&store, &nomad_db, "updater-b", committed_root);
+            let lagging = updater_instance(&store, &nomad_db, "updater-c", H256::repeat_byte(2));
+
+            // First instance up acquires the lease
+            active.step(100).await.unwrap();
+            standby.step(101).await.unwrap();
+            assert!(*active.signing().read().await);
+            assert!(!*standby.signing().read().await);
+
+            // Handoff: active stops signing and releases with a marker
+            active.request_handoff();
+            active.step(110).await.unwrap();
+            assert!(!*active.signing().read().await);
+            assert!(store.retrieve().await.unwrap().unwrap().handoff);
+
+            // An instance whose view does not match chain state cannot take it
+            lagging.step(111).await.unwrap();
+            assert!(!*lagging.signing().read().await);
+
+            // Standby verifies no gap and acquires
+            standby.step(112).await.unwrap();
+            active.step(113).await.unwrap();
+            assert!(*standby.signing().read().await);
+            assert!(!*active.signing().read().await);
+
+            // Heartbeat going stale only triggers takeover after the delay
+            active.step(112 + TAKEOVER).await.unwrap();
+            assert!(!*active.signing().read().await);
+            active.step(112 + TAKEOVER + 1).await.unwrap();
+            assert!(*active.signing().read().await);
+
+            // Former active notices it lost the lease and stops signing
+            standby.step(112 + TAKEOVER + 2).await.unwrap();
+            assert!(!*standby.signing().read().await);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn cancelled_handoff_is_not_lost() {
+        test_utils::run_test_db(|db| async move {
+            let committed_root = H256::zero();
+            let nomad_db = NomadDB::new("home_1", db);
+            let store = Arc::new(MemoryLeaseStore::default());
+            let active = updater_instance(&store, &nomad_db, "updater-a", committed_root);
+
+            active.step(100).await.unwrap();
+            assert!(*active.signing().read().await);
+
+            // An attestation is in flight, so the handoff waits on it until
+            // the round is cancelled
+            let in_flight = active.signing.clone();
+            let attestation = in_flight.read().await;
+            active.request_handoff();
+            let cancelled = tokio::time::timeout(Duration::from_millis(50), active.step(110)).await;
+            assert!(cancelled.is_err());
+            assert!(!store.retrieve().await.unwrap().unwrap().handoff);
+
+            // The next round hands off once the attestation is done
+            drop(attestation);
+            active.step(120).await.unwrap();
+            assert!(!*active.signing().read().await);
+            let lease = store.retrieve().await.unwrap().unwrap();
+            assert!(lease.handoff);
+            assert_eq!(lease.heartbeat, 120);
+        })
+        .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stalled_store_stops_signing_before_takeover() {
+        test_utils::run_test_db(|db| async move {
+            let committed_root = H256::zero();
+            let nomad_db = NomadDB::new("home_1", db);
+            let store = Arc::new(StallingLeaseStore::default());
+            let active = updater_instance(&store, &nomad_db, "updater-a", committed_root);
+            let signing = active.signing();
+            let task = active.spawn();
+
+            // The first round acquires, the next ones renew
+            sleep(Duration::from_secs(3 * HEARTBEAT)).await;
+            assert!(*signing.read().await);
+
+            // The store stops answering right after a renewal. A standby may
+            // take over `TAKEOVER` seconds after it.
+            store.stalled.store(true, Ordering::SeqCst);
+            let renewed = store.lease.retrieve().await.unwrap().unwrap();
+            sleep(Duration::from_secs(TAKEOVER - 2 * HEARTBEAT)).await;
+            assert!(!*signing.read().await);
+            assert_eq!(store.lease.retrieve().await.unwrap().unwrap(), renewed);
+
+            // The task keeps retrying rather than exiting on timeouts
+            let task = task.into_inner();
+            assert!(!task.is_finished());
+            task.abort();
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn only_one_racing_instance_acquires() {
+        test_utils::run_test_db(|db| async move {
+            let committed_root = H256::zero();
+            let nomad_db = NomadDB::new("home_1", db);
+            let store = Arc::new(MemoryLeaseStore::default());
+            let a = updater_instance(&store, &nomad_db, "updater-a", committed_root);
+            let b = updater_instance(&store, &nomad_db, "updater-b", committed_root);
+
+            // Both read an empty lease before either writes
+            let (res_a, res_b) = tokio::join!(a.step(100), b.step(100));
+            res_a.unwrap();
+            res_b.unwrap();
+            assert!(*a.signing().read().await ^ *b.signing().read().await);
+
+            let (holder, standby) = if *a.signing().read().await {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            assert_eq!(
+                store.retrieve().await.unwrap().unwrap().holder,
+                holder.instance_id
+            );
+
+            // The holder goes quiet. Two standbys see the stale heartbeat at
+            // once and race to take over.
+            let c = updater_instance(&store, &nomad_db, "updater-c", committed_root);
+            let now = 100 + TAKEOVER + 1;
+            let (res_standby, res_c) = tokio::join!(standby.step(now), c.step(now));
+            res_standby.unwrap();
+            res_c.unwrap();
+            assert!(*standby.signing().read().await ^ *c.signing().read().await);
+
+            // The former holder's heartbeat is refused and it stops signing
+            holder.step(now + 1).await.unwrap();
+            assert!(!*holder.signing().read().await);
+            let lease = store.retrieve().await.unwrap().unwrap();
+            assert_ne!(lease.holder, holder.instance_id);
+        })
+        .await
+    }
+}
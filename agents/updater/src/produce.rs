@@ -1,20 +1,94 @@
-use ethers::core::types::H256;
-use prometheus::IntCounter;
+use ethers::core::types::{H256, U256};
+use prometheus::{HistogramVec, IntCounter, IntGauge};
 use std::{sync::Arc, time::Duration};
 
 use color_eyre::Result;
-use nomad_base::{AttestationSigner, CachingHome, NomadDB, UpdaterError};
-use nomad_core::{Common, Home, SignedUpdate};
-use tokio::{task::JoinHandle, time::sleep};
-use tracing::{debug, error, info, info_span, instrument::Instrumented, Instrument};
+use nomad_base::{AttestationSigner, AuditEvent, CachingHome, NomadDB, Retry, UpdaterError};
+use nomad_core::{utils::abbreviated_hex, Common, Home, SignedUpdate, Update, GENESIS_ROOT};
+use tokio::{task::JoinHandle, time::Instant};
+use tracing::{debug, error, info, info_span, instrument::Instrumented, warn, Instrument};
+
+use crate::lease::SigningPermit;
+
+/// Wait after new dispatches before checking for an update, so a burst of
+/// dispatches is checked once
+const DISPATCH_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Signing latency label of checks run every interval
+const INTERVAL_TRIGGER: &str = "interval";
+/// Signing latency label of checks run after new dispatches
+const DISPATCH_TRIGGER: &str = "dispatch";
+
+/// Sanity bound on the home's queue length. A queue above the bound is
+/// treated as a backlog, in which only the queue end is attested.
+#[derive(Debug, Clone)]
+pub(crate) struct QueueBacklog {
+    max_length: Option<u64>,
+    active: IntGauge,
+    length: IntGauge,
+}
+
+impl QueueBacklog {
+    pub(crate) fn new(max_length: Option<u64>, active: IntGauge, length: IntGauge) -> Self {
+        Self {
+            max_length,
+            active,
+            length,
+        }
+    }
+
+    /// Check whether the suggested update may be signed. Always true below
+    /// the bound. In backlog mode, the suggested new root must be the queue
+    /// end so the whole backlog is committed at once.
+    async fn permits(&self, home: &CachingHome, suggested: &Update) -> Result<bool> {
+        let max_length = match self.max_length {
+            Some(max_length) => max_length,
+            None => return Ok(true),
+        };
+
+        let queue_length = home.queue_length().await?;
+        if queue_length <= U256::from(max_length) {
+            self.active.set(0);
+            self.length.set(0);
+            return Ok(true);
+        }
+
+        self.active.set(1);
+        self.length
+            .set(queue_length.min(U256::from(i64::MAX as u64)).as_u64() as i64);
+
+        let queue_end = home.queue_end().await?;
+        warn!(
+            queue_length = %queue_length,
+            max_queue_length = max_length,
+            queue_end = ?queue_end,
+            "Home queue exceeds sanity bound. Attesting only the queue end, committing the whole backlog at once."
+        );
+
+        if suggested.new_root != queue_end {
+            warn!(
+                suggested = ?suggested.new_root,
+                queue_end = ?queue_end,
+                "Suggested root is not the queue end. Skipping update."
+            );
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+}
 
 #[derive(Debug)]
 pub(crate) struct UpdateProducer {
     home: Arc<CachingHome>,
     db: NomadDB,
     signer: Arc<AttestationSigner>,
-    interval_seconds: u64,
+    signing: SigningPermit,
+    retry: Retry,
     signed_attestation_count: IntCounter,
+    queue_backlog: QueueBacklog,
+    dispatch_trigger: bool,
+    signing_latency: HistogramVec,
 }
 
 impl UpdateProducer {
@@ -22,22 +96,30 @@ impl UpdateProducer {
         home: Arc<CachingHome>,
         db: NomadDB,
         signer: Arc<AttestationSigner>,
-        interval_seconds: u64,
+        signing: SigningPermit,
+        retry: Retry,
         signed_attestation_count: IntCounter,
+        queue_backlog: QueueBacklog,
+        dispatch_trigger: bool,
+        signing_latency: HistogramVec,
     ) -> Self {
         Self {
             home,
             db,
             signer,
-            interval_seconds,
+            signing,
+            retry,
             signed_attestation_count,
+            queue_backlog,
+            dispatch_trigger,
+            signing_latency,
         }
     }
 
     fn find_latest_root(&self) -> Result<H256> {
-        // If db latest root is empty, this will produce `H256::default()`
-        // which is equal to `H256::zero()`
-        Ok(self.db.retrieve_latest_root()?.unwrap_or_default())
+        // Before the home's first update is indexed, the home is at the
+        // genesis root
+        Ok(self.db.retrieve_latest_root()?.unwrap_or(GENESIS_ROOT))
     }
 
     /// Store a pending update in the DB for potential submission.
@@ -67,73 +149,459 @@ impl UpdateProducer {
         Ok(())
     }
 
+    /// Whether the pending block suggests an update this updater would sign.
+    /// Only a hint to check now: signing is always based on the confirmed
+    /// suggestion.
+    async fn pending_update_ready(&self) -> Result<bool> {
+        if !*self.signing.read().await {
+            return Ok(false);
+        }
+
+        let suggested = match self.home.produce_pending_update().await? {
+            Some(suggested) => suggested,
+            None => return Ok(false),
+        };
+
+        Ok(suggested.previous_root == self.find_latest_root()?
+            && self
+                .db
+                .retrieve_produced_update(suggested.previous_root)?
+                .is_none())
+    }
+
+    /// Check the home for a confirmed update and sign it if it builds on our
+    /// latest root. Returns whether an update was signed.
+    async fn produce_once(&self) -> Result<bool> {
+        // Hold the permit until any update we sign has been stored,
+        // so a lease handoff never races an in-flight attestation
+        let signing = self.signing.read().await;
+        if !*signing {
+            debug!("Updater instance is on standby. Not signing.");
+            return Ok(false);
+        }
+
+        // Get home indexer's latest seen update from home. This call
+        // will only return a root from an update that is confirmed in
+        // the chain, as the updater indexer's timelag will ensure this.
+        let current_root = self.find_latest_root()?;
+
+        // The produced update is also confirmed state in the chain, as
+        // updater home timelag ensures this.
+        if let Some(suggested) = self.home.produce_update().await? {
+            if suggested.previous_root != current_root {
+                // This either indicates that the indexer is catching
+                // up or that the chain is awaiting a new update. We
+                // should ignore it.
+                debug!(
+                    local = ?suggested.previous_root,
+                    remote = ?current_root,
+                    "Local root not equal to chain root. Skipping update."
+                );
+                return Ok(false);
+            }
+
+            // Ensure we have not already signed a conflicting update.
+            // Ignore suggested if we have.
+            if let Some(existing) = self.db.retrieve_produced_update(suggested.previous_root)? {
+                if existing.update.new_root != suggested.new_root {
+                    info!("Updater ignoring conflicting suggested update. Indicates chain awaiting already produced update. Existing update: {:?}. Suggested conflicting update: {:?}.", &existing, &suggested);
+                }
+
+                return Ok(false);
+            }
+
+            // A runaway queue is only attested at its end
+            if !self.queue_backlog.permits(&self.home, &suggested).await? {
+                return Ok(false);
+            }
+
+            // If the suggested matches our local view, sign an update
+            // and store it as locally produced
+            self.db.audit(AuditEvent::SignUpdate {
+                home_domain: suggested.home_domain,
+                previous_root: suggested.previous_root,
+                new_root: suggested.new_root,
+            })?;
+            let signed = suggested.sign_with(self.signer.as_ref()).await?;
+
+            self.signed_attestation_count.inc();
+
+            let hex_signature = abbreviated_hex(&signed.signature.to_vec());
+            info!(
+                previous_root = ?signed.update.previous_root,
+                new_root = ?signed.update.new_root,
+                hex_signature = %hex_signature,
+                "Storing new update in DB for broadcast"
+            );
+
+            // Once we have stored signed update in db, updater can
+            // never produce a double update building off the same
+            // previous root (we check db each time we produce new
+            // signed update)
+            self.store_produced_update(&signed)?;
+            Ok(true)
+        } else {
+            let committed_root = self.home.committed_root().await?;
+            info!(
+                count = ?self.home.cached_count(),
+                "No updates to sign. Waiting for new root building off of current root {:?}.",
+                committed_root
+            );
+            Ok(false)
+        }
+    }
+
     /// Spawn the updater's produce task.
     ///
     /// Note that all data retrieved from either contract calls or the
     /// updater's db are confirmed state in the chain, as both indexed data and
     /// contract state are retrieved with a timelag.
+    ///
+    /// Updates are checked for every interval. With the dispatch trigger on,
+    /// they are also checked shortly after the home indexer stores new
+    /// dispatches, if the pending block suggests an update. The pending
+    /// suggestion is never signed; the confirmed one is read again first.
+    /// The task ends once the retry's cancellation token is cancelled.
+    ///
+    /// CancellationSafe: the signed update is stored after the last await,
+    /// and the store re-checks for a conflicting update.
     pub(crate) fn spawn(self) -> Instrumented<JoinHandle<Result<()>>> {
         let span = info_span!("UpdateProducer");
         tokio::spawn(async move {
+            let dispatches = self.home.dispatches();
+            let mut next_check = Instant::now() + self.retry.delay(1);
+            // When the first dispatch not yet covered by a signed update was
+            // stored
+            let mut waiting_since: Option<Instant> = None;
+
             loop {
-                // We sleep at the top to make continues work fine
-                sleep(Duration::from_secs(self.interval_seconds)).await;
-
-                // Get home indexer's latest seen update from home. This call 
-                // will only return a root from an update that is confirmed in 
-                // the chain, as the updater indexer's timelag will ensure this.
-                let current_root = self.find_latest_root()?;
-
-                // The produced update is also confirmed state in the chain, as 
-                // updater home timelag ensures this.
-                if let Some(suggested) = self.home.produce_update().await? {
-                    if suggested.previous_root != current_root {
-                        // This either indicates that the indexer is catching
-                        // up or that the chain is awaiting a new update. We 
-                        // should ignore it.
-                        debug!(
-                            local = ?suggested.previous_root,
-                            remote = ?current_root,
-                            "Local root not equal to chain root. Skipping update."
-                        );
-                        continue;
+                let until_check = next_check.saturating_duration_since(Instant::now());
+                let trigger = tokio::select! {
+                    waited = self.retry.wait(until_check) => {
+                        if !waited {
+                            return Ok(());
+                        }
+                        next_check = Instant::now() + self.retry.delay(1);
+                        INTERVAL_TRIGGER
                     }
-
-                    // Ensure we have not already signed a conflicting update.
-                    // Ignore suggested if we have.
-                    if let Some(existing) = self.db.retrieve_produced_update(suggested.previous_root)? {
-                        if existing.update.new_root != suggested.new_root {
-                            info!("Updater ignoring conflicting suggested update. Indicates chain awaiting already produced update. Existing update: {:?}. Suggested conflicting update: {:?}.", &existing, &suggested);
+                    _ = dispatches.notified() => {
+                        waiting_since.get_or_insert_with(Instant::now);
+                        if !self.dispatch_trigger {
+                            continue;
                         }
 
-                        continue;
+                        // Check once for a burst of dispatches
+                        if !self.retry.wait(DISPATCH_DEBOUNCE).await {
+                            return Ok(());
+                        }
+                        if !self.pending_update_ready().await? {
+                            continue;
+                        }
+                        DISPATCH_TRIGGER
                     }
+                };
 
-                    // If the suggested matches our local view, sign an update
-                    // and store it as locally produced
-                    let signed = suggested.sign_with(self.signer.as_ref()).await?;
-
-                    self.signed_attestation_count.inc();
-
-                    let hex_signature = format!("0x{}", hex::encode(signed.signature.to_vec()));
-                    info!(
-                        previous_root = ?signed.update.previous_root,
-                        new_root = ?signed.update.new_root,
-                        hex_signature = %hex_signature,
-                        "Storing new update in DB for broadcast"
-                    );
-
-                    // Once we have stored signed update in db, updater can 
-                    // never produce a double update building off the same 
-                    // previous root (we check db each time we produce new 
-                    // signed update)
-                    self.store_produced_update(&signed)?
-                } else {
-                    let committed_root = self.home.committed_root().await?;
-                    info!("No updates to sign. Waiting for new root building off of current root {:?}.", committed_root);
+                if self.produce_once().await? {
+                    if let Some(since) = waiting_since.take() {
+                        self.signing_latency
+                            .with_label_values(&[trigger, self.home.name(), "updater"])
+                            .observe(since.elapsed().as_secs_f64());
+                    }
                 }
             }
         })
         .instrument(span)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ethers::signers::LocalWallet;
+    use nomad_base::{
+        chains::PageSettings, ContractSync, ContractSyncMetrics, CoreMetrics, HomeIndexers,
+        IndexSettings,
+    };
+    use nomad_test::mocks::{MockHomeContract, MockIndexer};
+    use nomad_test::test_utils;
+    use prometheus::HistogramOpts;
+    use tokio::{sync::RwLock, time::sleep};
+
+    fn caching_home(db: &NomadDB, mut mock_home: MockHomeContract) -> CachingHome {
+        let metrics = Arc::new(
+            CoreMetrics::new(
+                "updater_produce_test",
+                "home",
+                None,
+                Arc::new(prometheus::Registry::new()),
+            )
+            .expect("could not make metrics"),
+        );
+
+        mock_home.expect__name().return_const("home_1".to_owned());
+
+        let home_indexer: Arc<HomeIndexers> = Arc::new(MockIndexer::new().into());
+        let home_sync = ContractSync::new(
+            "updater".to_owned(),
+            "home_1".to_owned(),
+            "".to_owned(),
+            db.clone(),
+            home_indexer,
+            IndexSettings::default(),
+            PageSettings::default(),
+            Default::default(),
+            ContractSyncMetrics::new(metrics),
+        );
+        CachingHome::new(mock_home.into(), home_sync, db.clone())
+    }
+
+    fn home_with_queue(db: &NomadDB, queue_length: u64, queue_end: H256) -> CachingHome {
+        let mut mock_home = MockHomeContract::new();
+        mock_home
+            .expect__queue_length()
+            .times(..)
+            .returning(move || Ok(queue_length.into()));
+        mock_home
+            .expect__queue_end()
+            .times(..)
+            .returning(move || Ok(queue_end));
+        caching_home(db, mock_home)
+    }
+
+    fn backlog(max_length: Option<u64>) -> QueueBacklog {
+        QueueBacklog::new(
+            max_length,
+            IntGauge::new("queue_backlog_active", "test").unwrap(),
+            IntGauge::new("queue_backlog_length", "test").unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn backlog_mode_only_attests_queue_end() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let queue_end = H256::repeat_byte(9);
+            let to_end = Update {
+                home_domain: 1,
+                previous_root: H256::zero(),
+                new_root: queue_end,
+            };
+            let to_middle = Update {
+                new_root: H256::repeat_byte(1),
+                ..to_end
+            };
+
+            // Below the bound any suggestion is signed
+            let home = home_with_queue(&db, 10, queue_end);
+            let guard = backlog(Some(10));
+            assert!(guard.permits(&home, &to_middle).await.unwrap());
+            assert_eq!(guard.active.get(), 0);
+
+            // Unbounded queues are never checked
+            assert!(backlog(None).permits(&home, &to_middle).await.unwrap());
+
+            // Above the bound only the queue end is signed
+            let home = home_with_queue(&db, 20_000, queue_end);
+            assert!(!guard.permits(&home, &to_middle).await.unwrap());
+            assert!(guard.permits(&home, &to_end).await.unwrap());
+            assert_eq!(guard.active.get(), 1);
+            assert_eq!(guard.length.get(), 20_000);
+        })
+        .await
+    }
+
+    fn producer(
+        db: &NomadDB,
+        home: CachingHome,
+        interval_seconds: u64,
+        dispatch_trigger: bool,
+    ) -> UpdateProducer {
+        let signer: LocalWallet =
+            "1111111111111111111111111111111111111111111111111111111111111111"
+                .parse()
+                .unwrap();
+        UpdateProducer::new(
+            Arc::new(home),
+            db.clone(),
+            Arc::new(signer.into()),
+            Arc::new(RwLock::new(true)),
+            Retry::fixed(Duration::from_secs(interval_seconds)),
+            IntCounter::new("signed_attestation_count", "test").unwrap(),
+            backlog(None),
+            dispatch_trigger,
+            HistogramVec::new(
+                HistogramOpts::new("update_signing_latency_seconds", "test"),
+                &["trigger", "network", "agent"],
+            )
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn it_signs_only_non_empty_suggestions() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+
+            // An empty queue suggests nothing
+            let mut mock_home = MockHomeContract::new();
+            mock_home
+                .expect__produce_update()
+                .times(1)
+                .returning(|| Ok(None));
+            mock_home
+                .expect__committed_root()
+                .times(1)
+                .returning(|| Ok(H256::zero()));
+            let idle = producer(&db, caching_home(&db, mock_home), 3600, false);
+            assert!(!idle.produce_once().await.unwrap());
+            assert!(db.retrieve_produced_update(H256::zero()).unwrap().is_none());
+
+            // A non-empty queue suggests an update to its end
+            let update = Update {
+                home_domain: 1,
+                previous_root: H256::zero(),
+                new_root: H256::repeat_byte(1),
+            };
+            let mut mock_home = MockHomeContract::new();
+            mock_home
+                .expect__produce_update()
+                .times(1)
+                .returning(move || Ok(Some(update)));
+            let busy = producer(&db, caching_home(&db, mock_home), 3600, false);
+            assert!(busy.produce_once().await.unwrap());
+
+            let signed = db.retrieve_produced_update(H256::zero()).unwrap().unwrap();
+            assert_eq!(signed.update, update);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn dispatch_trigger_signs_without_waiting_for_interval() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let update = Update {
+                home_domain: 1,
+                previous_root: H256::zero(),
+                new_root: H256::repeat_byte(1),
+            };
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home
+                .expect__produce_pending_update()
+                .times(1)
+                .returning(move || Ok(Some(update)));
+            mock_home
+                .expect__produce_update()
+                .times(1)
+                .returning(move || Ok(Some(update)));
+            let home = caching_home(&db, mock_home);
+            let dispatches = home.dispatches();
+
+            let producer = producer(&db, home, 3600, true);
+            let latency = producer.signing_latency.clone();
+            let task = producer.spawn();
+
+            dispatches.notify_one();
+            let signed = tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    if let Some(signed) = db.retrieve_produced_update(H256::zero()).unwrap() {
+                        return signed;
+                    }
+                    sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .expect("dispatch did not trigger signing");
+            task.into_inner().abort();
+
+            assert_eq!(signed.update, update);
+            let observed = latency.with_label_values(&[DISPATCH_TRIGGER, "home_1", "updater"]);
+            assert_eq!(observed.get_sample_count(), 1);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn pending_suggestion_alone_is_not_signed() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let update = Update {
+                home_domain: 1,
+                previous_root: H256::zero(),
+                new_root: H256::repeat_byte(1),
+            };
+
+            // The pending block suggests an update the confirmed one does
+            // not have yet
+            let mut mock_home = MockHomeContract::new();
+            mock_home
+                .expect__produce_pending_update()
+                .times(1)
+                .returning(move || Ok(Some(update)));
+            mock_home
+                .expect__produce_update()
+                .times(1)
+                .returning(|| Ok(None));
+            mock_home
+                .expect__committed_root()
+                .times(1)
+                .returning(|| Ok(H256::zero()));
+            let home = caching_home(&db, mock_home);
+            let dispatches = home.dispatches();
+
+            let task = producer(&db, home, 3600, true).spawn();
+            dispatches.notify_one();
+            sleep(DISPATCH_DEBOUNCE * 4).await;
+            task.into_inner().abort();
+
+            assert!(db.retrieve_produced_update(H256::zero()).unwrap().is_none());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn dispatches_are_ignored_without_trigger() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+
+            // Any home call would fail the mock
+            let home = caching_home(&db, MockHomeContract::new());
+            let dispatches = home.dispatches();
+
+            let task = producer(&db, home, 3600, false).spawn();
+            dispatches.notify_one();
+            sleep(DISPATCH_DEBOUNCE * 4).await;
+
+            // The task is still waiting for its interval
+            let task = task.into_inner();
+            assert!(!task.is_finished());
+            task.abort();
+        })
+        .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_stops_the_producer() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+
+            // Any home call would fail the mock
+            let home = caching_home(&db, MockHomeContract::new());
+            let cancellation = nomad_base::CancellationToken::default();
+            let mut producer = producer(&db, home, 3600, true);
+            producer.retry = producer.retry.with_cancellation(cancellation.clone());
+
+            let start = Instant::now();
+            let task = producer.spawn();
+            sleep(Duration::from_secs(1)).await;
+            cancellation.cancel();
+
+            // The task ends at shutdown rather than at its next check
+            task.await.unwrap().unwrap();
+            assert_eq!(start.elapsed(), Duration::from_secs(1));
+        })
+        .await
+    }
+}
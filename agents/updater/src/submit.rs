@@ -1,18 +1,18 @@
 use std::sync::Arc;
 
-use nomad_base::{CachingHome, NomadDB};
-use nomad_core::Common;
+use nomad_base::{AuditEvent, CachingHome, ChainCommunicationError, NomadDB, Retry};
+use nomad_core::{capture_submission, utils::abbreviated_hex, Common};
 use prometheus::IntCounter;
 use std::time::Duration;
 
 use color_eyre::Result;
-use tokio::{task::JoinHandle, time::sleep};
-use tracing::{info, info_span, instrument::Instrumented, Instrument};
+use tokio::task::JoinHandle;
+use tracing::{error, info, info_span, instrument::Instrumented, Instrument};
 
 pub(crate) struct UpdateSubmitter {
     home: Arc<CachingHome>,
     db: NomadDB,
-    interval_seconds: u64,
+    retry: Retry,
     finalization_seconds: u64,
     submitted_update_count: IntCounter,
 }
@@ -21,14 +21,14 @@ impl UpdateSubmitter {
     pub(crate) fn new(
         home: Arc<CachingHome>,
         db: NomadDB,
-        interval_seconds: u64,
+        retry: Retry,
         finalization_seconds: u64,
         submitted_update_count: IntCounter,
     ) -> Self {
         Self {
             home,
             db,
-            interval_seconds,
+            retry,
             finalization_seconds,
             submitted_update_count,
         }
@@ -42,12 +42,14 @@ impl UpdateSubmitter {
             let mut committed_root = self.home.committed_root().await?;
 
             loop {
-                sleep(Duration::from_secs(self.interval_seconds)).await;
+                if !self.retry.sleep(1).await {
+                    return Ok(());
+                }
 
                 // if we have produced an update building off the committed root
                 // submit it
                 if let Some(signed) = self.db.retrieve_produced_update(committed_root)? {
-                    let hex_signature = format!("0x{}", hex::encode(signed.signature.to_vec()));
+                    let hex_signature = abbreviated_hex(&signed.signature.to_vec());
                     info!(
                         previous_root = ?signed.update.previous_root,
                         new_root = ?signed.update.new_root,
@@ -57,7 +59,41 @@ impl UpdateSubmitter {
 
                     // Submit update and let the home indexer pick up the
                     // update once it is confirmed state in the chain
-                    let tx = self.home.update(&signed).await?;
+                    let decision = self.db.audit(AuditEvent::SubmitUpdate {
+                        home: self.home.name().to_owned(),
+                        previous_root: signed.update.previous_root,
+                        new_root: signed.update.new_root,
+                    })?;
+                    let (result, payload) = capture_submission(self.home.update(&signed)).await;
+                    self.db.audit_submission(
+                        decision,
+                        self.home.name(),
+                        &result,
+                        payload.as_ref(),
+                        |tx| tx.txid,
+                    )?;
+                    let tx = match result {
+                        Ok(tx) => tx,
+                        // Alerted on and tried again later, as the home's
+                        // queue may shrink or the cap be raised
+                        Err(e @ ChainCommunicationError::GasLimitExceeded { .. }) => {
+                            error!(
+                                error = %e,
+                                previous_root = ?signed.update.previous_root,
+                                new_root = ?signed.update.new_root,
+                                "Update needs more gas than the configured cap. Not submitting it. Raise maxGasLimit."
+                            );
+                            if !self
+                                .retry
+                                .wait(Duration::from_secs(self.finalization_seconds))
+                                .await
+                            {
+                                return Ok(());
+                            }
+                            continue;
+                        }
+                        Err(e) => return Err(e.into()),
+                    };
 
                     self.submitted_update_count.inc();
 
@@ -71,7 +107,13 @@ impl UpdateSubmitter {
                         sleep = self.finalization_seconds,
                         "Submitted update with tx hash {:?}. Sleeping before next tx submission.", tx.txid,
                     );
-                    sleep(Duration::from_secs(self.finalization_seconds)).await;
+                    if !self
+                        .retry
+                        .wait(Duration::from_secs(self.finalization_seconds))
+                        .await
+                    {
+                        return Ok(());
+                    }
                 } else {
                     info!(
                         committed_root = ?committed_root,
@@ -1,16 +1,28 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use crate::{
-    produce::UpdateProducer, settings::UpdaterSettings as Settings, submit::UpdateSubmitter,
+    lease::{DynamoLeaseStore, LeaseStore, SigningPermit, UpdaterLease, SIGNING_MARGIN_HEARTBEATS},
+    produce::{QueueBacklog, UpdateProducer},
+    settings::UpdaterSettings as Settings,
+    submit::UpdateSubmitter,
 };
 use async_trait::async_trait;
-use color_eyre::{eyre::ensure, Result};
+use color_eyre::{
+    eyre::{bail, ensure},
+    Result,
+};
 use ethers::{signers::Signer, types::Address};
 use futures_util::future::select_all;
-use nomad_base::{AgentCore, AttestationSigner, CachingHome, NomadAgent, NomadDB};
+use nomad_base::{
+    serve_admin, AgentCore, AttestationSigner, CachingHome, CancellationToken, NomadAgent, NomadDB,
+    Retry,
+};
 use nomad_core::{Common, FromSignerConf};
-use prometheus::IntCounter;
-use tokio::task::JoinHandle;
+use nomad_xyz_configuration::{
+    agent::updater::UpdaterLeaseConfig, ChainConf, ATTESTATION_SIGNER_ROLE,
+};
+use prometheus::{HistogramVec, IntCounter};
+use tokio::{sync::RwLock, task::JoinHandle};
 use tracing::{info, instrument::Instrumented, Instrument};
 
 /// An updater agent
@@ -19,9 +31,15 @@ pub struct Updater {
     signer: Arc<AttestationSigner>,
     interval_seconds: u64,
     finalization_seconds: u64,
+    lease: Option<UpdaterLease>,
+    admin_port: Option<u16>,
+    signing: SigningPermit,
     pub(crate) core: AgentCore,
     signed_attestation_count: IntCounter,
     submitted_update_count: IntCounter,
+    queue_backlog: QueueBacklog,
+    dispatch_trigger: bool,
+    signing_latency: HistogramVec,
 }
 
 impl AsRef<AgentCore> for Updater {
@@ -36,6 +54,10 @@ impl Updater {
         signer: AttestationSigner,
         interval_seconds: u64,
         finalization_seconds: u64,
+        lease_config: Option<(UpdaterLeaseConfig, Arc<dyn LeaseStore>)>,
+        admin_port: Option<u16>,
+        max_queue_length: Option<u64>,
+        dispatch_trigger: bool,
         core: AgentCore,
     ) -> Self {
         let home_name = core.home.name();
@@ -59,13 +81,70 @@ impl Updater {
             .expect("failed to register submitted_update_count")
             .with_label_values(&[home_name, Self::AGENT_NAME]);
 
+        let queue_backlog_active = core
+            .metrics
+            .new_int_gauge_vec(
+                "queue_backlog_active",
+                "1 if the home queue exceeds maxQueueLength and only the queue end is attested",
+                &["network", "agent"],
+            )
+            .expect("failed to register queue_backlog_active")
+            .with_label_values(&[home_name, Self::AGENT_NAME]);
+
+        let queue_backlog_length = core
+            .metrics
+            .new_int_gauge_vec(
+                "queue_backlog_length",
+                "Home queue length while in backlog mode, 0 otherwise",
+                &["network", "agent"],
+            )
+            .expect("failed to register queue_backlog_length")
+            .with_label_values(&[home_name, Self::AGENT_NAME]);
+
+        let signing_latency = core
+            .metrics
+            .new_histogram(
+                "update_signing_latency_seconds",
+                "Seconds from the first unsigned dispatch seen to signing an update, by whether a dispatch or the interval triggered the check",
+                &["trigger", "network", "agent"],
+                &[0.5, 1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0],
+            )
+            .expect("failed to register update_signing_latency_seconds");
+
+        // Without a lease the updater always signs. With one, signing stays
+        // disabled until the lease is acquired.
+        let lease = lease_config.map(|(config, store)| {
+            UpdaterLease::new(
+                store,
+                NomadDB::new(core.home.name(), core.db.clone()),
+                core.home.clone(),
+                config.instance_id,
+                config.heartbeat_seconds,
+                config.takeover_seconds,
+            )
+        });
+        let signing = match &lease {
+            Some(lease) => lease.signing(),
+            None => Arc::new(RwLock::new(true)),
+        };
+
         Self {
             signer: Arc::new(signer),
             interval_seconds,
             finalization_seconds,
+            lease,
+            admin_port,
+            signing,
             core,
             signed_attestation_count,
             submitted_update_count,
+            queue_backlog: QueueBacklog::new(
+                max_queue_length,
+                queue_backlog_active,
+                queue_backlog_length,
+            ),
+            dispatch_trigger,
+            signing_latency,
         }
     }
 }
@@ -76,10 +155,15 @@ impl From<&Updater> for UpdaterChannel {
             home: updater.home(),
             db: NomadDB::new(updater.home().name(), updater.db()),
             signer: updater.signer.clone(),
+            signing: updater.signing.clone(),
             signed_attestation_count: updater.signed_attestation_count.clone(),
             submitted_update_count: updater.submitted_update_count.clone(),
+            queue_backlog: updater.queue_backlog.clone(),
+            dispatch_trigger: updater.dispatch_trigger,
+            signing_latency: updater.signing_latency.clone(),
             finalization_seconds: updater.finalization_seconds,
             interval_seconds: updater.interval_seconds,
+            cancellation: updater.cancellation(),
         }
     }
 }
@@ -91,10 +175,15 @@ pub struct UpdaterChannel {
     home: Arc<CachingHome>,
     db: NomadDB,
     signer: Arc<AttestationSigner>,
+    signing: SigningPermit,
     signed_attestation_count: IntCounter,
     submitted_update_count: IntCounter,
+    queue_backlog: QueueBacklog,
+    dispatch_trigger: bool,
+    signing_latency: HistogramVec,
     finalization_seconds: u64,
     interval_seconds: u64,
+    cancellation: CancellationToken,
 }
 
 // This is a bit of a kludge to make from_settings work.
@@ -112,11 +201,21 @@ impl NomadAgent for Updater {
     where
         Self: Sized,
     {
+        // Substrate homes have no queue to check the backlog against
+        ensure!(
+            settings.agent.max_queue_length.is_none()
+                || !matches!(settings.as_ref().home.chain, ChainConf::Substrate(_)),
+            "maxQueueLength is not supported by Substrate homes"
+        );
+
+        settings
+            .as_ref()
+            .resolve_signer_roles(&[ATTESTATION_SIGNER_ROLE])
+            .await?;
         let signer = AttestationSigner::try_from_signer_conf(
             settings
                 .as_ref()
-                .attestation_signer
-                .as_ref()
+                .role_signer(ATTESTATION_SIGNER_ROLE)
                 .expect("!signer"),
         )
         .await?;
@@ -127,10 +226,36 @@ impl NomadAgent for Updater {
         let finalization_seconds = finality_blocks * block_time;
 
         let core = settings.as_ref().try_into_core(Self::AGENT_NAME).await?;
+
+        let lease = match settings.agent.lease.clone() {
+            Some(config) => {
+                if config.table.is_empty() {
+                    bail!("The updater lease requires a shared lease table");
+                }
+                if config.takeover_seconds <= SIGNING_MARGIN_HEARTBEATS * config.heartbeat_seconds {
+                    bail!(
+                        "The updater lease takeover delay must exceed {} heartbeats",
+                        SIGNING_MARGIN_HEARTBEATS
+                    );
+                }
+                let store: Arc<dyn LeaseStore> =
+                    Arc::new(DynamoLeaseStore::new(&config.table, core.home.name()).await);
+                Some((config, store))
+            }
+            None => None,
+        };
+        if settings.agent.admin_port.is_some() && lease.is_none() {
+            bail!("An updater admin port is set but no updater lease is configured");
+        }
+
         Ok(Self::new(
             signer,
             interval_seconds,
             finalization_seconds,
+            lease,
+            settings.agent.admin_port,
+            settings.agent.max_queue_length,
+            settings.agent.dispatch_trigger,
             core,
         ))
     }
@@ -139,23 +264,35 @@ impl NomadAgent for Updater {
         self.into()
     }
 
+    fn run_admin_server(&self) -> Option<Instrumented<JoinHandle<Result<()>>>> {
+        let port = self.admin_port?;
+        let lease = self.lease.as_ref()?;
+        serve_admin(Self::AGENT_NAME, lease.routes(), port)
+    }
+
     fn run(channel: Self::Channel) -> Instrumented<JoinHandle<Result<()>>> {
         let home = channel.home.clone();
         let address = channel.signer.address();
         let db = channel.db.clone();
+        let retry = Retry::fixed(Duration::from_secs(channel.interval_seconds))
+            .with_cancellation(channel.cancellation);
 
         let produce = UpdateProducer::new(
             home.clone(),
             db.clone(),
             channel.signer.clone(),
-            channel.interval_seconds,
+            channel.signing.clone(),
+            retry.clone(),
             channel.signed_attestation_count.clone(),
+            channel.queue_backlog.clone(),
+            channel.dispatch_trigger,
+            channel.signing_latency.clone(),
         );
 
         let submit = UpdateSubmitter::new(
             home.clone(),
             db,
-            channel.interval_seconds,
+            retry,
             channel.finalization_seconds,
             channel.submitted_update_count,
         );
@@ -196,6 +333,7 @@ impl NomadAgent for Updater {
             self.assert_home_not_failed().await??;
 
             let home_fail_watch_task = self.watch_home_fail(self.interval_seconds);
+            let tree_count_watch_task = self.watch_tree_counts(self.interval_seconds);
 
             info!("Starting updater sync task...");
             let sync_task = self.home().sync();
@@ -206,8 +344,30 @@ impl NomadAgent for Updater {
             info!("Starting updater produce and submit tasks...");
             let update_task = self.run_report_error("".to_owned());
 
-            let (res, _, rem) =
-                select_all(vec![home_fail_watch_task, sync_task, update_task]).await;
+            let mut tasks = vec![
+                home_fail_watch_task,
+                tree_count_watch_task,
+                sync_task,
+                update_task,
+            ];
+
+            // Standby instances keep syncing but only sign once they hold the
+            // lease
+            if let Some(lease) = &self.lease {
+                info!("Starting updater lease task...");
+                tasks.push(lease.clone().spawn());
+            }
+            if let Some(admin_task) = self.run_admin_server() {
+                tasks.push(admin_task);
+            }
+
+            if let Some(top_up_task) = self.top_up_task() {
+                tasks.push(top_up_task);
+            }
+
+            let (res, _, rem) = select_all(tasks).await;
+
+            self.cancellation().cancel();
 
             for task in rem.into_iter() {
                 task.into_inner().abort();
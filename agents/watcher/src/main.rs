@@ -14,14 +14,19 @@ mod watcher;
 
 use crate::{settings::WatcherSettings as Settings, watcher::Watcher};
 use color_eyre::Result;
-use nomad_base::NomadAgent;
+use nomad_base::{FatalError, NomadAgent};
 
 use tracing::info_span;
 use tracing_subscriber::prelude::*;
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<()> {
+async fn main() {
+    nomad_base::exit_with_summary(Watcher::AGENT_NAME, run().await)
+}
+
+async fn run() -> Result<()> {
     color_eyre::install()?;
+    nomad_base::dump_schema_if_requested();
 
     // sets the subscriber for this scope only
     let _bootup_guard = tracing_subscriber::FmtSubscriber::builder()
@@ -32,8 +37,13 @@ async fn main() -> Result<()> {
     let span = info_span!("WatcherBootup");
     let _span = span.enter();
 
-    let settings = Settings::new().await?;
-    let agent = Watcher::from_settings(settings).await?;
+    let settings = Settings::new()
+        .await
+        .map_err(|e| FatalError::config("settings", e))?;
+    let agent = Watcher::from_settings(settings)
+        .await
+        .map_err(|e| FatalError::classify_startup(e, "setup"))?;
+    agent.verify_db_if_requested()?;
 
     drop(_span);
     drop(span);
@@ -1,36 +1,141 @@
 use async_trait::async_trait;
-use color_eyre::{eyre::bail, Report, Result};
+use color_eyre::{
+    eyre::{bail, eyre},
+    Report, Result,
+};
 use thiserror::Error;
 
 use ethers::core::types::H256;
 use futures_util::future::{join, join_all, select_all};
 use prometheus::{IntGauge, IntGaugeVec};
-use std::{collections::HashMap, fmt::Display, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 use tokio::{
     select,
     sync::{mpsc, RwLock},
     task::JoinHandle,
     time::sleep,
 };
-use tracing::{error, info, info_span, instrument::Instrumented, Instrument};
+use tracing::{error, info, info_span, instrument::Instrumented, warn, Instrument};
 
 use nomad_base::{
-    cancel_task, AgentCore, AttestationSigner, BaseError, CachingHome, ChainCommunicationError,
-    ConnectionManagers, NomadAgent, NomadDB,
+    cancel_task, AgentCore, AttestationSigner, AuditEvent, AuditId, BaseError, CachingHome,
+    ChainCommunicationError, ConnectionManagers, EvidenceContract, FatalError, Fraud, Incident,
+    NomadAgent, NomadDB, StateRead,
 };
 use nomad_core::{
-    Common, CommonEvents, ConnectionManager, DoubleUpdate, FailureNotification, FromSignerConf,
-    Home, SignedFailureNotification, SignedUpdate, TxOutcome,
+    capture_submission, CanonicalH256, Common, CommonEvents, ConnectionManager, DoubleUpdate,
+    FailureNotification, FraudDetector, FromSignerConf, Home, Replica, SignedFailureNotification,
+    SignedUpdate, State, TxOutcome, TxPayload,
 };
 
+use nomad_xyz_configuration::WATCHER_KEY_ROLE;
+
 use crate::settings::WatcherSettings as Settings;
 
 const AGENT_NAME: &str = "watcher";
 
+const FRONTIER_LABEL: &str = "frontier";
+const AUDIT_LABEL: &str = "audit";
+
 #[derive(Debug, Error)]
 enum WatcherError {
     #[error("Syncing finished")]
     SyncingFinished,
+    #[error("No update found leading to root {0:?} behind the audit checkpoint")]
+    HistoryGap(H256),
+}
+
+/// Kind of check an update sent to the UpdateHandler is subject to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckKind {
+    /// Update at or beyond the contract's frontier. Passing the check
+    /// advances the frontier.
+    Frontier,
+    /// Historical update behind the frontier, swept by the optional startup
+    /// audit. Never moves the frontier.
+    Audit,
+}
+
+/// A signed update along with the contract it was observed on and the kind
+/// of check it is subject to
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateToCheck {
+    contract: String,
+    kind: CheckKind,
+    update: SignedUpdate,
+    /// Audit checkpoint of the contract to store once the update passes.
+    /// Set on the last update of an audit, which completes the history
+    /// behind the root the audit started from.
+    checkpoint: Option<H256>,
+}
+
+/// Time to protection after a start: the time until every contract's
+/// frontier has been checked up to the latest update on the contract. Until
+/// then, fraud in updates the watcher has not reached goes unnoticed.
+#[derive(Debug, Clone)]
+pub struct TimeToProtection {
+    started: Instant,
+    watcher_db: NomadDB,
+    /// Contracts whose frontier has not yet caught up
+    pending: Arc<Mutex<HashSet<String>>>,
+    protected_after: Arc<Mutex<Option<Duration>>>,
+    seconds: IntGauge,
+}
+
+impl TimeToProtection {
+    /// Start measuring until the frontiers of `contracts` catch up.
+    /// `seconds` is set once they have.
+    pub fn new(
+        watcher_db: NomadDB,
+        contracts: impl IntoIterator<Item = String>,
+        seconds: IntGauge,
+    ) -> Self {
+        Self {
+            started: Instant::now(),
+            watcher_db,
+            pending: Arc::new(Mutex::new(contracts.into_iter().collect())),
+            protected_after: Default::default(),
+            seconds,
+        }
+    }
+
+    /// Record that the watcher of `contract`, started at `from`, found no
+    /// update past `root`. The contract has caught up once the handler has
+    /// checked the updates sent up to `root`. Returns the time to protection
+    /// when this was the last contract to catch up.
+    pub fn caught_up(&self, contract: &str, from: H256, root: H256) -> Result<Option<Duration>> {
+        let mut pending = self.pending.lock().expect("poisoned");
+        if !pending.contains(contract) {
+            return Ok(None);
+        }
+        if root != from && self.watcher_db.retrieve_watcher_frontier(contract)? != Some(root) {
+            return Ok(None);
+        }
+
+        pending.remove(contract);
+        if !pending.is_empty() {
+            return Ok(None);
+        }
+
+        let elapsed = self.started.elapsed();
+        *self.protected_after.lock().expect("poisoned") = Some(elapsed);
+        self.seconds.set(elapsed.as_secs() as i64);
+        info!(
+            elapsed = ?elapsed,
+            "All contract frontiers checked up to their latest update."
+        );
+        Ok(Some(elapsed))
+    }
+
+    /// Time to protection, once every contract has caught up
+    pub fn protected_after(&self) -> Option<Duration> {
+        *self.protected_after.lock().expect("poisoned")
+    }
 }
 
 #[derive(Debug)]
@@ -39,10 +144,12 @@ where
     C: Common + CommonEvents + ?Sized + 'static,
 {
     interval: u64,
+    from: H256,
     committed_root: H256,
-    tx: mpsc::Sender<SignedUpdate>,
+    tx: mpsc::Sender<UpdateToCheck>,
     contract: Arc<C>,
     updates_inspected_for_double: IntGauge,
+    time_to_protection: Option<TimeToProtection>,
 }
 
 impl<C> Display for ContractWatcher<C>
@@ -66,19 +173,27 @@ where
     pub fn new(
         interval: u64,
         from: H256,
-        tx: mpsc::Sender<SignedUpdate>,
+        tx: mpsc::Sender<UpdateToCheck>,
         contract: Arc<C>,
         updates_inspected_for_double: IntGauge,
     ) -> Self {
         Self {
             interval,
+            from,
             committed_root: from,
             tx,
             contract,
             updates_inspected_for_double,
+            time_to_protection: None,
         }
     }
 
+    /// Report to `time_to_protection` once caught up with the contract
+    pub fn with_time_to_protection(mut self, time_to_protection: TimeToProtection) -> Self {
+        self.time_to_protection = Some(time_to_protection);
+        self
+    }
+
     async fn poll_and_send_update(&mut self) -> Result<()> {
         let update_opt = self
             .contract
@@ -88,9 +203,16 @@ where
         if update_opt.is_none() {
             info!(
                 "No new update found. Previous root: {}. From contract: {}.",
-                self.committed_root,
+                CanonicalH256(self.committed_root),
                 self.contract.name()
             );
+            if let Some(time_to_protection) = &self.time_to_protection {
+                time_to_protection.caught_up(
+                    self.contract.name(),
+                    self.from,
+                    self.committed_root,
+                )?;
+            }
             return Ok(());
         }
 
@@ -103,7 +225,14 @@ where
             self.contract.name()
         );
 
-        self.tx.send(new_update).await?;
+        self.tx
+            .send(UpdateToCheck {
+                contract: self.contract.name().to_owned(),
+                kind: CheckKind::Frontier,
+                update: new_update,
+                checkpoint: None,
+            })
+            .await?;
         self.updates_inspected_for_double.inc();
 
         Ok(())
@@ -126,8 +255,10 @@ where
     C: Common + CommonEvents + ?Sized + 'static,
 {
     interval: u64,
+    from: H256,
     committed_root: H256,
-    tx: mpsc::Sender<SignedUpdate>,
+    checkpoint: Option<H256>,
+    tx: mpsc::Sender<UpdateToCheck>,
     contract: Arc<C>,
     updates_inspected_for_double: IntGauge,
 }
@@ -139,12 +270,14 @@ where
     pub fn new(
         interval: u64,
         from: H256,
-        tx: mpsc::Sender<SignedUpdate>,
+        tx: mpsc::Sender<UpdateToCheck>,
         contract: Arc<C>,
         updates_inspected_for_double: IntGauge,
     ) -> Self {
         Self {
+            from,
             committed_root: from,
+            checkpoint: None,
             tx,
             contract,
             interval,
@@ -152,7 +285,48 @@ where
         }
     }
 
+    /// Resume from the audit checkpoint of a previous run. History behind
+    /// it is only checked for continuity rather than audited again.
+    pub fn with_checkpoint(mut self, checkpoint: Option<H256>) -> Self {
+        self.checkpoint = checkpoint;
+        self
+    }
+
+    /// Walk the history behind the audit checkpoint back to the zero root,
+    /// checking only that each update builds on an indexed one. Updates are
+    /// not sent to the handler. Returns the number of updates walked.
+    async fn check_continuity(&self) -> Result<usize> {
+        let mut root = self.committed_root;
+        let mut walked = 0;
+        while !root.is_zero() {
+            match self.contract.signed_update_by_new_root(root).await? {
+                Some(update) => root = update.update.previous_root,
+                None => return Err(Report::new(WatcherError::HistoryGap(root))),
+            }
+            walked += 1;
+        }
+        Ok(walked)
+    }
+
     async fn update_history(&mut self) -> Result<()> {
+        if self.checkpoint == Some(self.committed_root) {
+            let walked = self.check_continuity().await.map_err(|e| {
+                error!(
+                    error = %e,
+                    "History behind the audit checkpoint of contract {} is not continuous.",
+                    self.contract.name()
+                );
+                e
+            })?;
+            info!(
+                checkpoint = %CanonicalH256(self.committed_root),
+                walked,
+                "HistorySync for contract {} has finished. History behind the checkpoint is continuous.",
+                self.contract.name()
+            );
+            return Err(Report::new(WatcherError::SyncingFinished));
+        }
+
         let previous_update = self
             .contract
             .signed_update_by_new_root(self.committed_root)
@@ -166,9 +340,19 @@ where
             return Err(Report::new(WatcherError::SyncingFinished));
         }
 
-        // Dispatch to the handler
+        // Dispatch to the handler. The last update of the audit moves the
+        // checkpoint to where the audit started, once it has passed.
         let previous_update = previous_update.unwrap();
-        self.tx.send(previous_update.clone()).await?;
+        let previous_root = previous_update.update.previous_root;
+        let last = previous_root.is_zero() || self.checkpoint == Some(previous_root);
+        self.tx
+            .send(UpdateToCheck {
+                contract: self.contract.name().to_owned(),
+                kind: CheckKind::Audit,
+                update: previous_update.clone(),
+                checkpoint: if last { Some(self.from) } else { None },
+            })
+            .await?;
         self.updates_inspected_for_double.inc();
 
         // set up for next loop iteration
@@ -204,14 +388,14 @@ where
 
 #[derive(Debug)]
 pub struct UpdateHandler {
-    rx: mpsc::Receiver<SignedUpdate>,
+    rx: mpsc::Receiver<UpdateToCheck>,
     watcher_db: NomadDB,
     home: Arc<CachingHome>,
 }
 
 impl UpdateHandler {
     pub fn new(
-        rx: mpsc::Receiver<SignedUpdate>,
+        rx: mpsc::Receiver<UpdateToCheck>,
         watcher_db: NomadDB,
         home: Arc<CachingHome>,
     ) -> Self {
@@ -224,7 +408,6 @@ impl UpdateHandler {
 
     fn check_double_update(&mut self, update: &SignedUpdate) -> Result<(), DoubleUpdate> {
         let old_root = update.update.previous_root;
-        let new_root = update.update.new_root;
 
         match self
             .watcher_db
@@ -232,18 +415,20 @@ impl UpdateHandler {
             .expect("!db_get")
         {
             Some(existing) => {
-                if existing.update.new_root != new_root {
+                if let Some(double) = FraudDetector::conflict(&existing, update) {
                     error!(
                         "UpdateHandler detected double update! Existing: {:?}. Double: {:?}.",
                         &existing, &update
                     );
-                    return Err(DoubleUpdate(existing, update.to_owned()));
+                    return Err(double);
                 }
             }
             None => {
                 info!(
                     "UpdateHandler storing new update from root {} to {}. Update: {:?}.",
-                    &update.update.previous_root, &update.update.new_root, &update
+                    CanonicalH256(update.update.previous_root),
+                    CanonicalH256(update.update.new_root),
+                    &update
                 );
                 self.watcher_db.store_update(update).expect("!db_put");
             }
@@ -252,6 +437,38 @@ impl UpdateHandler {
         Ok(())
     }
 
+    /// Check a single update for fraud. Frontier checks advance the
+    /// persisted frontier of their contract, and the last update of an audit
+    /// its audit checkpoint, only after the check has passed, so a crash in
+    /// between results in a harmless re-check rather than a gap.
+    ///
+    /// CancellationSafe: the update, frontier and checkpoint are stored after
+    /// the last await.
+    async fn handle_update(&mut self, to_check: &UpdateToCheck) -> Result<Option<DoubleUpdate>> {
+        let update = &to_check.update;
+        let old_root = update.update.previous_root;
+
+        if old_root == self.home.committed_root().await? {
+            // It is okay if tx reverts
+            let _ = self.home.update(update).await;
+        }
+
+        if let Err(double_update) = self.check_double_update(update) {
+            return Ok(Some(double_update));
+        }
+
+        if to_check.kind == CheckKind::Frontier {
+            self.watcher_db
+                .store_watcher_frontier(&to_check.contract, update.update.new_root)?;
+        }
+        if let Some(checkpoint) = to_check.checkpoint {
+            self.watcher_db
+                .store_watcher_audit_checkpoint(&to_check.contract, checkpoint)?;
+        }
+
+        Ok(None)
+    }
+
     /// Receive updates and check them for fraud. If double update was
     /// found, return Ok(double_update). This loop should never exit naturally
     /// unless the channel for sending new updates was closed, in which case we
@@ -260,21 +477,13 @@ impl UpdateHandler {
     fn spawn(mut self) -> JoinHandle<Result<DoubleUpdate>> {
         tokio::spawn(async move {
             loop {
-                let update = self.rx.recv().await;
+                let to_check = self.rx.recv().await;
                 // channel is closed
-                if update.is_none() {
+                if to_check.is_none() {
                     bail!("Channel closed.")
                 }
 
-                let update = update.unwrap();
-                let old_root = update.update.previous_root;
-
-                if old_root == self.home.committed_root().await? {
-                    // It is okay if tx reverts
-                    let _ = self.home.update(&update).await;
-                }
-
-                if let Err(double_update) = self.check_double_update(&update) {
+                if let Some(double_update) = self.handle_update(&to_check.unwrap()).await? {
                     return Ok(double_update);
                 }
             }
@@ -288,12 +497,14 @@ type TaskMap = Arc<RwLock<HashMap<String, Instrumented<JoinHandle<Result<()>>>>>
 pub struct Watcher {
     signer: Arc<AttestationSigner>,
     interval_seconds: u64,
+    audit_history: bool,
     sync_tasks: TaskMap,
     watch_tasks: TaskMap,
     connection_managers: Vec<Arc<ConnectionManagers>>,
     core: AgentCore,
     double_updates_observed: IntGauge,
     updates_inspected_for_double: IntGaugeVec,
+    time_to_protection: IntGauge,
 }
 
 impl AsRef<AgentCore> for Watcher {
@@ -308,6 +519,7 @@ impl Watcher {
     pub fn new(
         signer: AttestationSigner,
         interval_seconds: u64,
+        audit_history: bool,
         connection_managers: Vec<Arc<ConnectionManagers>>,
         core: AgentCore,
     ) -> Self {
@@ -326,49 +538,81 @@ impl Watcher {
             .new_int_gauge_vec(
                 "updates_inspected_for_double",
                 "Number of updates inspected for double update per channel",
-                &["home", "checked", "agent"],
+                &["home", "checked", "kind", "agent"],
             )
             .expect("failed to register watcher metric");
 
+        let time_to_protection = core
+            .metrics
+            .new_int_gauge_vec(
+                "time_to_protection_seconds",
+                "Seconds from start until every contract was checked up to its latest update (0 until then)",
+                &["home", "agent"],
+            )
+            .expect("failed to register watcher metric")
+            .with_label_values(&[core.home.name(), Self::AGENT_NAME]);
+
         Self {
             signer: Arc::new(signer),
             interval_seconds,
+            audit_history,
             sync_tasks: Default::default(),
             watch_tasks: Default::default(),
             connection_managers,
             core,
             double_updates_observed,
             updates_inspected_for_double,
+            time_to_protection,
         }
     }
 
     /// Spawn UpdateHandler and sync tasks. Have sync tasks send UpdateHandler
     /// signed updates through mpsc. Return Some(double_update) if any
     /// conflicting updates are found.
+    ///
+    /// Watch tasks start at each contract's persisted frontier (falling back
+    /// to its current committed root), so updates long since superseded are
+    /// not re-checked on every start. History behind the frontier is only
+    /// swept if `audit_history` is set, and then only back to the audit
+    /// checkpoint of the previous run. Behind the checkpoint, history is only
+    /// checked for continuity.
     fn watch_double_update(&self) -> Instrumented<JoinHandle<Result<Option<DoubleUpdate>>>> {
         let home = self.home();
         let replicas = self.replicas().clone();
         let watcher_db_name = format!("{}_{}", home.name(), AGENT_NAME);
         let watcher_db = NomadDB::new(watcher_db_name, self.db());
         let interval_seconds = self.interval_seconds;
+        let audit_history = self.audit_history;
         let sync_tasks = self.sync_tasks.clone();
         let watch_tasks = self.watch_tasks.clone();
         let updates_inspected_for_double = self.updates_inspected_for_double.clone();
+        let time_to_protection = TimeToProtection::new(
+            watcher_db.clone(),
+            replicas
+                .values()
+                .map(|replica| replica.name().to_owned())
+                .chain(std::iter::once(home.name().to_owned())),
+            self.time_to_protection.clone(),
+        );
 
         tokio::spawn(async move {
             // Spawn update handler
             let (tx, rx) = mpsc::channel(200);
-            let handler = UpdateHandler::new(rx, watcher_db, home.clone()).spawn();
+            let handler = UpdateHandler::new(rx, watcher_db.clone(), home.clone()).spawn();
 
             // For each replica, spawn polling and history syncing tasks
             info!("Spawning replica watch and sync tasks...");
             for (name, replica) in replicas {
                 info!("Spawning watch and sync tasks for replica {}.", name);
-                let from = replica.committed_root().await?;
+                let from = match watcher_db.retrieve_watcher_frontier(replica.name())? {
+                    Some(frontier) => frontier,
+                    None => replica.committed_root().await?,
+                };
 
                 let inspected = updates_inspected_for_double.with_label_values(&[
                     home.name(),
                     replica.name(),
+                    FRONTIER_LABEL,
                     Self::AGENT_NAME,
                 ]);
 
@@ -379,40 +623,69 @@ impl Watcher {
                         from,
                         tx.clone(),
                         replica.clone(),
-                        inspected.clone(),
+                        inspected,
                     )
+                    .with_time_to_protection(time_to_protection.clone())
                     .spawn()
                     .in_current_span(),
                 );
-                sync_tasks.write().await.insert(
-                    (*name).to_owned(),
-                    HistorySync::new(interval_seconds, from, tx.clone(), replica, inspected)
-                        .spawn()
-                        .in_current_span(),
-                );
+
+                if audit_history {
+                    let audited = updates_inspected_for_double.with_label_values(&[
+                        home.name(),
+                        replica.name(),
+                        AUDIT_LABEL,
+                        Self::AGENT_NAME,
+                    ]);
+
+                    let checkpoint =
+                        watcher_db.retrieve_watcher_audit_checkpoint(replica.name())?;
+                    sync_tasks.write().await.insert(
+                        (*name).to_owned(),
+                        HistorySync::new(interval_seconds, from, tx.clone(), replica, audited)
+                            .with_checkpoint(checkpoint)
+                            .spawn()
+                            .in_current_span(),
+                    );
+                }
             }
 
             // Spawn polling and history syncing tasks for home
             info!("Starting watch and sync tasks for home {}.", home.name());
-            let from = home.committed_root().await?;
+            let from = match watcher_db.retrieve_watcher_frontier(home.name())? {
+                Some(frontier) => frontier,
+                None => home.committed_root().await?,
+            };
             let inspected = updates_inspected_for_double.with_label_values(&[
                 home.name(),
                 home.name(),
+                FRONTIER_LABEL,
                 Self::AGENT_NAME,
             ]);
 
-            let home_watcher = ContractWatcher::new(
-                interval_seconds,
-                from,
-                tx.clone(),
-                home.clone(),
-                inspected.clone(),
-            )
-            .spawn()
-            .in_current_span();
-            let home_sync = HistorySync::new(interval_seconds, from, tx.clone(), home, inspected)
-                .spawn()
-                .in_current_span();
+            let home_watcher =
+                ContractWatcher::new(interval_seconds, from, tx.clone(), home.clone(), inspected)
+                    .with_time_to_protection(time_to_protection)
+                    .spawn()
+                    .in_current_span();
+            let home_sync = if audit_history {
+                let audited = updates_inspected_for_double.with_label_values(&[
+                    home.name(),
+                    home.name(),
+                    AUDIT_LABEL,
+                    Self::AGENT_NAME,
+                ]);
+                let checkpoint = watcher_db.retrieve_watcher_audit_checkpoint(home.name())?;
+
+                Some(
+                    HistorySync::new(interval_seconds, from, tx.clone(), home, audited)
+                        .with_checkpoint(checkpoint)
+                        .spawn()
+                        .in_current_span(),
+                )
+            } else {
+                None
+            };
 
             // Wait for update handler to finish (should only happen watcher is
             // manually shut down)
@@ -421,7 +694,9 @@ impl Watcher {
             // Cancel running tasks
             tracing::info!("Update handler has resolved. Cancelling all other tasks");
             cancel_task!(home_watcher);
-            cancel_task!(home_sync);
+            if let Some(home_sync) = home_sync {
+                cancel_task!(home_sync);
+            }
 
             // Map Result<DoubleUpdate> into Option. If handler returned error
             // no double update. If handler returned Ok(double_update), map into
@@ -441,6 +716,137 @@ impl Watcher {
         .expect("!sign")
     }
 
+    /// DB the watcher's own records are kept in
+    fn watcher_db(&self) -> NomadDB {
+        NomadDB::new(format!("{}_{}", self.home().name(), AGENT_NAME), self.db())
+    }
+
+    /// Audit target names of the connection managers, in order
+    fn connection_manager_targets(&self) -> Vec<String> {
+        self.connection_managers
+            .iter()
+            .map(|manager| format!("connection_manager_{}", manager.local_domain()))
+            .collect()
+    }
+
+    /// Record the outcome of each submission made in response to fraud, with
+    /// its payload. A failure to record is logged, so it never stops the
+    /// response.
+    fn audit_responses(
+        &self,
+        decision: Option<AuditId>,
+        targets: &[String],
+        results: &[Result<TxOutcome, ChainCommunicationError>],
+        payloads: &[Option<TxPayload>],
+    ) {
+        let decision = match decision {
+            Some(decision) => decision,
+            None => return,
+        };
+        let db = self.watcher_db();
+        for ((target, result), payload) in targets.iter().zip(results).zip(payloads) {
+            if let Err(e) =
+                db.audit_submission(decision, target, result, payload.as_ref(), |tx| tx.txid)
+            {
+                error!(error = %e, target = %target, "Failed to record fraud response in audit trail");
+            }
+        }
+    }
+
+    /// Latest block number of the chain of `domain`, if the watcher has a
+    /// connection manager there
+    async fn block_number(&self, domain: u32) -> Option<u64> {
+        let manager = self
+            .connection_managers
+            .iter()
+            .find(|manager| manager.local_domain() == domain)?;
+        manager
+            .get_block_number()
+            .await
+            .map(u64::from)
+            .map_err(|e| warn!(error = %e, domain, "Failed to read block number for evidence"))
+            .ok()
+    }
+
+    /// Store the incident responded to in `decision`, with the contracts
+    /// involved and their state after the response, so its evidence bundle
+    /// can be exported. A failure to read or store is logged, so it never
+    /// stops the response.
+    async fn record_incident(
+        &self,
+        decision: AuditId,
+        fraud: Fraud,
+        signed_failure: &SignedFailureNotification,
+    ) {
+        let settings = &self.core.settings;
+        let home = &self.core.home;
+        let home_domain = signed_failure.notification.home_domain;
+
+        let mut contracts = vec![EvidenceContract {
+            name: home.name().to_owned(),
+            domain: home_domain,
+            address: settings.home.address.map(Into::into),
+        }];
+        let mut state_reads = vec![];
+
+        let block_number = self.block_number(home_domain).await;
+        let read = |function, value| state_read(home.name(), function, block_number, value);
+        state_reads.extend(read("state()", home.state().await.map(state_word)));
+        state_reads.extend(read("committedRoot()", home.committed_root().await));
+        state_reads.extend(read("updater()", home.updater().await));
+
+        for (name, replica) in self.core.replicas.iter() {
+            let domain = replica.local_domain();
+            contracts.push(EvidenceContract {
+                name: name.to_owned(),
+                domain,
+                address: settings
+                    .replicas
+                    .get(name)
+                    .and_then(|setup| setup.address)
+                    .map(Into::into),
+            });
+
+            let block_number = self.block_number(domain).await;
+            let read = |function, value| state_read(name, function, block_number, value);
+            state_reads.extend(read("state()", replica.state().await.map(state_word)));
+            state_reads.extend(read("committedRoot()", replica.committed_root().await));
+        }
+
+        for (manager, name) in self
+            .connection_managers
+            .iter()
+            .zip(self.connection_manager_targets())
+        {
+            let domain = manager.local_domain();
+            let address = settings
+                .managers
+                .iter()
+                .flat_map(|managers| managers.values())
+                .find(|setup| setup.domain == domain)
+                .and_then(|setup| setup.address)
+                .map(Into::into);
+            contracts.push(EvidenceContract {
+                name,
+                domain,
+                address,
+            });
+        }
+
+        let incident = Incident {
+            id: decision,
+            home_domain,
+            fraud,
+            updater: signed_failure.notification.updater.into(),
+            failure_signature: signed_failure.signature,
+            contracts,
+            state_reads,
+        };
+        if let Err(e) = self.watcher_db().store_incident(&incident) {
+            error!(error = %e, incident = decision.0, "Failed to store fraud incident");
+        }
+    }
+
     /// Handle a double-update once it has been detected. Submit double updates
     /// and failure notifications to all homes/replicas.
     #[tracing::instrument]
@@ -449,12 +855,14 @@ impl Watcher {
         double: &DoubleUpdate,
     ) -> Vec<Result<TxOutcome, ChainCommunicationError>> {
         // Create vector of double update futures
+        let mut targets: Vec<String> = self.core.replicas.keys().cloned().collect();
         let mut double_update_futs: Vec<_> = self
             .core
             .replicas
             .values()
             .map(|replica| replica.double_update(double))
             .collect();
+        targets.push(self.core.home.name().to_owned());
         double_update_futs.push(self.core.home.double_update(double));
 
         // Created signed failure notification
@@ -466,15 +874,41 @@ impl Watcher {
         for connection_manager in self.connection_managers.iter() {
             unenroll_futs.push(connection_manager.unenroll_replica(&signed_failure));
         }
+        targets.extend(self.connection_manager_targets());
+
+        let decision = self
+            .watcher_db()
+            .audit(AuditEvent::DoubleUpdateResponse {
+                home_domain: signed_failure.notification.home_domain,
+                updater: signed_failure.notification.updater.into(),
+                previous_root: double.0.update.previous_root,
+                new_roots: [double.0.update.new_root, double.1.update.new_root],
+            })
+            .map_err(
+                |e| error!(error = %e, "Failed to record double update response in audit trail"),
+            )
+            .ok();
 
         // Join both vectors of double update and unenroll futures and
         // return vector containing all results
-        let (double_update_res, unenroll_res) =
-            join(join_all(double_update_futs), join_all(unenroll_futs)).await;
-        double_update_res
+        let (double_update_res, unenroll_res) = join(
+            join_all(double_update_futs.into_iter().map(capture_submission)),
+            join_all(unenroll_futs.into_iter().map(capture_submission)),
+        )
+        .await;
+        let (results, payloads): (Vec<_>, Vec<_>) = double_update_res
             .into_iter()
             .chain(unenroll_res.into_iter())
-            .collect()
+            .unzip();
+        self.audit_responses(decision, &targets, &results, &payloads);
+
+        if let Some(decision) = decision {
+            let fraud = Fraud::DoubleUpdate {
+                updates: [double.0.clone(), double.1.clone()],
+            };
+            self.record_incident(decision, fraud, &signed_failure).await;
+        }
+        results
     }
 
     /// Handle a double-update once it has been detected. Submit double updates
@@ -489,7 +923,34 @@ impl Watcher {
             unenroll_futs.push(connection_manager.unenroll_replica(&signed_failure));
         }
 
-        join_all(unenroll_futs).await
+        let decision = self
+            .watcher_db()
+            .audit(AuditEvent::ImproperUpdateResponse {
+                home_domain: signed_failure.notification.home_domain,
+                updater: signed_failure.notification.updater.into(),
+            })
+            .map_err(
+                |e| error!(error = %e, "Failed to record improper update response in audit trail"),
+            )
+            .ok();
+
+        let (results, payloads): (Vec<_>, Vec<_>) =
+            join_all(unenroll_futs.into_iter().map(capture_submission))
+                .await
+                .into_iter()
+                .unzip();
+        self.audit_responses(
+            decision,
+            &self.connection_manager_targets(),
+            &results,
+            &payloads,
+        );
+
+        if let Some(decision) = decision {
+            self.record_incident(decision, Fraud::ImproperUpdate, &signed_failure)
+                .await;
+        }
+        results
     }
 
     async fn shutdown(&self) {
@@ -560,17 +1021,22 @@ impl NomadAgent for Watcher {
 
         let core = settings.as_ref().try_into_core("watcher").await?;
 
+        settings
+            .base
+            .resolve_signer_roles(&[WATCHER_KEY_ROLE])
+            .await?;
         let signer = AttestationSigner::try_from_signer_conf(
-            &settings
+            settings
                 .base
-                .attestation_signer
-                .expect("missing attestation signer"),
+                .role_signer(WATCHER_KEY_ROLE)
+                .expect("missing watcher key"),
         )
         .await?;
 
         Ok(Self::new(
             signer,
             settings.agent.interval,
+            settings.agent.audit_history,
             connection_managers,
             core,
         ))
@@ -610,6 +1076,8 @@ impl NomadAgent for Watcher {
 
             let double_update_watch_task = self.watch_double_update();
             let improper_update_watch_task = self.watch_home_fail(self.interval_seconds);
+            let updater_signer_watch_task = self.watch_updater_signers();
+            let updater_health_watch_task = self.watch_updater_health();
 
             // Race index and run tasks
             info!("Selecting across tasks...");
@@ -633,14 +1101,18 @@ impl NomadAgent for Watcher {
                             .iter()
                             .for_each(|res| tracing::info!("{:#?}", res));
 
-                        bail!(
-                            r#"
+                        return Err(FatalError::safety_halt(
+                            "double update watch",
+                            eyre!(
+                                r#"
                             Double update detected!
                             All contracts notified!
                             Replicas unenrolled!
                             Watcher has been shut down!
                         "#
+                            ),
                         )
+                        .into())
                     }
 
                     self.shutdown().await;
@@ -658,13 +1130,17 @@ impl NomadAgent for Watcher {
                                 .iter()
                                 .for_each(|res| tracing::info!("{:#?}", res));
 
-                            bail!(
-                                r#"
+                            return Err(FatalError::safety_halt(
+                                "home failure watch",
+                                eyre!(
+                                    r#"
                                 Improper update detected!
                                 Replicas unenrolled!
                                 Watcher has been shut down!
                             "#
+                                ),
                             )
+                            .into())
                         } else {
                             return Err(some_base_error.into())
                         }
@@ -673,6 +1149,14 @@ impl NomadAgent for Watcher {
                         self.shutdown().await;
                     }
                 }
+                updater_signer_res = updater_signer_watch_task => {
+                    updater_signer_res??;
+                    self.shutdown().await;
+                }
+                updater_health_res = updater_health_watch_task => {
+                    updater_health_res??;
+                    self.shutdown().await;
+                }
             }
 
             Ok(())
@@ -681,6 +1165,32 @@ impl NomadAgent for Watcher {
     }
 }
 
+/// Evidence read of `function` on `contract`, or `None` if the read failed
+fn state_read(
+    contract: &str,
+    function: &str,
+    block_number: Option<u64>,
+    value: Result<H256, ChainCommunicationError>,
+) -> Option<StateRead> {
+    match value {
+        Ok(value) => Some(StateRead {
+            contract: contract.to_owned(),
+            function: function.to_owned(),
+            value,
+            block_number,
+        }),
+        Err(e) => {
+            warn!(error = %e, contract, function, "Failed to read state for evidence");
+            None
+        }
+    }
+}
+
+/// ABI word of a contract state, as `state()` returns it
+fn state_word(state: State) -> H256 {
+    H256::from_low_u64_be(state as u64)
+}
+
 #[cfg(test)]
 mod test {
     use nomad_base::IndexSettings;
@@ -692,15 +1202,32 @@ mod test {
     use ethers::signers::{LocalWallet, Signer};
 
     use nomad_base::{
-        chains::PageSettings, CachingReplica, CommonIndexers, ContractSync, ContractSyncMetrics,
-        CoreMetrics, HomeIndexers, Homes, Replicas,
+        chains::PageSettings, CachingReplica, ChainSetupBuilder, CommonIndexers, ContractSync,
+        ContractSyncMetrics, CoreMetrics, EvidenceBundle, HomeIndexers, Homes, IndexDataTypes,
+        Replicas, SettingsBuilder,
+    };
+    use nomad_core::{
+        DoubleUpdate, SignedFailureNotification, SignedUpdateWithMeta, State, Update, UpdateMeta,
     };
-    use nomad_core::{DoubleUpdate, SignedFailureNotification, State, Update};
     use nomad_test::mocks::{MockConnectionManagerContract, MockHomeContract, MockReplicaContract};
     use nomad_test::test_utils;
+    use nomad_xyz_configuration::agent::SignerConf;
 
     use super::*;
 
+    /// Settings of a watcher of `home_1` with two replicas
+    fn test_settings() -> nomad_base::Settings {
+        SettingsBuilder::new(
+            AGENT_NAME,
+            ChainSetupBuilder::new("home_1", 1).build().unwrap(),
+        )
+        .replica(ChainSetupBuilder::new("replica_1", 2).build().unwrap())
+        .replica(ChainSetupBuilder::new("replica_2", 3).build().unwrap())
+        .signer_role(WATCHER_KEY_ROLE, SignerConf::Node)
+        .build()
+        .unwrap()
+    }
+
     #[tokio::test]
     async fn contract_watcher_polls_and_sends_update() {
         test_utils::run_test_db(|db| async move {
@@ -779,7 +1306,15 @@ mod test {
                 .expect("Should have received Ok(())");
 
             assert_eq!(contract_watcher.committed_root, second_root);
-            assert_eq!(rx.recv().await.unwrap(), signed_update);
+            assert_eq!(
+                rx.recv().await.unwrap(),
+                UpdateToCheck {
+                    contract: "home_1".to_owned(),
+                    kind: CheckKind::Frontier,
+                    update: signed_update,
+                    checkpoint: None,
+                }
+            );
         })
         .await
     }
@@ -869,7 +1404,15 @@ mod test {
                 .expect("Should have received Ok(())");
 
             assert_eq!(history_sync.committed_root, first_root);
-            assert_eq!(rx.recv().await.unwrap(), second_signed_update);
+            assert_eq!(
+                rx.recv().await.unwrap(),
+                UpdateToCheck {
+                    contract: "home_1".to_owned(),
+                    kind: CheckKind::Audit,
+                    update: second_signed_update,
+                    checkpoint: None,
+                }
+            );
 
             // Second update_history call returns zero -> first update
             // and should return WatcherError::SyncingFinished
@@ -881,7 +1424,16 @@ mod test {
             );
 
             assert_eq!(history_sync.committed_root, zero_root);
-            assert_eq!(rx.recv().await.unwrap(), first_signed_update)
+            assert_eq!(
+                rx.recv().await.unwrap(),
+                UpdateToCheck {
+                    contract: "home_1".to_owned(),
+                    kind: CheckKind::Audit,
+                    update: first_signed_update,
+                    // The last update of the audit completes the history
+                    checkpoint: Some(second_root),
+                }
+            )
         })
         .await
     }
@@ -983,6 +1535,327 @@ mod test {
         .await
     }
 
+    #[tokio::test]
+    async fn fraud_detector_reports_conflicts_but_not_duplicates() {
+        let signer: LocalWallet =
+            "1111111111111111111111111111111111111111111111111111111111111111"
+                .parse()
+                .unwrap();
+        let sign = |previous_root, new_root| {
+            Update {
+                home_domain: 1,
+                previous_root,
+                new_root,
+            }
+            .sign_with(&signer)
+        };
+
+        let first = sign(H256::from([0; 32]), H256::from([1; 32]))
+            .await
+            .unwrap();
+        let second = sign(H256::from([1; 32]), H256::from([2; 32]))
+            .await
+            .unwrap();
+        let conflicting = sign(H256::from([1; 32]), H256::from([3; 32]))
+            .await
+            .unwrap();
+
+        let mut detector = FraudDetector::new();
+        assert_eq!(detector.record(first.clone()), None);
+        assert_eq!(detector.record(second.clone()), None);
+
+        // the same update seen again, e.g. from another indexer, is benign
+        assert_eq!(detector.record(first.clone()), None);
+        assert_eq!(detector.record(second.clone()), None);
+        let resigned = sign(H256::from([1; 32]), H256::from([2; 32]))
+            .await
+            .unwrap();
+        assert_eq!(detector.record(resigned), None);
+        assert_eq!(detector.len(), 2);
+
+        // another new root off the same previous root is fraud, reported
+        // against the first update off that root
+        let double = detector
+            .record(conflicting.clone())
+            .expect("!double update");
+        assert_eq!(double, DoubleUpdate(second.clone(), conflicting.clone()));
+        assert_eq!(double.0.update.previous_root, double.1.update.previous_root);
+        assert_ne!(double.0.update.new_root, double.1.update.new_root);
+        double.0.verify(signer.address()).unwrap();
+        double.1.verify(signer.address()).unwrap();
+        assert_eq!(
+            detector.record(conflicting.clone()),
+            Some(DoubleUpdate(second.clone(), conflicting))
+        );
+
+        // updates off different roots never conflict
+        assert_eq!(FraudDetector::conflict(&first, &second), None);
+    }
+
+    #[tokio::test]
+    async fn update_handler_only_advances_frontier_on_frontier_checks() {
+        test_utils::run_test_db(|db| async move {
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+
+            let first_root = H256::from([1; 32]);
+            let second_root = H256::from([2; 32]);
+            let third_root = H256::from([3; 32]);
+
+            let first_update = Update {
+                home_domain: 1,
+                previous_root: first_root,
+                new_root: second_root,
+            }
+            .sign_with(&signer)
+            .await
+            .expect("!sign");
+
+            let second_update = Update {
+                home_domain: 1,
+                previous_root: second_root,
+                new_root: third_root,
+            }
+            .sign_with(&signer)
+            .await
+            .expect("!sign");
+
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "contract_sync_test",
+                    "home",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .expect("could not make metrics"),
+            );
+            let sync_metrics = ContractSyncMetrics::new(metrics);
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+            mock_home
+                .expect__committed_root()
+                .times(..)
+                .returning(move || Ok(third_root));
+
+            let nomad_db = NomadDB::new("home_1_watcher", db);
+            let home_indexer: Arc<HomeIndexers> = Arc::new(MockIndexer::new().into());
+            let home_sync = ContractSync::new(
+                AGENT_NAME.to_owned(),
+                "home_1".to_owned(),
+                "replica_1".to_owned(),
+                nomad_db.clone(),
+                home_indexer,
+                IndexSettings::default(),
+                PageSettings::default(),
+                Default::default(),
+                sync_metrics,
+            );
+
+            let home: Arc<CachingHome> =
+                CachingHome::new(mock_home.into(), home_sync, nomad_db.clone()).into();
+
+            let (_tx, rx) = mpsc::channel(200);
+            let mut handler = UpdateHandler {
+                rx,
+                watcher_db: nomad_db.clone(),
+                home,
+            };
+
+            // Frontier check advances the replica's frontier
+            let double = handler
+                .handle_update(&UpdateToCheck {
+                    contract: "replica_1".to_owned(),
+                    kind: CheckKind::Frontier,
+                    update: second_update,
+                    checkpoint: None,
+                })
+                .await
+                .expect("!handle_update");
+            assert!(double.is_none());
+            assert_eq!(
+                nomad_db.retrieve_watcher_frontier("replica_1").unwrap(),
+                Some(third_root)
+            );
+
+            // Audit of older history leaves the frontier untouched
+            let double = handler
+                .handle_update(&UpdateToCheck {
+                    contract: "replica_1".to_owned(),
+                    kind: CheckKind::Audit,
+                    update: first_update,
+                    checkpoint: Some(third_root),
+                })
+                .await
+                .expect("!handle_update");
+            assert!(double.is_none());
+            assert_eq!(
+                nomad_db.retrieve_watcher_frontier("replica_1").unwrap(),
+                Some(third_root)
+            );
+            assert_eq!(nomad_db.retrieve_watcher_frontier("home_1").unwrap(), None);
+
+            // The last update of the audit moved the checkpoint
+            assert_eq!(
+                nomad_db
+                    .retrieve_watcher_audit_checkpoint("replica_1")
+                    .unwrap(),
+                Some(third_root)
+            );
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_resumes_from_the_audit_checkpoint_over_a_large_history() {
+        test_utils::run_test_db(|db| async move {
+            // Updates on the home, made while the watcher was down, and
+            // updates between the audit checkpoint and the frontier
+            const HISTORY: usize = 2000;
+            const MISSED: usize = 5;
+            const UNAUDITED: usize = 10;
+
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+
+            let roots: Vec<H256> = (0..=HISTORY as u64).map(H256::from_low_u64_be).collect();
+            let latest_root = roots[HISTORY];
+            let frontier = roots[HISTORY - MISSED];
+            let checkpoint = roots[HISTORY - MISSED - UNAUDITED];
+
+            let nomad_db = NomadDB::new("home_1", db.clone());
+            for pair in roots.windows(2) {
+                let update = Update {
+                    home_domain: 1,
+                    previous_root: pair[0],
+                    new_root: pair[1],
+                }
+                .sign_with(&signer)
+                .await
+                .expect("!sign");
+                nomad_db.store_latest_update(&update).unwrap();
+            }
+
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "contract_sync_test",
+                    "home",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .expect("could not make metrics"),
+            );
+            let sync_metrics = ContractSyncMetrics::new(metrics);
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+            mock_home
+                .expect__committed_root()
+                .times(..)
+                .returning(move || Ok(latest_root));
+
+            let home_indexer: Arc<HomeIndexers> = Arc::new(MockIndexer::new().into());
+            let home_sync = ContractSync::new(
+                AGENT_NAME.to_owned(),
+                "home_1".to_owned(),
+                "replica_1".to_owned(),
+                nomad_db.clone(),
+                home_indexer,
+                IndexSettings::default(),
+                PageSettings::default(),
+                Default::default(),
+                sync_metrics,
+            );
+            let home: Arc<CachingHome> =
+                CachingHome::new(mock_home.into(), home_sync, nomad_db).into();
+
+            // Records of the previous run
+            let watcher_db = NomadDB::new("home_1_watcher", db);
+            watcher_db
+                .store_watcher_frontier("home_1", frontier)
+                .unwrap();
+            watcher_db
+                .store_watcher_audit_checkpoint("home_1", checkpoint)
+                .unwrap();
+
+            let inspected = IntGauge::new("inspected", "Frontier updates inspected").unwrap();
+            let audited = IntGauge::new("audited", "Audit updates inspected").unwrap();
+            let seconds = IntGauge::new("time_to_protection", "Time to protection").unwrap();
+
+            let time_to_protection =
+                TimeToProtection::new(watcher_db.clone(), ["home_1".to_owned()], seconds.clone());
+            let (tx, rx) = mpsc::channel(200);
+            let mut contract_watcher =
+                ContractWatcher::new(3, frontier, tx.clone(), home.clone(), inspected.clone())
+                    .with_time_to_protection(time_to_protection.clone());
+            let mut history_sync =
+                HistorySync::new(3, frontier, tx.clone(), home.clone(), audited.clone())
+                    .with_checkpoint(Some(checkpoint));
+            let mut handler = UpdateHandler {
+                rx,
+                watcher_db: watcher_db.clone(),
+                home: home.clone(),
+            };
+
+            // Only the updates past the checkpoint are audited. The rest of
+            // the history is walked for continuity.
+            let res = loop {
+                if let Err(e) = history_sync.update_history().await {
+                    break e;
+                }
+            };
+            assert_eq!(res.to_string(), WatcherError::SyncingFinished.to_string());
+            assert_eq!(audited.get(), UNAUDITED as i64);
+
+            // The frontier catches up with the updates missed while down
+            for _ in 0..=MISSED {
+                contract_watcher.poll_and_send_update().await.unwrap();
+                while let Ok(to_check) = handler.rx.try_recv() {
+                    let double = handler.handle_update(&to_check).await.unwrap();
+                    assert!(double.is_none());
+                }
+            }
+            assert_eq!(inspected.get(), MISSED as i64);
+            assert_eq!(
+                watcher_db.retrieve_watcher_frontier("home_1").unwrap(),
+                Some(latest_root)
+            );
+            assert_eq!(
+                watcher_db
+                    .retrieve_watcher_audit_checkpoint("home_1")
+                    .unwrap(),
+                Some(frontier)
+            );
+
+            let protected_after = time_to_protection
+                .protected_after()
+                .expect("frontier should have caught up");
+            assert_eq!(seconds.get(), protected_after.as_secs() as i64);
+            assert!(
+                protected_after < Duration::from_secs(30),
+                "took {:?} to protect {} updates of history",
+                protected_after,
+                HISTORY
+            );
+
+            // A checkpoint whose history has a gap fails the continuity check
+            let missing_root = H256::repeat_byte(0xee);
+            let res = HistorySync::new(3, missing_root, tx, home, audited)
+                .with_checkpoint(Some(missing_root))
+                .update_history()
+                .await;
+            assert_eq!(
+                res.unwrap_err().to_string(),
+                WatcherError::HistoryGap(missing_root).to_string()
+            );
+        })
+        .await
+    }
+
     #[tokio::test]
     async fn it_fails_contracts_and_unenrolls_replicas_on_double_update() {
         test_utils::run_test_db(|db| async move {
@@ -1042,11 +1915,21 @@ mod test {
                     .times(1)
                     .return_once(move || home_domain);
 
-                let updater = updater.clone();
+                // Read for the failure notification and for evidence
+                let address = updater.address();
                 mock_home
                     .expect__updater()
+                    .times(2)
+                    .returning(move || Ok(address.into()));
+
+                mock_home
+                    .expect__state()
                     .times(1)
-                    .return_once(move || Ok(updater.address().into()));
+                    .return_once(move || Ok(State::Failed));
+                mock_home
+                    .expect__committed_root()
+                    .times(1)
+                    .return_once(move || Ok(first_root));
 
                 // home.double_update called once
                 let double = double.clone();
@@ -1057,6 +1940,8 @@ mod test {
                     .return_once(move |_| {
                         Ok(TxOutcome {
                             txid: H256::default(),
+                            gas_used: None,
+                            block_number: None,
                         })
                     });
             }
@@ -1064,6 +1949,15 @@ mod test {
                 mock_replica_1
                     .expect__name()
                     .return_const("replica_1".to_owned());
+                mock_replica_1.expect__local_domain().return_const(2u32);
+                mock_replica_1
+                    .expect__state()
+                    .times(1)
+                    .return_once(move || Ok(State::Active));
+                mock_replica_1
+                    .expect__committed_root()
+                    .times(1)
+                    .return_once(move || Ok(first_root));
 
                 // replica_1.double_update called once
                 let double = double.clone();
@@ -1074,6 +1968,8 @@ mod test {
                     .return_once(move |_| {
                         Ok(TxOutcome {
                             txid: H256::default(),
+                            gas_used: None,
+                            block_number: None,
                         })
                     });
             }
@@ -1081,6 +1977,15 @@ mod test {
                 mock_replica_2
                     .expect__name()
                     .return_const("replica_2".to_owned());
+                mock_replica_2.expect__local_domain().return_const(3u32);
+                mock_replica_2
+                    .expect__state()
+                    .times(1)
+                    .return_once(move || Ok(State::Active));
+                mock_replica_2
+                    .expect__committed_root()
+                    .times(1)
+                    .return_once(move || Ok(first_root));
 
                 // replica_2.double_update called once
                 let double = double.clone();
@@ -1091,12 +1996,21 @@ mod test {
                     .return_once(move |_| {
                         Ok(TxOutcome {
                             txid: H256::default(),
+                            gas_used: None,
+                            block_number: None,
                         })
                     });
             }
 
             // Connection manager expectations
             {
+                mock_connection_manager_1
+                    .expect__local_domain()
+                    .return_const(1u32);
+                mock_connection_manager_1
+                    .expect__get_block_number()
+                    .returning(|| Ok(100));
+
                 // connection_manager_1.unenroll_replica called once
                 let signed_failure = signed_failure;
                 mock_connection_manager_1
@@ -1106,10 +2020,19 @@ mod test {
                     .return_once(move |_| {
                         Ok(TxOutcome {
                             txid: H256::default(),
+                            gas_used: None,
+                            block_number: None,
                         })
                     });
             }
             {
+                mock_connection_manager_2
+                    .expect__local_domain()
+                    .return_const(2u32);
+                mock_connection_manager_2
+                    .expect__get_block_number()
+                    .returning(|| Ok(200));
+
                 // connection_manager_2.unenroll_replica called once
                 let signed_failure = signed_failure;
                 mock_connection_manager_2
@@ -1119,6 +2042,8 @@ mod test {
                     .return_once(move |_| {
                         Ok(TxOutcome {
                             txid: H256::default(),
+                            gas_used: None,
+                            block_number: None,
                         })
                     });
             }
@@ -1211,7 +2136,8 @@ mod test {
                     replicas: replica_map,
                     db,
                     indexer: IndexSettings::default(),
-                    settings: nomad_base::Settings::default(),
+                    settings: test_settings(),
+                    cancellation: Default::default(),
                     metrics: Arc::new(
                         nomad_base::CoreMetrics::new(
                             "watcher_test",
@@ -1224,9 +2150,47 @@ mod test {
                 };
 
                 {
-                    let watcher =
-                        Watcher::new(updater.into(), 1, connection_managers.clone(), core);
+                    let watcher = Watcher::new(
+                        updater.clone().into(),
+                        1,
+                        false,
+                        connection_managers.clone(),
+                        core,
+                    );
                     watcher.handle_double_update_failure(&double).await;
+
+                    // The response is stored as an incident whose evidence
+                    // verifies
+                    let incidents: Vec<_> = watcher
+                        .watcher_db()
+                        .incidents()
+                        .collect::<Result<_, _>>()
+                        .unwrap();
+                    assert_eq!(incidents.len(), 1);
+                    let incident = &incidents[0];
+                    assert_eq!(
+                        incident.fraud,
+                        Fraud::DoubleUpdate {
+                            updates: [double.0.clone(), double.1.clone()]
+                        }
+                    );
+                    assert_eq!(incident.contracts.len(), 5);
+                    assert_eq!(incident.state_reads.len(), 7);
+                    let home_reads: Vec<_> = incident
+                        .state_reads
+                        .iter()
+                        .filter(|read| read.contract == "home_1")
+                        .collect();
+                    assert_eq!(home_reads.len(), 3);
+                    assert!(home_reads.iter().all(|read| read.block_number == Some(100)));
+                    assert_eq!(home_reads[0].value, H256::from_low_u64_be(2));
+
+                    let bundle = EvidenceBundle::export(&watcher.watcher_db(), incident.id)
+                        .unwrap()
+                        .unwrap();
+                    assert_eq!(bundle.signer, Some(updater.address()));
+                    assert_eq!(bundle.transactions.len(), 5);
+                    bundle.verify_signatures().unwrap();
                 }
 
                 // Checkpoint connection managers
@@ -1243,6 +2207,259 @@ mod test {
         .await
     }
 
+    #[tokio::test]
+    async fn it_submits_double_updates_indexed_from_home_and_replica() {
+        test_utils::run_test_db(|db| async move {
+            let home_domain = 1;
+
+            let updater: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+
+            // The home indexes an update the replica's indexer contradicts
+            let first_root = H256::from([1; 32]);
+            let second_root = H256::from([2; 32]);
+            let bad_second_root = H256::from([3; 32]);
+
+            let mut indexed = vec![];
+            for new_root in [second_root, bad_second_root] {
+                indexed.push(SignedUpdateWithMeta {
+                    signed_update: Update {
+                        home_domain,
+                        previous_root: first_root,
+                        new_root,
+                    }
+                    .sign_with(&updater)
+                    .await
+                    .expect("!sign"),
+                    metadata: UpdateMeta {
+                        block_number: 18,
+                        timestamp: Default::default(),
+                    },
+                });
+            }
+            let is_double = move |d: &DoubleUpdate| {
+                let mut new_roots = [d.0.update.new_root, d.1.update.new_root];
+                new_roots.sort();
+                d.0.update.previous_root == first_root
+                    && d.1.update.previous_root == first_root
+                    && new_roots == [second_root, bad_second_root]
+            };
+            let outcome = |byte| TxOutcome {
+                txid: H256::repeat_byte(byte),
+                gas_used: None,
+                block_number: None,
+            };
+
+            let mut mock_home = MockHomeContract::new();
+            {
+                mock_home.expect__name().return_const("home_1".to_owned());
+                mock_home.expect__local_domain().return_const(home_domain);
+                let address = updater.address();
+                mock_home
+                    .expect__updater()
+                    .returning(move || Ok(address.into()));
+                mock_home
+                    .expect__state()
+                    .returning(move || Ok(State::Failed));
+                mock_home
+                    .expect__committed_root()
+                    .returning(move || Ok(first_root));
+
+                // The handler relays the first update it checks to the home
+                mock_home
+                    .expect__update()
+                    .returning(move |_| Ok(outcome(0x01)));
+                mock_home
+                    .expect__double_update()
+                    .withf(is_double)
+                    .times(1)
+                    .returning(move |_| Ok(outcome(0x10)));
+            }
+            let mut mock_replicas = vec![];
+            for (name, domain, txid) in [("replica_1", 2u32, 0x11), ("replica_2", 3, 0x12)] {
+                let mut mock_replica = MockReplicaContract::new();
+                mock_replica.expect__name().return_const(name.to_owned());
+                mock_replica.expect__local_domain().return_const(domain);
+                mock_replica
+                    .expect__state()
+                    .returning(move || Ok(State::Active));
+                mock_replica
+                    .expect__committed_root()
+                    .returning(move || Ok(first_root));
+                mock_replica
+                    .expect__double_update()
+                    .withf(is_double)
+                    .times(1)
+                    .returning(move |_| Ok(outcome(txid)));
+                mock_replicas.push(mock_replica);
+            }
+            let mut connection_managers: Vec<Arc<ConnectionManagers>> = vec![];
+            for (domain, txid) in [(1u32, 0x21), (2, 0x22)] {
+                let mut mock_connection_manager = MockConnectionManagerContract::new();
+                mock_connection_manager
+                    .expect__local_domain()
+                    .return_const(domain);
+                mock_connection_manager
+                    .expect__get_block_number()
+                    .returning(|| Ok(100));
+                mock_connection_manager
+                    .expect__unenroll_replica()
+                    .times(1)
+                    .returning(move |_| Ok(outcome(txid)));
+                connection_managers.push(Arc::new(mock_connection_manager.into()));
+            }
+
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "contract_sync_test",
+                    "home",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .expect("could not make metrics"),
+            );
+            let sync_metrics = ContractSyncMetrics::new(metrics);
+            let index_settings = IndexSettings {
+                data_types: IndexDataTypes::Updates,
+                use_timelag: true,
+                max_block_range: None,
+                backoff: Default::default(),
+            };
+            let page_settings = PageSettings {
+                from: 0,
+                page_size: 50,
+            };
+            let mock_indexer = |update: SignedUpdateWithMeta| {
+                let mut mock_indexer = MockIndexer::new();
+                mock_indexer.expect__get_block_number().returning(|| Ok(20));
+                mock_indexer
+                    .expect__fetch_sorted_updates()
+                    .returning(move |_, _| Ok(vec![update.clone()]));
+                mock_indexer
+            };
+
+            // The home and replica_1 index conflicting updates. replica_2
+            // indexes nothing.
+            let home_db = NomadDB::new("home_1", db.clone());
+            let replica_1_db = NomadDB::new("replica_1", db.clone());
+            let replica_2_db = NomadDB::new("replica_2", db.clone());
+            let home_indexer: Arc<HomeIndexers> = Arc::new(mock_indexer(indexed[0].clone()).into());
+            let replica_1_indexer: Arc<CommonIndexers> =
+                Arc::new(mock_indexer(indexed[1].clone()).into());
+            let replica_2_indexer: Arc<CommonIndexers> = Arc::new(MockIndexer::new().into());
+
+            let home_sync = ContractSync::new(
+                AGENT_NAME.to_owned(),
+                "home_1".to_owned(),
+                "home_1".to_owned(),
+                home_db.clone(),
+                home_indexer,
+                index_settings.clone(),
+                page_settings.clone(),
+                Default::default(),
+                sync_metrics.clone(),
+            );
+            let replica_1_sync = ContractSync::new(
+                AGENT_NAME.to_owned(),
+                "home_1".to_owned(),
+                "replica_1".to_owned(),
+                replica_1_db.clone(),
+                replica_1_indexer,
+                index_settings.clone(),
+                page_settings.clone(),
+                Default::default(),
+                sync_metrics.clone(),
+            );
+            let replica_2_sync = ContractSync::new(
+                AGENT_NAME.to_owned(),
+                "home_1".to_owned(),
+                "replica_2".to_owned(),
+                replica_2_db.clone(),
+                replica_2_indexer,
+                index_settings.clone(),
+                page_settings,
+                Default::default(),
+                sync_metrics,
+            );
+            let home_sync_task = home_sync.sync_updates();
+            let replica_1_sync_task = replica_1_sync.sync_updates();
+
+            let home: Arc<CachingHome> =
+                CachingHome::new(mock_home.into(), home_sync, home_db).into();
+            let mut replica_map: HashMap<String, Arc<CachingReplica>> = HashMap::new();
+            for ((mock_replica, sync), replica_db) in mock_replicas
+                .into_iter()
+                .zip([replica_1_sync, replica_2_sync])
+                .zip([replica_1_db, replica_2_db])
+            {
+                let replica: Replicas = mock_replica.into();
+                let replica = CachingReplica::new(replica, sync, replica_db);
+                replica_map.insert(replica.name().to_owned(), replica.into());
+            }
+
+            let core = AgentCore {
+                home: home.clone(),
+                replicas: replica_map,
+                db,
+                indexer: index_settings,
+                settings: test_settings(),
+                cancellation: Default::default(),
+                metrics: Arc::new(
+                    nomad_base::CoreMetrics::new(
+                        "watcher_test",
+                        "home",
+                        None,
+                        Arc::new(prometheus::Registry::new()),
+                    )
+                    .expect("could not make metrics"),
+                ),
+            };
+            let watcher = Watcher::new(updater.into(), 1, false, connection_managers, core);
+
+            let double =
+                tokio::time::timeout(Duration::from_secs(30), watcher.watch_double_update())
+                    .await
+                    .expect("double update not detected in time")
+                    .expect("!join")
+                    .expect("!watch")
+                    .expect("watch ended without a double update");
+            assert!(is_double(&double));
+
+            // The first update seen is persisted, so the double update is
+            // detected again after a restart
+            let (_tx, rx) = mpsc::channel(1);
+            let mut restarted = UpdateHandler::new(rx, watcher.watcher_db(), home);
+            assert_eq!(
+                restarted.check_double_update(&double.1),
+                Err(double.clone())
+            );
+
+            // The double update is submitted to the home and every replica,
+            // and every connection manager unenrolls
+            let mut txids: Vec<_> = watcher
+                .handle_double_update_failure(&double)
+                .await
+                .into_iter()
+                .map(|res| res.expect("!submit").txid)
+                .collect();
+            txids.sort();
+            assert_eq!(
+                txids,
+                [0x10, 0x11, 0x12, 0x21, 0x22]
+                    .into_iter()
+                    .map(H256::repeat_byte)
+                    .collect::<Vec<_>>()
+            );
+
+            watcher.shutdown().await;
+            cancel_task!(home_sync_task);
+            cancel_task!(replica_1_sync_task);
+        })
+        .await
+    }
+
     #[tokio::test]
     async fn it_unenrolls_replicas_on_improper_update() {
         test_utils::run_test_db(|db| async move {
@@ -1266,8 +2483,8 @@ mod test {
             let mut mock_connection_manager_2 = MockConnectionManagerContract::new();
 
             let mut mock_home = MockHomeContract::new();
-            let mock_replica_1 = MockReplicaContract::new();
-            let mock_replica_2 = MockReplicaContract::new();
+            let mut mock_replica_1 = MockReplicaContract::new();
+            let mut mock_replica_2 = MockReplicaContract::new();
 
             // Home and replica expectations
             {
@@ -1278,21 +2495,44 @@ mod test {
                     .times(1)
                     .return_once(move || home_domain);
 
-                let updater = updater.clone();
+                // Read for the failure notification and for evidence
+                let address = updater.address();
                 mock_home
                     .expect__updater()
-                    .times(1)
-                    .return_once(move || Ok(updater.address().into()));
+                    .times(2)
+                    .returning(move || Ok(address.into()));
 
-                // Home returns failed state
+                // Home returns failed state, when watched and for evidence
                 mock_home
                     .expect__state()
+                    .times(2)
+                    .returning(move || Ok(State::Failed));
+                mock_home
+                    .expect__committed_root()
                     .times(1)
-                    .return_once(move || Ok(State::Failed));
+                    .return_once(move || Ok(H256::zero()));
+            }
+            for (replica, domain) in [(&mut mock_replica_1, 2u32), (&mut mock_replica_2, 3)] {
+                replica.expect__local_domain().return_const(domain);
+                replica
+                    .expect__state()
+                    .times(1)
+                    .return_once(move || Ok(State::Active));
+                replica
+                    .expect__committed_root()
+                    .times(1)
+                    .return_once(move || Ok(H256::zero()));
             }
 
             // Connection manager expectations
             {
+                mock_connection_manager_1
+                    .expect__local_domain()
+                    .return_const(1u32);
+                mock_connection_manager_1
+                    .expect__get_block_number()
+                    .returning(|| Ok(100));
+
                 // connection_manager_1.unenroll_replica called once
                 let signed_failure = signed_failure;
                 mock_connection_manager_1
@@ -1302,10 +2542,19 @@ mod test {
                     .return_once(move |_| {
                         Ok(TxOutcome {
                             txid: H256::default(),
+                            gas_used: None,
+                            block_number: None,
                         })
                     });
             }
             {
+                mock_connection_manager_2
+                    .expect__local_domain()
+                    .return_const(2u32);
+                mock_connection_manager_2
+                    .expect__get_block_number()
+                    .returning(|| Ok(200));
+
                 // connection_manager_2.unenroll_replica called once
                 let signed_failure = signed_failure;
                 mock_connection_manager_2
@@ -1315,6 +2564,8 @@ mod test {
                     .return_once(move |_| {
                         Ok(TxOutcome {
                             txid: H256::default(),
+                            gas_used: None,
+                            block_number: None,
                         })
                     });
             }
@@ -1407,7 +2658,8 @@ mod test {
                     replicas: replica_map,
                     db,
                     indexer: IndexSettings::default(),
-                    settings: nomad_base::Settings::default(),
+                    settings: test_settings(),
+                    cancellation: Default::default(),
                     metrics: Arc::new(
                         nomad_base::CoreMetrics::new(
                             "watcher_test",
@@ -1419,7 +2671,13 @@ mod test {
                     ),
                 };
 
-                let watcher = Watcher::new(updater.into(), 1, connection_managers.clone(), core);
+                let watcher = Watcher::new(
+                    updater.clone().into(),
+                    1,
+                    false,
+                    connection_managers.clone(),
+                    core,
+                );
                 let state = watcher
                     .watch_home_fail(1)
                     .await
@@ -1432,6 +2690,22 @@ mod test {
                 assert!(matches!(state, BaseError::FailedHome));
 
                 watcher.handle_improper_update_failure().await;
+
+                let incidents: Vec<_> = watcher
+                    .watcher_db()
+                    .incidents()
+                    .collect::<Result<_, _>>()
+                    .unwrap();
+                assert_eq!(incidents.len(), 1);
+                assert_eq!(incidents[0].fraud, Fraud::ImproperUpdate);
+                assert_eq!(incidents[0].failure_notification(), signed_failure);
+
+                let bundle = EvidenceBundle::export(&watcher.watcher_db(), incidents[0].id)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(bundle.signer, None);
+                assert_eq!(bundle.failure_notification.watcher, updater.address());
+                assert_eq!(bundle.transactions.len(), 2);
             }
 
             // Checkpoint connection managers
@@ -0,0 +1,117 @@
+//! Cost of decoding `Dispatch` logs in bulk, as indexers backfilling a home
+//! do.
+//!
+//! Decodes the same synthetic logs into owned records through the owned
+//! decoder and through borrowed views, several rounds each, and compares the
+//! fastest round of each. Checks both yield the same records, and fails if
+//! the borrowed path is not faster.
+
+use nomad_ethereum_bindings::{
+    ethers::{
+        abi::{self, RawLog, Token},
+        contract::EthEvent,
+        prelude::{Log, H256},
+    },
+    home::DispatchFilter,
+    prelude::*,
+    views::DispatchView,
+};
+use std::time::{Duration, Instant};
+
+const LOGS: u64 = 100_000;
+/// Message size: a 77 byte header and a token transfer sized body
+const MESSAGE_BYTES: usize = 77 + 160;
+const ROUNDS: usize = 5;
+
+/// Owned record built from each log, as stored after ingestion
+#[derive(Debug, PartialEq, Eq)]
+struct Record {
+    leaf_index: u64,
+    committed_root: [u8; 32],
+    message: Vec<u8>,
+}
+
+fn logs() -> Vec<Log> {
+    (0..LOGS)
+        .map(|leaf_index| Log {
+            topics: vec![
+                DispatchFilter::signature(),
+                H256::from_low_u64_be(leaf_index ^ 0x5555),
+                H256::from_low_u64_be(leaf_index),
+                H256::from_low_u64_be(2000 << 32 | leaf_index),
+            ],
+            data: abi::encode(&[
+                Token::FixedBytes(H256::from_low_u64_be(leaf_index).as_bytes().to_vec()),
+                Token::Bytes(vec![leaf_index as u8; MESSAGE_BYTES]),
+            ])
+            .into(),
+            block_number: Some((leaf_index / 4).into()),
+            log_index: Some((leaf_index % 4).into()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Decode as the owned event query does: copy the log, decode the copy
+fn owned(logs: &[Log]) -> Vec<Record> {
+    logs.iter()
+        .map(|log| {
+            let raw = RawLog {
+                topics: log.topics.clone(),
+                data: log.data.to_vec(),
+            };
+            let dispatch = DispatchFilter::decode_log(&raw).expect("!decode");
+            Record {
+                leaf_index: dispatch.leaf_index.as_u64(),
+                committed_root: dispatch.committed_root,
+                message: dispatch.message.to_vec(),
+            }
+        })
+        .collect()
+}
+
+/// Decode borrowing from the logs, copying only into the record
+fn borrowed(logs: &[Log]) -> Vec<Record> {
+    logs.iter()
+        .map(|log| {
+            let dispatch: DispatchView =
+                decode_log_borrowed(&log.topics, &log.data).expect("!decode");
+            Record {
+                leaf_index: dispatch.leaf_index.as_u64(),
+                committed_root: *dispatch.committed_root,
+                message: dispatch.message.to_vec(),
+            }
+        })
+        .collect()
+}
+
+/// Time decoding `logs` with `decode`, and its records
+fn time(logs: &[Log], decode: fn(&[Log]) -> Vec<Record>) -> (Duration, Vec<Record>) {
+    let started = Instant::now();
+    let records = decode(logs);
+    (started.elapsed(), records)
+}
+
+fn main() {
+    let logs = logs();
+
+    let (mut owned_best, mut borrowed_best) = (Duration::MAX, Duration::MAX);
+    for _ in 0..ROUNDS {
+        let (owned_elapsed, owned_records) = time(&logs, owned);
+        let (borrowed_elapsed, borrowed_records) = time(&logs, borrowed);
+        assert_eq!(owned_records, borrowed_records, "decoders disagree");
+        owned_best = owned_best.min(owned_elapsed);
+        borrowed_best = borrowed_best.min(borrowed_elapsed);
+    }
+
+    let rate = |elapsed: Duration| LOGS as f64 / elapsed.as_secs_f64();
+    let speedup = owned_best.as_secs_f64() / borrowed_best.as_secs_f64();
+    println!("owned:    {:.0} logs/s", rate(owned_best));
+    println!("borrowed: {:.0} logs/s", rate(borrowed_best));
+    println!("speedup:  {:.2}x", speedup);
+    assert!(
+        speedup > 1.0,
+        "borrowed decoding is not faster than owned decoding ({:.2}x)",
+        speedup
+    );
+}
@@ -10,8 +10,17 @@ use ethers::contract::Abigen;
 static ABI_DIR: &str = "./abis";
 static BINDINGS_DIR: &str = "./src/bindings";
 
+/// Set to regenerate the checked-in bindings from `abis/`. Published builds
+/// must not write to `src/`, so bindings are only regenerated on request.
+static REGENERATE_VAR: &str = "NOMAD_REGENERATE_BINDINGS";
+
 fn main() {
     println!("cargo:rerun-if-changed={}", ABI_DIR);
+    println!("cargo:rerun-if-env-changed={}", REGENERATE_VAR);
+
+    if std::env::var_os(REGENERATE_VAR).is_none() {
+        return;
+    }
 
     clean_old_bindings();
 
@@ -32,7 +41,7 @@ fn main() {
     names.sort();
 
     for name in names.iter() {
-        writeln!(mod_file, "pub(crate) mod {};", name).expect("failed to write to modfile");
+        writeln!(mod_file, "pub mod {};", name).expect("failed to write to modfile");
     }
 }
 
@@ -40,6 +49,7 @@ fn create_mod_rs() -> File {
     let mod_file_path = PathBuf::from(&format!("{}/mod.rs", BINDINGS_DIR));
     let mut mod_file = std::fs::File::create(&mod_file_path).expect("could not create modfile");
     writeln!(mod_file, "#![allow(clippy::all)]").unwrap();
+    writeln!(mod_file, "#![allow(missing_docs)]").unwrap();
     mod_file
 }
 
@@ -0,0 +1,5 @@
+#![allow(clippy::all)]
+#![allow(missing_docs)]
+pub mod home;
+pub mod replica;
+pub mod xappconnectionmanager;
@@ -0,0 +1,104 @@
+//! Generated bindings for the Nomad core contracts.
+//!
+//! Bindings are generated with ethers-rs abigen from the ABIs in `abis/`.
+//! The [`prelude`] re-exports the contract types, their call and event enums
+//! and their ABIs. Event structs live in the contract modules, as several
+//! contracts share event names.
+//!
+//! Reading from a Home:
+//!
+//! ```no_run
+//! use nomad_ethereum_bindings::{
+//!     ethers::prelude::{Address, Http, Provider},
+//!     prelude::*,
+//! };
+//! use std::{convert::TryFrom, sync::Arc};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = Arc::new(Provider::<Http>::try_from("http://localhost:8545")?);
+//! let address: Address = "0x92d3404a7e6c91455bbd81475cd9fad96aca4e3b".parse()?;
+//! let home = Home::new(address, provider);
+//!
+//! let committed_root: [u8; 32] = home.committed_root().call().await?;
+//! let count = home.count().call().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Querying a Home's `Dispatch` events:
+//!
+//! ```no_run
+//! use nomad_ethereum_bindings::{
+//!     ethers::prelude::{Address, Http, Provider},
+//!     home::DispatchFilter,
+//!     prelude::*,
+//! };
+//! use std::{convert::TryFrom, sync::Arc};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = Arc::new(Provider::<Http>::try_from("http://localhost:8545")?);
+//! let address: Address = "0x92d3404a7e6c91455bbd81475cd9fad96aca4e3b".parse()?;
+//! let home = Home::new(address, provider);
+//!
+//! let dispatches: Vec<DispatchFilter> = home
+//!     .dispatch_filter()
+//!     .from_block(15_000_000u64)
+//!     .to_block(15_001_000u64)
+//!     .query()
+//!     .await?;
+//! for dispatch in dispatches {
+//!     println!("leaf {} has {} body bytes", dispatch.leaf_index, dispatch.message.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Decoding raw `Dispatch` logs in bulk without copying them, as the
+//! [`views`] borrow from the logs:
+//!
+//! ```no_run
+//! use nomad_ethereum_bindings::{
+//!     ethers::prelude::{Filter, Http, Log, Middleware, Provider},
+//!     prelude::*,
+//!     views::DispatchView,
+//! };
+//! use std::convert::TryFrom;
+//!
+//! # async fn example(filter: Filter) -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = Provider::<Http>::try_from("http://localhost:8545")?;
+//! let logs: Vec<Log> = provider.get_logs(&filter).await?;
+//! for log in &logs {
+//!     let dispatch: DispatchView = decode_log_borrowed(&log.topics, &log.data)?;
+//!     println!("leaf {} has {} body bytes", dispatch.leaf_index, dispatch.message.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+#![warn(unused_extern_crates)]
+
+/// The ethers-rs the bindings are generated against. Use it to build
+/// providers and middleware of matching versions.
+pub use ethers;
+
+mod bindings;
+
+pub use bindings::{home, replica, xappconnectionmanager};
+
+pub mod views;
+
+/// Contract types, call and event enums, ABIs of the core contracts, and
+/// borrowed event decoding
+pub mod prelude {
+    pub use crate::home::{Home, HomeCalls, HomeEvents, HOME_ABI};
+    pub use crate::replica::{Replica, ReplicaCalls, ReplicaEvents, REPLICA_ABI};
+    pub use crate::views::{
+        decode_log_borrowed, EthLogDecodeBorrowed, HomeEventView, ReplicaEventView,
+    };
+    pub use crate::xappconnectionmanager::{
+        XAppConnectionManager, XAppConnectionManagerCalls, XAppConnectionManagerEvents,
+        XAPPCONNECTIONMANAGER_ABI,
+    };
+}
@@ -0,0 +1,405 @@
+//! Borrowed views of events, for decoding logs in bulk.
+//!
+//! The owned decoders copy a log's topics and data into a `RawLog`, then
+//! copy byte fields again into the event struct. The views decode straight
+//! from the log's buffers: topics are borrowed as fixed arrays and dynamic
+//! fields as slices of the data. Views live as long as the logs they borrow
+//! from, so convert them into owned records before the batch is dropped.
+//!
+//! Views cover the events indexers ingest in bulk. Other events decode
+//! through the owned path.
+
+use ethers::{
+    contract::EthLogDecode,
+    core::{
+        abi::{Error, RawLog},
+        types::{Bytes, H256, U256},
+    },
+};
+use once_cell::sync::Lazy;
+
+use crate::{home, replica};
+
+static DISPATCH_SIGNATURE: Lazy<H256> =
+    Lazy::new(<home::DispatchFilter as ethers::contract::EthEvent>::signature);
+static UPDATE_SIGNATURE: Lazy<H256> =
+    Lazy::new(<home::UpdateFilter as ethers::contract::EthEvent>::signature);
+
+/// Decoding of a log into a view borrowing from its topics and data
+pub trait EthLogDecodeBorrowed<'a>: Sized {
+    /// Decode the log with `topics` and `data`, borrowing from both
+    fn decode_log_borrowed(topics: &'a [H256], data: &'a [u8]) -> Result<Self, Error>;
+}
+
+/// Borrowed view of a Home `Dispatch` event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DispatchView<'a> {
+    /// Hash of the message
+    pub message_hash: &'a [u8; 32],
+    /// Index of the message's leaf
+    pub leaf_index: U256,
+    /// Destination domain and nonce of the message
+    pub destination_and_nonce: u64,
+    /// Root committed to when the message was dispatched
+    pub committed_root: &'a [u8; 32],
+    /// The message
+    pub message: &'a [u8],
+}
+
+impl<'a> EthLogDecodeBorrowed<'a> for DispatchView<'a> {
+    fn decode_log_borrowed(topics: &'a [H256], data: &'a [u8]) -> Result<Self, Error> {
+        let topics = event_topics(topics, &DISPATCH_SIGNATURE, 3)?;
+        Ok(Self {
+            message_hash: topics[0].as_fixed_bytes(),
+            leaf_index: U256::from_big_endian(topics[1].as_bytes()),
+            destination_and_nonce: topic_int(&topics[2])?,
+            committed_root: word(data, 0)?,
+            message: dynamic_bytes(data, 1)?,
+        })
+    }
+}
+
+impl From<DispatchView<'_>> for home::DispatchFilter {
+    fn from(view: DispatchView<'_>) -> Self {
+        Self {
+            message_hash: *view.message_hash,
+            leaf_index: view.leaf_index,
+            destination_and_nonce: view.destination_and_nonce,
+            committed_root: *view.committed_root,
+            message: Bytes::from(view.message.to_vec()),
+        }
+    }
+}
+
+/// Borrowed view of a Home or Replica `Update` event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateView<'a> {
+    /// Domain of the home
+    pub home_domain: u32,
+    /// Root the update builds off
+    pub old_root: &'a [u8; 32],
+    /// Root the update moves to
+    pub new_root: &'a [u8; 32],
+    /// Updater signature
+    pub signature: &'a [u8],
+}
+
+impl<'a> EthLogDecodeBorrowed<'a> for UpdateView<'a> {
+    fn decode_log_borrowed(topics: &'a [H256], data: &'a [u8]) -> Result<Self, Error> {
+        let topics = event_topics(topics, &UPDATE_SIGNATURE, 3)?;
+        Ok(Self {
+            home_domain: topic_int(&topics[0])?,
+            old_root: topics[1].as_fixed_bytes(),
+            new_root: topics[2].as_fixed_bytes(),
+            signature: dynamic_bytes(data, 0)?,
+        })
+    }
+}
+
+impl From<UpdateView<'_>> for home::UpdateFilter {
+    fn from(view: UpdateView<'_>) -> Self {
+        Self {
+            home_domain: view.home_domain,
+            old_root: *view.old_root,
+            new_root: *view.new_root,
+            signature: Bytes::from(view.signature.to_vec()),
+        }
+    }
+}
+
+impl From<UpdateView<'_>> for replica::UpdateFilter {
+    fn from(view: UpdateView<'_>) -> Self {
+        Self {
+            home_domain: view.home_domain,
+            old_root: *view.old_root,
+            new_root: *view.new_root,
+            signature: Bytes::from(view.signature.to_vec()),
+        }
+    }
+}
+
+/// Home event, borrowed where a view exists and owned otherwise
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HomeEventView<'a> {
+    /// `Dispatch`
+    Dispatch(DispatchView<'a>),
+    /// `Update`
+    Update(UpdateView<'a>),
+    /// Any other event, decoded by the owned decoder
+    Owned(home::HomeEvents),
+}
+
+impl<'a> EthLogDecodeBorrowed<'a> for HomeEventView<'a> {
+    fn decode_log_borrowed(topics: &'a [H256], data: &'a [u8]) -> Result<Self, Error> {
+        match topics.first() {
+            Some(signature) if *signature == *DISPATCH_SIGNATURE => {
+                DispatchView::decode_log_borrowed(topics, data).map(Self::Dispatch)
+            }
+            Some(signature) if *signature == *UPDATE_SIGNATURE => {
+                UpdateView::decode_log_borrowed(topics, data).map(Self::Update)
+            }
+            _ => home::HomeEvents::decode_log(&raw_log(topics, data)).map(Self::Owned),
+        }
+    }
+}
+
+impl From<HomeEventView<'_>> for home::HomeEvents {
+    fn from(view: HomeEventView<'_>) -> Self {
+        match view {
+            HomeEventView::Dispatch(view) => Self::DispatchFilter(view.into()),
+            HomeEventView::Update(view) => Self::UpdateFilter(view.into()),
+            HomeEventView::Owned(event) => event,
+        }
+    }
+}
+
+/// Replica event, borrowed where a view exists and owned otherwise
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplicaEventView<'a> {
+    /// `Update`
+    Update(UpdateView<'a>),
+    /// Any other event, decoded by the owned decoder
+    Owned(replica::ReplicaEvents),
+}
+
+impl<'a> EthLogDecodeBorrowed<'a> for ReplicaEventView<'a> {
+    fn decode_log_borrowed(topics: &'a [H256], data: &'a [u8]) -> Result<Self, Error> {
+        match topics.first() {
+            Some(signature) if *signature == *UPDATE_SIGNATURE => {
+                UpdateView::decode_log_borrowed(topics, data).map(Self::Update)
+            }
+            _ => replica::ReplicaEvents::decode_log(&raw_log(topics, data)).map(Self::Owned),
+        }
+    }
+}
+
+impl From<ReplicaEventView<'_>> for replica::ReplicaEvents {
+    fn from(view: ReplicaEventView<'_>) -> Self {
+        match view {
+            ReplicaEventView::Update(view) => Self::UpdateFilter(view.into()),
+            ReplicaEventView::Owned(event) => event,
+        }
+    }
+}
+
+/// Decode the log with `topics` and `data` into `V`, borrowing from both.
+/// Shorthand for [`EthLogDecodeBorrowed::decode_log_borrowed`].
+pub fn decode_log_borrowed<'a, V>(topics: &'a [H256], data: &'a [u8]) -> Result<V, Error>
+where
+    V: EthLogDecodeBorrowed<'a>,
+{
+    V::decode_log_borrowed(topics, data)
+}
+
+/// Copy of the log, for the owned decoders
+fn raw_log(topics: &[H256], data: &[u8]) -> RawLog {
+    RawLog {
+        topics: topics.to_vec(),
+        data: data.to_vec(),
+    }
+}
+
+/// The `indexed` topics of an event with `signature`, checked in number
+fn event_topics<'a>(
+    topics: &'a [H256],
+    signature: &H256,
+    indexed: usize,
+) -> Result<&'a [H256], Error> {
+    match topics.split_first() {
+        Some((first, rest)) if first == signature && rest.len() == indexed => Ok(rest),
+        _ => Err(Error::InvalidData),
+    }
+}
+
+/// An indexed integer topic, rejecting values that do not fit `T` rather
+/// than keeping their low bits as the owned decoders do
+fn topic_int<T: TryFrom<U256>>(topic: &H256) -> Result<T, Error> {
+    T::try_from(U256::from_big_endian(topic.as_bytes())).map_err(|_| Error::InvalidData)
+}
+
+/// ABI word number `index` of `data`
+fn word(data: &[u8], index: usize) -> Result<&[u8; 32], Error> {
+    word_at(data, index.checked_mul(32).ok_or(Error::InvalidData)?)
+}
+
+/// The 32 bytes of `data` from `offset`
+fn word_at(data: &[u8], offset: usize) -> Result<&[u8; 32], Error> {
+    data.get(offset..offset.checked_add(32).ok_or(Error::InvalidData)?)
+        .and_then(|word| word.try_into().ok())
+        .ok_or(Error::InvalidData)
+}
+
+/// An ABI word as an offset or length
+fn as_usize(word: &[u8; 32]) -> Result<usize, Error> {
+    if word[..24].iter().any(|&byte| byte != 0) {
+        return Err(Error::InvalidData);
+    }
+    let mut low = [0u8; 8];
+    low.copy_from_slice(&word[24..]);
+    usize::try_from(u64::from_be_bytes(low)).map_err(|_| Error::InvalidData)
+}
+
+/// The `bytes` whose offset is ABI word number `index` of `data`
+fn dynamic_bytes(data: &[u8], index: usize) -> Result<&[u8], Error> {
+    let offset = as_usize(word(data, index)?)?;
+    let len = as_usize(word_at(data, offset)?)?;
+    let start = offset + 32;
+    data.get(start..start.checked_add(len).ok_or(Error::InvalidData)?)
+        .ok_or(Error::InvalidData)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::core::abi::{self, Token};
+
+    fn dispatch_log(leaf_index: u64, message: Vec<u8>) -> RawLog {
+        RawLog {
+            topics: vec![
+                *DISPATCH_SIGNATURE,
+                H256::repeat_byte(0xaa),
+                H256::from_low_u64_be(leaf_index),
+                H256::from_low_u64_be(2000 << 32 | leaf_index),
+            ],
+            data: abi::encode(&[Token::FixedBytes(vec![0xcc; 32]), Token::Bytes(message)]),
+        }
+    }
+
+    fn update_log(home_domain: u64, signature: Vec<u8>) -> RawLog {
+        RawLog {
+            topics: vec![
+                *UPDATE_SIGNATURE,
+                H256::from_low_u64_be(home_domain),
+                H256::repeat_byte(1),
+                H256::repeat_byte(2),
+            ],
+            data: abi::encode(&[Token::Bytes(signature)]),
+        }
+    }
+
+    fn borrowed<'a, V: EthLogDecodeBorrowed<'a>>(log: &'a RawLog) -> Result<V, Error> {
+        decode_log_borrowed(&log.topics, &log.data)
+    }
+
+    #[test]
+    fn it_decodes_dispatches_as_the_owned_decoder() {
+        for message in [
+            vec![],
+            vec![0x42],
+            vec![0x92; 31],
+            vec![0x92; 32],
+            vec![7; 1000],
+        ] {
+            let log = dispatch_log(17, message.clone());
+            let view: DispatchView = borrowed(&log).unwrap();
+            assert_eq!(view.message, message.as_slice());
+            assert_eq!(view.leaf_index, 17.into());
+
+            let owned = home::DispatchFilter::decode_log(&log).unwrap();
+            assert_eq!(home::DispatchFilter::from(view), owned);
+
+            let event: HomeEventView = borrowed(&log).unwrap();
+            assert_eq!(event, HomeEventView::Dispatch(view));
+            assert_eq!(
+                home::HomeEvents::from(event),
+                home::HomeEvents::decode_log(&log).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn it_decodes_updates_as_the_owned_decoder() {
+        let log = update_log(1000, vec![0x5a; 65]);
+
+        let view: UpdateView = borrowed(&log).unwrap();
+        assert_eq!(view.home_domain, 1000);
+        assert_eq!(view.signature, &[0x5a; 65][..]);
+        assert_eq!(
+            home::UpdateFilter::from(view),
+            home::UpdateFilter::decode_log(&log).unwrap()
+        );
+        assert_eq!(
+            replica::UpdateFilter::from(view),
+            replica::UpdateFilter::decode_log(&log).unwrap()
+        );
+
+        let home_event: HomeEventView = borrowed(&log).unwrap();
+        assert_eq!(
+            home::HomeEvents::from(home_event),
+            home::HomeEvents::decode_log(&log).unwrap()
+        );
+        let replica_event: ReplicaEventView = borrowed(&log).unwrap();
+        assert_eq!(replica_event, ReplicaEventView::Update(view));
+        assert_eq!(
+            replica::ReplicaEvents::from(replica_event),
+            replica::ReplicaEvents::decode_log(&log).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_decodes_other_events_through_the_owned_decoder() {
+        let log = RawLog {
+            topics: vec![
+                <home::UpdaterSlashedFilter as ethers::contract::EthEvent>::signature(),
+                H256::from(ethers::core::types::Address::repeat_byte(1)),
+                H256::from(ethers::core::types::Address::repeat_byte(2)),
+            ],
+            data: vec![],
+        };
+        let owned = home::HomeEvents::decode_log(&log).unwrap();
+        assert_eq!(
+            borrowed::<HomeEventView>(&log).unwrap(),
+            HomeEventView::Owned(owned)
+        );
+    }
+
+    #[test]
+    fn it_rejects_integer_topics_out_of_range() {
+        // A home domain with its high bytes set
+        let mut log = update_log(1000, vec![0x5a; 65]);
+        log.topics[1].0[0] = 0x01;
+        assert!(borrowed::<UpdateView>(&log).is_err());
+        assert!(borrowed::<HomeEventView>(&log).is_err());
+        assert!(borrowed::<ReplicaEventView>(&log).is_err());
+
+        // Just above u32::MAX
+        let mut log = update_log(u64::from(u32::MAX) + 1, vec![0x5a; 65]);
+        assert!(borrowed::<UpdateView>(&log).is_err());
+        log.topics[1] = H256::from_low_u64_be(u32::MAX.into());
+        assert_eq!(borrowed::<UpdateView>(&log).unwrap().home_domain, u32::MAX);
+
+        let mut log = dispatch_log(3, vec![0x42; 40]);
+        log.topics[3].0[23] = 0x01;
+        assert!(borrowed::<DispatchView>(&log).is_err());
+    }
+
+    #[test]
+    fn it_rejects_what_the_owned_decoder_rejects() {
+        let valid = dispatch_log(3, vec![0x42; 40]);
+
+        let mut wrong_signature = valid.clone();
+        wrong_signature.topics[0] = H256::repeat_byte(9);
+        let mut missing_topic = valid.clone();
+        missing_topic.topics.pop();
+        let mut truncated = valid.clone();
+        truncated.data.truncate(valid.data.len() - 32);
+        let mut long_length = valid.clone();
+        long_length.data[95] = 0xff;
+        let mut far_offset = valid.clone();
+        far_offset.data[63] = 0xe0;
+        let mut empty = valid;
+        empty.data.clear();
+
+        for log in [
+            wrong_signature,
+            missing_topic,
+            truncated,
+            long_length,
+            far_offset,
+            empty,
+        ] {
+            assert!(home::DispatchFilter::decode_log(&log).is_err());
+            assert!(borrowed::<DispatchView>(&log).is_err());
+        }
+    }
+}
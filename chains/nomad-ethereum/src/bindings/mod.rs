@@ -1,4 +0,0 @@
-#![allow(clippy::all)]
-pub(crate) mod home;
-pub(crate) mod replica;
-pub(crate) mod xappconnectionmanager;
@@ -0,0 +1,328 @@
+use ethers::{abi::Abi, prelude::*};
+use nomad_ethereum_bindings::prelude::{HOME_ABI, REPLICA_ABI, XAPPCONNECTIONMANAGER_ABI};
+use nomad_types::evm_address_serde;
+use nomad_xyz_configuration::Connection;
+use serde::Serialize;
+use std::{collections::HashSet, sync::Arc};
+
+use crate::EthereumError;
+
+/// EIP-1967 implementation slot, `keccak256("eip1967.proxy.implementation") - 1`
+pub const EIP1967_IMPLEMENTATION_SLOT: H256 = H256([
+    0x36, 0x08, 0x94, 0xa1, 0x3b, 0xa1, 0xa3, 0x21, 0x06, 0x67, 0xc8, 0x28, 0x49, 0x2d, 0xb9, 0x8d,
+    0xca, 0x3e, 0x20, 0x76, 0xcc, 0x37, 0x35, 0xa9, 0x20, 0xa3, 0xca, 0x50, 0x5d, 0x38, 0x2b, 0xbc,
+]);
+
+/// EIP-1967 beacon slot, `keccak256("eip1967.proxy.beacon") - 1`
+pub const EIP1967_BEACON_SLOT: H256 = H256([
+    0xa3, 0xf0, 0xad, 0x74, 0xe5, 0x42, 0x3a, 0xeb, 0xfd, 0x80, 0xd3, 0xef, 0x43, 0x46, 0x57, 0x83,
+    0x35, 0xa9, 0xa7, 0x2a, 0xea, 0xee, 0x59, 0xff, 0x6c, 0xb3, 0x58, 0x2b, 0x35, 0x13, 0x3d, 0x50,
+]);
+
+/// Selector of a beacon's `implementation()`
+const IMPLEMENTATION_SELECTOR: [u8; 4] = [0x5c, 0x60, 0xda, 0x1b];
+
+const PUSH1: u8 = 0x60;
+const PUSH4: u8 = 0x63;
+const PUSH32: u8 = 0x7f;
+
+/// Nomad contracts agents call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContractKind {
+    /// Home
+    Home,
+    /// Replica
+    Replica,
+    /// XAppConnectionManager
+    ConnectionManager,
+}
+
+impl ContractKind {
+    /// Names of the functions agents call on this contract
+    pub fn called_functions(&self) -> &'static [&'static str] {
+        match self {
+            ContractKind::Home => &[
+                "committedRoot",
+                "count",
+                "dispatch",
+                "doubleUpdate",
+                "improperUpdate",
+                "nonces",
+                "queueContains",
+                "queueEnd",
+                "queueLength",
+                "root",
+                "state",
+                "suggestUpdate",
+                "tree",
+                "update",
+                "updater",
+            ],
+            ContractKind::Replica => &[
+                "acceptableRoot",
+                "committedRoot",
+                "confirmAt",
+                "messages",
+                "process",
+                "prove",
+                "proveAndProcess",
+                "remoteDomain",
+                "state",
+                "update",
+                "updater",
+            ],
+            ContractKind::ConnectionManager => &[
+                "isReplica",
+                "ownerEnrollReplica",
+                "ownerUnenrollReplica",
+                "replicaToDomain",
+                "setHome",
+                "setWatcherPermission",
+                "unenrollReplica",
+                "watcherPermission",
+            ],
+        }
+    }
+
+    fn abi(&self) -> &'static Abi {
+        match self {
+            ContractKind::Home => &HOME_ABI,
+            ContractKind::Replica => &REPLICA_ABI,
+            ContractKind::ConnectionManager => &XAPPCONNECTIONMANAGER_ABI,
+        }
+    }
+
+    /// Signatures and selectors of the functions agents call on this
+    /// contract
+    pub fn called_selectors(&self) -> Vec<(String, [u8; 4])> {
+        self.called_functions()
+            .iter()
+            .flat_map(|name| {
+                self.abi()
+                    .functions_by_name(name)
+                    .expect("called function missing from bindings")
+            })
+            .map(|function| (function.signature(), function.short_signature()))
+            .collect()
+    }
+}
+
+/// Selectors pushed with PUSH4 in `code`. Dispatchers compare the calldata
+/// selector against each function's selector pushed this way. Push data is
+/// skipped, so bytes inside other pushes are not mistaken for opcodes.
+pub fn pushed_selectors(code: &[u8]) -> HashSet<[u8; 4]> {
+    let mut selectors = HashSet::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let op = code[pc];
+        if (PUSH1..=PUSH32).contains(&op) {
+            let len = (op - PUSH1 + 1) as usize;
+            if op == PUSH4 && pc + 4 < code.len() {
+                let mut selector = [0u8; 4];
+                selector.copy_from_slice(&code[pc + 1..pc + 5]);
+                selectors.insert(selector);
+            }
+            pc += len;
+        }
+        pc += 1;
+    }
+    selectors
+}
+
+/// Signatures of the functions in `expected` whose selectors `code` does not
+/// push
+pub fn missing_functions(code: &[u8], expected: &[(String, [u8; 4])]) -> Vec<String> {
+    let pushed = pushed_selectors(code);
+    expected
+        .iter()
+        .filter(|(_, selector)| !pushed.contains(selector))
+        .map(|(signature, _)| signature.clone())
+        .collect()
+}
+
+/// Result of checking a contract's deployed code
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CodeReport {
+    /// Address of the contract, or of its proxy
+    #[serde(with = "evm_address_serde")]
+    pub address: Address,
+    /// Implementation behind the proxy, from its EIP-1967 slots or the
+    /// contract config
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "evm_address_serde::option"
+    )]
+    pub implementation: Option<Address>,
+    /// Called functions the deployed code lacks
+    pub missing: Vec<String>,
+}
+
+impl CodeReport {
+    /// Whether the code has every called function
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+fn address_in_word(word: H256) -> Option<Address> {
+    let address = Address::from_slice(&word.as_bytes()[12..]);
+    (!address.is_zero()).then_some(address)
+}
+
+/// Implementation behind `proxy`, if it is an EIP-1967 proxy. The
+/// implementation slot is read first, then the beacon slot, whose beacon is
+/// asked for its implementation.
+pub async fn implementation_address<M>(
+    client: &M,
+    proxy: Address,
+) -> Result<Option<Address>, EthereumError>
+where
+    M: Middleware + 'static,
+{
+    let read = |slot: H256| async move {
+        client
+            .get_storage_at(proxy, slot, None)
+            .await
+            .map_err(|e| EthereumError::MiddlewareError(e.into()))
+    };
+
+    if let Some(implementation) = address_in_word(read(EIP1967_IMPLEMENTATION_SLOT).await?) {
+        return Ok(Some(implementation));
+    }
+
+    match address_in_word(read(EIP1967_BEACON_SLOT).await?) {
+        Some(beacon) => {
+            let call: TypedTransaction = TransactionRequest::new()
+                .to(beacon)
+                .data(IMPLEMENTATION_SELECTOR.to_vec())
+                .into();
+            let returned = client
+                .call(&call, None)
+                .await
+                .map_err(|e| EthereumError::MiddlewareError(e.into()))?;
+            if returned.len() < 32 {
+                return Ok(None);
+            }
+            Ok(address_in_word(H256::from_slice(&returned[..32])))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Check that the code deployed at `address`, or behind it if it is a
+/// proxy, has every function agents call on a `kind` contract. Proxies
+/// without EIP-1967 slots, like Nomad's upgrade beacon proxies, are checked
+/// against `configured_implementation`.
+pub async fn check_code<M>(
+    client: &M,
+    kind: ContractKind,
+    address: Address,
+    configured_implementation: Option<Address>,
+) -> Result<CodeReport, EthereumError>
+where
+    M: Middleware + 'static,
+{
+    let implementation = implementation_address(client, address)
+        .await?
+        .or(configured_implementation);
+    let code = client
+        .get_code(implementation.unwrap_or(address), None)
+        .await
+        .map_err(|e| EthereumError::MiddlewareError(e.into()))?;
+
+    Ok(CodeReport {
+        address,
+        implementation,
+        missing: missing_functions(&code, &kind.called_selectors()),
+    })
+}
+
+/// Check the code of a `kind` contract at `address` on the chain behind
+/// `conn`. See [`check_code`].
+pub async fn check_contract_code(
+    conn: Connection,
+    kind: ContractKind,
+    address: Address,
+    configured_implementation: Option<Address>,
+) -> color_eyre::Result<CodeReport> {
+    let report = match conn {
+        Connection::Http(url) => {
+            let provider = http_provider!(url);
+            check_code(provider.as_ref(), kind, address, configured_implementation).await?
+        }
+        Connection::Ws(url) => {
+            let provider = ws_provider!(url);
+            check_code(provider.as_ref(), kind, address, configured_implementation).await?
+        }
+    };
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A dispatcher comparing the calldata selector against `selectors`,
+    /// jumping to a stub per function
+    fn dispatcher(selectors: &[[u8; 4]]) -> Vec<u8> {
+        // PUSH1 0 CALLDATALOAD PUSH1 0xe0 SHR
+        let mut code = vec![0x60, 0x00, 0x35, 0x60, 0xe0, 0x1c];
+        for (i, selector) in selectors.iter().enumerate() {
+            // DUP1 PUSH4 <selector> EQ PUSH2 <dest> JUMPI
+            code.extend([0x80, PUSH4]);
+            code.extend(selector);
+            code.extend([0x14, 0x61, 0x01, i as u8, 0x57]);
+        }
+        // PUSH1 0 DUP1 REVERT
+        code.extend([0x60, 0x00, 0x80, 0xfd]);
+        code
+    }
+
+    #[test]
+    fn it_finds_every_called_replica_function() {
+        let expected = ContractKind::Replica.called_selectors();
+        assert_eq!(
+            expected.len(),
+            ContractKind::Replica.called_functions().len()
+        );
+
+        let selectors: Vec<_> = expected.iter().map(|(_, selector)| *selector).collect();
+        assert!(missing_functions(&dispatcher(&selectors), &expected).is_empty());
+    }
+
+    #[test]
+    fn it_reports_a_missing_function() {
+        let expected = ContractKind::Replica.called_selectors();
+        let selectors: Vec<_> = expected
+            .iter()
+            .filter(|(signature, _)| signature != "process(bytes)")
+            .map(|(_, selector)| *selector)
+            .collect();
+
+        assert_eq!(
+            missing_functions(&dispatcher(&selectors), &expected),
+            vec!["process(bytes)".to_owned()]
+        );
+    }
+
+    #[test]
+    fn it_skips_selectors_inside_other_pushes() {
+        let process = [0x92, 0x8b, 0xc4, 0xb2];
+        // PUSH32 whose data contains PUSH4 <process>
+        let mut code = vec![PUSH32, 0x00, PUSH4];
+        code.extend(process);
+        code.extend([0u8; 25]);
+        assert!(!pushed_selectors(&code).contains(&process));
+
+        let mut code = vec![PUSH4];
+        code.extend(process);
+        assert!(pushed_selectors(&code).contains(&process));
+    }
+
+    #[test]
+    fn it_reads_addresses_from_slots() {
+        assert_eq!(address_in_word(H256::zero()), None);
+        let address = Address::repeat_byte(0x11);
+        assert_eq!(address_in_word(address.into()), Some(address));
+    }
+}
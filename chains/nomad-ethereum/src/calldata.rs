@@ -0,0 +1,111 @@
+use ethers::core::abi::AbiDecode;
+use nomad_core::{CanonicalH256, GovernanceCall};
+use nomad_ethereum_bindings::prelude::{HomeCalls, ReplicaCalls, XAppConnectionManagerCalls};
+
+/// Decode calldata by selector against the core contract ABIs. Returns
+/// `None` if no known function matches.
+pub fn decode_known_calldata(data: &[u8]) -> Option<String> {
+    if let Ok(call) = XAppConnectionManagerCalls::decode(data) {
+        return Some(format!("{:?}", call));
+    }
+    if let Ok(call) = HomeCalls::decode(data) {
+        return Some(format!("{:?}", call));
+    }
+    if let Ok(call) = ReplicaCalls::decode(data) {
+        return Some(format!("{:?}", call));
+    }
+    None
+}
+
+/// Describe a governance call. Calldata is decoded if its selector is known
+/// and shown raw otherwise.
+pub fn describe_governance_call(call: &GovernanceCall) -> String {
+    let data = decode_known_calldata(&call.data)
+        .unwrap_or_else(|| format!("raw 0x{}", hex::encode(&call.data)));
+    format!("to {}: {}", CanonicalH256(call.to), data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::types::H256;
+    use nomad_core::{batch_hash, Decode, Encode, GovernanceMessage};
+    use serde_json::Value;
+
+    fn hex_field(value: &Value) -> Vec<u8> {
+        hex::decode(value.as_str().unwrap().trim_start_matches("0x")).unwrap()
+    }
+
+    fn fixture() -> Value {
+        let fixture = std::fs::read_to_string("../../fixtures/governance_batch.json").unwrap();
+        serde_json::from_str(&fixture).unwrap()
+    }
+
+    fn fixture_calls(fixture: &Value) -> Vec<GovernanceCall> {
+        fixture["calls"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|call| GovernanceCall {
+                to: H256::from_slice(&hex_field(&call["to"])),
+                data: hex_field(&call["data"]),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_decodes_governance_batch_fixtures() {
+        let fixture = fixture();
+        let calls = fixture_calls(&fixture);
+        let hash = H256::from_slice(&hex_field(&fixture["batchHash"]));
+        assert_eq!(batch_hash(&calls), hash);
+
+        let message = hex_field(&fixture["batchMessage"]);
+        let decoded = GovernanceMessage::read_from(&mut message.as_slice()).unwrap();
+        assert_eq!(decoded, GovernanceMessage::BatchHash(hash));
+        assert_eq!(decoded.to_vec(), message);
+
+        let inline = hex_field(&fixture["inlineBatchMessage"]);
+        let decoded = GovernanceMessage::read_from(&mut inline.as_slice()).unwrap();
+        assert_eq!(decoded, GovernanceMessage::Batch(calls));
+        assert_eq!(decoded.batch_hash(), Some(hash));
+        assert_eq!(decoded.to_vec(), inline);
+
+        let transfer = &fixture["transferGovernor"];
+        let message = hex_field(&transfer["message"]);
+        assert_eq!(
+            GovernanceMessage::read_from(&mut message.as_slice()).unwrap(),
+            GovernanceMessage::TransferGovernor {
+                domain: transfer["domain"].as_u64().unwrap() as u32,
+                governor: H256::from_slice(&hex_field(&transfer["governor"])),
+            }
+        );
+
+        assert!(GovernanceMessage::read_from(&mut &inline[..inline.len() - 1]).is_err());
+        assert!(GovernanceMessage::read_from(&mut [3u8; 33].as_ref()).is_err());
+    }
+
+    #[test]
+    fn it_describes_known_and_unknown_calls() {
+        let calls = fixture_calls(&fixture());
+
+        let enroll = describe_governance_call(&calls[0]);
+        assert!(enroll.contains("OwnerEnrollReplica"), "{}", enroll);
+        assert!(enroll.contains("1650811245"), "{}", enroll);
+
+        let permission = describe_governance_call(&calls[1]);
+        assert!(
+            permission.contains("SetWatcherPermission"),
+            "{}",
+            permission
+        );
+
+        // BridgeRouter is not a known ABI
+        let unknown = describe_governance_call(&calls[2]);
+        assert!(
+            unknown.ends_with(&format!("raw 0x{}", hex::encode(&calls[2].data))),
+            "{}",
+            unknown
+        );
+    }
+}
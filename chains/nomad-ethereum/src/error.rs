@@ -1,6 +1,8 @@
-use crate::gelato::GelatoError;
+use crate::{gelato::GelatoError, ProofError};
 use ethers::core::types::H256;
 use ethers::prelude::{ContractError, Middleware, ProviderError};
+use nomad_core::NomadError;
+use nomad_types::NomadTypeError;
 use std::error::Error as StdError;
 
 /// Ethereum-specific error wrapper
@@ -11,10 +13,10 @@ pub enum EthereumError {
     ProviderError(#[from] ProviderError),
     /// Ethers contract error
     #[error("{0}")]
-    ContractError(Box<dyn StdError + Send + Sync>),
+    ContractError(#[source] Box<dyn StdError + Send + Sync>),
     /// Middleware error
     #[error("{0}")]
-    MiddlewareError(Box<dyn StdError + Send + Sync>),
+    MiddlewareError(#[source] Box<dyn StdError + Send + Sync>),
     /// Gelato client error
     #[error("{0}")]
     GelatoError(#[from] GelatoError),
@@ -24,6 +26,52 @@ pub enum EthereumError {
     /// Transaction was not executed successfully
     #[error("Transaction was not executed successfully {0:?}")]
     TxNotExecuted(H256),
+    /// A dispatch transaction emitted no `Dispatch` event
+    #[error("No Dispatch event in the receipt of transaction {0:?}")]
+    NoDispatchEvent(H256),
+    /// A transaction's gas limit from its estimate exceeded the configured
+    /// cap, so it was not submitted
+    #[error("Gas limit {limit} from estimate {estimate} exceeds the cap of {cap}")]
+    GasLimitExceeded {
+        /// Estimated gas
+        estimate: ethers::core::types::U256,
+        /// Gas limit from the estimate
+        limit: ethers::core::types::U256,
+        /// Configured cap
+        cap: u64,
+    },
+    /// A transaction was submitted through a read-only submitter
+    #[error("Refusing to submit a transaction through a read-only submitter")]
+    ReadOnlySubmitter,
+    /// A contract was already initialized with unexpected values
+    #[error("{contract:?} is already initialized with {field} {found}, expected {expected}")]
+    UnexpectedInitialization {
+        /// Contract address
+        contract: ethers::core::types::Address,
+        /// Field holding the unexpected value
+        field: &'static str,
+        /// Expected value
+        expected: String,
+        /// Value found on-chain
+        found: String,
+    },
+    /// A contract reported a state outside its `NomadBase.States`
+    #[error("{contract} reported invalid state {state}")]
+    InvalidState {
+        /// Contract kind
+        contract: &'static str,
+        /// State reported
+        state: u8,
+    },
+    /// Malformed event data, such as an unparseable signature
+    #[error("{0}")]
+    NomadError(#[from] NomadError),
+    /// A fetched proof could not be submitted
+    #[error("{0}")]
+    ProofError(#[from] ProofError),
+    /// An integer read from chain did not fit its domain type
+    #[error("{0}")]
+    ConversionError(#[from] NomadTypeError),
     /// Any other error
     #[error("{0}")]
     CustomError(#[from] Box<dyn StdError + Send + Sync>),
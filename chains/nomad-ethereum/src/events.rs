@@ -0,0 +1,377 @@
+use ethers::{contract::LogMeta, core::types::H256, providers::Middleware};
+use futures_util::future::join_all;
+use nomad_core::{
+    utils::saturating_u64, RootAdvance, RootProvenance, SignedUpdateWithMeta, UpdateEvent,
+    UpdateMeta,
+};
+use nomad_ethereum_bindings::{home, replica};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::EthereumError;
+
+impl From<home::UpdateFilter> for UpdateEvent {
+    fn from(event: home::UpdateFilter) -> Self {
+        Self {
+            home_domain: event.home_domain,
+            old_root: event.old_root.into(),
+            new_root: event.new_root.into(),
+            signature: event.signature.to_vec(),
+        }
+    }
+}
+
+impl From<replica::UpdateFilter> for UpdateEvent {
+    fn from(event: replica::UpdateFilter) -> Self {
+        Self {
+            home_domain: event.home_domain,
+            old_root: event.old_root.into(),
+            new_root: event.new_root.into(),
+            signature: event.signature.to_vec(),
+        }
+    }
+}
+
+/// Blocks `from..=to` split into consecutive windows of at most
+/// `max_block_range` blocks, bounds included. A single window if unset.
+pub(crate) fn block_windows(from: u32, to: u32, max_block_range: Option<u32>) -> Vec<(u32, u32)> {
+    let span = match max_block_range {
+        Some(blocks) => blocks.max(1) - 1,
+        None => return vec![(from, to)],
+    };
+
+    let mut windows = vec![];
+    let mut start = from;
+    loop {
+        let end = start.saturating_add(span).min(to);
+        windows.push((start, end));
+        if end >= to {
+            return windows;
+        }
+        start = end + 1;
+    }
+}
+
+/// Merge events queried over several block windows in block and log index
+/// order, dropping logs returned by more than one query
+pub(crate) fn merge_windowed_events<E>(mut events: Vec<(E, LogMeta)>) -> Vec<(E, LogMeta)> {
+    events.sort_by(|a, b| {
+        a.1.block_number
+            .cmp(&b.1.block_number)
+            .then(a.1.log_index.cmp(&b.1.log_index))
+    });
+    events.dedup_by(|a, b| a.1.block_hash == b.1.block_hash && a.1.log_index == b.1.log_index);
+    events
+}
+
+/// Sort `Update` events by block and transaction index and attach block
+/// metadata. Timestamps are best effort.
+pub(crate) async fn sorted_updates_with_meta<M, E>(
+    provider: &M,
+    mut events: Vec<(E, LogMeta)>,
+) -> Result<Vec<SignedUpdateWithMeta>, EthereumError>
+where
+    M: Middleware + 'static,
+    E: Into<UpdateEvent>,
+{
+    events.sort_by(|a, b| {
+        a.1.block_number
+            .cmp(&b.1.block_number)
+            .then(a.1.transaction_index.cmp(&b.1.transaction_index))
+    });
+
+    let update_futs: Vec<_> = events
+        .into_iter()
+        .map(|(event, meta)| async move {
+            let block_number = meta.block_number.as_u64();
+            let timestamp = provider
+                .get_block(block_number)
+                .await
+                .ok()
+                .flatten()
+                .map(|b| saturating_u64(b.timestamp));
+
+            let event: UpdateEvent = event.into();
+            event.with_meta(UpdateMeta {
+                block_number,
+                timestamp,
+            })
+        })
+        .collect();
+
+    join_all(update_futs)
+        .await
+        .into_iter()
+        .map(|update| update.map_err(Into::into))
+        .collect()
+}
+
+/// Roots a replica came to accept through its initialization and its
+/// `Update` and `SetConfirmation` events, sorted by block, transaction and log
+/// index, with block timestamps. `initializations` are the roots the replica
+/// was initialized with, at the logs of their initialization. Confirmations
+/// clearing a root are not advances.
+pub(crate) async fn sorted_root_advances<M>(
+    provider: &M,
+    initializations: Vec<(H256, LogMeta)>,
+    updates: Vec<(replica::UpdateFilter, LogMeta)>,
+    confirmations: Vec<(replica::SetConfirmationFilter, LogMeta)>,
+) -> Vec<RootAdvance>
+where
+    M: Middleware + 'static,
+{
+    let mut events: Vec<_> = initializations
+        .into_iter()
+        .map(|(root, meta)| (root, RootProvenance::Genesis, meta))
+        .chain(
+            updates
+                .into_iter()
+                .map(|(event, meta)| (H256::from(event.new_root), RootProvenance::Update, meta)),
+        )
+        .chain(
+            confirmations
+                .into_iter()
+                .filter(|(event, _)| !event.new_confirm_at.is_zero())
+                .map(|(event, meta)| (H256::from(event.root), RootProvenance::Governance, meta)),
+        )
+        .collect();
+    events.sort_by(|a, b| {
+        (a.2.block_number, a.2.transaction_index, a.2.log_index).cmp(&(
+            b.2.block_number,
+            b.2.transaction_index,
+            b.2.log_index,
+        ))
+    });
+
+    let timestamps = block_timestamps(
+        provider,
+        events.iter().map(|(_, _, meta)| meta.block_number.as_u64()),
+    )
+    .await;
+    events
+        .into_iter()
+        .map(|(root, provenance, meta)| {
+            let block_number = meta.block_number.as_u64();
+            RootAdvance {
+                root,
+                provenance,
+                block_number,
+                timestamp: timestamps.get(&block_number).copied(),
+                tx_hash: meta.transaction_hash,
+            }
+        })
+        .collect()
+}
+
+/// Timestamps of `blocks`, each fetched once. Best effort: blocks whose
+/// timestamp could not be fetched are left out.
+pub(crate) async fn block_timestamps<M>(
+    provider: &M,
+    blocks: impl IntoIterator<Item = u64>,
+) -> HashMap<u64, u64>
+where
+    M: Middleware + 'static,
+{
+    let blocks: BTreeSet<u64> = blocks.into_iter().collect();
+    let timestamp_futs = blocks.into_iter().map(|block_number| async move {
+        provider
+            .get_block(block_number)
+            .await
+            .ok()
+            .flatten()
+            .map(|block| (block_number, saturating_u64(block.timestamp)))
+    });
+
+    join_all(timestamp_futs)
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::providers::Provider;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::{Log, Signature};
+    use nomad_core::{CanonicalSignature, Decode, Encode, SignedUpdate, Update};
+    use serde_json::json;
+    use std::convert::TryFrom;
+
+    #[tokio::test]
+    async fn home_and_replica_events_convert_identically() {
+        let signer: LocalWallet = "1".repeat(64).parse().unwrap();
+        let signed = Update {
+            home_domain: 1000,
+            previous_root: [1u8; 32].into(),
+            new_root: [2u8; 32].into(),
+        }
+        .sign_with(&signer)
+        .await
+        .unwrap();
+
+        let home_event = home::UpdateFilter {
+            home_domain: 1000,
+            old_root: [1u8; 32],
+            new_root: [2u8; 32],
+            signature: signed.signature.to_vec().into(),
+        };
+        let replica_event = replica::UpdateFilter {
+            home_domain: 1000,
+            old_root: [1u8; 32],
+            new_root: [2u8; 32],
+            signature: signed.signature.to_vec().into(),
+        };
+
+        let from_home = UpdateEvent::from(home_event);
+        assert_eq!(from_home, UpdateEvent::from(replica_event));
+        assert_eq!(SignedUpdate::try_from(from_home).unwrap(), signed);
+        assert_eq!(signed.recover().unwrap(), signer.address());
+    }
+
+    #[tokio::test]
+    async fn signatures_normalize_from_every_input_form() {
+        let signer: LocalWallet = "1".repeat(64).parse().unwrap();
+        let signed = Update {
+            home_domain: 1000,
+            previous_root: [1u8; 32].into(),
+            new_root: [2u8; 32].into(),
+        }
+        .sign_with(&signer)
+        .await
+        .unwrap();
+        let canonical = signed.signature.to_vec();
+        assert!(CanonicalSignature::is_canonical(&canonical));
+        let mut raw = canonical.clone();
+        raw[64] -= 27;
+
+        let from_event = |signature: &[u8]| {
+            SignedUpdate::try_from(UpdateEvent::from(replica::UpdateFilter {
+                home_domain: 1000,
+                old_root: [1u8; 32],
+                new_root: [2u8; 32],
+                signature: signature.to_vec().into(),
+            }))
+            .unwrap()
+        };
+        let mut variants = vec![from_event(&canonical), from_event(&raw)];
+        for hex in [
+            format!("0x{}", hex::encode(&canonical)),
+            hex::encode(&canonical),
+            format!("0x{}", hex::encode(&raw)),
+            hex::encode(&raw),
+        ] {
+            let signature: CanonicalSignature = hex.parse().unwrap();
+            let json: CanonicalSignature = serde_json::from_value(json!(hex)).unwrap();
+            assert_eq!(signature, json);
+            variants.push(SignedUpdate {
+                update: signed.update,
+                signature,
+            });
+        }
+        let parts = Signature::try_from(raw.as_slice()).unwrap();
+        variants.push(
+            serde_json::from_value(json!({
+                "update": signed.update,
+                "signature": parts,
+            }))
+            .unwrap(),
+        );
+
+        for variant in variants {
+            // Recovery matches the signer and the unnormalized signature
+            assert_eq!(variant, signed);
+            assert_eq!(variant.recover().unwrap(), signer.address());
+            assert_eq!(
+                parts.recover(variant.update.prepended_hash()).unwrap(),
+                signer.address()
+            );
+            // Submitted and stored bytes are the canonical bytes
+            assert_eq!(variant.signature.to_vec(), canonical);
+            assert_eq!(
+                SignedUpdate::read_from(&mut variant.to_vec().as_slice()).unwrap(),
+                signed
+            );
+        }
+    }
+
+    #[test]
+    fn block_ranges_split_into_windows() {
+        assert_eq!(block_windows(100, 350, None), vec![(100, 350)]);
+        assert_eq!(
+            block_windows(100, 350, Some(100)),
+            vec![(100, 199), (200, 299), (300, 350)]
+        );
+        assert_eq!(
+            block_windows(100, 299, Some(100)),
+            vec![(100, 199), (200, 299)]
+        );
+        assert_eq!(block_windows(7, 7, Some(100)), vec![(7, 7)]);
+        assert_eq!(block_windows(7, 9, Some(1)), vec![(7, 7), (8, 8), (9, 9)]);
+        assert_eq!(
+            block_windows(u32::MAX - 5, u32::MAX, Some(4)),
+            vec![(u32::MAX - 5, u32::MAX - 2), (u32::MAX - 1, u32::MAX)]
+        );
+    }
+
+    #[tokio::test]
+    async fn root_advances_start_at_the_initialization_root() {
+        let (provider, _mock) = Provider::mocked();
+        let meta = |block: u64| {
+            LogMeta::from(&Log {
+                block_number: Some(block.into()),
+                block_hash: Some(H256::zero()),
+                transaction_hash: Some(H256::from_low_u64_be(block)),
+                transaction_index: Some(0.into()),
+                log_index: Some(0.into()),
+                ..Default::default()
+            })
+        };
+
+        let advances = sorted_root_advances(
+            &provider,
+            vec![(H256::repeat_byte(1), meta(10))],
+            vec![(
+                replica::UpdateFilter {
+                    home_domain: 1000,
+                    old_root: [1; 32],
+                    new_root: [2; 32],
+                    signature: Default::default(),
+                },
+                meta(12),
+            )],
+            vec![(
+                replica::SetConfirmationFilter {
+                    root: [3; 32],
+                    previous_confirm_at: 0.into(),
+                    new_confirm_at: 1.into(),
+                },
+                meta(11),
+            )],
+        )
+        .await;
+
+        // Timestamps are best effort, and none could be fetched
+        assert_eq!(
+            advances
+                .iter()
+                .map(|advance| (advance.root, advance.provenance, advance.timestamp))
+                .collect::<Vec<_>>(),
+            vec![
+                (H256::repeat_byte(1), RootProvenance::Genesis, None),
+                (H256::repeat_byte(3), RootProvenance::Governance, None),
+                (H256::repeat_byte(2), RootProvenance::Update, None),
+            ]
+        );
+        assert_eq!(advances[0].tx_hash, H256::from_low_u64_be(10));
+    }
+
+    #[test]
+    fn malformed_signatures_are_errors() {
+        let event = UpdateEvent {
+            signature: vec![0u8; 64],
+            ..Default::default()
+        };
+        assert!(SignedUpdate::try_from(event).is_err());
+    }
+}
@@ -0,0 +1,381 @@
+use ethers::providers::Middleware;
+use ethers::types::{
+    transaction::eip2718::TypedTransaction, BlockNumber, Eip1559TransactionRequest, FeeHistory,
+    TransactionRequest, U256,
+};
+use nomad_xyz_configuration::{FeeMode, GasConfig};
+use tracing::debug;
+
+use crate::gas::scale;
+
+/// Max fee multiplier of the base fee when not configured
+const DEFAULT_MAX_FEE_MULTIPLIER: f64 = 2.0;
+
+/// Percentile of recent priority fees bid when not configured
+const DEFAULT_PRIORITY_FEE_PERCENTILE: f64 = 50.0;
+
+/// Blocks of fee history the oracle reads
+const FEE_HISTORY_BLOCKS: u64 = 10;
+
+/// Wei in a gwei
+const WEI_PER_GWEI: f64 = 1e9;
+
+/// How an outgoing transaction is typed and priced
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeeStrategy {
+    /// Legacy transaction, priced by the submitting middleware's gas price
+    Legacy,
+    /// EIP-1559 transaction with fixed fee caps
+    Eip1559 {
+        /// Max fee per gas, in wei
+        max_fee: U256,
+        /// Max priority fee per gas, in wei
+        max_priority_fee: U256,
+    },
+    /// EIP-1559 transaction priced from `eth_feeHistory`: the fixed
+    /// `priority_fee`, or the median of recent blocks' priority fees at
+    /// `percentile`, plus `base_fee_multiplier` times the next base fee.
+    /// Legacy on chains reporting no base fee
+    Oracle {
+        /// Fixed priority fee, in wei
+        priority_fee: Option<U256>,
+        /// Percentile of each block's priority fees
+        percentile: f64,
+        /// Multiple of the next base fee allowed for
+        base_fee_multiplier: f64,
+    },
+}
+
+impl Default for FeeStrategy {
+    fn default() -> Self {
+        FeeStrategy::Legacy
+    }
+}
+
+impl From<&GasConfig> for FeeStrategy {
+    fn from(cfg: &GasConfig) -> Self {
+        let priority_fee = gwei_to_wei(cfg.max_priority_fee_gwei);
+        let base_fee_multiplier = cfg.max_fee_multiplier.unwrap_or(DEFAULT_MAX_FEE_MULTIPLIER);
+        let percentile = cfg
+            .priority_fee_percentile
+            .unwrap_or(DEFAULT_PRIORITY_FEE_PERCENTILE);
+        match (cfg.fee_mode, cfg.max_fee_gwei) {
+            (FeeMode::Legacy, _) => FeeStrategy::Legacy,
+            (FeeMode::Eip1559, Some(max_fee_gwei)) => FeeStrategy::Eip1559 {
+                max_fee: gwei_to_wei(max_fee_gwei),
+                max_priority_fee: priority_fee,
+            },
+            (FeeMode::Eip1559, None) => FeeStrategy::Oracle {
+                priority_fee: Some(priority_fee),
+                percentile,
+                base_fee_multiplier,
+            },
+            (FeeMode::Oracle, _) => FeeStrategy::Oracle {
+                priority_fee: None,
+                percentile,
+                base_fee_multiplier,
+            },
+        }
+    }
+}
+
+impl FeeStrategy {
+    /// Give `tx` the type and fee caps of the strategy, reading the fee
+    /// history through `client` if needed. Legacy transactions are left
+    /// without a gas price, for the submitting middleware to price.
+    pub async fn apply<M: Middleware>(&self, client: &M, tx: &mut TypedTransaction) {
+        let caps = match *self {
+            FeeStrategy::Legacy => None,
+            FeeStrategy::Eip1559 {
+                max_fee,
+                max_priority_fee,
+            } => Some((max_fee, max_priority_fee)),
+            FeeStrategy::Oracle {
+                priority_fee,
+                percentile,
+                base_fee_multiplier,
+            } => {
+                let blocks = if priority_fee.is_some() {
+                    1
+                } else {
+                    FEE_HISTORY_BLOCKS
+                };
+                match client
+                    .fee_history(blocks, BlockNumber::Latest, &[percentile])
+                    .await
+                {
+                    Ok(history) => oracle_fees(&history, priority_fee, base_fee_multiplier),
+                    Err(e) => {
+                        debug!(
+                            error = %e,
+                            "Fee history unavailable, pricing as legacy transaction"
+                        );
+                        None
+                    }
+                }
+            }
+        };
+
+        *tx = match caps {
+            Some((max_fee, max_priority_fee)) => into_eip1559(tx)
+                .max_fee_per_gas(max_fee)
+                .max_priority_fee_per_gas(max_priority_fee)
+                .into(),
+            None => into_legacy(tx).into(),
+        };
+    }
+}
+
+/// Max fee and priority fee of an EIP-1559 transaction in the block after
+/// `history`, or `None` if it reports no base fee. Without a fixed
+/// `priority_fee`, the priority fee is the median of the blocks' rewards.
+pub(crate) fn oracle_fees(
+    history: &FeeHistory,
+    priority_fee: Option<U256>,
+    base_fee_multiplier: f64,
+) -> Option<(U256, U256)> {
+    // The last base fee is the next block's
+    let base_fee = match history.base_fee_per_gas.last() {
+        Some(base_fee) if !base_fee.is_zero() => *base_fee,
+        _ => {
+            debug!("Chain reports no base fee, pricing as legacy transaction");
+            return None;
+        }
+    };
+
+    let priority_fee = priority_fee.unwrap_or_else(|| {
+        let mut rewards: Vec<U256> = history
+            .reward
+            .iter()
+            .filter_map(|block| block.first().copied())
+            .collect();
+        rewards.sort();
+        rewards.get(rewards.len() / 2).copied().unwrap_or_default()
+    });
+    let max_fee = scale(base_fee, base_fee_multiplier) + priority_fee;
+    Some((max_fee, priority_fee))
+}
+
+/// Wei in `gwei` gwei, rounded to the wei
+fn gwei_to_wei(gwei: f64) -> U256 {
+    U256::from((gwei.max(0.0) * WEI_PER_GWEI).round() as u128)
+}
+
+/// The request of `tx` as a legacy transaction, without fees of another
+/// type
+fn into_legacy(tx: &TypedTransaction) -> TransactionRequest {
+    match tx {
+        TypedTransaction::Legacy(request) => request.clone(),
+        TypedTransaction::Eip2930(request) => request.tx.clone(),
+        TypedTransaction::Eip1559(request) => {
+            let mut legacy = TransactionRequest::new();
+            legacy.from = request.from;
+            legacy.to = request.to.clone();
+            legacy.gas = request.gas;
+            legacy.value = request.value;
+            legacy.data = request.data.clone();
+            legacy.nonce = request.nonce;
+            legacy.chain_id = request.chain_id;
+            legacy
+        }
+    }
+}
+
+/// The request of `tx` as an EIP-1559 transaction, without fees of another
+/// type
+fn into_eip1559(tx: &TypedTransaction) -> Eip1559TransactionRequest {
+    let (legacy, access_list) = match tx {
+        TypedTransaction::Eip1559(request) => return request.clone(),
+        TypedTransaction::Eip2930(request) => (&request.tx, request.access_list.clone()),
+        TypedTransaction::Legacy(request) => (request, Default::default()),
+    };
+    let mut request = Eip1559TransactionRequest::new();
+    request.from = legacy.from;
+    request.to = legacy.to.clone();
+    request.gas = legacy.gas;
+    request.value = legacy.value;
+    request.data = legacy.data.clone();
+    request.nonce = legacy.nonce;
+    request.chain_id = legacy.chain_id;
+    request.access_list = access_list;
+    request
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ethers::{
+        providers::{MockProvider, Provider},
+        types::Address,
+    };
+
+    fn gwei(amount: u64) -> U256 {
+        U256::from(amount) * 1_000_000_000u64
+    }
+
+    fn history(base_fees: Vec<U256>, rewards: Vec<U256>) -> FeeHistory {
+        FeeHistory {
+            base_fee_per_gas: base_fees,
+            gas_used_ratio: vec![0.5; rewards.len()],
+            oldest_block: 100u64.into(),
+            reward: rewards.into_iter().map(|reward| vec![reward]).collect(),
+        }
+    }
+
+    fn tx() -> TypedTransaction {
+        TransactionRequest::new()
+            .to(Address::repeat_byte(1))
+            .gas(500_000u64)
+            .gas_price(gwei(7))
+            .data(vec![1, 2, 3])
+            .into()
+    }
+
+    #[test]
+    fn it_resolves_strategies_from_fee_settings() {
+        assert_eq!(
+            FeeStrategy::from(&GasConfig::default()),
+            FeeStrategy::Legacy
+        );
+
+        let cfg = GasConfig {
+            fee_mode: FeeMode::Eip1559,
+            max_priority_fee_gwei: 1.5,
+            ..Default::default()
+        };
+        assert_eq!(
+            FeeStrategy::from(&cfg),
+            FeeStrategy::Oracle {
+                priority_fee: Some(U256::from(1_500_000_000u64)),
+                percentile: 50.0,
+                base_fee_multiplier: 2.0,
+            }
+        );
+        assert_eq!(
+            FeeStrategy::from(&GasConfig {
+                max_fee_gwei: Some(40.0),
+                ..cfg
+            }),
+            FeeStrategy::Eip1559 {
+                max_fee: gwei(40),
+                max_priority_fee: U256::from(1_500_000_000u64),
+            }
+        );
+        assert_eq!(
+            FeeStrategy::from(&GasConfig {
+                fee_mode: FeeMode::Oracle,
+                priority_fee_percentile: Some(90.0),
+                max_fee_multiplier: Some(1.5),
+                ..cfg
+            }),
+            FeeStrategy::Oracle {
+                priority_fee: None,
+                percentile: 90.0,
+                base_fee_multiplier: 1.5,
+            }
+        );
+    }
+
+    #[test]
+    fn it_prices_from_the_median_reward() {
+        let history = history(
+            vec![gwei(20), gwei(22), gwei(25), gwei(30)],
+            vec![gwei(3), gwei(1), gwei(2)],
+        );
+        assert_eq!(oracle_fees(&history, None, 2.0), Some((gwei(62), gwei(2))));
+        // A fixed priority fee ignores the rewards
+        assert_eq!(
+            oracle_fees(&history, Some(gwei(5)), 1.0),
+            Some((gwei(35), gwei(5)))
+        );
+        // Chains before EIP-1559 report zero base fees
+        let history = history_without_base_fee();
+        assert_eq!(oracle_fees(&history, None, 2.0), None);
+    }
+
+    fn history_without_base_fee() -> FeeHistory {
+        history(vec![U256::zero(), U256::zero()], vec![gwei(1)])
+    }
+
+    #[tokio::test]
+    async fn it_sends_legacy_transactions_unpriced() {
+        let (provider, _) = Provider::mocked();
+
+        let mut legacy = tx();
+        FeeStrategy::Legacy.apply(&provider, &mut legacy).await;
+        assert!(matches!(legacy, TypedTransaction::Legacy(_)));
+        assert_eq!(legacy.gas_price(), Some(gwei(7)));
+
+        let mut eip1559: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(Address::repeat_byte(1))
+            .max_fee_per_gas(gwei(9))
+            .into();
+        FeeStrategy::Legacy.apply(&provider, &mut eip1559).await;
+        match eip1559 {
+            TypedTransaction::Legacy(request) => {
+                assert_eq!(request.gas_price, None);
+                assert_eq!(request.to, Some(Address::repeat_byte(1).into()));
+            }
+            other => panic!("expected a legacy transaction, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_sends_eip1559_transactions_with_fixed_caps() {
+        // No fee history is read
+        let (provider, _) = Provider::<MockProvider>::mocked();
+        let strategy = FeeStrategy::Eip1559 {
+            max_fee: gwei(40),
+            max_priority_fee: gwei(2),
+        };
+
+        let mut tx = tx();
+        strategy.apply(&provider, &mut tx).await;
+        match tx {
+            TypedTransaction::Eip1559(request) => {
+                assert_eq!(request.max_fee_per_gas, Some(gwei(40)));
+                assert_eq!(request.max_priority_fee_per_gas, Some(gwei(2)));
+                assert_eq!(request.gas, Some(500_000u64.into()));
+                assert_eq!(request.to, Some(Address::repeat_byte(1).into()));
+                assert_eq!(request.data, Some(vec![1, 2, 3].into()));
+            }
+            other => panic!("expected an EIP-1559 transaction, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_sends_oracle_priced_transactions() {
+        let strategy = FeeStrategy::Oracle {
+            priority_fee: None,
+            percentile: 50.0,
+            base_fee_multiplier: 2.0,
+        };
+
+        let (provider, mock) = Provider::mocked();
+        mock.push(history(
+            vec![gwei(20), gwei(22), gwei(25), gwei(30)],
+            vec![gwei(3), gwei(1), gwei(2)],
+        ))
+        .unwrap();
+        let mut priced = tx();
+        strategy.apply(&provider, &mut priced).await;
+        match priced {
+            TypedTransaction::Eip1559(request) => {
+                assert_eq!(request.max_fee_per_gas, Some(gwei(62)));
+                assert_eq!(request.max_priority_fee_per_gas, Some(gwei(2)));
+            }
+            other => panic!("expected an EIP-1559 transaction, got {:?}", other),
+        }
+
+        // Legacy without a base fee, or without a fee history
+        mock.push(history_without_base_fee()).unwrap();
+        let mut legacy = tx();
+        strategy.apply(&provider, &mut legacy).await;
+        assert!(matches!(legacy, TypedTransaction::Legacy(_)));
+
+        let mut legacy = tx();
+        strategy.apply(&provider, &mut legacy).await;
+        assert!(matches!(legacy, TypedTransaction::Legacy(_)));
+    }
+}
@@ -1,15 +1,24 @@
 use ethers::providers::{FromErr, Middleware};
 use ethers::types::{transaction::eip2718::TypedTransaction, BlockId, U256};
+use nomad_xyz_configuration::GasConfig;
 use std::fmt;
 use thiserror::Error;
 
+use crate::{EthereumError, FeeStrategy};
+
+/// Multiple of the estimated gas used as a gas limit when not configured
+const DEFAULT_GAS_LIMIT_MULTIPLIER: f64 = 1.25;
+
 /// Closure that will be used for gas calculation. Takes existing gas
 type GasPolicy = Box<dyn Fn(U256) -> U256 + Send + Sync>;
 
-/// Middleware used for adjusting gas using predefined policy
+/// Middleware used for adjusting gas using predefined policy. Types and
+/// prices transactions by the chain's fee strategy, pricing legacy
+/// transactions with the adjusted gas price.
 pub struct GasAdjusterMiddleware<M> {
     inner: M,
     gas_price_policy: GasPolicy,
+    fees: FeeStrategy,
 }
 
 impl<M> fmt::Debug for GasAdjusterMiddleware<M>
@@ -19,6 +28,7 @@ where
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("GasAdjusterMiddleware")
             .field("inner", &self.inner)
+            .field("fees", &self.fees)
             .finish()
     }
 }
@@ -33,6 +43,7 @@ where
         Self {
             inner,
             gas_price_policy,
+            fees: Default::default(),
         }
     }
 
@@ -49,6 +60,63 @@ where
 
         Self::new(inner, Box::new(gas_price_policy))
     }
+
+    /// Price transactions by the chain's fee settings
+    pub fn with_fees(mut self, fees: GasConfig) -> Self {
+        self.fees = (&fees).into();
+        self
+    }
+}
+
+/// `value` times `factor`, to a thousandth of the factor
+pub(crate) fn scale(value: U256, factor: f64) -> U256 {
+    value * U256::from((factor.max(0.0) * 1000.0).round() as u64) / 1000
+}
+
+/// Gas limit of a transaction estimated to use `estimate`: the estimate
+/// times the configured multiplier, at least the configured minimum. Errors
+/// if that exceeds the configured cap.
+pub(crate) fn gas_limit(estimate: U256, cfg: &GasConfig) -> Result<U256, EthereumError> {
+    let multiplier = cfg
+        .gas_limit_multiplier
+        .unwrap_or(DEFAULT_GAS_LIMIT_MULTIPLIER);
+    let mut limit = scale(estimate, multiplier);
+    if let Some(min) = cfg.min_gas_limit {
+        limit = limit.max(min.into());
+    }
+    match cfg.max_gas_limit {
+        Some(cap) if limit > cap.into() => Err(EthereumError::GasLimitExceeded {
+            estimate,
+            limit,
+            cap,
+        }),
+        _ => Ok(limit),
+    }
+}
+
+/// Give `tx` the gas limit of its estimate through `client`, adjusted by
+/// `cfg`. Transactions with a gas limit keep it. Only the gas is estimated,
+/// so the nonce and fees are left to the middlewares sending `tx`.
+pub(crate) async fn fill_gas_limit<M: Middleware>(
+    client: &M,
+    tx: &mut TypedTransaction,
+    cfg: &GasConfig,
+) -> Result<(), EthereumError> {
+    if tx.gas().is_some() {
+        return Ok(());
+    }
+    // Estimated from the signer's address
+    if tx.from().is_none() {
+        if let Some(sender) = client.default_sender() {
+            tx.set_from(sender);
+        }
+    }
+    let estimate = client
+        .estimate_gas(tx)
+        .await
+        .map_err(|e| EthereumError::MiddlewareError(e.into()))?;
+    tx.set_gas(gas_limit(estimate, cfg)?);
+    Ok(())
 }
 
 #[derive(Error, Debug)]
@@ -84,15 +152,20 @@ where
         tx: &mut TypedTransaction,
         block: Option<BlockId>,
     ) -> Result<(), Self::Error> {
+        // Fees already set on the transaction, e.g. by an earlier fill, are
+        // kept
+        if tx.gas_price().is_none() {
+            // Priced before the inner fill, so it estimates no fees
+            self.fees.apply(&self.inner, tx).await;
+            if tx.gas_price().is_none() {
+                tx.set_gas_price(self.get_gas_price().await?);
+            }
+        }
+
         self.inner
             .fill_transaction(tx, block)
             .await
-            .map_err(FromErr::from)?;
-
-        let adjusted_price = self.get_gas_price().await?;
-        tx.set_gas_price(adjusted_price);
-
-        Ok(())
+            .map_err(FromErr::from)
     }
 
     async fn get_gas_price(&self) -> Result<U256, Self::Error> {
@@ -103,3 +176,269 @@ where
             .map_err(FromErr::from)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ethers::{
+        providers::{MockProvider, Provider, ProviderError},
+        types::{
+            Address, BlockNumber, Eip1559TransactionRequest, FeeHistory, NameOrAddress,
+            TransactionRequest,
+        },
+    };
+    use nomad_xyz_configuration::FeeMode;
+
+    /// Middleware reporting a fee history, gas price and gas estimate, and
+    /// filling transactions without a gas limit with the estimate and a
+    /// nonce. Other calls go to a mock provider without responses.
+    #[derive(Debug)]
+    struct Chain {
+        inner: Provider<MockProvider>,
+        base_fees: Option<Vec<U256>>,
+        gas_price: U256,
+        gas_estimate: U256,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("{0}")]
+    struct ChainError(ProviderError);
+
+    impl FromErr<ProviderError> for ChainError {
+        fn from(src: ProviderError) -> Self {
+            Self(src)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Middleware for Chain {
+        type Error = ChainError;
+        type Provider = MockProvider;
+        type Inner = Provider<MockProvider>;
+
+        fn inner(&self) -> &Self::Inner {
+            &self.inner
+        }
+
+        async fn fill_transaction(
+            &self,
+            tx: &mut TypedTransaction,
+            _: Option<BlockId>,
+        ) -> Result<(), Self::Error> {
+            if tx.gas().is_none() {
+                tx.set_gas(self.gas_estimate);
+                tx.set_nonce(9);
+            }
+            Ok(())
+        }
+
+        async fn estimate_gas(&self, _: &TypedTransaction) -> Result<U256, Self::Error> {
+            Ok(self.gas_estimate)
+        }
+
+        async fn get_gas_price(&self) -> Result<U256, Self::Error> {
+            Ok(self.gas_price)
+        }
+
+        async fn fee_history<T: Into<U256> + Send + Sync>(
+            &self,
+            _: T,
+            _: BlockNumber,
+            _: &[f64],
+        ) -> Result<FeeHistory, Self::Error> {
+            let base_fee_per_gas = self.base_fees.clone().ok_or_else(|| {
+                ChainError(ProviderError::CustomError(
+                    "the method eth_feeHistory does not exist".to_owned(),
+                ))
+            })?;
+            Ok(FeeHistory {
+                base_fee_per_gas,
+                gas_used_ratio: vec![0.5],
+                oldest_block: 100u64.into(),
+                reward: vec![],
+            })
+        }
+    }
+
+    fn gwei(amount: u64) -> U256 {
+        U256::from(amount) * 1_000_000_000u64
+    }
+
+    fn chain(base_fees: Option<Vec<U256>>) -> Chain {
+        Chain {
+            inner: Provider::mocked().0,
+            base_fees,
+            gas_price: gwei(100),
+            gas_estimate: 100_000u64.into(),
+        }
+    }
+
+    fn gas_adjuster(chain_id: u64, base_fees: Option<Vec<U256>>) -> GasAdjusterMiddleware<Chain> {
+        GasAdjusterMiddleware::with_default_policy(chain(base_fees), chain_id)
+    }
+
+    fn eip1559(max_priority_fee_gwei: f64, max_fee_multiplier: Option<f64>) -> GasConfig {
+        GasConfig {
+            fee_mode: FeeMode::Eip1559,
+            max_priority_fee_gwei,
+            max_fee_multiplier,
+            ..Default::default()
+        }
+    }
+
+    async fn filled<M: Middleware>(adjuster: &GasAdjusterMiddleware<M>) -> TypedTransaction {
+        let mut tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::repeat_byte(1))
+            .gas(500_000u64)
+            .data(vec![1, 2, 3])
+            .into();
+        adjuster.fill_transaction(&mut tx, None).await.unwrap();
+        tx
+    }
+
+    #[tokio::test]
+    async fn it_prices_legacy_transactions_with_the_adjusted_gas_price() {
+        let tx = filled(&gas_adjuster(1, Some(vec![gwei(30), gwei(30)]))).await;
+        match tx {
+            TypedTransaction::Legacy(request) => {
+                assert_eq!(request.gas_price, Some(gwei(150)));
+                assert_eq!(request.gas, Some(500_000u64.into()));
+            }
+            other => panic!("expected a legacy transaction, got {:?}", other),
+        }
+
+        // Including transactions built as EIP-1559 ones
+        let adjuster = gas_adjuster(5, None);
+        let mut tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(Address::repeat_byte(1))
+            .gas(500_000u64)
+            .into();
+        adjuster.fill_transaction(&mut tx, None).await.unwrap();
+        assert!(matches!(tx, TypedTransaction::Legacy(_)));
+        assert_eq!(tx.gas_price(), Some(gwei(200)));
+        assert_eq!(
+            tx.to(),
+            Some(&NameOrAddress::Address(Address::repeat_byte(1)))
+        );
+    }
+
+    #[tokio::test]
+    async fn it_prices_eip1559_transactions_from_the_fee_history() {
+        let base_fees = vec![gwei(25), gwei(30)];
+
+        let adjuster = gas_adjuster(1, Some(base_fees.clone())).with_fees(eip1559(2.0, None));
+        match filled(&adjuster).await {
+            TypedTransaction::Eip1559(request) => {
+                // Twice the next block's base fee, plus the priority fee
+                assert_eq!(request.max_fee_per_gas, Some(gwei(62)));
+                assert_eq!(request.max_priority_fee_per_gas, Some(gwei(2)));
+                assert_eq!(request.gas, Some(500_000u64.into()));
+                assert_eq!(request.to, Some(Address::repeat_byte(1).into()));
+                assert_eq!(request.data, Some(vec![1, 2, 3].into()));
+            }
+            other => panic!("expected an EIP-1559 transaction, got {:?}", other),
+        }
+
+        let adjuster = gas_adjuster(1, Some(base_fees)).with_fees(eip1559(1.5, Some(1.25)));
+        match filled(&adjuster).await {
+            TypedTransaction::Eip1559(request) => {
+                assert_eq!(request.max_fee_per_gas, Some(gwei(39)));
+                assert_eq!(
+                    request.max_priority_fee_per_gas,
+                    Some(U256::from(1_500_000_000u64))
+                );
+            }
+            other => panic!("expected an EIP-1559 transaction, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_falls_back_to_legacy_without_a_base_fee() {
+        // Reported as zero by chains before EIP-1559
+        let adjuster =
+            gas_adjuster(100, Some(vec![U256::zero(), U256::zero()])).with_fees(eip1559(2.0, None));
+        let tx = filled(&adjuster).await;
+        assert!(matches!(tx, TypedTransaction::Legacy(_)));
+        assert_eq!(tx.gas_price(), Some(gwei(200)));
+
+        // Or not reported at all
+        let adjuster = gas_adjuster(100, None).with_fees(eip1559(2.0, None));
+        let tx = filled(&adjuster).await;
+        assert!(matches!(tx, TypedTransaction::Legacy(_)));
+        assert_eq!(tx.gas_price(), Some(gwei(200)));
+    }
+
+    #[test]
+    fn it_adjusts_estimated_gas_limits() {
+        let estimate = U256::from(100_000u64);
+
+        // 1.25x by default
+        assert_eq!(
+            gas_limit(estimate, &Default::default()).unwrap(),
+            125_000u64.into()
+        );
+
+        let mut cfg = GasConfig {
+            gas_limit_multiplier: Some(1.5),
+            ..Default::default()
+        };
+        assert_eq!(gas_limit(estimate, &cfg).unwrap(), 150_000u64.into());
+
+        // Floored at the minimum
+        cfg.min_gas_limit = Some(200_000);
+        assert_eq!(gas_limit(estimate, &cfg).unwrap(), 200_000u64.into());
+
+        // Capped after the floor
+        cfg.max_gas_limit = Some(200_000);
+        assert_eq!(gas_limit(estimate, &cfg).unwrap(), 200_000u64.into());
+        cfg.max_gas_limit = Some(199_999);
+        match gas_limit(estimate, &cfg) {
+            Err(EthereumError::GasLimitExceeded {
+                estimate: estimated,
+                limit,
+                cap,
+            }) => {
+                assert_eq!(estimated, estimate);
+                assert_eq!(limit, 200_000u64.into());
+                assert_eq!(cap, 199_999);
+            }
+            other => panic!("expected the cap to be exceeded, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_fills_gas_limits_from_estimates() {
+        let chain = chain(None);
+        let cfg = GasConfig {
+            min_gas_limit: Some(21_000),
+            max_gas_limit: Some(130_000),
+            ..Default::default()
+        };
+        let tx =
+            || -> TypedTransaction { TransactionRequest::new().to(Address::repeat_byte(1)).into() };
+
+        let mut estimated = tx();
+        fill_gas_limit(&chain, &mut estimated, &cfg).await.unwrap();
+        assert_eq!(estimated.gas(), Some(&125_000u64.into()));
+        // Left for the sending middlewares to fill
+        assert_eq!(estimated.nonce(), None);
+
+        // Configured limits are kept, even above the cap
+        let mut configured = tx();
+        configured.set_gas(1_900_000u64);
+        fill_gas_limit(&chain, &mut configured, &cfg).await.unwrap();
+        assert_eq!(configured.gas(), Some(&1_900_000u64.into()));
+
+        // Estimates adjusted above the cap are refused
+        let mut capped = tx();
+        let cfg = GasConfig {
+            gas_limit_multiplier: Some(1.5),
+            ..cfg
+        };
+        assert!(matches!(
+            fill_gas_limit(&chain, &mut capped, &cfg).await,
+            Err(EthereumError::GasLimitExceeded { .. })
+        ));
+    }
+}
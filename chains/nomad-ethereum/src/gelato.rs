@@ -1,4 +1,4 @@
-use crate::EthereumSigners;
+use crate::{relay_task_store, EthereumSigners, RelayTaskStore, RelayTasks};
 use ethers::{
     prelude::{Address, Bytes, H256, U64},
     providers::Middleware,
@@ -9,12 +9,22 @@ use gelato_sdk::{
     rpc::{CheckOrDate, RelayResponse, TaskState},
     FeeToken, ForwardRequestBuilder, GelatoClient,
 };
-use nomad_core::TxOutcome;
+use nomad_core::{db::DbError, TxOutcome};
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    Url,
+};
 use std::{error::Error as StdError, sync::Arc};
 use tokio::task::JoinHandle;
 use tokio::time::{sleep, Duration};
 use tracing::info;
 
+/// Gelato relay service url
+pub const GELATO_RELAY_URL: &str = "https://relay.gelato.digital";
+
+/// Header carrying the idempotency key of a forward request
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
 pub(crate) const ACCEPTABLE_STATES: [TaskState; 4] = [
     TaskState::CheckPending,
     TaskState::ExecPending,
@@ -36,6 +46,9 @@ pub enum GelatoError {
         /// Status
         check_info: Option<CheckOrDate>,
     },
+    /// Relay task store error
+    #[error("{0}")]
+    DbError(#[from] DbError),
     /// Custom error
     #[error("{0}")]
     CustomError(#[from] Box<dyn StdError + Send + Sync>),
@@ -46,6 +59,8 @@ pub enum GelatoError {
 pub struct SingleChainGelatoClient<M> {
     /// Gelato client
     pub gelato: Arc<GelatoClient>,
+    /// Gelato relay url, for requests carrying idempotency keys
+    pub relay_url: Url,
     /// Ethers client (for estimating gas)
     pub eth_client: Arc<M>,
     /// Sponsor signer
@@ -58,6 +73,8 @@ pub struct SingleChainGelatoClient<M> {
     pub fee_token: FeeToken,
     /// Transactions are of high priority
     pub is_high_priority: bool,
+    /// Relay tasks by idempotency key
+    pub relay_tasks: RelayTasks,
 }
 
 impl<M> SingleChainGelatoClient<M>
@@ -79,41 +96,70 @@ where
     ) -> Self {
         Self {
             gelato: GelatoClient::default().into(),
+            relay_url: GELATO_RELAY_URL.parse().expect("!relay url"),
             eth_client,
             sponsor,
             forwarder: get_forwarder(chain_id).expect("!forwarder proxy"),
             chain_id,
             fee_token: fee_token.into(),
             is_high_priority,
+            relay_tasks: RelayTasks::new(chain_id, relay_task_store()),
         }
     }
 
+    /// Record relay tasks in `store` instead of the default store
+    pub fn with_relay_tasks(mut self, store: Arc<dyn RelayTaskStore>) -> Self {
+        self.relay_tasks = RelayTasks::new(self.chain_id, store);
+        self
+    }
+
+    /// Current state of relay task `task_id`
+    pub async fn get_task_status(&self, task_id: H256) -> Result<TaskState, GelatoError> {
+        Ok(self.gelato().get_task_status(task_id).await?.task_state)
+    }
+
     /// Submit a transaction to Gelato and poll until completion or failure.
+    ///
+    /// The submission carries an idempotency key. If an earlier attempt to
+    /// submit the same calldata created a task that has not reached a
+    /// terminal state, that task is polled instead of creating another.
     pub async fn submit_blocking(
         &self,
         domain: u32,
         contract_address: Address,
         tx: &TypedTransaction,
     ) -> Result<TxOutcome, GelatoError> {
-        let task_id = self
-            .dispatch_tx(domain, contract_address, tx)
-            .await?
-            .task_id();
-
-        info!(task_id = ?&task_id, "Submitted tx to Gelato relay.");
+        let data = tx.data().cloned().unwrap_or_default();
 
-        info!(task_id = ?&task_id, "Polling Gelato task...");
-        self.poll_task_id(task_id)
+        self.relay_tasks
+            .submit(
+                &data,
+                |key| async move {
+                    let task_id = self
+                        .dispatch_tx(domain, contract_address, tx, key)
+                        .await?
+                        .task_id();
+                    info!(task_id = ?&task_id, key = ?&key, "Submitted tx to Gelato relay.");
+                    Ok(task_id)
+                },
+                |task_id| async move {
+                    let state = self.get_task_status(task_id).await?;
+                    info!(task_id = ?&task_id, state = ?state, "Polling Gelato task...");
+                    self.poll_task_id(task_id)
+                        .await
+                        .map_err(|e| GelatoError::CustomError(e.into()))?
+                },
+            )
             .await
-            .map_err(|e| GelatoError::CustomError(e.into()))?
     }
 
-    /// Dispatch tx to Gelato and return task id.
+    /// Dispatch tx to Gelato with idempotency key `key` and return task id.
     pub async fn dispatch_tx(
         &self,
         domain: u32,
         contract_address: Address,
         tx: &TypedTransaction,
+        key: H256,
     ) -> Result<RelayResponse, GelatoError> {
         // If gas limit not hardcoded in tx, eth_estimateGas
         let gas_limit = tx
@@ -135,7 +181,7 @@ where
             "Dispatching tx to Gelato relay."
         );
 
-        self.send_forward_request(contract_address, data, gas_limit)
+        self.send_forward_request(contract_address, data, gas_limit, key)
             .await
     }
 
@@ -165,7 +211,11 @@ where
 
                     let txid = execution.transaction_hash;
 
-                    return Ok(TxOutcome { txid });
+                    return Ok(TxOutcome {
+                        txid,
+                        gas_used: None,
+                        block_number: None,
+                    });
                 }
 
                 if status.task_state == TaskState::CheckPending {
@@ -179,12 +229,15 @@ where
 
     /// Format and sign forward request, then dispatch to Gelato relay service.
     ///
-    /// This function pads gas by 100k to allow for gelato ops
+    /// This function pads gas by 100k to allow for gelato ops. The request
+    /// carries idempotency key `key`, so the relay can answer a repeated
+    /// request with the task it already created.
     pub async fn send_forward_request(
         &self,
         target: Address,
         data: impl Into<Bytes>,
         gas_limit: U64,
+        key: H256,
     ) -> Result<RelayResponse, GelatoError> {
         // add 100k gas padding for Gelato contract ops
         let adjusted_limit = gas_limit + U64::from(100_000);
@@ -213,6 +266,20 @@ where
             "Signed gelato forward request."
         );
 
-        Ok(self.gelato().send_forward_request(&request).await?)
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            IDEMPOTENCY_KEY_HEADER,
+            HeaderValue::from_str(&format!("{:?}", key)).expect("hex is a valid header"),
+        );
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .map_err(|e| GelatoError::CustomError(e.into()))?;
+
+        Ok(
+            GelatoClient::new_with_client(self.relay_url.clone(), client)
+                .send_forward_request(&request)
+                .await?,
+        )
     }
 }
@@ -0,0 +1,222 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// History depth detected for each provider, keyed by the name of the chain
+/// it serves. Agents report it in `/status`.
+pub static HISTORY_DEPTHS: Lazy<HistoryDepths> = Lazy::new(Default::default);
+
+/// Consecutive reads of pruned state before a depth is detected. A single
+/// miss may come from one lagging node behind a load balancer.
+const MISSES_TO_DETECT: u32 = 3;
+
+/// Time after which reads go unmoved again, to find out whether a provider
+/// detected to prune state still does
+const DEFAULT_RECHECK_AFTER: Duration = Duration::from_secs(600);
+
+/// State history a provider was found to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct HistoryDepth {
+    /// Number of blocks behind its head whose state the provider serves
+    pub depth: u64,
+    /// Block whose state was found missing
+    pub missing_block: u64,
+    /// Head of the provider when the state was found missing
+    pub head: u64,
+    /// Blocks the unmoved reads lagged the head when the state was found
+    /// missing
+    pub lag: u64,
+}
+
+/// Misses seen on a provider
+#[derive(Debug, Clone, Copy)]
+struct Misses {
+    /// Shallowest depth seen, or the latest once detected
+    depth: HistoryDepth,
+    /// Consecutive misses
+    count: u32,
+    /// When a miss last confirmed the depth
+    checked_at: Instant,
+}
+
+impl Misses {
+    fn detected(&self) -> bool {
+        self.count >= MISSES_TO_DETECT
+    }
+}
+
+/// State history depths of non-archive providers, detected from repeated
+/// reads of pruned state. Providers without a detected depth served every
+/// read so far, or missed too few reads to tell.
+#[derive(Debug)]
+pub struct HistoryDepths {
+    misses: RwLock<BTreeMap<String, Misses>>,
+    recheck_after: Duration,
+}
+
+impl Default for HistoryDepths {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECHECK_AFTER)
+    }
+}
+
+impl HistoryDepths {
+    /// Depths rechecked `recheck_after` their detection
+    pub fn new(recheck_after: Duration) -> Self {
+        Self {
+            misses: Default::default(),
+            recheck_after,
+        }
+    }
+
+    /// Depth detected for `provider`, if reads of pruned state failed on it
+    /// repeatedly
+    pub fn get(&self, provider: &str) -> Option<HistoryDepth> {
+        self.misses
+            .read()
+            .expect("poisoned")
+            .get(provider)
+            .filter(|misses| misses.detected())
+            .map(|misses| misses.depth)
+    }
+
+    /// Record that `provider` had no state for `block` with its head at
+    /// `head`, `lag` blocks ahead of its unmoved reads. Returns the depth to
+    /// read again within. Until the depth is detected, the shallowest miss
+    /// is kept. Once detected, each miss replaces it, so the depth follows
+    /// the provider as it grows or shrinks.
+    pub fn record_missing(&self, provider: &str, block: u64, head: u64, lag: u64) -> u64 {
+        let depth = HistoryDepth {
+            depth: head.saturating_sub(block).saturating_sub(1),
+            missing_block: block,
+            head,
+            lag,
+        };
+        let mut misses = self.misses.write().expect("poisoned");
+        let entry = misses.entry(provider.to_owned()).or_insert_with(|| Misses {
+            depth,
+            count: 0,
+            checked_at: Instant::now(),
+        });
+        let was_detected = entry.detected();
+        let previous = entry.depth.depth;
+        if was_detected || depth.depth < entry.depth.depth {
+            entry.depth = depth;
+        }
+        entry.count = entry.count.saturating_add(1);
+        entry.checked_at = Instant::now();
+
+        if entry.detected() && (!was_detected || entry.depth.depth != previous) {
+            warn!(
+                provider,
+                depth = entry.depth.depth,
+                missing_block = block,
+                head,
+                "Provider for {} serves the state of its last {} blocks only. Moving reads within its history.",
+                provider,
+                entry.depth.depth,
+            );
+        }
+        entry.depth.depth
+    }
+
+    /// Record that `provider` served an unmoved read. Earlier misses came
+    /// from a transient failure, or the provider keeps deeper history now.
+    pub fn record_served(&self, provider: &str) {
+        let mut misses = self.misses.write().expect("poisoned");
+        if let Some(previous) = misses.remove(provider) {
+            if previous.detected() {
+                info!(
+                    provider,
+                    "Provider for {} serves unmoved reads again. Reading at the pinned block.",
+                    provider,
+                );
+            }
+        }
+    }
+
+    /// Block at or after `block` whose state `provider` serves, for an
+    /// unmoved read at `block`. Moved past the detected depth by the lag
+    /// seen on the provider. Unmoved once the depth is due for a recheck.
+    pub fn within(&self, provider: &str, block: u64) -> u64 {
+        match self.misses.read().expect("poisoned").get(provider) {
+            Some(misses)
+                if misses.detected() && misses.checked_at.elapsed() < self.recheck_after =>
+            {
+                block.saturating_add(misses.depth.lag.saturating_sub(misses.depth.depth))
+            }
+            _ => block,
+        }
+    }
+
+    /// Detected depths by provider, as reported in `/status`
+    pub fn status(&self) -> BTreeMap<String, serde_json::Value> {
+        self.misses
+            .read()
+            .expect("poisoned")
+            .iter()
+            .filter(|(_, misses)| misses.detected())
+            .map(|(provider, misses)| {
+                (
+                    provider.clone(),
+                    serde_json::to_value(misses.depth).expect("!serialize"),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_detects_depths_from_repeated_misses() {
+        let depths = HistoryDepths::default();
+        assert_eq!(depths.within("ethereum", 1000), 1000);
+
+        // a single miss is retried within it but not detected
+        assert_eq!(depths.record_missing("ethereum", 1000, 1200, 200), 199);
+        assert_eq!(depths.get("ethereum"), None);
+        assert_eq!(depths.within("ethereum", 1000), 1000);
+        depths.record_served("ethereum");
+
+        // repeated misses keep the shallowest depth
+        assert_eq!(depths.record_missing("ethereum", 1000, 1200, 200), 199);
+        assert_eq!(depths.record_missing("ethereum", 1100, 1229, 129), 128);
+        assert_eq!(depths.record_missing("ethereum", 1100, 1300, 200), 128);
+        assert_eq!(depths.get("ethereum").unwrap().depth, 128);
+        // moved by the lag past the depth
+        assert_eq!(depths.within("ethereum", 1100), 1101);
+        assert_eq!(depths.within("moonbeam", 1100), 1100);
+
+        let status = depths.status();
+        assert_eq!(status["ethereum"]["depth"], 128);
+        assert_eq!(status["ethereum"]["missing_block"], 1100);
+        assert!(!status.contains_key("moonbeam"));
+
+        // once detected, misses follow the provider's depth
+        assert_eq!(depths.record_missing("ethereum", 1000, 1300, 300), 299);
+        assert_eq!(depths.within("ethereum", 1100), 1101);
+    }
+
+    #[test]
+    fn it_rechecks_detected_depths() {
+        let depths = HistoryDepths::new(Duration::ZERO);
+        for _ in 0..MISSES_TO_DETECT {
+            depths.record_missing("ethereum", 1000, 1200, 200);
+        }
+        assert!(depths.get("ethereum").is_some());
+
+        // due for a recheck, reads go unmoved
+        assert_eq!(depths.within("ethereum", 1000), 1000);
+        depths.record_served("ethereum");
+        assert_eq!(depths.get("ethereum"), None);
+        assert!(depths.status().is_empty());
+    }
+}
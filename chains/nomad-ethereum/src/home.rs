@@ -1,22 +1,34 @@
 #![allow(clippy::enum_variant_names)]
 #![allow(missing_docs)]
+#![deny(clippy::cast_possible_truncation)]
 
 use async_trait::async_trait;
 use color_eyre::Result;
 use ethers::{
-    core::types::{Signature, H256, U256},
+    contract::{ContractError, EthLogDecode, LogMeta},
+    core::types::{Address, BlockNumber, Log, TransactionReceipt, H256, U256, U64},
     providers::Middleware,
 };
-use futures_util::future::join_all;
+use futures_util::future::try_join_all;
 use nomad_core::{
-    Common, CommonIndexer, ContractLocator, DoubleUpdate, Home, HomeIndexer, Message,
-    RawCommittedMessage, SignedUpdate, SignedUpdateWithMeta, State, TxOutcome, Update, UpdateMeta,
+    utils::{abbreviated_hex, saturating_u64},
+    Common, CommonIndexer, ContractLocator, DispatchOutcome, DoubleUpdate, Home, HomeIndexer,
+    Message, RawCommittedMessage, SignedUpdate, SignedUpdateWithMeta, State, TxOutcome, Update,
 };
+use nomad_ethereum_bindings::{
+    home::{DispatchCall, DispatchFilter, Home as EthereumHomeInternal},
+    views::{decode_log_borrowed, DispatchView},
+};
+use nomad_types::{CheckedCast, ChecksumAddress, LeafIndex};
 use nomad_xyz_configuration::HomeGasLimits;
-use std::{convert::TryFrom, sync::Arc};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 use tracing::instrument;
 
-use crate::{bindings::home::Home as EthereumHomeInternal, utils, EthereumError, TxSubmitter};
+use crate::{
+    events::{block_timestamps, block_windows, merge_windowed_events, sorted_updates_with_meta},
+    pinned, utils, EthereumError, HomeState, SubmitWithGas, TxSubmitter,
+};
 
 impl<M> std::fmt::Display for EthereumHomeInternal<M>
 where
@@ -27,6 +39,99 @@ where
     }
 }
 
+/// Encode a `Message` as the calldata of a
+/// `Home.dispatch(uint32,bytes32,bytes)` call
+pub fn encode_dispatch_calldata(message: &Message) -> Vec<u8> {
+    use ethers::core::abi::AbiEncode;
+
+    DispatchCall {
+        destination_domain: message.destination,
+        recipient_address: message.recipient.to_fixed_bytes(),
+        message_body: message.body.clone().into(),
+    }
+    .encode()
+}
+
+/// Locate a dispatched message from the `Dispatch` event emitted by the home
+/// at `home` in the receipt of its dispatch transaction
+fn dispatch_outcome(
+    home: Address,
+    receipt: &TransactionReceipt,
+    tx: TxOutcome,
+) -> Result<DispatchOutcome, EthereumError> {
+    let dispatch = receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == home)
+        .find_map(|log| DispatchFilter::decode_log(&log.clone().into()).ok())
+        .ok_or(EthereumError::NoDispatchEvent(tx.txid))?;
+
+    Ok(DispatchOutcome {
+        tx,
+        leaf_index: LeafIndex::try_from(dispatch.leaf_index)?.into(),
+        message_hash: dispatch.message_hash.into(),
+        destination_and_nonce: dispatch.destination_and_nonce,
+    })
+}
+
+/// Decode the calldata of a `Home.dispatch(uint32,bytes32,bytes)` call into
+/// a `Message`.
+///
+/// The offset and length words of the dynamic `bytes` argument are bounds
+/// checked against the calldata before ABI decoding, so malformed calldata
+/// returns an error instead of tripping arithmetic overflow in the decoder.
+pub fn decode_dispatch_calldata(calldata: &[u8]) -> Result<Message, EthereumError> {
+    use ethers::{contract::EthCall, core::abi::AbiDecode};
+
+    let invalid =
+        |reason: &str| -> EthereumError { format!("invalid dispatch calldata: {}", reason).into() };
+
+    if calldata.len() < 4 || calldata[..4] != DispatchCall::selector() {
+        return Err(invalid("selector mismatch"));
+    }
+
+    let args = &calldata[4..];
+    if args.len() < 96 {
+        return Err(invalid("truncated arguments"));
+    }
+
+    let available = U256::from(args.len());
+    let offset = U256::from_big_endian(&args[64..96]);
+    if offset > available - 32 {
+        return Err(invalid("body offset out of bounds"));
+    }
+
+    let offset = offset.try_usize()?;
+    let length = U256::from_big_endian(&args[offset..offset + 32]);
+    if length > available - (offset + 32) {
+        return Err(invalid("body length out of bounds"));
+    }
+
+    let call = DispatchCall::decode(calldata).map_err(|e| EthereumError::CustomError(e.into()))?;
+
+    Ok(Message {
+        destination: call.destination_domain,
+        recipient: call.recipient_address.into(),
+        body: call.message_body.to_vec(),
+    })
+}
+
+/// Blocks each `Dispatch` log query of `EthereumHomeIndexer::fetch_dispatches`
+/// spans, unless the indexer has a `max_block_range`
+pub const DEFAULT_DISPATCH_WINDOW: u32 = 2000;
+
+/// Log queries of one dispatch fetch in flight at once
+const MAX_CONCURRENT_LOG_QUERIES: usize = 4;
+
+/// A committed message along with the block it was dispatched in
+#[derive(Debug, Clone, PartialEq)]
+pub struct DispatchedMessage {
+    /// Block the `Dispatch` event was emitted in
+    pub block_number: u64,
+    /// The committed message
+    pub message: RawCommittedMessage,
+}
+
 #[derive(Debug)]
 /// Struct that retrieves event data for an Ethereum home
 pub struct EthereumHomeIndexer<R>
@@ -35,13 +140,15 @@ where
 {
     contract: Arc<EthereumHomeInternal<R>>,
     provider: Arc<R>,
+    max_block_range: Option<u32>,
 }
 
 impl<R> EthereumHomeIndexer<R>
 where
     R: ethers::providers::Middleware + 'static,
 {
-    /// Create new EthereumHomeIndexer
+    /// Create new EthereumHomeIndexer. Log queries span at most
+    /// `max_block_range` blocks each, if set.
     pub fn new(
         provider: Arc<R>,
         ContractLocator {
@@ -49,22 +156,109 @@ where
             domain,
             address,
         }: &ContractLocator,
+        max_block_range: Option<u32>,
     ) -> Self {
+        let evm_address = address.as_ethereum_address().expect("!eth address");
         tracing::info!(
-            address = ?address.as_ethereum_address(),
+            address = %ChecksumAddress(evm_address),
             name = name,
             domain = domain,
             "Connecting Home Indexer"
         );
 
         Self {
-            contract: Arc::new(EthereumHomeInternal::new(
-                address.as_ethereum_address().expect("!eth address"),
-                provider.clone(),
-            )),
+            contract: Arc::new(EthereumHomeInternal::new(evm_address, provider.clone())),
             provider,
+            max_block_range,
         }
     }
+
+    /// Messages dispatched from block `from` to `to` with the blocks they
+    /// were dispatched in, sorted by leaf index. The range is queried in
+    /// windows of `max_block_range` blocks, or `DEFAULT_DISPATCH_WINDOW` if
+    /// unset, a few windows at once, so ranges beyond provider log limits
+    /// can be indexed.
+    #[instrument(err, skip(self))]
+    pub async fn fetch_dispatches(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<DispatchedMessage>, EthereumError> {
+        let window = self.max_block_range.unwrap_or(DEFAULT_DISPATCH_WINDOW);
+        self.dispatched_messages(from.try_u32()?, to.try_u32()?, Some(window))
+            .await
+    }
+
+    /// Messages dispatched from block `from` to `to`, queried in windows of
+    /// at most `max_block_range` blocks, sorted by leaf index and without
+    /// logs returned by more than one query
+    async fn dispatched_messages(
+        &self,
+        from: u32,
+        to: u32,
+        max_block_range: Option<u32>,
+    ) -> Result<Vec<DispatchedMessage>, EthereumError> {
+        let permits = Semaphore::new(MAX_CONCURRENT_LOG_QUERIES);
+        let queries = block_windows(from, to, max_block_range)
+            .into_iter()
+            .map(|(start, end)| {
+                let permits = &permits;
+                async move {
+                    let _permit = permits.acquire().await.expect("!semaphore");
+                    let filter = self
+                        .contract
+                        .dispatch_filter()
+                        .from_block(start)
+                        .to_block(end)
+                        .filter;
+                    let logs = self
+                        .provider
+                        .get_logs(&filter)
+                        .await
+                        .map_err(ContractError::<R>::MiddlewareError)?;
+                    logs.iter()
+                        .map(dispatched_message)
+                        .collect::<Result<Vec<_>, EthereumError>>()
+                }
+            });
+        let windows = try_join_all(queries).await?;
+        let mut events = merge_windowed_events(windows.into_iter().flatten().collect());
+
+        events.sort_by(|a, b| a.0.leaf_index.cmp(&b.0.leaf_index));
+
+        // Dispatch times, for the processor's maximum message age
+        let timestamps = block_timestamps(
+            self.provider.as_ref(),
+            events.iter().map(|(_, meta)| meta.block_number.as_u64()),
+        )
+        .await;
+
+        Ok(events
+            .into_iter()
+            .map(|(mut message, meta)| {
+                let block_number = meta.block_number.as_u64();
+                message.timestamp = timestamps.get(&block_number).copied();
+                DispatchedMessage {
+                    block_number,
+                    message,
+                }
+            })
+            .collect())
+    }
+}
+
+/// The message a `Dispatch` log commits, without its timestamp. Decoded
+/// borrowing from the log, so the message is copied once, into the record.
+fn dispatched_message(log: &Log) -> Result<(RawCommittedMessage, LogMeta), EthereumError> {
+    let dispatch: DispatchView = decode_log_borrowed(&log.topics, &log.data)
+        .map_err(|e| EthereumError::ContractError(e.into()))?;
+    let message = RawCommittedMessage {
+        leaf_index: LeafIndex::try_from(dispatch.leaf_index)?.into(),
+        committed_root: H256::from(*dispatch.committed_root),
+        message: dispatch.message.to_vec(),
+        timestamp: None,
+    };
+    Ok((message, LogMeta::from(log)))
 }
 
 #[async_trait]
@@ -81,7 +275,17 @@ where
             .get_block_number()
             .await
             .map_err(|e| EthereumError::MiddlewareError(e.into()))?
-            .as_u32())
+            .try_u32()?)
+    }
+
+    #[instrument(err, skip(self))]
+    async fn get_latest_block_timestamp(&self) -> Result<Option<u64>, Self::Error> {
+        Ok(self
+            .provider
+            .get_block(ethers::core::types::BlockNumber::Latest)
+            .await
+            .map_err(|e| EthereumError::MiddlewareError(e.into()))?
+            .map(|block| saturating_u64(block.timestamp)))
     }
 
     #[instrument(err, skip(self))]
@@ -90,55 +294,19 @@ where
         from: u32,
         to: u32,
     ) -> Result<Vec<SignedUpdateWithMeta>, Self::Error> {
-        let mut events = self
-            .contract
-            .update_filter()
-            .from_block(from)
-            .to_block(to)
-            .query_with_meta()
-            .await?;
-
-        events.sort_by(|a, b| {
-            let mut ordering = a.1.block_number.cmp(&b.1.block_number);
-            if ordering == std::cmp::Ordering::Equal {
-                ordering = a.1.transaction_index.cmp(&b.1.transaction_index);
-            }
-
-            ordering
-        });
-
-        let update_futs: Vec<_> = events
-            .iter()
-            .map(|event| async {
-                let signature = Signature::try_from(event.0.signature.as_ref())
-                    .expect("chain accepted invalid signature");
-
-                let update = Update {
-                    home_domain: event.0.home_domain,
-                    previous_root: event.0.old_root.into(),
-                    new_root: event.0.new_root.into(),
-                };
-
-                let block_number = event.1.block_number.as_u64();
-                let timestamp = self
-                    .provider
-                    .get_block(block_number)
-                    .await
-                    .ok()
-                    .flatten()
-                    .map(|b| b.timestamp.as_u64());
-
-                SignedUpdateWithMeta {
-                    signed_update: SignedUpdate { update, signature },
-                    metadata: UpdateMeta {
-                        block_number,
-                        timestamp,
-                    },
-                }
-            })
-            .collect();
+        let mut events = vec![];
+        for (start, end) in block_windows(from, to, self.max_block_range) {
+            events.extend(
+                self.contract
+                    .update_filter()
+                    .from_block(start)
+                    .to_block(end)
+                    .query_with_meta()
+                    .await?,
+            );
+        }
 
-        Ok(join_all(update_futs).await)
+        sorted_updates_with_meta(self.provider.as_ref(), merge_windowed_events(events)).await
     }
 }
 
@@ -153,23 +321,11 @@ where
         from: u32,
         to: u32,
     ) -> Result<Vec<RawCommittedMessage>, <Self as CommonIndexer>::Error> {
-        let mut events = self
-            .contract
-            .dispatch_filter()
-            .from_block(from)
-            .to_block(to)
-            .query()
-            .await?;
-
-        events.sort_by(|a, b| a.leaf_index.cmp(&b.leaf_index));
-
-        Ok(events
+        Ok(self
+            .dispatched_messages(from, to, self.max_block_range)
+            .await?
             .into_iter()
-            .map(|f| RawCommittedMessage {
-                leaf_index: f.leaf_index.as_u32(),
-                committed_root: f.committed_root.into(),
-                message: f.message.to_vec(),
-            })
+            .map(|dispatched| dispatched.message)
             .collect())
     }
 }
@@ -205,23 +361,64 @@ where
         }: &ContractLocator,
         gas: Option<HomeGasLimits>,
     ) -> Self {
+        let evm_address = address.as_ethereum_address().expect("!eth address");
         tracing::info!(
-            address = ?address.as_ethereum_address(),
+            address = %ChecksumAddress(evm_address),
             name = name,
             domain = domain,
             "Connecting Home"
         );
         Self {
             submitter,
-            contract: Arc::new(EthereumHomeInternal::new(
-                address.as_ethereum_address().expect("!eth address"),
-                read_provider,
-            )),
+            contract: Arc::new(EthereumHomeInternal::new(evm_address, read_provider)),
             domain: *domain,
             name: name.to_owned(),
             gas,
         }
     }
+
+    /// Fetch the Home state, rejecting state words outside the contract's
+    /// enum.
+    pub async fn status(&self) -> Result<HomeState, EthereumError> {
+        HomeState::try_from(self.contract.state().call().await?)
+    }
+
+    /// Run `read` at a block pinned on the read provider
+    async fn read_pinned<T, F, Fut>(&self, read: F) -> Result<T, EthereumError>
+    where
+        F: Fn(U64) -> Fut,
+        Fut: std::future::Future<Output = Result<T, EthereumError>>,
+    {
+        pinned::read_pinned(&*self.contract.client(), &self.name, read).await
+    }
+}
+
+/// The update suggested by `suggestUpdate`, if it suggested one. A zero new
+/// root means the queue is empty and there is nothing to sign.
+fn suggested_update(home_domain: u32, previous_root: H256, new_root: H256) -> Option<Update> {
+    if new_root.is_zero() {
+        return None;
+    }
+
+    Some(Update {
+        home_domain,
+        previous_root,
+        new_root,
+    })
+}
+
+impl<W, R> SubmitWithGas<W> for EthereumHome<W, R>
+where
+    W: ethers::providers::Middleware + 'static,
+    R: ethers::providers::Middleware + 'static,
+{
+    fn tx_submitter(&self) -> &TxSubmitter<W> {
+        &self.submitter
+    }
+
+    fn contract_location(&self) -> (u32, Address) {
+        (self.domain, self.contract.address())
+    }
 }
 
 impl<W, R> std::fmt::Display for EthereumHome<W, R>
@@ -270,13 +467,7 @@ where
 
     #[tracing::instrument(err, skip(self))]
     async fn state(&self) -> Result<State, Self::Error> {
-        let state = self.contract.state().call().await?;
-        match state {
-            0 => Ok(State::Uninitialized),
-            1 => Ok(State::Active),
-            2 => Ok(State::Failed),
-            _ => unreachable!(),
-        }
+        Ok(self.status().await?.into())
     }
 
     #[tracing::instrument(err, skip(self))]
@@ -300,9 +491,7 @@ where
             );
         }
 
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
-            .await
+        self.submit_with_gas(tx, &self.submitter.gas).await
     }
 
     #[tracing::instrument(err, skip(self, double), fields(double = %double))]
@@ -321,9 +510,7 @@ where
             tx.tx.set_gas(U256::from(limits.double_update));
         }
 
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
-            .await
+        self.submit_with_gas(tx, &self.submitter.gas).await
     }
 }
 
@@ -350,9 +537,24 @@ where
             message.body.clone().into(),
         );
 
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
+        self.submit_with_gas(tx, &self.submitter.gas).await
+    }
+
+    #[tracing::instrument(err, skip(self))]
+    async fn dispatch_tracked(
+        &self,
+        message: &Message,
+    ) -> Result<DispatchOutcome, <Self as Common>::Error> {
+        let outcome = self.dispatch(message).await?;
+        let receipt = self
+            .contract
+            .client()
+            .get_transaction_receipt(outcome.txid)
             .await
+            .map_err(|e| EthereumError::MiddlewareError(e.into()))?
+            .ok_or(EthereumError::NoDispatchEvent(outcome.txid))?;
+
+        dispatch_outcome(self.contract.address(), &receipt, outcome)
     }
 
     async fn queue_length(&self) -> Result<U256, <Self as Common>::Error> {
@@ -363,7 +565,40 @@ where
         Ok(self.contract.queue_contains(root.into()).call().await?)
     }
 
-    #[tracing::instrument(err, skip(self), fields(hex_signature = %format!("0x{}", hex::encode(update.signature.to_vec()))))]
+    async fn queue_end(&self) -> Result<H256, <Self as Common>::Error> {
+        Ok(self.contract.queue_end().call().await?.into())
+    }
+
+    #[tracing::instrument(err, skip(self))]
+    async fn counted_root(&self) -> Result<(H256, u32), <Self as Common>::Error> {
+        let (root, count) = self
+            .read_pinned(|block| async move {
+                Ok::<_, EthereumError>(futures_util::try_join!(
+                    self.contract.root().block(block).call(),
+                    self.contract.count().block(block).call(),
+                )?)
+            })
+            .await?;
+        Ok((root.into(), LeafIndex::try_from(count)?.into()))
+    }
+
+    #[tracing::instrument(err, skip(self))]
+    async fn tree_counts(&self) -> Result<(u32, u32), <Self as Common>::Error> {
+        let (tree_count, count) = self
+            .read_pinned(|block| async move {
+                Ok::<_, EthereumError>(futures_util::try_join!(
+                    self.contract.tree().block(block).call(),
+                    self.contract.count().block(block).call(),
+                )?)
+            })
+            .await?;
+        Ok((
+            LeafIndex::try_from(tree_count)?.into(),
+            LeafIndex::try_from(count)?.into(),
+        ))
+    }
+
+    #[tracing::instrument(err, skip(self), fields(hex_signature = %abbreviated_hex(&update.signature.to_vec())))]
     async fn improper_update(
         &self,
         update: &SignedUpdate,
@@ -382,26 +617,372 @@ where
             );
         }
 
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
-            .await
+        self.submit_with_gas(tx, &self.submitter.gas).await
     }
 
     #[tracing::instrument(err, skip(self))]
     async fn produce_update(&self) -> Result<Option<Update>, <Self as Common>::Error> {
         let (a, b) = self.contract.suggest_update().call().await?;
+        Ok(suggested_update(self.domain, a.into(), b.into()))
+    }
+
+    #[tracing::instrument(err, skip(self))]
+    async fn produce_pending_update(&self) -> Result<Option<Update>, <Self as Common>::Error> {
+        let (a, b) = self
+            .contract
+            .suggest_update()
+            .block(BlockNumber::Pending)
+            .call()
+            .await?;
+        Ok(suggested_update(self.domain, a.into(), b.into()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ethers::{
+        contract::EthEvent,
+        core::{
+            abi::{self, AbiEncode, Token},
+            types::{Address, Bytes, Filter, FilterBlockOption, Log},
+        },
+        providers::{FromErr, MockProvider, Provider, ProviderError},
+        signers::{LocalWallet, Signer},
+    };
+    use nomad_ethereum_bindings::home::{DispatchFilter, UpdateFilter};
+    use std::sync::Mutex;
+
+    /// Middleware answering `eth_getLogs` from `logs` and recording the
+    /// block range of each query. Like some providers, it also returns the
+    /// logs of the block after the range, and in no particular order. Other
+    /// calls go to a mock provider without responses.
+    #[derive(Debug)]
+    struct LogsMiddleware {
+        inner: Provider<MockProvider>,
+        logs: Vec<Log>,
+        queried: Mutex<Vec<(u64, u64)>>,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("{0}")]
+    struct LogsMiddlewareError(ProviderError);
+
+    impl FromErr<ProviderError> for LogsMiddlewareError {
+        fn from(src: ProviderError) -> Self {
+            Self(src)
+        }
+    }
+
+    #[async_trait]
+    impl Middleware for LogsMiddleware {
+        type Error = LogsMiddlewareError;
+        type Provider = MockProvider;
+        type Inner = Provider<MockProvider>;
+
+        fn inner(&self) -> &Self::Inner {
+            &self.inner
+        }
 
-        let previous_root: H256 = a.into();
-        let new_root: H256 = b.into();
+        async fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>, Self::Error> {
+            let (from, to) = match filter.block_option {
+                FilterBlockOption::Range {
+                    from_block: Some(BlockNumber::Number(from)),
+                    to_block: Some(BlockNumber::Number(to)),
+                } => (from.as_u64(), to.as_u64()),
+                _ => panic!("unexpected filter {:?}", filter),
+            };
+            self.queried.lock().unwrap().push((from, to));
+
+            Ok(self
+                .logs
+                .iter()
+                .rev()
+                .filter(|log| {
+                    let block = log.block_number.unwrap().as_u64();
+                    from <= block && block <= to + 1
+                })
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn windowed_indexer(
+        logs: Vec<Log>,
+        max_block_range: Option<u32>,
+    ) -> EthereumHomeIndexer<LogsMiddleware> {
+        let provider = LogsMiddleware {
+            inner: Provider::mocked().0,
+            logs,
+            queried: Default::default(),
+        };
+        EthereumHomeIndexer::new(
+            Arc::new(provider),
+            &ContractLocator {
+                name: "ethereum".into(),
+                domain: 6648936,
+                address: Address::repeat_byte(1).into(),
+            },
+            max_block_range,
+        )
+    }
+
+    /// Block ranges of the log queries made, in block order
+    fn queried(indexer: &EthereumHomeIndexer<LogsMiddleware>) -> Vec<(u64, u64)> {
+        let mut queried = indexer.provider.queried.lock().unwrap().clone();
+        queried.sort_unstable();
+        queried
+    }
 
-        if new_root.is_zero() {
-            return Ok(None);
+    fn log_at(block: u64, log_index: u64, topics: Vec<H256>, data: Vec<u8>) -> Log {
+        Log {
+            topics,
+            data: data.into(),
+            block_hash: Some(H256::from_low_u64_be(block)),
+            block_number: Some(block.into()),
+            transaction_hash: Some(H256::from_low_u64_be(block << 8 | log_index)),
+            transaction_index: Some(log_index.into()),
+            log_index: Some(log_index.into()),
+            ..Default::default()
         }
+    }
+
+    fn dispatch_log(block: u64, log_index: u64, leaf_index: u64) -> Log {
+        log_at(
+            block,
+            log_index,
+            vec![
+                DispatchFilter::signature(),
+                H256::repeat_byte(0xaa),
+                H256::from_low_u64_be(leaf_index),
+                H256::from_low_u64_be(1),
+            ],
+            abi::encode(&[
+                Token::FixedBytes(H256::from_low_u64_be(leaf_index).as_bytes().to_vec()),
+                Token::Bytes(leaf_index.to_be_bytes().to_vec()),
+            ]),
+        )
+    }
+
+    #[tokio::test]
+    async fn it_queries_messages_in_windows() {
+        // Two on the boundaries of each window
+        let blocks = [100, 124, 125, 140, 149, 150, 159];
+        let logs = blocks
+            .iter()
+            .enumerate()
+            .map(|(leaf_index, block)| dispatch_log(*block, 0, leaf_index as u64))
+            .collect();
+        let indexer = windowed_indexer(logs, Some(25));
+
+        let messages = indexer.fetch_sorted_messages(100, 159).await.unwrap();
+        assert_eq!(queried(&indexer), vec![(100, 124), (125, 149), (150, 159)]);
+        let leaf_indexes: Vec<_> = messages.iter().map(|m| m.leaf_index as usize).collect();
+        assert_eq!(leaf_indexes, (0..blocks.len()).collect::<Vec<_>>());
+        assert_eq!(messages[3].message, 3u64.to_be_bytes());
+        assert_eq!(messages[3].committed_root, H256::from_low_u64_be(3));
+    }
+
+    #[tokio::test]
+    async fn it_fetches_dispatches_in_default_windows() {
+        // Out of leaf order, and on window boundaries
+        let blocks = [4999, 0, 1999, 2000, 3999, 4000];
+        let logs = blocks
+            .iter()
+            .enumerate()
+            .map(|(leaf_index, block)| dispatch_log(*block, 3, leaf_index as u64))
+            .collect();
+        let indexer = windowed_indexer(logs, None);
+
+        let dispatches = indexer.fetch_dispatches(0, 4999).await.unwrap();
+        assert_eq!(
+            queried(&indexer),
+            vec![(0, 1999), (2000, 3999), (4000, 4999)]
+        );
+        let found: Vec<_> = dispatches
+            .iter()
+            .map(|dispatched| (dispatched.message.leaf_index, dispatched.block_number))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (0, 4999),
+                (1, 0),
+                (2, 1999),
+                (3, 2000),
+                (4, 3999),
+                (5, 4000)
+            ]
+        );
+        assert_eq!(
+            dispatches[2].message.committed_root,
+            H256::from_low_u64_be(2)
+        );
+
+        // Unindexable block numbers are errors, not truncated
+        assert!(indexer
+            .fetch_dispatches(0, u64::from(u32::MAX) + 1)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn it_queries_updates_in_windows() {
+        let signer: LocalWallet = "1".repeat(64).parse().unwrap();
+        let mut logs = vec![];
+        let mut roots = vec![];
+        // Updates at the start of each window, and two in one block
+        for (i, (block, log_index)) in [(100, 0), (110, 0), (110, 1), (120, 0), (130, 0)]
+            .into_iter()
+            .enumerate()
+        {
+            let update = Update {
+                home_domain: 6648936,
+                previous_root: H256::from_low_u64_be(i as u64),
+                new_root: H256::from_low_u64_be(i as u64 + 1),
+            }
+            .sign_with(&signer)
+            .await
+            .unwrap();
+            logs.push(log_at(
+                block,
+                log_index,
+                vec![
+                    UpdateFilter::signature(),
+                    H256::from_low_u64_be(6648936),
+                    update.update.previous_root,
+                    update.update.new_root,
+                ],
+                abi::encode(&[Token::Bytes(update.signature.to_vec())]),
+            ));
+            roots.push(update.update.new_root);
+        }
+        let indexer = windowed_indexer(logs, Some(10));
+
+        let updates = indexer.fetch_sorted_updates(100, 139).await.unwrap();
+        assert_eq!(
+            queried(&indexer),
+            vec![(100, 109), (110, 119), (120, 129), (130, 139)]
+        );
+        let new_roots: Vec<_> = updates
+            .iter()
+            .map(|update| update.signed_update.update.new_root)
+            .collect();
+        assert_eq!(new_roots, roots);
+        assert_eq!(updates[2].metadata.block_number, 110);
+    }
+
+    fn dispatch_calldata() -> Vec<u8> {
+        DispatchCall {
+            destination_domain: 1650811245,
+            recipient_address: H256::repeat_byte(0xaa).to_fixed_bytes(),
+            message_body: Bytes::from(vec![1u8, 2, 3]),
+        }
+        .encode()
+    }
+
+    #[test]
+    fn it_suggests_no_update_for_an_empty_queue() {
+        let committed = H256::repeat_byte(1);
+        assert_eq!(suggested_update(1000, committed, H256::zero()), None);
+
+        let queue_end = H256::repeat_byte(2);
+        assert_eq!(
+            suggested_update(1000, committed, queue_end),
+            Some(Update {
+                home_domain: 1000,
+                previous_root: committed,
+                new_root: queue_end,
+            })
+        );
+    }
+
+    #[test]
+    fn it_decodes_dispatch_calldata() {
+        let message = decode_dispatch_calldata(&dispatch_calldata()).unwrap();
+        assert_eq!(message.destination, 1650811245);
+        assert_eq!(message.recipient, H256::repeat_byte(0xaa));
+        assert_eq!(message.body, vec![1u8, 2, 3]);
+    }
+
+    #[test]
+    fn it_encodes_dispatch_calldata() {
+        let message = Message {
+            destination: 1650811245,
+            recipient: H256::repeat_byte(0xaa),
+            body: vec![1u8, 2, 3],
+        };
+        let calldata = encode_dispatch_calldata(&message);
+        assert_eq!(calldata, dispatch_calldata());
+
+        let decoded = decode_dispatch_calldata(&calldata).unwrap();
+        assert_eq!(decoded.destination, message.destination);
+        assert_eq!(decoded.recipient, message.recipient);
+        assert_eq!(decoded.body, message.body);
+    }
+
+    #[test]
+    fn it_decodes_empty_dispatch_bodies() {
+        let calldata = DispatchCall {
+            destination_domain: 1650811245,
+            recipient_address: H256::repeat_byte(0xaa).to_fixed_bytes(),
+            message_body: Bytes::default(),
+        }
+        .encode();
+        assert_eq!(calldata.len(), 4 + 32 * 4);
+
+        let message = decode_dispatch_calldata(&calldata).unwrap();
+        assert_eq!(message.recipient, H256::repeat_byte(0xaa));
+        assert!(message.body.is_empty());
+    }
+
+    #[test]
+    fn it_rejects_malformed_dispatch_calldata() {
+        let calldata = dispatch_calldata();
+
+        // Truncated at every length
+        for len in 0..calldata.len() {
+            assert!(decode_dispatch_calldata(&calldata[..len]).is_err());
+        }
+
+        // Body offset pointing far past the end of the calldata
+        let mut bad_offset = calldata.clone();
+        bad_offset[4 + 64..4 + 96].copy_from_slice(&[0xff; 32]);
+        assert!(decode_dispatch_calldata(&bad_offset).is_err());
+
+        // Body length word close to usize::MAX
+        let mut bad_length = calldata;
+        bad_length[4 + 96..4 + 128].copy_from_slice(&[0xff; 32]);
+        bad_length[4 + 96..4 + 120].copy_from_slice(&[0; 24]);
+        assert!(decode_dispatch_calldata(&bad_length).is_err());
+    }
 
-        Ok(Some(Update {
-            home_domain: self.local_domain(),
-            previous_root,
-            new_root,
-        }))
+    #[test]
+    fn it_reads_dispatch_outcomes_from_receipts() {
+        let home = Address::repeat_byte(1);
+        let tx = TxOutcome {
+            txid: H256::repeat_byte(2),
+            gas_used: None,
+            block_number: None,
+        };
+        // A Dispatch event of another contract, then the home's
+        let mut receipt = TransactionReceipt {
+            logs: vec![dispatch_log(100, 0, 3), dispatch_log(100, 1, 7)],
+            ..Default::default()
+        };
+        receipt.logs[1].address = home;
+
+        let outcome = dispatch_outcome(home, &receipt, tx).unwrap();
+        assert_eq!(outcome.tx.txid, tx.txid);
+        assert_eq!(outcome.leaf_index, 7);
+        assert_eq!(outcome.message_hash, H256::repeat_byte(0xaa));
+        assert_eq!(outcome.destination_and_nonce, 1);
+
+        receipt.logs.truncate(1);
+        assert!(matches!(
+            dispatch_outcome(home, &receipt, tx),
+            Err(EthereumError::NoDispatchEvent(txid)) if txid == tx.txid
+        ));
     }
 }
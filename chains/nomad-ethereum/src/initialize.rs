@@ -0,0 +1,170 @@
+use ethers::prelude::*;
+use nomad_ethereum_bindings::{home::Home as HomeInternal, replica::Replica as ReplicaInternal};
+use std::sync::Arc;
+
+use crate::EthereumError;
+
+/// Result of an idempotent `initialize`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Initialization {
+    /// `initialize` was sent in the transaction
+    Initialized(H256),
+    /// The contract was already initialized with the expected values
+    AlreadyInitialized,
+}
+
+/// Values a Replica is initialized with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplicaInitialization {
+    /// Domain of the home the replica mirrors
+    pub remote_domain: u32,
+    /// Updater of the remote home
+    pub updater: Address,
+    /// Root the replica starts from
+    pub committed_root: H256,
+    /// Seconds before a root is confirmed
+    pub optimistic_seconds: U256,
+}
+
+/// Initialize the Home at `address` with `updater_manager`, unless it is
+/// already initialized with it. Errors if it was initialized with another
+/// updater manager.
+pub async fn initialize_home<M>(
+    address: Address,
+    client: Arc<M>,
+    updater_manager: Address,
+) -> Result<Initialization, EthereumError>
+where
+    M: Middleware + 'static,
+{
+    let home = HomeInternal::new(address, client);
+    let current = home.updater_manager().call().await?;
+    if !check_initialized(address, "updaterManager", current, updater_manager)? {
+        return send(home.initialize(updater_manager)).await;
+    }
+    Ok(Initialization::AlreadyInitialized)
+}
+
+/// Initialize the Replica at `address`, unless it is already initialized
+/// with `expected`. Errors if it was initialized with other values.
+///
+/// The committed root advances with updates, so an initialized replica is
+/// matched on the initial root being confirmed rather than on
+/// `committedRoot`.
+pub async fn initialize_replica<M>(
+    address: Address,
+    client: Arc<M>,
+    expected: ReplicaInitialization,
+) -> Result<Initialization, EthereumError>
+where
+    M: Middleware + 'static,
+{
+    let replica = ReplicaInternal::new(address, client);
+    let updater = replica.updater().call().await?;
+    if !check_initialized(address, "updater", updater, expected.updater)? {
+        return send(replica.initialize(
+            expected.remote_domain,
+            expected.updater,
+            expected.committed_root.into(),
+            expected.optimistic_seconds,
+        ))
+        .await;
+    }
+
+    let remote_domain = replica.remote_domain().call().await?;
+    check_initialized(
+        address,
+        "remoteDomain",
+        remote_domain,
+        expected.remote_domain,
+    )?;
+    let optimistic_seconds = replica.optimistic_seconds().call().await?;
+    check_initialized(
+        address,
+        "optimisticSeconds",
+        optimistic_seconds,
+        expected.optimistic_seconds,
+    )?;
+    // `initialize` confirms the initial root at 1, updates at later times
+    let committed_root = H256::from(replica.committed_root().call().await?);
+    if committed_root != expected.committed_root
+        && replica
+            .confirm_at(expected.committed_root.into())
+            .call()
+            .await?
+            .is_zero()
+    {
+        return Err(EthereumError::UnexpectedInitialization {
+            contract: address,
+            field: "committedRoot",
+            expected: format!("{:?}", expected.committed_root),
+            found: format!("{:?}", committed_root),
+        });
+    }
+
+    Ok(Initialization::AlreadyInitialized)
+}
+
+/// Whether a contract's `field` shows it was initialized. Unset fields mean
+/// it was not. Set fields must hold `expected`.
+fn check_initialized<T>(
+    contract: Address,
+    field: &'static str,
+    found: T,
+    expected: T,
+) -> Result<bool, EthereumError>
+where
+    T: Default + PartialEq + std::fmt::Debug,
+{
+    if found == T::default() {
+        return Ok(false);
+    }
+    if found != expected {
+        return Err(EthereumError::UnexpectedInitialization {
+            contract,
+            field,
+            expected: format!("{:?}", expected),
+            found: format!("{:?}", found),
+        });
+    }
+    Ok(true)
+}
+
+async fn send<M>(
+    call: ethers::contract::builders::ContractCall<M, ()>,
+) -> Result<Initialization, EthereumError>
+where
+    M: Middleware + 'static,
+{
+    let pending = call.send().await?;
+    let tx_hash = *pending;
+    match pending.await? {
+        Some(receipt) if receipt.status == Some(1.into()) => {
+            Ok(Initialization::Initialized(tx_hash))
+        }
+        Some(_) => Err(EthereumError::TxNotExecuted(tx_hash)),
+        None => Err(EthereumError::DroppedError(tx_hash)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_skips_matching_initializations() {
+        let contract = Address::repeat_byte(1);
+        let expected = Address::repeat_byte(2);
+
+        assert!(!check_initialized(contract, "updater", Address::zero(), expected).unwrap());
+        assert!(check_initialized(contract, "updater", expected, expected).unwrap());
+
+        let error = check_initialized(contract, "updater", Address::repeat_byte(3), expected)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("updater"), "{}", error);
+
+        assert!(check_initialized(contract, "remoteDomain", 1000u32, 1000).unwrap());
+        assert!(check_initialized(contract, "remoteDomain", 2000u32, 1000).is_err());
+    }
+}
@@ -22,24 +22,47 @@ pub use error::*;
 
 /// Retrying Provider
 mod retrying;
-pub use retrying::{RetryingProvider, RetryingProviderError};
+pub use retrying::{is_idempotent, RetryConfig, RetryingProvider, RetryingProviderError};
+
+/// JSON-RPC error classification
+mod rpc_errors;
+pub use rpc_errors::*;
+
+/// State history depth of non-archive providers
+mod history;
+pub use history::*;
+
+/// Reads of several values at one block
+mod pinned;
+
+/// Block range caps of providers' log queries
+mod log_ranges;
+pub use log_ranges::*;
 
 /// Gelato client types
 mod gelato;
 pub use gelato::*;
 
+/// Idempotent relay submissions
+mod relay_tasks;
+pub use relay_tasks::*;
+
+/// Nonce management of concurrent submissions
+mod nonce;
+pub use nonce::*;
+
 /// Chain submitter
 mod submitter;
 pub use submitter::*;
 
+/// Receipt await strategies
+mod receipts;
+pub use receipts::*;
+
 /// EthereumSigners
 mod signer;
 pub use signer::*;
 
-/// Contract binding
-#[cfg(not(doctest))]
-pub(crate) mod bindings;
-
 /// Home abi
 #[cfg(not(doctest))]
 mod home;
@@ -48,13 +71,63 @@ mod home;
 #[cfg(not(doctest))]
 mod replica;
 
+/// Conversion of contract events into core types
+#[cfg(not(doctest))]
+mod events;
+
+/// Decoding of calldata for known contracts
+#[cfg(not(doctest))]
+mod calldata;
+#[cfg(not(doctest))]
+pub use calldata::*;
+
+/// Safe transaction builder batches
+#[cfg(not(doctest))]
+mod safe;
+#[cfg(not(doctest))]
+pub use safe::*;
+
 /// XAppConnectionManager abi
 #[cfg(not(doctest))]
 mod xapp;
 
+/// Ownership of core contracts
+#[cfg(not(doctest))]
+mod ownership;
+#[cfg(not(doctest))]
+pub use ownership::*;
+
+/// Idempotent initialization of core contracts
+#[cfg(not(doctest))]
+mod initialize;
+#[cfg(not(doctest))]
+pub use initialize::*;
+
+/// Checks of deployed contract code
+#[cfg(not(doctest))]
+mod bytecode;
+#[cfg(not(doctest))]
+pub use bytecode::*;
+
+/// States of core contracts
+mod state;
+pub use state::*;
+
+/// Proofs of messages as the replica takes them
+mod proofs;
+pub use proofs::*;
+
+/// Simulation of calls with eth_call
+mod simulate;
+pub use simulate::*;
+
 /// Gas increasing Middleware
 mod gas;
 
+/// Transaction fee strategies
+mod fees;
+pub use fees::*;
+
 /// Utilities
 mod utils;
 
@@ -72,6 +145,7 @@ boxed_indexer!(
     make_home_indexer,
     EthereumHomeIndexer,
     HomeIndexer<Error = EthereumError>,
+    max_block_range: Option<u32>
 );
 boxed_indexer!(
     make_replica_indexer,
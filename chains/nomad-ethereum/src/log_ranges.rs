@@ -0,0 +1,303 @@
+use once_cell::sync::Lazy;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::{cmp::min, collections::BTreeMap, sync::RwLock};
+use tracing::{info, warn};
+
+/// Block range cap of each provider's log queries, keyed by the name of the
+/// chain it serves. Agents report it in `/status`.
+pub static LOG_RANGE_CAPS: Lazy<LogRangeCaps> = Lazy::new(Default::default);
+
+/// Block ranges vendors report with their errors: a range to retry with, a
+/// cap before `range` (`up to a 2K block range`), or after it (`maximum
+/// block range: 5000`)
+static SUGGESTED_RANGE: Lazy<Regex> =
+    Lazy::new(|| case_insensitive(r"\[\s*0x([0-9a-f]+)\s*,\s*0x([0-9a-f]+)\s*\]"));
+static CAP_BEFORE_RANGE: Lazy<Regex> =
+    Lazy::new(|| case_insensitive(r"(\d[\d,]*)\s*(k)?\s*(block\s*)?range"));
+static CAP_AFTER_RANGE: Lazy<Regex> =
+    Lazy::new(|| case_insensitive(r"range[^\d\[]{0,24}?(\d[\d,]*)\s*(k)?"));
+
+/// Consecutive queries served at a learned cap before it doubles. Vendors
+/// cap some queries by the logs they return, so a range rejected in a busy
+/// stretch of blocks may be served in a quieter one.
+pub const SERVED_TO_GROW_CAP: u32 = 16;
+
+fn case_insensitive(re: &str) -> Regex {
+    RegexBuilder::new(re)
+        .case_insensitive(true)
+        .build()
+        .expect("invalid log range regex")
+}
+
+/// Number of blocks a provider's error message reports as its cap on log
+/// queries, if any
+pub fn implied_range_cap(message: &str) -> Option<u32> {
+    if let Some(captures) = SUGGESTED_RANGE.captures(message) {
+        let bound = |i| u64::from_str_radix(&captures[i], 16).ok();
+        let (start, end) = (bound(1)?, bound(2)?);
+        return u32::try_from(end.checked_sub(start)? + 1).ok();
+    }
+
+    let captures = CAP_BEFORE_RANGE
+        .captures(message)
+        .or_else(|| CAP_AFTER_RANGE.captures(message))?;
+    let blocks: u32 = captures[1].replace(',', "").parse().ok()?;
+    let blocks = match captures.get(2) {
+        Some(_) => blocks.checked_mul(1000)?,
+        None => blocks,
+    };
+    if blocks == 0 {
+        return None;
+    }
+    Some(blocks)
+}
+
+/// Block range cap a provider was found to put on log queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LogRangeCap {
+    /// Most blocks a log query may span, its bounds included
+    pub blocks: u32,
+    /// Blocks of the rejected query the cap was learned from. `None` if the
+    /// cap was restored from a previous run
+    pub rejected_blocks: Option<u32>,
+    /// Queries served at the cap since it was learned or last grew
+    pub served: u32,
+}
+
+/// Block range caps of providers, learned from log queries they rejected as
+/// too large, and grown back as queries at the cap are served. Providers
+/// without an entry served every range so far.
+#[derive(Debug, Default)]
+pub struct LogRangeCaps {
+    caps: RwLock<BTreeMap<String, LogRangeCap>>,
+}
+
+impl LogRangeCaps {
+    /// Cap learned for `provider`, if it rejected a range
+    pub fn get(&self, provider: &str) -> Option<LogRangeCap> {
+        self.caps.read().expect("poisoned").get(provider).copied()
+    }
+
+    /// Record that `provider` rejected a query over `blocks` blocks with
+    /// `message`. The cap is the one the message reports, or else half the
+    /// rejected range. Keeps the smallest cap seen and returns it.
+    pub fn record_rejected(&self, provider: &str, blocks: u32, message: &str) -> u32 {
+        let implied = implied_range_cap(message).filter(|cap| *cap < blocks);
+        let cap = implied.unwrap_or(blocks / 2).max(1);
+        let mut caps = self.caps.write().expect("poisoned");
+        if let Some(known) = caps.get(provider) {
+            if known.blocks <= cap {
+                return known.blocks;
+            }
+        }
+
+        warn!(
+            provider,
+            cap,
+            rejected_blocks = blocks,
+            reported = implied.is_some(),
+            "Provider for {} caps log queries at {} blocks. Requesting smaller ranges.",
+            provider,
+            cap,
+        );
+        caps.insert(
+            provider.to_owned(),
+            LogRangeCap {
+                blocks: cap,
+                rejected_blocks: Some(blocks),
+                served: 0,
+            },
+        );
+        cap
+    }
+
+    /// Record that `provider` served a query over `blocks` blocks. Once
+    /// `SERVED_TO_GROW_CAP` queries at the cap are served in a row, the cap
+    /// doubles. Returns the grown cap, if it grew.
+    pub fn record_served(&self, provider: &str, blocks: u32) -> Option<u32> {
+        let mut caps = self.caps.write().expect("poisoned");
+        let cap = caps.get_mut(provider)?;
+        if blocks < cap.blocks {
+            return None;
+        }
+        cap.served += 1;
+        if cap.served < SERVED_TO_GROW_CAP {
+            return None;
+        }
+
+        cap.blocks = cap.blocks.saturating_mul(2);
+        cap.served = 0;
+        info!(
+            provider,
+            cap = cap.blocks,
+            "Provider for {} served {} log queries at its cap. Growing it to {} blocks.",
+            provider,
+            SERVED_TO_GROW_CAP,
+            cap.blocks,
+        );
+        Some(cap.blocks)
+    }
+
+    /// Restore a cap learned for `provider` by a previous run. A smaller
+    /// cap already known is kept.
+    pub fn restore(&self, provider: &str, blocks: u32) {
+        let blocks = blocks.max(1);
+        let mut caps = self.caps.write().expect("poisoned");
+        if caps
+            .get(provider)
+            .map_or(false, |known| known.blocks <= blocks)
+        {
+            return;
+        }
+        info!(
+            provider,
+            cap = blocks,
+            "Restored log query cap of {} blocks for {}",
+            blocks,
+            provider,
+        );
+        caps.insert(
+            provider.to_owned(),
+            LogRangeCap {
+                blocks,
+                rejected_blocks: None,
+                served: 0,
+            },
+        );
+    }
+
+    /// Page size of log queries to `provider`: `page_size` blocks past the
+    /// start of a page, within its cap
+    pub fn page_size(&self, provider: &str, page_size: u32) -> u32 {
+        self.get(provider)
+            .map_or(page_size, |cap| min(page_size, cap.blocks - 1))
+    }
+
+    /// End of a query to `provider` from `start` to at most `end`, within
+    /// its cap
+    pub fn clamp_end(&self, provider: &str, start: u32, end: u32) -> u32 {
+        min(
+            end,
+            start.saturating_add(self.page_size(provider, end - start)),
+        )
+    }
+
+    /// Learned caps by provider, as reported in `/status`
+    pub fn status(&self) -> BTreeMap<String, serde_json::Value> {
+        self.caps
+            .read()
+            .expect("poisoned")
+            .iter()
+            .map(|(provider, cap)| {
+                (
+                    provider.clone(),
+                    serde_json::to_value(cap).expect("!serialize"),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_reads_caps_from_vendor_messages() {
+        let cases = [
+            ("Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range and no limit on the response size, or you can request any block range with a cap of 10K logs in the response.", Some(2000)),
+            ("eth_getLogs is limited to a 10,000 range", Some(10000)),
+            ("exceed maximum block range: 5000", Some(5000)),
+            ("query returned more than 10000 results. Try with this block range [0x10D5E28, 0x10D6A07].", Some(3040)),
+            ("block range is too wide", None),
+            ("Block range limit exceeded.", None),
+        ];
+        for (message, cap) in cases {
+            assert_eq!(implied_range_cap(message), cap, "{}", message);
+        }
+    }
+
+    #[test]
+    fn it_keeps_the_smallest_cap() {
+        let caps = LogRangeCaps::default();
+        assert_eq!(caps.page_size("ethereum", 10000), 10000);
+
+        // the reported cap is taken
+        assert_eq!(
+            caps.record_rejected("ethereum", 10001, "up to a 2K block range"),
+            2000
+        );
+        assert_eq!(caps.page_size("ethereum", 10000), 1999);
+        assert_eq!(caps.page_size("ethereum", 500), 500);
+        assert_eq!(caps.clamp_end("ethereum", 100, 5000), 2099);
+        assert_eq!(caps.clamp_end("ethereum", 100, 600), 600);
+
+        // without one, the rejected range is halved
+        assert_eq!(
+            caps.record_rejected("moonbeam", 1000, "block range is too wide"),
+            500
+        );
+        assert_eq!(
+            caps.record_rejected("moonbeam", 500, "block range is too wide"),
+            250
+        );
+        // a larger cap leaves the known one
+        assert_eq!(
+            caps.record_rejected("ethereum", 20000, "exceed maximum block range: 5000"),
+            2000
+        );
+        // a reported cap the rejected range was within is not trusted
+        assert_eq!(
+            caps.record_rejected("ethereum", 1500, "limited to a 10,000 range"),
+            750
+        );
+        caps.restore("ethereum", 5000);
+        assert_eq!(caps.get("ethereum").unwrap().blocks, 750);
+        caps.restore("polygon", 3500);
+        assert_eq!(
+            caps.get("polygon"),
+            Some(LogRangeCap {
+                blocks: 3500,
+                rejected_blocks: None,
+                served: 0,
+            })
+        );
+
+        let status = caps.status();
+        assert_eq!(status["moonbeam"]["blocks"], 250);
+        assert_eq!(status["moonbeam"]["rejected_blocks"], 500);
+        assert!(!status.contains_key("avalanche"));
+    }
+
+    #[test]
+    fn it_grows_caps_back_after_served_queries() {
+        let caps = LogRangeCaps::default();
+        assert_eq!(caps.record_served("ethereum", 10000), None);
+        assert_eq!(
+            caps.record_rejected(
+                "ethereum",
+                10001,
+                "more than 10000 results. Try with this block range [0x0, 0x3e7]."
+            ),
+            1000
+        );
+
+        // smaller queries say nothing about the cap
+        for _ in 0..SERVED_TO_GROW_CAP {
+            assert_eq!(caps.record_served("ethereum", 500), None);
+        }
+        for _ in 1..SERVED_TO_GROW_CAP {
+            assert_eq!(caps.record_served("ethereum", 1000), None);
+        }
+        assert_eq!(caps.record_served("ethereum", 1000), Some(2000));
+        assert_eq!(caps.page_size("ethereum", 10000), 1999);
+
+        // a rejection learns the cap again
+        assert_eq!(
+            caps.record_rejected("ethereum", 2000, "block range is too wide"),
+            1000
+        );
+        assert_eq!(caps.get("ethereum").unwrap().served, 0);
+    }
+}
@@ -36,7 +36,7 @@ macro_rules! report_tx {
     }};
 
     // Legacy way of sending transactions.
-    (@legacy $tx:expr, $provider:expr,) => {{
+    (@legacy $tx:expr, $provider:expr, $receipts:expr) => {{
         log_tx_details!($tx);
 
         let dispatched = $provider
@@ -45,9 +45,7 @@ macro_rules! report_tx {
             .map_err(|e| crate::EthereumError::MiddlewareError(e.into()))?;
 
         let tx_hash: ethers::core::types::H256 = *dispatched;
-        let result = dispatched
-            .await?
-            .ok_or_else(|| crate::EthereumError::DroppedError(tx_hash))?;
+        let result = $receipts.await_receipt($provider.clone(), tx_hash).await?;
 
         tracing::info!(
             tx_hash = ?tx_hash,
@@ -61,8 +59,8 @@ macro_rules! report_tx {
 #[allow(unused_macros)]
 macro_rules! log_tx_details {
     ($tx:expr) => {
-        // "0x..."
-        let data = format!("0x{}", hex::encode(&$tx.data().map(|b| b.to_vec()).unwrap_or_default()));
+        // "0x12345678..9abcdef0 (n bytes)", never the full payload
+        let data = nomad_core::utils::abbreviated_hex(&$tx.data().map(|b| b.to_vec()).unwrap_or_default());
 
         let to = $tx.to().cloned().unwrap_or_else(|| ethers::types::NameOrAddress::Address(Default::default()));
 
@@ -130,34 +128,41 @@ macro_rules! ws_provider {
 /// Create ethers::SignerMiddleware from websockets connection
 #[macro_export]
 macro_rules! wrap_with_signer {
-    ($provider:expr, $signer:expr) => {{
+    ($provider:expr, $signer:expr, $fees:expr) => {{
         // First set the chain ID locally
         let provider_chain_id = $provider.get_chainid().await?;
         let signer = ethers::signers::Signer::with_chain_id($signer, provider_chain_id.as_u64());
 
-        // Manage the nonce locally
         let address = ethers::prelude::Signer::address(&signer);
-        let provider =
-            ethers::middleware::nonce_manager::NonceManagerMiddleware::new($provider, address);
 
         // Kludge. Increase the gas by multiplication of every estimated gas by
-        // 2, except the gas for chain id 1 (Ethereum Mainnet)
+        // 2, except the gas for chain id 1 (Ethereum Mainnet). Unless the
+        // chain's fees are EIP-1559
         let provider = $crate::gas::GasAdjusterMiddleware::with_default_policy(
-            provider,
+            $provider,
             provider_chain_id.as_u64(),
-        );
+        )
+        .with_fees($fees);
 
         // Manage signing locally
-        Arc::new(ethers::middleware::SignerMiddleware::new(provider, signer))
+        let provider = ethers::middleware::SignerMiddleware::new(provider, signer);
+
+        // Manage the nonce locally, shared by every contract the signer
+        // submits to on the chain
+        Arc::new($crate::NonceManagedSubmitter::new(
+            provider,
+            provider_chain_id.as_u64(),
+            address,
+        ))
     }};
 }
 
 /// Create TxSubmitter::Local
 #[macro_export]
 macro_rules! tx_submitter_local {
-    ($base_provider:expr, $signer_conf:ident) => {{
+    ($base_provider:expr, $signer_conf:ident, $fees:expr) => {{
         let signer = signer::EthereumSigners::try_from_signer_conf(&$signer_conf).await?;
-        let signing_provider: Arc<_> = wrap_with_signer!($base_provider.clone(), signer);
+        let signing_provider: Arc<_> = wrap_with_signer!($base_provider.clone(), signer, $fees);
         TxSubmitter::new(signing_provider.into())
     }};
 }
@@ -165,11 +170,11 @@ macro_rules! tx_submitter_local {
 /// Create TxSubmitter::Gelato
 #[macro_export]
 macro_rules! tx_submitter_gelato {
-    ($base_provider:expr, $gelato_conf:ident) => {{
+    ($base_provider:expr, $gelato_conf:ident, $fees:expr) => {{
         let signer = signer::EthereumSigners::try_from_signer_conf(&$gelato_conf.sponsor).await?;
         let sponsor = signer.clone();
         let chain_id = $base_provider.get_chainid().await?.as_u64();
-        let signing_provider: Arc<_> = wrap_with_signer!($base_provider.clone(), signer); // kludge: only using signing provider for type consistency with TxSubmitter::Local
+        let signing_provider: Arc<_> = wrap_with_signer!($base_provider.clone(), signer, $fees); // kludge: only using signing provider for type consistency with TxSubmitter::Local
 
         let client = SingleChainGelatoClient::with_default_url(
             signing_provider,
@@ -191,39 +196,43 @@ macro_rules! boxed_contract {
             Box::new($crate::$abi::new($submitter, $base_provider, $($tail)*))
         }
     }};
-    (@submitter $base_provider:expr, $submitter_conf:ident, $($tail:tt)*) => {{
+    (@submitter $base_provider:expr, $receipts:ident, $fees:ident, $submitter_conf:ident, $($tail:tt)*) => {{
         if let Some(conf) = $submitter_conf {
             let submitter = match conf {
                 nomad_xyz_configuration::ethereum::TxSubmitterConf::Local(signer_conf) => {
-                    tx_submitter_local!($base_provider, signer_conf)
+                    tx_submitter_local!($base_provider, signer_conf, $fees)
                 }
                 nomad_xyz_configuration::ethereum::TxSubmitterConf::Gelato(gelato_conf) => {
-                    tx_submitter_gelato!($base_provider, gelato_conf)
+                    tx_submitter_gelato!($base_provider, gelato_conf, $fees)
                 }
-            };
+            }
+            .with_receipts($receipts)
+            .with_gas($fees);
 
             boxed_contract!(@timelag $base_provider, submitter, $($tail)*)
         } else {
             panic!("Not supporting contracts with tx submitter"); // TODO: allow readonly contracts?
         }
     }};
-    (@ws $url:expr, $($tail:tt)*) => {{
+    (@ws $url:expr, $receipts:ident, $($tail:tt)*) => {{
         let provider = ws_provider!($url);
-        boxed_contract!(@submitter provider, $($tail)*)
+        let receipts = $receipts.with_heads(provider.clone());
+        boxed_contract!(@submitter provider, receipts, $($tail)*)
     }};
-    (@http $url:expr, $($tail:tt)*) => {{
+    (@http $url:expr, $receipts:ident, $($tail:tt)*) => {{
         let provider = http_provider!($url);
-        boxed_contract!(@submitter provider, $($tail)*)
+        boxed_contract!(@submitter provider, $receipts, $($tail)*)
     }};
     ($name:ident, $abi:ident, $trait:path, $($n:ident:$t:ty),*)  => {
         #[doc = "Cast a contract locator to a live contract handle"]
-        pub async fn $name(conn: nomad_xyz_configuration::Connection, locator: &ContractLocator, submitter_conf: Option<nomad_xyz_configuration::ethereum::TxSubmitterConf>, timelag: Option<u8>, $($n:$t),*) -> color_eyre::Result<Box<dyn $trait>> {
+        pub async fn $name(conn: nomad_xyz_configuration::Connection, locator: &ContractLocator, submitter_conf: Option<nomad_xyz_configuration::ethereum::TxSubmitterConf>, timelag: Option<u8>, receipt_settings: $crate::ReceiptSettings, fees: nomad_xyz_configuration::GasConfig, $($n:$t),*) -> color_eyre::Result<Box<dyn $trait>> {
+            let receipts = $crate::ReceiptStrategy::new(locator.name.as_str(), receipt_settings);
             let b: Box<dyn $trait> = match conn {
                 nomad_xyz_configuration::Connection::Http (url) => {
-                    boxed_contract!(@http url, submitter_conf, $abi, timelag, locator, $($n),*)
+                    boxed_contract!(@http url, receipts, fees, submitter_conf, $abi, timelag, locator, $($n),*)
                 }
                 nomad_xyz_configuration::Connection::Ws (url) => {
-                    boxed_contract!(@ws url, submitter_conf, $abi, timelag, locator, $($n),*)
+                    boxed_contract!(@ws url, receipts, fees, submitter_conf, $abi, timelag, locator, $($n),*)
                 }
             };
             Ok(b)
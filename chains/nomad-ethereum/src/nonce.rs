@@ -0,0 +1,398 @@
+use ethers::providers::{FromErr, Middleware, PendingTransaction};
+use ethers::types::{transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, U256};
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex as StdMutex},
+};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Nonces of each signer, by chain id and address. Shared by every contract
+/// the signer submits to on the chain, so their submissions do not race on
+/// the nonce.
+static NONCES: Lazy<StdMutex<HashMap<(u64, Address), Arc<Mutex<SignerNonces>>>>> =
+    Lazy::new(Default::default);
+
+/// Nonce state of a signer on a chain
+#[derive(Debug, Default)]
+struct SignerNonces {
+    /// Next nonce to send with. `None` until read from the chain
+    next: Option<U256>,
+    /// Nonces given to filled transactions not sent yet, with the number of
+    /// transactions given each
+    filled: HashMap<U256, usize>,
+}
+
+impl SignerNonces {
+    /// Whether `nonce` was given to a filled transaction, which is then
+    /// considered sent
+    fn take_filled(&mut self, nonce: U256) -> bool {
+        match self.filled.get_mut(&nonce) {
+            Some(count) => {
+                *count -= 1;
+                if *count == 0 {
+                    self.filled.remove(&nonce);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Errors of a transaction sent with a nonce the chain does not expect next
+const NONCE_ERRORS: &[&str] = &[
+    "nonce too low",
+    "nonce too high",
+    "nonce has already been used",
+    "invalid nonce",
+    "replacement transaction underpriced",
+    "already known",
+];
+
+/// Whether `message` rejects a transaction for its nonce
+fn is_nonce_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    NONCE_ERRORS.iter().any(|error| message.contains(error))
+}
+
+/// Middleware assigning the nonces of a signer's transactions. Nonces are
+/// cached per signer and chain, and handed out one send at a time, so
+/// concurrent submissions through any of the signer's contracts get
+/// increasing nonces. Filled transactions get the next nonce provisionally,
+/// so the inner signer does not read one from the latest block, and are
+/// given the nonce due when sent. The cache is resynced from the chain's
+/// pending transaction count when a send is rejected for its nonce, as when
+/// another process submitted with the same signer, and after any other
+/// failed send, so the unused nonce leaves no gap.
+pub struct NonceManagedSubmitter<M> {
+    inner: M,
+    address: Address,
+    nonces: Arc<Mutex<SignerNonces>>,
+}
+
+impl<M> fmt::Debug for NonceManagedSubmitter<M>
+where
+    M: Middleware,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NonceManagedSubmitter")
+            .field("inner", &self.inner)
+            .field("address", &self.address)
+            .finish()
+    }
+}
+
+impl<M> NonceManagedSubmitter<M>
+where
+    M: Middleware,
+{
+    /// Manage the nonces of `address` on the chain with `chain_id`, shared
+    /// with every other submitter of the signer on the chain
+    pub fn new(inner: M, chain_id: u64, address: Address) -> Self {
+        let nonces = NONCES
+            .lock()
+            .expect("poisoned")
+            .entry((chain_id, address))
+            .or_default()
+            .clone();
+        Self {
+            inner,
+            address,
+            nonces,
+        }
+    }
+
+    /// Nonce after the signer's pending transactions
+    async fn pending_nonce(&self) -> Result<U256, NonceManagedSubmitterError<M>> {
+        self.inner
+            .get_transaction_count(self.address, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(FromErr::from)
+    }
+
+    /// Next nonce of the signer, read from the chain if not cached
+    async fn next_nonce(
+        &self,
+        nonces: &mut SignerNonces,
+    ) -> Result<U256, NonceManagedSubmitterError<M>> {
+        match nonces.next {
+            Some(nonce) => Ok(nonce),
+            None => {
+                let nonce = self.pending_nonce().await?;
+                nonces.next = Some(nonce);
+                Ok(nonce)
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+/// Thrown when an error happens at the nonce managing middleware
+pub enum NonceManagedSubmitterError<M: Middleware> {
+    /// Thrown when the internal middleware errors
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+}
+
+/// Convert inner Middleware error into NonceManagedSubmitterError
+impl<M: Middleware> FromErr<M::Error> for NonceManagedSubmitterError<M> {
+    fn from(src: M::Error) -> Self {
+        NonceManagedSubmitterError::MiddlewareError(src)
+    }
+}
+
+#[async_trait::async_trait]
+impl<M> Middleware for NonceManagedSubmitter<M>
+where
+    M: Middleware,
+{
+    type Error = NonceManagedSubmitterError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    async fn fill_transaction(
+        &self,
+        tx: &mut TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<(), Self::Error> {
+        if tx.nonce().is_none() {
+            let mut nonces = self.nonces.lock().await;
+            let nonce = self.next_nonce(&mut nonces).await?;
+            *nonces.filled.entry(nonce).or_default() += 1;
+            tx.set_nonce(nonce);
+        }
+        self.inner
+            .fill_transaction(tx, block)
+            .await
+            .map_err(FromErr::from)
+    }
+
+    async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        block: Option<BlockId>,
+    ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
+        let mut tx: TypedTransaction = tx.into();
+
+        // Held until the send returns, so nonces are handed out in order
+        let mut nonces = self.nonces.lock().await;
+        // Nonces set by the caller are kept
+        if let Some(nonce) = tx.nonce().copied() {
+            if !nonces.take_filled(nonce) {
+                drop(nonces);
+                return self
+                    .inner
+                    .send_transaction(tx, block)
+                    .await
+                    .map_err(FromErr::from);
+            }
+        }
+
+        let nonce = self.next_nonce(&mut nonces).await?;
+        tx.set_nonce(nonce);
+
+        match self.inner.send_transaction(tx.clone(), block).await {
+            Ok(pending) => {
+                nonces.next = Some(nonce + 1);
+                Ok(pending)
+            }
+            Err(e) if is_nonce_error(&e.to_string()) => {
+                let synced = self.pending_nonce().await?;
+                warn!(
+                    address = ?self.address,
+                    nonce = %nonce,
+                    synced = %synced,
+                    error = %e,
+                    "Transaction rejected for its nonce. Resynced the nonce from the chain, sending again."
+                );
+                tx.set_nonce(synced);
+                match self.inner.send_transaction(tx, block).await {
+                    Ok(pending) => {
+                        nonces.next = Some(synced + 1);
+                        Ok(pending)
+                    }
+                    Err(e) => {
+                        nonces.next = None;
+                        Err(FromErr::from(e))
+                    }
+                }
+            }
+            Err(e) => {
+                nonces.next = None;
+                Err(FromErr::from(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::TxSubmitter;
+    use ethers::{
+        providers::{MockProvider, Provider, ProviderError},
+        types::{TransactionRequest, H256},
+    };
+    use futures_util::future::join_all;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Middleware accepting transactions with the nonce it expects next,
+    /// recording the nonce of each, and rejecting others as too low. Fills
+    /// transactions like a signer, with the nonce of the latest block. Other
+    /// calls go to a mock provider without responses.
+    #[derive(Debug)]
+    struct Node {
+        inner: Provider<MockProvider>,
+        latest_nonce: u64,
+        next_nonce: AtomicU64,
+        sent: StdMutex<Vec<u64>>,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("{0}")]
+    struct NodeError(ProviderError);
+
+    impl FromErr<ProviderError> for NodeError {
+        fn from(src: ProviderError) -> Self {
+            Self(src)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Middleware for Node {
+        type Error = NodeError;
+        type Provider = MockProvider;
+        type Inner = Provider<MockProvider>;
+
+        fn inner(&self) -> &Self::Inner {
+            &self.inner
+        }
+
+        async fn get_transaction_count<T: Into<ethers::types::NameOrAddress> + Send + Sync>(
+            &self,
+            _: T,
+            _: Option<BlockId>,
+        ) -> Result<U256, Self::Error> {
+            Ok(self.next_nonce.load(Ordering::SeqCst).into())
+        }
+
+        async fn estimate_gas(&self, _: &TypedTransaction) -> Result<U256, Self::Error> {
+            Ok(21_000.into())
+        }
+
+        async fn fill_transaction(
+            &self,
+            tx: &mut TypedTransaction,
+            _: Option<BlockId>,
+        ) -> Result<(), Self::Error> {
+            if tx.nonce().is_none() {
+                tx.set_nonce(self.latest_nonce);
+            }
+            if tx.gas().is_none() {
+                tx.set_gas(21_000);
+            }
+            Ok(())
+        }
+
+        async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+            &self,
+            tx: T,
+            _: Option<BlockId>,
+        ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
+            let tx: TypedTransaction = tx.into();
+            let nonce = tx.nonce().expect("!nonce").as_u64();
+            // Let other sends run while this one is in flight
+            tokio::task::yield_now().await;
+            if nonce < self.next_nonce.load(Ordering::SeqCst) {
+                return Err(NodeError(ProviderError::CustomError(
+                    "nonce too low".to_owned(),
+                )));
+            }
+            self.next_nonce.store(nonce + 1, Ordering::SeqCst);
+            self.sent.lock().unwrap().push(nonce);
+            Ok(PendingTransaction::new(
+                H256::from_low_u64_be(nonce),
+                &self.inner,
+            ))
+        }
+    }
+
+    fn node(next_nonce: u64) -> Node {
+        Node {
+            inner: Provider::mocked().0,
+            latest_nonce: next_nonce,
+            next_nonce: next_nonce.into(),
+            sent: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_assigns_increasing_nonces_to_concurrent_sends() {
+        let submitter = NonceManagedSubmitter::new(node(7), 1001, Address::repeat_byte(1));
+
+        let sends = (0..20).map(|_| {
+            submitter.send_transaction(TransactionRequest::new().to(Address::repeat_byte(2)), None)
+        });
+        for sent in join_all(sends).await {
+            sent.unwrap();
+        }
+
+        let sent = submitter.inner().sent.lock().unwrap().clone();
+        assert_eq!(sent, (7..27).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn it_resyncs_nonces_used_elsewhere() {
+        let address = Address::repeat_byte(1);
+        let submitter = NonceManagedSubmitter::new(node(7), 1002, address);
+        let tx = || TransactionRequest::new().to(Address::repeat_byte(2));
+
+        submitter.send_transaction(tx(), None).await.unwrap();
+        // Another process sends with the signer
+        submitter.inner().next_nonce.store(12, Ordering::SeqCst);
+        submitter.send_transaction(tx(), None).await.unwrap();
+        submitter.send_transaction(tx(), None).await.unwrap();
+
+        assert_eq!(*submitter.inner().sent.lock().unwrap(), vec![7, 12, 13]);
+
+        // Submitters of the signer on the chain share its nonces
+        let other = NonceManagedSubmitter::new(node(0), 1002, address);
+        other.send_transaction(tx(), None).await.unwrap();
+        assert_eq!(*other.inner().sent.lock().unwrap(), vec![14]);
+    }
+
+    #[tokio::test]
+    async fn it_manages_nonces_of_filled_submissions() {
+        let client = Arc::new(NonceManagedSubmitter::new(
+            node(3),
+            1003,
+            Address::repeat_byte(1),
+        ));
+        let submitter = TxSubmitter::new(client.clone().into());
+        let gas = Default::default();
+
+        // Filled with estimated gas limits before sending. Receipts are
+        // unavailable, so each submission errors once sent.
+        let submissions = (0..10).map(|_| {
+            submitter.submit_with_gas(
+                1000,
+                Address::repeat_byte(2),
+                TransactionRequest::new().to(Address::repeat_byte(2)),
+                &gas,
+            )
+        });
+        join_all(submissions).await;
+
+        let sent = client.inner().sent.lock().unwrap().clone();
+        assert_eq!(sent, (3..13).collect::<Vec<_>>());
+    }
+}
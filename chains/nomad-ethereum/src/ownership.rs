@@ -0,0 +1,86 @@
+use ethers::prelude::*;
+use nomad_ethereum_bindings::home::Home as OwnableInternal;
+use std::sync::Arc;
+
+use crate::EthereumError;
+
+/// Owner lookups and ownership changes on a Nomad core contract. Home,
+/// Replica and XAppConnectionManager share the `Ownable` interface and
+/// `localDomain`, so any of them may be used.
+#[derive(Debug)]
+pub struct OwnableContract<M>
+where
+    M: Middleware,
+{
+    contract: OwnableInternal<M>,
+}
+
+impl<M> OwnableContract<M>
+where
+    M: Middleware + 'static,
+{
+    /// Instantiate for the contract at `address`
+    pub fn new(address: Address, client: Arc<M>) -> Self {
+        Self {
+            contract: OwnableInternal::new(address, client),
+        }
+    }
+
+    /// Address of the contract
+    pub fn address(&self) -> Address {
+        self.contract.address()
+    }
+
+    /// Current owner
+    pub async fn owner(&self) -> Result<Address, EthereumError> {
+        Ok(self.contract.owner().call().await?)
+    }
+
+    /// Domain of the chain the contract is deployed on
+    pub async fn local_domain(&self) -> Result<u32, EthereumError> {
+        Ok(self.contract.local_domain().call().await?)
+    }
+
+    /// Transfer ownership to `new_owner`. Returns the transaction hash once
+    /// mined.
+    pub async fn transfer_ownership(&self, new_owner: Address) -> Result<H256, EthereumError> {
+        self.send(self.contract.transfer_ownership(new_owner)).await
+    }
+
+    /// Renounce ownership. Irreversible. Returns the transaction hash once
+    /// mined.
+    pub async fn renounce_ownership(&self) -> Result<H256, EthereumError> {
+        self.send(self.contract.renounce_ownership()).await
+    }
+
+    /// Calldata of a `transferOwnership(new_owner)` call, for submitting
+    /// through another account
+    pub fn transfer_ownership_calldata(&self, new_owner: Address) -> Bytes {
+        self.contract
+            .transfer_ownership(new_owner)
+            .calldata()
+            .expect("!calldata")
+    }
+
+    /// Calldata of a `renounceOwnership()` call, for submitting through
+    /// another account
+    pub fn renounce_ownership_calldata(&self) -> Bytes {
+        self.contract
+            .renounce_ownership()
+            .calldata()
+            .expect("!calldata")
+    }
+
+    async fn send(
+        &self,
+        call: ethers::contract::builders::ContractCall<M, ()>,
+    ) -> Result<H256, EthereumError> {
+        let pending = call.send().await?;
+        let tx_hash = *pending;
+        match pending.await? {
+            Some(receipt) if receipt.status == Some(1.into()) => Ok(tx_hash),
+            Some(_) => Err(EthereumError::TxNotExecuted(tx_hash)),
+            None => Err(EthereumError::DroppedError(tx_hash)),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use ethers::{core::types::U64, providers::Middleware};
+use std::future::Future;
+use tracing::debug;
+
+use crate::{is_block_unavailable, is_history_unavailable, EthereumError, HISTORY_DEPTHS};
+
+/// Attempts of a pinned read whose block a lagging node has not seen. Each
+/// attempt pins a freshly fetched block.
+const PINNED_READ_ATTEMPTS: usize = 5;
+
+/// Latest block number of `client`, used to read several values at the same
+/// block
+pub(crate) async fn pinned_block<M: Middleware>(client: &M) -> Result<U64, EthereumError> {
+    client
+        .get_block_number()
+        .await
+        .map_err(|e| EthereumError::MiddlewareError(e.into()))
+}
+
+/// Head of the read provider, ignoring any timelag
+pub(crate) async fn head_block<M: Middleware>(client: &M) -> Result<U64, EthereumError> {
+    Ok(client.provider().get_block_number().await?)
+}
+
+/// Run `read` at the pinned block of `client`, moved within the history of
+/// a provider detected to prune state. If the provider pruned the state of
+/// that block, record the miss and run `read` again at the earliest block
+/// within its history. If a node behind a load balancer
+/// has not seen the block yet, run `read` again at a fresh pinned block, so
+/// the values read never mix blocks.
+pub(crate) async fn read_pinned<M, T, F, Fut>(
+    client: &M,
+    name: &str,
+    read: F,
+) -> Result<T, EthereumError>
+where
+    M: Middleware,
+    F: Fn(U64) -> Fut,
+    Fut: Future<Output = Result<T, EthereumError>>,
+{
+    let mut attempt = 1;
+    loop {
+        let block = pinned_block(client).await?.as_u64();
+        let moved = HISTORY_DEPTHS.within(name, block);
+        match read(moved.into()).await {
+            Ok(value) => {
+                if moved == block {
+                    HISTORY_DEPTHS.record_served(name);
+                }
+                return Ok(value);
+            }
+            Err(e) if is_history_unavailable(&e) => {
+                let head = head_block(client).await?.as_u64();
+                let depth =
+                    HISTORY_DEPTHS.record_missing(name, moved, head, head.saturating_sub(block));
+                return read(head.saturating_sub(depth).into()).await;
+            }
+            Err(e) if is_block_unavailable(&e) && attempt < PINNED_READ_ATTEMPTS => {
+                debug!(
+                    provider = name,
+                    block = moved,
+                    attempt,
+                    error = %e,
+                    "Pinned block unavailable on a node. Reading again at a fresh block."
+                );
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
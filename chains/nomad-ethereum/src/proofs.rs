@@ -0,0 +1,115 @@
+use async_trait::async_trait;
+use ethers::core::types::H256;
+use nomad_core::accumulator::{NomadTree, ProofLengthError, ProvingError, TREE_DEPTH};
+use std::error::Error as StdError;
+
+/// Leaves a home's tree holds. The last index of a depth `TREE_DEPTH` tree
+/// is never filled.
+pub const MAX_LEAVES: u64 = (1 << TREE_DEPTH) - 1;
+
+/// Merkle branch of a message as the replica contract takes it
+pub type SolidityProof = [[u8; 32]; TREE_DEPTH];
+
+/// Errors turning a fetched branch into a `SolidityProof`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ProofError {
+    /// The leaf index is past the last leaf of the tree
+    #[error(
+        "Leaf index {0} is beyond the {} leaves of a depth {} tree",
+        MAX_LEAVES,
+        TREE_DEPTH
+    )]
+    IndexOutOfRange(u32),
+    /// The branch does not have one node per level of the tree
+    #[error("{0}")]
+    Length(#[from] ProofLengthError),
+}
+
+/// Source of merkle branches of messages by leaf index, such as a prover
+/// synced with the home
+#[async_trait]
+pub trait ProofProvider: Send + Sync {
+    /// Error fetching a branch
+    type Error: StdError + Send + Sync + 'static;
+
+    /// Branch of the leaf at `leaf_index`, from the leaf up
+    async fn branch(&self, leaf_index: u32) -> Result<Vec<H256>, Self::Error>;
+}
+
+#[async_trait]
+impl ProofProvider for NomadTree {
+    type Error = ProvingError;
+
+    async fn branch(&self, leaf_index: u32) -> Result<Vec<H256>, Self::Error> {
+        Ok(self.prove(leaf_index as usize)?.path.to_vec())
+    }
+}
+
+/// Convert the branch of the leaf at `leaf_index` into the fixed array the
+/// replica contract takes. Fails unless the index is within the tree and
+/// the branch has exactly `TREE_DEPTH` nodes.
+pub fn solidity_proof(leaf_index: u32, branch: &[H256]) -> Result<SolidityProof, ProofError> {
+    if u64::from(leaf_index) >= MAX_LEAVES {
+        return Err(ProofError::IndexOutOfRange(leaf_index));
+    }
+
+    let branch: [H256; TREE_DEPTH] = branch.try_into().map_err(|_| ProofLengthError {
+        expected: TREE_DEPTH,
+        got: branch.len(),
+    })?;
+    Ok(branch.map(H256::to_fixed_bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn branch(len: usize) -> Vec<H256> {
+        (0..len).map(|i| H256::repeat_byte(i as u8 + 1)).collect()
+    }
+
+    #[test]
+    fn it_converts_full_branches() {
+        let proof = solidity_proof(7, &branch(TREE_DEPTH)).unwrap();
+        assert_eq!(proof[0], [1u8; 32]);
+        assert_eq!(proof[TREE_DEPTH - 1], [TREE_DEPTH as u8; 32]);
+
+        // The last leaf of the tree
+        assert!(solidity_proof(u32::MAX - 1, &branch(TREE_DEPTH)).is_ok());
+    }
+
+    #[test]
+    fn it_rejects_indexes_past_the_tree() {
+        // One past the last leaf of the tree
+        assert_eq!(
+            solidity_proof(u32::MAX, &branch(TREE_DEPTH)),
+            Err(ProofError::IndexOutOfRange(u32::MAX))
+        );
+    }
+
+    #[test]
+    fn it_rejects_branches_of_the_wrong_length() {
+        for len in [0, TREE_DEPTH - 1, TREE_DEPTH + 1] {
+            assert_eq!(
+                solidity_proof(0, &branch(len)),
+                Err(ProofError::Length(ProofLengthError {
+                    expected: TREE_DEPTH,
+                    got: len
+                }))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn it_fetches_branches_from_a_tree() {
+        let leaves = branch(5);
+        let tree: NomadTree = leaves.as_slice().into();
+
+        let fetched = tree.branch(3).await.unwrap();
+        let proof = solidity_proof(3, &fetched).unwrap();
+        assert_eq!(proof, tree.prove(3).unwrap().path.map(H256::to_fixed_bytes));
+
+        // One past the last leaf has no proof
+        assert!(tree.branch(5).await.is_err());
+    }
+}
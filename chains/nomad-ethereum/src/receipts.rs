@@ -0,0 +1,255 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ethers::prelude::*;
+use futures_util::StreamExt;
+use once_cell::sync::Lazy;
+use prometheus::{HistogramOpts, HistogramVec};
+use tokio::time::{sleep, timeout};
+use tracing::{error, info, warn};
+
+use crate::EthereumError;
+
+/// Duration from dispatch to first receipt (`receipt`) and from dispatch to
+/// the configured number of confirmations (`final`), per chain. Agents
+/// register it with their metrics registry.
+pub static RECEIPT_LATENCIES: Lazy<HistogramVec> = Lazy::new(|| {
+    HistogramVec::new(
+        HistogramOpts::new(
+            "receipt_latency_seconds",
+            "Duration from transaction dispatch to receipt and to final confirmation",
+        )
+        .namespace("nomad")
+        .buckets(vec![
+            0.5, 1.0, 2.0, 4.0, 8.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0,
+        ]),
+        &["chain", "stage"],
+    )
+    .expect("invalid receipt latency histogram")
+});
+
+/// Poll interval used when a chain has no block time estimate. Matches the
+/// ethers `PendingTransaction` default.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(7);
+/// Lower bound on the poll interval, for chains with sub-second blocks
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Poll interval for a chain with the given block time in seconds: a quarter
+/// of the block time, but no less than `MIN_POLL_INTERVAL`.
+pub fn poll_interval(block_time: u64) -> Duration {
+    if block_time == 0 {
+        return DEFAULT_POLL_INTERVAL;
+    }
+    (Duration::from_secs(block_time) / 4).max(MIN_POLL_INTERVAL)
+}
+
+/// Per-chain receipt settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReceiptSettings {
+    /// Estimated block time in seconds. 0 if unknown.
+    pub block_time: u64,
+    /// Confirmations before a transaction is considered final. 0 is
+    /// treated as 1.
+    pub confirmations: u64,
+}
+
+impl Default for ReceiptSettings {
+    fn default() -> Self {
+        Self {
+            block_time: 0,
+            confirmations: 1,
+        }
+    }
+}
+
+/// How a submitter awaits receipts on a single chain. Polls at an interval
+/// derived from block time, or checks on each new head when a websocket
+/// provider is available. Confirmations past the first are awaited in the
+/// background.
+#[derive(Debug, Clone)]
+pub struct ReceiptStrategy {
+    chain: String,
+    interval: Duration,
+    confirmations: u64,
+    heads: Option<Arc<Provider<Ws>>>,
+}
+
+impl Default for ReceiptStrategy {
+    fn default() -> Self {
+        Self::new("unknown", ReceiptSettings::default())
+    }
+}
+
+impl ReceiptStrategy {
+    /// Polling strategy for `chain`
+    pub fn new(chain: impl Into<String>, settings: ReceiptSettings) -> Self {
+        Self {
+            chain: chain.into(),
+            interval: poll_interval(settings.block_time),
+            confirmations: settings.confirmations.max(1),
+            heads: None,
+        }
+    }
+
+    /// Check for receipts on each new head from `ws`. Polling remains the
+    /// fallback if the subscription fails or stalls.
+    pub fn with_heads(mut self, ws: Arc<Provider<Ws>>) -> Self {
+        self.heads = Some(ws);
+        self
+    }
+
+    /// Wait for the first receipt of `tx_hash`. Remaining confirmations are
+    /// awaited in a spawned task, which reports if the transaction is
+    /// reorged out before becoming final.
+    pub async fn await_receipt<M>(
+        &self,
+        client: Arc<M>,
+        tx_hash: H256,
+    ) -> Result<TransactionReceipt, EthereumError>
+    where
+        M: Middleware + 'static,
+    {
+        let start = Instant::now();
+
+        let mut heads = match &self.heads {
+            Some(ws) => match ws.subscribe_blocks().await {
+                Ok(stream) => Some(stream),
+                Err(error) => {
+                    warn!(
+                        chain = self.chain.as_str(),
+                        error = %error,
+                        "Failed to subscribe to new heads, polling for receipt",
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let receipt = loop {
+            let receipt = client
+                .get_transaction_receipt(tx_hash)
+                .await
+                .map_err(|e| EthereumError::MiddlewareError(e.into()))?;
+            if let Some(receipt) = receipt.filter(|r| r.block_number.is_some()) {
+                break receipt;
+            }
+
+            let pending = client
+                .get_transaction(tx_hash)
+                .await
+                .map_err(|e| EthereumError::MiddlewareError(e.into()))?;
+            if pending.is_none() {
+                return Err(EthereumError::DroppedError(tx_hash));
+            }
+
+            match heads.as_mut() {
+                // With a subscription, fall back to polling once per block
+                Some(stream) => {
+                    if let Ok(None) = timeout(self.interval * 4, stream.next()).await {
+                        warn!(
+                            chain = self.chain.as_str(),
+                            "New heads subscription ended, polling for receipt",
+                        );
+                        heads = None;
+                    }
+                }
+                None => sleep(self.interval).await,
+            }
+        };
+
+        RECEIPT_LATENCIES
+            .with_label_values(&[&self.chain, "receipt"])
+            .observe(start.elapsed().as_secs_f64());
+
+        self.confirm_in_background(client, &receipt, start);
+
+        Ok(receipt)
+    }
+
+    fn confirm_in_background<M>(&self, client: Arc<M>, receipt: &TransactionReceipt, start: Instant)
+    where
+        M: Middleware + 'static,
+    {
+        if self.confirmations <= 1 {
+            RECEIPT_LATENCIES
+                .with_label_values(&[&self.chain, "final"])
+                .observe(start.elapsed().as_secs_f64());
+            return;
+        }
+
+        let chain = self.chain.clone();
+        let interval = self.interval;
+        let tx_hash = receipt.transaction_hash;
+        let block_hash = receipt.block_hash;
+        let target = receipt.block_number.expect("checked above") + (self.confirmations - 1);
+
+        tokio::spawn(async move {
+            loop {
+                match client.get_block_number().await {
+                    Ok(current) if current >= target => break,
+                    Ok(_) => {}
+                    Err(error) => warn!(
+                        chain = chain.as_str(),
+                        tx_hash = ?tx_hash,
+                        error = %error,
+                        "Failed to get block number while awaiting confirmations",
+                    ),
+                }
+                sleep(interval).await;
+            }
+
+            match client.get_transaction_receipt(tx_hash).await {
+                Ok(Some(final_receipt)) if final_receipt.block_hash == block_hash => {
+                    RECEIPT_LATENCIES
+                        .with_label_values(&[&chain, "final"])
+                        .observe(start.elapsed().as_secs_f64());
+                    info!(
+                        chain = chain.as_str(),
+                        tx_hash = ?tx_hash,
+                        "Transaction final",
+                    );
+                }
+                Ok(_) => error!(
+                    chain = chain.as_str(),
+                    tx_hash = ?tx_hash,
+                    block_hash = ?block_hash,
+                    "Transaction receipt changed before reaching confirmations. It may have been reorged out.",
+                ),
+                Err(error) => warn!(
+                    chain = chain.as_str(),
+                    tx_hash = ?tx_hash,
+                    error = %error,
+                    "Failed to recheck receipt after confirmations",
+                ),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn poll_interval_tracks_block_time() {
+        assert_eq!(poll_interval(0), DEFAULT_POLL_INTERVAL);
+        assert_eq!(poll_interval(1), MIN_POLL_INTERVAL);
+        assert_eq!(poll_interval(12), Duration::from_secs(3));
+        assert_eq!(poll_interval(60), Duration::from_secs(15));
+    }
+
+    #[test]
+    fn zero_confirmations_means_one() {
+        let strategy = ReceiptStrategy::new(
+            "ethereum",
+            ReceiptSettings {
+                block_time: 12,
+                confirmations: 0,
+            },
+        );
+        assert_eq!(strategy.confirmations, 1);
+    }
+}
@@ -0,0 +1,383 @@
+use crate::GelatoError;
+use ethers::{types::H256, utils::keccak256};
+use nomad_core::{
+    db::{DbError, TypedDB},
+    TxOutcome,
+};
+use once_cell::sync::OnceCell;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, RwLock},
+};
+use tracing::info;
+
+const TASKS: &str = "relay_task_";
+const EPOCHS: &str = "relay_epoch_";
+
+/// Store shared by relay submitters built after agents open their db
+static PERSISTED: OnceCell<Arc<dyn RelayTaskStore>> = OnceCell::new();
+
+/// Persist the relay tasks of submitters built from now on in `store`, so
+/// retries resume tasks across restarts. Agents call this once they open
+/// their db. Later calls are ignored.
+pub fn persist_relay_tasks(store: impl RelayTaskStore + 'static) {
+    let _ = PERSISTED.set(Arc::new(store));
+}
+
+/// Store for a new relay submitter: the persisted store if agents set one,
+/// otherwise a fresh in-memory store
+pub fn relay_task_store() -> Arc<dyn RelayTaskStore> {
+    PERSISTED
+        .get()
+        .cloned()
+        .unwrap_or_else(|| Arc::new(MemoryRelayTasks::default()))
+}
+
+/// Idempotency key of an attempt to relay calldata with hash
+/// `calldata_hash` on chain `chain_id`. Retries of an attempt share its
+/// epoch and so its key.
+pub fn idempotency_key(chain_id: u64, calldata_hash: H256, epoch: u32) -> H256 {
+    let mut buf = Vec::with_capacity(44);
+    buf.extend(chain_id.to_be_bytes());
+    buf.extend(calldata_hash.as_bytes());
+    buf.extend(epoch.to_be_bytes());
+    keccak256(buf).into()
+}
+
+fn epoch_key(chain_id: u64, calldata_hash: H256) -> H256 {
+    let mut buf = Vec::with_capacity(40);
+    buf.extend(chain_id.to_be_bytes());
+    buf.extend(calldata_hash.as_bytes());
+    keccak256(buf).into()
+}
+
+/// Mapping of idempotency keys to the relay tasks created for them, and the
+/// current attempt epoch of each calldata
+pub trait RelayTaskStore: std::fmt::Debug + Send + Sync {
+    /// Task created for `key`, if any
+    fn task(&self, key: H256) -> Result<Option<H256>, DbError>;
+    /// Record the task created for `key`
+    fn store_task(&self, key: H256, task_id: H256) -> Result<(), DbError>;
+    /// Forget the task of `key` once it reached a terminal state
+    fn expire_task(&self, key: H256) -> Result<(), DbError>;
+    /// Current attempt epoch of calldata with hash `calldata_hash`
+    fn epoch(&self, chain_id: u64, calldata_hash: H256) -> Result<u32, DbError>;
+    /// Set the attempt epoch of calldata with hash `calldata_hash`. Epoch 0
+    /// is the default and is not stored.
+    fn store_epoch(&self, chain_id: u64, calldata_hash: H256, epoch: u32) -> Result<(), DbError>;
+}
+
+impl RelayTaskStore for TypedDB {
+    fn task(&self, key: H256) -> Result<Option<H256>, DbError> {
+        self.retrieve_keyed_decodable(TASKS, &key)
+    }
+
+    fn store_task(&self, key: H256, task_id: H256) -> Result<(), DbError> {
+        self.store_keyed_encodable(TASKS, &key, &task_id)
+    }
+
+    fn expire_task(&self, key: H256) -> Result<(), DbError> {
+        self.delete_keyed(TASKS, &key)
+    }
+
+    fn epoch(&self, chain_id: u64, calldata_hash: H256) -> Result<u32, DbError> {
+        Ok(self
+            .retrieve_keyed_decodable(EPOCHS, &epoch_key(chain_id, calldata_hash))?
+            .unwrap_or_default())
+    }
+
+    fn store_epoch(&self, chain_id: u64, calldata_hash: H256, epoch: u32) -> Result<(), DbError> {
+        let key = epoch_key(chain_id, calldata_hash);
+        if epoch == 0 {
+            self.delete_keyed(EPOCHS, &key)
+        } else {
+            self.store_keyed_encodable(EPOCHS, &key, &epoch)
+        }
+    }
+}
+
+/// In-memory relay task store. Retries resume tasks, but restarts do not.
+#[derive(Debug, Default)]
+pub struct MemoryRelayTasks {
+    tasks: RwLock<HashMap<H256, H256>>,
+    epochs: RwLock<HashMap<H256, u32>>,
+}
+
+impl RelayTaskStore for MemoryRelayTasks {
+    fn task(&self, key: H256) -> Result<Option<H256>, DbError> {
+        Ok(self.tasks.read().expect("poisoned").get(&key).copied())
+    }
+
+    fn store_task(&self, key: H256, task_id: H256) -> Result<(), DbError> {
+        self.tasks.write().expect("poisoned").insert(key, task_id);
+        Ok(())
+    }
+
+    fn expire_task(&self, key: H256) -> Result<(), DbError> {
+        self.tasks.write().expect("poisoned").remove(&key);
+        Ok(())
+    }
+
+    fn epoch(&self, chain_id: u64, calldata_hash: H256) -> Result<u32, DbError> {
+        Ok(self
+            .epochs
+            .read()
+            .expect("poisoned")
+            .get(&epoch_key(chain_id, calldata_hash))
+            .copied()
+            .unwrap_or_default())
+    }
+
+    fn store_epoch(&self, chain_id: u64, calldata_hash: H256, epoch: u32) -> Result<(), DbError> {
+        let key = epoch_key(chain_id, calldata_hash);
+        let mut epochs = self.epochs.write().expect("poisoned");
+        if epoch == 0 {
+            epochs.remove(&key);
+        } else {
+            epochs.insert(key, epoch);
+        }
+        Ok(())
+    }
+}
+
+/// Relay submissions on one chain. Each attempt carries an idempotency key,
+/// and the task created for it is recorded, so a retry after a timeout
+/// checks the earlier task instead of creating a second one.
+///
+/// A key is expired when its task reaches a terminal state. A failed task
+/// also starts a new epoch, so the next retry creates a new task.
+#[derive(Debug, Clone)]
+pub struct RelayTasks {
+    chain_id: u64,
+    store: Arc<dyn RelayTaskStore>,
+}
+
+impl RelayTasks {
+    /// Relay submissions on chain `chain_id`, recorded in `store`
+    pub fn new(chain_id: u64, store: Arc<dyn RelayTaskStore>) -> Self {
+        Self { chain_id, store }
+    }
+
+    /// Idempotency key of the current attempt to relay `calldata`
+    pub fn key(&self, calldata: &[u8]) -> Result<H256, DbError> {
+        let calldata_hash = keccak256(calldata).into();
+        let epoch = self.store.epoch(self.chain_id, calldata_hash)?;
+        Ok(idempotency_key(self.chain_id, calldata_hash, epoch))
+    }
+
+    /// Relay `calldata` and wait for the outcome. `dispatch` forwards it to
+    /// the relay with the given idempotency key and returns the task id.
+    /// `wait` polls a task until it reaches a terminal state. If an earlier
+    /// attempt's task is recorded, it is waited on and `dispatch` is not
+    /// called.
+    pub async fn submit<D, DF, W, WF>(
+        &self,
+        calldata: &[u8],
+        dispatch: D,
+        wait: W,
+    ) -> Result<TxOutcome, GelatoError>
+    where
+        D: FnOnce(H256) -> DF,
+        DF: Future<Output = Result<H256, GelatoError>>,
+        W: FnOnce(H256) -> WF,
+        WF: Future<Output = Result<TxOutcome, GelatoError>>,
+    {
+        let calldata_hash = keccak256(calldata).into();
+        let epoch = self.store.epoch(self.chain_id, calldata_hash)?;
+        let key = idempotency_key(self.chain_id, calldata_hash, epoch);
+
+        let task_id = match self.store.task(key)? {
+            Some(task_id) => {
+                info!(
+                    key = ?key,
+                    task_id = ?task_id,
+                    "Found relay task of an earlier attempt. Checking it instead of resubmitting."
+                );
+                task_id
+            }
+            None => {
+                let task_id = dispatch(key).await?;
+                self.store.store_task(key, task_id)?;
+                task_id
+            }
+        };
+
+        let outcome = wait(task_id).await;
+        match &outcome {
+            Ok(_) => {
+                self.store.expire_task(key)?;
+                self.store.store_epoch(self.chain_id, calldata_hash, 0)?;
+            }
+            Err(GelatoError::FailedTaskError { .. }) => {
+                self.store.expire_task(key)?;
+                self.store
+                    .store_epoch(self.chain_id, calldata_hash, epoch + 1)?;
+            }
+            // Not terminal. A retry checks the task again.
+            Err(_) => {}
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Relay that deduplicates forward requests by idempotency key, like a
+    /// relay service honoring the key would
+    #[derive(Debug, Default)]
+    struct MockRelay {
+        /// Task created per key, in creation order
+        tasks: Mutex<Vec<(H256, H256)>>,
+        /// Forward requests received
+        forwards: Mutex<usize>,
+    }
+
+    impl MockRelay {
+        /// Create or look up the task of `key`. With `timeout`, the task is
+        /// created but the response is lost.
+        fn forward(&self, key: H256, timeout: bool) -> Result<H256, GelatoError> {
+            *self.forwards.lock().unwrap() += 1;
+            let mut tasks = self.tasks.lock().unwrap();
+            let task_id = match tasks.iter().find(|(k, _)| *k == key) {
+                Some((_, task_id)) => *task_id,
+                None => {
+                    let task_id = H256::from_low_u64_be(tasks.len() as u64 + 1);
+                    tasks.push((key, task_id));
+                    task_id
+                }
+            };
+            if timeout {
+                return Err(GelatoError::CustomError("relay request timed out".into()));
+            }
+            Ok(task_id)
+        }
+
+        fn created(&self) -> usize {
+            self.tasks.lock().unwrap().len()
+        }
+    }
+
+    fn executed(task_id: H256) -> Result<TxOutcome, GelatoError> {
+        Ok(TxOutcome {
+            txid: task_id,
+            gas_used: None,
+            block_number: None,
+        })
+    }
+
+    fn failed(task_id: H256) -> Result<TxOutcome, GelatoError> {
+        Err(GelatoError::FailedTaskError {
+            task_id,
+            check_info: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn it_resumes_a_task_after_a_timeout() {
+        let relay = &MockRelay::default();
+        let store = Arc::new(MemoryRelayTasks::default());
+        let tasks = RelayTasks::new(1, store.clone());
+        let calldata = b"process";
+
+        // The relay accepts the task, but the response times out
+        let err = tasks
+            .submit(
+                calldata,
+                |key| async move { relay.forward(key, true) },
+                |task_id| async move { executed(task_id) },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GelatoError::CustomError(_)));
+
+        // The retry carries the same key and gets the same task
+        let key = tasks.key(calldata).unwrap();
+        let outcome = tasks
+            .submit(
+                calldata,
+                |key| async move { relay.forward(key, false) },
+                |task_id| async move { executed(task_id) },
+            )
+            .await
+            .unwrap();
+        assert_eq!(outcome.txid, H256::from_low_u64_be(1));
+        assert_eq!(relay.created(), 1);
+        // Success expires the key
+        assert_eq!(store.task(key).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn it_checks_a_recorded_task_instead_of_resubmitting() {
+        let relay = &MockRelay::default();
+        let tasks = RelayTasks::new(1, Arc::new(MemoryRelayTasks::default()));
+        let calldata = b"process";
+
+        // Polling fails before the task is done
+        tasks
+            .submit(
+                calldata,
+                |key| async move { relay.forward(key, false) },
+                |_| async move { Err(GelatoError::CustomError("poll timed out".into())) },
+            )
+            .await
+            .unwrap_err();
+
+        tasks
+            .submit(
+                calldata,
+                |_| async move { Err(GelatoError::CustomError("resubmitted".into())) },
+                |task_id| async move { executed(task_id) },
+            )
+            .await
+            .unwrap();
+        assert_eq!(*relay.forwards.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_starts_a_new_epoch_after_a_failed_task() {
+        let relay = &MockRelay::default();
+        let store = Arc::new(MemoryRelayTasks::default());
+        let tasks = RelayTasks::new(1, store.clone());
+        let calldata = b"process";
+        let first_key = tasks.key(calldata).unwrap();
+
+        let err = tasks
+            .submit(
+                calldata,
+                |key| async move { relay.forward(key, false) },
+                |task_id| async move { failed(task_id) },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GelatoError::FailedTaskError { .. }));
+        assert_eq!(store.task(first_key).unwrap(), None);
+
+        let second_key = tasks.key(calldata).unwrap();
+        assert_ne!(first_key, second_key);
+        tasks
+            .submit(
+                calldata,
+                |key| async move { relay.forward(key, false) },
+                |task_id| async move { executed(task_id) },
+            )
+            .await
+            .unwrap();
+        assert_eq!(relay.created(), 2);
+        // Success resets the epoch
+        assert_eq!(tasks.key(calldata).unwrap(), first_key);
+    }
+
+    #[test]
+    fn keys_differ_by_chain_calldata_and_epoch() {
+        let hash = H256::repeat_byte(1);
+        let key = idempotency_key(1, hash, 0);
+        assert_eq!(key, idempotency_key(1, hash, 0));
+        assert_ne!(key, idempotency_key(2, hash, 0));
+        assert_ne!(key, idempotency_key(1, H256::repeat_byte(2), 0));
+        assert_ne!(key, idempotency_key(1, hash, 1));
+    }
+}
@@ -1,23 +1,46 @@
 #![allow(clippy::enum_variant_names)]
 #![allow(missing_docs)]
+#![deny(clippy::cast_possible_truncation)]
 
 use async_trait::async_trait;
 use color_eyre::Result;
-use ethers::core::types::{Signature, H256, U256};
-use futures_util::future::join_all;
+use ethers::contract::LogMeta;
+use ethers::core::types::{Address, H256, U256, U64};
 use nomad_core::{
-    accumulator::NomadProof, Common, CommonIndexer, ContractLocator, DoubleUpdate, Encode,
-    MessageStatus, NomadMessage, Replica, SignedUpdate, SignedUpdateWithMeta, State, TxOutcome,
-    Update, UpdateMeta,
+    accumulator::NomadProof, utils::saturating_u64, CommittedMessage, Common, CommonIndexer,
+    ContractLocator, DoubleUpdate, Encode, MessageStatus, NomadMessage, ProcessingState, Replica,
+    RootAdvance, RootProvenance, SignedUpdate, SignedUpdateWithMeta, State, TxOutcome,
 };
+use nomad_ethereum_bindings::replica::{InitializeCall, Replica as EthereumReplicaInternal};
+use nomad_types::{CheckedCast, ChecksumAddress};
 use nomad_xyz_configuration::ReplicaGasLimits;
-use std::{convert::TryFrom, sync::Arc};
-use tracing::instrument;
+use std::sync::Arc;
+use tracing::{instrument, warn};
 
 use crate::{
-    bindings::replica::Replica as EthereumReplicaInternal, utils, EthereumError, TxSubmitter,
+    events::{sorted_root_advances, sorted_updates_with_meta},
+    handle_calldata, is_history_unavailable, pinned, simulate_call, solidity_proof, utils,
+    EthereumError, ProofProvider, ReplicaState, Simulation, SolidityProof, StateOverrides,
+    SubmitWithGas, TxSubmitter, LOG_RANGE_CAPS,
 };
 
+/// Blocks back from the head searched for the event confirming a root
+const ROOT_PROVENANCE_LOOKBACK_BLOCKS: u64 = 200_000;
+
+/// Blocks past the start of each page of the root confirmation search
+const ROOT_PROVENANCE_PAGE_BLOCKS: u32 = 10_000;
+
+/// Blocks past the start of each page of the search for the first update
+/// after an initialization
+const INITIAL_ROOT_PAGE_BLOCKS: u32 = 10_000;
+
+/// Blocks back from the head searched for the `Process` event of a message
+/// processed in an unknown block
+const PROCESS_LOOKBACK_BLOCKS: u64 = 200_000;
+
+/// Blocks past the start of each page of the `Process` event search
+const PROCESS_PAGE_BLOCKS: u32 = 10_000;
+
 #[derive(Debug)]
 /// Struct that retrieves indexes event data for Ethereum replica
 pub struct EthereumReplicaIndexer<R>
@@ -26,6 +49,7 @@ where
 {
     contract: Arc<EthereumReplicaInternal<R>>,
     provider: Arc<R>,
+    name: String,
 }
 
 impl<R> EthereumReplicaIndexer<R>
@@ -41,19 +65,113 @@ where
             address,
         }: &ContractLocator,
     ) -> Self {
+        let evm_address = address.as_ethereum_address().expect("!eth address");
         tracing::info!(
-            address = ?address.as_ethereum_address(),
+            address = %ChecksumAddress(evm_address),
             name = name,
             domain = domain,
             "Connecting Replica Indexer"
         );
         Self {
-            contract: Arc::new(EthereumReplicaInternal::new(
-                address.as_ethereum_address().expect("!eth address"),
-                provider.clone(),
-            )),
+            contract: Arc::new(EthereumReplicaInternal::new(evm_address, provider.clone())),
             provider,
+            name: name.to_owned(),
+        }
+    }
+
+    /// Roots the replica was initialized with in blocks `from` to `to`, at
+    /// the logs of their initialization. `initialize` transfers ownership of
+    /// the replica from the zero address. The root is read from the
+    /// `initialize` calldata, or from the replica's state after the block if
+    /// it was initialized by a proxy's constructor. If the provider pruned
+    /// that state, the root is the old root of the first update after the
+    /// initialization, which must extend the committed root.
+    async fn initializations(
+        &self,
+        from: u32,
+        to: u32,
+    ) -> Result<Vec<(H256, LogMeta)>, EthereumError> {
+        use ethers::core::abi::AbiDecode;
+
+        let events = self
+            .contract
+            .ownership_transferred_filter()
+            .topic1(H256::zero())
+            .from_block(from)
+            .to_block(to)
+            .query_with_meta()
+            .await?;
+
+        let mut initializations = vec![];
+        for (_, meta) in events {
+            let calldata = self
+                .provider
+                .get_transaction(meta.transaction_hash)
+                .await
+                .map_err(|e| EthereumError::MiddlewareError(e.into()))?
+                .map(|tx| tx.input);
+            let block = meta.block_number.as_u64();
+            let root = match calldata.and_then(|input| InitializeCall::decode(input).ok()) {
+                Some(call) => call.committed_root.into(),
+                None => match self
+                    .contract
+                    .committed_root()
+                    .block(block)
+                    .call()
+                    .await
+                    .map_err(EthereumError::from)
+                {
+                    Ok(root) => root.into(),
+                    Err(e) if is_history_unavailable(&e) => match self.first_old_root(block).await?
+                    {
+                        Some(root) => root,
+                        None => {
+                            warn!(
+                                block,
+                                "Replica initialized at block {} with pruned state and no update since. Its root is unknown.",
+                                block,
+                            );
+                            continue;
+                        }
+                    },
+                    Err(e) => return Err(e),
+                },
+            };
+            initializations.push((root, meta));
+        }
+        Ok(initializations)
+    }
+
+    /// Old root of the first update from block `from` on, searched in pages
+    /// within the provider's log range cap
+    async fn first_old_root(&self, from: u64) -> Result<Option<H256>, EthereumError> {
+        let head = self
+            .provider
+            .get_block_number()
+            .await
+            .map_err(|e| EthereumError::MiddlewareError(e.into()))?
+            .as_u64();
+        let page = u64::from(LOG_RANGE_CAPS.page_size(&self.name, INITIAL_ROOT_PAGE_BLOCKS));
+
+        let mut start = from;
+        while start <= head {
+            let end = start.saturating_add(page).min(head);
+            let updates = self
+                .contract
+                .update_filter()
+                .from_block(start)
+                .to_block(end)
+                .query_with_meta()
+                .await?;
+            if let Some((update, _)) = updates
+                .into_iter()
+                .min_by_key(|(_, meta)| (meta.block_number, meta.log_index))
+            {
+                return Ok(Some(update.old_root.into()));
+            }
+            start = end + 1;
         }
+        Ok(None)
     }
 }
 
@@ -71,7 +189,17 @@ where
             .get_block_number()
             .await
             .map_err(|e| EthereumError::MiddlewareError(e.into()))?
-            .as_u32())
+            .try_u32()?)
+    }
+
+    #[instrument(err, skip(self))]
+    async fn get_latest_block_timestamp(&self) -> Result<Option<u64>, Self::Error> {
+        Ok(self
+            .provider
+            .get_block(ethers::core::types::BlockNumber::Latest)
+            .await
+            .map_err(|e| EthereumError::MiddlewareError(e.into()))?
+            .map(|block| saturating_u64(block.timestamp)))
     }
 
     #[instrument(err, skip(self))]
@@ -80,7 +208,7 @@ where
         from: u32,
         to: u32,
     ) -> Result<Vec<SignedUpdateWithMeta>, Self::Error> {
-        let mut events = self
+        let events = self
             .contract
             .update_filter()
             .from_block(from)
@@ -88,47 +216,38 @@ where
             .query_with_meta()
             .await?;
 
-        events.sort_by(|a, b| {
-            let mut ordering = a.1.block_number.cmp(&b.1.block_number);
-            if ordering == std::cmp::Ordering::Equal {
-                ordering = a.1.transaction_index.cmp(&b.1.transaction_index);
-            }
-
-            ordering
-        });
+        sorted_updates_with_meta(self.provider.as_ref(), events).await
+    }
 
-        let update_futs: Vec<_> = events
-            .iter()
-            .map(|event| async {
-                let signature = Signature::try_from(event.0.signature.as_ref())
-                    .expect("chain accepted invalid signature");
-
-                let update = Update {
-                    home_domain: event.0.home_domain,
-                    previous_root: event.0.old_root.into(),
-                    new_root: event.0.new_root.into(),
-                };
-
-                let block_number = event.1.block_number.as_u64();
-                let timestamp = self
-                    .provider
-                    .get_block(block_number)
-                    .await
-                    .ok()
-                    .flatten()
-                    .map(|b| b.timestamp.as_u64());
-
-                SignedUpdateWithMeta {
-                    signed_update: SignedUpdate { update, signature },
-                    metadata: UpdateMeta {
-                        block_number,
-                        timestamp,
-                    },
-                }
-            })
-            .collect();
+    #[instrument(err, skip(self))]
+    async fn fetch_root_advances(
+        &self,
+        from: u32,
+        to: u32,
+    ) -> Result<Vec<RootAdvance>, Self::Error> {
+        let updates = self
+            .contract
+            .update_filter()
+            .from_block(from)
+            .to_block(to)
+            .query_with_meta()
+            .await?;
+        let confirmations = self
+            .contract
+            .set_confirmation_filter()
+            .from_block(from)
+            .to_block(to)
+            .query_with_meta()
+            .await?;
+        let initializations = self.initializations(from, to).await?;
 
-        Ok(join_all(update_futs).await)
+        Ok(sorted_root_advances(
+            self.provider.as_ref(),
+            initializations,
+            updates,
+            confirmations,
+        )
+        .await)
     }
 }
 
@@ -163,23 +282,154 @@ where
         }: &ContractLocator,
         gas: Option<ReplicaGasLimits>,
     ) -> Self {
+        let evm_address = address.as_ethereum_address().expect("!eth address");
         tracing::info!(
-            address = ?address.as_ethereum_address(),
+            address = %ChecksumAddress(evm_address),
             name = name,
             domain = domain,
             "Connecting Replica"
         );
         Self {
             submitter,
-            contract: Arc::new(EthereumReplicaInternal::new(
-                address.as_ethereum_address().expect("!eth address"),
-                read_provider,
-            )),
+            contract: Arc::new(EthereumReplicaInternal::new(evm_address, read_provider)),
             domain: *domain,
             name: name.to_owned(),
             gas,
         }
     }
+
+    /// Fetch the Replica state, rejecting state words outside the contract's
+    /// enum.
+    pub async fn status(&self) -> Result<ReplicaState, EthereumError> {
+        ReplicaState::try_from(self.contract.state().call().await?)
+    }
+
+    /// Run `read` at a block pinned on the read provider
+    async fn read_pinned<T, F, Fut>(&self, read: F) -> Result<T, EthereumError>
+    where
+        F: Fn(U64) -> Fut,
+        Fut: std::future::Future<Output = Result<T, EthereumError>>,
+    {
+        pinned::read_pinned(&*self.contract.client(), &self.name, read).await
+    }
+
+    fn prove_call(&self, proof: &NomadProof) -> ethers::contract::builders::ContractCall<R, bool> {
+        let sol_proof = proof.path.map(H256::to_fixed_bytes);
+        let mut call = self
+            .contract
+            .prove(proof.leaf.into(), sol_proof, proof.index.into());
+        if let Some(limits) = &self.gas {
+            call.tx.set_gas(U256::from(limits.prove));
+        }
+        call
+    }
+
+    fn prove_and_process_call(
+        &self,
+        message: &NomadMessage,
+        sol_proof: SolidityProof,
+        index: U256,
+    ) -> ethers::contract::builders::ContractCall<R, ()> {
+        let mut call = self
+            .contract
+            .prove_and_process(message.to_vec().into(), sol_proof, index)
+            .gas(1_900_000);
+        if let Some(limits) = &self.gas {
+            call.tx.set_gas(U256::from(limits.prove_and_process));
+        }
+        call
+    }
+
+    /// Prove and process `message` with its branch fetched from `prover`.
+    /// Nothing is submitted if the leaf index is past the tree or the branch
+    /// is not one node per level.
+    #[tracing::instrument(err, skip(prover))]
+    pub async fn prove_and_process_with_proof<P: ProofProvider>(
+        &self,
+        message: &CommittedMessage,
+        prover: &P,
+    ) -> Result<TxOutcome, EthereumError> {
+        let branch = prover
+            .branch(message.leaf_index)
+            .await
+            .map_err(|e| EthereumError::CustomError(Box::new(e)))?;
+        let sol_proof = solidity_proof(message.leaf_index, &branch)?;
+        let tx =
+            self.prove_and_process_call(&message.message, sol_proof, message.leaf_index.into());
+
+        self.submit_with_gas(tx, &self.submitter.gas).await
+    }
+
+    fn process_call(
+        &self,
+        message: &NomadMessage,
+    ) -> ethers::contract::builders::ContractCall<R, bool> {
+        let mut call = self.contract.process(message.to_vec().into());
+        if let Some(limits) = &self.gas {
+            call.tx.set_gas(U256::from(limits.process));
+        }
+        call
+    }
+
+    /// Simulate `prove` with eth_call on the read provider, as submitted by
+    /// `prove`. Never sends a transaction
+    pub async fn simulate_prove(
+        &self,
+        proof: &NomadProof,
+        overrides: &StateOverrides,
+    ) -> Result<Simulation, EthereumError> {
+        let call = self.prove_call(proof);
+        simulate_call(&*self.contract.client(), &call.tx, overrides).await
+    }
+
+    /// Simulate `process` with eth_call on the read provider, as submitted by
+    /// `process`. The message must already be proven, on chain or by
+    /// `overrides`. Never sends a transaction
+    pub async fn simulate_process(
+        &self,
+        message: &NomadMessage,
+        overrides: &StateOverrides,
+    ) -> Result<Simulation, EthereumError> {
+        let call = self.process_call(message);
+        simulate_call(&*self.contract.client(), &call.tx, overrides).await
+    }
+
+    /// Simulate the recipient's `handle` call as made by the replica during
+    /// `process`, so handler reverts are seen with their reason. Never sends
+    /// a transaction
+    pub async fn simulate_handle(
+        &self,
+        message: &NomadMessage,
+        overrides: &StateOverrides,
+    ) -> Result<Simulation, EthereumError> {
+        let mut tx = ethers::types::TransactionRequest::new()
+            .from(self.contract.address())
+            .to(ethers::types::Address::from(message.recipient))
+            .data(handle_calldata(
+                message.origin,
+                message.nonce,
+                message.sender,
+                &message.body,
+            ));
+        if let Some(limits) = &self.gas {
+            tx = tx.gas(limits.process);
+        }
+        simulate_call(&*self.contract.client(), &tx.into(), overrides).await
+    }
+}
+
+impl<W, R> SubmitWithGas<W> for EthereumReplica<W, R>
+where
+    W: ethers::providers::Middleware + 'static,
+    R: ethers::providers::Middleware + 'static,
+{
+    fn tx_submitter(&self) -> &TxSubmitter<W> {
+        &self.submitter
+    }
+
+    fn contract_location(&self) -> (u32, Address) {
+        (self.domain, self.contract.address())
+    }
 }
 
 impl<W, R> std::fmt::Display for EthereumReplica<W, R>
@@ -228,13 +478,7 @@ where
 
     #[tracing::instrument(err)]
     async fn state(&self) -> Result<State, Self::Error> {
-        let state = self.contract.state().call().await?;
-        match state {
-            0 => Ok(State::Uninitialized),
-            1 => Ok(State::Active),
-            2 => Ok(State::Failed),
-            _ => unreachable!(),
-        }
+        Ok(self.status().await?.into())
     }
 
     #[tracing::instrument(err)]
@@ -254,9 +498,7 @@ where
             tx.tx.set_gas(U256::from(limits.update));
         }
 
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
-            .await
+        self.submit_with_gas(tx, &self.submitter.gas).await
     }
 
     #[tracing::instrument(err)]
@@ -264,6 +506,8 @@ where
         tracing::warn!("double-update submission has been deprecated");
         Ok(TxOutcome {
             txid: Default::default(),
+            gas_used: None,
+            block_number: None,
         })
     }
 }
@@ -284,36 +528,16 @@ where
 
     #[tracing::instrument(err)]
     async fn prove(&self, proof: &NomadProof) -> Result<TxOutcome, <Self as Common>::Error> {
-        let mut sol_proof: [[u8; 32]; 32] = Default::default();
-        sol_proof
-            .iter_mut()
-            .enumerate()
-            .for_each(|(i, elem)| *elem = proof.path[i].to_fixed_bytes());
-
-        let mut tx = self
-            .contract
-            .prove(proof.leaf.into(), sol_proof, proof.index.into());
+        let tx = self.prove_call(proof);
 
-        if let Some(limits) = &self.gas {
-            tx.tx.set_gas(U256::from(limits.prove));
-        }
-
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
-            .await
+        self.submit_with_gas(tx, &self.submitter.gas).await
     }
 
     #[tracing::instrument(err)]
     async fn process(&self, message: &NomadMessage) -> Result<TxOutcome, <Self as Common>::Error> {
-        let mut tx = self.contract.process(message.to_vec().into());
-
-        if let Some(limits) = &self.gas {
-            tx.tx.set_gas(U256::from(limits.process));
-        }
+        let tx = self.process_call(message);
 
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
-            .await
+        self.submit_with_gas(tx, &self.submitter.gas).await
     }
 
     #[tracing::instrument(err)]
@@ -322,24 +546,10 @@ where
         message: &NomadMessage,
         proof: &NomadProof,
     ) -> Result<TxOutcome, <Self as Common>::Error> {
-        let mut sol_proof: [[u8; 32]; 32] = Default::default();
-        sol_proof
-            .iter_mut()
-            .enumerate()
-            .for_each(|(i, elem)| *elem = proof.path[i].to_fixed_bytes());
-
-        let mut tx = self
-            .contract
-            .prove_and_process(message.to_vec().into(), sol_proof, proof.index.into())
-            .gas(1_900_000);
-
-        if let Some(limits) = &self.gas {
-            tx.tx.set_gas(U256::from(limits.prove_and_process));
-        }
+        let sol_proof = proof.path.map(H256::to_fixed_bytes);
+        let tx = self.prove_and_process_call(message, sol_proof, proof.index.into());
 
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
-            .await
+        self.submit_with_gas(tx, &self.submitter.gas).await
     }
 
     #[tracing::instrument(err)]
@@ -347,7 +557,382 @@ where
         Ok(self.contract.messages(leaf.into()).call().await?.into())
     }
 
+    /// Outcome of the `Process` event of `leaf`, looked up in `block` if
+    /// known. Otherwise the last `PROCESS_LOOKBACK_BLOCKS` blocks are
+    /// searched, newest first, in pages within the provider's log range cap.
+    #[tracing::instrument(err)]
+    async fn process_success(
+        &self,
+        leaf: H256,
+        block: Option<u64>,
+    ) -> Result<Option<bool>, <Self as Common>::Error> {
+        let success = |start: u64, end: u64| async move {
+            let events = self
+                .contract
+                .process_filter()
+                .topic1(leaf)
+                .from_block(start)
+                .to_block(end)
+                .query()
+                .await?;
+            Ok::<_, EthereumError>(events.last().map(|event| event.success))
+        };
+        if let Some(block) = block {
+            return success(block, block).await;
+        }
+
+        let head = self
+            .contract
+            .client()
+            .get_block_number()
+            .await
+            .map_err(|e| EthereumError::MiddlewareError(e.into()))?
+            .as_u64();
+        let from = head.saturating_sub(PROCESS_LOOKBACK_BLOCKS);
+        let page = u64::from(LOG_RANGE_CAPS.page_size(&self.name, PROCESS_PAGE_BLOCKS));
+
+        let mut end = head;
+        loop {
+            let start = end.saturating_sub(page).max(from);
+            if let Some(success) = success(start, end).await? {
+                return Ok(Some(success));
+            }
+            if start == from {
+                return Ok(None);
+            }
+            end = start - 1;
+        }
+    }
+
     async fn acceptable_root(&self, root: H256) -> Result<bool, <Self as Common>::Error> {
         Ok(self.contract.acceptable_root(root.into()).call().await?)
     }
+
+    async fn confirm_at(&self, root: H256) -> Result<u64, <Self as Common>::Error> {
+        let confirm_at = self.contract.confirm_at(root.into()).call().await?;
+        Ok(saturating_u64(confirm_at))
+    }
+
+    #[tracing::instrument(err, skip(self))]
+    async fn processing_state(
+        &self,
+        root: H256,
+        leaf: H256,
+    ) -> Result<ProcessingState, <Self as Common>::Error> {
+        let (root_acceptable, confirm_at, status) = self
+            .read_pinned(|block| async move {
+                Ok::<_, EthereumError>(futures_util::try_join!(
+                    self.contract
+                        .acceptable_root(root.into())
+                        .block(block)
+                        .call(),
+                    self.contract.confirm_at(root.into()).block(block).call(),
+                    self.contract.messages(leaf.into()).block(block).call(),
+                )?)
+            })
+            .await?;
+        Ok(ProcessingState {
+            root_acceptable,
+            confirm_at: saturating_u64(confirm_at),
+            status: status.into(),
+        })
+    }
+
+    /// Searches the last `ROOT_PROVENANCE_LOOKBACK_BLOCKS` blocks, newest
+    /// first, in pages within the provider's log range cap
+    async fn root_provenance(
+        &self,
+        root: H256,
+    ) -> Result<Option<RootProvenance>, <Self as Common>::Error> {
+        if self.confirm_at(root).await? == 0 {
+            return Ok(None);
+        }
+
+        let head = self
+            .contract
+            .client()
+            .get_block_number()
+            .await
+            .map_err(|e| EthereumError::MiddlewareError(e.into()))?
+            .as_u64();
+        let from = head.saturating_sub(ROOT_PROVENANCE_LOOKBACK_BLOCKS);
+        let page = u64::from(LOG_RANGE_CAPS.page_size(&self.name, ROOT_PROVENANCE_PAGE_BLOCKS));
+
+        let mut end = head;
+        loop {
+            let start = end.saturating_sub(page).max(from);
+            let updates = self
+                .contract
+                .update_filter()
+                .topic3(root)
+                .from_block(start)
+                .to_block(end)
+                .query()
+                .await?;
+            if !updates.is_empty() {
+                return Ok(Some(RootProvenance::Update));
+            }
+            let confirmations = self
+                .contract
+                .set_confirmation_filter()
+                .topic1(root)
+                .from_block(start)
+                .to_block(end)
+                .query()
+                .await?;
+            if !confirmations.is_empty() {
+                return Ok(Some(RootProvenance::Governance));
+            }
+            if start == from {
+                break;
+            }
+            end = start - 1;
+        }
+
+        // `initialize` confirms the genesis root without an event. With
+        // history past the lookback, the event may lie beyond it.
+        Ok(if from == 0 {
+            Some(RootProvenance::Genesis)
+        } else {
+            None
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ethers::contract::EthEvent;
+    use ethers::{
+        contract::EthCall,
+        core::{
+            abi::{self, Token},
+            types::{
+                transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, Bytes, Log,
+            },
+        },
+        providers::{FromErr, Middleware, MockProvider, Provider, ProviderError},
+    };
+    use nomad_ethereum_bindings::replica::{
+        AcceptableRootCall, ConfirmAtCall, MessagesCall, ProcessFilter,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use nomad_core::{LEGACY_STATUS_NONE, LEGACY_STATUS_PROCESSED, LEGACY_STATUS_PROVEN};
+
+    use crate::{SubmitterClient, TxSubmitter};
+
+    /// Middleware standing in for nodes at different heights behind a load
+    /// balancer. Each request goes to the next node in turn. Nodes report
+    /// their head as the block number and have not seen later blocks. Calls
+    /// answer with values of the block they were made at.
+    #[derive(Debug)]
+    struct BalancedNodes {
+        inner: Provider<MockProvider>,
+        heads: Vec<u64>,
+        requests: AtomicUsize,
+        block_numbers: AtomicUsize,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("{0}")]
+    struct BalancedNodesError(ProviderError);
+
+    impl FromErr<ProviderError> for BalancedNodesError {
+        fn from(src: ProviderError) -> Self {
+            Self(src)
+        }
+    }
+
+    impl BalancedNodes {
+        fn next_head(&self) -> u64 {
+            let request = self.requests.fetch_add(1, Ordering::SeqCst);
+            self.heads[request % self.heads.len()]
+        }
+    }
+
+    #[async_trait]
+    impl Middleware for BalancedNodes {
+        type Error = BalancedNodesError;
+        type Provider = MockProvider;
+        type Inner = Provider<MockProvider>;
+
+        fn inner(&self) -> &Self::Inner {
+            &self.inner
+        }
+
+        async fn get_block_number(&self) -> Result<U64, Self::Error> {
+            self.block_numbers.fetch_add(1, Ordering::SeqCst);
+            Ok(self.next_head().into())
+        }
+
+        async fn call(
+            &self,
+            tx: &TypedTransaction,
+            block: Option<BlockId>,
+        ) -> Result<Bytes, Self::Error> {
+            let head = self.next_head();
+            let block = match block {
+                Some(BlockId::Number(BlockNumber::Number(block))) => block.as_u64(),
+                other => panic!("call not pinned to a block: {:?}", other),
+            };
+            if block > head {
+                return Err(BalancedNodesError(ProviderError::CustomError(
+                    "header not found".to_owned(),
+                )));
+            }
+
+            let data = tx.data().expect("!data");
+            let selector: [u8; 4] = data[..4].try_into().unwrap();
+            let value = if selector == AcceptableRootCall::selector() {
+                Token::Bool(true)
+            } else if selector == ConfirmAtCall::selector() {
+                Token::Uint(block.into())
+            } else if selector == MessagesCall::selector() {
+                Token::FixedBytes(H256::from_low_u64_be(block).as_bytes().to_vec())
+            } else {
+                panic!("unexpected call {:?}", data)
+            };
+            Ok(abi::encode(&[value]).into())
+        }
+    }
+
+    #[tokio::test]
+    async fn it_reads_processing_state_at_one_block_across_nodes() {
+        let provider = Arc::new(BalancedNodes {
+            inner: Provider::mocked().0,
+            heads: vec![105, 100, 100],
+            requests: Default::default(),
+            block_numbers: Default::default(),
+        });
+        let replica = EthereumReplica::new(
+            TxSubmitter::<BalancedNodes>::new(SubmitterClient::ReadOnly),
+            provider.clone(),
+            &ContractLocator {
+                name: "balanced".into(),
+                domain: 1000,
+                address: Address::repeat_byte(1).into(),
+            },
+            None,
+        );
+
+        // The first block is ahead of the lagging nodes, whose calls fail.
+        // The group is read again at a block every node has.
+        let state = replica
+            .processing_state(H256::repeat_byte(2), H256::repeat_byte(3))
+            .await
+            .unwrap();
+        assert_eq!(
+            state,
+            ProcessingState {
+                root_acceptable: true,
+                confirm_at: 100,
+                status: MessageStatus::Proven(H256::from_low_u64_be(100)),
+            }
+        );
+        assert_eq!(provider.block_numbers.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn it_looks_up_process_events_in_bounded_ranges() {
+        let (provider, mock) = Provider::mocked();
+        let replica: EthereumReplica<Provider<MockProvider>, _> = EthereumReplica::new(
+            TxSubmitter::new(SubmitterClient::ReadOnly),
+            Arc::new(provider),
+            &ContractLocator {
+                name: "replica_process".into(),
+                domain: 2000,
+                address: Address::repeat_byte(1).into(),
+            },
+            None,
+        );
+        let leaf = H256::repeat_byte(3);
+        let filter = |start: u64, end: u64| {
+            replica
+                .contract
+                .process_filter()
+                .topic1(leaf)
+                .from_block(start)
+                .to_block(end)
+                .filter
+        };
+        let log = Log {
+            address: Address::repeat_byte(1),
+            topics: vec![
+                ProcessFilter::signature(),
+                leaf,
+                H256::zero(),
+                H256::repeat_byte(4),
+            ],
+            block_number: Some(U64::from(12)),
+            ..Default::default()
+        };
+
+        // Processed with a reverted handler by us, in the block of the
+        // receipt
+        mock.push(vec![log.clone()]).unwrap();
+        assert_eq!(
+            replica.process_success(leaf, Some(12)).await.unwrap(),
+            Some(false)
+        );
+        mock.assert_request("eth_getLogs", [filter(12, 12)])
+            .unwrap();
+
+        // Processed by others is searched back from the head in pages.
+        // Responses are served last pushed first.
+        mock.push(vec![log]).unwrap();
+        mock.push(U64::from(15_000)).unwrap();
+        assert_eq!(
+            replica.process_success(leaf, None).await.unwrap(),
+            Some(false)
+        );
+        mock.assert_request("eth_blockNumber", ()).unwrap();
+        mock.assert_request("eth_getLogs", [filter(5_000, 15_000)])
+            .unwrap();
+
+        // Not processed within the lookback
+        mock.push::<Vec<Log>, _>(vec![]).unwrap();
+        mock.push::<Vec<Log>, _>(vec![]).unwrap();
+        mock.push(U64::from(15_000)).unwrap();
+        assert_eq!(replica.process_success(leaf, None).await.unwrap(), None);
+        mock.assert_request("eth_blockNumber", ()).unwrap();
+        mock.assert_request("eth_getLogs", [filter(5_000, 15_000)])
+            .unwrap();
+        mock.assert_request("eth_getLogs", [filter(0, 4_999)])
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_maps_status_words_to_message_statuses() {
+        let (provider, mock) = Provider::mocked();
+        let replica: EthereumReplica<Provider<MockProvider>, _> = EthereumReplica::new(
+            TxSubmitter::new(SubmitterClient::ReadOnly),
+            Arc::new(provider),
+            &ContractLocator {
+                name: "replica_1".into(),
+                domain: 2000,
+                address: Address::repeat_byte(1).into(),
+            },
+            None,
+        );
+
+        let root = H256::repeat_byte(7);
+        for (word, status) in [
+            (LEGACY_STATUS_NONE, MessageStatus::None),
+            (LEGACY_STATUS_PROCESSED, MessageStatus::Processed),
+            (root, MessageStatus::Proven(root)),
+            (
+                LEGACY_STATUS_PROVEN,
+                MessageStatus::Proven(LEGACY_STATUS_PROVEN),
+            ),
+        ] {
+            mock.push::<Bytes, _>(Bytes::from(word.as_bytes().to_vec()))
+                .unwrap();
+            assert_eq!(
+                replica.message_status(H256::repeat_byte(9)).await.unwrap(),
+                status
+            );
+        }
+    }
 }
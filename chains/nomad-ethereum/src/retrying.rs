@@ -8,30 +8,75 @@ use thiserror::Error;
 use tokio::time::sleep;
 use tracing::{debug, instrument, warn};
 
-/// An HTTP Provider with a simple naive exponential backoff built-in
+use crate::{rpc_error_classifier, ClassifiedRpcError};
+use nomad_core::utils::exponential_backoff;
+use nomad_xyz_configuration::ethereum::RpcErrorClass;
+
+/// Methods that change state. They are sent once: a retry could submit a
+/// transaction twice, so resubmission is left to the caller.
+const STATE_CHANGING_METHODS: &[&str] = &["eth_sendRawTransaction", "eth_sendTransaction"];
+
+/// Whether a request for `method` may be retried
+pub fn is_idempotent(method: &str) -> bool {
+    !STATE_CHANGING_METHODS.contains(&method)
+}
+
+/// How the [`RetryingProvider`] retries failed requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry. Doubles before each further retry.
+    pub base_delay: Duration,
+    /// Longest delay
+    pub max_delay: Duration,
+    /// Draw each delay uniformly from its upper half, so requests failing
+    /// together spread out
+    pub jitter: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(32),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Delay before retry number `retry`, counted from 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        exponential_backoff(self.base_delay, self.max_delay, retry, self.jitter)
+    }
+}
+
+/// An HTTP Provider retrying failed requests with exponential backoff.
+/// Errors are classified by the configured JSON-RPC error patterns. Only
+/// retryable ones are retried; the others are returned at once. Requests
+/// changing state, such as transaction submissions, are never retried.
 #[derive(Debug, Clone)]
 pub struct RetryingProvider<P> {
     inner: P,
-    max_requests: usize,
+    config: RetryConfig,
 }
 
 impl<P> RetryingProvider<P> {
     /// Instantiate a RetryingProvider
-    pub fn new(inner: P, max_requests: usize) -> Self {
-        Self {
-            inner,
-            max_requests,
-        }
+    pub fn new(inner: P, config: RetryConfig) -> Self {
+        Self { inner, config }
     }
 
-    /// Set the max_requests (and by extension the total time a request can take)
-    pub fn set_max_requests(&mut self, max_requests: usize) {
-        self.max_requests = max_requests;
+    /// Set the retry config
+    pub fn set_config(&mut self, config: RetryConfig) {
+        self.config = config;
     }
 
-    /// Get the max_requests
-    pub fn get_max_requests(&self) -> usize {
-        self.max_requests
+    /// Get the retry config
+    pub fn config(&self) -> &RetryConfig {
+        &self.config
     }
 }
 
@@ -47,6 +92,9 @@ where
     /// Hit max requests
     #[error("Hit max requests")]
     MaxRequests(Vec<P::Error>),
+    /// Error that retrying cannot resolve, returned at once
+    #[error("{0}")]
+    Unretryable(#[source] ClassifiedRpcError),
 }
 
 impl<P> From<RetryingProviderError<P>> for ProviderError
@@ -81,35 +129,52 @@ where
         let mut errors = vec![];
 
         let params = serde_json::to_value(params).expect("valid");
+        let max_retries = if is_idempotent(method) {
+            self.config.max_retries
+        } else {
+            0
+        };
 
-        for i in 0..self.max_requests {
-            let backoff_seconds = 2u64.pow(i as u32);
-            {
-                debug!(attempt = i, "Dispatching request");
+        for attempt in 0..=max_retries {
+            debug!(attempt, "Dispatching request");
 
-                let fut = match params {
-                    Value::Null => self.inner.request(method, ()),
-                    _ => self.inner.request(method, &params),
-                };
+            let fut = match params {
+                Value::Null => self.inner.request(method, ()),
+                _ => self.inner.request(method, &params),
+            };
 
-                match fut.await {
-                    Ok(res) => return Ok(res),
-                    Err(e) => {
+            match fut.await {
+                Ok(res) => return Ok(res),
+                Err(e) => {
+                    let classified = rpc_error_classifier().classify_error(&e);
+                    if classified.class != RpcErrorClass::Retryable {
                         warn!(
-                            backoff_seconds,
-                            retries_remaining = self.max_requests - i - 1,
+                            pattern = %classified.pattern,
+                            class = classified.class.as_str(),
                             error = %e,
                             method = %method,
-                            "Error in retrying provider",
+                            "Unretryable error in retrying provider",
                         );
-                        errors.push(e);
+                        return Err(RetryingProviderError::Unretryable(classified));
+                    }
+                    let retries_remaining = max_retries - attempt;
+                    warn!(
+                        attempt,
+                        retries_remaining,
+                        pattern = %classified.pattern,
+                        error = %e,
+                        method = %method,
+                        "Error in retrying provider",
+                    );
+                    errors.push(e);
+                    if retries_remaining > 0 {
+                        sleep(self.config.delay(attempt + 1)).await;
                     }
                 }
             }
-            sleep(Duration::from_secs(backoff_seconds)).await;
         }
 
-        return Err(RetryingProviderError::MaxRequests(errors));
+        Err(RetryingProviderError::MaxRequests(errors))
     }
 }
 
@@ -120,6 +185,182 @@ where
     type Err = <P as FromStr>::Err;
 
     fn from_str(src: &str) -> Result<Self, Self::Err> {
-        Ok(Self::new(src.parse()?, 6))
+        Ok(Self::new(src.parse()?, RetryConfig::default()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tokio::time::Instant;
+
+    /// Client failing its first `failures` requests with `message`, then
+    /// answering `"0x1"`
+    #[derive(Debug)]
+    struct FlakyClient {
+        failures: u32,
+        message: &'static str,
+        calls: AtomicU32,
+    }
+
+    #[derive(Debug, Error)]
+    #[error("{0}")]
+    struct FlakyError(&'static str);
+
+    impl From<FlakyError> for ProviderError {
+        fn from(src: FlakyError) -> Self {
+            ProviderError::JsonRpcClientError(Box::new(src))
+        }
+    }
+
+    #[async_trait]
+    impl JsonRpcClient for FlakyClient {
+        type Error = FlakyError;
+
+        async fn request<T, R>(&self, _: &str, _: T) -> Result<R, Self::Error>
+        where
+            T: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned,
+        {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if call > self.failures {
+                Ok(serde_json::from_value(Value::from("0x1")).unwrap())
+            } else {
+                Err(FlakyError(self.message))
+            }
+        }
+    }
+
+    fn flaky(
+        failures: u32,
+        message: &'static str,
+        config: RetryConfig,
+    ) -> RetryingProvider<FlakyClient> {
+        RetryingProvider::new(
+            FlakyClient {
+                failures,
+                message,
+                calls: AtomicU32::new(0),
+            },
+            config,
+        )
+    }
+
+    fn calls(provider: &RetryingProvider<FlakyClient>) -> u32 {
+        provider.inner.calls.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn it_doubles_delays_up_to_the_max() {
+        let config = RetryConfig {
+            max_retries: 10,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+            jitter: false,
+        };
+        for (retry, delay) in [(1, 1), (2, 2), (4, 8), (5, 10), (u32::MAX, 10)] {
+            assert_eq!(config.delay(retry), Duration::from_secs(delay));
+        }
+
+        let jittered = RetryConfig {
+            jitter: true,
+            ..config
+        };
+        for retry in 1..=6 {
+            let full = config.delay(retry);
+            let delay = jittered.delay(retry);
+            assert!(delay <= full && delay >= full / 2, "{:?}", delay);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_retries_retryable_errors_with_backoff() {
+        let config = RetryConfig {
+            max_retries: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            jitter: true,
+        };
+
+        for message in [
+            "429 Too Many Requests",
+            "request timed out",
+            "HTTP status server error (502 Bad Gateway)",
+        ] {
+            let provider = flaky(3, message, config);
+            let start = Instant::now();
+
+            let block: String = provider.request("eth_blockNumber", ()).await.unwrap();
+            assert_eq!(block, "0x1");
+            assert_eq!(calls(&provider), 4);
+            // 1 + 2 + 4 seconds, each at least halved by jitter
+            let elapsed = start.elapsed();
+            assert!(
+                elapsed >= Duration::from_millis(3500) && elapsed <= Duration::from_secs(7),
+                "{:?}",
+                elapsed
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_gives_up_after_max_retries() {
+        let provider = flaky(
+            u32::MAX,
+            "503 Service Unavailable",
+            RetryConfig {
+                max_retries: 2,
+                base_delay: Duration::from_secs(1),
+                max_delay: Duration::from_millis(1500),
+                jitter: false,
+            },
+        );
+        let start = Instant::now();
+
+        let result: Result<String, _> = provider.request("eth_getLogs", ()).await;
+        assert!(matches!(
+            result,
+            Err(RetryingProviderError::MaxRequests(ref errors)) if errors.len() == 3
+        ));
+        assert_eq!(calls(&provider), 3);
+        // No wait after the last attempt
+        assert_eq!(start.elapsed(), Duration::from_millis(2500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_returns_fatal_errors_at_once() {
+        for message in [
+            "execution reverted: !proven",
+            "Deserialization Error: invalid type: string \"0x\", expected a sequence at line 1 column 4. Response: \"0x\"",
+        ] {
+            let provider = flaky(1, message, RetryConfig::default());
+            let start = Instant::now();
+
+            let result: Result<String, _> = provider.request("eth_call", ()).await;
+            assert!(matches!(
+                result,
+                Err(RetryingProviderError::Unretryable(ref classified))
+                    if classified.class == RpcErrorClass::Fatal
+            ));
+            assert_eq!(calls(&provider), 1);
+            assert_eq!(start.elapsed(), Duration::ZERO);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_never_retries_submissions() {
+        for method in ["eth_sendRawTransaction", "eth_sendTransaction"] {
+            let provider = flaky(1, "request timed out", RetryConfig::default());
+            let start = Instant::now();
+
+            let result: Result<String, _> = provider.request(method, ()).await;
+            assert!(matches!(
+                result,
+                Err(RetryingProviderError::MaxRequests(ref errors)) if errors.len() == 1
+            ));
+            assert_eq!(calls(&provider), 1);
+            assert_eq!(start.elapsed(), Duration::ZERO);
+        }
     }
 }
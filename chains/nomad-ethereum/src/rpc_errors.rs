@@ -0,0 +1,614 @@
+use ethers::providers::HttpClientError;
+use nomad_xyz_configuration::ethereum::{RpcErrorClass, RpcErrorPattern};
+use once_cell::sync::{Lazy, OnceCell};
+use prometheus::IntCounterVec;
+use regex::{Regex, RegexBuilder};
+use std::error::Error as StdError;
+
+/// Pattern name counted for errors no pattern matches. Unclassified errors
+/// are retried, as all errors were before classification.
+pub const UNCLASSIFIED: &str = "unclassified";
+
+/// Built-in patterns, checked after configured ones. The first match wins,
+/// so narrow patterns come before broad ones sharing a code.
+const DEFAULT_PATTERNS: &[(&str, Option<i64>, Option<&str>, RpcErrorClass)] = &[
+    // Vendors cap the block range of log queries. Alchemy's message also
+    // reports the response size
+    (
+        "logs_range_too_large",
+        None,
+        Some(
+            r"block range (is )?too (large|wide|big)|block range limit|(up to|limited to) an? [\d,.]+k? (block )?range|exceeds? (the )?max(imum)? (block )?range|range (is )?too (large|wide|big)|try with this block range",
+        ),
+        RpcErrorClass::RangeTooLarge,
+    ),
+    // Infura reuses its rate limit code for oversized log queries
+    (
+        "logs_too_many_results",
+        None,
+        Some(r"query returned more than \d+ results|log response size exceeded"),
+        RpcErrorClass::Fatal,
+    ),
+    (
+        "infura_rate_limit",
+        Some(-32005),
+        None,
+        RpcErrorClass::Retryable,
+    ),
+    (
+        "quicknode_rate_limit",
+        Some(-32007),
+        None,
+        RpcErrorClass::Retryable,
+    ),
+    (
+        "ankr_rate_limit",
+        Some(-32090),
+        None,
+        RpcErrorClass::Retryable,
+    ),
+    (
+        "alchemy_compute_units",
+        None,
+        Some(r"compute units per second"),
+        RpcErrorClass::Retryable,
+    ),
+    (
+        "http_too_many_requests",
+        Some(429),
+        None,
+        RpcErrorClass::Retryable,
+    ),
+    (
+        "rate_limit",
+        None,
+        Some(r"rate.?limit|too many requests|request limit|daily request count"),
+        RpcErrorClass::Retryable,
+    ),
+    // Non-archive nodes keep the state of recent blocks only. Retrying the
+    // same block cannot succeed once it is pruned
+    (
+        "missing_trie_node",
+        None,
+        Some(r"missing trie node"),
+        RpcErrorClass::HistoryUnavailable,
+    ),
+    (
+        "state_pruned",
+        None,
+        Some(
+            r"historical state unavailable|(world )?state (is )?not available|state histor(y|ies) (is |are )?not available",
+        ),
+        RpcErrorClass::HistoryUnavailable,
+    ),
+    (
+        "header_not_found",
+        None,
+        Some(
+            r"header not found|unknown block|block not found|cannot be found|after last accepted block",
+        ),
+        RpcErrorClass::RewindAndRetry,
+    ),
+    (
+        "upstream_unavailable",
+        None,
+        Some(r"timeout|timed out|bad gateway|service unavailable|temporarily unavailable|upstream"),
+        RpcErrorClass::Retryable,
+    ),
+    // A JSON body of the wrong shape. A body that is not JSON at all, such
+    // as a gateway's error page, is left to the patterns above or retried
+    (
+        "invalid_response",
+        None,
+        Some(
+            r"deserialization error: (invalid (type|length|value)|missing field|unknown (variant|field))",
+        ),
+        RpcErrorClass::Fatal,
+    ),
+    ("execution_reverted", Some(3), None, RpcErrorClass::Fatal),
+    (
+        "execution_reverted",
+        None,
+        Some(r"execution reverted"),
+        RpcErrorClass::Fatal,
+    ),
+    ("method_not_found", Some(-32601), None, RpcErrorClass::Fatal),
+    ("invalid_params", Some(-32602), None, RpcErrorClass::Fatal),
+    (
+        "rejected_transaction",
+        None,
+        Some(r"nonce too low|already known|insufficient funds|replacement transaction underpriced"),
+        RpcErrorClass::Fatal,
+    ),
+];
+
+/// Built-in classification patterns for common node vendors
+pub fn default_rpc_error_patterns() -> Vec<RpcErrorPattern> {
+    DEFAULT_PATTERNS
+        .iter()
+        .map(|(name, code, message, class)| RpcErrorPattern {
+            name: (*name).to_owned(),
+            code: *code,
+            message: message.map(str::to_owned),
+            class: *class,
+        })
+        .collect()
+}
+
+/// Count of classified JSON-RPC errors by pattern and class, to spot errors
+/// no pattern covers. Agents register it with their metrics registry.
+pub static RPC_ERROR_CLASSIFICATIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        prometheus::Opts::new(
+            "rpc_error_classifications",
+            "JSON-RPC errors by the pattern that classified them",
+        )
+        .namespace("nomad"),
+        &["pattern", "class"],
+    )
+    .expect("invalid rpc error classification counter")
+});
+
+/// Classifier shared by providers built after agents load their settings
+static CONFIGURED: OnceCell<RpcErrorClassifier> = OnceCell::new();
+
+/// Classify JSON-RPC errors with `patterns` ahead of the built-in ones.
+/// Agents call this once they load their settings. Later calls are ignored.
+pub fn set_rpc_error_patterns(patterns: &[RpcErrorPattern]) -> Result<(), RpcErrorPatternError> {
+    let _ = CONFIGURED.set(RpcErrorClassifier::new(patterns)?);
+    Ok(())
+}
+
+/// The configured classifier, or the built-in one if agents set none
+pub fn rpc_error_classifier() -> &'static RpcErrorClassifier {
+    static DEFAULT: Lazy<RpcErrorClassifier> =
+        Lazy::new(|| RpcErrorClassifier::new(&[]).expect("invalid default rpc error pattern"));
+    CONFIGURED.get().unwrap_or(&DEFAULT)
+}
+
+/// Invalid classification pattern
+#[derive(Debug, thiserror::Error)]
+pub enum RpcErrorPatternError {
+    /// Pattern would match every error
+    #[error("RPC error pattern {0} has neither a code nor a message")]
+    Unbounded(String),
+    /// Message is not a valid regex
+    #[error("RPC error pattern {name} has an invalid message regex: {source}")]
+    InvalidRegex {
+        /// Pattern name
+        name: String,
+        /// Regex error
+        source: regex::Error,
+    },
+}
+
+#[derive(Debug)]
+struct CompiledPattern {
+    name: String,
+    code: Option<i64>,
+    message: Option<Regex>,
+    class: RpcErrorClass,
+}
+
+impl CompiledPattern {
+    fn matches(&self, code: Option<i64>, message: &str) -> bool {
+        self.code.map_or(true, |expected| code == Some(expected))
+            && self
+                .message
+                .as_ref()
+                .map_or(true, |re| re.is_match(message))
+    }
+}
+
+/// Ordered table of classification patterns
+#[derive(Debug)]
+pub struct RpcErrorClassifier {
+    patterns: Vec<CompiledPattern>,
+}
+
+impl RpcErrorClassifier {
+    /// Classifier checking `configured` patterns, then the built-in ones
+    pub fn new(configured: &[RpcErrorPattern]) -> Result<Self, RpcErrorPatternError> {
+        let patterns = configured
+            .iter()
+            .cloned()
+            .chain(default_rpc_error_patterns())
+            .map(|pattern| {
+                if pattern.code.is_none() && pattern.message.is_none() {
+                    return Err(RpcErrorPatternError::Unbounded(pattern.name));
+                }
+                let message = pattern
+                    .message
+                    .as_deref()
+                    .map(|re| RegexBuilder::new(re).case_insensitive(true).build())
+                    .transpose()
+                    .map_err(|source| RpcErrorPatternError::InvalidRegex {
+                        name: pattern.name.clone(),
+                        source,
+                    })?;
+                Ok(CompiledPattern {
+                    name: pattern.name,
+                    code: pattern.code,
+                    message,
+                    class: pattern.class,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// Name of the first pattern matching an error with `code` and
+    /// `message`, and its class. Counts the decision.
+    pub fn classify(&self, code: Option<i64>, message: &str) -> (&str, RpcErrorClass) {
+        let (name, class) = self
+            .patterns
+            .iter()
+            .find(|pattern| pattern.matches(code, message))
+            .map_or((UNCLASSIFIED, RpcErrorClass::Retryable), |pattern| {
+                (pattern.name.as_str(), pattern.class)
+            });
+        RPC_ERROR_CLASSIFICATIONS
+            .with_label_values(&[name, class.as_str()])
+            .inc();
+        (name, class)
+    }
+
+    /// Classify a provider error by the JSON-RPC code and message of the
+    /// response it holds, or else by its text
+    pub fn classify_error(&self, error: &(dyn StdError + 'static)) -> ClassifiedRpcError {
+        let (code, message) = match error.downcast_ref::<HttpClientError>() {
+            Some(HttpClientError::JsonRpcError(e)) => (Some(e.code), e.message.clone()),
+            Some(HttpClientError::ReqwestError(e)) => (
+                e.status().map(|status| status.as_u16() as i64),
+                e.to_string(),
+            ),
+            _ => (None, error.to_string()),
+        };
+        let (pattern, class) = self.classify(code, &message);
+        ClassifiedRpcError {
+            pattern: pattern.to_owned(),
+            class,
+            code,
+            message,
+        }
+    }
+}
+
+/// A JSON-RPC error and its classification
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message} (code {code:?}, classified {} by {pattern})", .class.as_str())]
+pub struct ClassifiedRpcError {
+    /// Name of the pattern that matched
+    pub pattern: String,
+    /// Class of the error
+    pub class: RpcErrorClass,
+    /// JSON-RPC error code, or HTTP status
+    pub code: Option<i64>,
+    /// Error message
+    pub message: String,
+}
+
+/// First classified JSON-RPC error among `error` and its sources, if any
+pub fn classified_rpc_error<'a>(
+    error: &'a (dyn StdError + 'static),
+) -> Option<&'a ClassifiedRpcError> {
+    let mut next = Some(error);
+    while let Some(error) = next {
+        if let Some(classified) = error.downcast_ref::<ClassifiedRpcError>() {
+            return Some(classified);
+        }
+        next = error.source();
+    }
+    None
+}
+
+/// Class of the first classified JSON-RPC error among `error` and its
+/// sources, if any
+pub fn rpc_error_class(error: &(dyn StdError + 'static)) -> Option<RpcErrorClass> {
+    classified_rpc_error(error).map(|classified| classified.class)
+}
+
+/// Whether `error` is a read of state the provider no longer keeps. Errors
+/// not classified yet are classified by the JSON-RPC error among their
+/// sources, and errors wrapped without their source by their text.
+pub fn is_history_unavailable(error: &(dyn StdError + 'static)) -> bool {
+    has_rpc_error_class(error, RpcErrorClass::HistoryUnavailable)
+}
+
+/// Whether `error` is a read of a block the provider has not seen yet, as
+/// when a load-balanced node lags the one that reported the block. Errors
+/// are classified as by [`is_history_unavailable`].
+pub fn is_block_unavailable(error: &(dyn StdError + 'static)) -> bool {
+    has_rpc_error_class(error, RpcErrorClass::RewindAndRetry)
+}
+
+fn has_rpc_error_class(error: &(dyn StdError + 'static), class: RpcErrorClass) -> bool {
+    if let Some(found) = rpc_error_class(error) {
+        return found == class;
+    }
+    let mut next = Some(error);
+    while let Some(error) = next {
+        if error.is::<HttpClientError>() {
+            return rpc_error_classifier().classify_error(error).class == class;
+        }
+        next = error.source();
+    }
+    rpc_error_classifier().classify(None, &error.to_string()).1 == class
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn class_of(code: Option<i64>, message: &str) -> (String, RpcErrorClass) {
+        let classifier = RpcErrorClassifier::new(&[]).unwrap();
+        let (name, class) = classifier.classify(code, message);
+        (name.to_owned(), class)
+    }
+
+    #[test]
+    fn it_classifies_vendor_errors() {
+        let cases = [
+            // Infura
+            (
+                Some(-32005),
+                "daily request count exceeded, request rate limited",
+                "infura_rate_limit",
+                RpcErrorClass::Retryable,
+            ),
+            (
+                Some(-32005),
+                "query returned more than 10000 results",
+                "logs_too_many_results",
+                RpcErrorClass::Fatal,
+            ),
+            (
+                Some(-32005),
+                "query returned more than 10000 results. Try with this block range [0x10D5E28, 0x10D6A07].",
+                "logs_range_too_large",
+                RpcErrorClass::RangeTooLarge,
+            ),
+            // Alchemy
+            (
+                Some(-32602),
+                "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range and no limit on the response size, or you can request any block range with a cap of 10K logs in the response.",
+                "logs_range_too_large",
+                RpcErrorClass::RangeTooLarge,
+            ),
+            (
+                Some(429),
+                "Your app has exceeded its compute units per second capacity. If you have retries enabled, you can safely ignore this message.",
+                "alchemy_compute_units",
+                RpcErrorClass::Retryable,
+            ),
+            (
+                Some(-32000),
+                "One of the blocks specified in filter (fromBlock, toBlock or blockHash) cannot be found.",
+                "header_not_found",
+                RpcErrorClass::RewindAndRetry,
+            ),
+            // QuickNode and Ankr
+            (
+                Some(-32614),
+                "eth_getLogs is limited to a 10,000 range",
+                "logs_range_too_large",
+                RpcErrorClass::RangeTooLarge,
+            ),
+            (
+                Some(-32600),
+                "block range is too wide",
+                "logs_range_too_large",
+                RpcErrorClass::RangeTooLarge,
+            ),
+            // BSC and Chainstack
+            (
+                Some(-32000),
+                "exceed maximum block range: 5000",
+                "logs_range_too_large",
+                RpcErrorClass::RangeTooLarge,
+            ),
+            (
+                Some(-32602),
+                "Block range limit exceeded.",
+                "logs_range_too_large",
+                RpcErrorClass::RangeTooLarge,
+            ),
+            (
+                Some(-32007),
+                "100/second request limit reached - reduce calls per second or upgrade your account at quicknode.com",
+                "quicknode_rate_limit",
+                RpcErrorClass::Retryable,
+            ),
+            (
+                Some(-32090),
+                "Too many requests, reason: call rate limit exhausted, retry in 10s",
+                "ankr_rate_limit",
+                RpcErrorClass::Retryable,
+            ),
+            // Geth and Erigon
+            (
+                Some(-32000),
+                "missing trie node 1b8a4e0e6d8bb3a3ef7a5e02ef3a4d3e2c9db1ae4dbd18a4a6ae4d1c2c1f0e6b (path )",
+                "missing_trie_node",
+                RpcErrorClass::HistoryUnavailable,
+            ),
+            (
+                Some(-32000),
+                "required historical state unavailable (reexec=128)",
+                "state_pruned",
+                RpcErrorClass::HistoryUnavailable,
+            ),
+            // Nethermind and Besu
+            (
+                Some(-32002),
+                "World state not available for block 0x1e2f3a",
+                "state_pruned",
+                RpcErrorClass::HistoryUnavailable,
+            ),
+            (
+                Some(-32000),
+                "header not found",
+                "header_not_found",
+                RpcErrorClass::RewindAndRetry,
+            ),
+            (
+                Some(3),
+                "execution reverted: !proven",
+                "execution_reverted",
+                RpcErrorClass::Fatal,
+            ),
+            (
+                Some(-32000),
+                "nonce too low",
+                "rejected_transaction",
+                RpcErrorClass::Fatal,
+            ),
+            (
+                Some(-32601),
+                "the method eth_feeHistory does not exist/is not available",
+                "method_not_found",
+                RpcErrorClass::Fatal,
+            ),
+            // Gateways answering without a JSON-RPC body
+            (
+                None,
+                "Deserialization Error: expected value at line 1 column 1. Response: Too Many Requests",
+                "rate_limit",
+                RpcErrorClass::Retryable,
+            ),
+            (
+                Some(503),
+                "HTTP status server error (503 Service Unavailable) for url (https://rpc.example.com/)",
+                "upstream_unavailable",
+                RpcErrorClass::Retryable,
+            ),
+            (
+                None,
+                "Deserialization Error: invalid type: string \"0x\", expected a sequence at line 1 column 4. Response: \"0x\"",
+                "invalid_response",
+                RpcErrorClass::Fatal,
+            ),
+            // Nothing matches
+            (
+                Some(-32000),
+                "something unheard of",
+                UNCLASSIFIED,
+                RpcErrorClass::Retryable,
+            ),
+        ];
+
+        for (code, message, name, class) in cases {
+            assert_eq!(
+                class_of(code, message),
+                (name.to_owned(), class),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn configured_patterns_take_precedence() {
+        let configured: Vec<RpcErrorPattern> = serde_json::from_value(serde_json::json!([
+            {"name": "vendor_lagging", "code": -32000, "message": "^state unavailable", "class": "rewindAndRetry"},
+            {"name": "revert_retry", "message": "execution reverted: busy", "class": "retryable"},
+        ]))
+        .unwrap();
+        let classifier = RpcErrorClassifier::new(&configured).unwrap();
+
+        assert_eq!(
+            classifier.classify(Some(-32000), "State unavailable for block 0x10"),
+            ("vendor_lagging", RpcErrorClass::RewindAndRetry)
+        );
+        // Code must match too
+        assert_eq!(
+            classifier
+                .classify(Some(-32001), "state unavailable for block 0x10")
+                .0,
+            UNCLASSIFIED
+        );
+        assert_eq!(
+            classifier.classify(Some(3), "execution reverted: busy"),
+            ("revert_retry", RpcErrorClass::Retryable)
+        );
+        assert_eq!(
+            classifier.classify(Some(3), "execution reverted: !proven"),
+            ("execution_reverted", RpcErrorClass::Fatal)
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_patterns() {
+        let pattern = |code, message: Option<&str>| RpcErrorPattern {
+            name: "bad".to_owned(),
+            code,
+            message: message.map(str::to_owned),
+            class: RpcErrorClass::Fatal,
+        };
+        assert!(matches!(
+            RpcErrorClassifier::new(&[pattern(None, None)]),
+            Err(RpcErrorPatternError::Unbounded(_))
+        ));
+        assert!(matches!(
+            RpcErrorClassifier::new(&[pattern(None, Some("(unclosed"))]),
+            Err(RpcErrorPatternError::InvalidRegex { .. })
+        ));
+        assert!(RpcErrorClassifier::new(&[pattern(Some(-32000), None)]).is_ok());
+    }
+
+    #[test]
+    fn it_counts_decisions_and_finds_classified_sources() {
+        let before = RPC_ERROR_CLASSIFICATIONS
+            .with_label_values(&["header_not_found", "rewindAndRetry"])
+            .get();
+        let classified = rpc_error_classifier().classify_error(&std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "header not found",
+        ));
+        assert_eq!(classified.class, RpcErrorClass::RewindAndRetry);
+        assert!(
+            RPC_ERROR_CLASSIFICATIONS
+                .with_label_values(&["header_not_found", "rewindAndRetry"])
+                .get()
+                > before
+        );
+
+        #[derive(Debug, thiserror::Error)]
+        #[error("indexing failed")]
+        struct Wrapper(#[source] ClassifiedRpcError);
+
+        assert_eq!(
+            rpc_error_class(&Wrapper(classified)),
+            Some(RpcErrorClass::RewindAndRetry)
+        );
+        assert_eq!(
+            rpc_error_class(&std::io::Error::new(std::io::ErrorKind::Other, "x")),
+            None
+        );
+    }
+
+    #[test]
+    fn it_detects_pruned_state_through_wrappers() {
+        let io_error =
+            |message: &str| std::io::Error::new(std::io::ErrorKind::Other, message.to_owned());
+
+        let classified =
+            rpc_error_classifier().classify_error(&io_error("missing trie node 0x12 (path )"));
+        assert_eq!(classified.class, RpcErrorClass::HistoryUnavailable);
+
+        #[derive(Debug, thiserror::Error)]
+        #[error("contract call failed: {0}")]
+        struct Sourceless(String);
+
+        // Contract errors keep the message but not the source
+        assert!(is_history_unavailable(&Sourceless(classified.to_string())));
+        assert!(is_history_unavailable(&classified));
+        assert!(!is_history_unavailable(&io_error("header not found")));
+        assert!(!is_history_unavailable(&io_error("execution reverted")));
+
+        assert!(is_block_unavailable(&Sourceless(
+            "header not found".to_owned()
+        )));
+        assert!(!is_block_unavailable(&classified));
+    }
+}
@@ -0,0 +1,314 @@
+use ethers::{
+    core::abi::{Abi, Function, Token},
+    types::{Address, U256},
+    utils::to_checksum,
+};
+use nomad_ethereum_bindings::prelude::{HOME_ABI, REPLICA_ABI, XAPPCONNECTIONMANAGER_ABI};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Version of the Safe transaction builder batch format
+pub const SAFE_BATCH_VERSION: &str = "1.0";
+
+/// Transaction builder version recorded in exported batches
+pub const SAFE_TX_BUILDER_VERSION: &str = "1.11.1";
+
+/// Batch of transactions in the Safe transaction builder's JSON format, for
+/// importing into a Safe on the chain with id `chain_id`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafeBatch {
+    /// Format version
+    pub version: String,
+    /// Chain id as a decimal string
+    pub chain_id: String,
+    /// Creation time in milliseconds since the epoch
+    pub created_at: u64,
+    /// Batch metadata
+    pub meta: SafeBatchMeta,
+    /// Transactions in execution order
+    pub transactions: Vec<SafeTransaction>,
+}
+
+/// Metadata of a `SafeBatch`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafeBatchMeta {
+    /// Batch name shown by the transaction builder
+    pub name: String,
+    /// Batch description
+    pub description: String,
+    /// Transaction builder version
+    pub tx_builder_version: String,
+    /// Safe the batch is meant for. Empty if unknown
+    pub created_from_safe_address: String,
+    /// Owner creating the batch. Empty if unknown
+    pub created_from_owner_address: String,
+}
+
+/// Transaction of a `SafeBatch`. Calls to the core contracts carry their
+/// decoded method and arguments, which the transaction builder encodes into
+/// the same calldata as `data`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafeTransaction {
+    /// Target contract
+    pub to: String,
+    /// Wei sent with the call, as a decimal string
+    pub value: String,
+    /// Calldata as 0x-prefixed hex
+    pub data: String,
+    /// Called method, if its selector is known
+    pub contract_method: Option<SafeContractMethod>,
+    /// Arguments of the called method by name, if its selector is known
+    pub contract_inputs_values: Option<BTreeMap<String, String>>,
+}
+
+/// Method of a `SafeTransaction`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SafeContractMethod {
+    /// Method name
+    pub name: String,
+    /// Method parameters
+    pub inputs: Vec<SafeContractInput>,
+    /// Whether the method accepts value
+    pub payable: bool,
+}
+
+/// Parameter of a `SafeContractMethod`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafeContractInput {
+    /// Parameter name
+    pub name: String,
+    /// ABI type
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// ABI type, as the transaction builder shows it
+    pub internal_type: String,
+}
+
+impl SafeTransaction {
+    /// Call of `data` on `to` without value. The method and arguments are
+    /// decoded against the core contract ABIs.
+    pub fn new(to: Address, data: &[u8]) -> Self {
+        let decoded = known_function(data).and_then(|function| {
+            let tokens = function.decode_input(&data[4..]).ok()?;
+            Some((function, tokens))
+        });
+
+        let (contract_method, contract_inputs_values) = match decoded {
+            Some((function, tokens)) => {
+                let method = SafeContractMethod {
+                    name: function.name.clone(),
+                    inputs: function
+                        .inputs
+                        .iter()
+                        .map(|param| SafeContractInput {
+                            name: param.name.clone(),
+                            kind: param.kind.to_string(),
+                            internal_type: param.kind.to_string(),
+                        })
+                        .collect(),
+                    payable: false,
+                };
+                let values = function
+                    .inputs
+                    .iter()
+                    .zip(tokens.iter())
+                    .map(|(param, token)| (param.name.clone(), token_value(token)))
+                    .collect();
+                (Some(method), Some(values))
+            }
+            None => (None, None),
+        };
+
+        Self {
+            to: to_checksum(&to, None),
+            value: U256::zero().to_string(),
+            data: format!("0x{}", hex::encode(data)),
+            contract_method,
+            contract_inputs_values,
+        }
+    }
+}
+
+impl SafeBatch {
+    /// Batch of `transactions` on the chain with id `chain_id`, created now
+    pub fn new(
+        chain_id: u64,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        transactions: Vec<SafeTransaction>,
+    ) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("!time")
+            .as_millis() as u64;
+        Self {
+            version: SAFE_BATCH_VERSION.to_owned(),
+            chain_id: chain_id.to_string(),
+            created_at,
+            meta: SafeBatchMeta {
+                name: name.into(),
+                description: description.into(),
+                tx_builder_version: SAFE_TX_BUILDER_VERSION.to_owned(),
+                created_from_safe_address: String::new(),
+                created_from_owner_address: String::new(),
+            },
+            transactions,
+        }
+    }
+
+    /// Record the Safe the batch is meant for
+    pub fn for_safe(mut self, safe: Address) -> Self {
+        self.meta.created_from_safe_address = to_checksum(&safe, None);
+        self
+    }
+}
+
+/// Function of the core contract ABIs matching the selector of `data`
+fn known_function(data: &[u8]) -> Option<&'static Function> {
+    if data.len() < 4 {
+        return None;
+    }
+    let abis: [&'static Abi; 3] = [&XAPPCONNECTIONMANAGER_ABI, &HOME_ABI, &REPLICA_ABI];
+    abis.into_iter()
+        .flat_map(Abi::functions)
+        .find(|function| function.short_signature() == data[..4])
+}
+
+/// Argument as the transaction builder takes it: decimal numbers, checksummed
+/// addresses, 0x-prefixed bytes, and arrays and tuples as JSON arrays
+fn token_value(token: &Token) -> String {
+    match token {
+        Token::Address(address) => to_checksum(address, None),
+        Token::Uint(value) | Token::Int(value) => value.to_string(),
+        Token::Bool(value) => value.to_string(),
+        Token::String(value) => value.clone(),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => format!("0x{}", hex::encode(bytes)),
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            serde_json::to_string(&tokens.iter().map(token_value).collect::<Vec<_>>())
+                .expect("!serialize")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decode_known_calldata;
+    use ethers::core::abi::token::{LenientTokenizer, Tokenizer};
+    use serde_json::Value;
+
+    fn fixture_calls() -> Vec<(Address, Vec<u8>)> {
+        let fixture = std::fs::read_to_string("../../fixtures/governance_batch.json").unwrap();
+        let fixture: Value = serde_json::from_str(&fixture).unwrap();
+        let hex_field =
+            |value: &Value| hex::decode(value.as_str().unwrap().trim_start_matches("0x")).unwrap();
+        fixture["calls"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|call| {
+                let to = hex_field(&call["to"]);
+                (Address::from_slice(&to[12..]), hex_field(&call["data"]))
+            })
+            .collect()
+    }
+
+    fn fixture_batch() -> SafeBatch {
+        let transactions = fixture_calls()
+            .iter()
+            .map(|(to, data)| SafeTransaction::new(*to, data))
+            .collect();
+        SafeBatch::new(1, "governance batch", "fixture calls", transactions)
+            .for_safe(Address::repeat_byte(0x11))
+    }
+
+    #[test]
+    fn it_matches_the_transaction_builder_schema() {
+        let schema =
+            std::fs::read_to_string("../../fixtures/safe_transaction_batch.schema.json").unwrap();
+        let schema: Value = serde_json::from_str(&schema).unwrap();
+        let schema = jsonschema::JSONSchema::compile(&schema).unwrap();
+
+        let batch = serde_json::to_value(&fixture_batch()).unwrap();
+        if let Err(errors) = schema.validate(&batch) {
+            let errors: Vec<_> = errors.map(|error| error.to_string()).collect();
+            panic!("batch does not match the schema: {:?}", errors);
+        }
+        assert_eq!(batch["chainId"], "1");
+        assert_eq!(
+            batch["meta"]["createdFromSafeAddress"],
+            to_checksum(&Address::repeat_byte(0x11), None)
+        );
+
+        // An unknown call still carries its calldata
+        let mut unknown = batch;
+        unknown["transactions"][0]["data"] = Value::Null;
+        assert!(!schema.is_valid(&unknown));
+    }
+
+    #[test]
+    fn it_decodes_and_reencodes_exported_calldata() {
+        let calls = fixture_calls();
+        let batch = fixture_batch();
+        assert_eq!(batch.transactions.len(), calls.len());
+
+        for ((to, data), tx) in calls.iter().zip(batch.transactions.iter()) {
+            assert_eq!(tx.to.parse::<Address>().unwrap(), *to);
+            assert_eq!(tx.value, "0");
+            let exported = hex::decode(tx.data.trim_start_matches("0x")).unwrap();
+            assert_eq!(&exported, data);
+
+            let method = match &tx.contract_method {
+                Some(method) => method,
+                None => {
+                    // BridgeRouter is not a known ABI
+                    assert!(decode_known_calldata(&exported).is_none());
+                    assert!(tx.contract_inputs_values.is_none());
+                    continue;
+                }
+            };
+            let decoded = decode_known_calldata(&exported).unwrap();
+            assert!(
+                decoded
+                    .to_lowercase()
+                    .starts_with(&method.name.to_lowercase()),
+                "{} is not {}",
+                decoded,
+                method.name
+            );
+
+            // The builder encodes the method and arguments. They must give
+            // back the exported calldata.
+            let values = tx.contract_inputs_values.as_ref().unwrap();
+            let function = known_function(&exported).unwrap();
+            let tokens: Vec<_> = function
+                .inputs
+                .iter()
+                .map(|param| LenientTokenizer::tokenize(&param.kind, &values[&param.name]).unwrap())
+                .collect();
+            assert_eq!(function.encode_input(&tokens).unwrap(), exported);
+        }
+
+        let enroll = &batch.transactions[0];
+        assert_eq!(
+            enroll.contract_method.as_ref().unwrap().name,
+            "ownerEnrollReplica"
+        );
+        assert_eq!(
+            enroll.contract_inputs_values.as_ref().unwrap()["_domain"],
+            "1650811245"
+        );
+        let permission = &batch.transactions[1];
+        assert_eq!(
+            permission.contract_inputs_values.as_ref().unwrap()["_access"],
+            "true"
+        );
+    }
+}
@@ -125,8 +125,23 @@ impl Signer for EthereumSigners {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::Update;
-    use ethers::types::H256;
+    use crate::{CanonicalSignature, SignedUpdate, Update};
+    use ethers::{signers::LocalWallet, types::H256};
+
+    /// Address of the private key `0x1111...1111`
+    const UPDATER: &str = "0x19e7e376e7c213b7e7e7e46cc70a5dd086daff2a";
+
+    async fn signed_update() -> SignedUpdate {
+        let signer: LocalWallet = "1".repeat(64).parse().unwrap();
+        Update {
+            home_domain: 1000,
+            previous_root: H256::repeat_byte(1),
+            new_root: H256::repeat_byte(2),
+        }
+        .sign_with(&signer)
+        .await
+        .unwrap()
+    }
 
     #[test]
     fn it_sign() {
@@ -151,4 +166,31 @@ mod test {
             .unwrap()
             .block_on(t)
     }
+
+    #[tokio::test]
+    async fn it_recovers_the_updater_from_full_and_compact_signatures() {
+        let updater: EthAddress = UPDATER.parse().unwrap();
+        let signed = signed_update().await;
+        assert_eq!(signed.recover().unwrap(), updater);
+        signed.verify(updater).unwrap();
+
+        let compact = signed.signature.to_compact();
+        let from_compact = SignedUpdate {
+            update: signed.update,
+            signature: CanonicalSignature::try_from(compact.as_ref()).unwrap(),
+        };
+        assert_eq!(from_compact, signed);
+        assert_eq!(from_compact.recover().unwrap(), updater);
+        from_compact.verify(updater).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_rejects_updates_with_a_tampered_root() {
+        let updater: EthAddress = UPDATER.parse().unwrap();
+        let mut tampered = signed_update().await;
+        tampered.update.new_root = H256::repeat_byte(3);
+
+        assert_ne!(tampered.recover().unwrap(), updater);
+        assert!(tampered.verify(updater).is_err());
+    }
 }
@@ -0,0 +1,269 @@
+use ethers::{
+    abi::{AbiDecode, Token},
+    prelude::*,
+    providers::HttpClientError,
+    types::transaction::eip2718::TypedTransaction,
+    utils::keccak256,
+};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+use crate::EthereumError;
+
+/// Selector of `Error(string)`
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Selector of `Panic(uint256)`
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Result of a simulated call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Simulation {
+    /// The call would succeed, returning this data
+    Success(Bytes),
+    /// The call would revert with this decoded reason
+    Revert(String),
+}
+
+/// Storage overrides applied to a simulated call, by contract and slot
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateOverrides(BTreeMap<Address, BTreeMap<H256, H256>>);
+
+impl StateOverrides {
+    /// Whether there are no overrides
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Override `slot` of `contract` with `value`
+    pub fn set_storage(&mut self, contract: Address, slot: H256, value: H256) {
+        self.0.entry(contract).or_default().insert(slot, value);
+    }
+
+    /// Override the entry for `key` of a `bytes32`-keyed mapping declared at
+    /// `mapping_slot`
+    pub fn set_mapping_entry(
+        &mut self,
+        contract: Address,
+        mapping_slot: u64,
+        key: H256,
+        value: H256,
+    ) {
+        let slot = keccak256(
+            [
+                key.as_bytes(),
+                H256::from_low_u64_be(mapping_slot).as_bytes(),
+            ]
+            .concat(),
+        );
+        self.set_storage(contract, slot.into(), value);
+    }
+
+    /// The `eth_call` state override parameter
+    pub fn to_json(&self) -> Value {
+        let accounts: serde_json::Map<String, Value> = self
+            .0
+            .iter()
+            .map(|(contract, slots)| {
+                let diff: serde_json::Map<String, Value> = slots
+                    .iter()
+                    .map(|(slot, value)| (format!("{:?}", slot), json!(format!("{:?}", value))))
+                    .collect();
+                (format!("{:?}", contract), json!({ "stateDiff": diff }))
+            })
+            .collect();
+        Value::Object(accounts)
+    }
+}
+
+/// Decode revert data into a readable reason. `Error(string)` and
+/// `Panic(uint256)` are decoded, and other errors are shown raw.
+pub fn decode_revert_reason(data: &[u8]) -> String {
+    if data.is_empty() {
+        return "reverted without a reason".to_owned();
+    }
+    if data.len() >= 4 && data[..4] == ERROR_SELECTOR {
+        if let Ok(reason) = String::decode(&data[4..]) {
+            return reason;
+        }
+    }
+    if data.len() >= 4 && data[..4] == PANIC_SELECTOR {
+        if let Ok(code) = U256::decode(&data[4..]) {
+            return format!("panic 0x{:x}", code);
+        }
+    }
+    format!("custom error 0x{}", hex::encode(data))
+}
+
+/// Revert reason from a JSON-RPC error's message and data, or `None` if the
+/// error is not a revert. Nodes return revert data as a hex string or
+/// nested under `data`, and some put the reason in the message only.
+pub fn revert_reason_from_rpc(message: &str, data: Option<&Value>) -> Option<String> {
+    let hex_data = match data {
+        Some(Value::String(s)) => Some(s.as_str()),
+        Some(Value::Object(o)) => o.get("data").and_then(Value::as_str),
+        _ => None,
+    };
+    if let Some(bytes) = hex_data.and_then(|s| hex::decode(s.trim_start_matches("0x")).ok()) {
+        return Some(decode_revert_reason(&bytes));
+    }
+
+    let message = message.trim();
+    let reason = message.strip_prefix("execution reverted")?;
+    let reason = reason.trim_start_matches(':').trim();
+    if reason.is_empty() {
+        Some("reverted without a reason".to_owned())
+    } else {
+        Some(reason.to_owned())
+    }
+}
+
+fn revert_reason_from_provider_error(error: &ProviderError) -> Option<String> {
+    match error {
+        ProviderError::JsonRpcClientError(inner) => match inner.downcast_ref::<HttpClientError>() {
+            Some(HttpClientError::JsonRpcError(e)) => {
+                revert_reason_from_rpc(&e.message, e.data.as_ref())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Simulate `tx` with `eth_call` at the latest block. Never sends a
+/// transaction. Reverts are returned as [`Simulation::Revert`] and other
+/// failures as errors.
+pub async fn simulate_call<M: Middleware>(
+    client: &M,
+    tx: &TypedTransaction,
+    overrides: &StateOverrides,
+) -> Result<Simulation, EthereumError> {
+    let mut params = vec![
+        serde_json::to_value(tx).map_err(|e| EthereumError::CustomError(e.into()))?,
+        json!("latest"),
+    ];
+    if !overrides.is_empty() {
+        params.push(overrides.to_json());
+    }
+
+    match client
+        .provider()
+        .request::<_, Bytes>("eth_call", params)
+        .await
+    {
+        Ok(data) => Ok(Simulation::Success(data)),
+        Err(e) => match revert_reason_from_provider_error(&e) {
+            Some(reason) => Ok(Simulation::Revert(reason)),
+            None => Err(e.into()),
+        },
+    }
+}
+
+/// Calldata of `IMessageRecipient.handle(origin, nonce, sender, body)`, the
+/// call a replica makes to a message's recipient
+pub fn handle_calldata(origin: u32, nonce: u32, sender: H256, body: &[u8]) -> Bytes {
+    let selector = &keccak256("handle(uint32,uint32,bytes32,bytes)")[..4];
+    let args = ethers::abi::encode(&[
+        Token::Uint(origin.into()),
+        Token::Uint(nonce.into()),
+        Token::FixedBytes(sender.as_bytes().to_vec()),
+        Token::Bytes(body.to_vec()),
+    ]);
+    [selector, &args].concat().into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::abi::AbiEncode;
+
+    #[test]
+    fn it_decodes_revert_reasons() {
+        let error = [&ERROR_SELECTOR[..], &"!proven".to_owned().encode()].concat();
+        assert_eq!(decode_revert_reason(&error), "!proven");
+
+        let panic = [&PANIC_SELECTOR[..], &U256::from(0x11).encode()].concat();
+        assert_eq!(decode_revert_reason(&panic), "panic 0x11");
+
+        assert_eq!(decode_revert_reason(&[]), "reverted without a reason");
+        assert_eq!(
+            decode_revert_reason(&[0xde, 0xad, 0xbe, 0xef]),
+            "custom error 0xdeadbeef"
+        );
+        // a truncated Error(string) is shown raw
+        assert_eq!(
+            decode_revert_reason(&error[..8]),
+            format!("custom error 0x{}", hex::encode(&error[..8]))
+        );
+    }
+
+    #[test]
+    fn it_reads_revert_reasons_from_rpc_errors() {
+        let error = format!(
+            "0x{}",
+            hex::encode([&ERROR_SELECTOR[..], &"!prove".to_owned().encode()].concat())
+        );
+
+        // geth and anvil
+        assert_eq!(
+            revert_reason_from_rpc("execution reverted: !prove", Some(&json!(error))),
+            Some("!prove".to_owned())
+        );
+        // nested data
+        assert_eq!(
+            revert_reason_from_rpc("VM Exception", Some(&json!({ "data": error }))),
+            Some("!prove".to_owned())
+        );
+        // message only
+        assert_eq!(
+            revert_reason_from_rpc("execution reverted: !gas", None),
+            Some("!gas".to_owned())
+        );
+        assert_eq!(
+            revert_reason_from_rpc("execution reverted", None),
+            Some("reverted without a reason".to_owned())
+        );
+        // not a revert
+        assert_eq!(revert_reason_from_rpc("header not found", None), None);
+    }
+
+    #[test]
+    fn it_overrides_mapping_entries() {
+        let replica = Address::repeat_byte(1);
+        let root = H256::repeat_byte(2);
+        let mut overrides = StateOverrides::default();
+        assert!(overrides.is_empty());
+
+        overrides.set_mapping_entry(replica, 151, root, H256::from_low_u64_be(1));
+        let slot: H256 =
+            keccak256([root.as_bytes(), H256::from_low_u64_be(151).as_bytes()].concat()).into();
+
+        let json = overrides.to_json();
+        assert_eq!(
+            json[format!("{:?}", replica)]["stateDiff"][format!("{:?}", slot)],
+            json!(format!("{:?}", H256::from_low_u64_be(1)))
+        );
+    }
+
+    #[test]
+    fn it_encodes_handle_calls() {
+        let calldata = handle_calldata(1000, 7, H256::repeat_byte(3), &[]);
+        assert_eq!(
+            &calldata[..4],
+            &keccak256("handle(uint32,uint32,bytes32,bytes)")[..4]
+        );
+
+        let tokens = ethers::abi::decode(
+            &[
+                ethers::abi::ParamType::Uint(32),
+                ethers::abi::ParamType::Uint(32),
+                ethers::abi::ParamType::FixedBytes(32),
+                ethers::abi::ParamType::Bytes,
+            ],
+            &calldata[4..],
+        )
+        .unwrap();
+        assert_eq!(tokens[0], Token::Uint(1000.into()));
+        assert_eq!(tokens[1], Token::Uint(7.into()));
+        assert_eq!(tokens[3], Token::Bytes(vec![]));
+    }
+}
@@ -0,0 +1,127 @@
+use nomad_core::State;
+use std::convert::TryFrom;
+
+use crate::EthereumError;
+
+/// State of a replica, as its `NomadBase.States`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicaState {
+    /// Not yet initialized
+    Uninitialized,
+    /// Accepting updates and processing messages
+    Active,
+    /// Failed by a watcher's double update proof. Accepts no more updates.
+    Failed,
+}
+
+impl TryFrom<u8> for ReplicaState {
+    type Error = EthereumError;
+
+    fn try_from(state: u8) -> Result<Self, Self::Error> {
+        match state {
+            0 => Ok(Self::Uninitialized),
+            1 => Ok(Self::Active),
+            2 => Ok(Self::Failed),
+            _ => Err(EthereumError::InvalidState {
+                contract: "Replica",
+                state,
+            }),
+        }
+    }
+}
+
+impl From<ReplicaState> for State {
+    fn from(state: ReplicaState) -> Self {
+        match state {
+            ReplicaState::Uninitialized => State::Uninitialized,
+            ReplicaState::Active => State::Active,
+            ReplicaState::Failed => State::Failed,
+        }
+    }
+}
+
+/// State of a home, as its `NomadBase.States`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HomeState {
+    /// Not yet initialized
+    Uninitialized,
+    /// Dispatching messages and accepting updates
+    Active,
+    /// Failed by an improper update. Dispatches no more messages.
+    Failed,
+}
+
+impl TryFrom<u8> for HomeState {
+    type Error = EthereumError;
+
+    fn try_from(state: u8) -> Result<Self, Self::Error> {
+        match state {
+            0 => Ok(Self::Uninitialized),
+            1 => Ok(Self::Active),
+            2 => Ok(Self::Failed),
+            _ => Err(EthereumError::InvalidState {
+                contract: "Home",
+                state,
+            }),
+        }
+    }
+}
+
+impl From<HomeState> for State {
+    fn from(state: HomeState) -> Self {
+        match state {
+            HomeState::Uninitialized => State::Uninitialized,
+            HomeState::Active => State::Active,
+            HomeState::Failed => State::Failed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_maps_contract_states() {
+        for (state, replica, home, common) in [
+            (
+                0,
+                ReplicaState::Uninitialized,
+                HomeState::Uninitialized,
+                State::Uninitialized,
+            ),
+            (1, ReplicaState::Active, HomeState::Active, State::Active),
+            (2, ReplicaState::Failed, HomeState::Failed, State::Failed),
+        ] {
+            assert_eq!(ReplicaState::try_from(state).unwrap(), replica);
+            assert_eq!(HomeState::try_from(state).unwrap(), home);
+            assert_eq!(State::from(replica), common);
+            assert_eq!(State::from(home), common);
+        }
+    }
+
+    #[test]
+    fn it_rejects_out_of_range_states() {
+        for state in [3, u8::MAX] {
+            let err = ReplicaState::try_from(state).unwrap_err();
+            assert!(matches!(
+                err,
+                EthereumError::InvalidState {
+                    contract: "Replica",
+                    state: s,
+                } if s == state
+            ));
+            assert_eq!(
+                err.to_string(),
+                format!("Replica reported invalid state {}", state)
+            );
+            assert!(matches!(
+                HomeState::try_from(state).unwrap_err(),
+                EthereumError::InvalidState {
+                    contract: "Home",
+                    ..
+                }
+            ));
+        }
+    }
+}
@@ -1,8 +1,16 @@
-use crate::{EthereumError, SingleChainGelatoClient};
+use crate::{
+    gas::fill_gas_limit, simulate_call, EthereumError, ReceiptStrategy, Simulation,
+    SingleChainGelatoClient, StateOverrides,
+};
+use async_trait::async_trait;
 use color_eyre::Result;
+use ethers::abi::Detokenize;
 use ethers::prelude::*;
 use ethers::types::transaction::eip2718::TypedTransaction;
-use nomad_core::TxOutcome;
+use nomad_core::{
+    capturing_submission, record_submission, FeeDecision, StoredCalldata, TxOutcome, TxPayload,
+};
+use nomad_xyz_configuration::GasConfig;
 use std::sync::Arc;
 
 /// Component responsible for submitting transactions to the chain. Can
@@ -13,6 +21,8 @@ pub enum SubmitterClient<M> {
     Local(Arc<M>),
     /// Pass meta txs to Gelato relay service
     Gelato(Arc<SingleChainGelatoClient<M>>),
+    /// Refuse to submit. For read-only uses such as simulations
+    ReadOnly,
 }
 
 impl<M> From<Arc<M>> for SubmitterClient<M> {
@@ -32,6 +42,10 @@ impl<M> From<SingleChainGelatoClient<M>> for SubmitterClient<M> {
 pub struct TxSubmitter<M> {
     /// Tx submitter client
     pub client: SubmitterClient<M>,
+    /// How local submissions await receipts
+    pub receipts: ReceiptStrategy,
+    /// How gas limits of local submissions are estimated
+    pub gas: GasConfig,
 }
 
 impl<M> TxSubmitter<M>
@@ -40,7 +54,41 @@ where
 {
     /// Create new TxSubmitter from submitter
     pub fn new(client: SubmitterClient<M>) -> Self {
-        Self { client }
+        Self {
+            client,
+            receipts: Default::default(),
+            gas: Default::default(),
+        }
+    }
+
+    /// Set the receipt strategy for local submissions. Gelato tasks are
+    /// tracked by the relay.
+    pub fn with_receipts(mut self, receipts: ReceiptStrategy) -> Self {
+        self.receipts = receipts;
+        self
+    }
+
+    /// Set the chain's gas settings for estimating gas limits
+    pub fn with_gas(mut self, gas: GasConfig) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    /// Submit transaction to chain. Local submissions without a gas limit
+    /// get the estimate adjusted by `gas`, and are refused if that exceeds
+    /// its cap. Gelato estimates its own limits.
+    pub async fn submit_with_gas(
+        &self,
+        domain: u32,
+        contract_address: Address,
+        tx: impl Into<TypedTransaction>,
+        gas: &GasConfig,
+    ) -> Result<TxOutcome, EthereumError> {
+        let mut tx: TypedTransaction = tx.into();
+        if let SubmitterClient::Local(client) = &self.client {
+            fill_gas_limit(client.as_ref(), &mut tx, gas).await?;
+        }
+        self.submit(domain, contract_address, tx).await
     }
 
     /// Submit transaction to chain
@@ -50,13 +98,244 @@ where
         contract_address: Address,
         tx: impl Into<TypedTransaction>,
     ) -> Result<TxOutcome, EthereumError> {
-        let tx: TypedTransaction = tx.into();
+        let mut tx: TypedTransaction = tx.into();
 
         match &self.client {
-            SubmitterClient::Local(client) => report_tx!(tx, client,),
-            SubmitterClient::Gelato(client) => Ok(client
-                .submit_blocking(domain, contract_address, &tx)
-                .await?),
+            SubmitterClient::Local(client) => {
+                if capturing_submission() {
+                    Self::fill_and_record(client, &mut tx).await?;
+                }
+                report_tx!(tx, client, self.receipts)
+            }
+            SubmitterClient::Gelato(client) => {
+                if capturing_submission() {
+                    record_submission(tx_payload(&tx));
+                }
+                Ok(client
+                    .submit_blocking(domain, contract_address, &tx)
+                    .await?)
+            }
+            SubmitterClient::ReadOnly => Err(EthereumError::ReadOnlySubmitter),
+        }
+    }
+
+    /// Fill `tx` as it will be sent, simulate it and record its payload for
+    /// the captured submission. A transaction that fails to fill, e.g. as
+    /// gas estimation reverts, is recorded unfilled.
+    async fn fill_and_record(client: &M, tx: &mut TypedTransaction) -> Result<(), EthereumError> {
+        let network_gas_price = client.provider().get_gas_price().await.ok();
+        let filled = client
+            .fill_transaction(tx, None)
+            .await
+            .map_err(|e| EthereumError::MiddlewareError(e.into()));
+
+        let simulation = match simulate_call(client, tx, &StateOverrides::default()).await {
+            Ok(Simulation::Success(data)) => format!("success 0x{}", hex::encode(data)),
+            Ok(Simulation::Revert(reason)) => format!("revert: {}", reason),
+            Err(e) => format!("simulation failed: {}", e),
+        };
+        let mut payload = tx_payload(tx).with_simulation(&simulation);
+        payload.fee = network_gas_price.map(|network_gas_price| FeeDecision {
+            network_gas_price,
+            gas_price: tx.gas_price(),
+        });
+        record_submission(payload);
+
+        filled
+    }
+}
+
+/// Payload of `tx` as it stands, without simulation or fee decision
+pub fn tx_payload(tx: &TypedTransaction) -> TxPayload {
+    let (gas_price, max_fee_per_gas, max_priority_fee_per_gas) = match tx {
+        TypedTransaction::Eip1559(tx) => (None, tx.max_fee_per_gas, tx.max_priority_fee_per_gas),
+        _ => (tx.gas_price(), None, None),
+    };
+    let to = match tx.to() {
+        Some(NameOrAddress::Address(to)) => Some(*to),
+        _ => None,
+    };
+
+    TxPayload {
+        to,
+        from: tx.from().copied(),
+        nonce: tx.nonce().copied(),
+        gas: tx.gas().copied(),
+        gas_price,
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+        value: tx.value().copied(),
+        chain_id: tx.chain_id().map(|id| id.as_u64()),
+        calldata: StoredCalldata::new(tx.data().map(|data| data.as_ref()).unwrap_or_default()),
+        simulation: None,
+        fee: None,
+    }
+}
+
+/// Contract wrappers submitting calls with estimated gas limits
+#[async_trait]
+pub trait SubmitWithGas<W>: Sync
+where
+    W: Middleware + 'static,
+{
+    /// Submitter of the contract's transactions
+    fn tx_submitter(&self) -> &TxSubmitter<W>;
+
+    /// Domain and address of the contract
+    fn contract_location(&self) -> (u32, Address);
+
+    /// Submit `call`. Unless the call sets a gas limit, its estimate is
+    /// adjusted by `cfg`, and nothing is submitted if that exceeds the cap.
+    async fn submit_with_gas<M, D>(
+        &self,
+        call: ContractCall<M, D>,
+        cfg: &GasConfig,
+    ) -> Result<TxOutcome, EthereumError>
+    where
+        M: Middleware + 'static,
+        D: Detokenize + Send + Sync + 'static,
+    {
+        let (domain, address) = self.contract_location();
+        self.tx_submitter()
+            .submit_with_gas(domain, address, call.tx, cfg)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::providers::{FromErr, MockProvider, ProviderError};
+    use nomad_core::{capture_submission, Decode, Encode};
+    use std::sync::Mutex;
+
+    /// Middleware filling nonces like the nonce manager and recording the
+    /// transaction it is asked to send, which always fails to send. Other
+    /// calls go to a mock provider.
+    #[derive(Debug)]
+    struct FailingSendMiddleware {
+        inner: Provider<MockProvider>,
+        sent: Mutex<Option<TypedTransaction>>,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("{0}")]
+    struct FailingSendError(String);
+
+    impl FromErr<ProviderError> for FailingSendError {
+        fn from(src: ProviderError) -> Self {
+            Self(src.to_string())
         }
     }
+
+    #[async_trait::async_trait]
+    impl Middleware for FailingSendMiddleware {
+        type Error = FailingSendError;
+        type Provider = MockProvider;
+        type Inner = Provider<MockProvider>;
+
+        fn inner(&self) -> &Self::Inner {
+            &self.inner
+        }
+
+        async fn fill_transaction(
+            &self,
+            tx: &mut TypedTransaction,
+            block: Option<BlockId>,
+        ) -> Result<(), Self::Error> {
+            if tx.nonce().is_none() {
+                tx.set_nonce(3);
+            }
+            self.inner
+                .fill_transaction(tx, block)
+                .await
+                .map_err(FromErr::from)
+        }
+
+        async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+            &self,
+            tx: T,
+            _: Option<BlockId>,
+        ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
+            *self.sent.lock().unwrap() = Some(tx.into());
+            Err(FailingSendError("nonce too low".to_owned()))
+        }
+    }
+
+    fn failing_submitter() -> (
+        TxSubmitter<FailingSendMiddleware>,
+        Arc<FailingSendMiddleware>,
+        MockProvider,
+    ) {
+        let (inner, mock) = Provider::mocked();
+        let client = Arc::new(FailingSendMiddleware {
+            inner,
+            sent: Default::default(),
+        });
+        (TxSubmitter::new(client.clone().into()), client, mock)
+    }
+
+    fn process_tx() -> TransactionRequest {
+        TransactionRequest::new()
+            .from(Address::repeat_byte(1))
+            .to(Address::repeat_byte(2))
+            .data(vec![0x92; 600])
+            .gas(1_900_000)
+            .gas_price(7)
+    }
+
+    #[tokio::test]
+    async fn it_records_the_payload_of_failed_submissions() {
+        let (submitter, client, mock) = failing_submitter();
+        // Both the gas price and the simulation read this answer, so their
+        // order does not matter
+        mock.push::<String, _>("0x05".to_owned()).unwrap();
+        mock.push::<String, _>("0x05".to_owned()).unwrap();
+
+        let (result, payload) =
+            capture_submission(submitter.submit(1000, Address::repeat_byte(2), process_tx())).await;
+        assert!(result.is_err());
+
+        // The stored payload is the transaction the provider was asked to
+        // send, with its fee decision and simulation
+        let sent = client.sent.lock().unwrap().clone().expect("not sent");
+        let stored = TxPayload::read_from(&mut payload.unwrap().to_vec().as_slice()).unwrap();
+        assert_eq!(stored.to, Some(Address::repeat_byte(2)));
+        assert_eq!(stored.from, Some(Address::repeat_byte(1)));
+        assert_eq!(stored.nonce, Some(3.into()));
+        assert_eq!(stored.gas, Some(1_900_000.into()));
+        assert_eq!(stored.gas_price, Some(7.into()));
+        assert_eq!(
+            stored.calldata.inflate().unwrap(),
+            sent.data().unwrap().to_vec()
+        );
+        assert!(stored.calldata.deflated.len() < 600);
+        assert_eq!(stored.simulation.as_deref(), Some("success 0x05"));
+        assert_eq!(
+            stored.fee,
+            Some(FeeDecision {
+                network_gas_price: 5.into(),
+                gas_price: Some(7.into()),
+            })
+        );
+
+        let mut expected = tx_payload(&sent).with_simulation("success 0x05");
+        expected.fee = stored.fee;
+        assert_eq!(stored, expected);
+    }
+
+    #[tokio::test]
+    async fn it_sends_uncaptured_submissions_as_is() {
+        let (submitter, client, mock) = failing_submitter();
+
+        let result = submitter
+            .submit(1000, Address::repeat_byte(2), process_tx())
+            .await;
+        assert!(result.is_err());
+
+        // Not filled or simulated before sending
+        let sent = client.sent.lock().unwrap().clone().expect("not sent");
+        assert_eq!(sent.nonce(), None);
+        assert!(mock.assert_request("eth_gasPrice", ()).is_err());
+    }
 }
@@ -1,4 +1,6 @@
-use ethers::prelude::TransactionReceipt;
+#![deny(clippy::cast_possible_truncation)]
+
+use ethers::prelude::{TransactionReceipt, U64};
 use nomad_core::TxOutcome;
 
 use crate::EthereumError;
@@ -9,9 +11,11 @@ use crate::EthereumError;
 pub fn try_transaction_receipt_to_tx_outcome(
     receipt: TransactionReceipt,
 ) -> Result<TxOutcome, EthereumError> {
-    if receipt.status.unwrap().low_u32() == 1 {
+    if receipt.status.unwrap() == U64::one() {
         Ok(TxOutcome {
             txid: receipt.transaction_hash,
+            gas_used: receipt.gas_used,
+            block_number: receipt.block_number.map(|block| block.as_u64()),
         })
     } else {
         Err(EthereumError::TxNotExecuted(receipt.transaction_hash))
@@ -39,6 +43,7 @@ mod test {
 
         let receipt = TransactionReceipt {
             status: Some(U64::from(1)),
+            gas_used: Some(84_000.into()),
             ..Default::default()
         };
         let tx_outcome: Result<TxOutcome, EthereumError> =
@@ -47,5 +52,6 @@ mod test {
             tx_outcome.is_ok(),
             "Turning succeeded transaction receipt into successful tx outcome not succeeded"
         );
+        assert_eq!(tx_outcome.unwrap().gas_used, Some(84_000.into()));
     }
 }
@@ -1,17 +1,22 @@
 #![allow(clippy::enum_variant_names)]
 #![allow(missing_docs)]
+#![deny(clippy::cast_possible_truncation)]
 
 use async_trait::async_trait;
-use ethers::core::types::U256;
-use nomad_core::*;
-use nomad_types::NomadIdentifier;
+use ethers::{
+    core::types::{Address, U256},
+    providers::Middleware,
+};
+use futures_util::future::join_all;
+use nomad_core::{utils::saturating_u64, *};
+use nomad_ethereum_bindings::xappconnectionmanager::{
+    UnenrollReplicaCall, XAppConnectionManager as EthereumConnectionManagerInternal,
+};
+use nomad_types::{CheckedCast, ChecksumAddress, NomadIdentifier};
 use nomad_xyz_configuration::ConnectionManagerGasLimits;
 use std::sync::Arc;
 
-use crate::{
-    bindings::xappconnectionmanager::XAppConnectionManager as EthereumConnectionManagerInternal,
-    EthereumError, TxSubmitter,
-};
+use crate::{EthereumError, SubmitWithGas, TxSubmitter};
 
 /// A reference to a XAppConnectionManager contract on some Ethereum chain
 #[derive(Debug)]
@@ -22,6 +27,7 @@ where
 {
     submitter: TxSubmitter<W>,
     contract: Arc<EthereumConnectionManagerInternal<R>>,
+    provider: Arc<R>,
     domain: u32,
     gas: Option<ConnectionManagerGasLimits>,
 }
@@ -44,8 +50,9 @@ where
         }: &ContractLocator,
         gas: Option<ConnectionManagerGasLimits>,
     ) -> Self {
+        let evm_address = address.as_ethereum_address().expect("!eth address");
         tracing::info!(
-            address = ?address.as_ethereum_address(),
+            address = %ChecksumAddress(evm_address),
             name = name,
             domain = domain,
             "Connecting XappConnectionManager"
@@ -53,13 +60,78 @@ where
         Self {
             submitter,
             contract: Arc::new(EthereumConnectionManagerInternal::new(
-                address.as_ethereum_address().expect("!eth address"),
-                read_provider,
+                evm_address,
+                read_provider.clone(),
             )),
+            provider: read_provider,
             domain: *domain,
             gas,
         }
     }
+
+    /// The replica enrolled for `domain`, or `None` if no replica is
+    /// enrolled for it
+    #[tracing::instrument(err, skip(self))]
+    pub async fn replica_for_domain(&self, domain: u32) -> Result<Option<Address>, EthereumError> {
+        let replica = self.contract.domain_to_replica(domain).call().await?;
+        Ok(if replica.is_zero() {
+            None
+        } else {
+            Some(replica)
+        })
+    }
+
+    /// The remote domain `replica` is enrolled for, or `None` if it is not
+    /// enrolled
+    #[tracing::instrument(err, skip(self))]
+    pub async fn domain_for_replica(&self, replica: Address) -> Result<Option<u32>, EthereumError> {
+        if replica.is_zero() {
+            return Ok(None);
+        }
+        let domain = self.contract.replica_to_domain(replica).call().await?;
+        Ok(if domain == 0 { None } else { Some(domain) })
+    }
+
+    /// Whether `watcher` may unenroll replicas of `domain`. The zero address
+    /// is never a watcher.
+    #[tracing::instrument(err, skip(self))]
+    pub async fn is_watcher(&self, watcher: Address, domain: u32) -> Result<bool, EthereumError> {
+        if watcher.is_zero() {
+            return Ok(false);
+        }
+        Ok(self
+            .contract
+            .watcher_permission(watcher, domain)
+            .call()
+            .await?)
+    }
+}
+
+/// Encode a signed failure notification as the calldata of a
+/// `XAppConnectionManager.unenrollReplica(uint32,bytes32,bytes)` call
+pub fn encode_unenroll_replica_calldata(signed_failure: &SignedFailureNotification) -> Vec<u8> {
+    use ethers::core::abi::AbiEncode;
+
+    UnenrollReplicaCall {
+        domain: signed_failure.notification.home_domain,
+        updater: signed_failure.notification.updater.into(),
+        signature: signed_failure.signature.to_vec().into(),
+    }
+    .encode()
+}
+
+impl<W, R> SubmitWithGas<W> for EthereumConnectionManager<W, R>
+where
+    W: ethers::providers::Middleware + 'static,
+    R: ethers::providers::Middleware + 'static,
+{
+    fn tx_submitter(&self) -> &TxSubmitter<W> {
+        &self.submitter
+    }
+
+    fn contract_location(&self) -> (u32, Address) {
+        (self.domain, self.contract.address())
+    }
 }
 
 #[async_trait]
@@ -83,6 +155,82 @@ where
             .await?)
     }
 
+    #[tracing::instrument(err)]
+    async fn replica_to_domain(&self, address: NomadIdentifier) -> Result<u32, Self::Error> {
+        Ok(self
+            .contract
+            .replica_to_domain(address.as_ethereum_address().expect("!eth address"))
+            .call()
+            .await?)
+    }
+
+    #[tracing::instrument(err, skip(self))]
+    async fn get_block_number(&self) -> Result<u32, Self::Error> {
+        Ok(self
+            .provider
+            .get_block_number()
+            .await
+            .map_err(|e| EthereumError::MiddlewareError(e.into()))?
+            .try_u32()?)
+    }
+
+    #[tracing::instrument(err, skip(self))]
+    async fn fetch_replica_enrollments(
+        &self,
+        from: u32,
+        to: u32,
+    ) -> Result<Vec<ReplicaEnrollment>, Self::Error> {
+        let enrolled = self
+            .contract
+            .replica_enrolled_filter()
+            .from_block(from)
+            .to_block(to)
+            .query_with_meta()
+            .await?
+            .into_iter()
+            .map(|(event, meta)| (event.domain, event.replica, true, meta));
+        let unenrolled = self
+            .contract
+            .replica_unenrolled_filter()
+            .from_block(from)
+            .to_block(to)
+            .query_with_meta()
+            .await?
+            .into_iter()
+            .map(|(event, meta)| (event.domain, event.replica, false, meta));
+
+        let mut events: Vec<_> = enrolled.chain(unenrolled).collect();
+        events.sort_by(|a, b| {
+            a.3.block_number
+                .cmp(&b.3.block_number)
+                .then(a.3.transaction_index.cmp(&b.3.transaction_index))
+                .then(a.3.log_index.cmp(&b.3.log_index))
+        });
+
+        // Timestamps are best effort
+        let enrollment_futs =
+            events
+                .into_iter()
+                .map(|(domain, replica, enrolled, meta)| async move {
+                    let block_number = meta.block_number.as_u64();
+                    let timestamp = self
+                        .provider
+                        .get_block(block_number)
+                        .await
+                        .ok()
+                        .flatten()
+                        .map(|b| saturating_u64(b.timestamp));
+                    ReplicaEnrollment {
+                        domain,
+                        replica: replica.into(),
+                        enrolled,
+                        block_number,
+                        timestamp,
+                    }
+                });
+        Ok(join_all(enrollment_futs).await)
+    }
+
     #[tracing::instrument(err)]
     async fn watcher_permission(
         &self,
@@ -106,9 +254,7 @@ where
             .contract
             .owner_enroll_replica(replica.as_ethereum_address().expect("!eth address"), domain);
 
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
-            .await
+        self.submit_with_gas(tx, &self.submitter.gas).await
     }
 
     #[tracing::instrument(err)]
@@ -124,9 +270,7 @@ where
             tx.tx.set_gas(U256::from(limits.owner_unenroll_replica));
         }
 
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
-            .await
+        self.submit_with_gas(tx, &self.submitter.gas).await
     }
 
     #[tracing::instrument(err)]
@@ -135,9 +279,7 @@ where
             .contract
             .set_home(home.as_ethereum_address().expect("!eth address"));
 
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
-            .await
+        self.submit_with_gas(tx, &self.submitter.gas).await
     }
 
     #[tracing::instrument(err)]
@@ -153,9 +295,7 @@ where
             access,
         );
 
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
-            .await
+        self.submit_with_gas(tx, &self.submitter.gas).await
     }
 
     #[tracing::instrument(err)]
@@ -173,8 +313,88 @@ where
             tx.tx.set_gas(U256::from(limits.unenroll_replica));
         }
 
-        self.submitter
-            .submit(self.domain, self.contract.address(), tx.tx)
-            .await
+        self.submit_with_gas(tx, &self.submitter.gas).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::{
+        core::types::{Bytes, H256},
+        providers::{MockProvider, Provider},
+    };
+
+    fn connection_manager() -> (
+        EthereumConnectionManager<Provider<MockProvider>, Provider<MockProvider>>,
+        MockProvider,
+    ) {
+        let (provider, mock) = Provider::mocked();
+        let manager = EthereumConnectionManager::new(
+            TxSubmitter::new(crate::SubmitterClient::ReadOnly),
+            Arc::new(provider),
+            &ContractLocator {
+                name: "xapp_connection_manager".into(),
+                domain: 1000,
+                address: Address::repeat_byte(1).into(),
+            },
+            None,
+        );
+        (manager, mock)
+    }
+
+    /// ABI-encoded return value of a call returning a single word
+    fn word(word: H256) -> Bytes {
+        Bytes::from(word.as_bytes().to_vec())
+    }
+
+    #[tokio::test]
+    async fn it_maps_enrolled_and_unenrolled_replicas() {
+        let (manager, mock) = connection_manager();
+        let replica = Address::repeat_byte(2);
+
+        mock.push::<Bytes, _>(word(replica.into())).unwrap();
+        assert_eq!(
+            manager.replica_for_domain(2000).await.unwrap(),
+            Some(replica)
+        );
+        mock.push::<Bytes, _>(word(H256::zero())).unwrap();
+        assert_eq!(manager.replica_for_domain(3000).await.unwrap(), None);
+
+        mock.push::<Bytes, _>(word(H256::from_low_u64_be(2000)))
+            .unwrap();
+        assert_eq!(
+            manager.domain_for_replica(replica).await.unwrap(),
+            Some(2000)
+        );
+        mock.push::<Bytes, _>(word(H256::zero())).unwrap();
+        assert_eq!(
+            manager
+                .domain_for_replica(Address::repeat_byte(3))
+                .await
+                .unwrap(),
+            None
+        );
+
+        // The zero address is never looked up
+        assert_eq!(
+            manager.domain_for_replica(Address::zero()).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn it_reports_watcher_permissions() {
+        let (manager, mock) = connection_manager();
+        let watcher = Address::repeat_byte(4);
+
+        mock.push::<Bytes, _>(word(H256::from_low_u64_be(1)))
+            .unwrap();
+        assert!(manager.is_watcher(watcher, 2000).await.unwrap());
+        mock.push::<Bytes, _>(word(H256::zero())).unwrap();
+        assert!(!manager.is_watcher(watcher, 3000).await.unwrap());
+
+        // The zero address is never looked up
+        assert!(!manager.is_watcher(Address::zero(), 2000).await.unwrap());
     }
 }
@@ -1,8 +1,8 @@
 use crate::SubstrateError;
 use avail_subxt::api::nomad_home as home;
 use color_eyre::Result;
-use ethers_core::types::{Signature, H256};
-use nomad_core::{RawCommittedMessage, SignedUpdate, SignedUpdateWithMeta, Update, UpdateMeta};
+use ethers_core::types::H256;
+use nomad_core::{RawCommittedMessage, SignedUpdateWithMeta, UpdateEvent, UpdateMeta};
 use std::collections::HashMap;
 use std::convert::TryInto;
 use subxt::ext::sp_runtime::traits::Header;
@@ -90,28 +90,17 @@ where
         let sorted_update_events: Vec<home::events::Update> = sort_update_events(update_events);
 
         // Map update events into SignedUpdates with meta
-        Ok(sorted_update_events
+        sorted_update_events
             .into_iter()
             .map(|ev| {
-                let signature = Signature::try_from(ev.signature.as_ref())
-                    .expect("chain accepted invalid signature");
-
-                SignedUpdateWithMeta {
-                    signed_update: SignedUpdate {
-                        update: Update {
-                            home_domain: ev.home_domain,
-                            previous_root: ev.previous_root,
-                            new_root: ev.new_root,
-                        },
-                        signature,
-                    },
-                    metadata: UpdateMeta {
+                UpdateEvent::from(ev)
+                    .with_meta(UpdateMeta {
                         block_number: block_number as u64,
                         timestamp: None,
-                    },
-                }
+                    })
+                    .map_err(Into::into)
             })
-            .collect())
+            .collect()
     }
 
     /// Fetch ordered signed updates from the specific `block_number`
@@ -147,11 +136,23 @@ where
                 leaf_index: ev.leaf_index,
                 committed_root: ev.committed_root,
                 message: ev.message,
+                timestamp: None,
             })
             .collect())
     }
 }
 
+impl From<home::events::Update> for UpdateEvent {
+    fn from(event: home::events::Update) -> Self {
+        Self {
+            home_domain: event.home_domain,
+            old_root: event.previous_root,
+            new_root: event.new_root,
+            signature: event.signature,
+        }
+    }
+}
+
 /// sort_update_events sorts events based on the previous and new root. In most cases there will be
 /// only one event per block.
 fn sort_update_events(update_events: Vec<home::events::Update>) -> Vec<home::events::Update> {
@@ -1,4 +1,6 @@
 use ethers_core::types::H256;
+use nomad_core::NomadError;
+use nomad_types::NomadTypeError;
 use std::error::Error as StdError;
 use subxt::{ext::scale_value, Error as SubxtError};
 
@@ -8,12 +10,21 @@ pub enum SubstrateError {
     /// A transaction was not executed successfully
     #[error("Transaction was not executed successfully {0:?}")]
     TxNotExecuted(H256),
+    /// A dispatch transaction emitted no `Dispatch` event
+    #[error("No Dispatch event in the events of transaction {0:?}")]
+    NoDispatchEvent(H256),
     /// Substrate provider error
     #[error("{0}")]
     ProviderError(#[from] SubxtError),
     /// Scale value deserialization error
     #[error("{0}")]
     DeserializationError(#[from] scale_value::serde::DeserializerError),
+    /// Malformed event data, such as an unparseable signature
+    #[error("{0}")]
+    NomadError(#[from] NomadError),
+    /// An integer read from chain did not fit its domain type
+    #[error("{0}")]
+    ConversionError(#[from] NomadTypeError),
     /// Any other error
     #[error("{0}")]
     CustomError(#[from] Box<dyn StdError + Send + Sync>),
@@ -1,14 +1,19 @@
+#![deny(clippy::cast_possible_truncation)]
+
 use crate::decodings::{NomadBase, NomadLightMerkleWrapper, NomadState};
-use crate::{report_tx, utils, NomadOnlineClient, SubstrateError, SubstrateSigner};
+use crate::{report_tx, submit_tx, utils, NomadOnlineClient, SubstrateError, SubstrateSigner};
 use async_trait::async_trait;
+use avail_subxt::api::nomad_home;
 use color_eyre::Result;
 use ethers_core::types::{H160, H256, U256};
 use futures::{stream::FuturesOrdered, StreamExt};
 use nomad_core::{
     accumulator::{Merkle, NomadLightMerkle},
-    Common, CommonIndexer, DoubleUpdate, Home, HomeIndexer, Message, RawCommittedMessage,
-    SignedUpdate, SignedUpdateWithMeta, State, TxOutcome, Update,
+    utils::abbreviated_hex,
+    Common, CommonIndexer, DispatchOutcome, DoubleUpdate, Home, HomeIndexer, Message, NomadMessage,
+    RawCommittedMessage, SignedUpdate, SignedUpdateWithMeta, State, TxOutcome, Update,
 };
+use nomad_types::LeafIndex;
 use std::{convert::TryInto, sync::Arc};
 use subxt::ext::scale_value::{self, Primitive, Value};
 use subxt::tx::ExtrinsicParams;
@@ -25,6 +30,25 @@ const UPDATE_CALL_NAME: &str = "update";
 const IMPROPER_UPDATE_CALL_NAME: &str = "improper_update";
 const UPDATE_MAX_INDEX: u32 = 1000;
 
+/// Payload of a `dispatch` extrinsic sending `message`
+fn dispatch_payload(message: &Message) -> subxt::tx::DynamicTxPayload<'static> {
+    let Message {
+        destination,
+        recipient,
+        body,
+    } = message;
+
+    let destination_value = Value::u128(*destination as u128);
+    let recipient_value = Value::primitive(Primitive::U256((*recipient).into()));
+    let body_value = Value::from_bytes(body);
+
+    subxt::dynamic::tx(
+        HOME_PALLET_NAME,
+        DISPATCH_CALL_NAME,
+        vec![destination_value, recipient_value, body_value],
+    )
+}
+
 /// Substrate home indexer
 #[derive(Clone)]
 pub struct SubstrateHomeIndexer<T: Config>(NomadOnlineClient<T>);
@@ -269,6 +293,8 @@ where
     async fn double_update(&self, _double: &DoubleUpdate) -> Result<TxOutcome, Self::Error> {
         Ok(TxOutcome {
             txid: Default::default(),
+            gas_used: None,
+            block_number: None,
         })
     }
 }
@@ -305,24 +331,37 @@ where
 
     #[tracing::instrument(err, skip(self))]
     async fn dispatch(&self, message: &Message) -> Result<TxOutcome, <Self as Common>::Error> {
-        let Message {
-            destination,
-            recipient,
-            body,
-        } = message;
+        let tx_payload = dispatch_payload(message);
 
-        let destination_value = Value::u128(*destination as u128);
-        let recipient_value = Value::primitive(Primitive::U256((*recipient).into()));
-        let body_value = Value::from_bytes(body);
+        info!(message = ?message, "Dispatching message to chain.");
+        report_tx!(DISPATCH_CALL_NAME, self.api, self.signer, tx_payload)
+    }
 
-        let tx_payload = subxt::dynamic::tx(
-            HOME_PALLET_NAME,
-            DISPATCH_CALL_NAME,
-            vec![destination_value, recipient_value, body_value],
-        );
+    #[tracing::instrument(err, skip(self))]
+    async fn dispatch_tracked(
+        &self,
+        message: &Message,
+    ) -> Result<DispatchOutcome, <Self as Common>::Error> {
+        let tx_payload = dispatch_payload(message);
 
         info!(message = ?message, "Dispatching message to chain.");
-        report_tx!(DISPATCH_CALL_NAME, self.api, self.signer, tx_payload)
+        let successful_tx = submit_tx!(DISPATCH_CALL_NAME, self.api, self.signer, tx_payload);
+        let txid: H256 = successful_tx.extrinsic_hash().into();
+        let dispatch = successful_tx
+            .find_first::<nomad_home::events::Dispatch>()?
+            .ok_or(SubstrateError::NoDispatchEvent(txid))?;
+        let message = NomadMessage::from_raw(&dispatch.message)?;
+
+        Ok(DispatchOutcome {
+            tx: TxOutcome {
+                txid,
+                gas_used: None,
+                block_number: None,
+            },
+            leaf_index: dispatch.leaf_index,
+            message_hash: message.to_leaf(),
+            destination_and_nonce: message.destination_and_nonce(),
+        })
     }
 
     async fn queue_length(&self) -> Result<U256, <Self as Common>::Error> {
@@ -339,7 +378,25 @@ where
         Ok(index_value.is_some())
     }
 
-    #[tracing::instrument(err, skip(self), fields(hex_signature = % format ! ("0x{}", hex::encode(update.signature.to_vec()))))]
+    async fn queue_end(&self) -> Result<H256, <Self as Common>::Error> {
+        unimplemented!("Queue deprecated for Substrate implementations")
+    }
+
+    #[tracing::instrument(err, skip(self))]
+    async fn counted_root(&self) -> Result<(H256, u32), <Self as Common>::Error> {
+        // Root and count come from the same storage read
+        let tree = self.tree().await?;
+        Ok((tree.root(), LeafIndex::try_from(tree.count())?.into()))
+    }
+
+    #[tracing::instrument(err, skip(self))]
+    async fn tree_counts(&self) -> Result<(u32, u32), <Self as Common>::Error> {
+        // The pallet only stores the tree, so both counts come from it
+        let count: u32 = LeafIndex::try_from(self.tree().await?.count())?.into();
+        Ok((count, count))
+    }
+
+    #[tracing::instrument(err, skip(self), fields(hex_signature = %abbreviated_hex(&update.signature.to_vec())))]
     async fn improper_update(
         &self,
         update: &SignedUpdate,
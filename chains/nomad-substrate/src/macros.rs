@@ -1,7 +1,8 @@
 use avail_subxt::AvailConfig;
-/// Dispatches an extrinsic, waits for inclusion, and logs details
+/// Dispatches an extrinsic, waits for inclusion, and logs details. Evaluates
+/// to the events of the successful extrinsic.
 #[macro_export]
-macro_rules! report_tx {
+macro_rules! submit_tx {
     ($method:expr, $client:expr, $signer:expr, $tx:expr) => {{
         let pending_tx = $client
             .tx()
@@ -37,10 +38,23 @@ macro_rules! report_tx {
             $method,
         );
 
-        Ok(TxOutcome { txid: successful_tx.extrinsic_hash().into() })
+        successful_tx
     }}
 }
 
+/// Dispatches an extrinsic, waits for inclusion, and logs details
+#[macro_export]
+macro_rules! report_tx {
+    ($method:expr, $client:expr, $signer:expr, $tx:expr) => {{
+        let successful_tx = $crate::submit_tx!($method, $client, $signer, $tx);
+        Ok(TxOutcome {
+            txid: successful_tx.extrinsic_hash().into(),
+            gas_used: None,
+            block_number: None,
+        })
+    }};
+}
+
 /// Generate function that creates boxed non_signing object (i.e. an indexer)
 macro_rules! boxed_indexer {
     ($fn_name:ident, $chain_name:ident, $abi:ident, $trait:path, $($n:ident:$t:ty),*)  => {
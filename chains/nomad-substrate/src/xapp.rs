@@ -3,7 +3,7 @@ use crate::{NomadOnlineClient, SubstrateSigner};
 use async_trait::async_trait;
 use color_eyre::Result;
 use ethers_core::types::H256;
-use nomad_core::{ConnectionManager, SignedFailureNotification, TxOutcome};
+use nomad_core::{ConnectionManager, ReplicaEnrollment, SignedFailureNotification, TxOutcome};
 use nomad_types::NomadIdentifier;
 use std::sync::Arc;
 use subxt::tx::ExtrinsicParams;
@@ -97,6 +97,25 @@ where
         unimplemented!("Substrate connection manager not yet implemented")
     }
 
+    #[tracing::instrument(err)]
+    async fn replica_to_domain(&self, _address: NomadIdentifier) -> Result<u32, Self::Error> {
+        unimplemented!("Substrate connection manager not yet implemented")
+    }
+
+    #[tracing::instrument(err)]
+    async fn get_block_number(&self) -> Result<u32, Self::Error> {
+        unimplemented!("Substrate connection manager not yet implemented")
+    }
+
+    #[tracing::instrument(err)]
+    async fn fetch_replica_enrollments(
+        &self,
+        _from: u32,
+        _to: u32,
+    ) -> Result<Vec<ReplicaEnrollment>, Self::Error> {
+        unimplemented!("Substrate connection manager not yet implemented")
+    }
+
     #[tracing::instrument(err)]
     async fn watcher_permission(
         &self,
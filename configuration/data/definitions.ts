@@ -113,6 +113,18 @@ export interface NetworkSpecs {
   confirmations: number | string;
   blockExplorer: string;
   indexPageSize: number;
+  fees?: GasConfig;
+}
+
+export interface GasConfig {
+  feeMode?: 'legacy' | 'eip1559' | 'oracle';
+  maxPriorityFeeGwei?: number;
+  maxFeeMultiplier?: number;
+  maxFeeGwei?: number;
+  priorityFeePercentile?: number;
+  gasLimitMultiplier?: number;
+  minGasLimit?: number;
+  maxGasLimit?: number;
 }
 
 export interface CustomTokenSpecifier {
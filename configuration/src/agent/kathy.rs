@@ -1,21 +1,55 @@
 //! Kathy public configuration
 
 use crate::{decl_config, decl_env_overrides};
-use ethers::types::H256;
+use nomad_types::{Address32, NomadIdentifier};
+use std::collections::HashMap;
 
 decl_config!(Kathy {
     /// Chat generator config
     #[serde(default)]
     chat: ChatGenConfig,
+    /// Round-trip echo config. If set, kathy sends echo requests instead of
+    /// chat messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    echo: Option<EchoConfig>,
 });
 
+fn default_echo_deadline() -> u64 {
+    3600
+}
+
+/// Kathy round-trip echo configuration. Kathy dispatches an echo request to
+/// the echo xApp of each remote, which dispatches a reply back to the echo
+/// xApp on kathy's home network.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EchoConfig {
+    /// Echo xApp address by network name, including kathy's home network
+    pub recipients: HashMap<String, NomadIdentifier>,
+    /// Contracts to observe the return leg on, by remote network name
+    pub returns: HashMap<String, EchoReturnConfig>,
+    /// Seconds after dispatch by which each leg must be processed
+    #[serde(default = "default_echo_deadline")]
+    pub deadline_seconds: u64,
+}
+
+/// Contracts carrying the return leg from a remote back to kathy's home
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EchoReturnConfig {
+    /// Home on the remote network
+    pub home: NomadIdentifier,
+    /// Replica of the remote home on kathy's home network
+    pub replica: NomadIdentifier,
+}
+
 decl_env_overrides!(Kathy {self, {
     if let (Ok(rec), Ok(msg)) = (
         std::env::var("KATHY_CHAT_RECIPIENT"),
         std::env::var("KATHY_CHAT_MESSAGE"),
     ) {
         self.chat = ChatGenConfig::Static {
-            recipient: rec.parse::<H256>().expect("invalid KATHY_CHAT_RECIPIENT"),
+            recipient: rec.parse::<Address32>().expect("invalid KATHY_CHAT_RECIPIENT"),
             message: msg,
         }
     }
@@ -30,16 +64,29 @@ decl_env_overrides!(Kathy {self, {
         let length = var.parse::<usize>().expect("invalid KATHY_CHAT_RANDOM");
         self.chat = ChatGenConfig::Random { length }
     }
+    else if let Ok(var) = std::env::var("KATHY_CHAT_EMPTY_RECIPIENT") {
+        self.chat = ChatGenConfig::Empty {
+            recipient: var.parse::<Address32>().expect("invalid KATHY_CHAT_EMPTY_RECIPIENT"),
+        }
+    }
+    if let Some(echo) = self.echo.as_mut() {
+        if let Ok(var) = std::env::var("KATHY_ECHO_DEADLINE_SECONDS") {
+            echo.deadline_seconds = var
+                .parse::<u64>()
+                .expect("invalid KATHY_ECHO_DEADLINE_SECONDS");
+        }
+    }
 }});
 
 /// Kathy chat generator configuration
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum ChatGenConfig {
     /// Static messages
     Static {
-        /// Recipient
-        recipient: H256,
+        /// Recipient, as a 32-byte value or an EVM address
+        #[schemars(with = "String")]
+        recipient: Address32,
         /// Message
         message: String,
     },
@@ -53,6 +100,12 @@ pub enum ChatGenConfig {
         /// Message length
         length: usize,
     },
+    /// Zero-length messages, as sent by pings
+    Empty {
+        /// Recipient, as a 32-byte value or an EVM address
+        #[schemars(with = "String")]
+        recipient: Address32,
+    },
     /// Default
     #[serde(other)]
     Default,
@@ -67,6 +120,7 @@ impl Default for ChatGenConfig {
 #[cfg(test)]
 mod test {
     use super::*;
+    use ethers::types::{H160, H256};
     use nomad_test::test_utils;
     use std::{env, str::FromStr};
 
@@ -98,7 +152,7 @@ mod test {
             assert_eq!(
                 config.chat,
                 ChatGenConfig::Static {
-                    recipient: H256::from_str(
+                    recipient: Address32::from_str(
                         "0x1111111111111111111111111111111111111111111111111111111111111111"
                     )
                     .unwrap(),
@@ -111,6 +165,58 @@ mod test {
             env::set_var("KATHY_CHAT_RANDOM", "99");
             config.load_env_overrides();
             assert_eq!(config.chat, ChatGenConfig::Random { length: 99 });
+
+            env::remove_var("KATHY_CHAT_RANDOM");
+            env::set_var(
+                "KATHY_CHAT_EMPTY_RECIPIENT",
+                "0x2222222222222222222222222222222222222222222222222222222222222222",
+            );
+            config.load_env_overrides();
+            assert_eq!(
+                config.chat,
+                ChatGenConfig::Empty {
+                    recipient: Address32(H256::repeat_byte(0x22)),
+                }
+            );
+
+            // EVM addresses are left-padded
+            env::set_var(
+                "KATHY_CHAT_EMPTY_RECIPIENT",
+                "0x2222222222222222222222222222222222222222",
+            );
+            config.load_env_overrides();
+            assert_eq!(
+                config.chat,
+                ChatGenConfig::Empty {
+                    recipient: Address32::from_evm(H160::repeat_byte(0x22)),
+                }
+            );
+            env::remove_var("KATHY_CHAT_EMPTY_RECIPIENT");
         });
     }
+
+    #[test]
+    fn it_deserializes_echo_config() {
+        let config: KathyConfig = serde_json::from_value(serde_json::json!({
+            "interval": 10,
+            "echo": {
+                "recipients": {
+                    "ethereum": "0x1111111111111111111111111111111111111111",
+                    "moonbeam": "0x2222222222222222222222222222222222222222",
+                },
+                "returns": {
+                    "moonbeam": {
+                        "home": "0x3333333333333333333333333333333333333333",
+                        "replica": "0x4444444444444444444444444444444444444444",
+                    },
+                },
+            },
+        }))
+        .unwrap();
+
+        let echo = config.echo.unwrap();
+        assert_eq!(echo.deadline_seconds, 3600);
+        assert_eq!(echo.recipients.len(), 2);
+        assert!(echo.returns.contains_key("moonbeam"));
+    }
 }
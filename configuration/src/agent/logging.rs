@@ -1,7 +1,9 @@
 //! Agent configuration types
 
 /// Rpc Styles
-#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
+#[derive(
+    Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema, Eq, PartialEq,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum RpcStyles {
     /// Ethereum
@@ -28,7 +30,9 @@ impl Default for RpcStyles {
 }
 
 /// Basic tracing configuration
-#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
+#[derive(
+    Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema, Eq, PartialEq,
+)]
 #[serde(rename_all = "camelCase")]
 pub enum LogStyle {
     /// Pretty print
@@ -49,7 +53,9 @@ impl Default for LogStyle {
 }
 
 /// Logging level
-#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq)]
+#[derive(
+    Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq,
+)]
 #[serde(rename_all = "camelCase")]
 pub enum LogLevel {
     /// Off
@@ -74,7 +80,9 @@ impl Default for LogLevel {
 }
 
 /// Logger configuration
-#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
+#[derive(
+    Debug, Copy, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq,
+)]
 #[serde(rename_all = "camelCase")]
 pub struct LogConfig {
     /// fmt specifier
@@ -17,7 +17,12 @@ pub mod relayer;
 pub mod updater;
 pub mod watcher;
 
-use std::path::PathBuf;
+mod top_up;
+pub use top_up::*;
+
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use crate::ethereum::RpcErrorPattern;
 
 use self::{
     kathy::KathyConfig, processor::ProcessorConfig, relayer::RelayerConfig, updater::UpdaterConfig,
@@ -25,7 +30,7 @@ use self::{
 };
 
 /// Full agent configuration
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AgentConfig {
     /// RPC specifier
@@ -34,6 +39,43 @@ pub struct AgentConfig {
     pub db: PathBuf,
     /// Metrics port
     pub metrics: Option<u16>,
+    /// Serve CPU and heap profiles on the metrics server. Agents must be
+    /// built with the `nomad-base/profiling` feature
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub profiling: bool,
+    /// Check at startup that the deployed code of each contract has every
+    /// function the agent calls
+    #[serde(default, skip_serializing_if = "CodeCheck::is_off")]
+    pub code_check: CodeCheck,
+    /// NDJSON file the audit trail of decisions leading to on-chain actions
+    /// is mirrored to. The trail is always kept in the DB.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_file: Option<PathBuf>,
+    /// Classification of JSON-RPC errors, checked before the built-in
+    /// patterns for common node vendors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rpc_error_patterns: Vec<RpcErrorPattern>,
+    /// Batch the DB writes of event indexing rather than writing each event
+    /// on its own. Unset writes each event at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_write_batch: Option<DbWriteBatchConfig>,
+    /// Read db values written before db encryption was enabled, while
+    /// writing new values encrypted. Overridden by `DB_ENCRYPTION_MIGRATE`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub db_encryption_migrate: bool,
+    /// Most blocks a single log query of the home indexer spans. Longer
+    /// ranges are queried in windows of this many blocks. Unset queries each
+    /// range at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_block_range: Option<u32>,
+    /// Backoff of the event indexers after failed provider requests. Unset
+    /// uses the default backoff
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_backoff: Option<IndexBackoffConfig>,
+    /// Detection of other processes submitting with the agent's transaction
+    /// signers. Unset heartbeats claims into the DB only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer_claims: Option<SignerClaimsConfig>,
     /// Logging configuration
     pub logging: LogConfig,
     /// Updater configuration
@@ -46,6 +88,156 @@ pub struct AgentConfig {
     pub watcher: WatcherConfig,
     /// Kathy configuration
     pub kathy: KathyConfig,
+    /// Testnet top-up of the agents' transaction signers, by network name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub top_up: HashMap<String, TopUpConfig>,
+}
+
+impl AgentConfig {
+    /// Whether to migrate the db to encryption, overridden by
+    /// `DB_ENCRYPTION_MIGRATE` if it is set
+    pub fn db_encryption_migrate(&self) -> bool {
+        match std::env::var("DB_ENCRYPTION_MIGRATE") {
+            Ok(var) => var
+                .parse::<bool>()
+                .expect("invalid DB_ENCRYPTION_MIGRATE value"),
+            Err(_) => self.db_encryption_migrate,
+        }
+    }
+}
+
+/// Strictness of the startup check of deployed contract code
+#[derive(
+    Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema, Eq, PartialEq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum CodeCheck {
+    /// Do not check
+    Off,
+    /// Warn about missing functions
+    Warn,
+    /// Refuse to start if a function is missing
+    Strict,
+}
+
+impl Default for CodeCheck {
+    fn default() -> Self {
+        CodeCheck::Off
+    }
+}
+
+impl CodeCheck {
+    /// Whether the check is disabled
+    pub fn is_off(&self) -> bool {
+        *self == CodeCheck::Off
+    }
+}
+
+/// Batching of the DB writes of event indexing. Staged writes are flushed in
+/// one batch once `maxRecords` are staged or the oldest is `maxDelayMs` old.
+/// A crash loses the staged writes, including the sync cursors staged with
+/// them, so indexing resumes before them.
+#[derive(
+    Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema, Eq, PartialEq,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct DbWriteBatchConfig {
+    /// Number of staged writes that triggers a flush
+    pub max_records: usize,
+    /// Age in milliseconds of the oldest staged write that triggers a flush
+    pub max_delay_ms: u64,
+}
+
+impl DbWriteBatchConfig {
+    /// Age of the oldest staged write that triggers a flush
+    pub fn max_delay(&self) -> Duration {
+        Duration::from_millis(self.max_delay_ms)
+    }
+}
+
+/// Backoff of the event indexers after failed provider requests. The delay
+/// starts at `initialDelayMs` and is multiplied by `multiplier` on each
+/// consecutive failure, up to `maxDelayMs`. Each delay is shortened by a
+/// random fraction of up to `jitter`. A successful poll resets it.
+#[derive(
+    Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexBackoffConfig {
+    /// Delay in milliseconds after the first failure
+    pub initial_delay_ms: u64,
+    /// Factor the delay grows by with each consecutive failure
+    pub multiplier: f64,
+    /// Longest delay in milliseconds
+    pub max_delay_ms: u64,
+    /// Largest fraction, from 0 to 1, each delay is randomly shortened by
+    pub jitter: f64,
+}
+
+impl Default for IndexBackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 10_000,
+            multiplier: 2.0,
+            max_delay_ms: 300_000,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl IndexBackoffConfig {
+    /// Delay after the first failure
+    pub fn initial_delay(&self) -> Duration {
+        Duration::from_millis(self.initial_delay_ms)
+    }
+
+    /// Longest delay
+    pub fn max_delay(&self) -> Duration {
+        Duration::from_millis(self.max_delay_ms)
+    }
+}
+
+/// Detection of other processes submitting with the agent's transaction
+/// signers, which race on the signers' nonces. Each process heartbeats a
+/// claim on each of its signers into its DB and, with a `directory` shared
+/// by the agents, into a claim file there. Other processes' claims in the
+/// directory renewed within three heartbeats are alerted on.
+#[derive(
+    Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema, Eq, PartialEq,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct SignerClaimsConfig {
+    /// Directory of claim files shared by the agents' processes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<PathBuf>,
+    /// Seconds between heartbeats
+    #[serde(default = "default_heartbeat_seconds")]
+    pub heartbeat_seconds: u64,
+    /// Pause the corridor to a network while another process claims its
+    /// signer
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pause_on_conflict: bool,
+}
+
+fn default_heartbeat_seconds() -> u64 {
+    30
+}
+
+impl Default for SignerClaimsConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            heartbeat_seconds: default_heartbeat_seconds(),
+            pause_on_conflict: false,
+        }
+    }
+}
+
+impl SignerClaimsConfig {
+    /// Time between heartbeats
+    pub fn heartbeat_interval(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_seconds)
+    }
 }
 
 #[macro_export]
@@ -80,7 +272,14 @@ macro_rules! decl_config {
                 fn load_env_overrides_private(&mut self) {}
             }
 
-            #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+            #[derive(
+                Debug,
+                Default,
+                Clone,
+                serde::Serialize,
+                serde::Deserialize,
+                schemars::JsonSchema,
+            )]
             #[serde(rename_all = "camelCase")]
             #[doc = "Config for `" $name "`"]
             #[allow(missing_copy_implementations)]
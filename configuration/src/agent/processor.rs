@@ -2,14 +2,19 @@
 
 use crate::{decl_config, decl_env_overrides, S3Config};
 use ethers::types::H256;
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
 decl_config!(Processor {
     /// Allow list
     #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<HashSet<String>>")]
     allowed: Option<HashSet<H256>>,
     /// Deny list
     #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<HashSet<String>>")]
     denied: Option<HashSet<H256>>,
     /// Remote chains to subsidize processing on
     #[serde(default, skip_serializing_if = "HashSet::is_empty")]
@@ -17,8 +22,127 @@ decl_config!(Processor {
     /// Whether to upload proofs to s3
     #[serde(default, skip_serializing_if = "Option::is_none")]
     s3: Option<S3Config>,
+    /// Order in which messages are processed
+    #[serde(default)]
+    ordering: ProcessingOrder,
+    /// Leaves of poisoned head-of-line messages to release from their
+    /// sender's queue in `perSenderNonce` ordering
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    #[schemars(with = "HashSet<String>")]
+    released: HashSet<H256>,
+    /// Seconds to wait past a message's `confirmAt` before processing it,
+    /// by destination network. Networks not listed have no extra delay
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    extra_delay_seconds: HashMap<String, u64>,
+    /// JSON file of extra delays by destination network. Re-read every
+    /// interval while the processor runs. Entries override
+    /// `extraDelaySeconds`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extra_delay_path: Option<PathBuf>,
+    /// Seconds of host clock skew against a replica chain above which an
+    /// alert is raised. Defaults to 30
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    clock_skew_alert_seconds: Option<u64>,
+    /// Seconds subtracted from the skew-corrected chain time before
+    /// checking whether a message is confirmable. Defaults to 5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    clock_skew_margin_seconds: Option<u64>,
+    /// Seconds past chain time beyond which a root's `confirmAt` is treated
+    /// as never reached, and its messages as never processable, with an
+    /// alert. Defaults to 10 years
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confirm_horizon_seconds: Option<u64>,
+    /// Multiple of a corridor's baseline dispatch rate above which a window
+    /// of dispatches is flagged as anomalous. Observational only. Defaults
+    /// to 20
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dispatch_rate_alert_multiple: Option<f64>,
+    /// Destination networks paused at startup, with the reason for each.
+    /// Corridors can also be paused and resumed through the admin endpoint
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    paused: HashMap<String, String>,
+    /// Port of the admin endpoint pausing and resuming corridors. If unset,
+    /// the endpoint is not served
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    admin_port: Option<u16>,
+    /// Destination networks that only process messages under roots the
+    /// replica accepted through a signed update. Messages under roots set
+    /// by governance `setConfirmation`, or the genesis root, wait until an
+    /// update attests to the root
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    attested_roots_only: HashSet<String>,
+    /// History reconciled against the replicas' message statuses at
+    /// startup, besides the messages added since the last reconciliation.
+    /// Older messages are reconciled when first accessed. If unset, the
+    /// first startup reconciles all history. `--full-reconcile` overrides it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reconciliation_lookback: Option<ReconciliationLookback>,
+    /// Pause processing to a destination while its replica is not enrolled
+    /// in the destination's XAppConnectionManager, and resume once it is
+    /// enrolled again. Enrollment is monitored either way
+    #[serde(default)]
+    pause_unenrolled: bool,
+    /// Seconds since dispatch, by the timestamp of the block the message was
+    /// dispatched in, past which a message is held for explicit release
+    /// through the admin endpoint instead of processed. If unset, messages
+    /// of any age are processed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_message_age: Option<u64>,
+    /// Messages before the last processed message that a restarted
+    /// processor inspects again, in case the home reorganized them. If
+    /// unset, a small default applies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    resume_rewind: Option<u32>,
 });
 
+/// Window of history reconciled at startup
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum ReconciliationLookback {
+    /// The latest leaves
+    Leaves(u32),
+    /// Messages committed to by an update within the last seconds. Messages
+    /// not yet committed are always reconciled
+    Seconds(u64),
+}
+
+impl std::str::FromStr for ReconciliationLookback {
+    type Err = String;
+
+    /// Parse `leaves:<count>` or `seconds:<seconds>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid reconciliation lookback {}", s);
+        let (unit, amount) = s.split_once(':').ok_or_else(invalid)?;
+        match unit {
+            "leaves" => amount.parse().map(Self::Leaves).map_err(|_| invalid()),
+            "seconds" => amount.parse().map(Self::Seconds).map_err(|_| invalid()),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Message processing order
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum ProcessingOrder {
+    /// Process messages by nonce. A message that fails to process does not
+    /// hold back later messages.
+    Global,
+    /// Hold later messages from a sender until its earlier messages are
+    /// processed on the replica
+    PerSenderNonce,
+}
+
+impl Default for ProcessingOrder {
+    fn default() -> Self {
+        Self::Global
+    }
+}
+
 decl_env_overrides!(Processor {self, {
     if let Ok(var) = std::env::var("PROCESSOR_ALLOWED") {
         let allowed = var
@@ -56,6 +180,94 @@ decl_env_overrides!(Processor {self, {
     ) {
         self.s3 = Some(S3Config { bucket, region })
     }
+    if let Ok(var) = std::env::var("PROCESSOR_ORDERING") {
+        self.ordering = serde_json::from_value(serde_json::Value::String(var))
+            .expect("invalid PROCESSOR_ORDERING");
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_RELEASED") {
+        self.released = var
+            .split(',')
+            .map(|v| v.parse::<H256>().expect("invalid PROCESSOR_RELEASED"))
+            .collect();
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_EXTRA_DELAY_SECONDS") {
+        self.extra_delay_seconds = var
+            .split(',')
+            .map(|entry| {
+                let (network, seconds) = entry
+                    .split_once(':')
+                    .expect("invalid PROCESSOR_EXTRA_DELAY_SECONDS");
+                let seconds = seconds
+                    .parse::<u64>()
+                    .expect("invalid PROCESSOR_EXTRA_DELAY_SECONDS");
+                (network.to_owned(), seconds)
+            })
+            .collect();
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_EXTRA_DELAY_PATH") {
+        self.extra_delay_path = Some(var.into());
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_CLOCK_SKEW_ALERT_SECONDS") {
+        self.clock_skew_alert_seconds = Some(
+            var.parse::<u64>()
+                .expect("invalid PROCESSOR_CLOCK_SKEW_ALERT_SECONDS"),
+        );
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_CLOCK_SKEW_MARGIN_SECONDS") {
+        self.clock_skew_margin_seconds = Some(
+            var.parse::<u64>()
+                .expect("invalid PROCESSOR_CLOCK_SKEW_MARGIN_SECONDS"),
+        );
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_CONFIRM_HORIZON_SECONDS") {
+        self.confirm_horizon_seconds = Some(
+            var.parse::<u64>()
+                .expect("invalid PROCESSOR_CONFIRM_HORIZON_SECONDS"),
+        );
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_DISPATCH_RATE_ALERT_MULTIPLE") {
+        self.dispatch_rate_alert_multiple = Some(
+            var.parse::<f64>()
+                .expect("invalid PROCESSOR_DISPATCH_RATE_ALERT_MULTIPLE"),
+        );
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_PAUSED") {
+        self.paused = var
+            .split(',')
+            .map(|entry| {
+                let (network, reason) = entry
+                    .split_once(':')
+                    .expect("invalid PROCESSOR_PAUSED");
+                (network.to_owned(), reason.to_owned())
+            })
+            .collect();
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_ADMIN_PORT") {
+        self.admin_port = Some(var.parse::<u16>().expect("invalid PROCESSOR_ADMIN_PORT"));
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_ATTESTED_ROOTS_ONLY") {
+        self.attested_roots_only = var.split(',').map(String::from).collect();
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_RECONCILIATION_LOOKBACK") {
+        self.reconciliation_lookback = Some(
+            var.parse()
+                .expect("invalid PROCESSOR_RECONCILIATION_LOOKBACK"),
+        );
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_PAUSE_UNENROLLED") {
+        self.pause_unenrolled = var
+            .parse::<bool>()
+            .expect("invalid PROCESSOR_PAUSE_UNENROLLED");
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_MAX_MESSAGE_AGE") {
+        self.max_message_age = Some(
+            var.parse::<u64>()
+                .expect("invalid PROCESSOR_MAX_MESSAGE_AGE"),
+        );
+    }
+    if let Ok(var) = std::env::var("PROCESSOR_RESUME_REWIND") {
+        self.resume_rewind = Some(var.parse::<u32>().expect("invalid PROCESSOR_RESUME_REWIND"));
+    }
 }});
 
 #[cfg(test)]
@@ -102,6 +314,42 @@ mod test {
                     region: "region-1".to_string(),
                 })
             );
+            assert_eq!(config.ordering, ProcessingOrder::PerSenderNonce);
+            assert_eq!(
+                config.released,
+                HashSet::from([H256::from_str(
+                    "0x2222222222222222222222222222222222222222222222222222222222222222",
+                )
+                .unwrap()])
+            );
+            assert_eq!(
+                config.extra_delay_seconds,
+                HashMap::from([("chain1".to_string(), 3600), ("chain2".to_string(), 60)])
+            );
+            assert_eq!(
+                config.extra_delay_path,
+                Some(PathBuf::from("/tmp/processor-delays.json"))
+            );
+            assert_eq!(config.clock_skew_alert_seconds, Some(45));
+            assert_eq!(config.clock_skew_margin_seconds, Some(10));
+            assert_eq!(config.confirm_horizon_seconds, Some(31536000));
+            assert_eq!(config.dispatch_rate_alert_multiple, Some(50.0));
+            assert_eq!(
+                config.paused,
+                HashMap::from([("chain2".to_string(), "bridge-upgrade".to_string())])
+            );
+            assert_eq!(config.admin_port, Some(9092));
+            assert_eq!(
+                config.attested_roots_only,
+                HashSet::from(["chain3".to_string()])
+            );
+            assert_eq!(
+                config.reconciliation_lookback,
+                Some(ReconciliationLookback::Seconds(259200))
+            );
+            assert!(config.pause_unenrolled);
+            assert_eq!(config.max_message_age, Some(86400));
+            assert_eq!(config.resume_rewind, Some(3));
             assert_eq!(config.interval, 999);
         });
     }
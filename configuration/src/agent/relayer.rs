@@ -1,6 +1,71 @@
 //! Relayer public configuration
 
 use crate::{decl_config, decl_env_overrides};
+use std::collections::{HashMap, HashSet};
 
-decl_config!(Relayer {});
-decl_env_overrides!(Relayer {});
+decl_config!(Relayer {
+    /// Replicas supporting batched updates. When such a replica is several
+    /// updates behind, only the latest update of a verified chain is relayed.
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    batched_replicas: HashSet<String>,
+    /// Seconds after which a home update not yet seen on a replica is
+    /// logged as a warning. Defaults to one hour
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unmatched_update_warn_seconds: Option<u64>,
+    /// Destination networks paused at startup, with the reason for each.
+    /// Corridors can also be paused and resumed through the admin endpoint
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    paused: HashMap<String, String>,
+    /// Port of the admin endpoint pausing and resuming corridors. If unset,
+    /// the endpoint is not served
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    admin_port: Option<u16>,
+});
+
+decl_env_overrides!(Relayer {self, {
+    if let Ok(var) = std::env::var("RELAYER_UNMATCHED_UPDATE_WARN_SECONDS") {
+        self.unmatched_update_warn_seconds = Some(
+            var.parse::<u64>()
+                .expect("invalid RELAYER_UNMATCHED_UPDATE_WARN_SECONDS"),
+        );
+    }
+    if let Ok(var) = std::env::var("RELAYER_PAUSED") {
+        self.paused = var
+            .split(',')
+            .map(|entry| {
+                let (network, reason) = entry
+                    .split_once(':')
+                    .expect("invalid RELAYER_PAUSED");
+                (network.to_owned(), reason.to_owned())
+            })
+            .collect();
+    }
+    if let Ok(var) = std::env::var("RELAYER_ADMIN_PORT") {
+        self.admin_port = Some(var.parse::<u16>().expect("invalid RELAYER_ADMIN_PORT"));
+    }
+}});
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nomad_test::test_utils;
+
+    #[test]
+    #[serial_test::serial]
+    fn it_overrides_config_from_env() {
+        test_utils::run_test_with_env_sync("../fixtures/env.test-agents", move || {
+            let mut config = RelayerConfig::default();
+            config.load_env_overrides();
+            assert_eq!(config.unmatched_update_warn_seconds, Some(600));
+            assert_eq!(
+                config.paused,
+                HashMap::from([
+                    ("chain1".to_string(), "incident-42".to_string()),
+                    ("chain3".to_string(), "deprecated".to_string()),
+                ])
+            );
+            assert_eq!(config.admin_port, Some(9093));
+            assert_eq!(config.interval, 999);
+        });
+    }
+}
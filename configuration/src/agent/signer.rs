@@ -33,7 +33,7 @@ use nomad_types::HexString;
 /// "asdjf"
 /// 38
 /// ```
-#[derive(Clone, PartialEq, serde::Deserialize)]
+#[derive(Clone, PartialEq, serde::Deserialize, schemars::JsonSchema)]
 #[serde(untagged, rename_all = "camelCase")]
 pub enum SignerConf {
     /// A local hex key, hex string of private key, with or without 0x prefix
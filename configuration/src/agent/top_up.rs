@@ -0,0 +1,106 @@
+//! Testnet signer top-up configuration
+
+use nomad_types::deser_nomad_u64;
+
+/// Chain ids of production networks. Top-ups can never be enabled for these.
+pub const MAINNET_CHAIN_IDS: &[u64] = &[
+    1,          // ethereum
+    10,         // optimism
+    56,         // bsc
+    100,        // gnosis
+    137,        // polygon
+    250,        // fantom
+    1284,       // moonbeam
+    1285,       // moonriver
+    2001,       // milkomeda c1
+    9001,       // evmos
+    42161,      // arbitrum
+    42220,      // celo
+    43114,      // avalanche
+    1313161554, // aurora
+];
+
+/// Automatic top-up of an agent's transaction signer on a test network.
+/// Funds are requested from `faucetUrl` if set, otherwise transferred from the
+/// network's funder key in the secrets.
+#[derive(
+    Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct TopUpConfig {
+    /// Must be set for top-ups to run. Guards against enabling top-ups on a
+    /// production network by accident.
+    #[serde(default)]
+    pub is_test_network: bool,
+    /// Balance floor in wei. The signer is topped up when below it
+    #[serde(deserialize_with = "deser_nomad_u64")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
+    pub floor: u64,
+    /// Amount in wei requested per top-up
+    #[serde(deserialize_with = "deser_nomad_u64")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
+    pub amount: u64,
+    /// Minimum seconds between two top-ups
+    #[serde(default = "default_cooldown_seconds")]
+    pub cooldown_seconds: u64,
+    /// Maximum number of top-ups in any 24 hour window
+    #[serde(default = "default_max_per_day")]
+    pub max_per_day: u32,
+    /// Seconds between balance checks
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    /// Faucet endpoint. Receives a JSON POST of `{"address", "amount"}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub faucet_url: Option<String>,
+}
+
+fn default_cooldown_seconds() -> u64 {
+    3600
+}
+
+fn default_max_per_day() -> u32 {
+    4
+}
+
+fn default_interval() -> u64 {
+    60
+}
+
+impl TopUpConfig {
+    /// Ensure top-ups are explicitly flagged for a test network and that the
+    /// chain id is not a known mainnet
+    pub fn validate(&self, network: &str, chain_id: u64) -> eyre::Result<()> {
+        eyre::ensure!(
+            self.is_test_network,
+            "Top-up for {} requires isTestNetwork to be set",
+            network,
+        );
+        eyre::ensure!(
+            !MAINNET_CHAIN_IDS.contains(&chain_id),
+            "Top-up cannot be enabled for {}: chain id {} is a mainnet",
+            network,
+            chain_id,
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_refuses_mainnets_and_unflagged_networks() {
+        let mut config: TopUpConfig =
+            serde_json::from_str(r#"{"isTestNetwork": true, "floor": "0x10", "amount": 100}"#)
+                .unwrap();
+        assert_eq!(config.floor, 16);
+        assert_eq!(config.max_per_day, default_max_per_day());
+
+        assert!(config.validate("goerli", 5).is_ok());
+        assert!(config.validate("ethereum", 1).is_err());
+
+        config.is_test_network = false;
+        assert!(config.validate("goerli", 5).is_err());
+    }
+}
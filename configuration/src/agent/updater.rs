@@ -2,5 +2,107 @@
 
 use crate::{decl_config, decl_env_overrides};
 
-decl_config!(Updater {});
-decl_env_overrides!(Updater {});
+decl_config!(Updater {
+    /// Active/standby lease. If unset, the updater always signs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lease: Option<UpdaterLeaseConfig>,
+    /// Port of the admin endpoint, on which a lease handoff is requested.
+    /// Only served to loopback clients bearing `ADMIN_TOKEN`. Requires a
+    /// lease.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    admin_port: Option<u16>,
+    /// Sanity bound on the home's queue length. Above it, the updater only
+    /// attests to the queue end, committing the whole backlog at once. If
+    /// unset, the queue is not checked. Not supported by Substrate homes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_queue_length: Option<u64>,
+    /// Also check for updates when the home indexer stores new dispatches,
+    /// instead of only every interval. The pending block's suggested update
+    /// decides whether to check, but only the confirmed one is ever signed.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    dispatch_trigger: bool,
+});
+
+decl_env_overrides!(Updater {self, {
+    if let Ok(instance_id) = std::env::var("UPDATER_LEASE_INSTANCE_ID") {
+        let mut lease = self.lease.clone().unwrap_or_default();
+        lease.instance_id = instance_id;
+        self.lease = Some(lease);
+    }
+    if let Ok(table) = std::env::var("UPDATER_LEASE_TABLE") {
+        let lease = self
+            .lease
+            .as_mut()
+            .expect("UPDATER_LEASE_TABLE is set but no updater lease is configured");
+        lease.table = table;
+    }
+    if let Ok(var) = std::env::var("UPDATER_ADMIN_PORT") {
+        self.admin_port = Some(var.parse::<u16>().expect("invalid UPDATER_ADMIN_PORT"));
+    }
+}});
+
+/// Updater active/standby lease configuration
+#[derive(
+    Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdaterLeaseConfig {
+    /// Unique id of this updater instance
+    pub instance_id: String,
+    /// DynamoDB table shared by the instances, holding their lease. Keyed by
+    /// the string attribute `lease_key`. The region is read from the AWS
+    /// environment.
+    pub table: String,
+    /// Seconds between lease heartbeats
+    #[serde(default = "default_heartbeat_seconds")]
+    pub heartbeat_seconds: u64,
+    /// Seconds without a heartbeat before a standby takes over the lease
+    /// without a handoff. Must exceed four heartbeats, as the holder stops
+    /// signing that long before a takeover if it cannot renew its lease.
+    #[serde(default = "default_takeover_seconds")]
+    pub takeover_seconds: u64,
+}
+
+fn default_heartbeat_seconds() -> u64 {
+    10
+}
+
+fn default_takeover_seconds() -> u64 {
+    300
+}
+
+impl Default for UpdaterLeaseConfig {
+    fn default() -> Self {
+        Self {
+            instance_id: Default::default(),
+            table: Default::default(),
+            heartbeat_seconds: default_heartbeat_seconds(),
+            takeover_seconds: default_takeover_seconds(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nomad_test::test_utils;
+
+    #[test]
+    #[serial_test::serial]
+    fn it_overrides_config_from_env() {
+        test_utils::run_test_with_env_sync("../fixtures/env.test-agents", move || {
+            let mut config = UpdaterConfig::default();
+            config.load_env_overrides();
+            assert_eq!(
+                config.lease,
+                Some(UpdaterLeaseConfig {
+                    instance_id: "updater-a".to_string(),
+                    table: "updater-leases".to_string(),
+                    ..Default::default()
+                })
+            );
+            assert_eq!(config.admin_port, Some(9999));
+            assert_eq!(config.interval, 999);
+        });
+    }
+}
@@ -6,5 +6,33 @@ use crate::{decl_config, decl_env_overrides};
 // home and flag fraud on any corresponding replica chains. We assume the
 // watcher has permissions over connection managers on each replica chain for
 // now. This is likely to change in the future.
-decl_config!(Watcher {});
-decl_env_overrides!(Watcher {});
+decl_config!(Watcher {
+    /// Sweep update history behind each contract's frontier once at startup
+    #[serde(default)]
+    audit_history: bool,
+});
+
+decl_env_overrides!(Watcher {self, {
+    if let Ok(var) = std::env::var("WATCHER_AUDIT_HISTORY") {
+        self.audit_history = var
+            .parse::<bool>()
+            .expect("invalid WATCHER_AUDIT_HISTORY");
+    }
+}});
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nomad_test::test_utils;
+
+    #[test]
+    #[serial_test::serial]
+    fn it_overrides_config_from_env() {
+        test_utils::run_test_with_env_sync("../fixtures/env.test-agents", move || {
+            let mut config = WatcherConfig::default();
+            config.load_env_overrides();
+            assert!(config.audit_history);
+            assert_eq!(config.interval, 999);
+        });
+    }
+}
@@ -8,7 +8,17 @@ use nomad_types::{NomadIdentifier, NomadLocator, Proxy};
 use crate::network::CustomTokenSpecifier;
 
 /// Deploy-time custom tokens
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, Eq, PartialEq, Hash)]
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    Eq,
+    PartialEq,
+    Hash,
+)]
 #[serde(rename_all = "camelCase")]
 pub struct DeployedCustomToken {
     /// Token domain and ID
@@ -26,11 +36,12 @@ pub struct DeployedCustomToken {
 }
 
 /// EVM Bridge Contracts
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct EthereumBridgeDeploymentInfo {
     /// Contract Deploy Height
     #[serde(default, deserialize_with = "deser_nomad_u32")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
     pub deploy_height: u32,
     /// Bridge Route proxy
     pub bridge_router: Proxy,
@@ -47,16 +58,19 @@ pub struct EthereumBridgeDeploymentInfo {
 }
 
 /// Empty Substrate contracts
-#[derive(Default, Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Default, Debug, Copy, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
 #[serde(rename_all = "camelCase")]
 pub struct SubstrateBridgeDeploymentInfo {
     /// Contract Deploy Height
     #[serde(default, deserialize_with = "deser_nomad_u32")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
     pub deploy_height: u32,
 }
 
 /// Bridge contract abstraction
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(untagged)]
 pub enum BridgeDeploymentInfo {
     /// EVM Bridge Contracts
@@ -76,7 +90,7 @@ const fn is_false(b: &bool) -> bool {
 }
 
 /// EVM Bridge Contracts
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AppConfig {
     display_name: String,
@@ -90,7 +104,7 @@ pub struct AppConfig {
 }
 
 /// Configuration for bridge contracts
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BridgeConfiguration {
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -1,4 +1,7 @@
 //! Ethereum/EVM configuration types
 
+mod rpc_errors;
+pub use rpc_errors::*;
+
 mod submitter;
 pub use submitter::*;
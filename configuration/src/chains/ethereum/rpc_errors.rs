@@ -0,0 +1,64 @@
+//! JSON-RPC error classification types
+
+/// How agents react to a JSON-RPC error
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum RpcErrorClass {
+    /// Transient, such as rate limiting. The request is retried with backoff.
+    Retryable,
+    /// The node is behind the requested block, such as a header not found
+    /// near the head. Indexers rewind a few blocks and retry.
+    RewindAndRetry,
+    /// The state of the requested block was pruned by the node, such as a
+    /// missing trie node on a non-archive node. Returned at once, and reads
+    /// move within the provider's history.
+    HistoryUnavailable,
+    /// The requested block range exceeds the provider's cap on log queries.
+    /// Indexers learn the cap and request smaller ranges.
+    RangeTooLarge,
+    /// Retrying cannot succeed, such as a revert. Returned at once.
+    Fatal,
+}
+
+impl RpcErrorClass {
+    /// Name used in metric labels
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RpcErrorClass::Retryable => "retryable",
+            RpcErrorClass::RewindAndRetry => "rewindAndRetry",
+            RpcErrorClass::HistoryUnavailable => "historyUnavailable",
+            RpcErrorClass::RangeTooLarge => "rangeTooLarge",
+            RpcErrorClass::Fatal => "fatal",
+        }
+    }
+}
+
+/// Pattern classifying JSON-RPC errors. An error matches if it has the
+/// pattern's code, when set, and its message matches the pattern's
+/// case-insensitive regex, when set.
+#[derive(
+    Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcErrorPattern {
+    /// Name counted in classification metrics
+    pub name: String,
+    /// JSON-RPC error code, or HTTP status
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<i64>,
+    /// Regex over the error message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Class of matching errors
+    pub class: RpcErrorClass,
+}
@@ -1,7 +1,7 @@
 use crate::agent::SignerConf;
 
 /// Configuration for tx submission through Gelato relay
-#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct GelatoConf {
     /// Sponsor signer configuration
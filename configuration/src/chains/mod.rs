@@ -70,6 +70,30 @@ impl Default for Connection {
     }
 }
 
+impl schemars::JsonSchema for Connection {
+    fn schema_name() -> String {
+        "Connection".to_owned()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        use schemars::schema::{InstanceType, Metadata, SchemaObject, StringValidation};
+
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some("^(https?|wss?)://".to_owned()),
+                ..Default::default()
+            })),
+            metadata: Some(Box::new(Metadata {
+                description: Some("Fully qualified http(s) or websocket URI".to_owned()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 impl<'de> serde::Deserialize<'de> for Connection {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -84,7 +108,7 @@ impl<'de> serde::Deserialize<'de> for Connection {
 ///
 /// Specify the chain name (enum variant) in toml under the `chain` key
 /// Specify the connection details as a toml object under the `connection` key.
-#[derive(Clone, Debug, serde::Deserialize, PartialEq)]
+#[derive(Clone, Debug, serde::Deserialize, schemars::JsonSchema, PartialEq)]
 #[serde(tag = "rpcStyle", content = "connection", rename_all = "camelCase")]
 pub enum ChainConf {
     /// Ethereum configuration
@@ -124,7 +148,7 @@ impl ChainConf {
 }
 
 /// Transaction submssion configuration for some chain.
-#[derive(Clone, Debug, serde::Deserialize, PartialEq)]
+#[derive(Clone, Debug, serde::Deserialize, schemars::JsonSchema, PartialEq)]
 #[serde(tag = "rpcStyle", rename_all = "camelCase")]
 pub enum TxSubmitterConf {
     /// Ethereum configuration
@@ -23,8 +23,9 @@ impl FromStr for SubmitterType {
 }
 
 /// Local or relay-based transaction submission
-#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, schemars::JsonSchema)]
 #[serde(tag = "submitterType", content = "submitter", rename_all = "camelCase")]
+#[schemars(rename = "SubstrateTxSubmitterConf")]
 pub enum TxSubmitterConf {
     /// Signer configuration for local signer
     Local(SignerConf),
@@ -6,11 +6,12 @@ use nomad_types::deser_nomad_u32;
 use nomad_types::{NomadIdentifier, Proxy};
 
 /// Evm Core Contracts
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct EthereumCoreDeploymentInfo {
     /// Contract Deploy Height
     #[serde(default, deserialize_with = "deser_nomad_u32")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
     pub deploy_height: u32,
     /// UBC address
     pub upgrade_beacon_controller: NomadIdentifier,
@@ -28,17 +29,20 @@ pub struct EthereumCoreDeploymentInfo {
 }
 
 /// Empty Substrate contracts
-#[derive(Default, Copy, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Default, Copy, Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
 #[serde(rename_all = "camelCase")]
 pub struct SubstrateCoreDeploymentInfo {
     /// Contract Deploy Height
     #[serde(default, deserialize_with = "deser_nomad_u32")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
     pub deploy_height: u32,
     // TODO: add replicas for substrate rollout v2
 }
 
 /// Core Contract abstract
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(untagged)]
 pub enum CoreDeploymentInfo {
     /// EVM Core
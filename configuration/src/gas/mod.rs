@@ -7,7 +7,7 @@ mod defaults;
 use defaults::EVM_DEFAULT;
 
 /// Gas configuration for core and bridge contract methods
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct NomadGasConfig {
     /// Core gas limits
@@ -17,7 +17,7 @@ pub struct NomadGasConfig {
 }
 
 /// Gas configuration for core contract methods
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct CoreGasConfig {
     /// Home gas limits
@@ -29,7 +29,7 @@ pub struct CoreGasConfig {
 }
 
 /// Gas limits specifically for a home update call
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct HomeUpdateGasLimit {
     /// Per message additional gas cost
@@ -39,7 +39,7 @@ pub struct HomeUpdateGasLimit {
 }
 
 /// Home gas limits
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct HomeGasLimits {
     /// Update
@@ -51,7 +51,7 @@ pub struct HomeGasLimits {
 }
 
 /// Replica gas limits
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ReplicaGasLimits {
     /// Update
@@ -67,7 +67,7 @@ pub struct ReplicaGasLimits {
 }
 
 /// Connection manager gas limits
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ConnectionManagerGasLimits {
     /// Owner unenroll replica
@@ -77,7 +77,7 @@ pub struct ConnectionManagerGasLimits {
 }
 
 /// Gas configuration for bridge contract methods
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct BridgeGasConfig {
     /// BridgeRouter gas limits
@@ -87,7 +87,7 @@ pub struct BridgeGasConfig {
 }
 
 /// Gas limits for BridgeRouter
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct BridgeRouterGasLimits {
     /// Send
@@ -95,7 +95,7 @@ pub struct BridgeRouterGasLimits {
 }
 
 /// Gas limits for EthHelper
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct EthHelperGasLimits {
     /// Send
@@ -104,6 +104,83 @@ pub struct EthHelperGasLimits {
     pub send_to_evm_like: u64,
 }
 
+/// How a chain's transactions are priced
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FeeMode {
+    /// Legacy transactions with a `gasPrice`
+    Legacy,
+    /// EIP-1559 transactions with a max fee and priority fee from the
+    /// chain's fee history, or with a fixed max fee if `maxFeeGwei` is set.
+    /// Legacy on chains reporting no base fee
+    Eip1559,
+    /// EIP-1559 transactions bidding the `priorityFeePercentile` percentile
+    /// of the priority fees paid in recent blocks, with a max fee as in
+    /// `eip1559`. Legacy on chains reporting no base fee
+    Oracle,
+}
+
+impl Default for FeeMode {
+    fn default() -> Self {
+        FeeMode::Legacy
+    }
+}
+
+fn default_max_priority_fee_gwei() -> f64 {
+    2.0
+}
+
+/// Transaction fee and gas limit settings of a chain
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GasConfig {
+    /// How transactions are priced
+    #[serde(default)]
+    pub fee_mode: FeeMode,
+    /// Priority fee of EIP-1559 transactions, in gwei
+    #[serde(default = "default_max_priority_fee_gwei")]
+    pub max_priority_fee_gwei: f64,
+    /// Multiple of the base fee EIP-1559 transactions allow for on top of
+    /// the priority fee. Defaults to 2, covering six full blocks of base
+    /// fee increases
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fee_multiplier: Option<f64>,
+    /// Fixed max fee of EIP-1559 transactions, in gwei, in place of one
+    /// from the fee history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fee_gwei: Option<f64>,
+    /// Percentile of recent blocks' priority fees bid by oracle priced
+    /// transactions. Defaults to 50
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_fee_percentile: Option<f64>,
+    /// Multiple of the estimated gas used as the gas limit of transactions
+    /// without a configured limit. Defaults to 1.25
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_limit_multiplier: Option<f64>,
+    /// Least gas limit of transactions with estimated limits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_gas_limit: Option<u64>,
+    /// Most gas a transaction with an estimated limit may use. Transactions
+    /// whose adjusted estimate exceeds it are not submitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_gas_limit: Option<u64>,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        Self {
+            fee_mode: Default::default(),
+            max_priority_fee_gwei: default_max_priority_fee_gwei(),
+            max_fee_multiplier: None,
+            max_fee_gwei: None,
+            priority_fee_percentile: None,
+            gas_limit_multiplier: None,
+            min_gas_limit: None,
+            max_gas_limit: None,
+        }
+    }
+}
+
 pub(crate) mod gas_map_ser {
     use serde::Deserializer;
     use std::collections::HashMap;
@@ -174,6 +251,35 @@ pub(crate) mod gas_map_ser {
         }
     }
 
+    /// Schema for the gas map. Each entry is either a full gas config or the
+    /// name of a preset.
+    pub(crate) fn schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        use schemars::schema::{InstanceType, ObjectValidation, SchemaObject, SubschemaValidation};
+
+        let preset = SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            enum_values: Some(vec!["evmDefault".into()]),
+            ..Default::default()
+        };
+        let entry = SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![gen.subschema_for::<NomadGasConfig>(), preset.into()]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+
+        SchemaObject {
+            instance_type: Some(InstanceType::Object.into()),
+            object: Some(Box::new(ObjectValidation {
+                additional_properties: Some(Box::new(entry.into())),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+
     pub(crate) fn deserialize<'de, D>(d: D) -> Result<HashMap<String, NomadGasConfig>, D::Error>
     where
         D: Deserializer<'de>,
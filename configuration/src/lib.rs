@@ -29,6 +29,9 @@ pub use secrets::*;
 pub mod gas;
 pub use gas::*;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod schema;
+
 mod utils;
 
 #[cfg(target_arch = "wasm32")]
@@ -48,7 +51,9 @@ use bridge::{AppConfig, BridgeDeploymentInfo};
 use network::{Domain, NetworkInfo};
 
 /// S3 Configuration
-#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
+#[derive(
+    Debug, Default, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema, PartialEq,
+)]
 #[serde(rename_all = "camelCase")]
 pub struct S3Config {
     /// Bucket
@@ -58,7 +63,7 @@ pub struct S3Config {
 }
 
 /// A Nomad configuration json format
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct NomadConfig {
     /// Config version
@@ -79,6 +84,7 @@ pub struct NomadConfig {
     agent: HashMap<String, AgentConfig>,
     /// Optional per-chain gas configurations
     #[serde(deserialize_with = "gas::gas_map_ser::deserialize")]
+    #[schemars(schema_with = "gas::gas_map_ser::schema")]
     gas: HashMap<String, NomadGasConfig>,
     /// Bridge application GUI configuration
     pub bridge_gui: HashMap<String, AppConfig>,
@@ -136,6 +142,19 @@ impl NomadConfig {
                 network
             );
 
+            // Top-ups may only be enabled for test networks
+            for (top_up_network, top_up) in self.agent.get(network).unwrap().top_up.iter() {
+                let chain_id = self
+                    .protocol
+                    .networks
+                    .get(top_up_network)
+                    .map(|domain| domain.specs.chain_id)
+                    .ok_or_else(|| {
+                        eyre::eyre!("Top-up configured for unknown network '{}'", top_up_network)
+                    })?;
+                top_up.validate(top_up_network, chain_id)?;
+            }
+
             // Ensure every remote network the current `network` is connected to
             // has a core and the core has a replica for `network`.
             for connection in domain.connections.iter() {
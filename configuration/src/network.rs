@@ -1,6 +1,6 @@
 //! Core deploy information
 
-use crate::bridge::BridgeConfiguration;
+use crate::{bridge::BridgeConfiguration, GasConfig};
 use nomad_types::{
     deser_nomad_u32, deser_nomad_u64, deser_nomad_u8, NameOrDomain, NomadIdentifier, NomadLocator,
 };
@@ -8,7 +8,16 @@ use std::collections::{HashMap, HashSet};
 
 /// Governance details
 #[derive(
-    Default, Debug, Clone, Copy, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize,
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    Eq,
+    PartialEq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
 )]
 #[serde(rename_all = "camelCase")]
 pub struct Governance {
@@ -16,15 +25,17 @@ pub struct Governance {
     pub recovery_manager: NomadIdentifier,
     /// Length of the recovery timelock (in seconds) on this domain
     #[serde(deserialize_with = "deser_nomad_u64")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
     pub recovery_timelock: u64,
 }
 
 /// Nomad Contract deploy-time config
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ContractConfig {
     /// Optimsitic seconds for replicas to wait
     #[serde(deserialize_with = "deser_nomad_u64")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
     pub optimistic_seconds: u64,
     /// List of updaters for this network
     pub updater: NomadIdentifier,
@@ -35,33 +46,51 @@ pub struct ContractConfig {
 }
 
 /// Core network information
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct NetworkSpecs {
     /// EVM chain id. 0 for non-EVM chains
     #[serde(default, deserialize_with = "deser_nomad_u64")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
     pub chain_id: u64,
     /// Block time on the network
     #[serde(deserialize_with = "deser_nomad_u64")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
     pub block_time: u64,
     /// Timelag for agents using the timelag provider
     #[serde(deserialize_with = "deser_nomad_u8")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
     pub finalization_blocks: u8,
     /// True if the networks supports 1559. Otherwise false
     #[serde(default)]
     pub supports_1559: bool,
     /// Desired number of confirmations on transactions
     #[serde(deserialize_with = "deser_nomad_u64")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
     pub confirmations: u64,
     /// Block explorer URL
     pub block_explorer: String,
     /// Number of blocks to include in a page while indexing
     #[serde(deserialize_with = "deser_nomad_u32")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
     pub index_page_size: u32,
+    /// Transaction fee settings
+    #[serde(default)]
+    pub fees: GasConfig,
 }
 
 /// Specifier for deploy-time custom bridge tokens
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, Hash, Eq, PartialEq)]
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    Hash,
+    Eq,
+    PartialEq,
+)]
 #[serde(rename_all = "camelCase")]
 pub struct CustomTokenSpecifier {
     /// Token domain and id
@@ -75,13 +104,14 @@ pub struct CustomTokenSpecifier {
 }
 
 /// Core network information
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Domain {
     /// Network name
     pub name: String,
     /// Network domain identifier
     #[serde(deserialize_with = "deser_nomad_u32")]
+    #[schemars(schema_with = "nomad_types::nomad_number_schema")]
     pub domain: u32,
     /// List of connections to other networks
     pub connections: HashSet<String>,
@@ -94,7 +124,7 @@ pub struct Domain {
 }
 
 /// Core deployment info
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct NetworkInfo {
     /// The domain and ID of the governor
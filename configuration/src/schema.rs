@@ -0,0 +1,87 @@
+//! JSON schemas for the config and secrets files
+//!
+//! Schemas are generated from the same types the loader deserializes into,
+//! so they are versioned alongside this crate. Unknown fields are accepted,
+//! matching the runtime loader.
+
+use schemars::schema::RootSchema;
+
+use crate::{AgentSecrets, NomadConfig};
+
+fn versioned(mut schema: RootSchema) -> RootSchema {
+    schema.schema.extensions.insert(
+        "version".to_owned(),
+        serde_json::Value::String(env!("CARGO_PKG_VERSION").to_owned()),
+    );
+    schema
+}
+
+/// JSON schema for a `NomadConfig` file
+pub fn config_schema() -> RootSchema {
+    versioned(schemars::schema_for!(NomadConfig))
+}
+
+/// JSON schema for an `AgentSecrets` file
+pub fn secrets_schema() -> RootSchema {
+    versioned(schemars::schema_for!(AgentSecrets))
+}
+
+/// Config and secrets schemas, tagged with the crate version
+pub fn agent_schemas() -> serde_json::Value {
+    serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "config": config_schema(),
+        "secrets": secrets_schema(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::Path;
+
+    fn assert_validates(schema: &RootSchema, path: &Path) {
+        let schema = serde_json::to_value(schema).unwrap();
+        let compiled = jsonschema::JSONSchema::compile(&schema).expect("invalid schema");
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        let instance: serde_json::Value = serde_json::from_str(&contents).unwrap();
+
+        if let Err(errors) = compiled.validate(&instance) {
+            let errors: Vec<_> = errors
+                .map(|e| format!("{}: {}", e.instance_path, e))
+                .collect();
+            panic!(
+                "{} does not match schema:\n{}",
+                path.display(),
+                errors.join("\n")
+            );
+        }
+    }
+
+    #[test]
+    fn every_example_config_validates() {
+        let schema = config_schema();
+
+        let configs = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/configs"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "json"));
+        let fixtures = ["external_config.json", "killswitch_config.json"]
+            .iter()
+            .map(|name| Path::new("../fixtures").join(name));
+
+        for path in configs.chain(fixtures) {
+            assert_validates(&schema, &path);
+        }
+    }
+
+    #[test]
+    fn every_example_secrets_file_validates() {
+        let schema = secrets_schema();
+
+        for name in ["test_secrets.json", "external_secrets.json"] {
+            assert_validates(&schema, &Path::new("../fixtures").join(name));
+        }
+    }
+}
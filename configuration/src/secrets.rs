@@ -9,12 +9,20 @@ use crate::{
     ChainConf, Connection, TxSubmitterConf,
 };
 use eyre::Result;
+use nomad_types::HexString;
 use serde::Deserialize;
 use std::collections::{HashMap, HashSet};
 use std::{fs::File, io::BufReader, path::Path};
 
+/// Role of the key the updater signs updates with
+pub const ATTESTATION_SIGNER_ROLE: &str = "attestationSigner";
+/// Role of the key the watcher signs failure notifications with
+pub const WATCHER_KEY_ROLE: &str = "watcherKey";
+/// Signer roles that can be configured in `signerRoles`
+pub const SIGNER_ROLES: &[&str] = &[ATTESTATION_SIGNER_ROLE, WATCHER_KEY_ROLE];
+
 /// Agent secrets block
-#[derive(Debug, Deserialize, Default, Clone)]
+#[derive(Debug, Deserialize, schemars::JsonSchema, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct AgentSecrets {
     /// RPC endpoints
@@ -23,6 +31,15 @@ pub struct AgentSecrets {
     pub tx_submitters: HashMap<String, TxSubmitterConf>,
     /// Attestation signers
     pub attestation_signer: Option<SignerConf>,
+    /// Signers for named roles. Roles not listed use `attestationSigner`
+    #[serde(default)]
+    pub signer_roles: HashMap<String, SignerConf>,
+    /// Funder keys for testnet top-ups, by network name
+    #[serde(default)]
+    pub top_up_funders: HashMap<String, SignerConf>,
+    /// 32-byte hex key the agent db values are encrypted at rest with
+    #[serde(default)]
+    pub db_encryption_key: Option<HexString<64>>,
 }
 
 impl AgentSecrets {
@@ -48,24 +65,61 @@ impl AgentSecrets {
             secrets
                 .tx_submitters
                 .insert(network.to_owned(), tx_submitter);
+
+            if let Some(funder) = SignerConf::from_env(Some("TOPUP_FUNDER"), Some(&network_upper)) {
+                secrets.top_up_funders.insert(network.to_owned(), funder);
+            }
         }
 
         let attestation_signer = SignerConf::from_env(Some("ATTESTATION_SIGNER"), None);
         secrets.attestation_signer = attestation_signer;
 
+        // a malformed key fails loading rather than leave the db unencrypted
+        if let Ok(key) = std::env::var("DB_ENCRYPTION_KEY") {
+            secrets.db_encryption_key = Some(key.parse().ok()?);
+        }
+
+        for role in SIGNER_ROLES {
+            let prefix = format!("SIGNER_ROLE_{}", env_role(role));
+            if let Some(signer) = SignerConf::from_env(Some(&prefix), None) {
+                secrets.signer_roles.insert(role.to_string(), signer);
+            }
+        }
+
         Some(secrets)
     }
 
+    /// Signer for `role`, falling back to the legacy `attestationSigner`
+    pub fn role_signer(&self, role: &str) -> Option<&SignerConf> {
+        self.signer_roles
+            .get(role)
+            .or(self.attestation_signer.as_ref())
+    }
+
     /// Ensure populated RPCs and transaction signers
     pub fn validate(&self, agent_name: &str, networks: &HashSet<String>) -> Result<()> {
         // TODO: replace agent name with associated type
-        if agent_name == "updater" || agent_name == "watcher" {
+        let role = match agent_name {
+            "updater" => Some(ATTESTATION_SIGNER_ROLE),
+            "watcher" => Some(WATCHER_KEY_ROLE),
+            _ => None,
+        };
+        if let Some(role) = role {
             eyre::ensure!(
-                self.attestation_signer.is_some(),
-                "Must pass in attestation signer for {}",
+                self.role_signer(role).is_some(),
+                "Must pass in {} or attestation signer for {}",
+                role,
                 agent_name,
             )
         }
+        for (role, signer) in self.signer_roles.iter() {
+            eyre::ensure!(
+                SIGNER_ROLES.contains(&role.as_str()),
+                "Unknown signer role {}",
+                role
+            );
+            signer.validate(role)?;
+        }
 
         for network in networks.iter() {
             let chain_conf = self
@@ -111,6 +165,18 @@ impl AgentSecrets {
     }
 }
 
+/// `watcherKey` -> `WATCHER_KEY`
+fn env_role(role: &str) -> String {
+    let mut upper = String::new();
+    for c in role.chars() {
+        if c.is_uppercase() {
+            upper.push('_');
+        }
+        upper.push(c.to_ascii_uppercase());
+    }
+    upper
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -235,6 +301,24 @@ mod test {
             secrets
                 .validate("", networks)
                 .expect("Failed to validate secrets");
+
+            // watcherKey is configured, attestationSigner falls back to the
+            // legacy signer
+            let watcher_key = SignerConf::HexKey(
+                "0x2222222222222222222222222222222222222222222222222222222222222222"
+                    .parse()
+                    .unwrap(),
+            );
+            assert_eq!(secrets.role_signer(WATCHER_KEY_ROLE), Some(&watcher_key));
+            assert_eq!(
+                secrets.role_signer(ATTESTATION_SIGNER_ROLE),
+                Some(&SignerConf::Aws {
+                    id: "dummy_id".into()
+                })
+            );
+            secrets
+                .validate("watcher", networks)
+                .expect("Failed to validate secrets");
         });
     }
 
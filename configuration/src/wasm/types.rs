@@ -34,18 +34,116 @@ export type ProcessorConfig = BaseAgentConfig & {
   denied?: string[];
   subsidizedRemotes?: string[];
   s3?: S3Config;
+  ordering?: "global" | "perSenderNonce";
+  released?: string[];
+  extraDelaySeconds?: Record<string, number>;
+  extraDelayPath?: string;
+  clockSkewAlertSeconds?: number;
+  clockSkewMarginSeconds?: number;
+  confirmHorizonSeconds?: number;
+  dispatchRateAlertMultiple?: number;
+  paused?: Record<string, string>;
+  adminPort?: number;
+  attestedRootsOnly?: string[];
+  reconciliationLookback?: { leaves: number } | { seconds: number };
+  pauseUnenrolled?: boolean;
 };
 
+export interface UpdaterLeaseConfig {
+  instanceId: string;
+  table: string;
+  heartbeatSeconds?: number;
+  takeoverSeconds?: number;
+}
+
+export type UpdaterConfig = BaseAgentConfig & {
+  lease?: UpdaterLeaseConfig;
+  adminPort?: number;
+  maxQueueLength?: number;
+  dispatchTrigger?: boolean;
+};
+
+export type RelayerConfig = BaseAgentConfig & {
+  batchedReplicas?: string[];
+  unmatchedUpdateWarnSeconds?: number;
+  paused?: Record<string, string>;
+  adminPort?: number;
+};
+
+export type WatcherConfig = BaseAgentConfig & {
+  auditHistory?: boolean;
+};
+
+export interface EchoReturnConfig {
+  home: NomadIdentifier;
+  replica: NomadIdentifier;
+}
+
+export interface EchoConfig {
+  recipients: Record<string, NomadIdentifier>;
+  returns: Record<string, EchoReturnConfig>;
+  deadlineSeconds?: number;
+}
+
+export type KathyConfig = BaseAgentConfig & {
+  echo?: EchoConfig;
+};
+
+export interface TopUpConfig {
+  isTestNetwork: boolean;
+  floor: number | string;
+  amount: number | string;
+  cooldownSeconds?: number;
+  maxPerDay?: number;
+  interval?: number;
+  faucetUrl?: string;
+}
+
+export interface RpcErrorPattern {
+  name: string;
+  code?: number;
+  message?: string;
+  class: "retryable" | "rewindAndRetry" | "historyUnavailable" | "rangeTooLarge" | "fatal";
+}
+
+export interface DbWriteBatchConfig {
+  maxRecords: number;
+  maxDelayMs: number;
+}
+
+export interface IndexBackoffConfig {
+  initialDelayMs: number;
+  multiplier: number;
+  maxDelayMs: number;
+  jitter: number;
+}
+
+export interface SignerClaimsConfig {
+  directory?: string;
+  heartbeatSeconds?: number;
+  pauseOnConflict?: boolean;
+}
+
 export interface AgentConfig {
   rpcStyle: string;
   db: string;
   metrics: number;
+  profiling?: boolean;
+  codeCheck?: "off" | "warn" | "strict";
+  auditFile?: string;
+  rpcErrorPatterns?: RpcErrorPattern[];
+  dbWriteBatch?: DbWriteBatchConfig;
+  dbEncryptionMigrate?: boolean;
+  maxBlockRange?: number;
+  indexBackoff?: IndexBackoffConfig;
+  signerClaims?: SignerClaimsConfig;
   logging: LogConfig;
-  updater: BaseAgentConfig;
-  relayer: BaseAgentConfig;
+  updater: UpdaterConfig;
+  relayer: RelayerConfig;
   processor: ProcessorConfig;
-  watcher: BaseAgentConfig;
-  kathy: BaseAgentConfig;
+  watcher: WatcherConfig;
+  kathy: KathyConfig;
+  topUp?: Record<string, TopUpConfig>;
 }
 
 export interface Proxy {
@@ -119,6 +217,18 @@ export interface NetworkSpecs {
   confirmations: number | string;
   blockExplorer: string;
   indexPageSize: number;
+  fees?: GasConfig;
+}
+
+export interface GasConfig {
+  feeMode?: 'legacy' | 'eip1559' | 'oracle';
+  maxPriorityFeeGwei?: number;
+  maxFeeMultiplier?: number;
+  maxFeeGwei?: number;
+  priorityFeePercentile?: number;
+  gasLimitMultiplier?: number;
+  minGasLimit?: number;
+  maxGasLimit?: number;
 }
 
 export interface CustomTokenSpecifier {
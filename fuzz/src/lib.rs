@@ -0,0 +1,55 @@
+//! Fuzz harnesses shared by the `cargo fuzz` targets and the regression
+//! tests replaying committed inputs on stable.
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+
+use accumulator::{Merkle, MerkleProof, NomadProof, NomadTree};
+use nomad_core::{
+    CommittedMessage, Decode, Encode, NomadMessage, RawCommittedMessage, SignedUpdate,
+};
+use std::convert::TryFrom;
+
+/// `NomadMessage` and `RawCommittedMessage` parsing. Any message that parses
+/// must re-encode to the same bytes.
+pub fn nomad_message(data: &[u8]) {
+    if let Ok(message) = NomadMessage::read_from(&mut &data[..]) {
+        assert_eq!(message.to_vec(), data);
+        let _ = message.to_leaf();
+    }
+
+    if let Ok(raw) = RawCommittedMessage::read_from(&mut &data[..]) {
+        assert_eq!(raw.to_vec(), data);
+        let _ = CommittedMessage::try_from(raw);
+    }
+}
+
+/// JSON proof deserialization and verification
+pub fn proof(data: &[u8]) {
+    if let Ok(proof) = serde_json::from_slice::<NomadProof>(data) {
+        let root = proof.root();
+
+        let reserialized = serde_json::to_vec(&proof).expect("!serialize");
+        let roundtripped: NomadProof = serde_json::from_slice(&reserialized).expect("!roundtrip");
+        assert_eq!(roundtripped, proof);
+        assert_eq!(roundtripped.root(), root);
+
+        let _ = NomadTree::default().verify(&proof);
+    }
+}
+
+/// `SignedUpdate` decoding and signer recovery
+pub fn signed_update(data: &[u8]) {
+    if let Ok(signed) = SignedUpdate::read_from(&mut &data[..]) {
+        if let Ok(signer) = signed.recover() {
+            assert!(signed.verify(signer).is_ok());
+        }
+    }
+}
+
+/// `Home.dispatch` calldata decoding
+pub fn dispatch_calldata(data: &[u8]) {
+    if let Ok(message) = nomad_ethereum::decode_dispatch_calldata(data) {
+        assert!(data.len() >= 4 + 32 * 4 + message.body.len());
+    }
+}
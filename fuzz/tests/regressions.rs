@@ -0,0 +1,49 @@
+//! Replay the seed corpus and committed crash reproducers through the fuzz
+//! harnesses. Runs on stable with `cargo test` from `fuzz/`.
+//!
+//! When a fuzz run finds a crash, minimize it with `cargo fuzz tmin`, fix
+//! it, and commit the reproducer under `regressions/<target>/`.
+
+use std::{fs, panic, path::Path};
+
+fn replay(target: &str, harness: fn(&[u8])) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    for dir in ["corpus", "regressions"] {
+        let dir = root.join(dir).join(target);
+        if !dir.exists() {
+            continue;
+        }
+
+        for entry in fs::read_dir(&dir).expect("!read_dir") {
+            let path = entry.expect("!entry").path();
+            let data = fs::read(&path).expect("!read");
+            assert!(
+                panic::catch_unwind(|| harness(&data)).is_ok(),
+                "{} panicked the {} harness",
+                path.display(),
+                target,
+            );
+        }
+    }
+}
+
+#[test]
+fn nomad_message() {
+    replay("nomad_message", nomad_fuzz::nomad_message);
+}
+
+#[test]
+fn proof() {
+    replay("proof", nomad_fuzz::proof);
+}
+
+#[test]
+fn signed_update() {
+    replay("signed_update", nomad_fuzz::signed_update);
+}
+
+#[test]
+fn dispatch_calldata() {
+    replay("dispatch_calldata", nomad_fuzz::dispatch_calldata);
+}
@@ -0,0 +1,85 @@
+//! Cost of encrypting db values at rest on the leaf-insertion path.
+//!
+//! Inserts the same leaves into an unencrypted and an encrypted db, several
+//! rounds each, and compares the fastest round of each. Fails if encryption
+//! slows insertion down by more than 10%.
+
+use ethers::core::types::H256;
+use nomad_base::NomadDB;
+use nomad_core::{db::DbCipher, Encode, NomadMessage, RawCommittedMessage};
+use nomad_test::test_utils::setup_db;
+use std::time::{Duration, Instant};
+
+const LEAVES: u32 = 20_000;
+/// Leaves stored per call, as a page of indexed messages is
+const PAGE: usize = 100;
+const ROUNDS: usize = 5;
+const MAX_SLOWDOWN: f64 = 1.10;
+
+fn leaves() -> Vec<RawCommittedMessage> {
+    (0..LEAVES)
+        .map(|leaf_index| RawCommittedMessage {
+            leaf_index,
+            committed_root: H256::repeat_byte(3),
+            message: NomadMessage {
+                origin: 1000,
+                sender: H256::repeat_byte(1),
+                nonce: leaf_index,
+                destination: 2000,
+                recipient: H256::repeat_byte(2),
+                body: vec![0xab; 128],
+            }
+            .to_vec(),
+            timestamp: Some(1_650_000_000),
+        })
+        .collect()
+}
+
+/// Time inserting `leaves` into a fresh db, encrypted with `key` if given
+fn insert(leaves: &[RawCommittedMessage], key: Option<&[u8; 32]>) -> Duration {
+    let path = std::env::temp_dir().join(format!(
+        "nomad-db-encryption-bench-{}-{}",
+        std::process::id(),
+        key.is_some()
+    ));
+    let path = path.to_str().expect("!path").to_owned();
+
+    let mut db = setup_db(path.clone());
+    if let Some(key) = key {
+        db = db.with_cipher(DbCipher::new(key));
+    }
+    let db = NomadDB::new("home_1", db);
+
+    let started = Instant::now();
+    for page in leaves.chunks(PAGE) {
+        db.store_messages(page).expect("!store");
+    }
+    let elapsed = started.elapsed();
+
+    drop(db);
+    rocksdb::DB::destroy(&rocksdb::Options::default(), &path).expect("!destroy");
+    elapsed
+}
+
+fn main() {
+    let leaves = leaves();
+    let key = [7u8; 32];
+
+    let (mut baseline, mut encrypted) = (Duration::MAX, Duration::MAX);
+    for _ in 0..ROUNDS {
+        baseline = baseline.min(insert(&leaves, None));
+        encrypted = encrypted.min(insert(&leaves, Some(&key)));
+    }
+
+    let rate = |elapsed: Duration| LEAVES as f64 / elapsed.as_secs_f64();
+    let slowdown = encrypted.as_secs_f64() / baseline.as_secs_f64();
+    println!("unencrypted: {:.0} leaves/s", rate(baseline));
+    println!("encrypted:   {:.0} leaves/s", rate(encrypted));
+    println!("slowdown:    {:.1}%", (slowdown - 1.0) * 100.0);
+    assert!(
+        slowdown <= MAX_SLOWDOWN,
+        "encryption slows leaf insertion down by {:.1}%, more than {:.0}%",
+        (slowdown - 1.0) * 100.0,
+        (MAX_SLOWDOWN - 1.0) * 100.0,
+    );
+}
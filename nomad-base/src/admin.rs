@@ -0,0 +1,175 @@
+use color_eyre::Result;
+use std::net::SocketAddr;
+use tokio::task::JoinHandle;
+use tracing::{info, info_span, instrument::Instrumented, warn, Instrument};
+use warp::{
+    http::StatusCode,
+    reply::{self, Response},
+    Filter, Rejection, Reply,
+};
+
+/// Environment variable holding the token admin requests must bear
+pub const ADMIN_TOKEN_ENV: &str = "ADMIN_TOKEN";
+
+/// The admin token, if one is set
+pub fn admin_token() -> Option<String> {
+    std::env::var(ADMIN_TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+/// Reasons an admin request is refused
+#[derive(Debug)]
+enum Denied {
+    NotLoopback,
+    BadToken,
+}
+
+impl warp::reject::Reject for Denied {}
+
+/// Filter passing requests from loopback that bear `token`. Without a token,
+/// nothing passes.
+pub(crate) fn admin_access(
+    token: Option<String>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::addr::remote()
+        .and(warp::header::optional::<String>("authorization"))
+        .and_then(
+            move |remote: Option<SocketAddr>, authorization: Option<String>| {
+                let access = authorize(token.as_deref(), remote, authorization.as_deref());
+                async move { access }
+            },
+        )
+        .untuple_one()
+}
+
+fn authorize(
+    token: Option<&str>,
+    remote: Option<SocketAddr>,
+    authorization: Option<&str>,
+) -> Result<(), Rejection> {
+    let token = token.ok_or_else(warp::reject::not_found)?;
+    if !remote.map_or(false, |addr| addr.ip().is_loopback()) {
+        return Err(warp::reject::custom(Denied::NotLoopback));
+    }
+    let bearer = authorization.and_then(|value| value.strip_prefix("Bearer "));
+    if !bearer.map_or(false, |bearer| {
+        constant_time_eq(bearer.as_bytes(), token.as_bytes())
+    }) {
+        return Err(warp::reject::custom(Denied::BadToken));
+    }
+    Ok(())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Reply to requests refused by `admin_access`
+pub(crate) async fn denied(rejection: Rejection) -> Result<Response, Rejection> {
+    match rejection.find::<Denied>() {
+        Some(Denied::NotLoopback) => Ok(reply::with_status(
+            "admin routes are only served to loopback clients",
+            StatusCode::FORBIDDEN,
+        )
+        .into_response()),
+        Some(Denied::BadToken) => Ok(reply::with_status(
+            "missing or wrong admin token",
+            StatusCode::UNAUTHORIZED,
+        )
+        .into_response()),
+        None => Err(rejection),
+    }
+}
+
+/// Admin `routes`, only passing requests from loopback that bear `token`
+pub fn authorized_routes<F, R>(
+    token: Option<String>,
+    routes: F,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    admin_access(token).and(routes).recover(denied)
+}
+
+/// Spawn a task serving the admin `routes` of `agent` on loopback `port`.
+/// Requests must bear the admin token. Without a token, the routes are not
+/// served and `None` is returned. The task errs if the port cannot be bound.
+pub fn serve_admin<F, R>(
+    agent: &str,
+    routes: F,
+    port: u16,
+) -> Option<Instrumented<JoinHandle<Result<()>>>>
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    let token = match admin_token() {
+        Some(token) => token,
+        None => {
+            warn!(
+                agent,
+                port,
+                "An admin port is set but {} is not. Not serving admin routes.",
+                ADMIN_TOKEN_ENV
+            );
+            return None;
+        }
+    };
+    let routes = authorized_routes(Some(token), routes);
+
+    let span = info_span!("admin_server", agent);
+    Some(
+        tokio::spawn(async move {
+            let (addr, server) = warp::serve(routes).try_bind_ephemeral(([127, 0, 0, 1], port))?;
+            info!(%addr, "Serving admin routes on {}", addr);
+            server.await;
+            Ok(())
+        })
+        .instrument(span),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(remote: [u8; 4], token: &str) -> warp::test::RequestBuilder {
+        warp::test::request()
+            .method("POST")
+            .path("/switch")
+            .remote_addr((remote, 40000).into())
+            .header("authorization", format!("Bearer {}", token))
+    }
+
+    #[tokio::test]
+    async fn it_only_passes_loopback_admins() {
+        let routes = || warp::post().and(warp::path!("switch")).map(|| "switched");
+        let filter = authorized_routes(Some("secret".to_owned()), routes());
+
+        let response = request([127, 0, 0, 1], "secret").reply(&filter).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.body(), "switched");
+
+        let response = request([10, 0, 0, 1], "secret").reply(&filter).await;
+        assert_eq!(response.status(), 403);
+
+        let response = request([127, 0, 0, 1], "guess").reply(&filter).await;
+        assert_eq!(response.status(), 401);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/switch")
+            .remote_addr(([127, 0, 0, 1], 40000).into())
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 401);
+
+        // without a token nothing is served
+        let filter = authorized_routes(None, routes());
+        let response = request([127, 0, 0, 1], "secret").reply(&filter).await;
+        assert_eq!(response.status(), 404);
+    }
+}
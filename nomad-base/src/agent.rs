@@ -6,14 +6,19 @@ use crate::{
         fmt::{log_level_to_level_filter, LogOutputLayer},
         TimeSpanLifetime,
     },
-    BaseError, CachingHome, CachingReplica, NomadDB,
+    BaseError, CachingHome, CachingReplica, CancellationToken, CorridorSwitches, FatalError,
+    NomadDB, Retry,
 };
 use async_trait::async_trait;
-use color_eyre::{eyre::WrapErr, Result};
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
 use futures_util::future::select_all;
 use nomad_core::{db::DB, Common};
+use nomad_xyz_configuration::ChainConf;
 use tracing::{dispatcher::DefaultGuard, instrument::Instrumented};
-use tracing::{error, info_span, warn, Instrument};
+use tracing::{error, info, info_span, warn, Instrument};
 use tracing_subscriber::{prelude::*, util::SubscriberInitExt};
 
 use std::{
@@ -23,7 +28,10 @@ use std::{
 };
 use tokio::{task::JoinHandle, time::sleep};
 
-const MAX_EXPONENTIAL: u32 = 7; // 2^7 = 128 second timeout
+/// Delay before the first restart of a failed channel
+const RESTART_BASE_SECONDS: u64 = 2;
+/// Longest delay before restarting a failed channel
+const RESTART_MAX_SECONDS: u64 = 128;
 
 /// Properties shared across all agents
 #[derive(Debug, Clone)]
@@ -40,6 +48,8 @@ pub struct AgentCore {
     pub indexer: IndexSettings,
     /// Settings this agent was created with
     pub settings: crate::settings::Settings,
+    /// Cancelled when the agent shuts down
+    pub cancellation: CancellationToken,
 }
 
 /// Commmon data needed for a single agent channel
@@ -51,6 +61,8 @@ pub struct ChannelBase {
     pub replica: Arc<CachingReplica>,
     /// NomadDB keyed by home
     pub db: NomadDB,
+    /// Cancelled when the agent shuts down
+    pub cancellation: CancellationToken,
 }
 
 /// A trait for an application:
@@ -82,6 +94,7 @@ pub trait NomadAgent: Send + Sync + Sized + std::fmt::Debug + AsRef<AgentCore> {
             home: self.home(),
             replica: self.replica_by_name(replica).expect("!replica exist"),
             db: NomadDB::new(self.home().name(), self.db()),
+            cancellation: self.cancellation(),
         }
     }
 
@@ -90,6 +103,11 @@ pub trait NomadAgent: Send + Sync + Sized + std::fmt::Debug + AsRef<AgentCore> {
         self.as_ref().metrics.clone()
     }
 
+    /// Return the agent's shutdown signal
+    fn cancellation(&self) -> CancellationToken {
+        self.as_ref().cancellation.clone()
+    }
+
     /// Return a handle to the DB
     fn db(&self) -> DB {
         self.as_ref().db.clone()
@@ -110,20 +128,124 @@ pub trait NomadAgent: Send + Sync + Sized + std::fmt::Debug + AsRef<AgentCore> {
         self.replicas().get(name).map(Clone::clone)
     }
 
+    /// Spawn the testnet top-up task for the agent's transaction signers.
+    /// Returns `None` if no top-ups are configured.
+    fn top_up_task(&self) -> Option<Instrumented<JoinHandle<Result<()>>>> {
+        let settings = &self.as_ref().settings;
+        if settings.top_up.is_empty() {
+            return None;
+        }
+
+        Some(crate::spawn_top_ups(
+            Clone::clone(settings),
+            Self::AGENT_NAME,
+            self.metrics(),
+        ))
+    }
+
+    /// Spawn the task flushing batched DB writes of event indexing at the
+    /// configured delay, so a quiet stage is not left unflushed. Returns
+    /// `None` if writes are not batched.
+    fn flush_db_writes_task(&self) -> Option<Instrumented<JoinHandle<Result<()>>>> {
+        let max_delay = self.as_ref().settings.db_write_batch?.max_delay();
+        let span = info_span!("flush_db_writes");
+        let db = self.db();
+
+        Some(
+            tokio::spawn(async move {
+                loop {
+                    sleep(max_delay).await;
+                    db.flush_writes()?;
+                }
+            })
+            .instrument(span),
+        )
+    }
+
+    /// Spawn the task serving the agent's admin endpoint. Returns `None` if
+    /// the agent has none or it is not configured.
+    fn run_admin_server(&self) -> Option<Instrumented<JoinHandle<Result<()>>>> {
+        None
+    }
+
+    /// The agent's corridor pause switches, if it has any
+    fn corridors(&self) -> Option<Arc<CorridorSwitches>> {
+        None
+    }
+
+    /// Spawn the task heartbeating claims on the agent's transaction
+    /// signers, detecting other processes submitting with them
+    fn signer_claims_task(&self) -> Instrumented<JoinHandle<Result<()>>> {
+        crate::spawn_signer_claims(
+            Clone::clone(&self.as_ref().settings),
+            Self::AGENT_NAME,
+            NomadDB::new(self.home().name(), self.db()),
+            self.corridors(),
+            self.metrics(),
+        )
+    }
+
     /// Run the agent with the given home and replica
     fn run(channel: Self::Channel) -> Instrumented<JoinHandle<Result<()>>>;
 
+    /// If the agent was started with `--verify-db`, verify the home's db,
+    /// resuming an interrupted verification. With `--verify-db=quarantine`,
+    /// proofs failing verification are quarantined to be regenerated. Errs,
+    /// so that the agent exits with a failure code, if any violation is left.
+    fn verify_db_if_requested(&self) -> Result<()> {
+        let quarantine = match std::env::args().find(|arg| arg.starts_with("--verify-db")) {
+            Some(arg) if arg == "--verify-db" => false,
+            Some(arg) if arg == "--verify-db=quarantine" => true,
+            Some(arg) => {
+                return Err(FatalError::config(
+                    "db_verify",
+                    eyre!("Unknown db verification flag {}", arg),
+                )
+                .into())
+            }
+            None => return Ok(()),
+        };
+
+        let db = NomadDB::new(self.home().name(), self.db());
+        let report = db
+            .verify(quarantine, false, None)
+            .map_err(|e| FatalError::db_corruption("db_verify", e))?;
+        let unresolved = report.unresolved().count();
+        info!(
+            from = report.from,
+            leaves = report.leaves,
+            violations = report.violations.len(),
+            unresolved,
+            "Verified db"
+        );
+        if unresolved > 0 {
+            return Err(FatalError::db_corruption(
+                "db_verify",
+                eyre!("DB verification left {} violations unresolved", unresolved),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
     /// Run the agent for a given channel. If the channel dies, exponentially
     /// retry. If failures are more than 5 minutes apart, reset exponential
-    /// backoff (likely unrelated after that point).
+    /// backoff (likely unrelated after that point). Shutdown interrupts the
+    /// wait before a restart.
     #[allow(clippy::unit_arg)]
     #[tracing::instrument]
     fn run_report_error(&self, replica: String) -> Instrumented<JoinHandle<Result<()>>> {
         let channel = self.build_channel(&replica);
         let channel_faults_gauge = self.metrics().channel_faults_gauge(&replica);
 
+        let retry = Retry::exponential(
+            Duration::from_secs(RESTART_BASE_SECONDS),
+            Duration::from_secs(RESTART_MAX_SECONDS),
+        )
+        .with_cancellation(self.cancellation());
+
         tokio::spawn(async move {
-            let mut exponential = 0;
+            let mut restarts = 0;
             loop {
                 let running_time = SystemTime::now();
 
@@ -134,6 +256,8 @@ pub trait NomadAgent: Send + Sync + Sized + std::fmt::Debug + AsRef<AgentCore> {
 
                 match res {
                     Ok(_) => return Ok(()),
+                    // Fatal errors stop the agent instead of the channel
+                    Err(e) if e.downcast_ref::<FatalError>().is_some() => return Err(e),
                     Err(e) => {
                         error!(
                             "Channel for replica {} errored out! Error: {:?}",
@@ -144,18 +268,20 @@ pub trait NomadAgent: Send + Sync + Sized + std::fmt::Debug + AsRef<AgentCore> {
                         // If running time >= 5 minutes, current failure likely
                         // unrelated to previous
                         if running_time.elapsed().unwrap().as_secs() >= 300 {
-                            exponential = 0;
-                        } else if exponential < MAX_EXPONENTIAL {
-                            exponential += 1;
+                            restarts = 0;
                         }
+                        restarts += 1;
 
-                        let sleep_time = 2u64.pow(exponential);
+                        let delay = retry.delay(restarts);
                         warn!(
                             "Restarting channel to {} in {} seconds",
-                            &replica, sleep_time
+                            &replica,
+                            delay.as_secs()
                         );
 
-                        sleep(Duration::from_secs(sleep_time)).await;
+                        if !retry.wait(delay).await {
+                            return Ok(());
+                        }
                     }
                 }
             }
@@ -177,11 +303,13 @@ pub trait NomadAgent: Send + Sync + Sized + std::fmt::Debug + AsRef<AgentCore> {
             .iter()
             .map(|replica| self.run_report_error(replica.to_string()))
             .collect();
+        let cancellation = self.cancellation();
 
         tokio::spawn(async move {
             // This gets the first future to resolve.
             let (res, _, remaining) = select_all(handles).await;
 
+            cancellation.cancel();
             for task in remaining.into_iter() {
                 cancel_task!(task);
             }
@@ -217,13 +345,28 @@ pub trait NomadAgent: Send + Sync + Sized + std::fmt::Debug + AsRef<AgentCore> {
                 let sync_task = self.home().sync();
 
                 tasks.push(sync_task);
+                tasks.push(self.watch_updater_signers());
+            }
+
+            if let Some(top_up_task) = self.top_up_task() {
+                tasks.push(top_up_task);
+            }
+            if let Some(flush_task) = self.flush_db_writes_task() {
+                tasks.push(flush_task);
+            }
+            tasks.push(self.signer_claims_task());
+            if let Some(admin_task) = self.run_admin_server() {
+                tasks.push(admin_task);
             }
 
             let (res, _, remaining) = select_all(tasks).await;
 
+            self.cancellation().cancel();
             for task in remaining.into_iter() {
                 cancel_task!(task);
             }
+            // batched writes staged before the tasks stopped are kept
+            self.db().flush_writes()?;
 
             res?
         })
@@ -254,6 +397,56 @@ pub trait NomadAgent: Send + Sync + Sized + std::fmt::Debug + AsRef<AgentCore> {
         .instrument(span)
     }
 
+    /// Spawn a task which periodically asserts that the home's `tree().count`
+    /// matches `count()`. See `crate::watch_tree_counts`.
+    fn watch_tree_counts(&self, interval: u64) -> Instrumented<JoinHandle<Result<()>>> {
+        crate::watch_tree_counts(
+            self.home(),
+            interval,
+            self.metrics().tree_count_mismatches(),
+        )
+    }
+
+    /// Spawn a task which periodically checks the signers of recently indexed
+    /// updates against the home's `updater()` and the configured updater
+    fn watch_updater_signers(&self) -> Instrumented<JoinHandle<Result<()>>> {
+        let configured = self
+            .as_ref()
+            .settings
+            .expected_updater
+            .and_then(|updater| updater.as_ethereum_address().ok());
+
+        crate::watch_updater_signers(
+            self.home(),
+            self.home().db(),
+            configured,
+            self.metrics().updater_signer_mismatches(),
+        )
+    }
+
+    /// Spawn a task which periodically judges the health of the home's
+    /// updater from its queue and dispatches. Roots are overdue once pending
+    /// for the updater's interval and `UPDATER_HEALTH_GRACE`.
+    /// Substrate homes have no queue, so their updater is not watched.
+    fn watch_updater_health(&self) -> Instrumented<JoinHandle<Result<()>>> {
+        if let ChainConf::Substrate(_) = self.as_ref().settings.home.chain {
+            info!("Substrate homes have no queue. Not watching updater health.");
+            return tokio::spawn(std::future::pending())
+                .instrument(info_span!("updater_health_watch"));
+        }
+
+        let expected_interval = self.as_ref().settings.updater_interval.unwrap_or_default()
+            + crate::UPDATER_HEALTH_GRACE;
+
+        crate::watch_updater_health(
+            self.home(),
+            expected_interval,
+            self.metrics().updater_stalled(),
+            self.metrics().updater_idle(),
+            self.metrics().pending_root_age(),
+        )
+    }
+
     /// Returns `true` if home is in failed state. Intended to return once and immediately
     #[allow(clippy::unit_arg)]
     fn assert_home_not_failed(&self) -> Instrumented<JoinHandle<Result<()>>> {
@@ -0,0 +1,438 @@
+use ethers::core::types::H256;
+use nomad_core::{db::DbError, Decode, Encode, NomadError, RootProvenance, TxPayload};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::warn;
+
+use crate::NomadDB;
+
+/// Key space of audit records in a `NomadDB`. Records are only ever added.
+const AUDIT: &str = "audit_";
+/// Key space of the transaction payloads of submission records
+const SUBMISSION_PAYLOAD: &str = "submission_payload_";
+
+/// Days transaction payloads of submissions are kept
+pub const SUBMISSION_PAYLOAD_RETENTION_DAYS: u64 = 14;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// NDJSON file audit records are mirrored to, if set
+static MIRROR: OnceCell<Mutex<File>> = OnceCell::new();
+
+/// Last audit id handed out by this process
+static LAST_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Mirror every audit record written by this process to the NDJSON file at
+/// `path`, appending to it
+pub fn mirror_audit_log(path: impl AsRef<Path>) -> std::io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    // Later calls keep the first mirror
+    let _ = MIRROR.set(Mutex::new(file));
+    Ok(())
+}
+
+/// Id of an audit record: nanoseconds since the unix epoch at which it was
+/// written, bumped so that ids stay unique and increasing within a process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct AuditId(pub u64);
+
+impl AuditId {
+    fn next() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        let mut last = LAST_ID.load(Ordering::Relaxed);
+        loop {
+            let id = now.max(last + 1);
+            match LAST_ID.compare_exchange_weak(last, id, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => return Self(id),
+                Err(current) => last = current,
+            }
+        }
+    }
+
+    /// First id written at or after `unix_seconds`
+    pub fn since(unix_seconds: u64) -> Self {
+        Self(unix_seconds.saturating_mul(1_000_000_000))
+    }
+}
+
+/// A decision leading to an on-chain action, with the inputs it was made on.
+/// Recorded before the action is taken.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum AuditEvent {
+    /// The updater signed a confirmed suggested update building on its latest
+    /// root, with no conflicting update signed before
+    #[serde(rename_all = "camelCase")]
+    SignUpdate {
+        /// Home domain
+        home_domain: u32,
+        /// Previous root, the updater's latest root
+        previous_root: H256,
+        /// New root suggested by the home
+        new_root: H256,
+    },
+    /// The updater submitted an update it signed on the home's committed root
+    #[serde(rename_all = "camelCase")]
+    SubmitUpdate {
+        /// Home name
+        home: String,
+        /// Previous root, the home's committed root
+        previous_root: H256,
+        /// New root
+        new_root: H256,
+    },
+    /// The relayer relayed a home update building on the replica's committed
+    /// root
+    #[serde(rename_all = "camelCase")]
+    RelayUpdate {
+        /// Replica name
+        replica: String,
+        /// Previous root, the replica's committed root
+        previous_root: H256,
+        /// New root
+        new_root: H256,
+        /// New roots of the verified intermediate updates a batched relay
+        /// skipped
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        skipped: Vec<H256>,
+    },
+    /// The processor processed a message not attempted before, proven against
+    /// a root the replica accepts
+    #[serde(rename_all = "camelCase")]
+    ProcessMessage {
+        /// Replica name
+        replica: String,
+        /// Message leaf
+        leaf: H256,
+        /// Message leaf index
+        leaf_index: u32,
+        /// Message nonce
+        nonce: u32,
+        /// Root the message is proven against
+        proof_root: H256,
+        /// Whether the message is proven in the same transaction
+        prove: bool,
+        /// How the replica came to accept the root
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        provenance: Option<RootProvenance>,
+    },
+    /// The watcher notified contracts of a double update and unenrolled
+    /// replicas
+    #[serde(rename_all = "camelCase")]
+    DoubleUpdateResponse {
+        /// Home domain
+        home_domain: u32,
+        /// Updater that signed both updates
+        updater: H256,
+        /// Previous root both updates build on
+        previous_root: H256,
+        /// Conflicting new roots
+        new_roots: [H256; 2],
+    },
+    /// The watcher unenrolled replicas of a home failed by an improper update
+    #[serde(rename_all = "camelCase")]
+    ImproperUpdateResponse {
+        /// Home domain
+        home_domain: u32,
+        /// Updater of the failed home
+        updater: H256,
+    },
+    /// Transaction a decision resulted in
+    #[serde(rename_all = "camelCase")]
+    Submitted {
+        /// Record of the decision
+        decision: AuditId,
+        /// Contract the transaction was sent to
+        target: String,
+        /// Transaction hash
+        txid: H256,
+    },
+    /// A decision's submission failed
+    #[serde(rename_all = "camelCase")]
+    SubmissionFailed {
+        /// Record of the decision
+        decision: AuditId,
+        /// Contract the transaction was sent to
+        target: String,
+        /// Error returned by the submission
+        error: String,
+    },
+}
+
+/// An audit event and when it was recorded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Id, which orders records by time
+    pub id: AuditId,
+    /// Event
+    #[serde(flatten)]
+    pub event: AuditEvent,
+}
+
+impl Encode for AuditRecord {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        writer.write_all(&json)?;
+        Ok(json.len())
+    }
+}
+
+impl Decode for AuditRecord {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let mut json = vec![];
+        reader.read_to_end(&mut json)?;
+        serde_json::from_slice(&json).map_err(|e| {
+            NomadError::IoError(std::io::Error::new(ErrorKind::InvalidData, e.to_string()))
+        })
+    }
+}
+
+impl NomadDB {
+    /// Append an audit record of `event`, mirroring it to the audit file if
+    /// one is set. Returns the record's id, for follow-up records. Costs a
+    /// single db write.
+    ///
+    /// Keys --> Values:
+    /// - `id` --> `audit record`
+    pub fn audit(&self, event: AuditEvent) -> Result<AuditId, DbError> {
+        let record = AuditRecord {
+            id: AuditId::next(),
+            event,
+        };
+        self.store_keyed_encodable(AUDIT, &record.id.0, &record)?;
+
+        if let Some(mirror) = MIRROR.get() {
+            let mut line = record.to_vec();
+            line.push(b'\n');
+            if let Err(e) = mirror.lock().expect("poisoned").write_all(&line) {
+                warn!(error = %e, "Failed to mirror audit record to file");
+            }
+        }
+        Ok(record.id)
+    }
+
+    /// Record the outcome of submitting the transaction decided on in
+    /// `decision`, with the transaction's payload if it was captured.
+    /// Payloads past their retention are pruned.
+    ///
+    /// Keys --> Values:
+    /// - `submission record id` --> `payload`
+    pub fn audit_submission<T, E: std::fmt::Display>(
+        &self,
+        decision: AuditId,
+        target: &str,
+        result: &Result<T, E>,
+        payload: Option<&TxPayload>,
+        txid: impl Fn(&T) -> H256,
+    ) -> Result<AuditId, DbError> {
+        let target = target.to_owned();
+        let id = self.audit(match result {
+            Ok(outcome) => AuditEvent::Submitted {
+                decision,
+                target,
+                txid: txid(outcome),
+            },
+            Err(e) => AuditEvent::SubmissionFailed {
+                decision,
+                target,
+                error: e.to_string(),
+            },
+        })?;
+
+        if let Some(payload) = payload {
+            self.store_keyed_encodable(SUBMISSION_PAYLOAD, &id.0, payload)?;
+            let retention = SUBMISSION_PAYLOAD_RETENTION_DAYS * SECONDS_PER_DAY;
+            self.prune_submission_payloads(AuditId::since(
+                (id.0 / 1_000_000_000).saturating_sub(retention),
+            ))?;
+        }
+        Ok(id)
+    }
+
+    /// Transaction payload of the submission recorded in `submission`, if it
+    /// was captured and not pruned
+    pub fn submission_payload(&self, submission: AuditId) -> Result<Option<TxPayload>, DbError> {
+        self.retrieve_keyed_decodable(SUBMISSION_PAYLOAD, &submission.0)
+    }
+
+    /// Delete the payloads of submissions recorded before `before`. Returns
+    /// the number deleted.
+    pub fn prune_submission_payloads(&self, before: AuditId) -> Result<usize, DbError> {
+        // Ids order payloads by time
+        let mut stale = vec![];
+        for entry in self.raw_entries(SUBMISSION_PAYLOAD) {
+            let (key, _) = entry?;
+            let id = u64::read_from(&mut key.as_slice())?;
+            if id >= before.0 {
+                break;
+            }
+            stale.push(id);
+        }
+        for id in stale.iter() {
+            self.delete_keyed(SUBMISSION_PAYLOAD, id)?;
+        }
+        Ok(stale.len())
+    }
+
+    /// Audit records written at or after `since`, oldest first
+    pub fn audit_records(
+        &self,
+        since: AuditId,
+    ) -> impl Iterator<Item = Result<AuditRecord, DbError>> + '_ {
+        self.iterate_from(AUDIT, since.0.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nomad_core::{StoredCalldata, MAX_STORED_CALLDATA, MAX_STORED_SIMULATION};
+    use nomad_test::test_utils::run_test_db;
+
+    #[test]
+    fn audit_ids_increase() {
+        let ids: Vec<_> = (0..1000).map(|_| AuditId::next()).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert!(AuditId::since(1) < ids[0]);
+    }
+
+    #[tokio::test]
+    async fn it_appends_and_exports_audit_records() {
+        run_test_db(|db| async move {
+            let other = NomadDB::new("home_2", db.clone());
+            let db = NomadDB::new("home_1", db);
+
+            let decision = db
+                .audit(AuditEvent::RelayUpdate {
+                    replica: "replica_1".to_owned(),
+                    previous_root: H256::repeat_byte(1),
+                    new_root: H256::repeat_byte(3),
+                    skipped: vec![H256::repeat_byte(2)],
+                })
+                .unwrap();
+            other
+                .audit(AuditEvent::ImproperUpdateResponse {
+                    home_domain: 2,
+                    updater: H256::repeat_byte(9),
+                })
+                .unwrap();
+            let outcome: Result<H256, String> = Ok(H256::repeat_byte(4));
+            let follow_up = db
+                .audit_submission(decision, "replica_1", &outcome, None, |txid| *txid)
+                .unwrap();
+
+            let records: Vec<_> = db
+                .audit_records(AuditId(0))
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(records.len(), 2);
+            assert_eq!(records[0].id, decision);
+            assert_eq!(
+                records[1].event,
+                AuditEvent::Submitted {
+                    decision,
+                    target: "replica_1".to_owned(),
+                    txid: H256::repeat_byte(4),
+                }
+            );
+
+            // Only later records are exported
+            let since: Vec<_> = db
+                .audit_records(follow_up)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(since.len(), 1);
+            assert_eq!(since[0].id, follow_up);
+
+            // Records are self-describing JSON
+            let json = serde_json::to_value(&records[0]).unwrap();
+            assert_eq!(json["event"], "relayUpdate");
+            assert_eq!(json["replica"], "replica_1");
+            assert_eq!(json["id"], decision.0);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_stores_bounded_submission_payloads_until_pruned() {
+        run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let decision = db
+                .audit(AuditEvent::SubmitUpdate {
+                    home: "home_1".to_owned(),
+                    previous_root: H256::repeat_byte(1),
+                    new_root: H256::repeat_byte(2),
+                })
+                .unwrap();
+
+            // Calldata past the cap is truncated, and long simulation output
+            // is cut short
+            let calldata: Vec<u8> = (0..MAX_STORED_CALLDATA + 100).map(|i| i as u8).collect();
+            let payload = TxPayload {
+                to: Some(ethers::core::types::H160::repeat_byte(2)),
+                nonce: Some(3.into()),
+                gas: Some(100_000.into()),
+                gas_price: Some(7.into()),
+                calldata: StoredCalldata::new(&calldata),
+                ..Default::default()
+            }
+            .with_simulation(&"revert: !proven ".repeat(1000));
+            assert!(payload.calldata.truncated);
+            assert!(payload.calldata.deflated.len() < MAX_STORED_CALLDATA);
+            assert_eq!(
+                payload.simulation.as_ref().unwrap().chars().count(),
+                MAX_STORED_SIMULATION + 3
+            );
+
+            let failed: Result<H256, String> = Err("nonce too low".to_owned());
+            let submission = db
+                .audit_submission(decision, "home_1", &failed, Some(&payload), |txid| *txid)
+                .unwrap();
+            let unrecorded = db
+                .audit_submission(decision, "home_1", &failed, None, |txid| *txid)
+                .unwrap();
+
+            let stored = db.submission_payload(submission).unwrap().unwrap();
+            assert_eq!(stored, payload);
+            assert_eq!(
+                stored.calldata.inflate().unwrap(),
+                &calldata[..MAX_STORED_CALLDATA]
+            );
+            assert_eq!(stored.report()["calldata"]["len"], calldata.len());
+            assert_eq!(db.submission_payload(decision).unwrap(), None);
+            assert_eq!(db.submission_payload(unrecorded).unwrap(), None);
+
+            // Only payloads recorded before the cutoff are pruned
+            assert_eq!(db.prune_submission_payloads(submission).unwrap(), 0);
+            assert_eq!(
+                db.prune_submission_payloads(AuditId(submission.0 + 1))
+                    .unwrap(),
+                1
+            );
+            assert_eq!(db.submission_payload(submission).unwrap(), None);
+            // The audit record itself stays
+            assert_eq!(db.audit_records(submission).count(), 2);
+        })
+        .await
+    }
+}
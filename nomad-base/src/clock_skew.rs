@@ -0,0 +1,252 @@
+use color_eyre::Result;
+use nomad_core::CommonIndexer;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{error, info_span, instrument::Instrumented, warn, Instrument};
+
+use crate::CommonIndexers;
+
+/// Number of samples the skew estimate is the median of
+pub const SKEW_WINDOW: usize = 15;
+
+/// Sliding window of `local time - latest block timestamp` samples.
+///
+/// Samples include the age of the latest block, so the estimate is biased
+/// towards the host being ahead by up to a block time. That errs on the side
+/// of waiting longer.
+#[derive(Debug, Clone)]
+pub struct SkewWindow {
+    samples: VecDeque<i64>,
+    capacity: usize,
+}
+
+impl Default for SkewWindow {
+    fn default() -> Self {
+        Self::new(SKEW_WINDOW)
+    }
+}
+
+impl SkewWindow {
+    /// Window of at most `capacity` samples
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Record a latest block timestamp seen at local unix time `local`
+    pub fn observe(&mut self, local: u64, block_timestamp: u64) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples
+            .push_back(local as i64 - block_timestamp as i64);
+    }
+
+    /// Median sample, robust to single slow or early blocks. `None` before
+    /// the first sample.
+    pub fn median(&self) -> Option<i64> {
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        match sorted.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(sorted[n / 2]),
+            n => Some((sorted[n / 2 - 1] + sorted[n / 2]) / 2),
+        }
+    }
+}
+
+/// Shared estimate of how far the host clock is ahead of a chain's clock,
+/// with a safety margin applied to chain time
+#[derive(Debug, Clone, Default)]
+pub struct ClockSkew {
+    seconds: Arc<AtomicI64>,
+    margin: u64,
+}
+
+impl ClockSkew {
+    /// Skew estimate applying `margin` seconds of safety
+    pub fn new(margin: u64) -> Self {
+        Self {
+            seconds: Default::default(),
+            margin,
+        }
+    }
+
+    /// Estimated seconds the host is ahead of the chain. Negative if behind
+    pub fn seconds(&self) -> i64 {
+        self.seconds.load(Ordering::Relaxed)
+    }
+
+    /// Set the estimate
+    pub fn set(&self, seconds: i64) {
+        self.seconds.store(seconds, Ordering::Relaxed)
+    }
+
+    /// Conservative chain time at local unix time `local`: corrected for
+    /// skew, less the safety margin
+    pub fn chain_now(&self, local: u64) -> u64 {
+        let chain_now = local as i64 - self.seconds() - self.margin as i64;
+        chain_now.max(0) as u64
+    }
+
+    /// Conservative chain time now
+    pub fn chain_now_from_host(&self) -> Result<u64> {
+        let local = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        Ok(self.chain_now(local))
+    }
+}
+
+/// Estimates a chain's clock skew from its latest block timestamps
+#[derive(Debug)]
+pub struct ClockSkewMonitor {
+    /// Network name
+    pub network: String,
+    /// Indexer of a contract on the network
+    pub indexer: Arc<CommonIndexers>,
+    /// Estimate to keep current
+    pub skew: ClockSkew,
+    /// Absolute skew in seconds above which to alert
+    pub alert_seconds: u64,
+    /// Seconds between samples
+    pub interval: u64,
+    /// Skew gauge
+    pub skew_gauge: prometheus::IntGauge,
+    /// Set to 1 while the skew exceeds `alert_seconds`
+    pub alert_gauge: prometheus::IntGauge,
+}
+
+impl ClockSkewMonitor {
+    /// Record a sample and update the estimate and gauges. Returns the
+    /// estimate and whether it exceeds the alert threshold.
+    pub fn record(
+        &self,
+        window: &mut SkewWindow,
+        local: u64,
+        block_timestamp: u64,
+    ) -> Option<(i64, bool)> {
+        window.observe(local, block_timestamp);
+        let skew = window.median()?;
+        let alert = skew.unsigned_abs() > self.alert_seconds;
+
+        self.skew.set(skew);
+        self.skew_gauge.set(skew);
+        self.alert_gauge.set(alert as i64);
+        Some((skew, alert))
+    }
+
+    /// Spawn the monitor
+    pub fn spawn(self) -> Instrumented<JoinHandle<Result<()>>> {
+        let span = info_span!("ClockSkewMonitor", network = self.network.as_str());
+        tokio::spawn(async move {
+            let mut window = SkewWindow::default();
+            loop {
+                match self.indexer.get_latest_block_timestamp().await {
+                    Ok(Some(block_timestamp)) => {
+                        let local = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                        if let Some((skew, true)) = self.record(&mut window, local, block_timestamp)
+                        {
+                            error!(
+                                network = self.network.as_str(),
+                                skew_seconds = skew,
+                                threshold = self.alert_seconds,
+                                "Host clock is {} seconds off {} chain time. Check the host's time sync",
+                                skew,
+                                self.network,
+                            );
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!(
+                        network = self.network.as_str(),
+                        error = %e,
+                        "Failed to fetch latest block timestamp"
+                    ),
+                }
+                sleep(Duration::from_secs(self.interval)).await;
+            }
+        })
+        .instrument(span)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nomad_test::mocks::MockIndexer;
+
+    #[test]
+    fn it_estimates_skew_robust_to_outliers() {
+        let mut window = SkewWindow::new(5);
+        assert_eq!(window.median(), None);
+
+        // host 100 seconds ahead, blocks a few seconds old, one block
+        // timestamped far in the past and one in the future
+        for (local, block) in [
+            (1100, 995),
+            (1112, 1008),
+            (1124, 0),
+            (1136, 1033),
+            (1148, 5000),
+        ] {
+            window.observe(local, block);
+        }
+        assert_eq!(window.median(), Some(104));
+
+        // old samples leave the window
+        for i in 0..5 {
+            window.observe(2000 + i, 2000 + i);
+        }
+        assert_eq!(window.median(), Some(0));
+    }
+
+    #[test]
+    fn it_applies_skew_and_margin_to_chain_time() {
+        let skew = ClockSkew::new(10);
+        assert_eq!(skew.chain_now(1000), 990);
+
+        skew.set(100);
+        assert_eq!(skew.chain_now(1000), 890);
+
+        // a host behind the chain waits less, but never past the margin
+        skew.set(-5);
+        assert_eq!(skew.chain_now(1000), 995);
+        assert_eq!(skew.chain_now(3), 0);
+    }
+
+    #[test]
+    fn it_alerts_above_the_threshold() {
+        let monitor = ClockSkewMonitor {
+            network: "moonbeam".to_owned(),
+            indexer: Arc::new(MockIndexer::new().into()),
+            skew: ClockSkew::new(0),
+            alert_seconds: 30,
+            interval: 1,
+            skew_gauge: prometheus::IntGauge::new("skew", "skew").unwrap(),
+            alert_gauge: prometheus::IntGauge::new("alert", "alert").unwrap(),
+        };
+        let mut window = SkewWindow::new(3);
+
+        assert_eq!(monitor.record(&mut window, 1010, 1000), Some((10, false)));
+        assert_eq!(monitor.record(&mut window, 1010, 1000), Some((10, false)));
+        assert_eq!(monitor.alert_gauge.get(), 0);
+
+        // one outlier does not alert
+        assert_eq!(monitor.record(&mut window, 1100, 1000), Some((10, false)));
+        assert_eq!(monitor.alert_gauge.get(), 0);
+
+        // sustained skew does
+        assert_eq!(monitor.record(&mut window, 1100, 1000), Some((100, true)));
+        assert_eq!(monitor.skew.seconds(), 100);
+        assert_eq!(monitor.skew_gauge.get(), 100);
+        assert_eq!(monitor.alert_gauge.get(), 1);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::CoreMetrics;
-use prometheus::{HistogramVec, IntGaugeVec};
+use prometheus::{HistogramVec, IntCounterVec, IntGaugeVec};
 use std::sync::Arc;
 
 /// Struct encapsulating prometheus metrics used by the ContractSync.
@@ -12,6 +12,21 @@ pub struct ContractSyncMetrics {
     pub store_event_latency: HistogramVec,
     /// Events stored into DB (label values differentiate updates vs. messages)
     pub stored_events: IntGaugeVec,
+    /// Attempts to move a sync cursor backwards outside of an explicit rewind
+    /// (label values differentiate updates vs. messages)
+    pub rejected_cursor_regressions: IntCounterVec,
+    /// Observations of updates already observed by this or another source
+    /// (label values differentiate sources)
+    pub duplicate_update_observations: IntCounterVec,
+    /// Leaves dispatched at more than one leaf index, set to their number of
+    /// leaf indexes (label values differentiate leaves)
+    pub duplicate_leaves: IntGaugeVec,
+    /// Seconds since the committed root of a replica last advanced (label
+    /// values differentiate replicas)
+    pub root_advance_age: IntGaugeVec,
+    /// Agent metrics, reporting the latest root advance of each replica in
+    /// `/status`
+    pub core: Arc<CoreMetrics>,
 }
 
 impl ContractSyncMetrics {
@@ -44,10 +59,47 @@ impl ContractSyncMetrics {
             )
             .expect("failed to register stored_events metric");
 
+        let rejected_cursor_regressions = metrics
+            .new_int_counter(
+                "contract_sync_rejected_cursor_regressions",
+                "Number of rejected attempts to move a sync cursor backwards",
+                &["data_type", "home", "replica", "agent"],
+            )
+            .expect("failed to register rejected_cursor_regressions metric");
+
+        let duplicate_update_observations = metrics
+            .new_int_counter(
+                "contract_sync_duplicate_update_observations",
+                "Number of observed updates that were already observed by any source",
+                &["source", "home", "agent"],
+            )
+            .expect("failed to register duplicate_update_observations metric");
+
+        let duplicate_leaves = metrics
+            .new_int_gauge_vec(
+                "contract_sync_duplicate_leaf_indexes",
+                "Number of leaf indexes of a leaf dispatched more than once",
+                &["leaf", "home", "agent"],
+            )
+            .expect("failed to register duplicate_leaves metric");
+
+        let root_advance_age = metrics
+            .new_int_gauge_vec(
+                "contract_sync_root_advance_age_seconds",
+                "Seconds since the committed root of a replica last advanced",
+                &["home", "replica", "agent"],
+            )
+            .expect("failed to register root_advance_age metric");
+
         ContractSyncMetrics {
             indexed_height,
             store_event_latency,
             stored_events,
+            rejected_cursor_regressions,
+            duplicate_update_observations,
+            duplicate_leaves,
+            root_advance_age,
+            core: metrics,
         }
     }
 }
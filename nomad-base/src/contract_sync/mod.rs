@@ -1,13 +1,22 @@
 use crate::chains::PageSettings;
-use crate::{IndexDataTypes, IndexSettings, NomadDB};
-use color_eyre::Result;
+use crate::{
+    Backoff, CoreMetrics, IndexDataTypes, IndexSettings, LeafOccurrence, MessageIntegrityError,
+    NomadDB, Observation, SnapshotError, TreeSnapshot,
+};
+use color_eyre::{eyre::bail, Result};
+use ethers::core::types::H256;
 use futures_util::future::select_all;
-use nomad_core::{CommonIndexer, HomeIndexer};
-use tokio::{task::JoinHandle, time::sleep};
-use tracing::{info, info_span};
+use nomad_core::{CanonicalH256, CommonIndexer, HomeIndexer, RawCommittedMessage};
+use nomad_ethereum::{classified_rpc_error, rpc_error_class, LOG_RANGE_CAPS};
+use nomad_types::CheckedCast;
+use nomad_xyz_configuration::ethereum::RpcErrorClass;
+use prometheus::{IntCounter, IntGauge, IntGaugeVec};
+use tokio::{sync::Notify, task::JoinHandle, time::sleep};
+use tracing::{debug, info, info_span, warn};
 use tracing::{instrument::Instrumented, Instrument};
 
 use std::cmp::min;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -15,16 +24,276 @@ mod metrics;
 mod schema;
 
 pub use metrics::ContractSyncMetrics;
-use schema::{CommonContractSyncDB, HomeContractSyncDB};
+use schema::{CommonContractSyncDB, CursorUpdate};
+pub(crate) use schema::{HomeContractSyncDB, MESSAGES_LAST_BLOCK_END, UPDATES_LAST_BLOCK_END};
 
 const UPDATES_LABEL: &str = "updates";
 const MESSAGES_LABEL: &str = "messages";
 
+/// `/status` section reporting the latest root advance of each replica
+const ROOT_ADVANCES_SECTION: &str = "root_advances";
+
+/// Blocks behind the stored update cursor the next range starts from when a
+/// node is behind the requested blocks. The stored cursor is left in place.
+const RPC_REWIND_BLOCKS: u32 = 5;
+/// Seconds before retrying a backfill or check request a node was behind
+/// for. Indexing loops back off per `IndexSettings::backoff` instead.
+const RPC_REWIND_SECONDS: u64 = 10;
+
+/// Whether an indexer error was classified as the node being behind the
+/// requested blocks, such as a header not found near the head. Waiting and
+/// retrying resolves these.
+fn node_behind<E: std::error::Error + 'static>(error: &E) -> bool {
+    rpc_error_class(error) == Some(RpcErrorClass::RewindAndRetry)
+}
+
+/// Whether syncing backs off and retries after an indexer error. Errors
+/// classified as fatal, or as history the node no longer serves, end
+/// syncing.
+fn retryable<E: std::error::Error + 'static>(error: &E) -> bool {
+    !matches!(
+        rpc_error_class(error),
+        Some(RpcErrorClass::Fatal) | Some(RpcErrorClass::HistoryUnavailable)
+    )
+}
+
+/// Learn the block range cap of the provider of `chain` from an indexer
+/// error rejecting blocks `start` to `end` as too large a range, and persist
+/// it so restarts request ranges within it. Returns whether the error was
+/// such a rejection.
+fn learn_range_cap<E: std::error::Error + 'static>(
+    db: &NomadDB,
+    chain: &str,
+    error: &E,
+    (start, end): (u32, u32),
+) -> Result<bool> {
+    let classified = match classified_rpc_error(error) {
+        Some(classified) if classified.class == RpcErrorClass::RangeTooLarge => classified,
+        _ => return Ok(false),
+    };
+    let cap = LOG_RANGE_CAPS.record_rejected(chain, end - start + 1, &classified.message);
+    db.store_log_range_cap(cap)?;
+    Ok(true)
+}
+
+/// Record that the provider of `chain` served blocks `start` to `end`, and
+/// persist its cap if it grew back
+fn record_served_range(db: &NomadDB, chain: &str, (start, end): (u32, u32)) -> Result<()> {
+    if let Some(cap) = LOG_RANGE_CAPS.record_served(chain, end - start + 1) {
+        db.store_log_range_cap(cap)?;
+    }
+    Ok(())
+}
+
+/// Restore the block range cap a previous run learned for the provider of
+/// `chain`
+fn restore_range_cap(db: &NomadDB, chain: &str) -> Result<()> {
+    if let Some(blocks) = db.retrieve_log_range_cap()? {
+        LOG_RANGE_CAPS.restore(chain, blocks);
+    }
+    Ok(())
+}
+
+/// Resolve the height syncing should continue from after attempting to move
+/// a cursor to `to`. A rejected regression is counted and syncing continues
+/// from the persisted cursor instead.
+fn next_from(outcome: CursorUpdate, to: u32, rejected_regressions: &IntCounter) -> u32 {
+    match outcome {
+        CursorUpdate::Stored => to,
+        CursorUpdate::RejectedRegression { current } => {
+            rejected_regressions.inc();
+            current
+        }
+    }
+}
+
+/// Report the latest recorded advance of a replica's committed root: its
+/// age in `root_advance_age` and the full record in `/status`
+fn report_root_advance(
+    db: &NomadDB,
+    root_advance_age: &IntGauge,
+    metrics: &CoreMetrics,
+    replica: &str,
+) -> Result<()> {
+    let latest = match db.latest_root_advance()? {
+        Some(latest) => latest,
+        None => return Ok(()),
+    };
+
+    if let Some(timestamp) = latest.timestamp {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("!timestamp")
+            .as_secs();
+        root_advance_age.set(now.saturating_sub(timestamp) as i64);
+    }
+    metrics.set_status(
+        ROOT_ADVANCES_SECTION,
+        replica,
+        serde_json::to_value(latest).expect("!serialize"),
+    );
+    Ok(())
+}
+
+/// Record the advances of a replica's committed root in blocks `from` to
+/// `to`, indexed before root advances were recorded. Pages a node is behind
+/// for are retried, and pages the provider of `chain` rejects as too large
+/// are retried within its cap.
+async fn backfill_root_advances<I: CommonIndexer>(
+    db: &NomadDB,
+    indexer: &I,
+    chain: &str,
+    (from, to): (u32, u32),
+    chunk_size: u32,
+) -> Result<()> {
+    if to <= from {
+        return Ok(());
+    }
+    info!(
+        from = from,
+        to = to,
+        "[Updates]: backfilling root advances from indexed blocks {}...{}",
+        from,
+        to,
+    );
+
+    let mut advances = vec![];
+    let mut start = from;
+    while start < to {
+        let end = min(start + LOG_RANGE_CAPS.page_size(chain, chunk_size), to);
+        match indexer.fetch_root_advances(start, end).await {
+            Ok(page) => {
+                record_served_range(db, chain, (start, end))?;
+                advances.extend(page)
+            }
+            Err(e) if node_behind(&e) => {
+                warn!(
+                    start = start,
+                    end = end,
+                    error = %e,
+                    "[Updates]: node behind requested blocks, retrying root advance backfill",
+                );
+                sleep(Duration::from_secs(RPC_REWIND_SECONDS)).await;
+                continue;
+            }
+            Err(e) if learn_range_cap(db, chain, &e, (start, end))? => continue,
+            Err(e) => return Err(e.into()),
+        }
+        start = end;
+    }
+
+    db.store_root_advances(&advances)?;
+    info!(
+        advances = advances.len(),
+        "[Updates]: backfilled {} root advances",
+        advances.len(),
+    );
+    Ok(())
+}
+
+/// Check the leaves of a page of messages indexed from blocks `start` to
+/// `end` that are also indexed at other leaf indexes. The pages every such
+/// leaf was indexed from are fetched again, and the leaf indexes of its
+/// `Dispatch` events must be the ones indexed. Matching duplicates are
+/// legitimate, such as the same message dispatched twice, and are reported in
+/// `duplicate_leaves`. Otherwise the indexed messages cannot be trusted.
+async fn check_duplicate_leaves<I: HomeIndexer>(
+    db: &NomadDB,
+    indexer: &I,
+    messages: &[RawCommittedMessage],
+    (start, end): (u32, u32),
+    duplicate_leaves: &IntGaugeVec,
+    labels: (&str, &str),
+) -> Result<()> {
+    let mut page: BTreeMap<H256, Vec<u32>> = BTreeMap::new();
+    for message in messages {
+        page.entry(message.leaf())
+            .or_default()
+            .push(message.leaf_index);
+    }
+
+    for (leaf, leaf_indexes) in page {
+        let mut occurrences: BTreeMap<u32, LeafOccurrence> = db
+            .leaf_occurrences(leaf)?
+            .into_iter()
+            .map(|occurrence| (occurrence.leaf_index, occurrence))
+            .collect();
+        occurrences.extend(leaf_indexes.into_iter().map(|leaf_index| {
+            (
+                leaf_index,
+                LeafOccurrence {
+                    leaf_index,
+                    from_block: start,
+                    to_block: end,
+                },
+            )
+        }));
+        if occurrences.len() < 2 {
+            continue;
+        }
+
+        let pages: BTreeSet<(u32, u32)> = occurrences
+            .values()
+            .map(|occurrence| (occurrence.from_block, occurrence.to_block))
+            .collect();
+        let mut dispatched = BTreeSet::new();
+        for (from, to) in pages {
+            let messages = loop {
+                match indexer.fetch_sorted_messages(from, to).await {
+                    Ok(messages) => break messages,
+                    Err(e) if node_behind(&e) => {
+                        warn!(
+                            from = from,
+                            to = to,
+                            error = %e,
+                            "[Messages]: node behind requested blocks, retrying duplicate leaf check",
+                        );
+                        sleep(Duration::from_secs(RPC_REWIND_SECONDS)).await;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            };
+            dispatched.extend(
+                messages
+                    .iter()
+                    .filter(|message| message.leaf() == leaf)
+                    .map(|message| message.leaf_index),
+            );
+        }
+
+        let indexed: Vec<u32> = occurrences.into_keys().collect();
+        let dispatched: Vec<u32> = dispatched.into_iter().collect();
+        if indexed != dispatched {
+            return Err(MessageIntegrityError::DuplicateLeafMismatch {
+                leaf,
+                indexed,
+                dispatched,
+            }
+            .into());
+        }
+
+        info!(
+            leaf = %CanonicalH256(leaf),
+            leaf_indexes = ?indexed,
+            "[Messages]: leaf dispatched at {} leaf indexes",
+            indexed.len(),
+        );
+        let (home, agent) = labels;
+        duplicate_leaves
+            .with_label_values(&[&CanonicalH256(leaf).to_string(), home, agent])
+            .set(indexed.len() as i64);
+    }
+    Ok(())
+}
+
 /// Entity that drives the syncing of an agent's db with on-chain data.
 /// Extracts chain-specific data (emitted updates, messages, etc) from an
 /// `indexer` and fills the agent's db with this data. A CachingHome or
 /// CachingReplica will use a contract sync to spawn syncing tasks to keep the
 /// db up-to-date.
+///
+/// Log queries are kept within the block range cap learned for the provider
+/// of the indexed chain, see `LOG_RANGE_CAPS`.
 #[derive(Debug, Clone)]
 pub struct ContractSync<I> {
     agent_name: String,
@@ -36,6 +305,7 @@ pub struct ContractSync<I> {
     page_settings: PageSettings,
     finality: u8,
     metrics: ContractSyncMetrics,
+    dispatches: Arc<Notify>,
 }
 
 impl<I> std::fmt::Display for ContractSync<I>
@@ -71,8 +341,21 @@ impl<I> ContractSync<I> {
             page_settings,
             finality,
             metrics,
+            dispatches: Default::default(),
         }
     }
+
+    /// Notified whenever message syncing stores new dispatched messages. A
+    /// notification is kept until someone waits for it, so none is missed
+    /// between waits.
+    pub fn dispatches(&self) -> Arc<Notify> {
+        self.dispatches.clone()
+    }
+
+    /// Return handle on the indexer
+    pub fn indexer(&self) -> Arc<I> {
+        self.indexer.clone()
+    }
 }
 
 impl<I> ContractSync<I>
@@ -89,6 +372,17 @@ where
     /// them in db. If run in timelag is off, will index at the tip
     /// but use a manual timelag to catch any missed updates. If timelag on,
     /// update  syncing will be run timelag blocks behind the tip.
+    ///
+    /// Updates are stored once, however many sources observe them. Later
+    /// observations are counted as duplicates against their source.
+    ///
+    /// Replica syncs also record each advance of the replica's committed
+    /// root, backfilling the blocks already indexed on their first run.
+    ///
+    /// CancellationSafe: a page of updates is stored before the cursor moves
+    /// past it, with no await in between. A rewind made before a fetch is
+    /// cancelled only re-indexes blocks. Backfilled advances are stored at
+    /// once, so a cancelled backfill runs again.
     pub fn sync_updates(&self) -> Instrumented<JoinHandle<Result<()>>> {
         let span = info_span!("UpdateContractSync");
 
@@ -113,7 +407,28 @@ where
             &self.agent_name,
         ]);
 
+        let rejected_regressions = self
+            .metrics
+            .rejected_cursor_regressions
+            .with_label_values(&[UPDATES_LABEL, &self.home, &self.replica, &self.agent_name]);
+
+        let source = self.replica.clone();
+        let duplicate_observations = self
+            .metrics
+            .duplicate_update_observations
+            .with_label_values(&[&self.replica, &self.home, &self.agent_name]);
+
+        // Replica syncs record where the committed root advanced
+        let tracks_root_advances = self.home != self.replica;
+        let root_advance_age = self.metrics.root_advance_age.with_label_values(&[
+            &self.home,
+            &self.replica,
+            &self.agent_name,
+        ]);
+        let core_metrics = self.metrics.core.clone();
+
         let timelag_on = self.index_settings.timelag_on();
+        let backoff = Backoff::from(self.index_settings.backoff);
         let finality = self.finality as u32;
         let config_from = self.page_settings.from;
         let chunk_size = self.page_settings.page_size;
@@ -124,36 +439,80 @@ where
                 .map_or_else(|| config_from, |h| h);
 
             info!(from = from, "[Updates]: resuming indexer from {}", from);
+            restore_range_cap(&db, &source)?;
+
+            // Persist the resume point so later rewinds are relative to it
+            from = next_from(
+                db.store_update_latest_block_end(from)?,
+                from,
+                &rejected_regressions,
+            );
+
+            if tracks_root_advances && db.latest_root_advance()?.is_none() {
+                backfill_root_advances(
+                    &db,
+                    indexer.as_ref(),
+                    &source,
+                    (config_from, from),
+                    chunk_size,
+                )
+                .await?;
+            }
 
+            // Consecutive failed polls, reset by a successful one
+            let mut failures = 0;
             loop {
                 indexed_height.set(from as i64);
+                if tracks_root_advances {
+                    report_root_advance(&db, &root_advance_age, &core_metrics, &source)?;
+                }
 
-                let tip = indexer.get_block_number().await?;
+                let tip = match indexer.get_block_number().await {
+                    Ok(tip) => tip,
+                    Err(e) if retryable(&e) => {
+                        failures += 1;
+                        let delay = backoff.delay(failures);
+                        warn!(
+                            error = %e,
+                            failures,
+                            delay = ?delay,
+                            "[Updates]: failed to get block number, backing off",
+                        );
+                        sleep(delay).await;
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
                 if tip <= from {
                     // Sleep if we caught up to tip
+                    failures = 0;
                     sleep(Duration::from_secs(100)).await;
                     continue;
                 }
 
-                let to = min(from + chunk_size, tip);
+                let to = min(from + LOG_RANGE_CAPS.page_size(&source, chunk_size), tip);
 
                 let (start, end) = if timelag_on {
                     // if timelag on, don't modify range
                     (from, to)
                 } else {
-                    let range = to - from;
-                    let last_final_block = tip - finality;
+                    let last_final_block = tip.saturating_sub(finality);
 
                     // If range includes non-final blocks, include range
-                    // blocks behind last final block
+                    // blocks behind last final block. Only the range is
+                    // widened. The stored cursor moves once the range is
+                    // fetched, so failed fetches never rewind it.
                     let from = if to >= last_final_block {
-                        last_final_block - range
+                        from.saturating_sub(to - last_final_block)
                     } else {
                         from
                     };
 
                     (from, to)
                 };
+                // The reorg lookback widens the range past the page size
+                let end = LOG_RANGE_CAPS.clamp_end(&source, start, end);
+                let to = end;
 
                 info!(
                     start = start,
@@ -163,18 +522,90 @@ where
                     end,
                 );
 
-                let sorted_updates = indexer.fetch_sorted_updates(start, end).await?;
+                let fetched = async {
+                    let updates = indexer.fetch_sorted_updates(start, end).await?;
+                    let advances = if tracks_root_advances {
+                        indexer.fetch_root_advances(start, end).await?
+                    } else {
+                        vec![]
+                    };
+                    Ok::<_, I::Error>((updates, advances))
+                };
+                let (sorted_updates, root_advances) = match fetched.await {
+                    Ok(fetched) => {
+                        record_served_range(&db, &source, (start, end))?;
+                        fetched
+                    }
+                    Err(e) if node_behind(&e) => {
+                        failures += 1;
+                        let delay = backoff.delay(failures);
+                        warn!(
+                            start = start,
+                            end = end,
+                            error = %e,
+                            delay = ?delay,
+                            "[Updates]: node behind requested blocks, rewinding {} blocks",
+                            RPC_REWIND_BLOCKS,
+                        );
+                        // Repeated errors look back no further than once
+                        let cursor = db.retrieve_update_latest_block_end().unwrap_or(config_from);
+                        from = cursor.saturating_sub(RPC_REWIND_BLOCKS).max(config_from);
+                        sleep(delay).await;
+                        continue;
+                    }
+                    Err(e) if learn_range_cap(&db, &source, &e, (start, end))? => continue,
+                    Err(e) if retryable(&e) => {
+                        failures += 1;
+                        let delay = backoff.delay(failures);
+                        warn!(
+                            start = start,
+                            end = end,
+                            error = %e,
+                            failures,
+                            delay = ?delay,
+                            "[Updates]: failed to fetch updates, backing off",
+                        );
+                        sleep(delay).await;
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                failures = 0;
+
+                // Record root advances of the page before the cursor moves
+                // past it
+                db.store_root_advances(&root_advances)?;
 
                 // If no updates found, update last seen block and next height
                 // and continue
                 if sorted_updates.is_empty() {
-                    db.store_update_latest_block_end(to)?;
-                    from = to;
+                    from = next_from(
+                        db.store_update_latest_block_end(to)?,
+                        to,
+                        &rejected_regressions,
+                    );
                     continue;
                 }
 
-                // Store updates
-                db.store_updates_and_meta(&sorted_updates)?;
+                // Store updates no source observed before
+                let mut new_updates = vec![];
+                for update in sorted_updates.iter() {
+                    match db.observe_update(&source, update)? {
+                        Observation::New => new_updates.push(update),
+                        Observation::Duplicate { .. } => {
+                            debug!(
+                                source = source.as_str(),
+                                new_root = ?update.signed_update.update.new_root,
+                                "Skipping update already observed",
+                            );
+                            duplicate_observations.inc();
+                        }
+                    }
+                }
+
+                // Updates below the lowest block the next range can start
+                // from are never fetched again
+                db.prune_seen_updates(end.saturating_sub(finality) as u64)?;
 
                 // Report latencies from emit to store if caught up
                 if to == tip {
@@ -182,7 +613,7 @@ where
                         .duration_since(UNIX_EPOCH)
                         .expect("!timestamp")
                         .as_secs();
-                    for update in sorted_updates.iter() {
+                    for update in new_updates.iter() {
                         let new_root = update.signed_update.update.new_root;
 
                         if let Some(event_timestamp) = update.metadata.timestamp {
@@ -191,7 +622,7 @@ where
                                 new_root = ?new_root,
                                 latency = latency,
                                 "Latency for update with new_root {}: {}.",
-                                new_root,
+                                CanonicalH256(new_root),
                                 latency,
                             );
                             store_update_latency.observe(latency as f64);
@@ -202,11 +633,14 @@ where
                 }
 
                 // Report amount of updates stored into db
-                stored_updates.add(sorted_updates.len().try_into()?);
+                stored_updates.add(new_updates.len().try_into()?);
 
                 // Move forward next height
-                db.store_update_latest_block_end(to)?;
-                from = to;
+                from = next_from(
+                    db.store_update_latest_block_end(to)?,
+                    to,
+                    &rejected_regressions,
+                );
             }
         })
         .instrument(span)
@@ -217,6 +651,88 @@ impl<I> ContractSync<I>
 where
     I: HomeIndexer + 'static,
 {
+    /// Load a tree snapshot into an empty db and move the update cursor to
+    /// the snapshot block, so indexing resumes from there. The snapshot is
+    /// rejected unless its committed leaves rebuild its root and an update
+    /// committing that root exists on chain at the snapshot block.
+    ///
+    /// Only the committed leaves are stored. The message cursor moves to a
+    /// block at or before the on-chain dispatch of the last committed leaf,
+    /// so live indexing fetches the leaves after it from chain rather than
+    /// trusting the snapshot's tail.
+    ///
+    /// CancellationSafe: every write follows the last await, so a cancelled
+    /// bootstrap leaves the db empty.
+    pub async fn bootstrap_from(&self, snapshot: TreeSnapshot) -> Result<()> {
+        if !self.db.is_empty()? {
+            bail!(SnapshotError::DbNotEmpty);
+        }
+        snapshot.verify_root()?;
+        let update = snapshot.committing_update(self.indexer.as_ref()).await?;
+
+        let block = snapshot.block_number.try_u32()?;
+        let committed = snapshot.committed_messages();
+        let message_block = match committed.last() {
+            Some(last) => Some(self.dispatch_page_start(last, block).await?),
+            None => None,
+        };
+
+        for message in committed.iter() {
+            self.db.store_latest_message(message)?;
+        }
+        self.db.store_updates_and_meta(&[update])?;
+        self.db.store_prover_latest_committed(snapshot.root)?;
+
+        self.db.store_update_latest_block_end(block)?;
+        if let Some(message_block) = message_block {
+            self.db.store_message_latest_block_end(message_block)?;
+        }
+
+        info!(
+            root = %CanonicalH256(snapshot.root),
+            committed = snapshot.committed,
+            skipped_tail = snapshot.messages.len() - committed.len(),
+            block = block,
+            message_block = ?message_block,
+            "Bootstrapped db from snapshot with {} committed leaves at block {}",
+            snapshot.committed,
+            block,
+        );
+        Ok(())
+    }
+
+    /// Page back from block `to` until the dispatch of `leaf` is found on
+    /// chain, and return the first block of its page. The dispatched leaf
+    /// must match `leaf`.
+    async fn dispatch_page_start(&self, leaf: &RawCommittedMessage, to: u32) -> Result<u32> {
+        let config_from = self.page_settings.from;
+        let page = LOG_RANGE_CAPS.page_size(&self.replica, self.page_settings.page_size);
+
+        let mut end = to;
+        loop {
+            let start = end.saturating_sub(page).max(config_from);
+            let messages = self.indexer.fetch_sorted_messages(start, end).await?;
+
+            if let Some(dispatched) = messages.iter().find(|m| m.leaf_index == leaf.leaf_index) {
+                if dispatched.leaf() != leaf.leaf() {
+                    bail!(SnapshotError::DispatchMismatch {
+                        leaf_index: leaf.leaf_index,
+                    });
+                }
+                return Ok(start);
+            }
+
+            // Paged past the leaf, or out of blocks to search
+            if messages.iter().any(|m| m.leaf_index < leaf.leaf_index) || start <= config_from {
+                bail!(SnapshotError::NotDispatched {
+                    leaf_index: leaf.leaf_index,
+                    block_number: to.into(),
+                });
+            }
+            end = start - 1;
+        }
+    }
+
     /// Spawn sync task to sync home updates (and potentially messages)
     pub fn spawn_home(self) -> Instrumented<JoinHandle<Result<()>>> {
         let span = info_span!("ContractSync: Home", self = %self);
@@ -245,6 +761,12 @@ where
     /// ordering of messages is not guaranteed like it is for updates. Running
     /// without a timelag could cause messages with the incorrectly ordered
     /// index to be stored.
+    ///
+    /// Leaves indexed at more than one leaf index are checked against the
+    /// chain before their page is stored, see `check_duplicate_leaves`.
+    ///
+    /// CancellationSafe: a page of messages and its leaf occurrences are
+    /// stored before the cursor moves past it, with no await in between.
     pub fn sync_messages(&self) -> Instrumented<JoinHandle<Result<()>>> {
         let span = info_span!("MessageContractSync");
 
@@ -264,9 +786,21 @@ where
             &self.agent_name,
         ]);
 
+        let rejected_regressions = self
+            .metrics
+            .rejected_cursor_regressions
+            .with_label_values(&[MESSAGES_LABEL, &self.home, &self.replica, &self.agent_name]);
+
+        let duplicate_leaves = self.metrics.duplicate_leaves.clone();
+        let chain = self.replica.clone();
+        let home = self.home.clone();
+        let agent_name = self.agent_name.clone();
+
         let timelag_on = self.index_settings.timelag_on();
+        let backoff = Backoff::from(self.index_settings.backoff);
         let config_from = self.page_settings.from;
         let chunk_size = self.page_settings.page_size;
+        let dispatches = self.dispatches.clone();
 
         tokio::spawn(async move {
             let mut from = db
@@ -274,18 +808,37 @@ where
                 .map_or_else(|| config_from, |h| h);
 
             info!(from = from, "[Messages]: resuming indexer from {}", from);
+            restore_range_cap(&db, &chain)?;
 
+            // Consecutive failed polls, reset by a successful one
+            let mut failures = 0;
             loop {
                 indexed_height.set(from as i64);
 
-                let tip = indexer.get_block_number().await?;
+                let tip = match indexer.get_block_number().await {
+                    Ok(tip) => tip,
+                    Err(e) if retryable(&e) => {
+                        failures += 1;
+                        let delay = backoff.delay(failures);
+                        warn!(
+                            error = %e,
+                            failures,
+                            delay = ?delay,
+                            "[Messages]: failed to get block number, backing off",
+                        );
+                        sleep(delay).await;
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
                 if tip <= from {
                     // Sleep if caught up to tip
+                    failures = 0;
                     sleep(Duration::from_secs(100)).await;
                     continue;
                 }
 
-                let candidate = from + chunk_size;
+                let candidate = from + LOG_RANGE_CAPS.page_size(&chain, chunk_size);
                 let to = min(tip, candidate);
 
                 // timelag always applied
@@ -303,25 +856,77 @@ where
                     end
                 );
 
-                let sorted_messages = indexer.fetch_sorted_messages(start, end).await?;
+                // Messages are indexed behind a timelag, so the range is
+                // retried as is rather than rewound, also when a node is
+                // behind the requested blocks
+                let sorted_messages = match indexer.fetch_sorted_messages(start, end).await {
+                    Ok(messages) => {
+                        record_served_range(&db, &chain, (start, end))?;
+                        messages
+                    }
+                    Err(e) if learn_range_cap(&db, &chain, &e, (start, end))? => continue,
+                    Err(e) if retryable(&e) => {
+                        failures += 1;
+                        let delay = backoff.delay(failures);
+                        warn!(
+                            start = start,
+                            end = end,
+                            error = %e,
+                            failures,
+                            delay = ?delay,
+                            "[Messages]: failed to fetch messages, backing off",
+                        );
+                        sleep(delay).await;
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                failures = 0;
 
                 // If no messages found, update last seen block and next height
                 // and continue
                 if sorted_messages.is_empty() {
-                    db.store_message_latest_block_end(to)?;
-                    from = to;
+                    from = next_from(
+                        db.store_message_latest_block_end(to)?,
+                        to,
+                        &rejected_regressions,
+                    );
                     continue;
                 }
 
-                // Store messages
+                check_duplicate_leaves(
+                    &db,
+                    indexer.as_ref(),
+                    &sorted_messages,
+                    (start, end),
+                    &duplicate_leaves,
+                    (&home, &agent_name),
+                )
+                .await?;
+
+                // Store messages and where their leaves were indexed from
                 db.store_messages(&sorted_messages)?;
+                for message in &sorted_messages {
+                    db.record_leaf_occurrence(
+                        message.leaf(),
+                        LeafOccurrence {
+                            leaf_index: message.leaf_index,
+                            from_block: start,
+                            to_block: end,
+                        },
+                    )?;
+                }
+                dispatches.notify_one();
 
                 // Report amount of messages stored into db
                 stored_messages.add(sorted_messages.len().try_into()?);
 
                 // Move forward next height
-                db.store_message_latest_block_end(to)?;
-                from = to;
+                from = next_from(
+                    db.store_message_latest_block_end(to)?,
+                    to,
+                    &rejected_regressions,
+                );
             }
         })
         .instrument(span)
@@ -339,7 +944,11 @@ mod test {
     use ethers::signers::LocalWallet;
 
     use crate::chains::PageSettings;
-    use nomad_core::{SignedUpdateWithMeta, Update, UpdateMeta};
+    use crate::snapshot::test::{snapshot_of, snapshot_with_tail};
+    use nomad_core::{
+        accumulator::{Merkle, NomadLightMerkle},
+        RootAdvance, RootProvenance, SignedUpdateWithMeta, Update, UpdateMeta,
+    };
     use nomad_test::test_utils;
 
     use super::*;
@@ -347,6 +956,388 @@ mod test {
 
     const FINALITY: u8 = 5;
 
+    #[tokio::test]
+    async fn rejects_silent_cursor_regressions() {
+        test_utils::run_test_db(|db| async move {
+            let nomad_db = NomadDB::new("home_1", db);
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "contract_sync_test",
+                    "home",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .expect("could not make metrics"),
+            );
+            let rejected_regressions = ContractSyncMetrics::new(metrics)
+                .rejected_cursor_regressions
+                .with_label_values(&[UPDATES_LABEL, "home_1", "replica_1", "agent"]);
+
+            let outcome = nomad_db.store_update_latest_block_end(20).expect("!db");
+            assert_eq!(next_from(outcome, 20, &rejected_regressions), 20);
+
+            // Silent regression is blocked, reported and the cursor is kept
+            let outcome = nomad_db.store_update_latest_block_end(10).expect("!db");
+            assert_eq!(outcome, CursorUpdate::RejectedRegression { current: 20 });
+            assert_eq!(next_from(outcome, 10, &rejected_regressions), 20);
+            assert_eq!(rejected_regressions.get(), 1);
+            assert_eq!(nomad_db.retrieve_update_latest_block_end(), Some(20));
+
+            // Explicit rewind is allowed
+            let rewound = nomad_db
+                .rewind_update_latest_block_end(5, "test rewind")
+                .expect("!db");
+            assert_eq!(rewound, 15);
+            assert_eq!(nomad_db.retrieve_update_latest_block_end(), Some(15));
+
+            // Message cursor is guarded independently
+            nomad_db.store_message_latest_block_end(30).expect("!db");
+            let outcome = nomad_db.store_message_latest_block_end(29).expect("!db");
+            assert_eq!(next_from(outcome, 29, &rejected_regressions), 30);
+            assert_eq!(rejected_regressions.get(), 2);
+            assert_eq!(nomad_db.retrieve_message_latest_block_end(), Some(30));
+
+            // Rewinds never wrap below genesis
+            let rewound = nomad_db
+                .rewind_update_latest_block_end(100, "test rewind")
+                .expect("!db");
+            assert_eq!(rewound, 0);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn reports_the_latest_root_advance() {
+        test_utils::run_test_db(|db| async move {
+            let nomad_db = NomadDB::new("replica_1", db);
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "contract_sync_test",
+                    "home",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .expect("could not make metrics"),
+            );
+            let root_advance_age = ContractSyncMetrics::new(metrics.clone())
+                .root_advance_age
+                .with_label_values(&["home_1", "replica_1", "agent"]);
+
+            // Nothing to report before the first advance
+            report_root_advance(&nomad_db, &root_advance_age, &metrics, "replica_1").unwrap();
+            assert!(metrics.status().get(ROOT_ADVANCES_SECTION).is_none());
+
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let advance = |root: u8, provenance| RootAdvance {
+                root: H256::repeat_byte(root),
+                provenance,
+                block_number: root as u64,
+                timestamp: Some(now - 600 + root as u64),
+                tx_hash: H256::repeat_byte(root + 100),
+            };
+            nomad_db
+                .store_root_advances(&[
+                    advance(1, RootProvenance::Update),
+                    advance(2, RootProvenance::Governance),
+                ])
+                .unwrap();
+
+            report_root_advance(&nomad_db, &root_advance_age, &metrics, "replica_1").unwrap();
+            assert!((597..=610).contains(&root_advance_age.get()));
+            let status = &metrics.status()[ROOT_ADVANCES_SECTION]["replica_1"];
+            assert_eq!(status["provenance"], "governance");
+            assert_eq!(status["blockNumber"], 2);
+            assert_eq!(
+                status["txHash"],
+                serde_json::to_value(H256::repeat_byte(102)).unwrap()
+            );
+        })
+        .await
+    }
+
+    #[test]
+    fn it_recognizes_nodes_behind_through_error_chains() {
+        use crate::ChainCommunicationError;
+        use ethers::providers::{Http, ProviderError};
+        use nomad_ethereum::{ClassifiedRpcError, EthereumError, RetryingProviderError};
+
+        let chain_error = |class| {
+            let provider_error: ProviderError =
+                RetryingProviderError::<Http>::Unretryable(ClassifiedRpcError {
+                    pattern: "header_not_found".to_owned(),
+                    class,
+                    code: Some(-32000),
+                    message: "header not found".to_owned(),
+                })
+                .into();
+            ChainCommunicationError::from(EthereumError::MiddlewareError(provider_error.into()))
+        };
+
+        assert!(node_behind(&chain_error(RpcErrorClass::RewindAndRetry)));
+        assert!(!node_behind(&chain_error(RpcErrorClass::Fatal)));
+        assert!(!node_behind(&ChainCommunicationError::HomeFailed(
+            "home".to_owned()
+        )));
+    }
+
+    fn bootstrap_sync(db: NomadDB, indexer: MockIndexer) -> ContractSync<MockIndexer> {
+        let metrics = Arc::new(
+            CoreMetrics::new(
+                "contract_sync_test",
+                "home",
+                None,
+                Arc::new(prometheus::Registry::new()),
+            )
+            .expect("could not make metrics"),
+        );
+
+        ContractSync::new(
+            "agent".to_owned(),
+            "home_1".to_owned(),
+            "replica_1".to_owned(),
+            db,
+            Arc::new(indexer),
+            IndexSettings {
+                data_types: IndexDataTypes::UpdatesAndMessages,
+                use_timelag: true,
+                max_block_range: None,
+                backoff: Default::default(),
+            },
+            PageSettings {
+                from: 0,
+                page_size: 10,
+            },
+            FINALITY,
+            ContractSyncMetrics::new(metrics),
+        )
+    }
+
+    async fn committing_update(snapshot: &TreeSnapshot) -> SignedUpdateWithMeta {
+        let signer: LocalWallet =
+            "1111111111111111111111111111111111111111111111111111111111111111"
+                .parse()
+                .unwrap();
+
+        SignedUpdateWithMeta {
+            signed_update: Update {
+                home_domain: snapshot.domain,
+                previous_root: H256::zero(),
+                new_root: snapshot.root,
+            }
+            .sign_with(&signer)
+            .await
+            .expect("!sign"),
+            metadata: UpdateMeta {
+                block_number: snapshot.block_number,
+                timestamp: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn bootstraps_from_verified_snapshot() {
+        test_utils::run_test_db(|db| async move {
+            let snapshot = snapshot_of(5);
+            let update = committing_update(&snapshot).await;
+
+            let mut mock_indexer = MockIndexer::new();
+            mock_indexer
+                .expect__fetch_sorted_updates()
+                .withf(|from, to| *from == 50 && *to == 50)
+                .return_once(move |_, _| Ok(vec![update]));
+            let dispatched = snapshot.messages.clone();
+            mock_indexer
+                .expect__fetch_sorted_messages()
+                .withf(|from, to| *from == 40 && *to == 50)
+                .return_once(move |_, _| Ok(dispatched));
+
+            let nomad_db = NomadDB::new("home_1", db);
+            let contract_sync = bootstrap_sync(nomad_db.clone(), mock_indexer);
+            contract_sync
+                .bootstrap_from(snapshot.clone())
+                .await
+                .expect("!bootstrap");
+
+            assert_eq!(nomad_db.retrieve_latest_leaf_index().unwrap(), Some(4));
+            assert_eq!(
+                nomad_db.leaf_by_leaf_index(2).unwrap(),
+                Some(snapshot.messages[2].leaf())
+            );
+            assert_eq!(
+                nomad_db.retrieve_latest_root().unwrap(),
+                Some(snapshot.root)
+            );
+            assert_eq!(
+                nomad_db.retrieve_prover_latest_committed().unwrap(),
+                Some(snapshot.root)
+            );
+            assert_eq!(nomad_db.retrieve_update_latest_block_end(), Some(50));
+            assert_eq!(nomad_db.retrieve_message_latest_block_end(), Some(40));
+
+            // A second bootstrap into the now populated db is refused
+            let err = contract_sync.bootstrap_from(snapshot).await.unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<SnapshotError>(),
+                Some(&SnapshotError::DbNotEmpty)
+            );
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn bootstraps_only_the_committed_leaves() {
+        test_utils::run_test_db(|db| async move {
+            // The tail is not covered by the root, and was tampered with
+            let mut snapshot = snapshot_with_tail(5, 2);
+            let last = snapshot.messages[5].message.len() - 1;
+            snapshot.messages[5].message[last] ^= 1;
+            let update = committing_update(&snapshot).await;
+
+            let mut mock_indexer = MockIndexer::new();
+            mock_indexer
+                .expect__fetch_sorted_updates()
+                .return_once(move |_, _| Ok(vec![update]));
+
+            // The last committed leaf was dispatched a page before the
+            // committing update
+            let mut seq = Sequence::new();
+            mock_indexer
+                .expect__fetch_sorted_messages()
+                .withf(|from, to| *from == 40 && *to == 50)
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_once(|_, _| Ok(vec![]));
+            let dispatched = snapshot.committed_messages().to_vec();
+            mock_indexer
+                .expect__fetch_sorted_messages()
+                .withf(|from, to| *from == 29 && *to == 39)
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_once(move |_, _| Ok(dispatched));
+
+            let nomad_db = NomadDB::new("home_1", db);
+            let contract_sync = bootstrap_sync(nomad_db.clone(), mock_indexer);
+            contract_sync
+                .bootstrap_from(snapshot.clone())
+                .await
+                .expect("!bootstrap");
+
+            assert_eq!(nomad_db.retrieve_latest_leaf_index().unwrap(), Some(4));
+            assert_eq!(nomad_db.leaf_by_leaf_index(5).unwrap(), None);
+            assert_eq!(nomad_db.retrieve_update_latest_block_end(), Some(50));
+            // Live indexing fetches the tail from chain
+            assert_eq!(nomad_db.retrieve_message_latest_block_end(), Some(29));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn cancelled_bootstrap_leaves_db_empty() {
+        test_utils::run_test_db(|db| async move {
+            let snapshot = snapshot_of(5);
+            let update = committing_update(&snapshot).await;
+
+            let mut mock_indexer = MockIndexer::new();
+            mock_indexer
+                .expect__fetch_sorted_updates()
+                .returning(move |_, _| Ok(vec![update.clone()]));
+            let dispatched = snapshot.messages.clone();
+            mock_indexer
+                .expect__fetch_sorted_messages()
+                .returning(move |_, _| Ok(dispatched.clone()));
+
+            let nomad_db = NomadDB::new("home_1", db);
+            let contract_sync = &bootstrap_sync(nomad_db.clone(), mock_indexer);
+            let polls = test_utils::cancel_at_each_await(
+                || contract_sync.bootstrap_from(snapshot.clone()),
+                |polls| {
+                    assert!(nomad_db.is_empty().unwrap(), "cancelled after {}", polls);
+                    assert_eq!(nomad_db.retrieve_latest_leaf_index().unwrap(), None);
+                    assert_eq!(nomad_db.retrieve_prover_latest_committed().unwrap(), None);
+                    assert_eq!(nomad_db.retrieve_message_latest_block_end(), None);
+                },
+            );
+            assert!(polls > 1);
+
+            assert_eq!(nomad_db.retrieve_latest_leaf_index().unwrap(), Some(4));
+            assert_eq!(
+                nomad_db.retrieve_prover_latest_committed().unwrap(),
+                Some(snapshot.root)
+            );
+            assert_eq!(nomad_db.retrieve_message_latest_block_end(), Some(40));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn rejects_bootstrap_from_tampered_snapshot() {
+        test_utils::run_test_db(|db| async move {
+            let mut snapshot = snapshot_of(5);
+            let update = committing_update(&snapshot).await;
+
+            // The chain commits the original root, but one leaf was altered
+            let last = snapshot.messages[3].message.len() - 1;
+            snapshot.messages[3].message[last] ^= 1;
+
+            let mut mock_indexer = MockIndexer::new();
+            mock_indexer
+                .expect__fetch_sorted_updates()
+                .return_once(move |_, _| Ok(vec![update]));
+
+            let nomad_db = NomadDB::new("home_1", db);
+            let contract_sync = bootstrap_sync(nomad_db.clone(), mock_indexer);
+            let err = contract_sync.bootstrap_from(snapshot).await.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<SnapshotError>(),
+                Some(SnapshotError::RootMismatch { .. })
+            ));
+            assert!(nomad_db.is_empty().unwrap());
+            assert_eq!(nomad_db.retrieve_message_latest_block_end(), None);
+
+            // Recomputing the root over the tampered leaves does not help
+            // without an update committing it on chain
+            let mut snapshot = snapshot_of(5);
+            snapshot.messages[3].message[last] ^= 1;
+            let leaves: Vec<_> = snapshot.messages.iter().map(|m| m.leaf()).collect();
+            snapshot.root = NomadLightMerkle::from_leaves(&leaves).root();
+
+            let mut mock_indexer = MockIndexer::new();
+            mock_indexer
+                .expect__fetch_sorted_updates()
+                .return_once(move |_, _| Ok(vec![]));
+            let contract_sync = bootstrap_sync(nomad_db.clone(), mock_indexer);
+            let err = contract_sync.bootstrap_from(snapshot).await.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<SnapshotError>(),
+                Some(SnapshotError::NotCommitted { .. })
+            ));
+            assert!(nomad_db.is_empty().unwrap());
+
+            // The committed leaves must have been dispatched on chain
+            let snapshot = snapshot_of(5);
+            let update = committing_update(&snapshot).await;
+            let mut mock_indexer = MockIndexer::new();
+            mock_indexer
+                .expect__fetch_sorted_updates()
+                .return_once(move |_, _| Ok(vec![update]));
+            mock_indexer
+                .expect__fetch_sorted_messages()
+                .times(5)
+                .returning(|_, _| Ok(vec![]));
+            let contract_sync = bootstrap_sync(nomad_db.clone(), mock_indexer);
+            let err = contract_sync.bootstrap_from(snapshot).await.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<SnapshotError>(),
+                Some(SnapshotError::NotDispatched { leaf_index: 4, .. })
+            ));
+            assert!(nomad_db.is_empty().unwrap());
+        })
+        .await
+    }
+
     /* RPC Behavior:
      *  Starting Tip: block 20
      *  Starting Last Final Block: block 15
@@ -520,6 +1511,8 @@ mod test {
             let index_settings = IndexSettings {
                 data_types: IndexDataTypes::Updates,
                 use_timelag: false,
+                max_block_range: None,
+                backoff: Default::default(),
             };
             let page_settings = PageSettings {
                 from: 10,
@@ -586,4 +1579,718 @@ mod test {
         })
         .await
     }
+
+    #[tokio::test]
+    async fn stores_updates_observed_by_several_sources_once() {
+        test_utils::run_test_db(|db| async move {
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+            let update = SignedUpdateWithMeta {
+                signed_update: Update {
+                    home_domain: 1,
+                    previous_root: H256::zero(),
+                    new_root: H256::repeat_byte(1),
+                }
+                .sign_with(&signer)
+                .await
+                .expect("!sign"),
+                metadata: UpdateMeta {
+                    block_number: 18,
+                    timestamp: Default::default(),
+                },
+            };
+
+            let nomad_db = NomadDB::new("home_1", db);
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "contract_sync_test",
+                    "home",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .expect("could not make metrics"),
+            );
+            let sync_metrics = ContractSyncMetrics::new(metrics);
+
+            let sources = ["replica_1", "replica_2", "replica_3"];
+            for source in sources {
+                let mut mock_indexer = MockIndexer::new();
+                mock_indexer.expect__get_block_number().returning(|| Ok(20));
+                let fetched = update.clone();
+                mock_indexer
+                    .expect__fetch_sorted_updates()
+                    .times(1)
+                    .return_once(move |_, _| Ok(vec![fetched]));
+
+                let contract_sync = ContractSync::new(
+                    "agent".to_owned(),
+                    "home_1".to_owned(),
+                    source.to_owned(),
+                    nomad_db.clone(),
+                    Arc::new(mock_indexer),
+                    IndexSettings {
+                        data_types: IndexDataTypes::Updates,
+                        use_timelag: true,
+                        max_block_range: None,
+                        backoff: Default::default(),
+                    },
+                    PageSettings {
+                        from: 0,
+                        page_size: 50,
+                    },
+                    FINALITY,
+                    sync_metrics.clone(),
+                );
+                // Sources share the db cursor, so each indexes from the start
+                nomad_db
+                    .rewind_update_latest_block_end(20, "next source")
+                    .expect("!db");
+
+                let sync_task = contract_sync.sync_updates();
+                sleep(Duration::from_secs(1)).await;
+                cancel_task!(sync_task);
+            }
+
+            assert_eq!(
+                nomad_db
+                    .update_by_previous_root(H256::zero())
+                    .expect("!db")
+                    .expect("!update"),
+                update.signed_update
+            );
+
+            let seen = nomad_db.retrieve_seen_updates().expect("!db");
+            assert_eq!(seen.len(), 1);
+            assert_eq!(seen.get(&update).expect("!seen").sources, sources);
+
+            for (source, duplicates, stored) in [
+                ("replica_1", 0, 1),
+                ("replica_2", 1, 0),
+                ("replica_3", 1, 0),
+            ] {
+                assert_eq!(
+                    sync_metrics
+                        .duplicate_update_observations
+                        .with_label_values(&[source, "home_1", "agent"])
+                        .get(),
+                    duplicates
+                );
+                assert_eq!(
+                    sync_metrics
+                        .stored_events
+                        .with_label_values(&[UPDATES_LABEL, "home_1", source, "agent"])
+                        .get(),
+                    stored
+                );
+            }
+
+            // Once every source is past its block, the update is pruned
+            nomad_db.prune_seen_updates(19).expect("!db");
+            assert!(nomad_db.retrieve_seen_updates().expect("!db").is_empty());
+        })
+        .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_failed_polls_and_resets_after_success() {
+        use nomad_test::MockError;
+        use nomad_xyz_configuration::agent::IndexBackoffConfig;
+        use std::sync::Mutex;
+        use tokio::time::Instant;
+
+        test_utils::run_test_db(|db| async move {
+            // Four failures, a poll indexing to the tip, a poll caught up
+            // with it, then another failure
+            let polls: Arc<Mutex<Vec<Instant>>> = Default::default();
+            let mut mock_indexer = MockIndexer::new();
+            {
+                let polls = polls.clone();
+                mock_indexer.expect__get_block_number().returning(move || {
+                    let mut polls = polls.lock().unwrap();
+                    polls.push(Instant::now());
+                    match polls.len() {
+                        1..=4 | 7 => Err(MockError::Failed),
+                        _ => Ok(20),
+                    }
+                });
+            }
+            mock_indexer
+                .expect__fetch_sorted_updates()
+                .returning(|_, _| Ok(vec![]));
+
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "contract_sync_test",
+                    "home",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .expect("could not make metrics"),
+            );
+            let contract_sync = ContractSync::new(
+                "agent".to_owned(),
+                "home_1".to_owned(),
+                "home_1".to_owned(),
+                NomadDB::new("home_1", db),
+                Arc::new(mock_indexer),
+                IndexSettings {
+                    data_types: IndexDataTypes::Updates,
+                    use_timelag: true,
+                    max_block_range: None,
+                    backoff: IndexBackoffConfig {
+                        initial_delay_ms: 1000,
+                        multiplier: 2.0,
+                        max_delay_ms: 3000,
+                        jitter: 0.0,
+                    },
+                },
+                PageSettings {
+                    from: 0,
+                    page_size: 50,
+                },
+                FINALITY,
+                ContractSyncMetrics::new(metrics),
+            );
+
+            let start = Instant::now();
+            let sync_task = contract_sync.sync_updates();
+            sleep(Duration::from_secs(115)).await;
+            cancel_task!(sync_task);
+
+            // Delays double up to the max, and the poll indexing to the tip
+            // resets them
+            let polls = polls.lock().unwrap();
+            let offsets: Vec<u64> = polls
+                .iter()
+                .map(|poll| poll.duration_since(start).as_secs())
+                .collect();
+            assert_eq!(offsets, [0, 1, 3, 6, 9, 9, 109, 110]);
+        })
+        .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn nodes_behind_rewind_the_range_but_not_the_cursor() {
+        use nomad_ethereum::ClassifiedRpcError;
+        use nomad_test::MockError;
+        use nomad_xyz_configuration::agent::IndexBackoffConfig;
+        use std::sync::Mutex;
+
+        test_utils::run_test_db(|db| async move {
+            // The node is behind for eight fetches before one succeeds
+            let ranges: Arc<Mutex<Vec<(u32, u32)>>> = Default::default();
+            let mut mock_indexer = MockIndexer::new();
+            mock_indexer
+                .expect__get_block_number()
+                .returning(|| Ok(100));
+            {
+                let ranges = ranges.clone();
+                mock_indexer
+                    .expect__fetch_sorted_updates()
+                    .returning(move |from, to| {
+                        let mut ranges = ranges.lock().unwrap();
+                        ranges.push((from, to));
+                        if ranges.len() <= 8 {
+                            Err(MockError::Rpc(ClassifiedRpcError {
+                                pattern: "header_not_found".to_owned(),
+                                class: RpcErrorClass::RewindAndRetry,
+                                code: Some(-32000),
+                                message: "header not found".to_owned(),
+                            }))
+                        } else {
+                            Ok(vec![])
+                        }
+                    });
+            }
+
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "contract_sync_test",
+                    "home",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .expect("could not make metrics"),
+            );
+            let nomad_db = NomadDB::new("home_1", db);
+            nomad_db.store_update_latest_block_end(60).expect("!db");
+            let contract_sync = ContractSync::new(
+                "agent".to_owned(),
+                "home_1".to_owned(),
+                "home_1".to_owned(),
+                nomad_db.clone(),
+                Arc::new(mock_indexer),
+                IndexSettings {
+                    data_types: IndexDataTypes::Updates,
+                    use_timelag: true,
+                    max_block_range: None,
+                    backoff: IndexBackoffConfig {
+                        initial_delay_ms: 1000,
+                        multiplier: 2.0,
+                        max_delay_ms: 3000,
+                        jitter: 0.0,
+                    },
+                },
+                PageSettings {
+                    from: 0,
+                    page_size: 50,
+                },
+                FINALITY,
+                ContractSyncMetrics::new(metrics),
+            );
+
+            let sync_task = contract_sync.sync_updates();
+            while ranges.lock().unwrap().len() <= 8 {
+                assert_eq!(nomad_db.retrieve_update_latest_block_end(), Some(60));
+                sleep(Duration::from_secs(1)).await;
+            }
+            sleep(Duration::from_secs(1)).await;
+            cancel_task!(sync_task);
+
+            // Retries start `RPC_REWIND_BLOCKS` behind the cursor however
+            // often the node is behind
+            let ranges = ranges.lock().unwrap();
+            assert_eq!(ranges[0], (60, 100));
+            assert!(ranges[1..].iter().all(|range| *range == (55, 100)));
+            assert_eq!(nomad_db.retrieve_update_latest_block_end(), Some(100));
+        })
+        .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_fetches_over_non_final_blocks_keep_the_cursor() {
+        use nomad_test::MockError;
+        use nomad_xyz_configuration::agent::IndexBackoffConfig;
+        use std::sync::Mutex;
+
+        test_utils::run_test_db(|db| async move {
+            // The cursor starts within finality of the tip, so every range
+            // is widened by the reorg lookback. Fetches fail five times
+            // before one succeeds.
+            let ranges: Arc<Mutex<Vec<(u32, u32)>>> = Default::default();
+            let mut mock_indexer = MockIndexer::new();
+            mock_indexer
+                .expect__get_block_number()
+                .returning(|| Ok(100));
+            {
+                let ranges = ranges.clone();
+                mock_indexer
+                    .expect__fetch_sorted_updates()
+                    .returning(move |from, to| {
+                        let mut ranges = ranges.lock().unwrap();
+                        ranges.push((from, to));
+                        if ranges.len() <= 5 {
+                            Err(MockError::Failed)
+                        } else {
+                            Ok(vec![])
+                        }
+                    });
+            }
+
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "contract_sync_test",
+                    "home",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .expect("could not make metrics"),
+            );
+            let nomad_db = NomadDB::new("home_1", db);
+            nomad_db.store_update_latest_block_end(98).expect("!db");
+            let contract_sync = ContractSync::new(
+                "agent".to_owned(),
+                "home_1".to_owned(),
+                "home_1".to_owned(),
+                nomad_db.clone(),
+                Arc::new(mock_indexer),
+                IndexSettings {
+                    data_types: IndexDataTypes::Updates,
+                    use_timelag: false,
+                    max_block_range: None,
+                    backoff: IndexBackoffConfig {
+                        initial_delay_ms: 1000,
+                        multiplier: 2.0,
+                        max_delay_ms: 3000,
+                        jitter: 0.0,
+                    },
+                },
+                PageSettings {
+                    from: 0,
+                    page_size: 50,
+                },
+                FINALITY,
+                ContractSyncMetrics::new(metrics),
+            );
+
+            let sync_task = contract_sync.sync_updates();
+            while ranges.lock().unwrap().len() <= 5 {
+                // Retries never rewind the stored cursor
+                assert_eq!(nomad_db.retrieve_update_latest_block_end(), Some(98));
+                sleep(Duration::from_millis(500)).await;
+            }
+            sleep(Duration::from_secs(1)).await;
+            cancel_task!(sync_task);
+
+            // Every attempt looked back the same distance, and the
+            // successful one moved the cursor to the tip
+            let ranges = ranges.lock().unwrap();
+            assert!(ranges.len() > 5);
+            assert!(ranges
+                .iter()
+                .all(|range| *range == (98 - FINALITY as u32, 100)));
+            assert_eq!(nomad_db.retrieve_update_latest_block_end(), Some(100));
+        })
+        .await
+    }
+
+    fn dispatched(leaf_index: u32) -> RawCommittedMessage {
+        RawCommittedMessage {
+            leaf_index,
+            committed_root: H256::zero(),
+            message: nomad_core::NomadMessage {
+                origin: 1000,
+                sender: H256::repeat_byte(1),
+                nonce: 7,
+                destination: 2000,
+                recipient: H256::repeat_byte(2),
+                body: vec![1, 2, 3],
+            }
+            .to_vec(),
+            timestamp: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn checks_duplicate_leaves_against_the_chain() {
+        test_utils::run_test_db(|db| async move {
+            let nomad_db = NomadDB::new("home_1", db);
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "contract_sync_test",
+                    "home",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .expect("could not make metrics"),
+            );
+            let duplicate_leaves = ContractSyncMetrics::new(metrics).duplicate_leaves;
+
+            let leaf = dispatched(0).leaf();
+            nomad_db
+                .record_leaf_occurrence(
+                    leaf,
+                    LeafOccurrence {
+                        leaf_index: 0,
+                        from_block: 0,
+                        to_block: 10,
+                    },
+                )
+                .expect("!db");
+
+            // The same message dispatched again, with both events on chain
+            let mut mock_indexer = MockIndexer::new();
+            mock_indexer
+                .expect__fetch_sorted_messages()
+                .returning(|from, _| {
+                    Ok(if from == 0 {
+                        vec![dispatched(0)]
+                    } else {
+                        vec![dispatched(3)]
+                    })
+                });
+            check_duplicate_leaves(
+                &nomad_db,
+                &mock_indexer,
+                &[dispatched(3)],
+                (10, 20),
+                &duplicate_leaves,
+                ("home_1", "agent"),
+            )
+            .await
+            .expect("legitimate duplicate");
+            assert_eq!(
+                duplicate_leaves
+                    .with_label_values(&[&CanonicalH256(leaf).to_string(), "home_1", "agent"])
+                    .get(),
+                2
+            );
+
+            // The earlier event is gone from the chain
+            let mut mock_indexer = MockIndexer::new();
+            mock_indexer
+                .expect__fetch_sorted_messages()
+                .returning(|from, _| {
+                    Ok(if from == 0 {
+                        vec![]
+                    } else {
+                        vec![dispatched(3)]
+                    })
+                });
+            let error = check_duplicate_leaves(
+                &nomad_db,
+                &mock_indexer,
+                &[dispatched(3)],
+                (10, 20),
+                &duplicate_leaves,
+                ("home_1", "agent"),
+            )
+            .await
+            .expect_err("mismatched duplicate");
+            match error.downcast_ref::<MessageIntegrityError>() {
+                Some(MessageIntegrityError::DuplicateLeafMismatch {
+                    indexed,
+                    dispatched,
+                    ..
+                }) => {
+                    assert_eq!(indexed, &[0, 3]);
+                    assert_eq!(dispatched, &[3]);
+                }
+                other => panic!("unexpected error {:?}", other),
+            }
+        })
+        .await
+    }
+
+    /// Provider capping log queries at `cap` blocks, rejecting larger ones
+    /// with its vendor's error
+    #[derive(Debug)]
+    struct CappedProvider {
+        cap: u32,
+        error: &'static str,
+        tip: u32,
+        rejections: std::sync::atomic::AtomicU32,
+        largest_served: std::sync::atomic::AtomicU32,
+    }
+
+    impl CappedProvider {
+        fn new(cap: u32, error: &'static str, tip: u32) -> Self {
+            Self {
+                cap,
+                error,
+                tip,
+                rejections: Default::default(),
+                largest_served: Default::default(),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl CommonIndexer for CappedProvider {
+        type Error = crate::ChainCommunicationError;
+
+        async fn get_block_number(&self) -> Result<u32, Self::Error> {
+            Ok(self.tip)
+        }
+
+        async fn fetch_sorted_updates(
+            &self,
+            _from: u32,
+            _to: u32,
+        ) -> Result<Vec<SignedUpdateWithMeta>, Self::Error> {
+            Ok(vec![])
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HomeIndexer for CappedProvider {
+        async fn fetch_sorted_messages(
+            &self,
+            from: u32,
+            to: u32,
+        ) -> Result<Vec<RawCommittedMessage>, Self::Error> {
+            use ethers::providers::{Http, ProviderError};
+            use nomad_ethereum::{rpc_error_classifier, EthereumError, RetryingProviderError};
+            use std::sync::atomic::Ordering;
+
+            tokio::task::yield_now().await;
+            let blocks = to - from + 1;
+            if blocks > self.cap {
+                self.rejections.fetch_add(1, Ordering::SeqCst);
+                let classified = rpc_error_classifier()
+                    .classify_error(&std::io::Error::new(std::io::ErrorKind::Other, self.error));
+                let provider_error: ProviderError =
+                    RetryingProviderError::<Http>::Unretryable(classified).into();
+                return Err(EthereumError::MiddlewareError(provider_error.into()).into());
+            }
+            self.largest_served.fetch_max(blocks, Ordering::SeqCst);
+            Ok(vec![])
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn learns_each_providers_log_range_cap() {
+        use nomad_ethereum::SERVED_TO_GROW_CAP;
+        use std::sync::atomic::Ordering;
+
+        test_utils::run_test_db(|db| async move {
+            let vendors = [
+                (
+                    "capped_alchemy",
+                    2000,
+                    "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range and no limit on the response size, or you can request any block range with a cap of 10K logs in the response.",
+                ),
+                ("capped_bsc", 5000, "exceed maximum block range: 5000"),
+                (
+                    "capped_quicknode",
+                    10000,
+                    "eth_getLogs is limited to a 10,000 range",
+                ),
+            ];
+            let metrics = Arc::new(
+                CoreMetrics::new(
+                    "contract_sync_test",
+                    "home",
+                    None,
+                    Arc::new(prometheus::Registry::new()),
+                )
+                .expect("could not make metrics"),
+            );
+            let sync = |chain: &str, nomad_db: NomadDB, provider: Arc<CappedProvider>| {
+                ContractSync::new(
+                    "agent".to_owned(),
+                    "home_1".to_owned(),
+                    chain.to_owned(),
+                    nomad_db,
+                    provider,
+                    IndexSettings {
+                        data_types: IndexDataTypes::UpdatesAndMessages,
+                        use_timelag: true,
+                        max_block_range: None,
+                        backoff: Default::default(),
+                    },
+                    PageSettings {
+                        from: 0,
+                        page_size: 20000,
+                    },
+                    FINALITY,
+                    ContractSyncMetrics::new(metrics.clone()),
+                )
+            };
+
+            for (chain, cap, error) in vendors {
+                let nomad_db = NomadDB::new(chain, db.clone());
+                let provider = Arc::new(CappedProvider::new(cap, error, 100_000));
+                let sync_task = sync(chain, nomad_db.clone(), provider.clone()).sync_messages();
+                sleep(Duration::from_secs(1)).await;
+                cancel_task!(sync_task);
+
+                // one rejection, then ranges within the cap, rejected again
+                // only after the cap grew back past the provider's
+                let growths = 100_000 / (cap * SERVED_TO_GROW_CAP);
+                let rejections = provider.rejections.load(Ordering::SeqCst);
+                assert!((1..=1 + growths).contains(&rejections), "{}", chain);
+                assert_eq!(provider.largest_served.load(Ordering::SeqCst), cap);
+                assert_eq!(nomad_db.retrieve_message_latest_block_end(), Some(100_000));
+                let learned = LOG_RANGE_CAPS.get(chain).unwrap().blocks;
+                assert!(learned == cap || learned == 2 * cap, "{}", chain);
+                assert_eq!(nomad_db.retrieve_log_range_cap().unwrap(), Some(learned));
+                assert_eq!(metrics.status()["log_range_cap"][chain]["blocks"], learned);
+
+                // a restart resumes within the persisted cap
+                let restarted = format!("{}_restarted", chain);
+                let provider = Arc::new(CappedProvider::new(cap, error, 200_000));
+                let sync_task = sync(&restarted, nomad_db.clone(), provider.clone()).sync_messages();
+                sleep(Duration::from_secs(1)).await;
+                cancel_task!(sync_task);
+
+                let rejections = provider.rejections.load(Ordering::SeqCst);
+                assert!(rejections <= 1 + growths, "{}", chain);
+                assert_eq!(provider.largest_served.load(Ordering::SeqCst), cap);
+                assert_eq!(nomad_db.retrieve_message_latest_block_end(), Some(200_000));
+                assert_eq!(LOG_RANGE_CAPS.get(&restarted).unwrap().rejected_blocks, None);
+            }
+        })
+        .await
+    }
+
+    /// Two messages dispatched in each page of 10 blocks
+    fn page_of_messages(from: u32) -> Vec<RawCommittedMessage> {
+        let first = from / 10 * 2;
+        (first..first + 2)
+            .map(|leaf_index| RawCommittedMessage {
+                leaf_index,
+                committed_root: H256::zero(),
+                message: nomad_core::NomadMessage {
+                    origin: 1000,
+                    sender: H256::repeat_byte(1),
+                    nonce: leaf_index,
+                    destination: 2000,
+                    recipient: H256::repeat_byte(2),
+                    body: vec![],
+                }
+                .to_vec(),
+                timestamp: None,
+            })
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn batched_writes_never_persist_a_cursor_without_its_messages() {
+        test_utils::run_test_db(|db| async move {
+            let mut lost_writes = 0;
+            for max_records in 1..=7 {
+                for pages in 1..=4 {
+                    let tip = pages * 10;
+                    let entity = format!("home_{}_{}", max_records, pages);
+                    let nomad_db = NomadDB::new(
+                        &entity,
+                        db.coalescing(max_records, Duration::from_secs(3600)),
+                    );
+
+                    let mut mock_indexer = MockIndexer::new();
+                    mock_indexer
+                        .expect__get_block_number()
+                        .returning(move || Ok(tip));
+                    mock_indexer
+                        .expect__fetch_sorted_messages()
+                        .returning(|from, _| Ok(page_of_messages(from)));
+
+                    // Index every page, then crash with the writes staged
+                    // since the last flush
+                    let sync_task = bootstrap_sync(nomad_db.clone(), mock_indexer).sync_messages();
+                    sleep(Duration::from_secs(1)).await;
+                    cancel_task!(sync_task);
+                    lost_writes += db.discard_staged_writes();
+
+                    let cursor = nomad_db.retrieve_message_latest_block_end().unwrap_or(0);
+                    for leaf_index in 0..cursor / 10 * 2 {
+                        assert!(
+                            nomad_db
+                                .message_by_leaf_index(leaf_index)
+                                .unwrap()
+                                .is_some(),
+                            "cursor at {} without message {} with batches of {}",
+                            cursor,
+                            leaf_index,
+                            max_records,
+                        );
+                    }
+
+                    // Resuming indexes the lost pages again
+                    let mut mock_indexer = MockIndexer::new();
+                    mock_indexer
+                        .expect__get_block_number()
+                        .returning(move || Ok(tip));
+                    mock_indexer
+                        .expect__fetch_sorted_messages()
+                        .returning(|from, _| Ok(page_of_messages(from)));
+                    let sync_task = bootstrap_sync(nomad_db.clone(), mock_indexer).sync_messages();
+                    sleep(Duration::from_secs(1)).await;
+                    cancel_task!(sync_task);
+                    db.flush_writes().unwrap();
+
+                    assert_eq!(nomad_db.retrieve_message_latest_block_end(), Some(tip));
+                    assert_eq!(
+                        nomad_db.retrieve_latest_leaf_index().unwrap(),
+                        Some(pages * 2 - 1)
+                    );
+                }
+            }
+            assert!(lost_writes > 0);
+        })
+        .await
+    }
 }
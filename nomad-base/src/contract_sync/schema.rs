@@ -1,23 +1,94 @@
 use crate::NomadDB;
 use color_eyre::Result;
 use nomad_core::db::DbError;
+use tracing::{info, warn};
 
-static UPDATES_LAST_BLOCK_END: &str = "updates_last_block";
-static MESSAGES_LAST_BLOCK_END: &str = "messages_last_block";
+pub(crate) const UPDATES_LAST_BLOCK_END: &str = "updates_last_block";
+pub(crate) const MESSAGES_LAST_BLOCK_END: &str = "messages_last_block";
 
+/// Outcome of an attempt to move a persisted sync cursor forward
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CursorUpdate {
+    /// The cursor was stored at the requested height
+    Stored,
+    /// The requested height was below the persisted cursor. The cursor was
+    /// left untouched.
+    RejectedRegression {
+        /// The persisted cursor
+        current: u32,
+    },
+}
+
+/// Cursors may only move forward through the `store_*` setters. Moving a
+/// cursor backwards (reorg lookback, reindexing) must go through an explicit
+/// `rewind_*` op, which requires a reason and an amount.
 pub(crate) trait CommonContractSyncDB {
-    fn store_update_latest_block_end(&self, latest_block: u32) -> Result<(), DbError>;
+    fn store_update_latest_block_end(&self, latest_block: u32) -> Result<CursorUpdate, DbError>;
+    fn rewind_update_latest_block_end(&self, amount: u32, reason: &str) -> Result<u32, DbError>;
     fn retrieve_update_latest_block_end(&self) -> Option<u32>;
 }
 
 pub(crate) trait HomeContractSyncDB {
-    fn store_message_latest_block_end(&self, latest_block: u32) -> Result<(), DbError>;
+    fn store_message_latest_block_end(&self, latest_block: u32) -> Result<CursorUpdate, DbError>;
     fn retrieve_message_latest_block_end(&self) -> Option<u32>;
 }
 
+impl NomadDB {
+    fn store_cursor_monotonic(
+        &self,
+        key: &str,
+        latest_block: u32,
+    ) -> Result<CursorUpdate, DbError> {
+        let current: Option<u32> = self.retrieve_decodable("", key)?;
+
+        if let Some(current) = current {
+            if latest_block < current {
+                warn!(
+                    cursor = key,
+                    current = current,
+                    attempted = latest_block,
+                    "Rejected regression of sync cursor {} from {} to {}",
+                    key,
+                    current,
+                    latest_block,
+                );
+                return Ok(CursorUpdate::RejectedRegression { current });
+            }
+        }
+
+        self.store_encodable("", key, &latest_block)?;
+        Ok(CursorUpdate::Stored)
+    }
+
+    fn rewind_cursor(&self, key: &str, amount: u32, reason: &str) -> Result<u32, DbError> {
+        let current: u32 = self.retrieve_decodable("", key)?.unwrap_or_default();
+        let rewound = current.saturating_sub(amount);
+
+        info!(
+            cursor = key,
+            from = current,
+            to = rewound,
+            amount = amount,
+            reason = reason,
+            "Rewinding sync cursor {} from {} to {}: {}",
+            key,
+            current,
+            rewound,
+            reason,
+        );
+
+        self.store_encodable("", key, &rewound)?;
+        Ok(rewound)
+    }
+}
+
 impl CommonContractSyncDB for NomadDB {
-    fn store_update_latest_block_end(&self, latest_block: u32) -> Result<(), DbError> {
-        self.store_encodable("", UPDATES_LAST_BLOCK_END, &latest_block)
+    fn store_update_latest_block_end(&self, latest_block: u32) -> Result<CursorUpdate, DbError> {
+        self.store_cursor_monotonic(UPDATES_LAST_BLOCK_END, latest_block)
+    }
+
+    fn rewind_update_latest_block_end(&self, amount: u32, reason: &str) -> Result<u32, DbError> {
+        self.rewind_cursor(UPDATES_LAST_BLOCK_END, amount, reason)
     }
 
     fn retrieve_update_latest_block_end(&self) -> Option<u32> {
@@ -27,8 +98,8 @@ impl CommonContractSyncDB for NomadDB {
 }
 
 impl HomeContractSyncDB for NomadDB {
-    fn store_message_latest_block_end(&self, latest_block: u32) -> Result<(), DbError> {
-        self.store_encodable("", MESSAGES_LAST_BLOCK_END, &latest_block)
+    fn store_message_latest_block_end(&self, latest_block: u32) -> Result<CursorUpdate, DbError> {
+        self.store_cursor_monotonic(MESSAGES_LAST_BLOCK_END, latest_block)
     }
 
     fn retrieve_message_latest_block_end(&self) -> Option<u32> {
@@ -0,0 +1,468 @@
+use color_eyre::Result;
+use nomad_core::{Decode, Encode, NomadError};
+use prometheus::IntGaugeVec;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::ErrorKind,
+    sync::{Arc, RwLock},
+};
+use tracing::{error, info, warn};
+
+use crate::{CoreMetrics, NomadDB};
+
+/// Corridor states toggled at runtime, by agent and destination
+const CORRIDOR_STATE: &str = "corridor_state_";
+/// `/status` section listing corridor states
+const STATUS_SECTION: &str = "corridors";
+
+/// Whether an agent relays or processes on a corridor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorridorState {
+    /// Whether the corridor is enabled
+    pub enabled: bool,
+    /// Reason given when the corridor was paused
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl CorridorState {
+    /// An enabled corridor
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            reason: None,
+        }
+    }
+
+    /// A corridor paused for `reason`
+    pub fn paused(reason: impl Into<String>) -> Self {
+        Self {
+            enabled: false,
+            reason: Some(reason.into()),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        if self.enabled {
+            "enabled"
+        } else {
+            "paused"
+        }
+    }
+}
+
+impl Encode for CorridorState {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        writer.write_all(&json)?;
+        Ok(json.len())
+    }
+}
+
+impl Decode for CorridorState {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let mut json = vec![];
+        reader.read_to_end(&mut json)?;
+        serde_json::from_slice(&json).map_err(|e| {
+            NomadError::IoError(std::io::Error::new(ErrorKind::InvalidData, e.to_string()))
+        })
+    }
+}
+
+/// Body of a pause request
+#[derive(Debug, Deserialize)]
+struct PauseRequest {
+    reason: String,
+}
+
+/// Pause switches of an agent's corridors from its home to each replica.
+///
+/// Corridors start paused if listed in the settings, and are toggled at
+/// runtime through the admin endpoint. Once persisted, toggles are stored in
+/// the db and replace the settings' states on restart. Every state is exported as a
+/// constant-value info metric and in `/status`, so alerting can tell a paused
+/// corridor from a stalled one.
+#[derive(Debug)]
+pub struct CorridorSwitches {
+    origin: String,
+    agent: String,
+    states: RwLock<BTreeMap<String, CorridorState>>,
+    db: Option<NomadDB>,
+    info: IntGaugeVec,
+    metrics: Arc<CoreMetrics>,
+}
+
+impl CorridorSwitches {
+    /// Switches for the corridors from `origin` to `destinations`, with the
+    /// corridors in `paused` paused for the given reasons
+    pub fn new(
+        origin: impl Into<String>,
+        agent: impl Into<String>,
+        destinations: impl IntoIterator<Item = String>,
+        paused: &HashMap<String, String>,
+        metrics: Arc<CoreMetrics>,
+    ) -> Result<Self> {
+        let info = metrics.new_int_gauge_vec(
+            "corridor_enabled_info",
+            "Constant 1, labeled with whether the agent relays or processes on a corridor and why it was paused",
+            &["origin", "destination", "state", "reason", "agent"],
+        )?;
+
+        let switches = Self {
+            origin: origin.into(),
+            agent: agent.into(),
+            states: Default::default(),
+            db: None,
+            info,
+            metrics,
+        };
+
+        for destination in destinations {
+            let state = match paused.get(&destination) {
+                Some(reason) => CorridorState::paused(reason),
+                None => CorridorState::enabled(),
+            };
+            switches.set(&destination, state);
+        }
+        for destination in paused.keys() {
+            if switches.state(destination).is_none() {
+                warn!(
+                    destination = destination.as_str(),
+                    "Paused corridor is not a replica of this agent. Ignoring."
+                );
+            }
+        }
+
+        Ok(switches)
+    }
+
+    /// Store toggles in `db`, restoring the states of earlier toggles over
+    /// those from the settings
+    pub fn persisted(mut self, db: NomadDB) -> Result<Self> {
+        let destinations: Vec<String> = self
+            .states
+            .read()
+            .expect("poisoned")
+            .keys()
+            .cloned()
+            .collect();
+        for destination in destinations {
+            let stored: Option<CorridorState> =
+                db.retrieve_decodable(CORRIDOR_STATE, self.key(&destination))?;
+            if let Some(state) = stored {
+                info!(
+                    destination = destination.as_str(),
+                    enabled = state.enabled,
+                    reason = ?state.reason,
+                    "Restoring corridor {} -> {} {}",
+                    self.origin,
+                    destination,
+                    state.label(),
+                );
+                self.set(&destination, state);
+            }
+        }
+        self.db = Some(db);
+        Ok(self)
+    }
+
+    /// State of the corridor to `destination`, if it is one of this agent's
+    pub fn state(&self, destination: &str) -> Option<CorridorState> {
+        self.states
+            .read()
+            .expect("poisoned")
+            .get(destination)
+            .cloned()
+    }
+
+    /// Whether the corridor to `destination` is enabled
+    pub fn is_enabled(&self, destination: &str) -> bool {
+        self.state(destination).map_or(true, |state| state.enabled)
+    }
+
+    /// Pause the corridor to `destination`. Returns false if it is not one of
+    /// this agent's corridors.
+    pub fn pause(&self, destination: &str, reason: impl Into<String>) -> bool {
+        self.toggle(destination, CorridorState::paused(reason))
+    }
+
+    /// Resume the corridor to `destination`. Returns false if it is not one
+    /// of this agent's corridors.
+    pub fn resume(&self, destination: &str) -> bool {
+        self.toggle(destination, CorridorState::enabled())
+    }
+
+    fn toggle(&self, destination: &str, state: CorridorState) -> bool {
+        if self.state(destination).is_none() {
+            return false;
+        }
+        info!(
+            destination = destination,
+            enabled = state.enabled,
+            reason = ?state.reason,
+            "Corridor {} -> {} {}",
+            self.origin,
+            destination,
+            state.label(),
+        );
+        if let Some(db) = &self.db {
+            if let Err(e) = db.store_encodable(CORRIDOR_STATE, self.key(destination), &state) {
+                error!(
+                    destination = destination,
+                    error = %e,
+                    "Could not store corridor state. It will not survive a restart."
+                );
+            }
+        }
+        self.set(destination, state);
+        true
+    }
+
+    /// Db key of the corridor to `destination`
+    fn key(&self, destination: &str) -> String {
+        format!("{}_{}", self.agent, destination)
+    }
+
+    /// Store the state and replace its info metric and `/status` entry
+    fn set(&self, destination: &str, state: CorridorState) {
+        let mut states = self.states.write().expect("poisoned");
+        if let Some(previous) = states.get(destination) {
+            let _ = self
+                .info
+                .remove_label_values(&self.labels(destination, previous));
+        }
+        self.info
+            .with_label_values(&self.labels(destination, &state))
+            .set(1);
+        self.metrics.set_status(
+            STATUS_SECTION,
+            destination,
+            serde_json::to_value(&state).expect("!serialize"),
+        );
+        states.insert(destination.to_owned(), state);
+    }
+
+    fn labels<'a>(&'a self, destination: &'a str, state: &'a CorridorState) -> [&'a str; 5] {
+        [
+            &self.origin,
+            destination,
+            state.label(),
+            state.reason.as_deref().unwrap_or_default(),
+            &self.agent,
+        ]
+    }
+
+    /// Admin routes. `POST /corridors/<destination>/pause` with a JSON body
+    /// `{"reason": "..."}` pauses a corridor and
+    /// `POST /corridors/<destination>/resume` resumes it.
+    pub fn routes(
+        self: Arc<Self>,
+    ) -> impl warp::Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        use warp::{http::StatusCode, Filter};
+
+        let reply = |switches: &Self, destination: &str, toggled: bool| {
+            if toggled {
+                warp::reply::with_status(
+                    warp::reply::json(&switches.state(destination)),
+                    StatusCode::OK,
+                )
+            } else {
+                warp::reply::with_status(
+                    warp::reply::json(&format!("unknown corridor {}", destination)),
+                    StatusCode::NOT_FOUND,
+                )
+            }
+        };
+
+        let pausing = self.clone();
+        let pause = warp::post()
+            .and(warp::path!("corridors" / String / "pause"))
+            .and(warp::body::content_length_limit(4096))
+            .and(warp::body::json())
+            .map(move |destination: String, request: PauseRequest| {
+                let toggled = pausing.pause(&destination, request.reason);
+                reply(&pausing, &destination, toggled)
+            });
+
+        let resume = warp::post()
+            .and(warp::path!("corridors" / String / "resume"))
+            .map(move |destination: String| {
+                let toggled = self.resume(&destination);
+                reply(&self, &destination, toggled)
+            });
+
+        pause.or(resume)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nomad_test::test_utils;
+
+    fn switches(paused: &[(&str, &str)]) -> Arc<CorridorSwitches> {
+        Arc::new(unshared(paused))
+    }
+
+    fn unshared(paused: &[(&str, &str)]) -> CorridorSwitches {
+        let metrics = Arc::new(
+            CoreMetrics::new(
+                "corridors_test",
+                "ethereum",
+                None,
+                Arc::new(prometheus::Registry::new()),
+            )
+            .expect("could not make metrics"),
+        );
+        let paused = paused
+            .iter()
+            .map(|(destination, reason)| (destination.to_string(), reason.to_string()))
+            .collect();
+
+        CorridorSwitches::new(
+            "ethereum",
+            "processor",
+            vec!["moonbeam".to_owned(), "evmos".to_owned()],
+            &paused,
+            metrics,
+        )
+        .unwrap()
+    }
+
+    fn info(switches: &CorridorSwitches, destination: &str, state: &str, reason: &str) -> i64 {
+        switches
+            .info
+            .get_metric_with_label_values(&["ethereum", destination, state, reason, "processor"])
+            .unwrap()
+            .get()
+    }
+
+    fn exported(switches: &CorridorSwitches) -> usize {
+        switches
+            .metrics
+            .gather()
+            .map(|report| {
+                String::from_utf8(report)
+                    .unwrap()
+                    .lines()
+                    .filter(|line| line.starts_with("nomad_corridor_enabled_info{"))
+                    .count()
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn it_exports_states_from_settings() {
+        let switches = switches(&[("evmos", "bridge upgrade"), ("avalanche", "not a replica")]);
+
+        assert!(switches.is_enabled("moonbeam"));
+        assert!(!switches.is_enabled("evmos"));
+        assert_eq!(switches.state("avalanche"), None);
+
+        assert_eq!(info(&switches, "moonbeam", "enabled", ""), 1);
+        assert_eq!(info(&switches, "evmos", "paused", "bridge upgrade"), 1);
+        assert_eq!(exported(&switches), 2);
+
+        let status = switches.metrics.status();
+        assert_eq!(status["corridors"]["moonbeam"]["enabled"], true);
+        assert_eq!(status["corridors"]["evmos"]["reason"], "bridge upgrade");
+    }
+
+    #[test]
+    fn it_updates_metrics_on_toggle() {
+        let switches = switches(&[]);
+
+        assert!(switches.pause("moonbeam", "incident 42"));
+        assert!(!switches.is_enabled("moonbeam"));
+        assert_eq!(info(&switches, "moonbeam", "paused", "incident 42"), 1);
+        // the enabled series is replaced, not left behind
+        assert_eq!(exported(&switches), 2);
+
+        // a new reason replaces the series of the old one
+        assert!(switches.pause("moonbeam", "incident 43"));
+        assert_eq!(info(&switches, "moonbeam", "paused", "incident 43"), 1);
+        assert_eq!(exported(&switches), 2);
+
+        assert!(switches.resume("moonbeam"));
+        assert!(switches.is_enabled("moonbeam"));
+        assert_eq!(info(&switches, "moonbeam", "enabled", ""), 1);
+        assert_eq!(exported(&switches), 2);
+        assert_eq!(
+            switches.metrics.status()["corridors"]["moonbeam"],
+            serde_json::json!({ "enabled": true })
+        );
+
+        assert!(!switches.pause("avalanche", "not a replica"));
+        assert_eq!(exported(&switches), 2);
+    }
+
+    #[tokio::test]
+    async fn it_restores_persisted_toggles() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("ethereum", db);
+            let switches = unshared(&[("evmos", "bridge upgrade")])
+                .persisted(db.clone())
+                .unwrap();
+            assert!(switches.pause("moonbeam", "incident 42"));
+            assert!(switches.resume("evmos"));
+
+            // toggles replace the settings' states after a restart
+            let switches = unshared(&[("evmos", "bridge upgrade")])
+                .persisted(db.clone())
+                .unwrap();
+            assert_eq!(
+                switches.state("moonbeam"),
+                Some(CorridorState::paused("incident 42"))
+            );
+            assert!(switches.is_enabled("evmos"));
+            assert_eq!(info(&switches, "moonbeam", "paused", "incident 42"), 1);
+            assert_eq!(exported(&switches), 2);
+
+            // switches that are not persisted keep the settings' states
+            let switches = unshared(&[("evmos", "bridge upgrade")]);
+            assert!(switches.is_enabled("moonbeam"));
+            assert!(!switches.is_enabled("evmos"));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_toggles_through_the_admin_endpoint() {
+        let switches = switches(&[]);
+        let routes = switches.clone().routes();
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/corridors/evmos/pause")
+            .json(&serde_json::json!({ "reason": "maintenance" }))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(info(&switches, "evmos", "paused", "maintenance"), 1);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/corridors/evmos/resume")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(info(&switches, "evmos", "enabled", ""), 1);
+        assert_eq!(exported(&switches), 2);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/corridors/avalanche/resume")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 404);
+    }
+}
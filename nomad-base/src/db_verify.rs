@@ -0,0 +1,532 @@
+use ethers::core::types::H256;
+use nomad_core::{db::DbError, is_genesis_root, CommittedMessage, NomadMessage};
+use std::convert::TryFrom;
+use tracing::{error, info};
+
+use crate::NomadDB;
+
+/// Next leaf index to verify, if a verification was interrupted
+const VERIFY_CURSOR: &str = "db_verify_cursor_";
+
+/// Leaves verified between writes of the cursor
+const CURSOR_INTERVAL: u32 = 1000;
+
+/// A stored record contradicting the record it was derived from, or the
+/// order in which records are written
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Violation {
+    /// A leaf index up to the latest one has no leaf
+    #[error("No leaf at leaf index {leaf_index}, below the latest leaf index")]
+    MissingLeaf {
+        /// Leaf index
+        leaf_index: u32,
+    },
+    /// A leaf has no message
+    #[error("Leaf {leaf:?} at leaf index {leaf_index} has no message")]
+    MissingMessage {
+        /// Leaf index
+        leaf_index: u32,
+        /// Leaf
+        leaf: H256,
+    },
+    /// A leaf, message or other record does not decode
+    #[error("{record} record of leaf index {leaf_index} does not decode: {error}")]
+    Malformed {
+        /// Leaf index
+        leaf_index: u32,
+        /// Kind of record
+        record: &'static str,
+        /// Decoding error
+        error: String,
+    },
+    /// A message does not hash to the leaf it is stored under
+    #[error("Message stored under leaf {leaf:?} at leaf index {leaf_index} hashes to {hash:?}")]
+    MessageHashMismatch {
+        /// Leaf index
+        leaf_index: u32,
+        /// Leaf the message is stored under
+        leaf: H256,
+        /// Hash of the stored message
+        hash: H256,
+    },
+    /// A message records another leaf index than the one pointing at it
+    #[error("Message at leaf index {leaf_index} records leaf index {recorded}")]
+    MessageIndexMismatch {
+        /// Leaf index
+        leaf_index: u32,
+        /// Leaf index recorded in the message
+        recorded: u32,
+    },
+    /// A message's destination and nonce do not point back at its leaf
+    #[error("Destination and nonce of the message at leaf index {leaf_index} point at {found:?}, not at its leaf")]
+    NonceIndexMismatch {
+        /// Leaf index
+        leaf_index: u32,
+        /// Leaf the destination and nonce point at
+        found: Option<H256>,
+    },
+    /// A proof does not decode
+    #[error("Proof at leaf index {leaf_index} does not decode: {error}")]
+    MalformedProof {
+        /// Leaf index
+        leaf_index: u32,
+        /// Decoding error
+        error: String,
+    },
+    /// A proof is of another leaf or index
+    #[error("Proof at leaf index {leaf_index} is of leaf {proven:?} at index {index}, not of leaf {leaf:?}")]
+    ProofMismatch {
+        /// Leaf index
+        leaf_index: u32,
+        /// Leaf at the leaf index
+        leaf: H256,
+        /// Leaf of the proof
+        proven: H256,
+        /// Index of the proof
+        index: usize,
+    },
+    /// A proof evaluates to a root no stored update commits to
+    #[error("Proof at leaf index {leaf_index} evaluates to root {root:?}, which no stored update commits to")]
+    UnknownProofRoot {
+        /// Leaf index
+        leaf_index: u32,
+        /// Root the proof evaluates to
+        root: H256,
+    },
+    /// A message is recorded processed with a failed handler, but was never
+    /// recorded attempted. Both are written together.
+    #[error("Message at leaf index {leaf_index} is recorded processed with a failed handler but not attempted")]
+    FailedWithoutAttempt {
+        /// Leaf index
+        leaf_index: u32,
+    },
+    /// A root recorded as committed has no stored update leading to it
+    #[error("{record} root {root:?} has no stored update")]
+    RootWithoutUpdate {
+        /// Kind of record
+        record: &'static str,
+        /// Root
+        root: H256,
+    },
+}
+
+impl Violation {
+    /// Leaf index of the proof to quarantine, if quarantining it remediates
+    /// the violation. Quarantined proofs are regenerated by the processor.
+    pub fn quarantinable_proof(&self) -> Option<u32> {
+        match self {
+            Violation::MalformedProof { leaf_index, .. }
+            | Violation::ProofMismatch { leaf_index, .. }
+            | Violation::UnknownProofRoot { leaf_index, .. } => Some(*leaf_index),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of a verification run
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// First leaf index verified
+    pub from: u32,
+    /// Next leaf index to verify. Zero once the whole db was verified.
+    pub next: u32,
+    /// Leaves verified
+    pub leaves: u32,
+    /// Violations found, and whether each was remediated by quarantine
+    pub violations: Vec<(Violation, bool)>,
+}
+
+impl VerifyReport {
+    /// Violations left unremediated
+    pub fn unresolved(&self) -> impl Iterator<Item = &Violation> {
+        self.violations
+            .iter()
+            .filter(|(_, quarantined)| !quarantined)
+            .map(|(violation, _)| violation)
+    }
+
+    /// Whether the run stopped before the latest leaf
+    pub fn is_partial(&self) -> bool {
+        self.next != 0
+    }
+}
+
+/// Split a decoding error off a db result, as a violation of `record`
+fn decoded<T>(
+    result: Result<T, DbError>,
+    leaf_index: u32,
+    record: &'static str,
+) -> Result<Result<T, Violation>, DbError> {
+    match result {
+        Ok(value) => Ok(Ok(value)),
+        Err(DbError::NomadError(error)) => Ok(Err(Violation::Malformed {
+            leaf_index,
+            record,
+            error: error.to_string(),
+        })),
+        Err(error) => Err(error),
+    }
+}
+
+impl NomadDB {
+    /// Retrieve the next leaf index to verify, if a verification stopped
+    /// part way
+    pub fn retrieve_verify_cursor(&self) -> Result<Option<u32>, DbError> {
+        self.retrieve_decodable("", VERIFY_CURSOR)
+    }
+
+    fn store_verify_cursor(&self, next: u32) -> Result<(), DbError> {
+        self.store_encodable("", VERIFY_CURSOR, &next)
+    }
+
+    /// Whether `root` is the new root of a stored update
+    fn is_committed(&self, root: H256) -> Result<bool, DbError> {
+        Ok(self.update_by_new_root(root)?.is_some())
+    }
+
+    /// Verify the records of the leaf at `leaf_index`: its message hashes to
+    /// it and indexes back to it, its proof is of it and evaluates to a
+    /// committed root, and its processing records are consistent.
+    pub fn verify_leaf(&self, leaf_index: u32) -> Result<Vec<Violation>, DbError> {
+        use nomad_core::accumulator::MerkleProof;
+
+        let mut violations = vec![];
+        let leaf = match decoded(self.leaf_by_leaf_index(leaf_index), leaf_index, "Leaf")? {
+            Ok(Some(leaf)) => leaf,
+            Ok(None) => return Ok(vec![Violation::MissingLeaf { leaf_index }]),
+            Err(violation) => return Ok(vec![violation]),
+        };
+
+        match decoded(self.message_by_leaf(leaf), leaf_index, "Message")? {
+            Ok(Some(raw)) => {
+                let hash = raw.leaf();
+                if hash != leaf {
+                    violations.push(Violation::MessageHashMismatch {
+                        leaf_index,
+                        leaf,
+                        hash,
+                    });
+                }
+                if raw.leaf_index != leaf_index {
+                    violations.push(Violation::MessageIndexMismatch {
+                        leaf_index,
+                        recorded: raw.leaf_index,
+                    });
+                }
+                match CommittedMessage::try_from(raw) {
+                    Ok(CommittedMessage {
+                        message:
+                            NomadMessage {
+                                destination, nonce, ..
+                            },
+                        ..
+                    }) => {
+                        let found = decoded(
+                            self.leaf_by_nonce(destination, nonce),
+                            leaf_index,
+                            "Nonce index",
+                        )?;
+                        match found {
+                            Ok(found) if found == Some(leaf) => {}
+                            Ok(found) => {
+                                violations.push(Violation::NonceIndexMismatch { leaf_index, found })
+                            }
+                            Err(violation) => violations.push(violation),
+                        }
+                    }
+                    Err(error) => violations.push(Violation::Malformed {
+                        leaf_index,
+                        record: "Message",
+                        error: error.to_string(),
+                    }),
+                }
+            }
+            Ok(None) => violations.push(Violation::MissingMessage { leaf_index, leaf }),
+            Err(violation) => violations.push(violation),
+        }
+
+        match self.proof_by_leaf_index(leaf_index) {
+            // Proofs are generated once an update commits to the leaf
+            Ok(None) => {}
+            Ok(Some(proof)) => {
+                if proof.leaf != leaf || proof.index != leaf_index as usize {
+                    violations.push(Violation::ProofMismatch {
+                        leaf_index,
+                        leaf,
+                        proven: proof.leaf,
+                        index: proof.index,
+                    });
+                } else {
+                    let root = proof.root();
+                    if !self.is_committed(root)? {
+                        violations.push(Violation::UnknownProofRoot { leaf_index, root });
+                    }
+                }
+            }
+            Err(DbError::NomadError(error)) => violations.push(Violation::MalformedProof {
+                leaf_index,
+                error: error.to_string(),
+            }),
+            Err(error) => return Err(error),
+        }
+
+        if self.processed_failed(leaf)? && !self.leaf_previously_attempted(leaf)? {
+            violations.push(Violation::FailedWithoutAttempt { leaf_index });
+        }
+
+        Ok(violations)
+    }
+
+    /// Verify every stored leaf up to the latest leaf index, and that the
+    /// latest root and the prover's committed root have updates. Resumes
+    /// from the cursor of a verification that stopped part way, unless
+    /// `restart`. At most `max_leaves` leaves are verified, if set, and the
+    /// cursor is kept every thousand leaves, so a long run can be stopped
+    /// and resumed. Violations are logged as they are found. With
+    /// `quarantine`, proofs failing verification are quarantined, to be
+    /// regenerated.
+    pub fn verify(
+        &self,
+        quarantine: bool,
+        restart: bool,
+        max_leaves: Option<u32>,
+    ) -> Result<VerifyReport, DbError> {
+        let from = if restart {
+            0
+        } else {
+            self.retrieve_verify_cursor()?.unwrap_or_default()
+        };
+        let mut report = VerifyReport {
+            from,
+            ..Default::default()
+        };
+
+        let genesis_root = self.genesis_root_advance()?.map(|advance| advance.root);
+        let mut found = vec![];
+        for (record, root) in [
+            ("Latest", self.retrieve_latest_root()?),
+            ("Prover committed", self.retrieve_prover_latest_committed()?),
+        ] {
+            if let Some(root) = root {
+                if !is_genesis_root(root, genesis_root) && !self.is_committed(root)? {
+                    found.push(Violation::RootWithoutUpdate { record, root });
+                }
+            }
+        }
+
+        let end = match self.retrieve_latest_leaf_index()? {
+            Some(latest) => latest as u64 + 1,
+            None => 0,
+        };
+        let mut leaf_index = from as u64;
+        info!(from, end, "Verifying db");
+        while leaf_index < end {
+            if max_leaves.map_or(false, |max| report.leaves >= max) {
+                break;
+            }
+            found.extend(self.verify_leaf(leaf_index as u32)?);
+            for violation in found.drain(..) {
+                let quarantined = match violation.quarantinable_proof() {
+                    Some(leaf_index) if quarantine => self.quarantine_proof(leaf_index)?,
+                    _ => false,
+                };
+                error!(violation = %violation, quarantined, "DB verification violation");
+                report.violations.push((violation, quarantined));
+            }
+
+            leaf_index += 1;
+            report.leaves += 1;
+            if report.leaves % CURSOR_INTERVAL == 0 {
+                self.store_verify_cursor(leaf_index as u32)?;
+                info!(leaf_index, end, "Verified db up to leaf index");
+            }
+        }
+        for violation in found {
+            error!(violation = %violation, "DB verification violation");
+            report.violations.push((violation, false));
+        }
+
+        // A complete run starts the next one over
+        report.next = if leaf_index < end {
+            leaf_index as u32
+        } else {
+            0
+        };
+        self.store_verify_cursor(report.next)?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nomad_db::{MESSAGE, PROOF};
+    use ethers::types::Signature;
+    use nomad_core::{accumulator::NomadTree, Encode, RawCommittedMessage, SignedUpdate, Update};
+    use nomad_test::test_utils::run_test_db;
+
+    /// Raw bytes stored without an encoding
+    struct Raw(Vec<u8>);
+
+    impl Encode for Raw {
+        fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+        where
+            W: std::io::Write,
+        {
+            writer.write_all(&self.0)?;
+            Ok(self.0.len())
+        }
+    }
+
+    fn raw_message(leaf_index: u32, nonce: u32) -> RawCommittedMessage {
+        let message = NomadMessage {
+            origin: 1000,
+            sender: H256::repeat_byte(1),
+            nonce,
+            destination: 2000,
+            recipient: H256::repeat_byte(2),
+            body: vec![leaf_index as u8],
+        };
+        RawCommittedMessage {
+            leaf_index,
+            committed_root: H256::zero(),
+            message: message.to_vec(),
+            timestamp: None,
+        }
+    }
+
+    /// A db with `count` messages, their proofs, and an update committing
+    /// to all of them
+    fn populate(db: &NomadDB, count: u32) -> NomadTree {
+        let messages: Vec<_> = (0..count).map(|i| raw_message(i, i)).collect();
+        let leaves: Vec<_> = messages.iter().map(|m| m.leaf()).collect();
+        let tree = NomadTree::from_leaves(&leaves);
+
+        db.store_messages(&messages).unwrap();
+        for i in 0..count {
+            db.store_proof(i, &tree.prove(i as usize).unwrap()).unwrap();
+        }
+        db.store_latest_update(&SignedUpdate {
+            update: Update {
+                home_domain: 1000,
+                previous_root: H256::zero(),
+                new_root: tree.root(),
+            },
+            signature: Signature {
+                r: Default::default(),
+                s: Default::default(),
+                v: 27,
+            }
+            .try_into()
+            .unwrap(),
+        })
+        .unwrap();
+        db.store_prover_latest_committed(tree.root()).unwrap();
+        tree
+    }
+
+    #[tokio::test]
+    async fn it_verifies_a_consistent_db() {
+        run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            populate(&db, 5);
+
+            let report = db.verify(false, false, None).unwrap();
+            assert_eq!(report.leaves, 5);
+            assert!(report.violations.is_empty());
+            assert!(!report.is_partial());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_reports_and_quarantines_violations() {
+        run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let tree = populate(&db, 5);
+
+            // Proof of another leaf
+            db.store_proof(1, &tree.prove(0).unwrap()).unwrap();
+            // Truncated proof
+            let mut truncated = tree.prove(2).unwrap().to_vec();
+            truncated.truncate(100);
+            db.store_keyed_encodable(PROOF, &2u32, &Raw(truncated))
+                .unwrap();
+            // Proof under a root no update commits to
+            let mut unknown_root = tree.prove(3).unwrap();
+            unknown_root.path[0] = H256::repeat_byte(9);
+            db.store_proof(3, &unknown_root).unwrap();
+            // Message body rewritten under its leaf
+            let leaf = db.leaf_by_leaf_index(4).unwrap().unwrap();
+            db.store_keyed_encodable(MESSAGE, &leaf, &raw_message(4, 40))
+                .unwrap();
+            // Handler failure recorded without the attempt
+            let message = CommittedMessage::try_from(raw_message(0, 0)).unwrap();
+            db.set_processed_failed(&message).unwrap();
+
+            let report = db.verify(true, false, None).unwrap();
+            let violations: Vec<_> = report.violations.iter().map(|(v, q)| (v, *q)).collect();
+            assert_eq!(report.violations.len(), 6, "{:?}", violations);
+            assert!(
+                violations.contains(&(&Violation::FailedWithoutAttempt { leaf_index: 0 }, false))
+            );
+            assert!(violations.iter().any(|(v, q)| *q
+                && matches!(
+                    v,
+                    Violation::ProofMismatch {
+                        leaf_index: 1,
+                        index: 0,
+                        ..
+                    }
+                )));
+            assert!(violations
+                .iter()
+                .any(|(v, q)| *q && matches!(v, Violation::MalformedProof { leaf_index: 2, .. })));
+            assert!(
+                violations
+                    .iter()
+                    .any(|(v, q)| *q
+                        && matches!(v, Violation::UnknownProofRoot { leaf_index: 3, .. }))
+            );
+            assert!(violations
+                .iter()
+                .any(|(v, q)| !*q
+                    && matches!(v, Violation::MessageHashMismatch { leaf_index: 4, .. })));
+            assert!(violations
+                .iter()
+                .any(|(v, q)| !*q
+                    && matches!(v, Violation::NonceIndexMismatch { leaf_index: 4, .. })));
+            assert_eq!(report.unresolved().count(), 2);
+
+            // Quarantined proofs are gone, to be regenerated
+            for leaf_index in 1..=3 {
+                assert!(db.proof_by_leaf_index(leaf_index).unwrap().is_none());
+            }
+            assert!(db.proof_by_leaf_index(4).unwrap().is_some());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_resumes_from_its_cursor() {
+        run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            populate(&db, 5);
+
+            let report = db.verify(false, false, Some(3)).unwrap();
+            assert_eq!((report.from, report.next, report.leaves), (0, 3, 3));
+            assert!(report.is_partial());
+            assert_eq!(db.retrieve_verify_cursor().unwrap(), Some(3));
+
+            let report = db.verify(false, false, None).unwrap();
+            assert_eq!((report.from, report.next, report.leaves), (3, 0, 2));
+
+            // A complete run starts the next one over
+            let report = db.verify(false, false, None).unwrap();
+            assert_eq!((report.from, report.leaves), (0, 5));
+            let report = db.verify(false, true, Some(1)).unwrap();
+            assert_eq!(report.from, 0);
+        })
+        .await
+    }
+}
@@ -1,4 +1,4 @@
-use ethers::prelude::H256;
+use ethers::prelude::{H256, U256};
 use nomad_core::{db::DbError, NomadError, Update};
 use nomad_ethereum::EthereumError;
 use nomad_substrate::SubstrateError;
@@ -16,22 +16,49 @@ pub enum ChainCommunicationError {
     TxNotExecuted(H256),
     /// Ethereum-specific error
     #[error("{0}")]
-    EthereumError(EthereumError),
+    EthereumError(#[source] EthereumError),
     /// Substrate-specific error
     #[error("{0}")]
     SubstrateError(SubstrateError),
     /// Mock error
     #[error("{0}")]
-    MockError(#[from] nomad_test::MockError),
+    MockError(nomad_test::MockError),
+    /// A dispatch transaction emitted no `Dispatch` event, so the message
+    /// could not be located in the tree
+    #[error("No Dispatch event emitted by dispatch transaction {0:?}")]
+    NoDispatchEvent(H256),
+    /// Home is in failed state and will not accept dispatches
+    #[error("Home {0} is in failed state")]
+    HomeFailed(String),
+    /// A transaction's gas limit from its estimate exceeded the configured
+    /// cap, so it was not submitted
+    #[error("Gas limit {limit} from estimate {estimate} exceeds the cap of {cap}")]
+    GasLimitExceeded {
+        /// Estimated gas
+        estimate: U256,
+        /// Gas limit from the estimate
+        limit: U256,
+        /// Configured cap
+        cap: u64,
+    },
 }
 
 // Catch ethereum-specific reverts
 impl From<EthereumError> for ChainCommunicationError {
     fn from(e: EthereumError) -> Self {
-        if let EthereumError::TxNotExecuted(txid) = e {
-            ChainCommunicationError::TxNotExecuted(txid)
-        } else {
-            ChainCommunicationError::EthereumError(e)
+        match e {
+            EthereumError::TxNotExecuted(txid) => ChainCommunicationError::TxNotExecuted(txid),
+            EthereumError::NoDispatchEvent(txid) => ChainCommunicationError::NoDispatchEvent(txid),
+            EthereumError::GasLimitExceeded {
+                estimate,
+                limit,
+                cap,
+            } => ChainCommunicationError::GasLimitExceeded {
+                estimate,
+                limit,
+                cap,
+            },
+            e => ChainCommunicationError::EthereumError(e),
         }
     }
 }
@@ -39,10 +66,29 @@ impl From<EthereumError> for ChainCommunicationError {
 // Catch substrate-specific reverts
 impl From<SubstrateError> for ChainCommunicationError {
     fn from(e: SubstrateError) -> Self {
-        if let SubstrateError::TxNotExecuted(txid) = e {
-            ChainCommunicationError::TxNotExecuted(txid)
-        } else {
-            ChainCommunicationError::SubstrateError(e)
+        match e {
+            SubstrateError::TxNotExecuted(txid) => ChainCommunicationError::TxNotExecuted(txid),
+            SubstrateError::NoDispatchEvent(txid) => ChainCommunicationError::NoDispatchEvent(txid),
+            e => ChainCommunicationError::SubstrateError(e),
+        }
+    }
+}
+
+// Catch mocked reverts
+impl From<nomad_test::MockError> for ChainCommunicationError {
+    fn from(e: nomad_test::MockError) -> Self {
+        match e {
+            nomad_test::MockError::TxNotExecuted(txid) => {
+                ChainCommunicationError::TxNotExecuted(txid)
+            }
+            nomad_test::MockError::GasLimitExceeded { limit, cap } => {
+                ChainCommunicationError::GasLimitExceeded {
+                    estimate: limit.into(),
+                    limit: limit.into(),
+                    cap,
+                }
+            }
+            e => ChainCommunicationError::MockError(e),
         }
     }
 }
@@ -114,3 +160,33 @@ pub enum ProcessorError {
         tx: H256,
     },
 }
+
+/// Error in the messages indexed from a home
+#[derive(Debug, thiserror::Error)]
+pub enum MessageIntegrityError {
+    /// A leaf index was indexed again with another leaf
+    #[error(
+        "Leaf index {leaf_index} holds leaf {stored:?} but was indexed again with leaf {indexed:?}"
+    )]
+    LeafRewritten {
+        /// Leaf index
+        leaf_index: u32,
+        /// Leaf stored at the index
+        stored: H256,
+        /// Leaf indexed again at the index
+        indexed: H256,
+    },
+    /// A leaf indexed at several leaf indexes does not match the `Dispatch`
+    /// events of the chain
+    #[error(
+        "Leaf {leaf:?} is indexed at leaf indexes {indexed:?} but dispatched at {dispatched:?}"
+    )]
+    DuplicateLeafMismatch {
+        /// Duplicated leaf
+        leaf: H256,
+        /// Leaf indexes the leaf is indexed at
+        indexed: Vec<u32>,
+        /// Leaf indexes of the leaf's `Dispatch` events, re-queried
+        dispatched: Vec<u32>,
+    },
+}
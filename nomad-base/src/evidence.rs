@@ -0,0 +1,510 @@
+use ethers::core::types::{Address, H256};
+use nomad_core::{
+    db::DbError, evm_address_serde, utils::home_domain_hash, CanonicalSignature, Decode, Encode,
+    FailureNotification, NomadError, SignedFailureNotification, SignedUpdate,
+};
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+
+use crate::{AuditEvent, AuditId, NomadDB};
+
+/// Key space of fraud incidents in the watcher's `NomadDB`
+const INCIDENT: &str = "incident_";
+
+/// Version of the evidence bundle format
+const BUNDLE_VERSION: u32 = 1;
+
+/// Errors building or checking evidence
+#[derive(Debug, thiserror::Error)]
+pub enum EvidenceError {
+    /// Bubbled up from the db
+    #[error("{0}")]
+    DbError(#[from] DbError),
+    /// A signature could not be recovered
+    #[error("{0}")]
+    NomadError(#[from] NomadError),
+    /// The evidence does not show what it claims
+    #[error("Evidence check failed: {0}")]
+    CheckFailed(String),
+}
+
+/// Fraud a watcher responded to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Fraud {
+    /// Two updates signed by the updater building on the same root
+    #[serde(rename_all = "camelCase")]
+    DoubleUpdate {
+        /// The conflicting signed updates
+        updates: [SignedUpdate; 2],
+    },
+    /// The home was failed by an improper update
+    ImproperUpdate,
+}
+
+/// A contract read or sent a transaction to in response to fraud
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvidenceContract {
+    /// Name, as used for audit targets
+    pub name: String,
+    /// Domain of the chain the contract is on
+    pub domain: u32,
+    /// Address, if configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<H256>,
+}
+
+/// A view function read on a contract in response to fraud
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateRead {
+    /// Contract name
+    pub contract: String,
+    /// Function signature, such as `state()`
+    pub function: String,
+    /// Returned ABI word
+    pub value: H256,
+    /// Block number of the chain just before the read, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+}
+
+/// A watcher's response to fraud, with the context to rebuild its evidence
+/// bundle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Incident {
+    /// Id of the response's audit record. Its transactions' records refer
+    /// to it.
+    pub id: AuditId,
+    /// Domain of the home
+    pub home_domain: u32,
+    /// Fraud responded to
+    pub fraud: Fraud,
+    /// Updater named in the failure notification
+    pub updater: H256,
+    /// Watcher's signature of the failure notification
+    pub failure_signature: CanonicalSignature,
+    /// Contracts read or sent transactions to
+    pub contracts: Vec<EvidenceContract>,
+    /// Reads taken after the response
+    pub state_reads: Vec<StateRead>,
+}
+
+impl Incident {
+    /// The failure notification submitted with `unenrollReplica`
+    pub fn failure_notification(&self) -> SignedFailureNotification {
+        SignedFailureNotification {
+            notification: FailureNotification {
+                home_domain: self.home_domain,
+                updater: self.updater.into(),
+            },
+            signature: self.failure_signature,
+        }
+    }
+}
+
+impl Encode for Incident {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        writer.write_all(&json)?;
+        Ok(json.len())
+    }
+}
+
+impl Decode for Incident {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let mut json = vec![];
+        reader.read_to_end(&mut json)?;
+        serde_json::from_slice(&json).map_err(|e| {
+            NomadError::IoError(std::io::Error::new(ErrorKind::InvalidData, e.to_string()))
+        })
+    }
+}
+
+impl NomadDB {
+    /// Store a fraud incident under its id
+    ///
+    /// Keys --> Values:
+    /// - `id` --> `incident`
+    pub fn store_incident(&self, incident: &Incident) -> Result<(), DbError> {
+        self.store_keyed_encodable(INCIDENT, &incident.id.0, incident)
+    }
+
+    /// Retrieve a fraud incident by id
+    pub fn incident(&self, id: AuditId) -> Result<Option<Incident>, DbError> {
+        self.retrieve_keyed_decodable(INCIDENT, &id.0)
+    }
+
+    /// All fraud incidents, oldest first
+    pub fn incidents(&self) -> impl Iterator<Item = Result<Incident, DbError>> + '_ {
+        self.iterate_from(INCIDENT, 0u64.to_vec())
+    }
+}
+
+/// The watcher's failure notification and the watcher it recovers to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureNotificationEvidence {
+    /// Updater of the failed home
+    pub updater: H256,
+    /// `keccak256(homeDomainHash, homeDomain, updater)`
+    pub digest: H256,
+    /// Watcher's signature of the digest, with the Ethereum signed message
+    /// prefix
+    pub signature: CanonicalSignature,
+    /// Watcher recovered from the signature
+    #[serde(with = "evm_address_serde")]
+    pub watcher: Address,
+}
+
+/// A transaction sent in response to fraud
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvidenceTransaction {
+    /// Contract name
+    pub contract: String,
+    /// Transaction hash
+    pub txid: H256,
+}
+
+/// A transaction that failed to send in response to fraud
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedSubmission {
+    /// Contract name
+    pub contract: String,
+    /// Error returned by the submission
+    pub error: String,
+}
+
+/// Self-contained evidence of a fraud incident and the watcher's response,
+/// for public disclosure. Signatures check offline. State reads and
+/// transactions check against public RPCs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvidenceBundle {
+    /// Bundle format version
+    pub version: u32,
+    /// Incident id
+    pub incident: AuditId,
+    /// Domain of the home
+    pub home_domain: u32,
+    /// `keccak256(homeDomain, "NOMAD")`
+    pub home_domain_hash: H256,
+    /// Fraud responded to
+    pub fraud: Fraud,
+    /// Updater recovered from the fraudulent updates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<Address>,
+    /// Failure notification submitted with `unenrollReplica`
+    pub failure_notification: FailureNotificationEvidence,
+    /// Contracts read or sent transactions to
+    pub contracts: Vec<EvidenceContract>,
+    /// Reads taken after the response
+    pub state_reads: Vec<StateRead>,
+    /// Transactions sent in response
+    pub transactions: Vec<EvidenceTransaction>,
+    /// Transactions that failed to send
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed_submissions: Vec<FailedSubmission>,
+    /// How to verify the bundle
+    pub verification: Vec<String>,
+}
+
+impl EvidenceBundle {
+    /// Build the bundle of the incident `id` from the watcher's db. `None`
+    /// if there is no such incident.
+    pub fn export(db: &NomadDB, id: AuditId) -> Result<Option<Self>, EvidenceError> {
+        let incident = match db.incident(id)? {
+            Some(incident) => incident,
+            None => return Ok(None),
+        };
+
+        let mut transactions = vec![];
+        let mut failed_submissions = vec![];
+        for record in db.audit_records(id) {
+            match record?.event {
+                AuditEvent::Submitted {
+                    decision,
+                    target,
+                    txid,
+                } if decision == id => transactions.push(EvidenceTransaction {
+                    contract: target,
+                    txid,
+                }),
+                AuditEvent::SubmissionFailed {
+                    decision,
+                    target,
+                    error,
+                } if decision == id => failed_submissions.push(FailedSubmission {
+                    contract: target,
+                    error,
+                }),
+                _ => {}
+            }
+        }
+
+        let signer = match &incident.fraud {
+            Fraud::DoubleUpdate { updates } => Some(updates[0].recover()?),
+            Fraud::ImproperUpdate => None,
+        };
+        let notification = incident.failure_notification();
+
+        let bundle = Self {
+            version: BUNDLE_VERSION,
+            incident: id,
+            home_domain: incident.home_domain,
+            home_domain_hash: home_domain_hash(incident.home_domain),
+            verification: verification_steps(&incident.fraud),
+            fraud: incident.fraud,
+            signer,
+            failure_notification: FailureNotificationEvidence {
+                updater: incident.updater,
+                digest: notification.notification.signing_hash(),
+                signature: incident.failure_signature,
+                watcher: notification.recover()?,
+            },
+            contracts: incident.contracts,
+            state_reads: incident.state_reads,
+            transactions,
+            failed_submissions,
+        };
+
+        // Never hand out a bundle that does not check
+        bundle.verify_signatures()?;
+        Ok(Some(bundle))
+    }
+
+    /// Contract by name
+    pub fn contract(&self, name: &str) -> Option<&EvidenceContract> {
+        self.contracts.iter().find(|contract| contract.name == name)
+    }
+
+    /// Check everything that needs no chain access: the home domain hash,
+    /// that the fraudulent updates conflict and recover to the signer, and
+    /// that the failure notification names the signer and recovers to the
+    /// watcher
+    pub fn verify_signatures(&self) -> Result<(), EvidenceError> {
+        let check = |ok: bool, reason: &str| {
+            if ok {
+                Ok(())
+            } else {
+                Err(EvidenceError::CheckFailed(reason.to_owned()))
+            }
+        };
+
+        check(
+            self.home_domain_hash == home_domain_hash(self.home_domain),
+            "homeDomainHash is not the hash of homeDomain",
+        )?;
+
+        if let Fraud::DoubleUpdate {
+            updates: [first, second],
+        } = &self.fraud
+        {
+            check(
+                first.update.home_domain == self.home_domain
+                    && second.update.home_domain == self.home_domain,
+                "updates are not for the home domain",
+            )?;
+            check(
+                first.update.previous_root == second.update.previous_root,
+                "updates do not build on the same root",
+            )?;
+            check(
+                first.update.new_root != second.update.new_root,
+                "updates commit to the same new root",
+            )?;
+            for update in [first, second] {
+                check(
+                    self.signer == Some(update.recover()?),
+                    "an update does not recover to the signer",
+                )?;
+            }
+        }
+
+        let evidence = &self.failure_notification;
+        let signed = SignedFailureNotification {
+            notification: FailureNotification {
+                home_domain: self.home_domain,
+                updater: evidence.updater.into(),
+            },
+            signature: evidence.signature,
+        };
+        check(
+            signed.notification.signing_hash() == evidence.digest,
+            "failure notification digest does not match its fields",
+        )?;
+        check(
+            signed.recover()? == evidence.watcher,
+            "failure notification does not recover to the watcher",
+        )?;
+        if let Some(signer) = self.signer {
+            check(
+                evidence.updater == H256::from(signer),
+                "failure notification names another updater than the signer",
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Steps a reader follows to verify a bundle
+fn verification_steps(fraud: &Fraud) -> Vec<String> {
+    let mut steps =
+        vec!["homeDomainHash equals keccak256(homeDomain as uint32, \"NOMAD\")".to_owned()];
+    if let Fraud::DoubleUpdate { .. } = fraud {
+        steps.extend([
+            "Both updates are for homeDomain, build on the same previousRoot and commit to different newRoots".to_owned(),
+            "Each update's signature recovers to signer from keccak256(homeDomainHash, previousRoot, newRoot) with the Ethereum signed message prefix".to_owned(),
+            "failureNotification.updater is signer, left-padded to 32 bytes".to_owned(),
+        ]);
+    }
+    steps.extend([
+        "failureNotification.digest equals keccak256(homeDomainHash, homeDomain as uint32, updater)".to_owned(),
+        "failureNotification.signature recovers to failureNotification.watcher from digest with the Ethereum signed message prefix".to_owned(),
+        "Each state read's function, called on its contract at blockNumber, returns value".to_owned(),
+        "Each transaction succeeded and was sent to its contract's address".to_owned(),
+        "`nomad-cli verify-evidence <bundle> --rpc <domain>=<url>` runs these checks".to_owned(),
+    ]);
+    steps
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::signers::{LocalWallet, Signer};
+    use nomad_core::Update;
+    use nomad_test::test_utils::run_test_db;
+
+    /// A double update drill: an updater key signs two updates on the same
+    /// root and a watcher key responds
+    async fn drill(db: &NomadDB) -> AuditId {
+        let updater: LocalWallet =
+            "1111111111111111111111111111111111111111111111111111111111111111"
+                .parse()
+                .unwrap();
+        let watcher: LocalWallet =
+            "2222222222222222222222222222222222222222222222222222222222222222"
+                .parse()
+                .unwrap();
+
+        let update = |new_root: H256| Update {
+            home_domain: 1000,
+            previous_root: H256::repeat_byte(1),
+            new_root,
+        };
+        let updates = [
+            update(H256::repeat_byte(2))
+                .sign_with(&updater)
+                .await
+                .unwrap(),
+            update(H256::repeat_byte(3))
+                .sign_with(&updater)
+                .await
+                .unwrap(),
+        ];
+        let failure = FailureNotification {
+            home_domain: 1000,
+            updater: updater.address().into(),
+        }
+        .sign_with(&watcher)
+        .await
+        .unwrap();
+
+        let id = db
+            .audit(AuditEvent::DoubleUpdateResponse {
+                home_domain: 1000,
+                updater: updater.address().into(),
+                previous_root: H256::repeat_byte(1),
+                new_roots: [H256::repeat_byte(2), H256::repeat_byte(3)],
+            })
+            .unwrap();
+        let sent: Result<H256, String> = Ok(H256::repeat_byte(0xaa));
+        db.audit_submission(id, "home_1", &sent, None, |txid| *txid)
+            .unwrap();
+        let failed: Result<H256, String> = Err("nonce too low".to_owned());
+        db.audit_submission(id, "connection_manager_2000", &failed, None, |txid| *txid)
+            .unwrap();
+
+        db.store_incident(&Incident {
+            id,
+            home_domain: 1000,
+            fraud: Fraud::DoubleUpdate { updates },
+            updater: updater.address().into(),
+            failure_signature: failure.signature,
+            contracts: vec![EvidenceContract {
+                name: "home_1".to_owned(),
+                domain: 1000,
+                address: Some(H256::repeat_byte(0x10)),
+            }],
+            state_reads: vec![StateRead {
+                contract: "home_1".to_owned(),
+                function: "state()".to_owned(),
+                value: H256::from_low_u64_be(2),
+                block_number: Some(100),
+            }],
+        })
+        .unwrap();
+        id
+    }
+
+    #[tokio::test]
+    async fn it_exports_and_verifies_a_drill_bundle() {
+        run_test_db(|db| async move {
+            let db = NomadDB::new("home_1_watcher", db);
+            assert!(EvidenceBundle::export(&db, AuditId(1)).unwrap().is_none());
+
+            let id = drill(&db).await;
+            assert_eq!(db.incidents().count(), 1);
+
+            let bundle = EvidenceBundle::export(&db, id).unwrap().unwrap();
+            assert_eq!(bundle.incident, id);
+            assert_eq!(
+                bundle.signer.map(H256::from),
+                Some(bundle.failure_notification.updater)
+            );
+            assert_eq!(
+                bundle.transactions,
+                vec![EvidenceTransaction {
+                    contract: "home_1".to_owned(),
+                    txid: H256::repeat_byte(0xaa),
+                }]
+            );
+            assert_eq!(bundle.failed_submissions.len(), 1);
+            assert_eq!(bundle.contract("home_1").unwrap().domain, 1000);
+
+            // The published JSON verifies on its own
+            let json = serde_json::to_string(&bundle).unwrap();
+            let published: EvidenceBundle = serde_json::from_str(&json).unwrap();
+            assert_eq!(published, bundle);
+            published.verify_signatures().unwrap();
+
+            // Tampering is caught
+            let mut tampered = published.clone();
+            tampered.home_domain = 2000;
+            assert!(tampered.verify_signatures().is_err());
+
+            let mut tampered = published.clone();
+            if let Fraud::DoubleUpdate { updates } = &mut tampered.fraud {
+                updates[1].update.new_root = H256::repeat_byte(4);
+            }
+            assert!(tampered.verify_signatures().is_err());
+
+            let mut tampered = published;
+            tampered.failure_notification.watcher = Address::repeat_byte(9);
+            assert!(tampered.verify_signatures().is_err());
+        })
+        .await
+    }
+}
@@ -0,0 +1,314 @@
+use color_eyre::Report;
+use nomad_core::db::DbError;
+use nomad_ethereum::EthereumError;
+
+use crate::{
+    BaseError, ChainCommunicationError, MessageIntegrityError, ProcessorError, UpdaterError,
+};
+
+/// An error that stops an agent, by the category deciding its exit code.
+/// Orchestrators restart on any nonzero exit and alert by category.
+#[derive(Debug, thiserror::Error)]
+pub enum FatalError {
+    /// Settings or startup configuration are invalid. Exits with 10
+    #[error("{component}: {error:#}")]
+    Config {
+        /// Component that failed
+        component: String,
+        /// Triggering error
+        error: Report,
+    },
+    /// Chain calls failed past their retries. Exits with 20
+    #[error("{component}: {error:#}")]
+    ChainConnectivity {
+        /// Component that failed
+        component: String,
+        /// Triggering error
+        error: Report,
+    },
+    /// A safety check halted the agent, such as a failed home, a double
+    /// update, a conflicting update or leaf or a rewritten leaf. Exits with 30
+    #[error("{component}: {error:#}")]
+    SafetyHalt {
+        /// Component that failed
+        component: String,
+        /// Triggering error
+        error: Report,
+    },
+    /// The db is unreadable or failed verification. Exits with 40
+    #[error("{component}: {error:#}")]
+    DbCorruption {
+        /// Component that failed
+        component: String,
+        /// Triggering error
+        error: Report,
+    },
+    /// Anything else. Exits with 1
+    #[error("{component}: {error:#}")]
+    Unclassified {
+        /// Component that failed
+        component: String,
+        /// Triggering error
+        error: Report,
+    },
+}
+
+impl FatalError {
+    /// Invalid configuration in `component`
+    pub fn config(component: impl Into<String>, error: impl Into<Report>) -> Self {
+        Self::Config {
+            component: component.into(),
+            error: error.into(),
+        }
+    }
+
+    /// Chain connectivity of `component` exhausted
+    pub fn chain_connectivity(component: impl Into<String>, error: impl Into<Report>) -> Self {
+        Self::ChainConnectivity {
+            component: component.into(),
+            error: error.into(),
+        }
+    }
+
+    /// Safety halt in `component`
+    pub fn safety_halt(component: impl Into<String>, error: impl Into<Report>) -> Self {
+        Self::SafetyHalt {
+            component: component.into(),
+            error: error.into(),
+        }
+    }
+
+    /// Corrupt db found by `component`
+    pub fn db_corruption(component: impl Into<String>, error: impl Into<Report>) -> Self {
+        Self::DbCorruption {
+            component: component.into(),
+            error: error.into(),
+        }
+    }
+
+    /// Categorize an error reaching `component`. A `FatalError` in the
+    /// report is kept as is. Otherwise the first known error in the chain
+    /// decides the category.
+    pub fn classify(error: Report, component: impl Into<String>) -> Self {
+        let error = match error.downcast::<FatalError>() {
+            Ok(fatal) => return fatal,
+            Err(error) => error,
+        };
+        let component = component.into();
+
+        for cause in error.chain() {
+            if let Some(base) = cause.downcast_ref::<BaseError>() {
+                match base {
+                    BaseError::FailedHome => return Self::SafetyHalt { component, error },
+                    BaseError::DbError(_) => return Self::DbCorruption { component, error },
+                    BaseError::ChainCommunicationError(ChainCommunicationError::HomeFailed(_)) => {
+                        return Self::SafetyHalt { component, error }
+                    }
+                    BaseError::ChainCommunicationError(_) => {
+                        return Self::ChainConnectivity { component, error }
+                    }
+                    _ => {}
+                }
+            }
+            if cause.is::<UpdaterError>()
+                || cause.is::<ProcessorError>()
+                || cause.is::<MessageIntegrityError>()
+                || matches!(
+                    cause.downcast_ref::<ChainCommunicationError>(),
+                    Some(ChainCommunicationError::HomeFailed(_))
+                )
+            {
+                return Self::SafetyHalt { component, error };
+            }
+            if cause.is::<DbError>() {
+                return Self::DbCorruption { component, error };
+            }
+            if cause.is::<ChainCommunicationError>()
+                || cause.is::<EthereumError>()
+                || cause.is::<ethers::providers::ProviderError>()
+            {
+                return Self::ChainConnectivity { component, error };
+            }
+        }
+        Self::Unclassified { component, error }
+    }
+
+    /// Categorize an error at startup, where errors of no known category
+    /// come from configuration
+    pub fn classify_startup(error: Report, component: impl Into<String>) -> Self {
+        match Self::classify(error, component) {
+            Self::Unclassified { component, error } => Self::Config { component, error },
+            fatal => fatal,
+        }
+    }
+
+    /// Category name, as printed in the exit summary
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::Config { .. } => "config",
+            Self::ChainConnectivity { .. } => "chain_connectivity",
+            Self::SafetyHalt { .. } => "safety_halt",
+            Self::DbCorruption { .. } => "db_corruption",
+            Self::Unclassified { .. } => "unclassified",
+        }
+    }
+
+    /// Process exit code
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Config { .. } => 10,
+            Self::ChainConnectivity { .. } => 20,
+            Self::SafetyHalt { .. } => 30,
+            Self::DbCorruption { .. } => 40,
+            Self::Unclassified { .. } => 1,
+        }
+    }
+
+    /// Component that failed
+    pub fn component(&self) -> &str {
+        match self {
+            Self::Config { component, .. }
+            | Self::ChainConnectivity { component, .. }
+            | Self::SafetyHalt { component, .. }
+            | Self::DbCorruption { component, .. }
+            | Self::Unclassified { component, .. } => component,
+        }
+    }
+
+    /// Triggering error
+    pub fn error(&self) -> &Report {
+        match self {
+            Self::Config { error, .. }
+            | Self::ChainConnectivity { error, .. }
+            | Self::SafetyHalt { error, .. }
+            | Self::DbCorruption { error, .. }
+            | Self::Unclassified { error, .. } => error,
+        }
+    }
+}
+
+/// One-line JSON summary of an agent's exit
+fn exit_summary(agent: &str, fatal: Option<&FatalError>) -> String {
+    let summary = match fatal {
+        Some(fatal) => serde_json::json!({
+            "exit": fatal.category(),
+            "code": fatal.exit_code(),
+            "agent": agent,
+            "component": fatal.component(),
+            "error": format!("{:#}", fatal.error()),
+        }),
+        None => serde_json::json!({
+            "exit": "clean",
+            "code": 0,
+            "agent": agent,
+        }),
+    };
+    summary.to_string()
+}
+
+/// Print the exit summary of `agent` and exit with the code of its fatal
+/// error's category, or 0 if it stopped cleanly
+pub fn exit_with_summary(agent: &str, result: color_eyre::Result<()>) -> ! {
+    let fatal = result.err().map(|error| FatalError::classify(error, agent));
+    if let Some(fatal) = &fatal {
+        eprintln!("Error: {:?}", fatal.error());
+    }
+    eprintln!("{}", exit_summary(agent, fatal.as_ref()));
+    std::process::exit(fatal.map_or(0, |fatal| fatal.exit_code()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use color_eyre::eyre::{eyre, WrapErr};
+    use ethers::{prelude::H256, providers::ProviderError};
+    use nomad_core::NomadError;
+
+    fn classify<E>(error: E) -> FatalError
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let report = Err::<(), _>(error)
+            .wrap_err("Task for replica named moonbeam failed")
+            .unwrap_err();
+        FatalError::classify(report, "processor")
+    }
+
+    #[test]
+    fn it_classifies_induced_failures() {
+        let failed_home = classify(BaseError::FailedHome);
+        assert_eq!(failed_home.category(), "safety_halt");
+        assert_eq!(failed_home.exit_code(), 30);
+        assert_eq!(failed_home.component(), "processor");
+
+        let conflict = classify(ProcessorError::ProverConflictError {
+            index: 1,
+            calculated_leaf: Default::default(),
+            proof_leaf: Default::default(),
+        });
+        assert_eq!(conflict.exit_code(), 30);
+        let rewritten = classify(MessageIntegrityError::LeafRewritten {
+            leaf_index: 1,
+            stored: Default::default(),
+            indexed: H256::repeat_byte(1),
+        });
+        assert_eq!(rewritten.exit_code(), 30);
+
+        let db = classify(DbError::NomadError(NomadError::InvalidSignatureV(5)));
+        assert_eq!(db.category(), "db_corruption");
+        assert_eq!(db.exit_code(), 40);
+        let db = classify(BaseError::DbError(DbError::NomadError(
+            NomadError::InvalidSignatureV(5),
+        )));
+        assert_eq!(db.exit_code(), 40);
+
+        let chain = classify(ChainCommunicationError::from(EthereumError::ProviderError(
+            ProviderError::CustomError("retries exhausted".to_owned()),
+        )));
+        assert_eq!(chain.category(), "chain_connectivity");
+        assert_eq!(chain.exit_code(), 20);
+
+        let other = FatalError::classify(eyre!("something else"), "kathy");
+        assert_eq!(other.category(), "unclassified");
+        assert_eq!(other.exit_code(), 1);
+        let startup = FatalError::classify_startup(eyre!("missing rpc url"), "settings");
+        assert_eq!(startup.category(), "config");
+        assert_eq!(startup.exit_code(), 10);
+    }
+
+    #[test]
+    fn it_keeps_explicit_categories() {
+        let report: Report = FatalError::safety_halt("watcher", eyre!("double update")).into();
+        let fatal = FatalError::classify(report, "watcher run_all");
+        assert_eq!(fatal.category(), "safety_halt");
+        assert_eq!(fatal.component(), "watcher");
+
+        // Also when wrapped in context
+        let wrapped = Err::<(), _>(FatalError::db_corruption(
+            "db_verify",
+            eyre!("2 violations"),
+        ))
+        .wrap_err("startup failed")
+        .unwrap_err();
+        assert_eq!(FatalError::classify(wrapped, "updater").exit_code(), 40);
+    }
+
+    #[test]
+    fn it_summarizes_exits_on_one_line() {
+        let fatal = FatalError::chain_connectivity("home sync", eyre!("retries exhausted"));
+        let summary = exit_summary("relayer", Some(&fatal));
+        assert!(!summary.contains('\n'));
+
+        let parsed: serde_json::Value = serde_json::from_str(&summary).unwrap();
+        assert_eq!(parsed["exit"], "chain_connectivity");
+        assert_eq!(parsed["code"], 20);
+        assert_eq!(parsed["agent"], "relayer");
+        assert_eq!(parsed["component"], "home sync");
+        assert_eq!(parsed["error"], "retries exhausted");
+
+        let parsed: serde_json::Value =
+            serde_json::from_str(&exit_summary("relayer", None)).unwrap();
+        assert_eq!(parsed["exit"], "clean");
+        assert_eq!(parsed["code"], 0);
+    }
+}
@@ -1,16 +1,16 @@
-use crate::{ChainCommunicationError, ContractSync, HomeIndexers, NomadDB};
+use crate::{ChainCommunicationError, ContractSync, HomeIndexers, NomadDB, TreeSnapshot};
 use async_trait::async_trait;
 use color_eyre::eyre::Result;
 use ethers::core::types::{H256, U256};
 use nomad_core::{
-    db::DbError, Common, CommonEvents, DoubleUpdate, Home, HomeEvents, Message,
+    db::DbError, Common, CommonEvents, DispatchOutcome, DoubleUpdate, Home, HomeEvents, Message,
     RawCommittedMessage, SignedUpdate, State, TxOutcome, Update,
 };
 use nomad_ethereum::EthereumHome;
 use nomad_test::mocks::MockHomeContract;
-use std::sync::Arc;
-use tokio::task::JoinHandle;
+use std::sync::{Arc, RwLock};
 use tokio::time::{sleep, Duration};
+use tokio::{sync::Notify, task::JoinHandle};
 use tracing::{instrument, instrument::Instrumented};
 
 /// Caching replica type
@@ -19,6 +19,8 @@ pub struct CachingHome {
     home: Homes,
     contract_sync: ContractSync<HomeIndexers>,
     db: NomadDB,
+    cached_state: Arc<RwLock<Option<State>>>,
+    cached_count: Arc<RwLock<Option<u32>>>,
 }
 
 impl std::fmt::Display for CachingHome {
@@ -34,6 +36,8 @@ impl CachingHome {
             home,
             contract_sync,
             db,
+            cached_state: Default::default(),
+            cached_count: Default::default(),
         }
     }
 
@@ -47,6 +51,62 @@ impl CachingHome {
         self.db.clone()
     }
 
+    /// Notified whenever home syncing stores new dispatched messages
+    pub fn dispatches(&self) -> Arc<Notify> {
+        self.contract_sync.dispatches()
+    }
+
+    /// Fetch the home's state from chain and update the cached state
+    pub async fn refresh_state(&self) -> Result<State, ChainCommunicationError> {
+        let state = self.home.state().await?;
+        *self.cached_state.write().expect("!cached_state") = Some(state);
+        Ok(state)
+    }
+
+    /// Return the home's leaf count as of the latest consistent tree count
+    /// check, without an RPC. `None` until a check succeeded.
+    pub fn cached_count(&self) -> Option<u32> {
+        *self.cached_count.read().expect("!cached_count")
+    }
+
+    /// Cache a leaf count read consistently from `tree().count` and `count()`
+    pub(crate) fn cache_count(&self, count: u32) {
+        *self.cached_count.write().expect("!cached_count") = Some(count);
+    }
+
+    /// Return the cached home state, fetching it if it is not cached yet or
+    /// was last seen as failed
+    async fn checked_state(&self) -> Result<State, ChainCommunicationError> {
+        let cached = *self.cached_state.read().expect("!cached_state");
+        match cached {
+            Some(State::Failed) | None => self.refresh_state().await,
+            Some(state) => Ok(state),
+        }
+    }
+
+    /// Refuse to dispatch through a home whose cached state is failed
+    async fn check_not_failed(&self) -> Result<(), ChainCommunicationError> {
+        if self.checked_state().await? == State::Failed {
+            return Err(ChainCommunicationError::HomeFailed(self.name().to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Refresh the cached state after a failed dispatch, classifying the
+    /// error as `HomeFailed` if the home failed in the meantime
+    async fn classify_dispatch_error(&self, e: ChainCommunicationError) -> ChainCommunicationError {
+        match self.refresh_state().await {
+            Ok(State::Failed) => ChainCommunicationError::HomeFailed(self.name().to_owned()),
+            _ => e,
+        }
+    }
+
+    /// Bootstrap an empty db from a tree snapshot before syncing. See
+    /// `ContractSync::bootstrap_from`.
+    pub async fn bootstrap_from(&self, snapshot: TreeSnapshot) -> Result<()> {
+        self.contract_sync.bootstrap_from(snapshot).await
+    }
+
     /// Spawn a task that syncs the CachingHome's db with the on-chain event
     /// data
     pub fn sync(&self) -> Instrumented<JoinHandle<Result<()>>> {
@@ -69,8 +129,28 @@ impl Home for CachingHome {
         self.home.nonces(destination).await
     }
 
+    /// Pre-check the cached home state before dispatching. A failed home
+    /// returns `ChainCommunicationError::HomeFailed` instead of reverting. If
+    /// the dispatch fails, the cached state is refreshed so a home that
+    /// failed in the meantime is classified as such.
     async fn dispatch(&self, message: &Message) -> Result<TxOutcome, ChainCommunicationError> {
-        self.home.dispatch(message).await
+        self.check_not_failed().await?;
+        match self.home.dispatch(message).await {
+            Ok(outcome) => Ok(outcome),
+            Err(e) => Err(self.classify_dispatch_error(e).await),
+        }
+    }
+
+    /// As `dispatch`, including the failed state pre-check
+    async fn dispatch_tracked(
+        &self,
+        message: &Message,
+    ) -> Result<DispatchOutcome, ChainCommunicationError> {
+        self.check_not_failed().await?;
+        match self.home.dispatch_tracked(message).await {
+            Ok(outcome) => Ok(outcome),
+            Err(e) => Err(self.classify_dispatch_error(e).await),
+        }
     }
 
     async fn queue_length(&self) -> Result<U256, ChainCommunicationError> {
@@ -81,6 +161,18 @@ impl Home for CachingHome {
         self.home.queue_contains(root).await
     }
 
+    async fn queue_end(&self) -> Result<H256, ChainCommunicationError> {
+        self.home.queue_end().await
+    }
+
+    async fn counted_root(&self) -> Result<(H256, u32), ChainCommunicationError> {
+        self.home.counted_root().await
+    }
+
+    async fn tree_counts(&self) -> Result<(u32, u32), ChainCommunicationError> {
+        self.home.tree_counts().await
+    }
+
     async fn improper_update(
         &self,
         update: &SignedUpdate,
@@ -91,6 +183,10 @@ impl Home for CachingHome {
     async fn produce_update(&self) -> Result<Option<Update>, ChainCommunicationError> {
         self.home.produce_update().await
     }
+
+    async fn produce_pending_update(&self) -> Result<Option<Update>, ChainCommunicationError> {
+        self.home.produce_pending_update().await
+    }
 }
 
 #[async_trait]
@@ -149,7 +245,7 @@ impl Common for CachingHome {
     }
 
     async fn state(&self) -> Result<State, ChainCommunicationError> {
-        self.home.state().await
+        self.refresh_state().await
     }
 
     async fn committed_root(&self) -> Result<H256, ChainCommunicationError> {
@@ -327,6 +423,18 @@ impl Home for HomeVariants {
         }
     }
 
+    #[instrument(level = "trace", err)]
+    async fn dispatch_tracked(
+        &self,
+        message: &Message,
+    ) -> Result<DispatchOutcome, ChainCommunicationError> {
+        match self {
+            HomeVariants::Ethereum(home) => Ok(home.dispatch_tracked(message).await?),
+            HomeVariants::Substrate(home) => Ok(home.dispatch_tracked(message).await?),
+            HomeVariants::Mock(mock_home) => Ok(mock_home.dispatch_tracked(message).await?),
+        }
+    }
+
     #[instrument(level = "trace", err)]
     async fn queue_length(&self) -> Result<U256, ChainCommunicationError> {
         match self {
@@ -344,6 +452,30 @@ impl Home for HomeVariants {
         }
     }
 
+    async fn queue_end(&self) -> Result<H256, ChainCommunicationError> {
+        match self {
+            HomeVariants::Ethereum(home) => Ok(home.queue_end().await?),
+            HomeVariants::Substrate(home) => Ok(home.queue_end().await?),
+            HomeVariants::Mock(mock_home) => Ok(mock_home.queue_end().await?),
+        }
+    }
+
+    async fn counted_root(&self) -> Result<(H256, u32), ChainCommunicationError> {
+        match self {
+            HomeVariants::Ethereum(home) => Ok(home.counted_root().await?),
+            HomeVariants::Substrate(home) => Ok(home.counted_root().await?),
+            HomeVariants::Mock(mock_home) => Ok(mock_home.counted_root().await?),
+        }
+    }
+
+    async fn tree_counts(&self) -> Result<(u32, u32), ChainCommunicationError> {
+        match self {
+            HomeVariants::Ethereum(home) => Ok(home.tree_counts().await?),
+            HomeVariants::Substrate(home) => Ok(home.tree_counts().await?),
+            HomeVariants::Mock(mock_home) => Ok(mock_home.tree_counts().await?),
+        }
+    }
+
     async fn improper_update(
         &self,
         update: &SignedUpdate,
@@ -363,6 +495,15 @@ impl Home for HomeVariants {
             HomeVariants::Mock(mock_home) => Ok(mock_home.produce_update().await?),
         }
     }
+
+    #[instrument(err)]
+    async fn produce_pending_update(&self) -> Result<Option<Update>, ChainCommunicationError> {
+        match self {
+            HomeVariants::Ethereum(home) => Ok(home.produce_pending_update().await?),
+            HomeVariants::Substrate(home) => Ok(home.produce_pending_update().await?),
+            HomeVariants::Mock(mock_home) => Ok(mock_home.produce_pending_update().await?),
+        }
+    }
 }
 
 #[async_trait]
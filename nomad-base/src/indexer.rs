@@ -1,6 +1,8 @@
 use async_trait::async_trait;
 use color_eyre::Result;
-use nomad_core::{CommonIndexer, HomeIndexer, RawCommittedMessage, SignedUpdateWithMeta};
+use nomad_core::{
+    CommonIndexer, HomeIndexer, RawCommittedMessage, RootAdvance, SignedUpdateWithMeta,
+};
 use nomad_test::mocks::MockIndexer;
 use std::{ops::Deref, sync::Arc};
 
@@ -46,6 +48,10 @@ impl CommonIndexer for CommonIndexers {
         self.deref().get_block_number().await
     }
 
+    async fn get_latest_block_timestamp(&self) -> Result<Option<u64>, Self::Error> {
+        self.deref().get_latest_block_timestamp().await
+    }
+
     async fn fetch_sorted_updates(
         &self,
         from: u32,
@@ -53,6 +59,14 @@ impl CommonIndexer for CommonIndexers {
     ) -> Result<Vec<SignedUpdateWithMeta>, Self::Error> {
         self.deref().fetch_sorted_updates(from, to).await
     }
+
+    async fn fetch_root_advances(
+        &self,
+        from: u32,
+        to: u32,
+    ) -> Result<Vec<RootAdvance>, Self::Error> {
+        self.deref().fetch_root_advances(from, to).await
+    }
 }
 
 /// Home/Replica CommonIndexerVariants type
@@ -75,6 +89,17 @@ impl CommonIndexer for CommonIndexerVariants {
         }
     }
 
+    async fn get_latest_block_timestamp(&self) -> Result<Option<u64>, Self::Error> {
+        match self {
+            CommonIndexerVariants::Ethereum(indexer) => {
+                Ok(indexer.get_latest_block_timestamp().await?)
+            }
+            CommonIndexerVariants::Mock(indexer) => {
+                Ok(indexer.get_latest_block_timestamp().await?)
+            }
+        }
+    }
+
     async fn fetch_sorted_updates(
         &self,
         from: u32,
@@ -89,6 +114,21 @@ impl CommonIndexer for CommonIndexerVariants {
             }
         }
     }
+
+    async fn fetch_root_advances(
+        &self,
+        from: u32,
+        to: u32,
+    ) -> Result<Vec<RootAdvance>, Self::Error> {
+        match self {
+            CommonIndexerVariants::Ethereum(indexer) => {
+                Ok(indexer.fetch_root_advances(from, to).await?)
+            }
+            CommonIndexerVariants::Mock(indexer) => {
+                Ok(indexer.fetch_root_advances(from, to).await?)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -129,6 +169,10 @@ impl CommonIndexer for HomeIndexers {
         self.deref().get_block_number().await
     }
 
+    async fn get_latest_block_timestamp(&self) -> Result<Option<u64>, Self::Error> {
+        self.deref().get_latest_block_timestamp().await
+    }
+
     async fn fetch_sorted_updates(
         &self,
         from: u32,
@@ -172,6 +216,18 @@ impl CommonIndexer for HomeIndexerVariants {
         }
     }
 
+    async fn get_latest_block_timestamp(&self) -> Result<Option<u64>, Self::Error> {
+        match self {
+            HomeIndexerVariants::Ethereum(indexer) => {
+                Ok(indexer.get_latest_block_timestamp().await?)
+            }
+            HomeIndexerVariants::Substrate(indexer) => {
+                Ok(indexer.get_latest_block_timestamp().await?)
+            }
+            HomeIndexerVariants::Mock(indexer) => Ok(indexer.get_latest_block_timestamp().await?),
+        }
+    }
+
     async fn fetch_sorted_updates(
         &self,
         from: u32,
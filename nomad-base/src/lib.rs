@@ -27,10 +27,26 @@ pub use macros::*;
 mod nomad_db;
 pub use nomad_db::*;
 
+/// Deep verification of stored messages and proofs
+mod db_verify;
+pub use db_verify::*;
+
+/// Append-only audit trail of decisions leading to on-chain actions
+mod audit;
+pub use audit::*;
+
+/// Evidence bundles of fraud incidents for public disclosure
+mod evidence;
+pub use evidence::*;
+
 /// Base errors
 mod error;
 pub use error::*;
 
+/// Fatal errors and the exit codes of their categories
+mod fatal;
+pub use fatal::*;
+
 /// Home type
 mod home;
 pub use home::*;
@@ -51,9 +67,62 @@ pub use contract_sync::*;
 mod indexer;
 pub use indexer::*;
 
+/// Message tree snapshots for bootstrapping agents
+mod snapshot;
+pub use snapshot::*;
+
 mod submitter;
 pub use submitter::*;
 
+/// Testnet top-up of agent signers
+mod top_up;
+pub use top_up::*;
+
+/// Named signer roles and their address checks
+mod signer_roles;
+pub use signer_roles::*;
+
+/// Checks of the home's updater against recent update signers
+mod updater_signers;
+pub use updater_signers::*;
+
+/// Updater health, from the home's queue and dispatches
+mod updater_health;
+pub use updater_health::*;
+
+/// Home tree count consistency checks
+mod tree_counts;
+pub use tree_counts::*;
+
+/// Estimates of host clock skew against chain time
+mod clock_skew;
+pub use clock_skew::*;
+
+/// Admin endpoints served to loopback clients bearing the admin token
+mod admin;
+pub use admin::*;
+
+/// Runtime pause switches of an agent's corridors
+mod corridors;
+pub use corridors::*;
+
+/// Heartbeat claims on transaction signers, detecting other processes
+/// submitting with them
+mod signer_claims;
+pub use signer_claims::*;
+
+/// Deduplication of signed updates observed by several sources
+mod seen_updates;
+pub use seen_updates::*;
+
+/// Retry and backoff strategies, interrupted by the agent's shutdown
+mod retry;
+pub use retry::*;
+
+/// CPU and heap profiling endpoints of the metrics server
+#[cfg(feature = "profiling")]
+mod profiling;
+
 /// Re-export signer trait for attestation signer.
 pub use ethers::signers::Signer;
 
@@ -88,6 +88,10 @@ macro_rules! decl_channel {
                 pub fn db(&self) -> nomad_base::NomadDB {
                     self.as_ref().db.clone()
                 }
+
+                pub fn cancellation(&self) -> nomad_base::CancellationToken {
+                    self.as_ref().cancellation.clone()
+                }
             }
         }
     }
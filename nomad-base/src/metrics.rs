@@ -1,16 +1,123 @@
 //! Useful metrics that all agents should track.
 
-use color_eyre::Result;
+#![deny(clippy::cast_possible_truncation)]
+
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use nomad_types::CheckedCast;
 use prometheus::{
+    core::{Collector, MetricVec, MetricVecBuilder},
     Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
 };
-use std::sync::Arc;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+};
 use tokio::task::JoinHandle;
 
 fn u16_from_env(s: impl AsRef<str>) -> Option<u16> {
     std::env::var(s.as_ref()).ok().and_then(|i| i.parse().ok())
 }
 
+/// A metric vec registered through `CoreMetrics`
+#[derive(Debug, Clone)]
+enum Family {
+    IntGauge(IntGaugeVec),
+    IntCounter(IntCounterVec),
+    Histogram(HistogramVec),
+}
+
+/// Metric vecs `CoreMetrics` registers, by type
+trait FamilyKind: Collector + Clone + Sized + 'static {
+    fn wrap(self) -> Family;
+    fn unwrap(family: &Family) -> Option<&Self>;
+}
+
+impl FamilyKind for IntGaugeVec {
+    fn wrap(self) -> Family {
+        Family::IntGauge(self)
+    }
+
+    fn unwrap(family: &Family) -> Option<&Self> {
+        match family {
+            Family::IntGauge(vec) => Some(vec),
+            _ => None,
+        }
+    }
+}
+
+impl FamilyKind for IntCounterVec {
+    fn wrap(self) -> Family {
+        Family::IntCounter(self)
+    }
+
+    fn unwrap(family: &Family) -> Option<&Self> {
+        match family {
+            Family::IntCounter(vec) => Some(vec),
+            _ => None,
+        }
+    }
+}
+
+impl FamilyKind for HistogramVec {
+    fn wrap(self) -> Family {
+        Family::Histogram(self)
+    }
+
+    fn unwrap(family: &Family) -> Option<&Self> {
+        match family {
+            Family::Histogram(vec) => Some(vec),
+            _ => None,
+        }
+    }
+}
+
+/// Series of a metric per corridor from the agent's home, labeled `home`,
+/// `replica` and `agent`. Series of the configured corridors are created up
+/// front, so they are exported before their first observation. Series of
+/// corridors appearing later are created on first use.
+#[derive(Clone)]
+pub struct CorridorMetric<T: MetricVecBuilder> {
+    vec: MetricVec<T>,
+    home: String,
+    agent: String,
+}
+
+impl<T: MetricVecBuilder> std::fmt::Debug for CorridorMetric<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CorridorMetric")
+            .field("home", &self.home)
+            .field("agent", &self.agent)
+            .finish()
+    }
+}
+
+impl<T: MetricVecBuilder> CorridorMetric<T> {
+    /// Create the series of `replicas`
+    pub fn prewarm<'a>(&self, replicas: impl IntoIterator<Item = &'a str>) {
+        for replica in replicas {
+            self.get(replica);
+        }
+    }
+
+    /// The series of the corridor to `replica`, created if it is new
+    pub fn get(&self, replica: &str) -> T::M {
+        self.vec
+            .with_label_values(&[&self.home, replica, &self.agent])
+    }
+}
+
+/// Int gauges per corridor
+pub type CorridorIntGauge =
+    CorridorMetric<prometheus::core::GaugeVecBuilder<prometheus::core::AtomicI64>>;
+
+/// Int counters per corridor
+pub type CorridorIntCounter =
+    CorridorMetric<prometheus::core::CounterVecBuilder<prometheus::core::AtomicU64>>;
+
 #[derive(Debug)]
 /// Metrics for a particular domain
 pub struct CoreMetrics {
@@ -23,7 +130,17 @@ pub struct CoreMetrics {
     span_durations: Box<HistogramVec>,
     home_failure_checks: Box<IntGaugeVec>,
     home_failure_observations: Box<IntGaugeVec>,
+    tree_count_mismatches: Box<IntGaugeVec>,
+    updater_signer_mismatches: Box<IntGaugeVec>,
+    updater_stalled: Box<IntGaugeVec>,
+    updater_idle: Box<IntGaugeVec>,
+    pending_root_age: Box<IntGaugeVec>,
+    start_time: Box<IntGaugeVec>,
     listen_port: Option<u16>,
+    profiling: bool,
+    status: RwLock<BTreeMap<String, BTreeMap<String, Value>>>,
+    /// Metric vecs registered through `new_*`, with their label names
+    families: RwLock<HashMap<String, (Vec<String>, Family)>>,
     /// Metrics registry for adding new metrics and gathering reports
     registry: Arc<Registry>,
 }
@@ -51,7 +168,7 @@ impl CoreMetrics {
             wallet_balance: Box::new(IntGaugeVec::new(
                 Opts::new(
                     "wallet_balance_total",
-                    "Balance of the smart contract wallet",
+                    "Balance of the smart contract wallet in gwei",
                 )
                 .namespace("nomad")
                 .const_label("VERSION", env!("CARGO_PKG_VERSION")),
@@ -102,8 +219,65 @@ impl CoreMetrics {
                 .const_label("VERSION", env!("CARGO_PKG_VERSION")),
                 &["home", "agent"]
             )?),
+            tree_count_mismatches: Box::new(IntGaugeVec::new(
+                Opts::new(
+                    "tree_count_mismatches",
+                    "Number of times agent has seen home tree().count differ from count() (anything > 0 is major red flag!)",
+                )
+                .namespace("nomad")
+                .const_label("VERSION", env!("CARGO_PKG_VERSION")),
+                &["home", "agent"]
+            )?),
+            updater_signer_mismatches: Box::new(IntGaugeVec::new(
+                Opts::new(
+                    "updater_signer_mismatches",
+                    "Number of checks where the home's updater, the configured updater and recent update signers disagreed",
+                )
+                .namespace("nomad")
+                .const_label("VERSION", env!("CARGO_PKG_VERSION")),
+                &["home", "agent"]
+            )?),
+            updater_stalled: Box::new(IntGaugeVec::new(
+                Opts::new(
+                    "updater_stalled",
+                    "1 while a root has been pending on the home longer than the expected updater interval",
+                )
+                .namespace("nomad")
+                .const_label("VERSION", env!("CARGO_PKG_VERSION")),
+                &["home", "agent"]
+            )?),
+            updater_idle: Box::new(IntGaugeVec::new(
+                Opts::new(
+                    "updater_idle",
+                    "1 while the home's queue is empty and nothing was dispatched recently (informational)",
+                )
+                .namespace("nomad")
+                .const_label("VERSION", env!("CARGO_PKG_VERSION")),
+                &["home", "agent"]
+            )?),
+            pending_root_age: Box::new(IntGaugeVec::new(
+                Opts::new(
+                    "pending_root_age_seconds",
+                    "Seconds the oldest unattested root has been pending on the home. 0 if the queue is empty",
+                )
+                .namespace("nomad")
+                .const_label("VERSION", env!("CARGO_PKG_VERSION")),
+                &["home", "agent"]
+            )?),
+            start_time: Box::new(IntGaugeVec::new(
+                Opts::new(
+                    "start_time_seconds",
+                    "Unix time the agent started at. Operator reports use it to tell when an agent was down",
+                )
+                .namespace("nomad")
+                .const_label("VERSION", env!("CARGO_PKG_VERSION")),
+                &["home", "agent"]
+            )?),
             registry,
             listen_port,
+            profiling: false,
+            status: Default::default(),
+            families: Default::default(),
         };
 
         // TODO: only register these if they aren't already registered?
@@ -119,48 +293,122 @@ impl CoreMetrics {
         metrics
             .registry
             .register(metrics.home_failure_observations.clone())?;
+        metrics
+            .registry
+            .register(metrics.tree_count_mismatches.clone())?;
+        metrics
+            .registry
+            .register(metrics.updater_signer_mismatches.clone())?;
+        metrics.registry.register(metrics.updater_stalled.clone())?;
+        metrics.registry.register(metrics.updater_idle.clone())?;
+        metrics
+            .registry
+            .register(metrics.pending_root_age.clone())?;
+        metrics.registry.register(metrics.start_time.clone())?;
+        let started = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        metrics
+            .start_time
+            .with_label_values(&[&metrics.home_name, &metrics.agent_name])
+            .set(i64::try_from(started).unwrap_or(i64::MAX));
+        metrics
+            .registry
+            .register(Box::new(nomad_ethereum::RECEIPT_LATENCIES.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(nomad_ethereum::RPC_ERROR_CLASSIFICATIONS.clone()))?;
 
         Ok(metrics)
     }
 
-    /// Register an int gauge vec
+    /// Register the hit and miss counters of `db`'s cache, and the sizes and
+    /// durations of its flushed write batches
+    pub fn register_db(&self, db: &nomad_core::db::DB) -> Result<()> {
+        self.registry.register(Box::new(db.cache_lookups()))?;
+        let (batch_sizes, flush_latencies) = db.write_batch_metrics();
+        self.registry.register(Box::new(batch_sizes))?;
+        self.registry.register(Box::new(flush_latencies))?;
+        Ok(())
+    }
+
+    /// Fetch the metric vec registered as `metric_name`, or create and
+    /// register it. Registering a name again returns the registered vec, so
+    /// tasks restarted by their supervisor share the series of their previous
+    /// run. Fails if the name is registered with another type or other labels.
+    fn family<T: FamilyKind>(
+        &self,
+        metric_name: &str,
+        labels: &[&str],
+        create: impl FnOnce() -> prometheus::Result<T>,
+    ) -> Result<T> {
+        let mut families = self.families.write().expect("poisoned");
+        if let Some((registered_labels, family)) = families.get(metric_name) {
+            if registered_labels
+                .iter()
+                .map(String::as_str)
+                .ne(labels.iter().copied())
+            {
+                bail!(
+                    "metric {} is registered with labels {:?}, not {:?}",
+                    metric_name,
+                    registered_labels,
+                    labels
+                );
+            }
+            return T::unwrap(family)
+                .cloned()
+                .ok_or_else(|| eyre!("metric {} is registered with another type", metric_name));
+        }
+
+        let vec = create()?;
+        self.registry.register(Box::new(vec.clone()))?;
+        families.insert(
+            metric_name.to_owned(),
+            (
+                labels.iter().map(|label| label.to_string()).collect(),
+                vec.clone().wrap(),
+            ),
+        );
+        Ok(vec)
+    }
+
+    /// Register an int gauge vec, or fetch it if it is registered
     pub fn new_int_gauge_vec(
         &self,
         metric_name: &str,
         help: &str,
         labels: &[&str],
     ) -> Result<prometheus::IntGaugeVec> {
-        let gauge_vec = IntGaugeVec::new(
-            Opts::new(metric_name, help)
-                .namespace("nomad")
-                .const_label("VERSION", env!("CARGO_PKG_VERSION")),
-            labels,
-        )?;
-        self.registry.register(Box::new(gauge_vec.clone()))?;
-
-        Ok(gauge_vec)
+        self.family(metric_name, labels, || {
+            IntGaugeVec::new(
+                Opts::new(metric_name, help)
+                    .namespace("nomad")
+                    .const_label("VERSION", env!("CARGO_PKG_VERSION")),
+                labels,
+            )
+        })
     }
 
-    /// Register an int counter.
+    /// Register an int counter, or fetch it if it is registered
     pub fn new_int_counter(
         &self,
         metric_name: &str,
         help: &str,
         labels: &[&str],
     ) -> Result<prometheus::IntCounterVec> {
-        let counter = IntCounterVec::new(
-            Opts::new(metric_name, help)
-                .namespace("nomad")
-                .const_label("VERSION", env!("CARGO_PKG_VERSION")),
-            labels,
-        )?;
-
-        self.registry.register(Box::new(counter.clone()))?;
-
-        Ok(counter)
+        self.family(metric_name, labels, || {
+            IntCounterVec::new(
+                Opts::new(metric_name, help)
+                    .namespace("nomad")
+                    .const_label("VERSION", env!("CARGO_PKG_VERSION")),
+                labels,
+            )
+        })
     }
 
-    /// Register a histogram.
+    /// Register a histogram, or fetch it if it is registered. A fetched
+    /// histogram keeps the buckets it was registered with.
     pub fn new_histogram(
         &self,
         metric_name: &str,
@@ -168,29 +416,53 @@ impl CoreMetrics {
         labels: &[&str],
         buckets: &[f64],
     ) -> Result<prometheus::HistogramVec> {
-        let histogram = HistogramVec::new(
-            HistogramOpts::new(metric_name, help)
-                .namespace("nomad")
-                .buckets(buckets.to_owned())
-                .const_label("VERSION", env!("CARGO_PKG_VERSION")),
-            labels,
-        )?;
+        self.family(metric_name, labels, || {
+            HistogramVec::new(
+                HistogramOpts::new(metric_name, help)
+                    .namespace("nomad")
+                    .buckets(buckets.to_owned())
+                    .const_label("VERSION", env!("CARGO_PKG_VERSION")),
+                labels,
+            )
+        })
+    }
 
-        self.registry.register(Box::new(histogram.clone()))?;
+    /// Register an int gauge per corridor from the home, or fetch it if it is
+    /// registered
+    pub fn corridor_int_gauge(&self, metric_name: &str, help: &str) -> Result<CorridorIntGauge> {
+        Ok(CorridorMetric {
+            vec: self.new_int_gauge_vec(metric_name, help, &["home", "replica", "agent"])?,
+            home: self.home_name.clone(),
+            agent: self.agent_name.clone(),
+        })
+    }
 
-        Ok(histogram)
+    /// Register an int counter per corridor from the home, or fetch it if it
+    /// is registered
+    pub fn corridor_int_counter(
+        &self,
+        metric_name: &str,
+        help: &str,
+    ) -> Result<CorridorIntCounter> {
+        Ok(CorridorMetric {
+            vec: self.new_int_counter(metric_name, help, &["home", "replica", "agent"])?,
+            home: self.home_name.clone(),
+            agent: self.agent_name.clone(),
+        })
     }
 
-    /// Call with the new balance when gas is spent.
+    /// Call with the new balance in wei when gas is spent. Reported in gwei,
+    /// as wei overflow the gauge above ~9.2 ETH.
     pub fn wallet_balance_changed(
         &self,
         chain: &str,
         address: ethers::types::Address,
         current_balance: ethers::types::U256,
     ) {
+        let gwei = current_balance / ethers::types::U256::exp10(9);
         self.wallet_balance
             .with_label_values(&[chain, &format!("{:x}", address), &self.agent_name])
-            .set(current_balance.as_u64() as i64) // XXX: truncated data
+            .set(gwei.saturating_i64())
     }
 
     /// Return single gauge for one home <> replica channel
@@ -211,6 +483,36 @@ impl CoreMetrics {
             .with_label_values(&[&self.home_name, &self.agent_name])
     }
 
+    /// Return home tree count mismatches gauge
+    pub fn tree_count_mismatches(&self) -> IntGauge {
+        self.tree_count_mismatches
+            .with_label_values(&[&self.home_name, &self.agent_name])
+    }
+
+    /// Return updater signer mismatches gauge
+    pub fn updater_signer_mismatches(&self) -> IntGauge {
+        self.updater_signer_mismatches
+            .with_label_values(&[&self.home_name, &self.agent_name])
+    }
+
+    /// Return updater stalled gauge
+    pub fn updater_stalled(&self) -> IntGauge {
+        self.updater_stalled
+            .with_label_values(&[&self.home_name, &self.agent_name])
+    }
+
+    /// Return updater idle gauge
+    pub fn updater_idle(&self) -> IntGauge {
+        self.updater_idle
+            .with_label_values(&[&self.home_name, &self.agent_name])
+    }
+
+    /// Return pending root age gauge
+    pub fn pending_root_age(&self) -> IntGauge {
+        self.pending_root_age
+            .with_label_values(&[&self.home_name, &self.agent_name])
+    }
+
     /// Call with RPC duration after it is complete
     pub fn rpc_complete(&self, chain: &str, method: &str, duration_ms: f64) {
         self.rpc_latencies
@@ -225,6 +527,59 @@ impl CoreMetrics {
         *self.span_durations.clone()
     }
 
+    /// Serve profiles on `/debug/pprof`. Has no effect unless built with the
+    /// `profiling` feature
+    pub fn with_profiling(mut self, enabled: bool) -> Self {
+        self.profiling = enabled;
+        self
+    }
+
+    /// Set the `/status` entry for `key` in `section`, e.g. a replica's
+    /// processing backlog
+    pub fn set_status(&self, section: &str, key: &str, value: Value) {
+        self.status
+            .write()
+            .expect("poisoned")
+            .entry(section.to_owned())
+            .or_default()
+            .insert(key.to_owned(), value);
+    }
+
+    /// Remove the `/status` entry for `key` in `section`, if any
+    pub fn remove_status(&self, section: &str, key: &str) {
+        if let Some(entries) = self.status.write().expect("poisoned").get_mut(section) {
+            entries.remove(key);
+        }
+    }
+
+    /// Status report served on `/status`
+    pub fn status(&self) -> Value {
+        let mut report = serde_json::Map::new();
+        report.insert("agent".to_owned(), self.agent_name.clone().into());
+        report.insert("home".to_owned(), self.home_name.clone().into());
+        for (section, entries) in self.status.read().expect("poisoned").iter() {
+            report.insert(
+                section.clone(),
+                Value::Object(entries.clone().into_iter().collect()),
+            );
+        }
+        let history_depths = nomad_ethereum::HISTORY_DEPTHS.status();
+        if !history_depths.is_empty() {
+            report.insert(
+                "history_depth".to_owned(),
+                Value::Object(history_depths.into_iter().collect()),
+            );
+        }
+        let log_range_caps = nomad_ethereum::LOG_RANGE_CAPS.status();
+        if !log_range_caps.is_empty() {
+            report.insert(
+                "log_range_cap".to_owned(),
+                Value::Object(log_range_caps.into_iter().collect()),
+            );
+        }
+        Value::Object(report)
+    }
+
     /// Gather available metrics into an encoded (plaintext, OpenMetrics format) report.
     pub fn gather(&self) -> prometheus::Result<Vec<u8>> {
         let collected_metrics = self.registry.gather();
@@ -235,6 +590,8 @@ impl CoreMetrics {
     }
 
     /// Run an HTTP server serving OpenMetrics format reports on `/metrics`
+    /// and the agent's JSON status report on `/status`. With the `profiling`
+    /// feature and setting, also serves profiles on `/debug/pprof`.
     ///
     /// This is compatible with Prometheus, which ought to be configured to scrape me!
     pub fn run_http_server(self: Arc<CoreMetrics>) -> JoinHandle<()> {
@@ -250,28 +607,173 @@ impl CoreMetrics {
             port = port
         );
 
+        #[cfg(feature = "profiling")]
+        let profiles = crate::profiling::routes(self.profiling, crate::admin_token());
+        #[cfg(not(feature = "profiling"))]
+        if self.profiling {
+            tracing::warn!("Profiling is enabled but the agent was built without the profiling feature. Ignoring.");
+        }
+
+        let status = self.clone();
+        let routes = warp::path!("status")
+            .map(move || warp::reply::json(&status.status()))
+            .or(warp::path!("metrics").map(move || {
+                warp::reply::with_header(
+                    self.gather().expect("failed to encode metrics"),
+                    "Content-Type",
+                    // OpenMetrics specs demands "application/openmetrics-text; version=1.0.0; charset=utf-8"
+                    // but the prometheus scraper itself doesn't seem to care?
+                    // try text/plain to make web browsers happy.
+                    "text/plain; charset=utf-8",
+                )
+            }));
+        #[cfg(feature = "profiling")]
+        let routes = routes.or(profiles);
+
         tokio::spawn(async move {
-            warp::serve(
-                warp::path!("metrics")
-                    .map(move || {
-                        warp::reply::with_header(
-                            self.gather().expect("failed to encode metrics"),
-                            "Content-Type",
-                            // OpenMetrics specs demands "application/openmetrics-text; version=1.0.0; charset=utf-8"
-                            // but the prometheus scraper itself doesn't seem to care?
-                            // try text/plain to make web browsers happy.
-                            "text/plain; charset=utf-8",
-                        )
-                    })
-                    .or(warp::any().map(|| {
-                        warp::reply::with_status(
-                            "go look at /metrics",
-                            warp::http::StatusCode::NOT_FOUND,
-                        )
-                    })),
-            )
+            warp::serve(routes.or(warp::any().map(|| {
+                warp::reply::with_status(
+                    "go look at /metrics or /status",
+                    warp::http::StatusCode::NOT_FOUND,
+                )
+            })))
             .run(([0, 0, 0, 0], port))
             .await;
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ContractSyncMetrics;
+
+    fn metrics() -> Arc<CoreMetrics> {
+        Arc::new(
+            CoreMetrics::new(
+                "metrics_test",
+                "ethereum",
+                None,
+                Arc::new(prometheus::Registry::new()),
+            )
+            .expect("could not make metrics"),
+        )
+    }
+
+    /// Exported series of `metric`
+    fn exported(metrics: &CoreMetrics, metric: &str) -> Vec<String> {
+        String::from_utf8(metrics.gather().unwrap())
+            .unwrap()
+            .lines()
+            .filter(|line| line.starts_with(&format!("{}{{", metric)))
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn it_fetches_metrics_registered_before_a_restart() {
+        let metrics = metrics();
+
+        // a task building its metrics, restarted by its supervisor
+        let first = ContractSyncMetrics::new(metrics.clone());
+        first
+            .stored_events
+            .with_label_values(&["messages", "ethereum", "moonbeam", "processor"])
+            .set(5);
+        let restarted = ContractSyncMetrics::new(metrics.clone());
+        assert_eq!(
+            restarted
+                .stored_events
+                .with_label_values(&["messages", "ethereum", "moonbeam", "processor"])
+                .get(),
+            5
+        );
+        assert_eq!(
+            exported(&metrics, "nomad_contract_sync_stored_events").len(),
+            1
+        );
+
+        // concurrent registrations share one vec
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let metrics = metrics.clone();
+                std::thread::spawn(move || {
+                    metrics
+                        .new_int_counter("signer_top_ups", "top-ups", &["network", "result"])
+                        .unwrap()
+                        .with_label_values(&["goerli", "ok"])
+                        .inc()
+                })
+            })
+            .collect();
+        handles.into_iter().for_each(|h| h.join().unwrap());
+        assert_eq!(
+            metrics
+                .new_int_counter("signer_top_ups", "top-ups", &["network", "result"])
+                .unwrap()
+                .with_label_values(&["goerli", "ok"])
+                .get(),
+            8
+        );
+
+        // a name is bound to its type and labels
+        assert!(metrics
+            .new_int_gauge_vec("signer_top_ups", "top-ups", &["network", "result"])
+            .is_err());
+        assert!(metrics
+            .new_int_counter("signer_top_ups", "top-ups", &["network"])
+            .is_err());
+    }
+
+    #[test]
+    fn it_counts_corridors_appearing_after_startup() {
+        let metrics = metrics();
+        let processed = metrics
+            .corridor_int_counter("processed", "processed messages")
+            .unwrap();
+
+        processed.prewarm(["moonbeam"]);
+        assert_eq!(
+            exported(&metrics, "nomad_processed"),
+            vec![format!(
+                "nomad_processed{{VERSION=\"{}\",agent=\"metrics_test\",home=\"ethereum\",replica=\"moonbeam\"}} 0",
+                env!("CARGO_PKG_VERSION")
+            )]
+        );
+
+        // a corridor added at runtime is counted from its first message
+        processed.get("evmos").inc();
+        let exported = exported(&metrics, "nomad_processed");
+        assert_eq!(exported.len(), 2);
+        assert!(exported
+            .iter()
+            .any(|line| line.contains("replica=\"evmos\"") && line.ends_with(" 1")));
+
+        // fetching the corridor metric again keeps the series
+        let refetched = metrics
+            .corridor_int_counter("processed", "processed messages")
+            .unwrap();
+        assert_eq!(refetched.get("evmos").get(), 1);
+    }
+
+    #[test]
+    fn it_reports_detected_history_depths() {
+        let metrics = metrics();
+        assert!(
+            metrics.status().get("history_depth").is_none()
+                || metrics.status()["history_depth"]
+                    .get("pruned_chain")
+                    .is_none()
+        );
+
+        for _ in 0..3 {
+            nomad_ethereum::HISTORY_DEPTHS.record_missing("pruned_chain", 1000, 1129, 129);
+        }
+        let status = metrics.status();
+        assert_eq!(status["history_depth"]["pruned_chain"]["depth"], 128);
+        assert_eq!(
+            status["history_depth"]["pruned_chain"]["missing_block"],
+            1000
+        );
+    }
+}
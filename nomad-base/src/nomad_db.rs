@@ -2,22 +2,30 @@ use color_eyre::Result;
 use ethers::core::types::H256;
 use nomad_core::db::{DbError, TypedDB, DB};
 use nomad_core::{
-    accumulator::NomadProof, utils, CommittedMessage, Decode, NomadMessage, RawCommittedMessage,
-    SignedUpdate, SignedUpdateWithMeta, UpdateMeta,
+    accumulator::NomadProof, utils, CanonicalSignature, CommittedMessage, Decode, Encode,
+    MessageId, MessageRef, NomadError, NomadMessage, RawCommittedMessage, RootAdvance,
+    RootProvenance, SignedUpdate, SignedUpdateWithMeta, UpdateChain, UpdateMeta, GENESIS_ROOT,
+    SIGNATURE_LENGTH,
 };
 use tokio::time::sleep;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
+use std::collections::{BTreeMap, HashMap};
 use std::future::Future;
 use std::time::Duration;
 
 use nomad_core::db::iterator::PrefixIterator;
 
+use crate::contract_sync::{MESSAGES_LAST_BLOCK_END, UPDATES_LAST_BLOCK_END};
+use crate::{MessageIntegrityError, Observation, SeenUpdates};
+
 const LEAF_IDX: &str = "leaf_index_";
 const LEAF: &str = "leaf_";
 const PREV_ROOT: &str = "update_prev_root_";
-const PROOF: &str = "proof_";
-const MESSAGE: &str = "message_";
+pub(crate) const PROOF: &str = "proof_";
+const QUARANTINED_PROOF: &str = "quarantined_proof_";
+pub(crate) const MESSAGE: &str = "message_";
+const MESSAGE_TIMESTAMP: &str = "message_timestamp_";
 const UPDATE: &str = "update_";
 const UPDATE_META: &str = "update_metadata_";
 const LATEST_ROOT: &str = "update_latest_root_";
@@ -25,10 +33,272 @@ const LATEST_LEAF_INDEX: &str = "latest_known_leaf_index_";
 const UPDATER_PRODUCED_UPDATE: &str = "updater_produced_update_";
 const PROVER_LATEST_COMMITTED: &str = "prover_latest_committed_";
 const PROCESSOR_ATTEMPTED: &str = "processor_attempted_";
+const WATCHER_FRONTIER: &str = "watcher_frontier_";
+const WATCHER_AUDIT_CHECKPOINT: &str = "watcher_audit_checkpoint_";
+const PROCESSED_FAILED: &str = "processed_failed_";
+const PROCESSING_ROOT: &str = "processing_root_";
+const LATEST_PROCESSED_LEAF: &str = "latest_processed_leaf_";
+const RELAY_RECORD: &str = "relay_record_";
+const RELAY_SUBMISSION: &str = "relay_submission_";
+const RELAY_CURSOR: &str = "relay_cursor_";
+const SEEN_UPDATES: &str = "seen_updates_";
+const LEAF_OCCURRENCE: &str = "dispatched_leaf_";
+const DUPLICATE_LEAF: &str = "duplicate_leaf_";
+const ROOT_ADVANCE: &str = "root_advance_";
+const LATEST_ROOT_ADVANCE: &str = "latest_root_advance_";
+const GENESIS_ROOT_ADVANCE: &str = "genesis_root_advance_";
+const RECIPIENT_GAS: &str = "recipient_gas_";
+const PROCESSED_LEG: &str = "processed_leg_";
+const LOG_RANGE_CAP: &str = "log_range_cap_";
+const GAS_CAPPED: &str = "gas_capped_";
+
+/// Seconds in a day of recipient gas totals
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+/// Days of recipient gas totals kept
+pub const RECIPIENT_GAS_RETENTION_DAYS: u64 = 30;
+
+/// Length of an encoded update: home domain, previous root and new root
+const UPDATE_LENGTH: usize = 4 + 32 + 32;
+/// Length of an encoded signed update
+const SIGNED_UPDATE_LENGTH: usize = UPDATE_LENGTH + SIGNATURE_LENGTH;
+
+/// Small, frequently read key spaces cached by every `NomadDB`
+const HOT_KEY_SPACES: [&str; 7] = [
+    LATEST_ROOT,
+    LATEST_ROOT_ADVANCE,
+    LATEST_LEAF_INDEX,
+    PROVER_LATEST_COMMITTED,
+    PROCESSOR_ATTEMPTED,
+    UPDATES_LAST_BLOCK_END,
+    MESSAGES_LAST_BLOCK_END,
+];
+
+/// Root a message was submitted for processing under, and how the replica
+/// came to accept it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessingRoot {
+    /// Root the message was proven against
+    pub root: H256,
+    /// How the replica came to accept the root
+    pub provenance: RootProvenance,
+}
+
+impl Encode for ProcessingRoot {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = self.root.write_to(writer)?;
+        written += self.provenance.write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for ProcessingRoot {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        Ok(Self {
+            root: H256::read_from(reader)?,
+            provenance: RootProvenance::read_from(reader)?,
+        })
+    }
+}
+
+/// Leaf index a leaf was indexed at, and the blocks of the page its
+/// `Dispatch` event was indexed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeafOccurrence {
+    /// Leaf index
+    pub leaf_index: u32,
+    /// First block of the page
+    pub from_block: u32,
+    /// Last block of the page
+    pub to_block: u32,
+}
+
+impl Encode for LeafOccurrence {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = self.leaf_index.write_to(writer)?;
+        written += self.from_block.write_to(writer)?;
+        written += self.to_block.write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for LeafOccurrence {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        Ok(Self {
+            leaf_index: u32::read_from(reader)?,
+            from_block: u32::read_from(reader)?,
+            to_block: u32::read_from(reader)?,
+        })
+    }
+}
+
+/// Transaction paying for a leg of processing a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessingLeg {
+    /// `prove`, submitted on its own
+    Prove,
+    /// `process` of a proven message
+    Process,
+    /// `proveAndProcess`
+    ProveAndProcess,
+}
+
+impl ProcessingLeg {
+    fn code(self) -> u32 {
+        match self {
+            ProcessingLeg::Prove => 0,
+            ProcessingLeg::Process => 1,
+            ProcessingLeg::ProveAndProcess => 2,
+        }
+    }
+
+    fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(ProcessingLeg::Prove),
+            1 => Some(ProcessingLeg::Process),
+            2 => Some(ProcessingLeg::ProveAndProcess),
+            _ => None,
+        }
+    }
+}
+
+/// Gas paid processing messages to a recipient
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipientGas {
+    /// Gas used by every leg
+    pub gas_used: u64,
+    /// Transactions paid for
+    pub transactions: u64,
+    /// Messages processed
+    pub messages: u64,
+}
+
+impl RecipientGas {
+    /// Add the totals of `other`
+    pub fn add(&mut self, other: &RecipientGas) {
+        self.gas_used = self.gas_used.saturating_add(other.gas_used);
+        self.transactions += other.transactions;
+        self.messages += other.messages;
+    }
+
+    /// Average gas per processed message, the prove legs of split
+    /// processing included
+    pub fn average_per_message(&self) -> Option<u64> {
+        if self.messages == 0 {
+            return None;
+        }
+        Some(self.gas_used / self.messages)
+    }
+}
+
+impl Encode for RecipientGas {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = self.gas_used.write_to(writer)?;
+        written += self.transactions.write_to(writer)?;
+        written += self.messages.write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for RecipientGas {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        Ok(Self {
+            gas_used: u64::read_from(reader)?,
+            transactions: u64::read_from(reader)?,
+            messages: u64::read_from(reader)?,
+        })
+    }
+}
+
+/// Leg of processing a message, recorded when its transaction was paid for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ProcessedLeg {
+    timestamp: u64,
+    destination: u32,
+    leaf_index: u32,
+    leg: ProcessingLeg,
+    gas_used: u64,
+}
+
+impl ProcessedLeg {
+    /// Totals the leg adds to its destination
+    fn totals(&self) -> RecipientGas {
+        RecipientGas {
+            gas_used: self.gas_used,
+            transactions: 1,
+            messages: (self.leg != ProcessingLeg::Prove) as u64,
+        }
+    }
+}
+
+impl Encode for ProcessedLeg {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = self.timestamp.write_to(writer)?;
+        written += self.destination.write_to(writer)?;
+        written += self.leaf_index.write_to(writer)?;
+        written += self.leg.code().write_to(writer)?;
+        written += self.gas_used.write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for ProcessedLeg {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let timestamp = u64::read_from(reader)?;
+        let destination = u32::read_from(reader)?;
+        let leaf_index = u32::read_from(reader)?;
+        let code = u32::read_from(reader)?;
+        let leg = ProcessingLeg::from_code(code).ok_or_else(|| {
+            NomadError::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unknown processing leg {}", code),
+            ))
+        })?;
+        Ok(Self {
+            timestamp,
+            destination,
+            leaf_index,
+            leg,
+            gas_used: u64::read_from(reader)?,
+        })
+    }
+}
 
 /// DB handle for storing data tied to a specific home.
 ///
 /// Key structure: ```<entity>_<additional_prefix(es)>_<key>```
+///
+/// Methods storing several keys write the record before the index pointing
+/// at it, so an interrupted store never leaves an index to a missing record.
+/// See [`DB`] for how async callers keep their writes cancellation safe.
 #[derive(Debug, Clone)]
 pub struct NomadDB(TypedDB);
 
@@ -53,14 +323,25 @@ impl AsRef<DB> for NomadDB {
 }
 
 impl NomadDB {
-    /// Instantiated new `NomadDB`
+    /// Instantiated new `NomadDB`. Reads of the latest root, leaf index,
+    /// prover and processor progress, and contract sync cursors are cached.
     pub fn new(entity: impl AsRef<str>, db: DB) -> Self {
-        Self(TypedDB::new(entity.as_ref().to_owned(), db))
+        let db = Self(TypedDB::new(entity.as_ref().to_owned(), db));
+        for prefix in HOT_KEY_SPACES {
+            db.cache_key_space(prefix);
+        }
+        db
+    }
+
+    /// Cache reads of keys under `prefix` for this entity. Writes through
+    /// any handle to the same db keep the cache current.
+    pub fn cache_key_space(&self, prefix: impl AsRef<[u8]>) {
+        self.0.cache_prefix(prefix)
     }
 
     /// Check if db is empty
     pub fn is_empty(&self) -> Result<bool> {
-        let no_updates = self.update_by_previous_root(H256::zero())?.is_none();
+        let no_updates = self.update_by_previous_root(GENESIS_ROOT)?.is_none();
         let no_messages = self.leaf_by_leaf_index(0)?.is_none();
         Ok(no_updates && no_messages)
     }
@@ -83,12 +364,14 @@ impl NomadDB {
         Ok(())
     }
 
-    /// Store a raw committed message
+    /// Store a raw committed message. Refuses to store a message at a leaf
+    /// index already holding another leaf.
     ///
     /// Keys --> Values:
     /// - `destination_and_nonce` --> `leaf`
     /// - `leaf_index` --> `leaf`
     /// - `leaf` --> `message`
+    /// - `leaf` --> `dispatch timestamp`, if known
     pub fn store_raw_committed_message(&self, message: &RawCommittedMessage) -> Result<()> {
         let parsed = NomadMessage::read_from(&mut message.message.clone().as_slice())?;
 
@@ -96,6 +379,17 @@ impl NomadDB {
 
         let leaf = message.leaf();
 
+        if let Some(stored) = self.leaf_by_leaf_index(message.leaf_index)? {
+            if stored != leaf {
+                return Err(MessageIntegrityError::LeafRewritten {
+                    leaf_index: message.leaf_index,
+                    stored,
+                    indexed: leaf,
+                }
+                .into());
+            }
+        }
+
         debug!(
             leaf = ?leaf,
             destination_and_nonce,
@@ -104,13 +398,19 @@ impl NomadDB {
             leaf_index = message.leaf_index,
             "storing raw committed message in db"
         );
-        self.store_leaf(message.leaf_index, destination_and_nonce, leaf)?;
         self.store_keyed_encodable(MESSAGE, &leaf, message)?;
+        if let Some(timestamp) = message.timestamp {
+            self.store_keyed_encodable(MESSAGE_TIMESTAMP, &leaf, &timestamp)?;
+        }
+        self.store_leaf(message.leaf_index, destination_and_nonce, leaf)?;
         Ok(())
     }
 
     /// Store a raw committed message building off of the latest leaf index
     pub fn store_latest_message(&self, message: &RawCommittedMessage) -> Result<()> {
+        // The message goes in before the latest leaf index moves to it
+        self.store_raw_committed_message(message)?;
+
         // If there is no latest root, or if this update is on the latest root
         // update latest root
         match self.retrieve_latest_leaf_index()? {
@@ -128,7 +428,7 @@ impl NomadDB {
             None => self.update_latest_leaf_index(message.leaf_index)?,
         }
 
-        self.store_raw_committed_message(message)
+        Ok(())
     }
 
     /// Store the latest known leaf_index
@@ -159,9 +459,15 @@ impl NomadDB {
         self.store_keyed_encodable(LEAF, &leaf_index, &leaf)
     }
 
-    /// Retrieve a raw committed message by its leaf hash
+    /// Retrieve a raw committed message by its leaf hash, with its dispatch
+    /// timestamp if it was indexed
     pub fn message_by_leaf(&self, leaf: H256) -> Result<Option<RawCommittedMessage>, DbError> {
-        self.retrieve_keyed_decodable(MESSAGE, &leaf)
+        let mut message: Option<RawCommittedMessage> =
+            self.retrieve_keyed_decodable(MESSAGE, &leaf)?;
+        if let Some(message) = message.as_mut() {
+            message.timestamp = self.retrieve_keyed_decodable(MESSAGE_TIMESTAMP, &leaf)?;
+        }
+        Ok(message)
     }
 
     /// Retrieve the leaf hash keyed by leaf index
@@ -200,6 +506,33 @@ impl NomadDB {
         }
     }
 
+    /// Retrieve a raw committed message by its id. `None` if the message at
+    /// its leaf index was dispatched on another domain, as this db indexes a
+    /// single home.
+    pub fn message_by_id(&self, id: MessageId) -> Result<Option<RawCommittedMessage>, DbError> {
+        let message = match self.message_by_leaf_index(id.leaf_index)? {
+            Some(message) => message,
+            None => return Ok(None),
+        };
+        let origin = NomadMessage::read_from(&mut message.message.as_slice())?.origin;
+        if origin == id.origin {
+            Ok(Some(message))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Retrieve a raw committed message by its leaf hash or by its id
+    pub fn message_by_ref(
+        &self,
+        message: MessageRef,
+    ) -> Result<Option<RawCommittedMessage>, DbError> {
+        match message {
+            MessageRef::Leaf(leaf) => self.message_by_leaf(leaf),
+            MessageRef::Id(id) => self.message_by_id(id),
+        }
+    }
+
     /// Store the latest committed
     fn store_latest_root(&self, root: H256) -> Result<(), DbError> {
         debug!(root = ?root, "storing new latest root in DB");
@@ -229,6 +562,48 @@ impl NomadDB {
         Ok(())
     }
 
+    /// Record that `source` observed `update` and store it if no source did
+    /// before. A duplicate observed in a different block, e.g. after a
+    /// reorg, only refreshes the update's metadata.
+    ///
+    /// Keys --> Values:
+    /// - `SEEN_UPDATES` --> `seen updates`
+    pub fn observe_update(
+        &self,
+        source: &str,
+        update: &SignedUpdateWithMeta,
+    ) -> Result<Observation> {
+        let mut seen = self.retrieve_seen_updates()?;
+        let observation = seen.observe(source, update);
+
+        match observation {
+            Observation::New => self.store_updates_and_meta(std::slice::from_ref(update))?,
+            Observation::Duplicate { moved: true } => self.store_update_metadata(update)?,
+            Observation::Duplicate { moved: false } => {}
+        }
+        self.store_encodable("", SEEN_UPDATES, &seen)?;
+
+        Ok(observation)
+    }
+
+    /// Retrieve the set of recently observed updates
+    pub fn retrieve_seen_updates(&self) -> Result<SeenUpdates, DbError> {
+        Ok(self
+            .retrieve_decodable("", SEEN_UPDATES)?
+            .unwrap_or_default())
+    }
+
+    /// Drop updates observed below `block_number` from the seen-set. Returns
+    /// how many were dropped.
+    pub fn prune_seen_updates(&self, block_number: u64) -> Result<usize, DbError> {
+        let mut seen = self.retrieve_seen_updates()?;
+        let pruned = seen.prune_below(block_number);
+        if pruned > 0 {
+            self.store_encodable("", SEEN_UPDATES, &seen)?;
+        }
+        Ok(pruned)
+    }
+
     /// Store update metadata (by update's new root)
     ///
     /// Keys --> Values:
@@ -314,6 +689,194 @@ impl NomadDB {
         }
     }
 
+    /// Retrieve up to `limit` of the most recent updates, newest first, by
+    /// walking back from the latest root
+    pub fn recent_updates(&self, limit: usize) -> Result<Vec<SignedUpdate>, DbError> {
+        let mut updates = vec![];
+        let mut root = match self.retrieve_latest_root()? {
+            Some(root) => root,
+            None => return Ok(updates),
+        };
+
+        while updates.len() < limit {
+            match self.update_by_new_root(root)? {
+                Some(update) => {
+                    root = update.update.previous_root;
+                    updates.push(update);
+                }
+                None => break,
+            }
+        }
+
+        Ok(updates)
+    }
+
+    /// Collect the stored updates leading from `start` to `target`, following
+    /// updates by previous root. Returns `None` if a link is missing or the
+    /// chain would be longer than `max_len`.
+    pub fn update_chain_between(
+        &self,
+        start: H256,
+        target: H256,
+        max_len: usize,
+    ) -> Result<Option<Vec<SignedUpdate>>, DbError> {
+        let mut updates = vec![];
+        let mut root = start;
+
+        while root != target {
+            if updates.len() == max_len {
+                return Ok(None);
+            }
+
+            match self.update_by_previous_root(root)? {
+                Some(update) => {
+                    root = update.update.new_root;
+                    updates.push(update);
+                }
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(updates))
+    }
+
+    /// Rewrite stored updates, accepted and produced, whose signature has v 0
+    /// or 1 with v 27 or 28. Reads normalize signatures regardless; this
+    /// brings records written before normalization in line with later ones.
+    /// Relay records are normalized as they are rewritten. Returns how many
+    /// records were rewritten.
+    pub fn normalize_update_signatures(&self) -> Result<usize, DbError> {
+        let mut normalized = 0;
+        for prefix in [UPDATE, UPDATER_PRODUCED_UPDATE] {
+            // `update_` also prefixes other key spaces, whose keys are longer
+            let mut stale = vec![];
+            for entry in self.raw_entries(prefix) {
+                let (key, value) = entry?;
+                if key.len() == 32
+                    && value.len() == SIGNED_UPDATE_LENGTH
+                    && !CanonicalSignature::is_canonical(&value[UPDATE_LENGTH..])
+                {
+                    stale.push((key, value));
+                }
+            }
+            for (key, value) in stale {
+                let update = SignedUpdate::read_from(&mut value.as_slice())?;
+                self.store_keyed_encodable(prefix, &H256::from_slice(&key), &update)?;
+                normalized += 1;
+            }
+        }
+        Ok(normalized)
+    }
+
+    /// Store the update chain relayed to a replica in a single submission
+    ///
+    /// Keys --> Values:
+    /// - `replica` + `new_root` --> `update chain`
+    pub fn store_relay_record(&self, replica: &str, chain: &UpdateChain) -> Result<(), DbError> {
+        self.store_keyed_encodable(
+            format!("{}{}_", RELAY_RECORD, replica),
+            &chain.latest().update.new_root,
+            chain,
+        )
+    }
+
+    /// Retrieve the update chain relayed to a replica for `new_root` (if one
+    /// exists)
+    pub fn retrieve_relay_record(
+        &self,
+        replica: &str,
+        new_root: H256,
+    ) -> Result<Option<UpdateChain>, DbError> {
+        self.retrieve_keyed_decodable(format!("{}{}_", RELAY_RECORD, replica), &new_root)
+    }
+
+    /// Store the unix time at which this agent submitted the update with
+    /// `new_root` to a replica
+    ///
+    /// Keys --> Values:
+    /// - `replica` + `new_root` --> `submitted_at`
+    pub fn store_relay_submission(
+        &self,
+        replica: &str,
+        new_root: H256,
+        submitted_at: u64,
+    ) -> Result<(), DbError> {
+        self.store_keyed_encodable(
+            format!("{}{}_", RELAY_SUBMISSION, replica),
+            &new_root,
+            &submitted_at,
+        )
+    }
+
+    /// Retrieve the unix time at which this agent submitted the update with
+    /// `new_root` to a replica (if it did)
+    pub fn retrieve_relay_submission(
+        &self,
+        replica: &str,
+        new_root: H256,
+    ) -> Result<Option<u64>, DbError> {
+        self.retrieve_keyed_decodable(format!("{}{}_", RELAY_SUBMISSION, replica), &new_root)
+    }
+
+    /// Store the replica root relaying to a replica continues from
+    ///
+    /// Keys --> Values:
+    /// - `replica` --> `root`
+    pub fn store_relay_cursor(&self, replica: &str, root: H256) -> Result<(), DbError> {
+        self.store_encodable(RELAY_CURSOR, replica, &root)
+    }
+
+    /// Retrieve the replica root relaying to a replica continues from (if
+    /// relaying to it ever started)
+    pub fn retrieve_relay_cursor(&self, replica: &str) -> Result<Option<H256>, DbError> {
+        self.retrieve_decodable(RELAY_CURSOR, replica)
+    }
+
+    /// Record that `leaf` was indexed at an occurrence's leaf index. Leaves
+    /// recorded at more than one index are listed as duplicates.
+    ///
+    /// Keys --> Values:
+    /// - `leaf` and `leaf_index` --> `occurrence`
+    /// - `leaf` --> number of leaf indexes, for duplicates
+    pub fn record_leaf_occurrence(
+        &self,
+        leaf: H256,
+        occurrence: LeafOccurrence,
+    ) -> Result<(), DbError> {
+        self.store_keyed_encodable(
+            Self::occurrence_prefix(leaf),
+            &occurrence.leaf_index,
+            &occurrence,
+        )?;
+        let indexes = self.leaf_occurrences(leaf)?.len() as u32;
+        if indexes > 1 {
+            self.store_keyed_encodable(DUPLICATE_LEAF, &leaf, &indexes)?;
+        }
+        Ok(())
+    }
+
+    /// Recorded occurrences of `leaf`, by leaf index
+    pub fn leaf_occurrences(&self, leaf: H256) -> Result<Vec<LeafOccurrence>, DbError> {
+        self.iterate_from(Self::occurrence_prefix(leaf), b"")
+            .collect()
+    }
+
+    /// Leaves recorded at more than one leaf index, with their number of
+    /// indexes
+    pub fn duplicate_leaves(&self) -> impl Iterator<Item = Result<(H256, u32), DbError>> + '_ {
+        self.raw_entries(DUPLICATE_LEAF).map(|entry| {
+            let (key, value) = entry?;
+            Ok((
+                H256::read_from(&mut key.as_slice())?,
+                u32::read_from(&mut value.as_slice())?,
+            ))
+        })
+    }
+
+    fn occurrence_prefix(leaf: H256) -> Vec<u8> {
+        [LEAF_OCCURRENCE.as_bytes(), leaf.as_bytes()].concat()
+    }
+
     /// Iterate over all leaves
     pub fn leaf_iterator(&self) -> PrefixIterator<H256> {
         PrefixIterator::new(self.0.as_ref().prefix_iterator(LEAF_IDX), LEAF_IDX.as_ref())
@@ -333,6 +896,29 @@ impl NomadDB {
         self.retrieve_keyed_decodable(PROOF, &leaf_index)
     }
 
+    /// Move a proof record out of the proof table, keeping its raw bytes
+    /// under a quarantine key. Returns false if there was no record.
+    pub fn quarantine_proof(&self, leaf_index: u32) -> Result<bool, DbError> {
+        self.move_keyed(PROOF, QUARANTINED_PROOF, &leaf_index)
+    }
+
+    /// Retrieve a proof by its leaf index. A record that fails to decode is
+    /// quarantined and reported as missing, so that it is regenerated.
+    pub fn proof_or_quarantine(&self, leaf_index: u32) -> Result<Option<NomadProof>, DbError> {
+        match self.proof_by_leaf_index(leaf_index) {
+            Err(DbError::NomadError(error)) => {
+                warn!(
+                    leaf_index,
+                    error = %error,
+                    "Quarantining malformed proof record"
+                );
+                self.quarantine_proof(leaf_index)?;
+                Ok(None)
+            }
+            result => result,
+        }
+    }
+
     // TODO(james): this is a quick-fix for the prover_sync and I don't like it
     /// poll db ever 100 milliseconds waiting for a leaf.
     pub fn wait_for_leaf(&self, leaf_index: u32) -> impl Future<Output = Result<H256, DbError>> {
@@ -384,18 +970,400 @@ impl NomadDB {
     /// Returns `true` if the processor has previously attempted to process the
     /// mesage
     pub fn previously_attempted(&self, message: &CommittedMessage) -> Result<bool, DbError> {
-        match self.retrieve_decodable(PROCESSOR_ATTEMPTED, message.to_leaf())? {
+        self.leaf_previously_attempted(message.to_leaf())
+    }
+
+    /// Returns `true` if the processor has previously attempted to process the
+    /// message with leaf `leaf`
+    pub fn leaf_previously_attempted(&self, leaf: H256) -> Result<bool, DbError> {
+        match self.retrieve_decodable(PROCESSOR_ATTEMPTED, leaf)? {
             Some(inner) => Ok(inner),
             None => Ok(false),
         }
     }
+
+    /// Record that a message was processed but its handler reverted, i.e.
+    /// the replica emitted `Process` with `success = false`
+    ///
+    /// Keys --> Values:
+    /// - `leaf` --> `true`
+    pub fn set_processed_failed(&self, message: &CommittedMessage) -> Result<(), DbError> {
+        self.store_encodable(PROCESSED_FAILED, message.to_leaf(), &true)
+    }
+
+    /// Returns `true` if the message was recorded as processed with a failed
+    /// handler
+    pub fn processed_failed(&self, leaf: H256) -> Result<bool, DbError> {
+        Ok(self
+            .retrieve_decodable(PROCESSED_FAILED, leaf)?
+            .unwrap_or_default())
+    }
+
+    /// Record that processing a message was skipped, for needing more gas
+    /// than the cap. Unlike an attempt, this is retried on restart.
+    ///
+    /// Keys --> Values:
+    /// - `destination || leaf_index` --> `true`
+    pub fn set_gas_capped(&self, message: &CommittedMessage) -> Result<(), DbError> {
+        self.store_keyed_encodable(
+            Self::gas_capped_prefix(message.message.destination),
+            &message.leaf_index,
+            &true,
+        )
+    }
+
+    /// Clear the record of a message skipped over the gas cap
+    pub fn clear_gas_capped(&self, message: &CommittedMessage) -> Result<(), DbError> {
+        self.delete_keyed(
+            Self::gas_capped_prefix(message.message.destination),
+            &message.leaf_index,
+        )
+    }
+
+    /// Returns `true` if processing the message was skipped over the gas cap
+    pub fn gas_capped(&self, message: &CommittedMessage) -> Result<bool, DbError> {
+        Ok(self
+            .retrieve_keyed_decodable(
+                Self::gas_capped_prefix(message.message.destination),
+                &message.leaf_index,
+            )?
+            .unwrap_or_default())
+    }
+
+    /// Leaf indexes of the messages to `destination` skipped over the gas
+    /// cap, ascending
+    pub fn gas_capped_leaf_indexes(&self, destination: u32) -> Result<Vec<u32>, DbError> {
+        self.raw_entries(Self::gas_capped_prefix(destination))
+            .map(|entry| {
+                let (key, _) = entry?;
+                Ok(u32::read_from(&mut key.as_slice())?)
+            })
+            .collect()
+    }
+
+    fn gas_capped_prefix(destination: u32) -> Vec<u8> {
+        [GAS_CAPPED.as_bytes(), &destination.to_be_bytes()].concat()
+    }
+
+    /// Record the root a message is submitted for processing under
+    ///
+    /// Keys --> Values:
+    /// - `leaf` --> `processing root`
+    pub fn store_processing_root(
+        &self,
+        message: &CommittedMessage,
+        record: &ProcessingRoot,
+    ) -> Result<(), DbError> {
+        self.store_encodable(PROCESSING_ROOT, message.to_leaf(), record)
+    }
+
+    /// Retrieve the root the message with leaf `leaf` was submitted for
+    /// processing under, if recorded
+    pub fn processing_root(&self, leaf: H256) -> Result<Option<ProcessingRoot>, DbError> {
+        self.retrieve_decodable(PROCESSING_ROOT, leaf)
+    }
+
+    /// Record that the message at `leaf_index` to `destination` was
+    /// confirmed processed. Only a later leaf than the one recorded moves
+    /// the record, so messages confirmed out of order do not rewind it.
+    ///
+    /// Keys --> Values:
+    /// - `destination` --> `leaf_index`
+    pub fn store_latest_processed_leaf(
+        &self,
+        destination: u32,
+        leaf_index: u32,
+    ) -> Result<(), DbError> {
+        match self.latest_processed_leaf_for_destination(destination)? {
+            Some(latest) if latest >= leaf_index => Ok(()),
+            _ => self.store_keyed_encodable(LATEST_PROCESSED_LEAF, &destination, &leaf_index),
+        }
+    }
+
+    /// Retrieve the leaf index of the latest message to `destination`
+    /// confirmed processed, if any
+    pub fn latest_processed_leaf_for_destination(
+        &self,
+        destination: u32,
+    ) -> Result<Option<u32>, DbError> {
+        self.retrieve_keyed_decodable(LATEST_PROCESSED_LEAF, &destination)
+    }
+
+    /// Store the watcher frontier for a contract: the latest root up to which
+    /// all updates on that contract have been checked for fraud
+    ///
+    /// Keys --> Values:
+    /// - `contract_name` --> `root`
+    pub fn store_watcher_frontier(&self, contract: &str, root: H256) -> Result<(), DbError> {
+        self.store_encodable(WATCHER_FRONTIER, contract, &root)
+    }
+
+    /// Retrieve the watcher frontier for a contract (if one exists)
+    pub fn retrieve_watcher_frontier(&self, contract: &str) -> Result<Option<H256>, DbError> {
+        self.retrieve_decodable(WATCHER_FRONTIER, contract)
+    }
+
+    /// Store the watcher audit checkpoint for a contract: the root from
+    /// which all history back to the zero root has been audited for fraud
+    ///
+    /// Keys --> Values:
+    /// - `contract_name` --> `root`
+    pub fn store_watcher_audit_checkpoint(
+        &self,
+        contract: &str,
+        root: H256,
+    ) -> Result<(), DbError> {
+        self.store_encodable(WATCHER_AUDIT_CHECKPOINT, contract, &root)
+    }
+
+    /// Retrieve the watcher audit checkpoint for a contract (if one exists)
+    pub fn retrieve_watcher_audit_checkpoint(
+        &self,
+        contract: &str,
+    ) -> Result<Option<H256>, DbError> {
+        self.retrieve_decodable(WATCHER_AUDIT_CHECKPOINT, contract)
+    }
+
+    /// Store sorted advances of the replica's committed root. The latest
+    /// advance moves to the last one stored unless it is in an earlier block
+    /// than the current latest. The `Genesis` advance to the root the replica
+    /// was initialized with is also kept as its genesis advance.
+    ///
+    /// Keys --> Values:
+    /// - `root` --> `advance`
+    /// - `LATEST_ROOT_ADVANCE` --> `advance`
+    /// - `GENESIS_ROOT_ADVANCE` --> `advance`
+    pub fn store_root_advances(&self, advances: &[RootAdvance]) -> Result<(), DbError> {
+        let mut latest = self.latest_root_advance()?;
+        for advance in advances {
+            debug!(
+                root = ?advance.root,
+                provenance = %advance.provenance,
+                block_number = advance.block_number,
+                "storing root advance in DB"
+            );
+            self.store_keyed_encodable(ROOT_ADVANCE, &advance.root, advance)?;
+            if advance.provenance == RootProvenance::Genesis {
+                self.store_encodable("", GENESIS_ROOT_ADVANCE, advance)?;
+            }
+            if latest.map_or(true, |latest| advance.block_number >= latest.block_number) {
+                self.store_encodable("", LATEST_ROOT_ADVANCE, advance)?;
+                latest = Some(*advance);
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieve the advance of the replica's committed root to `root`, if
+    /// recorded
+    pub fn root_advance(&self, root: H256) -> Result<Option<RootAdvance>, DbError> {
+        self.retrieve_keyed_decodable(ROOT_ADVANCE, &root)
+    }
+
+    /// Retrieve the latest recorded advance of the replica's committed root
+    pub fn latest_root_advance(&self) -> Result<Option<RootAdvance>, DbError> {
+        self.retrieve_decodable("", LATEST_ROOT_ADVANCE)
+    }
+
+    /// Retrieve the advance to the root the replica was initialized with, if
+    /// its initialization was indexed
+    pub fn genesis_root_advance(&self) -> Result<Option<RootAdvance>, DbError> {
+        self.retrieve_decodable("", GENESIS_ROOT_ADVANCE)
+    }
+
+    /// Store the block range cap learned for log queries to this chain's
+    /// provider
+    ///
+    /// Keys --> Values:
+    /// - `LOG_RANGE_CAP` --> `blocks`
+    pub fn store_log_range_cap(&self, blocks: u32) -> Result<(), DbError> {
+        self.store_encodable("", LOG_RANGE_CAP, &blocks)
+    }
+
+    /// Retrieve the block range cap learned for log queries to this chain's
+    /// provider
+    pub fn retrieve_log_range_cap(&self) -> Result<Option<u32>, DbError> {
+        self.retrieve_decodable("", LOG_RANGE_CAP)
+    }
+
+    /// Add the gas of a leg of processing a message to the totals of its
+    /// recipient on `destination` for the day of `timestamp`. Every leg
+    /// counts towards the recipient. The message counts once, with the leg
+    /// that processes it. The leg is also kept by `timestamp`, for totals
+    /// over any range.
+    ///
+    /// Keys --> Values:
+    /// - `day`, `destination` and `recipient` --> `totals`
+    /// - `timestamp`, `leaf_index` and `leg` --> `processed_leg`
+    pub fn record_recipient_gas(
+        &self,
+        destination: u32,
+        recipient: H256,
+        leaf_index: u32,
+        leg: ProcessingLeg,
+        gas_used: u64,
+        timestamp: u64,
+    ) -> Result<(), DbError> {
+        let processed = ProcessedLeg {
+            timestamp,
+            destination,
+            leaf_index,
+            leg,
+            gas_used,
+        };
+        self.store_keyed_encodable(
+            Self::processed_leg_prefix(timestamp, leaf_index),
+            &leg.code(),
+            &processed,
+        )?;
+
+        let prefix = Self::recipient_gas_prefix(timestamp / SECONDS_PER_DAY, destination);
+        let mut totals: RecipientGas = self
+            .retrieve_keyed_decodable(&prefix, &recipient)?
+            .unwrap_or_default();
+        totals.add(&RecipientGas {
+            gas_used,
+            transactions: 1,
+            messages: (leg != ProcessingLeg::Prove) as u64,
+        });
+        self.store_keyed_encodable(&prefix, &recipient, &totals)
+    }
+
+    /// Recipients on each destination with their totals summed from the day
+    /// of `since` on, by most gas used first
+    pub fn recipient_gas_since(
+        &self,
+        since: u64,
+    ) -> Result<Vec<(u32, H256, RecipientGas)>, DbError> {
+        let since_day = since / SECONDS_PER_DAY;
+        let mut summed: HashMap<(u32, H256), RecipientGas> = HashMap::new();
+        for entry in self.recipient_gas_entries() {
+            let (day, destination, recipient, totals) = entry?;
+            if day >= since_day {
+                summed
+                    .entry((destination, recipient))
+                    .or_default()
+                    .add(&totals);
+            }
+        }
+
+        let mut recipients: Vec<_> = summed
+            .into_iter()
+            .map(|((destination, recipient), totals)| (destination, recipient, totals))
+            .collect();
+        recipients.sort_by(|a, b| {
+            b.2.gas_used
+                .cmp(&a.2.gas_used)
+                .then((a.0, a.1).cmp(&(b.0, b.1)))
+        });
+        Ok(recipients)
+    }
+
+    /// Totals on each destination of the legs recorded from `from` until
+    /// `to`
+    pub fn destination_gas_between(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> Result<BTreeMap<u32, RecipientGas>, DbError> {
+        let mut summed: BTreeMap<u32, RecipientGas> = BTreeMap::new();
+        for processed in self.processed_legs_from(from) {
+            let processed = processed?;
+            if processed.timestamp >= to {
+                break;
+            }
+            summed
+                .entry(processed.destination)
+                .or_default()
+                .add(&processed.totals());
+        }
+        Ok(summed)
+    }
+
+    /// Delete recipient gas totals of days before the day of `before`, and
+    /// legs recorded before `before`. Returns the number of day totals
+    /// deleted.
+    pub fn prune_recipient_gas(&self, before: u64) -> Result<usize, DbError> {
+        let before_day = before / SECONDS_PER_DAY;
+        let mut stale = vec![];
+        for entry in self.recipient_gas_entries() {
+            let (day, destination, recipient, _) = entry?;
+            if day < before_day {
+                stale.push((day, destination, recipient));
+            }
+        }
+        for (day, destination, recipient) in stale.iter() {
+            self.delete_keyed(Self::recipient_gas_prefix(*day, *destination), recipient)?;
+        }
+
+        let mut stale_legs = vec![];
+        for processed in self.processed_legs_from(0) {
+            let processed = processed?;
+            if processed.timestamp >= before {
+                break;
+            }
+            stale_legs.push(processed);
+        }
+        for processed in stale_legs {
+            self.delete_keyed(
+                Self::processed_leg_prefix(processed.timestamp, processed.leaf_index),
+                &processed.leg.code(),
+            )?;
+        }
+        Ok(stale.len())
+    }
+
+    /// Legs recorded at or after `from`, oldest first
+    fn processed_legs_from(
+        &self,
+        from: u64,
+    ) -> impl Iterator<Item = Result<ProcessedLeg, DbError>> + '_ {
+        self.iterate_from(PROCESSED_LEG, from.to_be_bytes())
+    }
+
+    /// Recipient gas totals by day, destination and recipient, in key order
+    fn recipient_gas_entries(
+        &self,
+    ) -> impl Iterator<Item = Result<(u64, u32, H256, RecipientGas), DbError>> + '_ {
+        self.raw_entries(RECIPIENT_GAS).map(|entry| {
+            let (key, value) = entry?;
+            let mut key = key.as_slice();
+            Ok((
+                u64::read_from(&mut key)?,
+                u32::read_from(&mut key)?,
+                H256::read_from(&mut key)?,
+                RecipientGas::read_from(&mut value.as_slice())?,
+            ))
+        })
+    }
+
+    fn processed_leg_prefix(timestamp: u64, leaf_index: u32) -> Vec<u8> {
+        [
+            PROCESSED_LEG.as_bytes(),
+            &timestamp.to_be_bytes(),
+            &leaf_index.to_be_bytes(),
+        ]
+        .concat()
+    }
+
+    fn recipient_gas_prefix(day: u64, destination: u32) -> Vec<u8> {
+        [
+            RECIPIENT_GAS.as_bytes(),
+            &day.to_be_bytes(),
+            &destination.to_be_bytes(),
+        ]
+        .concat()
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use ethers::types::H256;
-    use nomad_core::{accumulator::Proof, Encode, NomadMessage, RawCommittedMessage};
+    use nomad_core::{
+        accumulator::{Proof, ProofLengthError},
+        format_h256, Encode, NomadError, NomadMessage, RawCommittedMessage,
+    };
     use nomad_test::test_utils::run_test_db;
 
     #[tokio::test]
@@ -417,6 +1385,7 @@ mod test {
                 leaf_index: 100,
                 committed_root: H256::from_low_u64_be(3),
                 message: m.to_vec(),
+                timestamp: Some(1_700_000_000),
             };
             assert_eq!(m.to_leaf(), message.leaf());
 
@@ -440,6 +1409,136 @@ mod test {
         .await;
     }
 
+    #[tokio::test]
+    async fn db_looks_up_messages_by_id_and_by_leaf() {
+        run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+
+            let messages: Vec<_> = (0..8u32)
+                .map(|leaf_index| {
+                    let m = NomadMessage {
+                        origin: 2000,
+                        sender: H256::from_low_u64_be(4),
+                        nonce: leaf_index / 2,
+                        destination: 1650811245 + leaf_index % 2,
+                        recipient: H256::from_low_u64_be(5),
+                        body: leaf_index.to_be_bytes().to_vec(),
+                    };
+                    RawCommittedMessage {
+                        leaf_index,
+                        committed_root: H256::from_low_u64_be(leaf_index.into()),
+                        message: m.to_vec(),
+                        timestamp: None,
+                    }
+                })
+                .collect();
+            db.store_messages(&messages).unwrap();
+
+            for message in &messages {
+                let committed = CommittedMessage::try_from(message.clone()).unwrap();
+                let id = committed.message_id();
+                assert_eq!(id, MessageId::new(2000, message.leaf_index));
+
+                // hash --> id --> hash
+                let by_leaf = db.message_by_leaf(message.leaf()).unwrap().unwrap();
+                let by_id = db
+                    .message_by_id(CommittedMessage::try_from(by_leaf).unwrap().message_id())
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(by_id.leaf(), message.leaf());
+
+                // id --> hash --> id, through their text forms
+                for text in [id.to_string(), format_h256(&message.leaf())] {
+                    let found = db.message_by_ref(text.parse().unwrap()).unwrap().unwrap();
+                    assert_eq!(&found, message);
+                }
+            }
+
+            // ids of other domains and unknown leaves find nothing
+            assert_eq!(db.message_by_id(MessageId::new(1000, 3)).unwrap(), None);
+            assert_eq!(db.message_by_id(MessageId::new(2000, 8)).unwrap(), None);
+            assert_eq!(
+                db.message_by_ref(MessageRef::Leaf(H256::repeat_byte(1)))
+                    .unwrap(),
+                None
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn db_tracks_leaves_at_several_indexes() {
+        run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+
+            let m = NomadMessage {
+                origin: 10,
+                sender: H256::from_low_u64_be(4),
+                nonce: 11,
+                destination: 12,
+                recipient: H256::from_low_u64_be(5),
+                body: vec![1, 2, 3],
+            };
+            let message = |leaf_index| RawCommittedMessage {
+                leaf_index,
+                committed_root: H256::zero(),
+                message: m.to_vec(),
+                timestamp: None,
+            };
+            let leaf = m.to_leaf();
+
+            for (leaf_index, from_block) in [(4, 100), (9, 200)] {
+                db.store_raw_committed_message(&message(leaf_index))
+                    .unwrap();
+                db.record_leaf_occurrence(
+                    leaf,
+                    LeafOccurrence {
+                        leaf_index,
+                        from_block,
+                        to_block: from_block + 50,
+                    },
+                )
+                .unwrap();
+                // indexing the same page again adds nothing
+                db.record_leaf_occurrence(
+                    leaf,
+                    LeafOccurrence {
+                        leaf_index,
+                        from_block,
+                        to_block: from_block + 50,
+                    },
+                )
+                .unwrap();
+            }
+
+            let occurrences = db.leaf_occurrences(leaf).unwrap();
+            assert_eq!(
+                occurrences.iter().map(|o| o.leaf_index).collect::<Vec<_>>(),
+                vec![4, 9]
+            );
+            assert_eq!(occurrences[1].from_block, 200);
+            let duplicates: Vec<_> = db.duplicate_leaves().collect::<Result<_, _>>().unwrap();
+            assert_eq!(duplicates, vec![(leaf, 2)]);
+
+            // a leaf index is never rewritten with another leaf
+            let other = RawCommittedMessage {
+                message: NomadMessage {
+                    nonce: 12,
+                    ..m.clone()
+                }
+                .to_vec(),
+                ..message(4)
+            };
+            let error = db.store_raw_committed_message(&other).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<MessageIntegrityError>(),
+                Some(MessageIntegrityError::LeafRewritten { leaf_index: 4, .. })
+            ));
+            assert_eq!(db.leaf_by_leaf_index(4).unwrap(), Some(leaf));
+        })
+        .await;
+    }
+
     #[tokio::test]
     async fn db_stores_and_retrieves_proofs() {
         run_test_db(|db| async move {
@@ -458,4 +1557,483 @@ mod test {
         })
         .await;
     }
+
+    /// Raw bytes stored without an encoding
+    struct Raw(Vec<u8>);
+
+    impl Decode for Raw {
+        fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+        where
+            R: std::io::Read,
+        {
+            let mut buf = vec![];
+            reader.read_to_end(&mut buf)?;
+            Ok(Self(buf))
+        }
+    }
+
+    impl Encode for Raw {
+        fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+        where
+            W: std::io::Write,
+        {
+            writer.write_all(&self.0)?;
+            Ok(self.0.len())
+        }
+    }
+
+    fn malformed_proofs() -> serde_json::Value {
+        let fixture = std::fs::read_to_string("../fixtures/malformed_proofs.json").unwrap();
+        serde_json::from_str(&fixture).unwrap()
+    }
+
+    #[tokio::test]
+    async fn db_quarantines_malformed_proofs() {
+        run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let valid = Proof {
+                leaf: H256::from_low_u64_be(15),
+                index: 3,
+                path: Default::default(),
+            };
+
+            for (leaf_index, case) in malformed_proofs()["encoded"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .enumerate()
+            {
+                let name = case["name"].as_str().unwrap();
+                let bytes = ethers::utils::hex::decode(case["bytes"].as_str().unwrap()).unwrap();
+                let leaf_index = leaf_index as u32;
+                db.store_keyed_encodable(PROOF, &leaf_index, &Raw(bytes.clone()))
+                    .unwrap();
+
+                let err = db.proof_by_leaf_index(leaf_index).unwrap_err();
+                match (case["got"].as_u64(), err) {
+                    (Some(got), DbError::NomadError(NomadError::ProofLength(e))) => {
+                        assert_eq!(e.expected, 32, "{}", name);
+                        assert_eq!(e.got as u64, got, "{}", name);
+                    }
+                    (None, DbError::NomadError(NomadError::IoError(_))) => {}
+                    (_, err) => panic!("{}: unexpected error {:?}", name, err),
+                }
+
+                assert_eq!(
+                    db.proof_or_quarantine(leaf_index).unwrap(),
+                    None,
+                    "{}",
+                    name
+                );
+                assert_eq!(
+                    db.proof_by_leaf_index(leaf_index).unwrap(),
+                    None,
+                    "{}",
+                    name
+                );
+                assert!(!db.quarantine_proof(leaf_index).unwrap(), "{}", name);
+                let quarantined: Raw = db
+                    .retrieve_keyed_decodable(QUARANTINED_PROOF, &leaf_index)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(quarantined.0, bytes, "{}", name);
+
+                // the regenerated proof replaces the quarantined record
+                db.store_proof(leaf_index, &valid).unwrap();
+                assert_eq!(
+                    db.proof_or_quarantine(leaf_index).unwrap(),
+                    Some(valid),
+                    "{}",
+                    name
+                );
+            }
+        })
+        .await;
+    }
+
+    #[test]
+    fn it_rejects_malformed_proof_paths() {
+        for case in malformed_proofs()["json"].as_array().unwrap() {
+            let name = case["name"].as_str().unwrap();
+            let got = case["got"].as_u64().unwrap() as usize;
+            let expected = ProofLengthError { expected: 32, got };
+
+            let err = serde_json::from_value::<NomadProof>(case["proof"].clone()).unwrap_err();
+            assert!(err.to_string().contains(&expected.to_string()), "{}", name);
+
+            let path: Vec<H256> = serde_json::from_value(case["proof"]["path"].clone()).unwrap();
+            assert_eq!(
+                NomadProof::from_slice(H256::zero(), 7, &path).unwrap_err(),
+                expected,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn db_cache_reads_its_own_writes() {
+        run_test_db(|db| async move {
+            let writer = NomadDB::new("home_1", db.clone());
+            let reader = NomadDB::new("home_1", db.clone());
+            let lookups = db.cache_lookups();
+
+            let writing = std::thread::spawn(move || {
+                for index in 0..500u32 {
+                    writer.update_latest_leaf_index(index).unwrap();
+                    assert_eq!(writer.retrieve_latest_leaf_index().unwrap(), Some(index));
+                }
+            });
+            let reading = std::thread::spawn(move || {
+                let mut last = None;
+                for _ in 0..500 {
+                    let index = reader.retrieve_latest_leaf_index().unwrap();
+                    assert!(index >= last, "read {:?} after {:?}", index, last);
+                    last = index;
+                }
+            });
+            writing.join().unwrap();
+            reading.join().unwrap();
+
+            let other = NomadDB::new("home_1", db);
+            assert_eq!(other.retrieve_latest_leaf_index().unwrap(), Some(499));
+            assert!(lookups.with_label_values(&["hit"]).get() > 0);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn it_normalizes_stored_update_signatures() {
+        run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let wallet: ethers::signers::LocalWallet = "1".repeat(64).parse().unwrap();
+            let sign = |previous_root: H256, new_root: H256| {
+                nomad_core::Update {
+                    home_domain: 1000,
+                    previous_root,
+                    new_root,
+                }
+                .sign_with(&wallet)
+            };
+            let (root_0, root_1, root_2) =
+                (H256::zero(), H256::repeat_byte(1), H256::repeat_byte(2));
+            let legacy = sign(root_0, root_1).await.unwrap();
+            let canonical = sign(root_1, root_2).await.unwrap();
+
+            // The legacy update was stored with v 0 or 1
+            let mut legacy_bytes = legacy.to_vec();
+            legacy_bytes[SIGNED_UPDATE_LENGTH - 1] -= 27;
+            db.store_keyed_encodable(UPDATE, &root_0, &Raw(legacy_bytes.clone()))
+                .unwrap();
+            db.store_keyed_encodable(PREV_ROOT, &root_1, &root_0)
+                .unwrap();
+            db.store_keyed_encodable(UPDATER_PRODUCED_UPDATE, &root_0, &Raw(legacy_bytes))
+                .unwrap();
+            db.store_latest_update(&canonical).unwrap();
+            let raw = |prefix: &str, key: H256| {
+                db.retrieve_keyed_decodable::<_, Raw>(prefix, &key)
+                    .unwrap()
+                    .unwrap()
+                    .0
+            };
+
+            // Reads normalize before the migration runs
+            assert_eq!(db.update_by_new_root(root_1).unwrap(), Some(legacy.clone()));
+            assert_ne!(raw(UPDATE, root_0), legacy.to_vec());
+
+            assert_eq!(db.normalize_update_signatures().unwrap(), 2);
+            assert_eq!(raw(UPDATE, root_0), legacy.to_vec());
+            assert_eq!(raw(UPDATER_PRODUCED_UPDATE, root_0), legacy.to_vec());
+            assert_eq!(raw(UPDATE, root_1), canonical.to_vec());
+            assert_eq!(
+                db.retrieve_produced_update(root_0).unwrap(),
+                Some(legacy.clone())
+            );
+            assert_eq!(db.recent_updates(5).unwrap(), vec![canonical, legacy]);
+
+            // Other key spaces under `update_` are untouched, and a second
+            // run finds nothing to do
+            assert_eq!(db.retrieve_latest_root().unwrap(), Some(root_2));
+            assert_eq!(db.normalize_update_signatures().unwrap(), 0);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn db_keeps_the_latest_root_advance() {
+        run_test_db(|db| async move {
+            let db = NomadDB::new("replica_1", db);
+            let advance = |root: u8, provenance, block_number| RootAdvance {
+                root: H256::repeat_byte(root),
+                provenance,
+                block_number,
+                timestamp: Some(1_000 + block_number),
+                tx_hash: H256::repeat_byte(root + 100),
+            };
+            assert_eq!(db.latest_root_advance().unwrap(), None);
+
+            let update = advance(1, RootProvenance::Update, 10);
+            let governance = advance(2, RootProvenance::Governance, 12);
+            db.store_root_advances(&[update, governance]).unwrap();
+            assert_eq!(db.latest_root_advance().unwrap(), Some(governance));
+            assert_eq!(db.root_advance(update.root).unwrap(), Some(update));
+
+            // a backfilled advance is recorded without moving the latest
+            let backfilled = RootAdvance {
+                timestamp: None,
+                ..advance(3, RootProvenance::Update, 5)
+            };
+            db.store_root_advances(&[backfilled]).unwrap();
+            assert_eq!(db.root_advance(backfilled.root).unwrap(), Some(backfilled));
+            assert_eq!(db.latest_root_advance().unwrap(), Some(governance));
+
+            // the initialization root is kept as the genesis advance
+            assert_eq!(db.genesis_root_advance().unwrap(), None);
+            let genesis = advance(4, RootProvenance::Genesis, 1);
+            db.store_root_advances(&[genesis]).unwrap();
+            assert_eq!(db.genesis_root_advance().unwrap(), Some(genesis));
+            assert_eq!(db.root_advance(genesis.root).unwrap(), Some(genesis));
+            assert_eq!(db.latest_root_advance().unwrap(), Some(governance));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn db_keeps_the_latest_processed_leaf_per_destination() {
+        run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            assert_eq!(
+                db.latest_processed_leaf_for_destination(2000).unwrap(),
+                None
+            );
+
+            db.store_latest_processed_leaf(2000, 7).unwrap();
+            db.store_latest_processed_leaf(3000, 4).unwrap();
+            assert_eq!(
+                db.latest_processed_leaf_for_destination(2000).unwrap(),
+                Some(7)
+            );
+            assert_eq!(
+                db.latest_processed_leaf_for_destination(3000).unwrap(),
+                Some(4)
+            );
+
+            // an earlier leaf confirmed late does not rewind the record
+            db.store_latest_processed_leaf(2000, 5).unwrap();
+            assert_eq!(
+                db.latest_processed_leaf_for_destination(2000).unwrap(),
+                Some(7)
+            );
+            db.store_latest_processed_leaf(2000, 9).unwrap();
+            assert_eq!(
+                db.latest_processed_leaf_for_destination(2000).unwrap(),
+                Some(9)
+            );
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn db_totals_recipient_gas_by_day() {
+        run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let day = 24 * 60 * 60;
+            let (busy, quiet) = (H256::repeat_byte(1), H256::repeat_byte(2));
+
+            // split processing attributes both legs to one message
+            db.record_recipient_gas(2000, busy, 1, ProcessingLeg::Prove, 100_000, day)
+                .unwrap();
+            db.record_recipient_gas(2000, busy, 1, ProcessingLeg::Process, 150_000, day + 10)
+                .unwrap();
+            db.record_recipient_gas(2000, quiet, 2, ProcessingLeg::ProveAndProcess, 200_000, day)
+                .unwrap();
+            db.record_recipient_gas(
+                2000,
+                busy,
+                3,
+                ProcessingLeg::ProveAndProcess,
+                210_000,
+                2 * day,
+            )
+            .unwrap();
+            // the same recipient on another destination is totalled apart
+            db.record_recipient_gas(
+                3000,
+                busy,
+                4,
+                ProcessingLeg::ProveAndProcess,
+                90_000,
+                2 * day,
+            )
+            .unwrap();
+
+            let totals = db.recipient_gas_since(day).unwrap();
+            assert_eq!(
+                totals,
+                vec![
+                    (
+                        2000,
+                        busy,
+                        RecipientGas {
+                            gas_used: 460_000,
+                            transactions: 3,
+                            messages: 2,
+                        }
+                    ),
+                    (
+                        2000,
+                        quiet,
+                        RecipientGas {
+                            gas_used: 200_000,
+                            transactions: 1,
+                            messages: 1,
+                        }
+                    ),
+                    (
+                        3000,
+                        busy,
+                        RecipientGas {
+                            gas_used: 90_000,
+                            transactions: 1,
+                            messages: 1,
+                        }
+                    ),
+                ]
+            );
+            assert_eq!(totals[0].2.average_per_message(), Some(230_000));
+            assert_eq!(
+                db.recipient_gas_since(2 * day).unwrap()[0].2.gas_used,
+                210_000
+            );
+
+            // destination totals of the legs within a range, not whole days
+            let destinations = db.destination_gas_between(day + 5, 2 * day).unwrap();
+            assert_eq!(destinations.len(), 1);
+            assert_eq!(destinations[&2000].gas_used, 150_000);
+            assert_eq!(destinations[&2000].messages, 1);
+            let destinations = db.destination_gas_between(day, day + 10).unwrap();
+            assert_eq!(destinations[&2000].gas_used, 300_000);
+            assert_eq!(destinations[&2000].transactions, 2);
+            let destinations = db.destination_gas_between(day, 3 * day).unwrap();
+            assert_eq!(destinations[&2000].gas_used, 660_000);
+            assert_eq!(destinations[&3000].transactions, 1);
+
+            // pruning drops whole days
+            assert_eq!(db.prune_recipient_gas(2 * day).unwrap(), 2);
+            assert_eq!(db.recipient_gas_since(0).unwrap().len(), 2);
+            let destinations = db.destination_gas_between(0, 3 * day).unwrap();
+            assert_eq!(destinations[&2000].gas_used, 210_000);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn db_encrypts_values_at_rest() {
+        run_test_db(|db| async move {
+            use nomad_core::db::{DbCipher, EncryptionError};
+
+            let message = |leaf_index: u32| RawCommittedMessage {
+                leaf_index,
+                committed_root: H256::zero(),
+                message: NomadMessage {
+                    origin: 1000,
+                    sender: H256::repeat_byte(1),
+                    nonce: leaf_index,
+                    destination: 2000,
+                    recipient: H256::repeat_byte(2),
+                    body: vec![leaf_index as u8; 8],
+                }
+                .to_vec(),
+                timestamp: None,
+            };
+            let (legacy, sealed) = (message(0), message(1));
+            let (old_key, new_key) = ([1u8; 32], [2u8; 32]);
+            let encrypted_db = db.clone().with_cipher(DbCipher::new(&old_key).migrating());
+            let rotated_db = db
+                .clone()
+                .with_cipher(DbCipher::new(&new_key).with_read_key(&old_key));
+            let plain = NomadDB::new("home_1", db.clone());
+            let encrypted = NomadDB::new("home_1", encrypted_db.clone());
+            let strict = NomadDB::new("home_1", db.clone().with_cipher(DbCipher::new(&old_key)));
+
+            // values written before encryption stay readable while migrating
+            plain.store_raw_committed_message(&legacy).unwrap();
+            encrypted.store_raw_committed_message(&sealed).unwrap();
+            assert_eq!(
+                encrypted.message_by_leaf(legacy.leaf()).unwrap(),
+                Some(legacy.clone())
+            );
+            assert_eq!(
+                encrypted.message_by_leaf(sealed.leaf()).unwrap(),
+                Some(sealed.clone())
+            );
+            assert_eq!(encrypted.leaf_iterator().count(), 2);
+
+            // and are rejected otherwise, also when iterating
+            assert!(matches!(
+                strict.message_by_leaf(legacy.leaf()),
+                Err(DbError::EncryptionError(
+                    EncryptionError::Unencrypted { .. }
+                ))
+            ));
+            assert!(strict.leaf_iterator().any(|leaf| leaf.is_err()));
+
+            // new values are unreadable without the key
+            assert!(matches!(
+                plain.message_by_leaf(sealed.leaf()),
+                Err(DbError::EncryptionError(EncryptionError::NoKey { .. }))
+            ));
+
+            // upgrading encrypts the remaining values once
+            assert!(encrypted_db.rekey(1).unwrap() > 0);
+            assert_eq!(encrypted_db.rekey(1).unwrap(), 0);
+            assert!(plain.message_by_leaf(legacy.leaf()).is_err());
+            assert_eq!(
+                strict.message_by_leaf(legacy.leaf()).unwrap(),
+                Some(legacy.clone())
+            );
+
+            // rotating rewrites every value under the new key
+            assert!(rotated_db.rekey(1000).unwrap() > 0);
+            let current = NomadDB::new("home_1", db.with_cipher(DbCipher::new(&new_key)));
+            for message in [&legacy, &sealed] {
+                assert_eq!(
+                    current.message_by_leaf(message.leaf()).unwrap().as_ref(),
+                    Some(message)
+                );
+            }
+            assert!(matches!(
+                encrypted.message_by_leaf(legacy.leaf()),
+                Err(DbError::EncryptionError(EncryptionError::UnknownKey { .. }))
+            ));
+        })
+        .await
+    }
+
+    #[test]
+    fn db_cache_is_bounded() {
+        let cache = nomad_core::db::DbCache::new(2);
+        let read = |key: &[u8]| {
+            let mut reads = 0;
+            cache
+                .get_or_read::<()>(key, || {
+                    reads += 1;
+                    Ok(Some(key.to_vec()))
+                })
+                .unwrap();
+            reads
+        };
+
+        assert_eq!(read(b"a"), 1);
+        assert_eq!(read(b"b"), 1);
+        assert_eq!(read(b"a"), 0);
+        // evicts the oldest key
+        assert_eq!(read(b"c"), 1);
+        assert_eq!(read(b"a"), 1);
+        assert_eq!(read(b"c"), 0);
+
+        let lookups = cache.lookups();
+        assert_eq!(lookups.with_label_values(&["hit"]).get(), 2);
+        assert_eq!(lookups.with_label_values(&["miss"]).get(), 4);
+    }
 }
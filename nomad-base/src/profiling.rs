@@ -0,0 +1,175 @@
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{info, warn};
+use warp::{
+    http::StatusCode,
+    reply::{self, Response},
+    Filter, Rejection, Reply,
+};
+
+use crate::{
+    admin::{admin_access, denied},
+    ADMIN_TOKEN_ENV,
+};
+
+/// Length of a CPU profile when the request does not give one
+const DEFAULT_PROFILE_SECONDS: u64 = 10;
+/// Longest CPU profile served
+const MAX_PROFILE_SECONDS: u64 = 60;
+/// Stack samples per second. Prime, so sampling does not line up with
+/// periodic work
+const SAMPLE_FREQUENCY: i32 = 99;
+
+#[derive(Debug, Deserialize)]
+struct ProfileQuery {
+    seconds: Option<u64>,
+}
+
+/// Profiling routes. `GET /debug/pprof/profile?seconds=N` samples the CPU
+/// for N seconds and returns a flamegraph SVG, and `GET /debug/pprof/heap`
+/// reports that heap profiles are unavailable. Requests must come from
+/// loopback and bear the admin token. Without a token, the routes are not
+/// served.
+pub(crate) fn routes(
+    enabled: bool,
+    token: Option<String>,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let token = match (enabled, token) {
+        (true, Some(token)) if !token.is_empty() => Some(token),
+        (true, _) => {
+            warn!(
+                "Profiling is enabled but {} is not set. Not serving profiles.",
+                ADMIN_TOKEN_ENV
+            );
+            None
+        }
+        (false, _) => None,
+    };
+
+    let access = warp::get()
+        .and(warp::path!("debug" / "pprof" / ..))
+        .and(admin_access(token));
+
+    let profile = access
+        .clone()
+        .and(warp::path!("profile"))
+        .and(warp::query::<ProfileQuery>())
+        .and_then(cpu_profile);
+    let heap = access.and(warp::path!("heap")).map(|| {
+        reply::with_status(
+            "heap profiles need a profiling allocator, and agents use the system allocator",
+            StatusCode::NOT_IMPLEMENTED,
+        )
+        .into_response()
+    });
+
+    profile.or(heap).unify().recover(denied).unify()
+}
+
+async fn cpu_profile(query: ProfileQuery) -> Result<Response, Rejection> {
+    let seconds = query
+        .seconds
+        .unwrap_or(DEFAULT_PROFILE_SECONDS)
+        .clamp(1, MAX_PROFILE_SECONDS);
+    info!(seconds, "Collecting {} second CPU profile", seconds);
+
+    // The profiler samples every thread, so this one only has to wait
+    let profile = tokio::task::spawn_blocking(move || flamegraph(Duration::from_secs(seconds)))
+        .await
+        .expect("profiler panicked");
+
+    Ok(match profile {
+        Ok(svg) => reply::with_header(svg, "Content-Type", "image/svg+xml").into_response(),
+        Err(e) => reply::with_status(
+            format!("profiling failed: {}", e),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+        .into_response(),
+    })
+}
+
+/// Sample stacks for `duration` and render them as a flamegraph SVG
+fn flamegraph(duration: Duration) -> color_eyre::Result<Vec<u8>> {
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(SAMPLE_FREQUENCY)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()?;
+    std::thread::sleep(duration);
+
+    let mut svg = vec![];
+    guard.report().build()?.flamegraph(&mut svg)?;
+    Ok(svg)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    fn request(path: &str, remote: [u8; 4], token: &str) -> warp::test::RequestBuilder {
+        warp::test::request()
+            .method("GET")
+            .path(path)
+            .remote_addr((remote, 40000).into())
+            .header("authorization", format!("Bearer {}", token))
+    }
+
+    #[tokio::test]
+    async fn it_serves_cpu_profiles_to_loopback_admins() {
+        let filter = routes(true, Some("secret".to_owned()));
+
+        // give the profiler something to sample
+        let stop = Arc::new(AtomicBool::new(false));
+        let busy = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut x = 0u64;
+                while !stop.load(Ordering::Relaxed) {
+                    x = x.wrapping_mul(31).wrapping_add(1);
+                }
+                x
+            })
+        };
+
+        let response = request("/debug/pprof/profile?seconds=1", [127, 0, 0, 1], "secret")
+            .reply(&filter)
+            .await;
+        stop.store(true, Ordering::Relaxed);
+        busy.join().unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert!(!response.body().is_empty());
+        assert!(String::from_utf8_lossy(response.body()).contains("<svg"));
+    }
+
+    #[tokio::test]
+    async fn it_refuses_unauthorized_profiles() {
+        let filter = routes(true, Some("secret".to_owned()));
+
+        let response = request("/debug/pprof/profile", [10, 0, 0, 1], "secret")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 403);
+
+        let response = request("/debug/pprof/profile", [127, 0, 0, 1], "guess")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 401);
+
+        let response = request("/debug/pprof/heap", [127, 0, 0, 1], "secret")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), 501);
+
+        // disabled, or enabled without a token, serves nothing
+        for filter in [routes(false, Some("secret".to_owned())), routes(true, None)] {
+            let response = request("/debug/pprof/profile", [127, 0, 0, 1], "secret")
+                .reply(&filter)
+                .await;
+            assert_eq!(response.status(), 404);
+        }
+    }
+}
@@ -3,7 +3,7 @@ use color_eyre::eyre::Result;
 use ethers::core::types::H256;
 use nomad_core::{
     accumulator::NomadProof, db::DbError, Common, CommonEvents, DoubleUpdate, MessageStatus,
-    NomadMessage, Replica, SignedUpdate, State, TxOutcome,
+    NomadMessage, ProcessingState, Replica, RootProvenance, SignedUpdate, State, TxOutcome,
 };
 
 use crate::{ChainCommunicationError, NomadDB};
@@ -55,6 +55,11 @@ impl CachingReplica {
         self.db.clone()
     }
 
+    /// Return handle on the replica's indexer
+    pub fn indexer(&self) -> Arc<CommonIndexers> {
+        self.contract_sync.indexer()
+    }
+
     /// Spawn a task that syncs the CachingReplica's db with the on-chain event
     /// data
     pub fn sync(&self) -> Instrumented<JoinHandle<Result<()>>> {
@@ -85,9 +90,36 @@ impl Replica for CachingReplica {
         self.replica.message_status(leaf).await
     }
 
+    async fn process_success(
+        &self,
+        leaf: H256,
+        block: Option<u64>,
+    ) -> Result<Option<bool>, ChainCommunicationError> {
+        self.replica.process_success(leaf, block).await
+    }
+
     async fn acceptable_root(&self, root: H256) -> Result<bool, ChainCommunicationError> {
         self.replica.acceptable_root(root).await
     }
+
+    async fn confirm_at(&self, root: H256) -> Result<u64, ChainCommunicationError> {
+        self.replica.confirm_at(root).await
+    }
+
+    async fn processing_state(
+        &self,
+        root: H256,
+        leaf: H256,
+    ) -> Result<ProcessingState, ChainCommunicationError> {
+        self.replica.processing_state(root, leaf).await
+    }
+
+    async fn root_provenance(
+        &self,
+        root: H256,
+    ) -> Result<Option<RootProvenance>, ChainCommunicationError> {
+        self.replica.root_provenance(root).await
+    }
 }
 
 #[async_trait]
@@ -265,6 +297,19 @@ impl Replica for ReplicaVariants {
         }
     }
 
+    async fn process_success(
+        &self,
+        leaf: H256,
+        block: Option<u64>,
+    ) -> Result<Option<bool>, ChainCommunicationError> {
+        match self {
+            ReplicaVariants::Ethereum(replica) => Ok(replica.process_success(leaf, block).await?),
+            ReplicaVariants::Mock(mock_replica) => {
+                Ok(mock_replica.process_success(leaf, block).await?)
+            }
+        }
+    }
+
     async fn prove_and_process(
         &self,
         message: &NomadMessage,
@@ -286,6 +331,36 @@ impl Replica for ReplicaVariants {
             ReplicaVariants::Mock(mock_replica) => Ok(mock_replica.acceptable_root(root).await?),
         }
     }
+
+    async fn confirm_at(&self, root: H256) -> Result<u64, ChainCommunicationError> {
+        match self {
+            ReplicaVariants::Ethereum(replica) => Ok(replica.confirm_at(root).await?),
+            ReplicaVariants::Mock(mock_replica) => Ok(mock_replica.confirm_at(root).await?),
+        }
+    }
+
+    async fn processing_state(
+        &self,
+        root: H256,
+        leaf: H256,
+    ) -> Result<ProcessingState, ChainCommunicationError> {
+        match self {
+            ReplicaVariants::Ethereum(replica) => Ok(replica.processing_state(root, leaf).await?),
+            ReplicaVariants::Mock(mock_replica) => {
+                Ok(mock_replica.processing_state(root, leaf).await?)
+            }
+        }
+    }
+
+    async fn root_provenance(
+        &self,
+        root: H256,
+    ) -> Result<Option<RootProvenance>, ChainCommunicationError> {
+        match self {
+            ReplicaVariants::Ethereum(replica) => Ok(replica.root_provenance(root).await?),
+            ReplicaVariants::Mock(mock_replica) => Ok(mock_replica.root_provenance(root).await?),
+        }
+    }
 }
 
 #[async_trait]
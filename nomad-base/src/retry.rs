@@ -0,0 +1,432 @@
+use futures_util::Future;
+use nomad_core::utils::exponential_backoff;
+use nomad_xyz_configuration::agent::IndexBackoffConfig;
+use rand::Rng;
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::watch,
+    time::{sleep, Instant},
+};
+
+/// Shutdown signal shared by an agent's tasks. Cancelling it wakes every
+/// [`Retry`] sleep at once, so tasks stop between attempts instead of
+/// finishing their wait.
+#[derive(Debug, Clone)]
+pub struct CancellationToken(Arc<watch::Sender<bool>>);
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self(Arc::new(watch::channel(false).0))
+    }
+}
+
+impl CancellationToken {
+    /// Cancel every clone of the token
+    pub fn cancel(&self) {
+        self.0.send_replace(true);
+    }
+
+    /// Whether the token was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once the token is cancelled
+    pub async fn cancelled(&self) {
+        let mut cancelled = self.0.subscribe();
+        while !*cancelled.borrow_and_update() {
+            // The sender lives as long as `self`, so this never errs
+            let _ = cancelled.changed().await;
+        }
+    }
+}
+
+/// Delay before each retry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backoff {
+    /// The same delay before every retry
+    Fixed(Duration),
+    /// A delay doubling from `base` up to `max`. Each delay is drawn
+    /// uniformly from its upper half, so tasks failing together spread out.
+    Exponential {
+        /// Delay before the first retry
+        base: Duration,
+        /// Longest delay
+        max: Duration,
+    },
+    /// A delay growing by `multiplier` from `base` up to `max`. Each delay
+    /// is shortened by a random fraction of up to `jitter`.
+    Scaled {
+        /// Delay before the first retry
+        base: Duration,
+        /// Factor the delay grows by with each retry, at least 1
+        multiplier: f64,
+        /// Longest delay
+        max: Duration,
+        /// Largest fraction each delay is shortened by, from 0 to 1
+        jitter: f64,
+    },
+}
+
+impl From<IndexBackoffConfig> for Backoff {
+    fn from(config: IndexBackoffConfig) -> Self {
+        Backoff::Scaled {
+            base: config.initial_delay(),
+            multiplier: config.multiplier,
+            max: config.max_delay(),
+            jitter: config.jitter,
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before retry number `retry`, counted from 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        match *self {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { base, max } => exponential_backoff(base, max, retry, true),
+            Backoff::Scaled {
+                base,
+                multiplier,
+                max,
+                jitter,
+            } => {
+                let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+                let full = (base.as_secs_f64() * multiplier.max(1.0).powi(exponent))
+                    .min(max.as_secs_f64());
+                let jitter = jitter.clamp(0.0, 1.0);
+                let shortened = if jitter > 0.0 {
+                    full * (1.0 - rand::thread_rng().gen_range(0.0..=jitter))
+                } else {
+                    full
+                };
+                Duration::from_secs_f64(shortened)
+            }
+        }
+    }
+}
+
+/// Why [`Retry::run`] stopped retrying
+#[derive(Debug, thiserror::Error)]
+pub enum RetryError<E> {
+    /// The cancellation token was cancelled
+    #[error("Cancelled after {attempts} attempts")]
+    Cancelled {
+        /// Attempts made
+        attempts: u32,
+    },
+    /// The next retry would start past the deadline
+    #[error("Deadline passed after {attempts} attempts. Last error: {error}")]
+    DeadlineExceeded {
+        /// Attempts made
+        attempts: u32,
+        /// Error of the last attempt
+        error: E,
+    },
+    /// Every allowed attempt failed
+    #[error("Failed {attempts} attempts. Last error: {error}")]
+    Exhausted {
+        /// Attempts made
+        attempts: u32,
+        /// Error of the last attempt
+        error: E,
+    },
+}
+
+/// Retry strategy: a backoff, optionally bounded by a number of attempts
+/// and a deadline, and interrupted by a cancellation token.
+///
+/// ```ignore
+/// let retry = Retry::exponential(Duration::from_secs(1), Duration::from_secs(60))
+///     .with_deadline(Duration::from_secs(600))
+///     .with_cancellation(channel.cancellation());
+/// let outcome = retry.run(|| home.update(&signed)).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Retry {
+    backoff: Backoff,
+    max_attempts: Option<u32>,
+    deadline: Option<Duration>,
+    cancellation: CancellationToken,
+}
+
+impl Retry {
+    /// Retry with a fixed delay
+    pub fn fixed(delay: Duration) -> Self {
+        Self::new(Backoff::Fixed(delay))
+    }
+
+    /// Retry with a jittered exponential delay from `base` up to `max`
+    pub fn exponential(base: Duration, max: Duration) -> Self {
+        Self::new(Backoff::Exponential { base, max })
+    }
+
+    fn new(backoff: Backoff) -> Self {
+        Self {
+            backoff,
+            max_attempts: None,
+            deadline: None,
+            cancellation: Default::default(),
+        }
+    }
+
+    /// Give up after `attempts` attempts
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Give up rather than retry past `deadline` from the first attempt
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stop sleeping and retrying once `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Delay before retry number `retry`, counted from 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.backoff.delay(retry)
+    }
+
+    /// Sleep for the delay before retry number `retry`, counted from 1.
+    /// Returns false, at once, if the token is or gets cancelled. Loops
+    /// polling at a fixed interval call this between iterations.
+    pub async fn sleep(&self, retry: u32) -> bool {
+        self.wait(self.delay(retry)).await
+    }
+
+    /// Sleep for `delay`. Returns false, at once, if the token is or gets
+    /// cancelled.
+    pub async fn wait(&self, delay: Duration) -> bool {
+        if self.cancellation.is_cancelled() {
+            return false;
+        }
+        tokio::select! {
+            _ = sleep(delay) => true,
+            _ = self.cancellation.cancelled() => false,
+        }
+    }
+
+    /// Run `op` until it succeeds, retrying per the strategy
+    pub async fn run<T, E, F, Fut>(&self, op: F) -> Result<T, RetryError<E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.run_with(op, |_, _, _| {}).await
+    }
+
+    /// Run `op` until it succeeds, retrying per the strategy. `on_retry` is
+    /// called after each failed attempt that will be retried, with the
+    /// attempt number, its error and the delay before the next attempt, for
+    /// logging and metrics.
+    pub async fn run_with<T, E, F, Fut, C>(
+        &self,
+        mut op: F,
+        mut on_retry: C,
+    ) -> Result<T, RetryError<E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        C: FnMut(u32, &E, Duration),
+    {
+        let start = Instant::now();
+        let mut attempts = 0;
+        loop {
+            if self.cancellation.is_cancelled() {
+                return Err(RetryError::Cancelled { attempts });
+            }
+            attempts += 1;
+            let error = match op().await {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+
+            if self.max_attempts.map_or(false, |max| attempts >= max) {
+                return Err(RetryError::Exhausted { attempts, error });
+            }
+            let delay = self.backoff.delay(attempts);
+            if let Some(deadline) = self.deadline {
+                if start.elapsed() + delay > deadline {
+                    return Err(RetryError::DeadlineExceeded { attempts, error });
+                }
+            }
+
+            on_retry(attempts, &error, delay);
+            if !self.wait(delay).await {
+                return Err(RetryError::Cancelled { attempts });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// An operation failing `failures` times, then succeeding with the
+    /// number of attempts made
+    fn flaky(
+        failures: u32,
+    ) -> (
+        Arc<AtomicU32>,
+        impl FnMut() -> futures_util::future::Ready<Result<u32, String>>,
+    ) {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let op = move || {
+            let call = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            futures_util::future::ready(if call > failures {
+                Ok(call)
+            } else {
+                Err(format!("failure {}", call))
+            })
+        };
+        (calls, op)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_retries_with_a_fixed_delay() {
+        let (_, op) = flaky(3);
+        let mut delays = vec![];
+        let start = Instant::now();
+
+        let attempts = Retry::fixed(Duration::from_secs(5))
+            .run_with(op, |attempt, _, delay| delays.push((attempt, delay)))
+            .await
+            .unwrap();
+
+        assert_eq!(attempts, 4);
+        assert_eq!(
+            delays,
+            (1..=3)
+                .map(|attempt| (attempt, Duration::from_secs(5)))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(start.elapsed(), Duration::from_secs(15));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_backs_off_exponentially_with_jitter() {
+        let backoff = Backoff::Exponential {
+            base: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+        };
+        for (retry, full) in [
+            (1, 1),
+            (2, 2),
+            (3, 4),
+            (6, 32),
+            (7, 60),
+            (40, 60),
+            (u32::MAX, 60),
+        ] {
+            let delay = backoff.delay(retry);
+            let full = Duration::from_secs(full);
+            assert!(
+                delay <= full && delay >= full / 2,
+                "{:?} for retry {}",
+                delay,
+                retry
+            );
+        }
+
+        let (calls, op) = flaky(u32::MAX);
+        let start = Instant::now();
+        let result = Retry::exponential(Duration::from_secs(1), Duration::from_secs(60))
+            .with_max_attempts(5)
+            .run(op)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(RetryError::Exhausted { attempts: 5, ref error }) if error == "failure 5"
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+        // 1 + 2 + 4 + 8 seconds, each at least halved by jitter
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(7500) && elapsed <= Duration::from_secs(15));
+    }
+
+    #[test]
+    fn it_scales_delays_by_the_multiplier_with_jitter() {
+        let backoff = |jitter| Backoff::Scaled {
+            base: Duration::from_millis(500),
+            multiplier: 3.0,
+            max: Duration::from_secs(10),
+            jitter,
+        };
+        for (retry, full) in [
+            (1, 500),
+            (2, 1500),
+            (3, 4500),
+            (4, 10_000),
+            (u32::MAX, 10_000),
+        ] {
+            let full = Duration::from_millis(full);
+            assert_eq!(backoff(0.0).delay(retry), full);
+
+            let delay = backoff(0.25).delay(retry);
+            assert!(
+                delay <= full && delay >= full.mul_f64(0.75),
+                "{:?} for retry {}",
+                delay,
+                retry
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_gives_up_at_the_deadline() {
+        let (calls, op) = flaky(u32::MAX);
+        let start = Instant::now();
+        let result = Retry::fixed(Duration::from_secs(10))
+            .with_deadline(Duration::from_secs(35))
+            .run(op)
+            .await;
+
+        // Attempts at 0, 10, 20 and 30s. A retry at 40s is past the deadline
+        assert!(matches!(
+            result,
+            Err(RetryError::DeadlineExceeded { attempts: 4, .. })
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(start.elapsed(), Duration::from_secs(30));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancellation_interrupts_sleeps() {
+        let token = CancellationToken::default();
+        let retry = Retry::fixed(Duration::from_secs(3600)).with_cancellation(token.clone());
+
+        let (calls, op) = flaky(u32::MAX);
+        let start = Instant::now();
+        let run = tokio::spawn({
+            let retry = retry.clone();
+            async move { retry.run(op).await }
+        });
+        sleep(Duration::from_secs(1)).await;
+        token.cancel();
+
+        let result = run.await.unwrap();
+        assert!(matches!(result, Err(RetryError::Cancelled { attempts: 1 })));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // Once cancelled, nothing sleeps or runs
+        assert!(!retry.sleep(1).await);
+        let (calls, op) = flaky(0);
+        assert!(matches!(
+            retry.run(op).await,
+            Err(RetryError::Cancelled { attempts: 0 })
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+}
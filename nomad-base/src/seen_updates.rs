@@ -0,0 +1,279 @@
+use ethers::{core::types::H256, utils::keccak256};
+use nomad_core::{Decode, Encode, NomadError, SignedUpdateWithMeta};
+
+/// Longest source name kept in the seen-set. Longer names are truncated.
+const MAX_SOURCE_LEN: usize = u8::MAX as usize;
+
+/// A signed update observed by update syncing, and the sources that observed
+/// it. Identified by its roots and the hash of its signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeenUpdate {
+    /// Previous root of the update
+    pub previous_root: H256,
+    /// New root of the update
+    pub new_root: H256,
+    /// Keccak hash of the update's signature
+    pub signature_hash: H256,
+    /// Block the update was last observed in
+    pub block_number: u64,
+    /// Names of the sources that observed the update, in observation order
+    pub sources: Vec<String>,
+}
+
+impl SeenUpdate {
+    fn key(update: &SignedUpdateWithMeta) -> (H256, H256, H256) {
+        let signed = &update.signed_update;
+        (
+            signed.update.previous_root,
+            signed.update.new_root,
+            keccak256(signed.signature.to_vec()).into(),
+        )
+    }
+
+    fn matches(&self, key: &(H256, H256, H256)) -> bool {
+        (self.previous_root, self.new_root, self.signature_hash) == *key
+    }
+}
+
+/// Outcome of observing an update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Observation {
+    /// No source observed the update before
+    New,
+    /// The update was already observed. `moved` is set if it was observed in
+    /// a different block than before, e.g. after a reorg.
+    Duplicate {
+        /// Whether the update's block changed
+        moved: bool,
+    },
+}
+
+/// Compact set of recently observed signed updates. Bounded by pruning
+/// updates from blocks no source re-fetches.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeenUpdates(Vec<SeenUpdate>);
+
+impl SeenUpdates {
+    /// Number of updates in the set
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The entry for `update`, if it was observed
+    pub fn get(&self, update: &SignedUpdateWithMeta) -> Option<&SeenUpdate> {
+        let key = SeenUpdate::key(update);
+        self.0.iter().find(|seen| seen.matches(&key))
+    }
+
+    /// Record that `source` observed `update`
+    pub fn observe(&mut self, source: &str, update: &SignedUpdateWithMeta) -> Observation {
+        let key = SeenUpdate::key(update);
+        let source = truncated(source);
+        let block_number = update.metadata.block_number;
+
+        match self.0.iter_mut().find(|seen| seen.matches(&key)) {
+            Some(seen) => {
+                if !seen.sources.iter().any(|s| s == source) {
+                    seen.sources.push(source.to_owned());
+                }
+                let moved = seen.block_number != block_number;
+                seen.block_number = block_number;
+                Observation::Duplicate { moved }
+            }
+            None => {
+                self.0.push(SeenUpdate {
+                    previous_root: key.0,
+                    new_root: key.1,
+                    signature_hash: key.2,
+                    block_number,
+                    sources: vec![source.to_owned()],
+                });
+                Observation::New
+            }
+        }
+    }
+
+    /// Drop updates observed below `block_number`. Returns how many were
+    /// dropped.
+    pub fn prune_below(&mut self, block_number: u64) -> usize {
+        let before = self.0.len();
+        self.0.retain(|seen| seen.block_number >= block_number);
+        before - self.0.len()
+    }
+}
+
+fn truncated(source: &str) -> &str {
+    if source.len() <= MAX_SOURCE_LEN {
+        return source;
+    }
+    let mut end = MAX_SOURCE_LEN;
+    while !source.is_char_boundary(end) {
+        end -= 1;
+    }
+    &source[..end]
+}
+
+impl Encode for SeenUpdate {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = self.previous_root.write_to(writer)?;
+        written += self.new_root.write_to(writer)?;
+        written += self.signature_hash.write_to(writer)?;
+        written += self.block_number.write_to(writer)?;
+        written += (self.sources.len() as u32).write_to(writer)?;
+        for source in self.sources.iter() {
+            let source = truncated(source);
+            writer.write_all(&[source.len() as u8])?;
+            writer.write_all(source.as_bytes())?;
+            written += 1 + source.len();
+        }
+        Ok(written)
+    }
+}
+
+impl Decode for SeenUpdate {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let previous_root = H256::read_from(reader)?;
+        let new_root = H256::read_from(reader)?;
+        let signature_hash = H256::read_from(reader)?;
+        let block_number = u64::read_from(reader)?;
+        let len = u32::read_from(reader)?;
+        let sources = (0..len)
+            .map(|_| {
+                let mut len = [0u8; 1];
+                reader.read_exact(&mut len)?;
+                let mut source = vec![0u8; len[0] as usize];
+                reader.read_exact(&mut source)?;
+                String::from_utf8(source)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e).into())
+            })
+            .collect::<Result<_, NomadError>>()?;
+
+        Ok(Self {
+            previous_root,
+            new_root,
+            signature_hash,
+            block_number,
+            sources,
+        })
+    }
+}
+
+impl Encode for SeenUpdates {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = (self.0.len() as u32).write_to(writer)?;
+        for seen in self.0.iter() {
+            written += seen.write_to(writer)?;
+        }
+        Ok(written)
+    }
+}
+
+impl Decode for SeenUpdates {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let len = u32::read_from(reader)?;
+        let seen = (0..len)
+            .map(|_| SeenUpdate::read_from(reader))
+            .collect::<Result<_, _>>()?;
+        Ok(Self(seen))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nomad_core::{SignedUpdate, Update, UpdateMeta};
+
+    fn update(new_root: u8, signature: u8, block_number: u64) -> SignedUpdateWithMeta {
+        let signature = ethers::types::Signature {
+            r: signature.into(),
+            s: signature.into(),
+            v: 27,
+        }
+        .try_into()
+        .unwrap();
+        SignedUpdateWithMeta {
+            signed_update: SignedUpdate {
+                update: Update {
+                    home_domain: 1000,
+                    previous_root: H256::zero(),
+                    new_root: H256::repeat_byte(new_root),
+                },
+                signature,
+            },
+            metadata: UpdateMeta {
+                block_number,
+                timestamp: None,
+            },
+        }
+    }
+
+    #[test]
+    fn it_dedups_by_roots_and_signature() {
+        let mut seen = SeenUpdates::default();
+
+        assert_eq!(
+            seen.observe("ethereum", &update(1, 1, 10)),
+            Observation::New
+        );
+        assert_eq!(
+            seen.observe("fallback", &update(1, 1, 10)),
+            Observation::Duplicate { moved: false }
+        );
+        // the same source is only listed once
+        assert_eq!(
+            seen.observe("ethereum", &update(1, 1, 10)),
+            Observation::Duplicate { moved: false }
+        );
+        // a reorg moves it
+        assert_eq!(
+            seen.observe("ethereum", &update(1, 1, 11)),
+            Observation::Duplicate { moved: true }
+        );
+        // a different signature over the same roots is a distinct update
+        assert_eq!(
+            seen.observe("ethereum", &update(1, 2, 10)),
+            Observation::New
+        );
+
+        assert_eq!(seen.len(), 2);
+        let first = seen.get(&update(1, 1, 0)).unwrap();
+        assert_eq!(first.sources, vec!["ethereum", "fallback"]);
+        assert_eq!(first.block_number, 11);
+    }
+
+    #[test]
+    fn it_prunes_and_round_trips() {
+        let mut seen = SeenUpdates::default();
+        seen.observe("ethereum", &update(1, 1, 10));
+        seen.observe("ethereum", &update(2, 1, 20));
+        seen.observe("a".repeat(300).as_str(), &update(2, 1, 20));
+
+        let decoded = SeenUpdates::read_from(&mut seen.to_vec().as_slice()).unwrap();
+        assert_eq!(decoded, seen);
+        assert_eq!(decoded.get(&update(2, 1, 0)).unwrap().sources[1].len(), 255);
+
+        assert_eq!(seen.prune_below(20), 1);
+        assert!(seen.get(&update(1, 1, 10)).is_none());
+        assert_eq!(seen.prune_below(21), 1);
+        assert!(seen.is_empty());
+    }
+}
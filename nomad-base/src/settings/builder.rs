@@ -0,0 +1,504 @@
+//! Builders for agent [`Settings`] without config or secrets files.
+//!
+//! Defaults suit tests against a local chain: a fresh DB path in the temp
+//! dir, no metrics server, no contract code check, and signing through the
+//! node on every chain. `build` runs the checks the settings loader runs, so
+//! built settings are ones the loader could have produced.
+
+use color_eyre::{eyre::ensure, Result};
+use nomad_types::{HexString, NomadIdentifier};
+use nomad_xyz_configuration::{
+    agent::{
+        CodeCheck, DbWriteBatchConfig, LogConfig, SignerClaimsConfig, SignerConf, TopUpConfig,
+    },
+    ethereum::{self, RpcErrorPattern},
+    substrate, ChainConf, Connection, GasConfig, NomadGasConfig, TxSubmitterConf,
+};
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::{chains::PageSettings, ChainSetup, IndexSettings, Settings};
+
+/// Agents settings can be built for
+const AGENT_NAMES: &[&str] = &["kathy", "updater", "relayer", "processor", "watcher"];
+
+/// Default RPC of built chain setups
+const DEFAULT_RPC: &str = "http://localhost:8545";
+
+/// Default index page size of built chain setups
+const DEFAULT_PAGE_SIZE: u32 = 2000;
+
+/// Builds a [`ChainSetup`]
+#[derive(Debug, Clone)]
+pub struct ChainSetupBuilder {
+    setup: ChainSetup,
+}
+
+impl ChainSetupBuilder {
+    /// Contract on the chain `name` with domain `domain`. Defaults to the
+    /// zero address on an Ethereum RPC at `localhost:8545`, indexed from
+    /// block 0, with 1 second blocks and no finality lag.
+    pub fn new(name: impl Into<String>, domain: u32) -> Self {
+        Self {
+            setup: ChainSetup {
+                name: name.into(),
+                domain,
+                address: Some(NomadIdentifier::default()),
+                implementation: None,
+                page_settings: PageSettings {
+                    from: 0,
+                    page_size: DEFAULT_PAGE_SIZE,
+                },
+                finality: 0,
+                block_time: 1,
+                confirmations: 0,
+                fees: Default::default(),
+                chain: ChainConf::Ethereum(Connection::Http(DEFAULT_RPC.to_owned())),
+                disabled: None,
+            },
+        }
+    }
+
+    /// Contract address
+    pub fn address(mut self, address: impl Into<NomadIdentifier>) -> Self {
+        self.setup.address = Some(address.into());
+        self
+    }
+
+    /// Implementation behind the contract's proxy
+    pub fn implementation(mut self, implementation: impl Into<NomadIdentifier>) -> Self {
+        self.setup.implementation = Some(implementation.into());
+        self
+    }
+
+    /// Chain connection. Substrate setups have no contract address.
+    pub fn chain(mut self, chain: ChainConf) -> Self {
+        if let ChainConf::Substrate(_) = chain {
+            self.setup.address = None;
+            self.setup.implementation = None;
+        }
+        self.setup.chain = chain;
+        self
+    }
+
+    /// Block to start indexing at and index page size
+    pub fn page_settings(mut self, from: u32, page_size: u32) -> Self {
+        self.setup.page_settings = PageSettings { from, page_size };
+        self
+    }
+
+    /// Finality in blocks
+    pub fn finality(mut self, finality: u8) -> Self {
+        self.setup.finality = finality;
+        self
+    }
+
+    /// Block time in seconds
+    pub fn block_time(mut self, block_time: u64) -> Self {
+        self.setup.block_time = block_time;
+        self
+    }
+
+    /// Confirmations before a transaction is final
+    pub fn confirmations(mut self, confirmations: u64) -> Self {
+        self.setup.confirmations = confirmations;
+        self
+    }
+
+    /// Transaction fee settings
+    pub fn fees(mut self, fees: GasConfig) -> Self {
+        self.setup.fees = fees;
+        self
+    }
+
+    /// Disable the replica for `reason`
+    pub fn disabled(mut self, reason: impl Into<String>) -> Self {
+        self.setup.disabled = Some(reason.into());
+        self
+    }
+
+    /// Validate and build the setup
+    pub fn build(self) -> Result<ChainSetup> {
+        self.setup.validate()?;
+        Ok(self.setup)
+    }
+}
+
+/// Builds the base [`Settings`] of an agent
+#[derive(Debug)]
+pub struct SettingsBuilder {
+    agent: String,
+    settings: Settings,
+    top_up_chain_ids: HashMap<String, u64>,
+}
+
+impl SettingsBuilder {
+    /// Settings of the agent named `agent`, one of `kathy`, `updater`,
+    /// `relayer`, `processor` or `watcher`, with its home at `home`
+    pub fn new(agent: impl Into<String>, home: ChainSetup) -> Self {
+        let agent = agent.into().to_lowercase();
+        Self {
+            settings: Settings {
+                db: fresh_db_path(&agent),
+                index: AGENT_NAMES
+                    .contains(&agent.as_str())
+                    .then(|| IndexSettings::from_agent_name(&agent))
+                    .unwrap_or_default(),
+                home,
+                ..Default::default()
+            },
+            agent,
+            top_up_chain_ids: Default::default(),
+        }
+    }
+
+    /// Add a replica
+    pub fn replica(mut self, replica: ChainSetup) -> Self {
+        self.settings.replicas.insert(replica.name.clone(), replica);
+        self
+    }
+
+    /// Add the connection manager on a replica's chain
+    pub fn manager(mut self, manager: ChainSetup) -> Self {
+        self.settings
+            .managers
+            .get_or_insert_with(Default::default)
+            .insert(manager.name.clone(), manager);
+        self
+    }
+
+    /// DB path
+    pub fn db(mut self, path: impl Into<String>) -> Self {
+        self.settings.db = path.into();
+        self
+    }
+
+    /// Port to serve metrics and `/status` on
+    pub fn metrics(mut self, port: u16) -> Self {
+        self.settings.metrics = Some(port);
+        self
+    }
+
+    /// Serve profiles on the metrics server
+    pub fn profiling(mut self, profiling: bool) -> Self {
+        self.settings.profiling = profiling;
+        self
+    }
+
+    /// Strictness of the startup check of deployed contract code
+    pub fn code_check(mut self, code_check: CodeCheck) -> Self {
+        self.settings.code_check = code_check;
+        self
+    }
+
+    /// NDJSON file to mirror audit records to
+    pub fn audit_file(mut self, path: impl Into<String>) -> Self {
+        self.settings.audit_file = Some(path.into());
+        self
+    }
+
+    /// Classification of JSON-RPC errors ahead of the built-in patterns
+    pub fn rpc_error_patterns(mut self, patterns: Vec<RpcErrorPattern>) -> Self {
+        self.settings.rpc_error_patterns = patterns;
+        self
+    }
+
+    /// Batch the DB writes of event indexing
+    pub fn db_write_batch(mut self, max_records: usize, max_delay_ms: u64) -> Self {
+        self.settings.db_write_batch = Some(DbWriteBatchConfig {
+            max_records,
+            max_delay_ms,
+        });
+        self
+    }
+
+    /// Claim the transaction signers with heartbeats
+    pub fn signer_claims(mut self, signer_claims: SignerClaimsConfig) -> Self {
+        self.settings.signer_claims = signer_claims;
+        self
+    }
+
+    /// Encrypt the db values at rest with `key`
+    pub fn db_encryption_key(mut self, key: HexString<64>) -> Self {
+        self.settings.db_encryption_key = Some(key);
+        self
+    }
+
+    /// Read values written before db encryption was enabled
+    pub fn db_encryption_migrate(mut self, migrate: bool) -> Self {
+        self.settings.db_encryption_migrate = migrate;
+        self
+    }
+
+    /// Tracing configuration
+    pub fn logging(mut self, logging: LogConfig) -> Self {
+        self.settings.logging = logging;
+        self
+    }
+
+    /// Transaction submitter on `network`. Chains without one sign through
+    /// their node.
+    pub fn submitter(mut self, network: impl Into<String>, conf: TxSubmitterConf) -> Self {
+        self.settings.submitters.insert(network.into(), conf);
+        self
+    }
+
+    /// Attestation signer, used by roles without their own signer
+    pub fn attestation_signer(mut self, signer: SignerConf) -> Self {
+        self.settings.attestation_signer = Some(signer);
+        self
+    }
+
+    /// Signer for the named `role`
+    pub fn signer_role(mut self, role: impl Into<String>, signer: SignerConf) -> Self {
+        self.settings.signer_roles.insert(role.into(), signer);
+        self
+    }
+
+    /// Gas settings of `network`
+    pub fn gas(mut self, network: impl Into<String>, gas: NomadGasConfig) -> Self {
+        self.settings.gas.insert(network.into(), gas);
+        self
+    }
+
+    /// Top-ups of the transaction signer on `network`, which has chain id
+    /// `chain_id`
+    pub fn top_up(
+        mut self,
+        network: impl Into<String>,
+        chain_id: u64,
+        top_up: TopUpConfig,
+        funder: Option<SignerConf>,
+    ) -> Self {
+        let network = network.into();
+        if let Some(funder) = funder {
+            self.settings.top_up_funders.insert(network.clone(), funder);
+        }
+        self.top_up_chain_ids.insert(network.clone(), chain_id);
+        self.settings.top_up.insert(network, top_up);
+        self
+    }
+
+    /// Updater the home is expected to have
+    pub fn expected_updater(mut self, updater: impl Into<NomadIdentifier>) -> Self {
+        self.settings.expected_updater = Some(updater.into());
+        self
+    }
+
+    /// Interval of the home's updater
+    pub fn updater_interval(mut self, interval: u64) -> Self {
+        self.settings.updater_interval = Some(interval);
+        self
+    }
+
+    /// Governance router on `network`
+    pub fn governance_router(
+        mut self,
+        network: impl Into<String>,
+        router: impl Into<NomadIdentifier>,
+    ) -> Self {
+        self.settings
+            .governance_routers
+            .insert(network.into(), router.into());
+        self
+    }
+
+    /// Validate and build the settings
+    pub fn build(self) -> Result<Settings> {
+        let Self {
+            agent,
+            mut settings,
+            top_up_chain_ids,
+        } = self;
+        ensure!(
+            AGENT_NAMES.contains(&agent.as_str()),
+            "Unknown agent {}",
+            agent
+        );
+
+        let chains: Vec<_> = std::iter::once(&settings.home)
+            .chain(settings.replicas.values())
+            .map(|setup| (setup.name.clone(), node_submitter(&setup.chain)))
+            .collect();
+        for (network, submitter) in chains {
+            settings.submitters.entry(network).or_insert(submitter);
+        }
+
+        for (network, top_up) in settings.top_up.iter() {
+            top_up.validate(network, top_up_chain_ids[network])?;
+        }
+        settings.validate(&agent)?;
+
+        Ok(settings)
+    }
+}
+
+/// Submitter signing through the node of `chain`
+fn node_submitter(chain: &ChainConf) -> TxSubmitterConf {
+    match chain {
+        ChainConf::Ethereum(_) => {
+            TxSubmitterConf::Ethereum(ethereum::TxSubmitterConf::Local(SignerConf::Node))
+        }
+        ChainConf::Substrate(_) => {
+            TxSubmitterConf::Substrate(substrate::TxSubmitterConf::Local(SignerConf::Node))
+        }
+    }
+}
+
+/// Path in the temp dir no other builder in this process returned
+fn fresh_db_path(agent: &str) -> String {
+    static BUILT: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir()
+        .join(format!(
+            "nomad-{}-{}-{}",
+            agent,
+            std::process::id(),
+            BUILT.fetch_add(1, Ordering::Relaxed)
+        ))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn home() -> ChainSetup {
+        ChainSetupBuilder::new("ethereum", 6648936).build().unwrap()
+    }
+
+    fn replica(name: &str, domain: u32) -> ChainSetup {
+        ChainSetupBuilder::new(name, domain)
+            .finality(5)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn it_builds_with_test_defaults() {
+        let settings = SettingsBuilder::new("processor", home())
+            .replica(replica("moonbeam", 1650811245))
+            .build()
+            .unwrap();
+
+        assert_eq!(settings.metrics, None);
+        assert!(settings.code_check.is_off());
+        assert_eq!(settings.index, IndexSettings::from_agent_name("processor"));
+        assert_eq!(settings.replica_timelag("moonbeam"), Some(5));
+        assert!(matches!(
+            settings.get_submitter_conf("moonbeam"),
+            Some(TxSubmitterConf::Ethereum(ethereum::TxSubmitterConf::Local(
+                SignerConf::Node
+            )))
+        ));
+
+        let other = SettingsBuilder::new("processor", home())
+            .replica(replica("moonbeam", 1650811245))
+            .build()
+            .unwrap();
+        assert_ne!(settings.db, other.db);
+    }
+
+    #[test]
+    fn it_rejects_what_the_loader_rejects() {
+        // no replica
+        assert!(SettingsBuilder::new("relayer", home()).build().is_err());
+
+        // updaters need an attestation signer
+        assert!(SettingsBuilder::new("updater", home())
+            .replica(replica("moonbeam", 1650811245))
+            .build()
+            .is_err());
+        assert!(SettingsBuilder::new("updater", home())
+            .replica(replica("moonbeam", 1650811245))
+            .attestation_signer(SignerConf::Node)
+            .build()
+            .is_ok());
+
+        // empty RPC urls
+        let unreachable = ChainSetupBuilder::new("evmos", 9001)
+            .chain(ChainConf::Ethereum(Connection::Http(String::new())))
+            .build()
+            .unwrap();
+        assert!(SettingsBuilder::new("relayer", home())
+            .replica(unreachable)
+            .build()
+            .is_err());
+
+        // unknown signer roles and agents
+        assert!(SettingsBuilder::new("relayer", home())
+            .replica(replica("moonbeam", 1650811245))
+            .signer_role("owner", SignerConf::Node)
+            .build()
+            .is_err());
+        assert!(SettingsBuilder::new("oracle", home())
+            .replica(replica("moonbeam", 1650811245))
+            .build()
+            .is_err());
+
+        // top-ups on mainnets
+        let top_up: TopUpConfig =
+            serde_json::from_str(r#"{"isTestNetwork": true, "floor": "0x10", "amount": 100}"#)
+                .unwrap();
+        assert!(SettingsBuilder::new("relayer", home())
+            .replica(replica("moonbeam", 1650811245))
+            .top_up("moonbeam", 1284, top_up.clone(), None)
+            .build()
+            .is_err());
+        assert!(SettingsBuilder::new("relayer", home())
+            .replica(replica("moonbase", 5000))
+            .top_up("moonbase", 1287, top_up, None)
+            .build()
+            .is_ok());
+
+        // managers off the replica chains, and contracts without addresses
+        assert!(SettingsBuilder::new("watcher", home())
+            .replica(replica("moonbeam", 1650811245))
+            .manager(replica("evmos", 9001))
+            .attestation_signer(SignerConf::Node)
+            .build()
+            .is_err());
+        let mut unaddressed = replica("moonbeam", 1650811245);
+        unaddressed.address = None;
+        assert!(unaddressed.validate().is_err());
+    }
+
+    #[test]
+    fn it_reads_unencrypted_values_only_when_migrating() {
+        let plain = SettingsBuilder::new("relayer", home())
+            .replica(replica("moonbeam", 1650811245))
+            .build()
+            .unwrap();
+        plain
+            .open_db()
+            .unwrap()
+            .store_encodable("", "written_before_encryption", &7u32)
+            .unwrap();
+
+        let key: HexString<64> = "07".repeat(32).parse().unwrap();
+        let encrypted = |migrate| {
+            SettingsBuilder::new("relayer", home())
+                .replica(replica("moonbeam", 1650811245))
+                .db(plain.db.clone())
+                .db_encryption_key(key.clone())
+                .db_encryption_migrate(migrate)
+                .build()
+                .unwrap()
+        };
+        assert!(encrypted(false)
+            .open_db()
+            .unwrap()
+            .retrieve_decodable::<u32>("", "written_before_encryption")
+            .is_err());
+        assert_eq!(
+            encrypted(true)
+                .open_db()
+                .unwrap()
+                .retrieve_decodable::<u32>("", "written_before_encryption")
+                .unwrap(),
+            Some(7)
+        );
+    }
+}
@@ -1,10 +1,10 @@
 use color_eyre::Result;
 use nomad_core::ContractLocator;
-use nomad_ethereum::{make_conn_manager, make_replica};
+use nomad_ethereum::{make_conn_manager, make_replica, ReceiptSettings};
 use nomad_types::NomadIdentifier;
 use nomad_xyz_configuration::{
-    core::CoreDeploymentInfo, AgentSecrets, ChainConf, ConnectionManagerGasLimits, HomeGasLimits,
-    NomadConfig, ReplicaGasLimits, TxSubmitterConf,
+    core::CoreDeploymentInfo, AgentSecrets, ChainConf, ConnectionManagerGasLimits, GasConfig,
+    HomeGasLimits, NomadConfig, ReplicaGasLimits, TxSubmitterConf,
 };
 use serde::Deserialize;
 
@@ -53,12 +53,21 @@ pub struct ChainSetup {
     pub domain: u32,
     /// Address of contract on the chain
     pub address: Option<NomadIdentifier>,
+    /// Implementation behind the contract's proxy, from the contract config
+    #[serde(default)]
+    pub implementation: Option<NomadIdentifier>,
     /// Paging settings
     pub page_settings: PageSettings,
     /// Network specific finality in blocks
     pub finality: u8,
     /// Network specific block time in seconds
     pub block_time: u64,
+    /// Network specific confirmations before a transaction is final
+    #[serde(default)]
+    pub confirmations: u64,
+    /// Network specific transaction fee settings
+    #[serde(default)]
+    pub fees: GasConfig,
     /// The chain connection details
     #[serde(flatten)]
     pub chain: ChainConf,
@@ -88,18 +97,25 @@ impl ChainSetup {
         let domain_number = domain.domain;
         let finality = domain.specs.finalization_blocks;
         let block_time = domain.specs.block_time;
+        let confirmations = domain.specs.confirmations;
+        let fees = domain.specs.fees;
         let core = config.core().get(&resident_network).expect("!core");
-        let (address, page_settings) = match core {
+        let (address, implementation, page_settings) = match core {
             CoreDeploymentInfo::Ethereum(core) => {
-                let address = match &setup_type {
-                    ChainSetupType::Home { .. } => core.home.proxy,
+                let (address, implementation) = match &setup_type {
+                    ChainSetupType::Home { .. } => {
+                        (core.home.proxy, Some(core.home.implementation))
+                    }
                     ChainSetupType::Replica { home_network, .. } => {
-                        core.replicas
+                        let replica = core
+                            .replicas
                             .get(&home_network.to_string())
-                            .expect("!replica")
-                            .proxy
+                            .expect("!replica");
+                        (replica.proxy, Some(replica.implementation))
+                    }
+                    ChainSetupType::ConnectionManager { .. } => {
+                        (core.x_app_connection_manager, None)
                     }
-                    ChainSetupType::ConnectionManager { .. } => core.x_app_connection_manager,
                 };
 
                 let page_settings = PageSettings {
@@ -107,7 +123,7 @@ impl ChainSetup {
                     page_size: domain.specs.index_page_size,
                 };
 
-                (Some(address), page_settings)
+                (Some(address), implementation, page_settings)
             }
             CoreDeploymentInfo::Substrate(core) => {
                 let page_settings = PageSettings {
@@ -115,7 +131,7 @@ impl ChainSetup {
                     page_size: domain.specs.index_page_size,
                 };
 
-                (None, page_settings)
+                (None, None, page_settings)
             }
         };
 
@@ -129,14 +145,39 @@ impl ChainSetup {
             name: resident_network,
             domain: domain_number,
             address,
+            implementation,
             page_settings,
             finality,
             block_time,
+            confirmations,
+            fees,
             chain,
             disabled: None,
         }
     }
 
+    /// Check the setup names its chain and, on chains with contract
+    /// addresses, its contract
+    pub fn validate(&self) -> Result<()> {
+        color_eyre::eyre::ensure!(!self.name.is_empty(), "Chain setup has no name");
+        if let ChainConf::Ethereum(_) = self.chain {
+            color_eyre::eyre::ensure!(
+                self.address.is_some(),
+                "Chain setup for {} has no contract address",
+                self.name
+            );
+        }
+        Ok(())
+    }
+
+    /// Receipt polling and confirmation settings for this chain
+    pub fn receipt_settings(&self) -> ReceiptSettings {
+        ReceiptSettings {
+            block_time: self.block_time,
+            confirmations: self.confirmations,
+        }
+    }
+
     /// Try to convert the chain setting into a Home contract
     pub async fn try_into_home(
         &self,
@@ -158,6 +199,8 @@ impl ChainSetup {
                         },
                         submitter_conf,
                         timelag,
+                        self.receipt_settings(),
+                        self.fees,
                         gas,
                     )
                     .await?,
@@ -202,6 +245,8 @@ impl ChainSetup {
                         },
                         submitter_conf,
                         None, // never need timelag for replica
+                        self.receipt_settings(),
+                        self.fees,
                         gas,
                     )
                     .await?,
@@ -231,6 +276,8 @@ impl ChainSetup {
                     },
                     submitter_conf,
                     None, // Never need timelag for xapp connection manager
+                    self.receipt_settings(),
+                    self.fees,
                     gas,
                 )
                 .await?,
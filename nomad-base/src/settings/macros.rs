@@ -102,12 +102,14 @@ macro_rules! decl_settings {
                     let mut all_networks = remote_networks.clone();
                     all_networks.insert(home.clone());
 
-                    // Get agent secrets
+                    // Resolve secret references, then get agent secrets
+                    let redactions = nomad_base::SecretResolver::for_agent().await.resolve_env().await?;
                     let secrets = nomad_xyz_configuration::AgentSecrets::from_env(&all_networks).expect("failed to build AgentSecrets from env");
                     secrets.validate(&agent, &all_networks)?;
 
                     // Create base settings
                     let base = nomad_base::Settings::from_config_and_secrets(&agent, &home, &remote_networks, &config, &secrets);
+                    base.validate(&agent)?;
                     base.validate_against_config_and_secrets(&agent, &home, &remote_networks, &config, &secrets)?;
 
                     let mut agent = config.agent().get(&home).expect("agent config").[<$name:lower>].clone();
@@ -115,10 +117,13 @@ macro_rules! decl_settings {
                     // Override with environment vars, if present
                     agent.load_env_overrides();
 
-                    Ok(Self {
+                    let settings = Self {
                         base,
                         agent,
-                    })
+                    };
+                    nomad_base::print_config_if_requested(&settings, &redactions);
+
+                    Ok(settings)
                 }
             }
         }
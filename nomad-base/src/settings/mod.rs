@@ -9,18 +9,43 @@
 //! variable to match the path to the file. See the "external" files in the
 //! `fixtures` directory for examples of an external config file and their
 //! corresponding env file and/or secrets.json file.
+//!
+//! Secret values may reference files, other environment variables or AWS
+//! Secrets Manager secrets instead of being inlined. See [`secret_refs`].
+//!
+//! Tests and embedders build settings in code with [`SettingsBuilder`], which
+//! runs the same validation as the loader.
 
 use crate::{
     agent::AgentCore, CachingHome, CachingReplica, CommonIndexerVariants, CommonIndexers,
-    ContractSync, ContractSyncMetrics, HomeIndexerVariants, HomeIndexers, Homes, NomadDB, Replicas,
+    ContractSync, ContractSyncMetrics, CoreMetrics, HomeIndexerVariants, HomeIndexers, Homes,
+    NomadDB, Replicas,
+};
+use color_eyre::{
+    eyre::{bail, ensure, WrapErr},
+    Result,
+};
+use ethers::core::types::H256;
+use nomad_core::{
+    db::{DbCipher, TypedDB, DB},
+    ChecksumAddress, Common, ContractLocator, NomadIdentifier,
+};
+use nomad_ethereum::ContractKind;
+use nomad_types::HexString;
+use nomad_xyz_configuration::{
+    agent::{
+        CodeCheck, DbWriteBatchConfig, IndexBackoffConfig, SignerClaimsConfig, SignerConf,
+        TopUpConfig,
+    },
+    ethereum::RpcErrorPattern,
+    AgentSecrets, TxSubmitterConf,
 };
-use color_eyre::{eyre::bail, Result};
-use nomad_core::{db::DB, Common, ContractLocator};
-use nomad_xyz_configuration::{agent::SignerConf, AgentSecrets, TxSubmitterConf};
 use nomad_xyz_configuration::{core::CoreDeploymentInfo, ChainConf, NomadConfig, NomadGasConfig};
 use serde::Deserialize;
 use std::collections::HashSet;
+use std::str::FromStr;
 use std::{collections::HashMap, sync::Arc};
+use tracing::{info, warn};
 
 /// Chain configuration
 pub mod chains;
@@ -33,8 +58,53 @@ pub use macros::*;
 /// Tracing subscriber management
 pub mod trace;
 
+/// Secret indirection
+pub mod secret_refs;
+pub use secret_refs::*;
+
+/// Settings built in code
+pub mod builder;
+pub use builder::{ChainSetupBuilder, SettingsBuilder};
+
 use nomad_xyz_configuration::agent::LogConfig;
 
+/// If the agent was started with `--dump-schema`, print the JSON schemas for
+/// the config and secrets files and exit
+pub fn dump_schema_if_requested() {
+    if std::env::args().any(|arg| arg == "--dump-schema") {
+        let schemas = nomad_xyz_configuration::schema::agent_schemas();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&schemas).expect("!schema serialization")
+        );
+        std::process::exit(0);
+    }
+}
+
+/// If the agent was started with `--print-config`, print its settings with
+/// resolved secret values redacted and exit
+pub fn print_config_if_requested(settings: &impl std::fmt::Debug, redactions: &Redactions) {
+    if std::env::args().any(|arg| arg == "--print-config") {
+        println!("{}", redactions.redact(&format!("{:#?}", settings)));
+        std::process::exit(0);
+    }
+}
+
+/// Path passed with `--bootstrap-from`, if any. Accepts both
+/// `--bootstrap-from <path>` and `--bootstrap-from=<path>`.
+pub fn bootstrap_path_if_requested() -> Option<String> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg == "--bootstrap-from" {
+            return args.next();
+        }
+        if let Some(path) = arg.strip_prefix("--bootstrap-from=") {
+            return Some(path.to_owned());
+        }
+    }
+    None
+}
+
 /// Agent types
 pub enum AgentType {
     /// Kathy
@@ -74,6 +144,13 @@ pub struct IndexSettings {
     /// Whether or not to use timelag
     #[serde(default)]
     pub use_timelag: bool,
+    /// Most blocks a single log query of the home indexer spans. Unset
+    /// queries each range at once
+    #[serde(default)]
+    pub max_block_range: Option<u32>,
+    /// Backoff of the indexing loops after failed provider requests
+    #[serde(default)]
+    pub backoff: IndexBackoffConfig,
 }
 
 impl IndexSettings {
@@ -83,22 +160,32 @@ impl IndexSettings {
             "kathy" => Self {
                 data_types: IndexDataTypes::Updates,
                 use_timelag: true,
+                max_block_range: None,
+                backoff: Default::default(),
             },
             "updater" => Self {
                 data_types: IndexDataTypes::Updates,
                 use_timelag: true,
+                max_block_range: None,
+                backoff: Default::default(),
             },
             "relayer" => Self {
                 data_types: IndexDataTypes::Updates,
                 use_timelag: false,
+                max_block_range: None,
+                backoff: Default::default(),
             },
             "processor" => Self {
                 data_types: IndexDataTypes::UpdatesAndMessages,
                 use_timelag: true,
+                max_block_range: None,
+                backoff: Default::default(),
             },
             "watcher" => Self {
                 data_types: IndexDataTypes::Updates,
                 use_timelag: false,
+                max_block_range: None,
+                backoff: Default::default(),
             },
             _ => std::panic!("Invalid agent-specific settings name!"),
         }
@@ -146,6 +233,24 @@ pub struct Settings {
     pub db: String,
     /// Port to listen for prometheus scrape requests
     pub metrics: Option<u16>,
+    /// Serve profiles on the metrics server
+    #[serde(default)]
+    pub profiling: bool,
+    /// Strictness of the startup check of deployed contract code
+    #[serde(default)]
+    pub code_check: CodeCheck,
+    /// NDJSON file audit records are mirrored to
+    #[serde(default)]
+    pub audit_file: Option<String>,
+    /// Classification of JSON-RPC errors ahead of the built-in patterns
+    #[serde(default)]
+    pub rpc_error_patterns: Vec<RpcErrorPattern>,
+    /// Batching of the DB writes of event indexing
+    pub db_write_batch: Option<DbWriteBatchConfig>,
+    /// Claims on the transaction signers, detecting other processes
+    /// submitting with them
+    #[serde(default)]
+    pub signer_claims: SignerClaimsConfig,
     /// Settings for the home indexer
     #[serde(default)]
     pub index: IndexSettings,
@@ -163,6 +268,32 @@ pub struct Settings {
     pub submitters: HashMap<String, TxSubmitterConf>,
     /// Optional attestation signer
     pub attestation_signer: Option<SignerConf>,
+    /// Signers for named roles. Roles not listed use the attestation signer
+    #[serde(default)]
+    pub signer_roles: HashMap<String, SignerConf>,
+    /// Testnet top-ups of transaction signers
+    #[serde(default)]
+    pub top_up: HashMap<String, TopUpConfig>,
+    /// Funder keys for testnet top-ups
+    #[serde(default)]
+    pub top_up_funders: HashMap<String, SignerConf>,
+    /// Key the db values are encrypted at rest with
+    #[serde(default)]
+    pub db_encryption_key: Option<HexString<64>>,
+    /// Read values written before db encryption was enabled
+    #[serde(default)]
+    pub db_encryption_migrate: bool,
+    /// Updater the home is expected to have, from the network's contract
+    /// config
+    #[serde(default)]
+    pub expected_updater: Option<NomadIdentifier>,
+    /// Interval of the home's updater, from its agent config
+    #[serde(default)]
+    pub updater_interval: Option<u64>,
+    /// Governance router of the home and each replica network, from the
+    /// networks' contract config
+    #[serde(default)]
+    pub governance_routers: HashMap<String, NomadIdentifier>,
 }
 
 impl Settings {
@@ -171,6 +302,12 @@ impl Settings {
         Self {
             db: self.db.clone(),
             metrics: self.metrics,
+            profiling: self.profiling,
+            code_check: self.code_check,
+            audit_file: self.audit_file.clone(),
+            rpc_error_patterns: self.rpc_error_patterns.clone(),
+            db_write_batch: self.db_write_batch,
+            signer_claims: self.signer_claims.clone(),
             index: self.index.clone(),
             home: self.home.clone(),
             replicas: self.replicas.clone(),
@@ -179,6 +316,14 @@ impl Settings {
             logging: self.logging,
             submitters: self.submitters.clone(),
             attestation_signer: self.attestation_signer.clone(),
+            signer_roles: self.signer_roles.clone(),
+            top_up: self.top_up.clone(),
+            top_up_funders: self.top_up_funders.clone(),
+            db_encryption_key: self.db_encryption_key.clone(),
+            db_encryption_migrate: self.db_encryption_migrate,
+            expected_updater: self.expected_updater,
+            updater_interval: self.updater_interval,
+            governance_routers: self.governance_routers.clone(),
         }
     }
 }
@@ -189,6 +334,13 @@ impl Settings {
         self.submitters.get(name).cloned()
     }
 
+    /// Signer for `role`, falling back to the attestation signer
+    pub fn role_signer(&self, role: &str) -> Option<&SignerConf> {
+        self.signer_roles
+            .get(role)
+            .or(self.attestation_signer.as_ref())
+    }
+
     /// Set agent-specific index data types
     pub fn set_index_data_types(&mut self, data_types: IndexDataTypes) {
         self.index.data_types = data_types;
@@ -229,6 +381,37 @@ impl Settings {
             .await
     }
 
+    /// Open the db, encrypting its values at rest if a db encryption key is
+    /// configured. Unencrypted values are only read with
+    /// `db_encryption_migrate` set.
+    pub fn open_db(&self) -> Result<DB> {
+        let db = DB::from_path(&self.db)?;
+        let key = match &self.db_encryption_key {
+            Some(key) => H256::from_str(key.as_ref())?,
+            None => return Ok(db),
+        };
+
+        let mut cipher = DbCipher::new(key.as_fixed_bytes());
+        if self.db_encryption_migrate {
+            cipher = cipher.migrating();
+        }
+        let db = db.with_cipher(cipher);
+        info!(
+            key_id = %db.encryption_key_id().expect("cipher set"),
+            migrating = self.db_encryption_migrate,
+            "Encrypting db values at rest"
+        );
+        Ok(db)
+    }
+
+    /// Handle to `db` for syncing, which batches its writes if configured
+    fn sync_db(&self, db: DB) -> DB {
+        match &self.db_write_batch {
+            Some(batch) => db.coalescing(batch.max_records, batch.max_delay()),
+            None => db,
+        }
+    }
+
     /// Try to get a home ContractSync
     pub async fn try_home_contract_sync(
         &self,
@@ -243,7 +426,7 @@ impl Settings {
         let indexer = Arc::new(self.try_home_indexer().await?);
         let home_name = &self.home.name;
 
-        let nomad_db = NomadDB::new(&home_name, db);
+        let nomad_db = NomadDB::new(&home_name, self.sync_db(db));
 
         Ok(ContractSync::new(
             agent_name.to_owned(),
@@ -299,7 +482,7 @@ impl Settings {
         let indexer = Arc::new(self.try_replica_indexer(replica_setup).await?);
         let replica_name = &replica_setup.name;
 
-        let nomad_db = NomadDB::new(&replica_name, db);
+        let nomad_db = NomadDB::new(&replica_name, self.sync_db(db));
 
         Ok(ContractSync::new(
             agent_name.to_owned(),
@@ -372,6 +555,7 @@ impl Settings {
                         address: self.home.address.expect("eth ChainSetup missing address"),
                     },
                     timelag,
+                    self.index.max_block_range,
                 )
                 .await?,
             )
@@ -407,15 +591,27 @@ impl Settings {
 
     /// Try to generate an agent core for a named agent
     pub async fn try_into_core(&self, name: &str) -> Result<AgentCore> {
-        let metrics = Arc::new(crate::metrics::CoreMetrics::new(
-            name,
-            &self.home.name,
-            self.metrics,
-            Arc::new(prometheus::Registry::new()),
-        )?);
+        let metrics = Arc::new(
+            crate::metrics::CoreMetrics::new(
+                name,
+                &self.home.name,
+                self.metrics,
+                Arc::new(prometheus::Registry::new()),
+            )?
+            .with_profiling(self.profiling),
+        );
         let sync_metrics = ContractSyncMetrics::new(metrics.clone());
 
-        let db = DB::from_path(&self.db)?;
+        let db = self.open_db()?;
+        metrics.register_db(&db)?;
+        // relay submitters built below resume their tasks across restarts
+        nomad_ethereum::persist_relay_tasks(TypedDB::new("relay_tasks".to_owned(), db.clone()));
+        if let Some(path) = &self.audit_file {
+            crate::mirror_audit_log(path)
+                .wrap_err_with(|| format!("opening audit file {}", path))?;
+        }
+        // providers built below classify errors with the configured patterns
+        nomad_ethereum::set_rpc_error_patterns(&self.rpc_error_patterns)?;
         let home = Arc::new(
             self.try_caching_home(name, db.clone(), sync_metrics.clone())
                 .await?,
@@ -423,6 +619,14 @@ impl Settings {
         let replicas = self
             .try_caching_replicas(name, db.clone(), sync_metrics.clone())
             .await?;
+        // updates stored before signatures were normalized are rewritten once
+        for entity in std::iter::once(home.name()).chain(replicas.keys().map(String::as_str)) {
+            let normalized = NomadDB::new(entity, db.clone()).normalize_update_signatures()?;
+            if normalized > 0 {
+                info!(entity, normalized, "Normalized stored update signatures");
+            }
+        }
+        self.check_contract_code(&metrics).await?;
 
         Ok(AgentCore {
             home,
@@ -431,9 +635,76 @@ impl Settings {
             settings: self.clone(),
             metrics,
             indexer: self.index.clone(),
+            cancellation: Default::default(),
         })
     }
 
+    /// Check that the deployed code of the home, each replica and each
+    /// connection manager has every function the agent calls. Reports each
+    /// contract's implementation and missing functions in `/status`, and
+    /// warns or fails per the `code_check` setting.
+    pub async fn check_contract_code(&self, metrics: &CoreMetrics) -> Result<()> {
+        if self.code_check.is_off() {
+            return Ok(());
+        }
+
+        let setups = std::iter::once((&self.home, ContractKind::Home))
+            .chain(
+                self.replicas
+                    .values()
+                    .map(|setup| (setup, ContractKind::Replica)),
+            )
+            .chain(
+                self.managers
+                    .iter()
+                    .flat_map(HashMap::values)
+                    .map(|setup| (setup, ContractKind::ConnectionManager)),
+            );
+
+        let mut incomplete = vec![];
+        for (setup, kind) in setups {
+            let conn = match &setup.chain {
+                ChainConf::Ethereum(conn) => conn.clone(),
+                ChainConf::Substrate(_) => continue,
+            };
+            let address = setup
+                .address
+                .expect("eth ChainSetup missing address")
+                .as_ethereum_address()?;
+            let implementation = setup
+                .implementation
+                .map(|implementation| implementation.as_ethereum_address())
+                .transpose()?;
+
+            let report =
+                nomad_ethereum::check_contract_code(conn, kind, address, implementation).await?;
+            metrics.set_status(
+                "contracts",
+                &format!("{:?}_{}", kind, setup.name).to_lowercase(),
+                serde_json::to_value(&report).expect("!serialize"),
+            );
+            if !report.is_complete() {
+                warn!(
+                    contract = setup.name.as_str(),
+                    kind = ?kind,
+                    address = %ChecksumAddress(report.address),
+                    implementation = ?report.implementation.map(ChecksumAddress),
+                    missing = ?report.missing,
+                    "Deployed code lacks functions the agent calls"
+                );
+                incomplete.push(format!("{:?} on {}", kind, setup.name));
+            }
+        }
+
+        if self.code_check == CodeCheck::Strict && !incomplete.is_empty() {
+            bail!(
+                "Deployed code lacks functions the agent calls: {}",
+                incomplete.join(", ")
+            );
+        }
+        Ok(())
+    }
+
     /// Instantiate Settings block from NomadConfig
     pub fn from_config_and_secrets(
         agent_name: &str,
@@ -446,7 +717,21 @@ impl Settings {
 
         let db = agent.db.to_str().expect("!db").to_owned();
         let metrics = agent.metrics;
-        let index = IndexSettings::from_agent_name(agent_name);
+        let profiling = agent.profiling;
+        let code_check = agent.code_check;
+        let audit_file = agent
+            .audit_file
+            .as_ref()
+            .map(|path| path.to_str().expect("!audit file").to_owned());
+        let rpc_error_patterns = agent.rpc_error_patterns.clone();
+        let db_write_batch = agent.db_write_batch;
+        let db_encryption_migrate = agent.db_encryption_migrate();
+        let signer_claims = agent.signer_claims.clone().unwrap_or_default();
+        let index = IndexSettings {
+            max_block_range: agent.max_block_range,
+            backoff: agent.index_backoff.unwrap_or_default(),
+            ..IndexSettings::from_agent_name(agent_name)
+        };
 
         let home = ChainSetup::from_config_and_secrets(
             ChainSetupType::Home { home_network },
@@ -471,9 +756,10 @@ impl Settings {
             })
             .collect();
 
-        // Create connection managers if watcher
+        // Create connection managers if watcher, which unenrolls replicas,
+        // or processor, which monitors their enrollment
         let managers: Option<HashMap<String, ChainSetup>> =
-            if agent_name.to_lowercase() == "watcher" {
+            if matches!(agent_name.to_lowercase().as_str(), "watcher" | "processor") {
                 Some(
                     remote_networks
                         .iter()
@@ -494,9 +780,33 @@ impl Settings {
             };
 
         let gas: HashMap<String, NomadGasConfig> = config.gas().clone();
+        let expected_updater = config
+            .protocol()
+            .networks
+            .get(home_network)
+            .map(|network| network.configuration.updater)
+            .filter(|updater| !updater.is_zero());
+        let updater_interval = Some(agent.updater.interval).filter(|interval| *interval > 0);
+        let governance_routers = remote_networks
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(home_network))
+            .filter_map(|network| match config.core().get(network) {
+                Some(CoreDeploymentInfo::Ethereum(core)) => {
+                    Some((network.to_owned(), core.governance_router.proxy))
+                }
+                _ => None,
+            })
+            .collect();
         Self {
             db,
             metrics,
+            profiling,
+            code_check,
+            audit_file,
+            rpc_error_patterns,
+            db_write_batch,
+            signer_claims,
             home,
             replicas,
             managers,
@@ -505,6 +815,75 @@ impl Settings {
             logging: agent.logging,
             submitters: secrets.tx_submitters.clone(),
             attestation_signer: secrets.attestation_signer.clone(),
+            signer_roles: secrets.signer_roles.clone(),
+            top_up: agent.top_up.clone(),
+            top_up_funders: secrets.top_up_funders.clone(),
+            db_encryption_key: secrets.db_encryption_key.clone(),
+            db_encryption_migrate,
+            expected_updater,
+            updater_interval,
+            governance_routers,
+        }
+    }
+
+    /// Check the settings of the agent named `agent_name` before it is
+    /// built. Run by the settings loader and by [`SettingsBuilder`].
+    pub fn validate(&self, agent_name: &str) -> Result<()> {
+        ensure!(
+            !self.replicas.is_empty(),
+            "Must pass in at least one replica"
+        );
+
+        let setups = std::iter::once(&self.home)
+            .chain(self.replicas.values())
+            .chain(self.managers.iter().flat_map(HashMap::values));
+        for setup in setups {
+            setup.validate()?;
+        }
+        for (key, setup) in self.replicas.iter() {
+            ensure!(
+                key == &setup.name,
+                "Replica key does not match replica name:\n key: {}  name: {}",
+                key,
+                setup.name
+            );
+        }
+        for network in self.managers.iter().flat_map(HashMap::keys) {
+            ensure!(
+                self.replicas.contains_key(network),
+                "Connection manager for {} has no replica",
+                network
+            );
+        }
+
+        let networks: HashSet<String> = std::iter::once(&self.home.name)
+            .chain(self.replicas.keys())
+            .cloned()
+            .collect();
+        for network in networks.iter() {
+            ensure!(
+                self.submitters.contains_key(network),
+                "No transaction submitter for {}",
+                network
+            );
+        }
+        self.secrets().validate(agent_name, &networks)?;
+
+        Ok(())
+    }
+
+    /// The secrets block these settings hold the values of
+    fn secrets(&self) -> AgentSecrets {
+        AgentSecrets {
+            rpcs: std::iter::once(&self.home)
+                .chain(self.replicas.values())
+                .map(|setup| (setup.name.clone(), setup.chain.clone()))
+                .collect(),
+            tx_submitters: self.submitters.clone(),
+            attestation_signer: self.attestation_signer.clone(),
+            signer_roles: self.signer_roles.clone(),
+            top_up_funders: self.top_up_funders.clone(),
+            db_encryption_key: self.db_encryption_key.clone(),
         }
     }
 
@@ -520,9 +899,26 @@ impl Settings {
         let agent = config.agent().get(home_network).unwrap();
         assert_eq!(self.db, agent.db.to_str().unwrap());
         assert_eq!(self.metrics, agent.metrics);
+        assert_eq!(self.profiling, agent.profiling);
+        assert_eq!(self.code_check, agent.code_check);
+        assert_eq!(
+            self.audit_file.as_deref(),
+            agent.audit_file.as_ref().and_then(|path| path.to_str())
+        );
+        assert_eq!(self.rpc_error_patterns, agent.rpc_error_patterns);
+        assert_eq!(self.db_write_batch, agent.db_write_batch);
+        assert_eq!(self.db_encryption_migrate, agent.db_encryption_migrate());
+        assert_eq!(
+            self.signer_claims,
+            agent.signer_claims.clone().unwrap_or_default()
+        );
         assert_eq!(self.logging, agent.logging);
 
-        let index_settings = IndexSettings::from_agent_name(agent_name);
+        let index_settings = IndexSettings {
+            max_block_range: agent.max_block_range,
+            backoff: agent.index_backoff.unwrap_or_default(),
+            ..IndexSettings::from_agent_name(agent_name)
+        };
         assert_eq!(self.index, index_settings);
 
         let config_home_domain = config
@@ -544,6 +940,7 @@ impl Settings {
         match config_home_core {
             CoreDeploymentInfo::Ethereum(core) => {
                 assert_eq!(self.home.address.unwrap(), core.home.proxy);
+                assert_eq!(self.home.implementation, Some(core.home.implementation));
                 assert_eq!(self.home.page_settings.from, core.deploy_height);
             }
             CoreDeploymentInfo::Substrate(core) => {
@@ -576,10 +973,9 @@ impl Settings {
             let config_replica_core = config.core().get(remote_network).unwrap();
             match config_replica_core {
                 CoreDeploymentInfo::Ethereum(core) => {
-                    assert_eq!(
-                        replica_setup.address.unwrap(),
-                        core.replicas.get(home_network).unwrap().proxy
-                    );
+                    let replica = core.replicas.get(home_network).unwrap();
+                    assert_eq!(replica_setup.address.unwrap(), replica.proxy);
+                    assert_eq!(replica_setup.implementation, Some(replica.implementation));
                     assert_eq!(replica_setup.page_settings.from, core.deploy_height);
                 }
                 CoreDeploymentInfo::Substrate(_) => {
@@ -596,6 +992,10 @@ impl Settings {
             assert_eq!(signer, secret_submitter);
         }
 
+        assert_eq!(self.attestation_signer, secrets.attestation_signer);
+        assert_eq!(self.signer_roles, secrets.signer_roles);
+        assert_eq!(self.db_encryption_key, secrets.db_encryption_key);
+
         Ok(())
     }
 }
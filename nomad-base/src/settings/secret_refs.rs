@@ -0,0 +1,433 @@
+//! Secret indirection.
+//!
+//! A secret value may be given as a reference instead of inline:
+//!
+//! - `${file:/path/to/secret}` reads the file. One trailing newline is
+//!   stripped.
+//! - `${env:NAME}` reads another environment variable
+//! - `${aws-sm:secret-name}` reads the string value of an AWS Secrets
+//!   Manager secret. Requires the `aws-secrets-manager` feature.
+//!
+//! References are resolved once at load time, before secrets are parsed and
+//! validated. Errors name where the reference was found, never the value.
+
+use async_trait::async_trait;
+use serde_json::Value;
+use std::{path::PathBuf, sync::Arc};
+
+/// Shown in place of resolved secret values
+pub const REDACTED: &str = "<redacted>";
+
+/// A reference to a secret held elsewhere
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretRef {
+    /// `${file:<path>}`
+    File(PathBuf),
+    /// `${env:<name>}`
+    Env(String),
+    /// `${aws-sm:<secret name>}`
+    AwsSecretsManager(String),
+}
+
+/// Errors resolving secret references. `path` is where the reference was
+/// found, e.g. an environment variable name or a JSON path.
+#[derive(thiserror::Error, Debug)]
+pub enum SecretError {
+    /// A reference with a known source and nothing to reference
+    #[error("{path}: malformed secret reference")]
+    Malformed {
+        /// Location of the reference
+        path: String,
+    },
+    /// Reading a referenced file failed
+    #[error("{path}: failed to read secret file {file:?}: {source}")]
+    File {
+        /// Location of the reference
+        path: String,
+        /// Referenced file
+        file: PathBuf,
+        /// Underlying error
+        source: std::io::Error,
+    },
+    /// A referenced environment variable is not set
+    #[error("{path}: referenced environment variable {var} is not set")]
+    Env {
+        /// Location of the reference
+        path: String,
+        /// Referenced variable
+        var: String,
+    },
+    /// No secret store is available for the reference
+    #[error("{path}: no secret store for {source_name} references. Is the aws-secrets-manager feature enabled?")]
+    Unsupported {
+        /// Location of the reference
+        path: String,
+        /// Reference source
+        source_name: &'static str,
+    },
+    /// The secret store failed to return the secret
+    #[error("{path}: failed to fetch secret {name}: {message}")]
+    Store {
+        /// Location of the reference
+        path: String,
+        /// Secret name
+        name: String,
+        /// Store error
+        message: String,
+    },
+}
+
+impl SecretRef {
+    /// Parse `value` as a reference. Returns `None` for inline values,
+    /// including `${..}` values with an unknown source, which are often shell
+    /// templates rather than secrets.
+    pub fn parse(path: &str, value: &str) -> Option<Result<Self, SecretError>> {
+        let inner = value.strip_prefix("${")?.strip_suffix('}')?;
+        let (source, reference) = inner.split_once(':')?;
+        if !matches!(source, "file" | "env" | "aws-sm") {
+            return None;
+        }
+        if reference.is_empty() {
+            return Some(Err(SecretError::Malformed {
+                path: path.to_owned(),
+            }));
+        }
+
+        Some(Ok(match source {
+            "file" => Self::File(reference.into()),
+            "env" => Self::Env(reference.to_owned()),
+            _ => Self::AwsSecretsManager(reference.to_owned()),
+        }))
+    }
+}
+
+/// A remote store of named secrets
+#[async_trait]
+pub trait SecretStore: Send + Sync + std::fmt::Debug {
+    /// Fetch the string value of secret `name`
+    async fn fetch(&self, name: &str) -> Result<String, String>;
+}
+
+/// Resolved secret values, to be redacted from anything printed
+#[derive(Debug, Default, Clone)]
+pub struct Redactions(Vec<String>);
+
+impl Redactions {
+    fn push(&mut self, value: &str) {
+        if !value.is_empty() && !self.0.iter().any(|v| v == value) {
+            self.0.push(value.to_owned());
+            // replace longer values first so a value containing another is
+            // fully redacted
+            self.0.sort_by_key(|v| std::cmp::Reverse(v.len()));
+        }
+    }
+
+    /// Add the values of `other`
+    pub fn extend(&mut self, other: Redactions) {
+        for value in other.0 {
+            self.push(&value);
+        }
+    }
+
+    /// Replace every resolved value in `text`
+    pub fn redact(&self, text: &str) -> String {
+        self.0
+            .iter()
+            .fold(text.to_owned(), |text, value| text.replace(value, REDACTED))
+    }
+}
+
+/// Resolves secret references from files, the environment and an optional
+/// secret store
+#[derive(Debug, Default, Clone)]
+pub struct SecretResolver {
+    store: Option<Arc<dyn SecretStore>>,
+}
+
+impl SecretResolver {
+    /// Resolver using `store` for `${aws-sm:..}` references
+    pub fn with_store(store: Arc<dyn SecretStore>) -> Self {
+        Self { store: Some(store) }
+    }
+
+    /// Resolver for the agent. Uses AWS Secrets Manager when built with the
+    /// `aws-secrets-manager` feature.
+    pub async fn for_agent() -> Self {
+        #[cfg(feature = "aws-secrets-manager")]
+        let store: Option<Arc<dyn SecretStore>> =
+            Some(Arc::new(aws::AwsSecretsManager::new().await));
+        #[cfg(not(feature = "aws-secrets-manager"))]
+        let store = None;
+
+        Self { store }
+    }
+
+    /// Resolve `value` found at `path`. Returns `None` for inline values.
+    pub async fn resolve(&self, path: &str, value: &str) -> Result<Option<String>, SecretError> {
+        let reference = match SecretRef::parse(path, value) {
+            Some(reference) => reference?,
+            None => return Ok(None),
+        };
+
+        let resolved = match reference {
+            SecretRef::File(file) => {
+                let contents =
+                    std::fs::read_to_string(&file).map_err(|source| SecretError::File {
+                        path: path.to_owned(),
+                        file: file.clone(),
+                        source,
+                    })?;
+                let contents = contents.strip_suffix('\n').unwrap_or(&contents);
+                contents.strip_suffix('\r').unwrap_or(contents).to_owned()
+            }
+            SecretRef::Env(var) => std::env::var(&var).map_err(|_| SecretError::Env {
+                path: path.to_owned(),
+                var,
+            })?,
+            SecretRef::AwsSecretsManager(name) => {
+                let store = self
+                    .store
+                    .as_ref()
+                    .ok_or_else(|| SecretError::Unsupported {
+                        path: path.to_owned(),
+                        source_name: "aws-sm",
+                    })?;
+                store
+                    .fetch(&name)
+                    .await
+                    .map_err(|message| SecretError::Store {
+                        path: path.to_owned(),
+                        name,
+                        message,
+                    })?
+            }
+        };
+        Ok(Some(resolved))
+    }
+
+    /// Resolve references in environment variables, replacing each with its
+    /// value so the secrets loaders read resolved values
+    pub async fn resolve_env(&self) -> Result<Redactions, SecretError> {
+        let mut redactions = Redactions::default();
+        for (var, value) in std::env::vars() {
+            if let Some(resolved) = self.resolve(&var, &value).await? {
+                std::env::set_var(&var, &resolved);
+                redactions.push(&resolved);
+            }
+        }
+        Ok(redactions)
+    }
+
+    /// Resolve references in the string values of a JSON document, such as
+    /// a secrets file
+    pub async fn resolve_json(&self, value: &mut Value) -> Result<Redactions, SecretError> {
+        let mut redactions = Redactions::default();
+        let mut pending = vec![(String::new(), value)];
+
+        while let Some((path, value)) = pending.pop() {
+            match value {
+                Value::String(s) => {
+                    if let Some(resolved) = self.resolve(&path, s).await? {
+                        redactions.push(&resolved);
+                        *s = resolved;
+                    }
+                }
+                Value::Array(items) => pending.extend(
+                    items
+                        .iter_mut()
+                        .enumerate()
+                        .map(|(i, item)| (format!("{}[{}]", path, i), item)),
+                ),
+                Value::Object(fields) => pending.extend(fields.iter_mut().map(|(k, v)| {
+                    let path = if path.is_empty() {
+                        k.clone()
+                    } else {
+                        format!("{}.{}", path, k)
+                    };
+                    (path, v)
+                })),
+                _ => {}
+            }
+        }
+        Ok(redactions)
+    }
+}
+
+#[cfg(feature = "aws-secrets-manager")]
+mod aws {
+    use super::SecretStore;
+    use async_trait::async_trait;
+    use rusoto_secretsmanager::{GetSecretValueRequest, SecretsManager, SecretsManagerClient};
+
+    /// AWS Secrets Manager, using the shared AWS client and the region from
+    /// the environment
+    #[derive(Debug)]
+    pub(super) struct AwsSecretsManager(SecretsManagerClient);
+
+    impl AwsSecretsManager {
+        pub(super) async fn new() -> Self {
+            let client = nomad_core::aws::get_client().await.clone();
+            Self(SecretsManagerClient::new_with_client(
+                client,
+                Default::default(),
+            ))
+        }
+    }
+
+    #[async_trait]
+    impl SecretStore for AwsSecretsManager {
+        async fn fetch(&self, name: &str) -> Result<String, String> {
+            let response = self
+                .0
+                .get_secret_value(GetSecretValueRequest {
+                    secret_id: name.to_owned(),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|e| e.to_string())?;
+            response
+                .secret_string
+                .ok_or_else(|| "secret has no string value".to_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Default)]
+    struct MockStore(HashMap<String, String>);
+
+    #[async_trait]
+    impl SecretStore for MockStore {
+        async fn fetch(&self, name: &str) -> Result<String, String> {
+            self.0
+                .get(name)
+                .cloned()
+                .ok_or_else(|| "ResourceNotFoundException".to_owned())
+        }
+    }
+
+    fn mock_resolver() -> SecretResolver {
+        SecretResolver::with_store(Arc::new(MockStore(HashMap::from([(
+            "prod/updater".to_owned(),
+            "aws-secret-value".to_owned(),
+        )]))))
+    }
+
+    #[test]
+    fn it_parses_secret_references() {
+        assert_eq!(SecretRef::parse("a", "0x1234").map(Result::unwrap), None);
+        assert_eq!(
+            SecretRef::parse("a", "${file:/run/key}").map(Result::unwrap),
+            Some(SecretRef::File("/run/key".into()))
+        );
+        assert_eq!(
+            SecretRef::parse("a", "${aws-sm:prod/updater}").map(Result::unwrap),
+            Some(SecretRef::AwsSecretsManager("prod/updater".to_owned()))
+        );
+        assert!(matches!(
+            SecretRef::parse("a", "${file:}"),
+            Some(Err(SecretError::Malformed { .. }))
+        ));
+
+        // unknown sources are left alone
+        for template in [
+            "${vault:key}",
+            "${debian_chroot:+($debian_chroot)}",
+            "${env}",
+        ] {
+            assert!(SecretRef::parse("a", template).is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn it_resolves_file_references() {
+        let file = std::env::temp_dir().join("nomad-base-secret-ref-test");
+        std::fs::write(&file, "file-secret-value\n").unwrap();
+        let reference = format!("${{file:{}}}", file.display());
+
+        let resolved = SecretResolver::default()
+            .resolve("ETHEREUM_TXSIGNER_KEY", &reference)
+            .await
+            .unwrap();
+        assert_eq!(resolved.as_deref(), Some("file-secret-value"));
+
+        std::fs::remove_file(&file).unwrap();
+        let err = SecretResolver::default()
+            .resolve("ETHEREUM_TXSIGNER_KEY", &reference)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("ETHEREUM_TXSIGNER_KEY: "));
+    }
+
+    #[tokio::test]
+    async fn it_resolves_env_references() {
+        std::env::set_var("NOMAD_SECRET_REF_TEST_TARGET", "env-secret-value");
+        std::env::set_var(
+            "NOMAD_SECRET_REF_TEST_KEY",
+            "${env:NOMAD_SECRET_REF_TEST_TARGET}",
+        );
+
+        let redactions = SecretResolver::default().resolve_env().await.unwrap();
+        assert_eq!(
+            std::env::var("NOMAD_SECRET_REF_TEST_KEY").unwrap(),
+            "env-secret-value"
+        );
+        assert_eq!(
+            redactions.redact("key is env-secret-value"),
+            "key is <redacted>"
+        );
+
+        let err = SecretResolver::default()
+            .resolve(
+                "NOMAD_SECRET_REF_TEST_KEY",
+                "${env:NOMAD_SECRET_REF_TEST_UNSET}",
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SecretError::Env { .. }));
+        std::env::remove_var("NOMAD_SECRET_REF_TEST_TARGET");
+        std::env::remove_var("NOMAD_SECRET_REF_TEST_KEY");
+    }
+
+    #[tokio::test]
+    async fn it_resolves_secret_store_references() {
+        let mut secrets = json!({
+            "rpcs": { "ethereum": { "rpcStyle": "ethereum", "connection": "https://rpc" } },
+            "txSubmitters": {
+                "ethereum": { "type": "local", "key": "${aws-sm:prod/updater}" }
+            },
+        });
+
+        let redactions = mock_resolver().resolve_json(&mut secrets).await.unwrap();
+        assert_eq!(
+            secrets["txSubmitters"]["ethereum"]["key"],
+            "aws-secret-value"
+        );
+        assert_eq!(secrets["rpcs"]["ethereum"]["connection"], "https://rpc");
+        assert!(!redactions
+            .redact(&secrets.to_string())
+            .contains("aws-secret-value"));
+
+        // errors name the path and never the value
+        let mut missing = json!({ "attestationSigner": { "key": "${aws-sm:prod/missing}" } });
+        let err = mock_resolver()
+            .resolve_json(&mut missing)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("attestationSigner.key: "), "{}", err);
+        assert!(!err.contains("aws-secret-value"));
+
+        // without a store, secret manager references are refused
+        let err = SecretResolver::default()
+            .resolve("DEFAULT_TXSIGNER_KEY", "${aws-sm:prod/updater}")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SecretError::Unsupported { .. }));
+    }
+}
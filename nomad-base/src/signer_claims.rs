@@ -0,0 +1,593 @@
+use color_eyre::Result;
+use ethers::types::Address;
+use nomad_core::{db::DbError, ChecksumAddress, Decode, Encode, NomadError};
+use nomad_xyz_configuration::agent::SignerClaimsConfig;
+use prometheus::IntGaugeVec;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{error, info, info_span, instrument::Instrumented, warn, Instrument};
+
+use crate::{CoreMetrics, CorridorSwitches, NomadDB, Settings};
+
+const SIGNER_CLAIM: &str = "signer_claim_";
+
+/// Heartbeats a claim may miss before it is no longer live
+const LIVE_HEARTBEATS: u64 = 3;
+
+/// Reason corridors are paused with while another process claims the
+/// signer of their destination
+pub const SHARED_SIGNER_REASON: &str = "transaction signer shared with";
+
+/// Identity of an agent process
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessIdentity {
+    /// Agent name
+    pub agent: String,
+    /// Host the process runs on
+    pub host: String,
+    /// Process id on the host
+    pub pid: u32,
+    /// Random id of this run of the process, telling apart processes that
+    /// reuse a host and pid, as containers do
+    pub instance: String,
+}
+
+impl ProcessIdentity {
+    /// Identity of the current process, running `agent`
+    pub fn current(agent: &str) -> Self {
+        Self {
+            agent: agent.to_owned(),
+            host: std::env::var("HOSTNAME").unwrap_or_else(|_| "unknown".to_owned()),
+            pid: std::process::id(),
+            instance: format!("{:016x}", rand::random::<u64>()),
+        }
+    }
+}
+
+impl std::fmt::Display for ProcessIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} on {} (pid {}, instance {})",
+            self.agent, self.host, self.pid, self.instance
+        )
+    }
+}
+
+/// A process's claim on submitting with a transaction signer on a network
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignerClaim {
+    /// Network the signer submits to
+    pub network: String,
+    /// Address of the signer
+    pub address: Address,
+    /// Process claiming the signer
+    pub process: ProcessIdentity,
+    /// Unix timestamp of the claim's latest heartbeat
+    pub heartbeat: u64,
+}
+
+impl SignerClaim {
+    /// Whether the claim was renewed within `LIVE_HEARTBEATS` heartbeats
+    /// of `now`
+    fn is_live(&self, now: u64, heartbeat_seconds: u64) -> bool {
+        now.saturating_sub(self.heartbeat) <= LIVE_HEARTBEATS * heartbeat_seconds
+    }
+}
+
+impl Encode for SignerClaim {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        writer.write_all(&json)?;
+        Ok(json.len())
+    }
+}
+
+impl Decode for SignerClaim {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let mut json = vec![];
+        reader.read_to_end(&mut json)?;
+        serde_json::from_slice(&json).map_err(|e| {
+            NomadError::IoError(std::io::Error::new(ErrorKind::InvalidData, e.to_string()))
+        })
+    }
+}
+
+/// Another live process claiming one of this process's signers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignerConflict {
+    /// Network of the signer
+    pub network: String,
+    /// Address of the signer
+    pub address: Address,
+    /// The other process
+    pub other: ProcessIdentity,
+}
+
+impl NomadDB {
+    /// Store this process's latest claim on a signer
+    ///
+    /// Keys --> Values:
+    /// - `network_address` --> `claim`
+    pub fn store_signer_claim(&self, claim: &SignerClaim) -> Result<(), DbError> {
+        self.store_encodable(
+            SIGNER_CLAIM,
+            claim_key(&claim.network, claim.address),
+            claim,
+        )
+    }
+
+    /// Retrieve the latest claim stored on a signer
+    pub fn retrieve_signer_claim(
+        &self,
+        network: &str,
+        address: Address,
+    ) -> Result<Option<SignerClaim>, DbError> {
+        self.retrieve_decodable(SIGNER_CLAIM, claim_key(network, address))
+    }
+}
+
+fn claim_key(network: &str, address: Address) -> String {
+    format!("{}_{:?}", network, address)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("!timestamp")
+        .as_secs()
+}
+
+/// Claims of a process on its transaction signers, to catch two processes
+/// submitting with one key on a chain. Their nonce managers do not see each
+/// other's transactions, so they race on nonces.
+///
+/// Every heartbeat stores a claim on each signer in the process's DB and,
+/// with a shared directory configured, writes it to a claim file there of
+/// its own. Claim files of other processes on the same network and signer
+/// renewed within `LIVE_HEARTBEATS` heartbeats are conflicts. They are
+/// logged with the other process's identity and exported as the
+/// `signer_conflicts` gauge. With `pause_on_conflict`, the corridor to the
+/// network is paused until the conflict clears.
+#[derive(Debug)]
+pub struct SignerClaims {
+    db: NomadDB,
+    process: ProcessIdentity,
+    signers: Vec<(String, Address)>,
+    config: SignerClaimsConfig,
+    corridors: Option<Arc<CorridorSwitches>>,
+    conflicts: IntGaugeVec,
+}
+
+impl SignerClaims {
+    /// Claims of `process` on `signers`, by network
+    pub fn new(
+        db: NomadDB,
+        process: ProcessIdentity,
+        signers: Vec<(String, Address)>,
+        config: SignerClaimsConfig,
+        metrics: &CoreMetrics,
+    ) -> Result<Self> {
+        let conflicts = metrics.new_int_gauge_vec(
+            "signer_conflicts",
+            "Number of other live processes claiming a transaction signer of the agent",
+            &["network", "signer", "agent"],
+        )?;
+
+        Ok(Self {
+            db,
+            process,
+            signers,
+            config,
+            corridors: None,
+            conflicts,
+        })
+    }
+
+    /// Pause corridors in `corridors` on conflicts, if configured to
+    pub fn with_corridors(mut self, corridors: Arc<CorridorSwitches>) -> Self {
+        self.corridors = Some(corridors);
+        self
+    }
+
+    /// Path of this process's claim file on a signer in `directory`
+    fn claim_path(&self, directory: &Path, network: &str, address: Address) -> PathBuf {
+        directory.join(format!(
+            "{}-{}.json",
+            claim_key(network, address),
+            self.process.instance
+        ))
+    }
+
+    /// Write `claim` to its file, through a temporary file so readers never
+    /// see it half written
+    fn write_claim_file(&self, directory: &Path, claim: &SignerClaim) -> Result<()> {
+        let path = self.claim_path(directory, &claim.network, claim.address);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(claim)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Live claims of other processes on a signer in `directory`
+    fn other_claims(
+        &self,
+        directory: &Path,
+        network: &str,
+        address: Address,
+        now: u64,
+    ) -> Result<Vec<SignerClaim>> {
+        let prefix = format!("{}-", claim_key(network, address));
+        let mut claims = vec![];
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|name| name.to_str());
+            if !matches!(name, Some(name) if name.starts_with(&prefix) && name.ends_with(".json")) {
+                continue;
+            }
+            // Claim files removed or replaced meanwhile are skipped
+            let claim: SignerClaim = match fs::read(&path)
+                .ok()
+                .and_then(|json| serde_json::from_slice(&json).ok())
+            {
+                Some(claim) => claim,
+                None => continue,
+            };
+            if claim.process.instance != self.process.instance
+                && claim.network == network
+                && claim.address == address
+                && claim.is_live(now, self.config.heartbeat_seconds)
+            {
+                claims.push(claim);
+            }
+        }
+        Ok(claims)
+    }
+
+    /// Renew the claims at unix time `now`, returning the conflicts found in
+    /// the shared directory
+    pub fn heartbeat(&self, now: u64) -> Result<Vec<SignerConflict>> {
+        let mut conflicts = vec![];
+        for (network, address) in &self.signers {
+            let claim = SignerClaim {
+                network: network.clone(),
+                address: *address,
+                process: self.process.clone(),
+                heartbeat: now,
+            };
+            self.db.store_signer_claim(&claim)?;
+
+            let directory = match &self.config.directory {
+                Some(directory) => directory,
+                None => continue,
+            };
+            self.write_claim_file(directory, &claim)?;
+            conflicts.extend(
+                self.other_claims(directory, network, *address, now)?
+                    .into_iter()
+                    .map(|other| SignerConflict {
+                        network: network.clone(),
+                        address: *address,
+                        other: other.process,
+                    }),
+            );
+        }
+        Ok(conflicts)
+    }
+
+    /// Renew the claims at unix time `now`, and alert on conflicts, pausing
+    /// or resuming corridors if configured to
+    pub fn check(&self, now: u64) -> Result<Vec<SignerConflict>> {
+        let conflicts = self.heartbeat(now)?;
+
+        for (network, address) in &self.signers {
+            let conflicting: Vec<_> = conflicts
+                .iter()
+                .filter(|conflict| &conflict.network == network && conflict.address == *address)
+                .collect();
+            let signer = ChecksumAddress(*address).to_string();
+            self.conflicts
+                .with_label_values(&[network, &signer, &self.process.agent])
+                .set(conflicting.len() as i64);
+
+            for conflict in &conflicting {
+                error!(
+                    network = network.as_str(),
+                    signer = signer.as_str(),
+                    process = %self.process,
+                    other = %conflict.other,
+                    "Another live process claims transaction signer {} on {}: {}. Their submissions race on its nonces.",
+                    signer,
+                    network,
+                    conflict.other,
+                );
+            }
+
+            if self.config.pause_on_conflict {
+                self.toggle_corridor(network, conflicting.first().map(|c| &c.other));
+            }
+        }
+
+        Ok(conflicts)
+    }
+
+    /// Pause the corridor to `network` while `other` claims its signer, and
+    /// resume it once no process does, unless paused for another reason
+    fn toggle_corridor(&self, network: &str, other: Option<&ProcessIdentity>) {
+        let corridors = match &self.corridors {
+            Some(corridors) => corridors,
+            None => return,
+        };
+        let state = match corridors.state(network) {
+            Some(state) => state,
+            None => return,
+        };
+        let paused_by_claims = state
+            .reason
+            .as_deref()
+            .map_or(false, |reason| reason.starts_with(SHARED_SIGNER_REASON));
+
+        match other {
+            Some(other) if state.enabled => {
+                corridors.pause(network, format!("{} {}", SHARED_SIGNER_REASON, other));
+            }
+            None if paused_by_claims => {
+                info!(
+                    network,
+                    "No other process claims the transaction signer anymore. Resuming corridor."
+                );
+                corridors.resume(network);
+            }
+            _ => {}
+        }
+    }
+
+    /// Check the claims every heartbeat
+    pub async fn run(self) -> Result<()> {
+        loop {
+            self.check(now())?;
+            sleep(self.config.heartbeat_interval()).await;
+        }
+    }
+}
+
+/// Spawn the heartbeat task claiming the transaction signers of `settings`,
+/// pausing corridors in `corridors` if configured to. Signers are resolved
+/// in the task, retried every heartbeat until they resolve, so a signer
+/// backend that is down at startup does not stop the agent.
+pub fn spawn_signer_claims(
+    settings: Settings,
+    agent: &str,
+    db: NomadDB,
+    corridors: Option<Arc<CorridorSwitches>>,
+    metrics: Arc<CoreMetrics>,
+) -> Instrumented<JoinHandle<Result<()>>> {
+    let span = info_span!("signer_claims");
+    let process = ProcessIdentity::current(agent);
+
+    tokio::spawn(async move {
+        let config = settings.signer_claims.clone();
+        let signers = loop {
+            match settings.resolve_signer_roles(&[]).await {
+                Ok(resolved) => {
+                    break resolved
+                        .into_iter()
+                        .filter_map(|signer| Some((signer.network?, signer.address)))
+                        .collect()
+                }
+                Err(e) => {
+                    warn!(error = %e, "Could not resolve transaction signers to claim. Retrying.");
+                    sleep(config.heartbeat_interval()).await;
+                }
+            }
+        };
+        info!(
+            process = %process,
+            directory = ?config.directory,
+            "Claiming transaction signers"
+        );
+
+        let mut claims = SignerClaims::new(db, process, signers, config, &metrics)?;
+        if let Some(corridors) = corridors {
+            claims = claims.with_corridors(corridors);
+        }
+        claims.run().await
+    })
+    .instrument(span)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nomad_test::test_utils::setup_db;
+
+    const HEARTBEAT_SECONDS: u64 = 30;
+
+    fn metrics() -> Arc<CoreMetrics> {
+        Arc::new(
+            CoreMetrics::new(
+                "signer_claims_test",
+                "ethereum",
+                None,
+                Arc::new(prometheus::Registry::new()),
+            )
+            .expect("could not make metrics"),
+        )
+    }
+
+    fn process(agent: &str, instance: &str) -> ProcessIdentity {
+        ProcessIdentity {
+            agent: agent.to_owned(),
+            host: "host".to_owned(),
+            pid: 7,
+            instance: instance.to_owned(),
+        }
+    }
+
+    /// A temporary directory removed on drop
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("{}-{:016x}", name, rand::random::<u64>()));
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn claims(
+        db: &TempDir,
+        directory: &TempDir,
+        process: ProcessIdentity,
+        pause_on_conflict: bool,
+        metrics: &CoreMetrics,
+    ) -> SignerClaims {
+        let db = setup_db(db.0.to_str().unwrap().to_owned());
+        SignerClaims::new(
+            NomadDB::new("ethereum", db),
+            process,
+            vec![
+                ("ethereum".to_owned(), Address::repeat_byte(1)),
+                ("moonbeam".to_owned(), Address::repeat_byte(1)),
+            ],
+            SignerClaimsConfig {
+                directory: Some(directory.0.clone()),
+                heartbeat_seconds: HEARTBEAT_SECONDS,
+                pause_on_conflict,
+            },
+            metrics,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn it_detects_live_claims_of_other_processes() {
+        let shared = TempDir::new("signer-claims-shared");
+        let (db_a, db_b) = (
+            TempDir::new("signer-claims-a"),
+            TempDir::new("signer-claims-b"),
+        );
+        let metrics = metrics();
+        let relayer = claims(&db_a, &shared, process("relayer", "a"), false, &metrics);
+        let processor = claims(&db_b, &shared, process("processor", "b"), false, &metrics);
+
+        // Alone, and heartbeating again, a process has no conflict
+        assert!(relayer.check(1000).unwrap().is_empty());
+        assert!(relayer.check(1030).unwrap().is_empty());
+        assert_eq!(
+            relayer
+                .db
+                .retrieve_signer_claim("moonbeam", Address::repeat_byte(1))
+                .unwrap()
+                .map(|claim| claim.heartbeat),
+            Some(1030)
+        );
+
+        // The second process sees the first on both networks
+        let conflicts = processor.check(1040).unwrap();
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(
+            conflicts[0],
+            SignerConflict {
+                network: "ethereum".to_owned(),
+                address: Address::repeat_byte(1),
+                other: process("relayer", "a"),
+            }
+        );
+        let gauge = |agent: &str, network: &str| {
+            metrics
+                .gather()
+                .map(|report| {
+                    String::from_utf8(report).unwrap().lines().any(|line| {
+                        line.starts_with("nomad_signer_conflicts{")
+                            && line.contains(&format!("agent=\"{}\"", agent))
+                            && line.contains(&format!("network=\"{}\"", network))
+                            && line.ends_with(" 1")
+                    })
+                })
+                .unwrap()
+        };
+        assert!(gauge("processor", "moonbeam"));
+        assert!(!gauge("relayer", "moonbeam"));
+
+        // And the first the second
+        assert_eq!(relayer.check(1060).unwrap().len(), 2);
+
+        // Until the second stops heartbeating
+        let stale = 1040 + LIVE_HEARTBEATS * HEARTBEAT_SECONDS + 1;
+        assert!(relayer.check(stale).unwrap().is_empty());
+        assert!(!gauge("relayer", "moonbeam"));
+    }
+
+    #[test]
+    fn it_pauses_corridors_while_conflicting() {
+        let shared = TempDir::new("signer-claims-shared");
+        let (db_a, db_b) = (
+            TempDir::new("signer-claims-a"),
+            TempDir::new("signer-claims-b"),
+        );
+        let metrics = metrics();
+        let corridors = Arc::new(
+            CorridorSwitches::new(
+                "ethereum",
+                "processor",
+                vec!["moonbeam".to_owned(), "evmos".to_owned()],
+                &Default::default(),
+                metrics.clone(),
+            )
+            .unwrap(),
+        );
+        let relayer = claims(&db_a, &shared, process("relayer", "a"), false, &metrics);
+        let processor = claims(&db_b, &shared, process("processor", "b"), true, &metrics)
+            .with_corridors(corridors.clone());
+
+        relayer.check(1000).unwrap();
+        processor.check(1000).unwrap();
+        let state = corridors.state("moonbeam").unwrap();
+        assert!(!state.enabled);
+        assert_eq!(
+            state.reason.as_deref(),
+            Some("transaction signer shared with relayer on host (pid 7, instance a)")
+        );
+        // Corridors to networks of other signers are left alone
+        assert!(corridors.is_enabled("evmos"));
+
+        // Resumed once the other process stops claiming the signer
+        processor.check(2000).unwrap();
+        assert!(corridors.is_enabled("moonbeam"));
+
+        // But not if paused for another reason
+        relayer.check(3000).unwrap();
+        corridors.pause("moonbeam", "bridge upgrade");
+        processor.check(3000).unwrap();
+        processor.check(4000).unwrap();
+        assert_eq!(
+            corridors.state("moonbeam").unwrap().reason.as_deref(),
+            Some("bridge upgrade")
+        );
+    }
+}
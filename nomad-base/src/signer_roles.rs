@@ -0,0 +1,185 @@
+use color_eyre::Result;
+use ethers::{signers::Signer, types::Address};
+use nomad_core::{ChecksumAddress, FromSignerConf};
+use nomad_xyz_configuration::{agent::SignerConf, ethereum, TxSubmitterConf};
+use std::collections::BTreeSet;
+use tracing::{info, warn};
+
+use crate::{AttestationSigner, Settings};
+
+/// Role of the key that signs and pays for transactions, one per network
+pub const TRANSACTION_SIGNER_ROLE: &str = "transactionSigner";
+
+/// A signer role resolved to its address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedSigner {
+    /// Role name
+    pub role: String,
+    /// Network, for per-network roles
+    pub network: Option<String>,
+    /// Address of the role's key
+    pub address: Address,
+    /// Whether the role was configured on its own rather than falling back
+    /// to the legacy single key
+    pub explicit: bool,
+}
+
+impl std::fmt::Display for ResolvedSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.network {
+            Some(network) => write!(f, "{} on {}", self.role, network),
+            None => write!(f, "{}", self.role),
+        }
+    }
+}
+
+/// Errors in the configured signer roles
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum SignerRoleError {
+    /// A required role has no key
+    #[error("No signer configured for role {0}")]
+    Missing(String),
+    /// Two roles configured to use distinct keys resolve to one address
+    #[error("Signer roles {first} and {second} share address {address}")]
+    SharedAddress {
+        /// First role
+        first: String,
+        /// Second role
+        second: String,
+        /// Shared address
+        address: String,
+    },
+}
+
+/// Ensure explicitly configured roles do not share an address. Roles that
+/// fall back to the legacy key, and one role on several networks, may share.
+pub fn check_distinct(signers: &[ResolvedSigner]) -> Result<(), SignerRoleError> {
+    let explicit: Vec<_> = signers.iter().filter(|s| s.explicit).collect();
+    for (i, first) in explicit.iter().enumerate() {
+        for second in &explicit[i + 1..] {
+            if first.role != second.role && first.address == second.address {
+                return Err(SignerRoleError::SharedAddress {
+                    first: first.to_string(),
+                    second: second.to_string(),
+                    address: ChecksumAddress(first.address).to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn address_of(conf: &SignerConf) -> Result<Address> {
+    Ok(AttestationSigner::try_from_signer_conf(conf)
+        .await?
+        .address())
+}
+
+impl Settings {
+    /// Resolve the transaction signer of every network the agent uses and
+    /// each of `roles`, log their addresses and refuse distinct roles that
+    /// share an address. Node signers and substrate submitters have no
+    /// address to check and are skipped.
+    pub async fn resolve_signer_roles(&self, roles: &[&str]) -> Result<Vec<ResolvedSigner>> {
+        let mut networks = BTreeSet::new();
+        networks.insert(self.home.name.as_str());
+        networks.extend(self.replicas.keys().map(String::as_str));
+        if let Some(managers) = &self.managers {
+            networks.extend(managers.keys().map(String::as_str));
+        }
+
+        let mut resolved = vec![];
+        for network in networks {
+            let conf = match self.submitters.get(network) {
+                Some(TxSubmitterConf::Ethereum(ethereum::TxSubmitterConf::Local(conf))) => conf,
+                Some(TxSubmitterConf::Ethereum(ethereum::TxSubmitterConf::Gelato(gelato))) => {
+                    &gelato.sponsor
+                }
+                _ => {
+                    warn!(
+                        network,
+                        "No local transaction signer to check for {}", network
+                    );
+                    continue;
+                }
+            };
+            if *conf == SignerConf::Node {
+                continue;
+            }
+            resolved.push(ResolvedSigner {
+                role: TRANSACTION_SIGNER_ROLE.to_owned(),
+                network: Some(network.to_owned()),
+                address: address_of(conf).await?,
+                explicit: true,
+            });
+        }
+
+        for role in roles {
+            let conf = self
+                .role_signer(role)
+                .ok_or_else(|| SignerRoleError::Missing(role.to_string()))?;
+            if *conf == SignerConf::Node {
+                continue;
+            }
+            resolved.push(ResolvedSigner {
+                role: role.to_string(),
+                network: None,
+                address: address_of(conf).await?,
+                explicit: self.signer_roles.contains_key(*role),
+            });
+        }
+
+        for signer in &resolved {
+            info!(
+                role = signer.role.as_str(),
+                network = signer.network.as_deref().unwrap_or_default(),
+                address = %ChecksumAddress(signer.address),
+                explicit = signer.explicit,
+                "Resolved signer role {}",
+                signer,
+            );
+        }
+
+        check_distinct(&resolved)?;
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn signer(role: &str, network: Option<&str>, byte: u8, explicit: bool) -> ResolvedSigner {
+        ResolvedSigner {
+            role: role.to_owned(),
+            network: network.map(ToOwned::to_owned),
+            address: Address::repeat_byte(byte),
+            explicit,
+        }
+    }
+
+    #[test]
+    fn it_refuses_distinct_roles_sharing_an_address() {
+        // one transaction signer across networks is fine
+        let mut signers = vec![
+            signer(TRANSACTION_SIGNER_ROLE, Some("ethereum"), 1, true),
+            signer(TRANSACTION_SIGNER_ROLE, Some("moonbeam"), 1, true),
+            signer("watcherKey", None, 2, true),
+        ];
+        assert_eq!(check_distinct(&signers), Ok(()));
+
+        // the legacy key is not checked against the transaction signer
+        signers.push(signer("attestationSigner", None, 1, false));
+        assert_eq!(check_distinct(&signers), Ok(()));
+
+        signers[2].address = Address::repeat_byte(1);
+        assert_eq!(
+            check_distinct(&signers),
+            Err(SignerRoleError::SharedAddress {
+                first: "transactionSigner on ethereum".to_owned(),
+                second: "watcherKey".to_owned(),
+                address: "0x0101010101010101010101010101010101010101".to_owned(),
+            })
+        );
+    }
+}
@@ -0,0 +1,500 @@
+#![deny(clippy::cast_possible_truncation)]
+
+use color_eyre::{eyre::bail, Result};
+use ethers::core::types::H256;
+use nomad_core::{
+    accumulator::{Merkle, NomadLightMerkle},
+    CommonIndexer, Decode, Encode, RawCommittedMessage, SignedUpdateWithMeta,
+};
+use nomad_types::CheckedCast;
+use std::io::{Read, Write};
+
+use crate::{contract_sync::HomeContractSyncDB, NomadDB};
+
+const MAGIC: [u8; 8] = *b"NMDSNAP\0";
+const VERSION: u8 = 2;
+
+/// Longest encoded message read from a snapshot: leaf index, committed root,
+/// message header and the home's 2 KiB body limit
+const MAX_ENCODED_MESSAGE_LEN: usize = 4 + 32 + 76 + 2 * 1024;
+/// Most messages preallocated for while reading a snapshot. Larger snapshots
+/// grow as their messages are read.
+const MAX_PREALLOCATED_MESSAGES: usize = 1 << 16;
+
+/// Errors that reject a tree snapshot
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum SnapshotError {
+    /// Input does not start with the snapshot magic bytes
+    #[error("Not a tree snapshot")]
+    BadMagic,
+    /// Snapshot was written by an unknown format version
+    #[error("Unsupported snapshot version {0}")]
+    UnsupportedVersion(u8),
+    /// Leaf at `position` has the wrong leaf index
+    #[error("Leaf at position {position} has leaf index {leaf_index}")]
+    OutOfOrder {
+        /// Position in the snapshot
+        position: u32,
+        /// Leaf index of the message at that position
+        leaf_index: u32,
+    },
+    /// Leaves do not reconstruct the snapshot root
+    #[error("Reconstructed root {actual:?} does not match snapshot root {expected:?}")]
+    RootMismatch {
+        /// Root in the snapshot header
+        expected: H256,
+        /// Root of the snapshot leaves
+        actual: H256,
+    },
+    /// No update on the home committed the snapshot root at the snapshot block
+    #[error("No update on domain {domain} committed root {root:?} at block {block_number}")]
+    NotCommitted {
+        /// Home domain
+        domain: u32,
+        /// Snapshot root
+        root: H256,
+        /// Snapshot block
+        block_number: u64,
+    },
+    /// The db already holds messages or updates
+    #[error("Refusing to bootstrap a non-empty db")]
+    DbNotEmpty,
+    /// The last committed leaf was not found dispatched on chain
+    #[error("Leaf {leaf_index} was not dispatched on chain at or before block {block_number}")]
+    NotDispatched {
+        /// Leaf index of the last committed leaf
+        leaf_index: u32,
+        /// Snapshot block the search started from
+        block_number: u64,
+    },
+    /// The last committed leaf differs from the one dispatched on chain
+    #[error("Leaf {leaf_index} differs from the leaf dispatched on chain")]
+    DispatchMismatch {
+        /// Leaf index of the last committed leaf
+        leaf_index: u32,
+    },
+    /// A length in the snapshot is out of bounds
+    #[error("Snapshot {field} of {length} exceeds {max}")]
+    TooLong {
+        /// Field holding the length
+        field: &'static str,
+        /// Length in the snapshot
+        length: u64,
+        /// Largest accepted length
+        max: u64,
+    },
+}
+
+/// Compact snapshot of a home's message tree, used to bootstrap new agents
+/// without indexing from the home's deploy height.
+///
+/// Format: magic, version, domain, block number, message block number, leaf
+/// count, committed leaf count and root, then each raw committed message
+/// prefixed by its length. Integers are big-endian.
+///
+/// A snapshot is trusted only as far as its root: the committed leaves must
+/// rebuild the root, and an update committing the root must exist on chain
+/// at the snapshot block. Leaves indexed after them, up to the message block
+/// number, are not covered by this check, so bootstrapping stores only the
+/// committed leaves and indexes the rest from chain. The `committed_root`
+/// recorded with each message is not covered either.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeSnapshot {
+    /// Home domain
+    pub domain: u32,
+    /// Block of the update that committed `root`
+    pub block_number: u64,
+    /// Block messages were indexed up to
+    pub message_block_number: u64,
+    /// Tree root after the committed messages
+    pub root: H256,
+    /// Number of leading messages under `root`
+    pub committed: u32,
+    /// Messages in leaf index order, starting at 0, up to the message block
+    /// number
+    pub messages: Vec<RawCommittedMessage>,
+}
+
+impl TreeSnapshot {
+    /// Snapshot the db's messages up to its message sync cursor, with the
+    /// latest update's root. Returns `None` if the db has no updates.
+    pub fn from_db(db: &NomadDB) -> Result<Option<Self>> {
+        let root = match db.retrieve_latest_root()? {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+        let domain = match db.update_by_new_root(root)? {
+            Some(update) => update.update.home_domain,
+            None => bail!("Missing update for latest root {:?}", root),
+        };
+        let block_number = match db.retrieve_update_metadata(root)? {
+            Some(meta) => meta.block_number,
+            None => bail!("Missing metadata for latest update root {:?}", root),
+        };
+
+        let message_block_number = match db.retrieve_message_latest_block_end() {
+            Some(block) => u64::from(block),
+            None => bail!("No messages indexed under latest root {:?}", root),
+        };
+
+        // every leaf up to the latest indexed one, so indexing can resume
+        // from the message cursor without missing any
+        let mut tree = NomadLightMerkle::default();
+        let mut committed = (tree.root() == root).then_some(0);
+        let mut messages = vec![];
+        let count = db
+            .retrieve_latest_leaf_index()?
+            .map_or(0, |latest| latest.saturating_add(1));
+        for leaf_index in 0..count {
+            let message = match db.message_by_leaf_index(leaf_index)? {
+                Some(message) => message,
+                None => bail!(
+                    "Missing leaf index {} before latest leaf index {}",
+                    leaf_index,
+                    count - 1
+                ),
+            };
+            tree.ingest(message.leaf())?;
+            messages.push(message);
+            if committed.is_none() && tree.root() == root {
+                committed = Some(leaf_index + 1);
+            }
+        }
+        let committed = match committed {
+            Some(committed) => committed,
+            None => bail!("Indexed leaves never reach latest root {:?}", root),
+        };
+
+        Ok(Some(Self {
+            domain,
+            block_number,
+            message_block_number,
+            root,
+            committed,
+            messages,
+        }))
+    }
+
+    /// Write the snapshot
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&self.domain.to_be_bytes())?;
+        writer.write_all(&self.block_number.to_be_bytes())?;
+        writer.write_all(&self.message_block_number.to_be_bytes())?;
+        writer.write_all(&self.messages.len().try_u32()?.to_be_bytes())?;
+        writer.write_all(&self.committed.to_be_bytes())?;
+        writer.write_all(self.root.as_ref())?;
+
+        for message in &self.messages {
+            let encoded = message.to_vec();
+            writer.write_all(&encoded.len().try_u32()?.to_be_bytes())?;
+            writer.write_all(&encoded)?;
+        }
+        Ok(())
+    }
+
+    /// Read a snapshot. Lengths are bounded before anything is allocated for
+    /// them, but the contents are not verified.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            bail!(SnapshotError::BadMagic);
+        }
+
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != VERSION {
+            bail!(SnapshotError::UnsupportedVersion(version[0]));
+        }
+
+        let mut domain = [0u8; 4];
+        reader.read_exact(&mut domain)?;
+        let mut block_number = [0u8; 8];
+        reader.read_exact(&mut block_number)?;
+        let mut message_block_number = [0u8; 8];
+        reader.read_exact(&mut message_block_number)?;
+        let mut count = [0u8; 4];
+        reader.read_exact(&mut count)?;
+        let mut committed = [0u8; 4];
+        reader.read_exact(&mut committed)?;
+        let mut root = [0u8; 32];
+        reader.read_exact(&mut root)?;
+
+        let count = u32::from_be_bytes(count).try_usize()?;
+        let committed = u32::from_be_bytes(committed);
+        if committed.try_usize()? > count {
+            bail!(SnapshotError::TooLong {
+                field: "committed leaf count",
+                length: committed.into(),
+                max: count.try_u64()?,
+            });
+        }
+
+        let mut messages = Vec::with_capacity(count.min(MAX_PREALLOCATED_MESSAGES));
+        for _ in 0..count {
+            let mut len = [0u8; 4];
+            reader.read_exact(&mut len)?;
+            let len = u32::from_be_bytes(len).try_usize()?;
+            if len > MAX_ENCODED_MESSAGE_LEN {
+                bail!(SnapshotError::TooLong {
+                    field: "message length",
+                    length: len.try_u64()?,
+                    max: MAX_ENCODED_MESSAGE_LEN.try_u64()?,
+                });
+            }
+            let mut encoded = vec![0u8; len];
+            reader.read_exact(&mut encoded)?;
+            messages.push(RawCommittedMessage::read_from(&mut encoded.as_slice())?);
+        }
+
+        Ok(Self {
+            domain: u32::from_be_bytes(domain),
+            block_number: u64::from_be_bytes(block_number),
+            message_block_number: u64::from_be_bytes(message_block_number),
+            root: root.into(),
+            committed,
+            messages,
+        })
+    }
+
+    /// The leading messages under `root`
+    pub fn committed_messages(&self) -> &[RawCommittedMessage] {
+        self.messages
+            .get(..self.committed as usize)
+            .unwrap_or(&self.messages)
+    }
+
+    /// Check that messages are in leaf index order and that the committed
+    /// ones rebuild the root
+    pub fn verify_root(&self) -> Result<()> {
+        let mut tree = NomadLightMerkle::default();
+        let mut committed_root = (self.committed == 0).then(|| tree.root());
+        for (position, message) in self.messages.iter().enumerate() {
+            let position = position.try_u32()?;
+            if message.leaf_index != position {
+                bail!(SnapshotError::OutOfOrder {
+                    position,
+                    leaf_index: message.leaf_index,
+                });
+            }
+            tree.ingest(message.leaf())?;
+            if position + 1 == self.committed {
+                committed_root = Some(tree.root());
+            }
+        }
+
+        let committed_root = committed_root.unwrap_or_default();
+        if committed_root != self.root {
+            bail!(SnapshotError::RootMismatch {
+                expected: self.root,
+                actual: committed_root,
+            });
+        }
+        Ok(())
+    }
+
+    /// Find the update that committed the snapshot root at the snapshot
+    /// block
+    pub async fn committing_update<I>(&self, indexer: &I) -> Result<SignedUpdateWithMeta>
+    where
+        I: CommonIndexer + 'static,
+    {
+        let block = self.block_number.try_u32()?;
+        indexer
+            .fetch_sorted_updates(block, block)
+            .await?
+            .into_iter()
+            .find(|update| {
+                update.signed_update.update.home_domain == self.domain
+                    && update.signed_update.update.new_root == self.root
+            })
+            .ok_or_else(|| {
+                SnapshotError::NotCommitted {
+                    domain: self.domain,
+                    root: self.root,
+                    block_number: self.block_number,
+                }
+                .into()
+            })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use nomad_core::NomadMessage;
+
+    pub(crate) fn snapshot_of(count: u32) -> TreeSnapshot {
+        snapshot_with_tail(count, 0)
+    }
+
+    /// Snapshot of `committed` leaves under its root, followed by `tail`
+    /// leaves indexed after them
+    pub(crate) fn snapshot_with_tail(committed: u32, tail: u32) -> TreeSnapshot {
+        let messages: Vec<_> = (0..committed + tail)
+            .map(|leaf_index| RawCommittedMessage {
+                leaf_index,
+                committed_root: H256::zero(),
+                message: NomadMessage {
+                    origin: 1000,
+                    sender: H256::repeat_byte(1),
+                    nonce: leaf_index,
+                    destination: 2000,
+                    recipient: H256::repeat_byte(2),
+                    body: vec![leaf_index.to_le_bytes()[0]; 4],
+                }
+                .to_vec(),
+                timestamp: None,
+            })
+            .collect();
+        let leaves: Vec<_> = messages
+            .iter()
+            .take(committed as usize)
+            .map(RawCommittedMessage::leaf)
+            .collect();
+
+        TreeSnapshot {
+            domain: 1000,
+            block_number: 50,
+            message_block_number: 50 + u64::from(tail) * 10,
+            root: NomadLightMerkle::from_leaves(&leaves).root(),
+            committed,
+            messages,
+        }
+    }
+
+    #[test]
+    fn it_round_trips_snapshots() {
+        let snapshot = snapshot_of(5);
+        snapshot.verify_root().unwrap();
+
+        let mut encoded = vec![];
+        snapshot.write_to(&mut encoded).unwrap();
+        let decoded = TreeSnapshot::read_from(&mut encoded.as_slice()).unwrap();
+        assert_eq!(decoded, snapshot);
+
+        encoded[0] = 0;
+        let err = TreeSnapshot::read_from(&mut encoded.as_slice()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SnapshotError>(),
+            Some(&SnapshotError::BadMagic)
+        );
+    }
+
+    #[test]
+    fn it_verifies_only_the_committed_leaves() {
+        let snapshot = snapshot_with_tail(5, 2);
+        snapshot.verify_root().unwrap();
+
+        let mut encoded = vec![];
+        snapshot.write_to(&mut encoded).unwrap();
+        assert_eq!(
+            TreeSnapshot::read_from(&mut encoded.as_slice()).unwrap(),
+            snapshot
+        );
+
+        // the root only covers the committed leaves
+        let mut shifted = snapshot.clone();
+        shifted.committed = 6;
+        assert!(matches!(
+            shifted
+                .verify_root()
+                .unwrap_err()
+                .downcast_ref::<SnapshotError>(),
+            Some(SnapshotError::RootMismatch { .. })
+        ));
+        shifted.committed = 8;
+        assert!(matches!(
+            shifted
+                .verify_root()
+                .unwrap_err()
+                .downcast_ref::<SnapshotError>(),
+            Some(SnapshotError::RootMismatch { .. })
+        ));
+
+        let empty = snapshot_of(0);
+        empty.verify_root().unwrap();
+    }
+
+    #[test]
+    fn it_bounds_lengths_before_allocating() {
+        let snapshot = snapshot_of(2);
+        let mut encoded = vec![];
+        snapshot.write_to(&mut encoded).unwrap();
+        // magic, version, domain, block numbers
+        let count_at = 8 + 1 + 4 + 8 + 8;
+        let first_len_at = count_at + 4 + 4 + 32;
+
+        let mut long = encoded.clone();
+        long[first_len_at..first_len_at + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        let err = TreeSnapshot::read_from(&mut long.as_slice()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SnapshotError>(),
+            Some(SnapshotError::TooLong {
+                field: "message length",
+                ..
+            })
+        ));
+
+        // a huge count fails on the missing messages, not on allocation
+        let mut many = encoded.clone();
+        many[count_at..count_at + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(TreeSnapshot::read_from(&mut many.as_slice()).is_err());
+
+        let mut committed = encoded;
+        committed[count_at + 4..count_at + 8].copy_from_slice(&3u32.to_be_bytes());
+        let err = TreeSnapshot::read_from(&mut committed.as_slice()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SnapshotError>(),
+            Some(SnapshotError::TooLong {
+                field: "committed leaf count",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn it_rejects_tampered_leaves() {
+        let mut snapshot = snapshot_of(5);
+        let last = snapshot.messages[2].message.len() - 1;
+        snapshot.messages[2].message[last] ^= 1;
+
+        let err = snapshot.verify_root().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SnapshotError>(),
+            Some(SnapshotError::RootMismatch { .. })
+        ));
+
+        let mut snapshot = snapshot_of(5);
+        snapshot.messages.swap(1, 2);
+        let err = snapshot.verify_root().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SnapshotError>(),
+            Some(SnapshotError::OutOfOrder { position: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn it_leaves_tampered_tails_out_of_the_committed_leaves() {
+        // The root does not cover the tail, so a tampered tail verifies
+        let mut snapshot = snapshot_with_tail(5, 2);
+        let last = snapshot.messages[6].message.len() - 1;
+        snapshot.messages[6].message[last] ^= 1;
+        snapshot.verify_root().unwrap();
+
+        let committed = snapshot.committed_messages();
+        assert_eq!(committed.len(), 5);
+        assert_eq!(committed, &snapshot_of(5).messages[..]);
+
+        // Out of order tail leaves are still rejected
+        let mut snapshot = snapshot_with_tail(5, 2);
+        snapshot.messages.swap(5, 6);
+        let err = snapshot.verify_root().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SnapshotError>(),
+            Some(SnapshotError::OutOfOrder { position: 5, .. })
+        ));
+    }
+}
@@ -0,0 +1,482 @@
+use std::{
+    collections::VecDeque,
+    convert::TryFrom,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use ethers::{
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, Provider},
+    signers::Signer,
+    types::{Address, TransactionRequest, U256},
+};
+use futures_util::future::select_all;
+use nomad_core::{format_evm_address, ChecksumAddress, FromSignerConf};
+use nomad_ethereum::EthereumSigners;
+use nomad_xyz_configuration::{
+    agent::TopUpConfig, ethereum, ChainConf, Connection, TxSubmitterConf,
+};
+use prometheus::IntCounterVec;
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{error, info, info_span, instrument::Instrumented, warn, Instrument};
+
+use crate::{CoreMetrics, Settings};
+
+const DAY_SECONDS: u64 = 24 * 60 * 60;
+
+/// Outcome of a top-up check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopUpDecision {
+    /// Balance is at or above the floor
+    Sufficient,
+    /// Balance is low, but the previous top-up is too recent
+    Cooldown,
+    /// Balance is low, but the daily cap is used up
+    DailyCapReached,
+    /// Balance is low and a top-up may be requested
+    TopUp,
+}
+
+/// Cooldown and daily cap bookkeeping for top-ups
+#[derive(Debug, Clone)]
+pub struct TopUpPolicy {
+    floor: U256,
+    cooldown_seconds: u64,
+    max_per_day: u32,
+    /// Timestamps of top-ups within the last day
+    history: VecDeque<u64>,
+}
+
+impl TopUpPolicy {
+    /// Instantiate a new policy
+    pub fn new(floor: U256, cooldown_seconds: u64, max_per_day: u32) -> Self {
+        Self {
+            floor,
+            cooldown_seconds,
+            max_per_day,
+            history: Default::default(),
+        }
+    }
+
+    /// Decide whether a signer with `balance` may be topped up at `now`
+    pub fn decide(&mut self, balance: U256, now: u64) -> TopUpDecision {
+        while let Some(oldest) = self.history.front() {
+            if now.saturating_sub(*oldest) < DAY_SECONDS {
+                break;
+            }
+            self.history.pop_front();
+        }
+
+        if balance >= self.floor {
+            return TopUpDecision::Sufficient;
+        }
+
+        if let Some(last) = self.history.back() {
+            if now.saturating_sub(*last) < self.cooldown_seconds {
+                return TopUpDecision::Cooldown;
+            }
+        }
+
+        if self.history.len() >= self.max_per_day as usize {
+            return TopUpDecision::DailyCapReached;
+        }
+
+        TopUpDecision::TopUp
+    }
+
+    /// Record a top-up at `now`
+    pub fn record(&mut self, now: u64) {
+        self.history.push_back(now);
+    }
+}
+
+/// Source of top-up funds
+#[derive(Debug, Clone)]
+pub enum TopUpSource {
+    /// Faucet HTTP endpoint
+    Faucet {
+        /// HTTP client
+        client: reqwest::Client,
+        /// Faucet URL
+        url: String,
+    },
+    /// Transfer from a funder key
+    Funder(Arc<SignerMiddleware<Arc<Provider<Http>>, EthereumSigners>>),
+}
+
+impl TopUpSource {
+    async fn fund(&self, address: Address, amount: U256) -> Result<()> {
+        match self {
+            TopUpSource::Faucet { client, url } => {
+                client
+                    .post(url)
+                    .json(&serde_json::json!({
+                        "address": format_evm_address(&address),
+                        "amount": amount.to_string(),
+                    }))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            TopUpSource::Funder(funder) => {
+                let tx = TransactionRequest::pay(address, amount);
+                funder.send_transaction(tx, None).await?.await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Testnet top-up of a single transaction signer
+#[derive(Debug)]
+pub struct TopUp {
+    network: String,
+    address: Address,
+    amount: U256,
+    interval: u64,
+    provider: Arc<Provider<Http>>,
+    source: TopUpSource,
+    policy: TopUpPolicy,
+    agent_name: &'static str,
+    top_ups: IntCounterVec,
+}
+
+impl TopUp {
+    /// Instantiate a new top-up
+    pub fn new(
+        network: String,
+        address: Address,
+        config: &TopUpConfig,
+        provider: Arc<Provider<Http>>,
+        source: TopUpSource,
+        agent_name: &'static str,
+        top_ups: IntCounterVec,
+    ) -> Self {
+        Self {
+            network,
+            address,
+            amount: config.amount.into(),
+            interval: config.interval,
+            provider,
+            source,
+            policy: TopUpPolicy::new(
+                config.floor.into(),
+                config.cooldown_seconds,
+                config.max_per_day,
+            ),
+            agent_name,
+            top_ups,
+        }
+    }
+
+    /// Build the top-up for the transaction signer of `network`. Returns
+    /// `None` if the signer is not a local or AWS key.
+    pub async fn from_settings(
+        network: &str,
+        config: &TopUpConfig,
+        settings: &Settings,
+        agent_name: &'static str,
+        top_ups: IntCounterVec,
+    ) -> Result<Option<Self>> {
+        let setup = if settings.home.name == network {
+            &settings.home
+        } else {
+            settings
+                .replicas
+                .get(network)
+                .ok_or_else(|| eyre!("No chain setup for top-up network {}", network))?
+        };
+
+        let url = match &setup.chain {
+            ChainConf::Ethereum(Connection::Http(url)) => url,
+            ChainConf::Ethereum(Connection::Ws(_)) => {
+                bail!("Top-up for {} requires an http rpc", network)
+            }
+            ChainConf::Substrate(_) => bail!("Top-up is not supported for substrate ({})", network),
+        };
+        let provider = Arc::new(Provider::<Http>::try_from(url.as_str())?);
+
+        // Never trust the config alone. Check the chain id the rpc reports.
+        let chain_id = provider.get_chainid().await?.as_u64();
+        config.validate(network, chain_id)?;
+
+        let signer_conf = match settings.get_submitter_conf(network) {
+            Some(TxSubmitterConf::Ethereum(ethereum::TxSubmitterConf::Local(conf))) => conf,
+            _ => {
+                warn!(
+                    network,
+                    "Top-up configured for {}, but its submitter is not a local signer. Skipping.",
+                    network
+                );
+                return Ok(None);
+            }
+        };
+        let address = EthereumSigners::try_from_signer_conf(&signer_conf)
+            .await?
+            .address();
+
+        let source = match (&config.faucet_url, settings.top_up_funders.get(network)) {
+            (Some(url), _) => TopUpSource::Faucet {
+                client: reqwest::Client::new(),
+                url: url.to_owned(),
+            },
+            (None, Some(funder)) => {
+                let signer = EthereumSigners::try_from_signer_conf(funder)
+                    .await?
+                    .with_chain_id(chain_id);
+                TopUpSource::Funder(Arc::new(SignerMiddleware::new(provider.clone(), signer)))
+            }
+            (None, None) => bail!("Top-up for {} has neither faucet url nor funder", network),
+        };
+
+        Ok(Some(Self::new(
+            network.to_owned(),
+            address,
+            config,
+            provider,
+            source,
+            agent_name,
+            top_ups,
+        )))
+    }
+
+    /// Run a single top-up check for a signer holding `balance` at time `now`
+    pub async fn step(&mut self, balance: U256, now: u64) -> Result<TopUpDecision> {
+        let decision = self.policy.decide(balance, now);
+
+        match decision {
+            TopUpDecision::TopUp => {
+                info!(
+                    network = self.network.as_str(),
+                    address = %ChecksumAddress(self.address),
+                    balance = %balance,
+                    amount = %self.amount,
+                    "Topping up signer {} on {}",
+                    ChecksumAddress(self.address),
+                    self.network,
+                );
+
+                // Attempts count against the cooldown and cap even if they
+                // fail, so a broken faucet is not hammered
+                self.policy.record(now);
+                let result = self.source.fund(self.address, self.amount).await;
+                let label = if result.is_ok() { "success" } else { "failure" };
+                self.top_ups
+                    .with_label_values(&[&self.network, label, self.agent_name])
+                    .inc();
+                result?;
+            }
+            TopUpDecision::Cooldown | TopUpDecision::DailyCapReached => {
+                warn!(
+                    network = self.network.as_str(),
+                    address = %ChecksumAddress(self.address),
+                    balance = %balance,
+                    decision = ?decision,
+                    "Signer balance on {} is below floor, but top-up is not allowed yet",
+                    self.network,
+                );
+            }
+            TopUpDecision::Sufficient => {}
+        }
+
+        Ok(decision)
+    }
+
+    /// Spawn the top-up loop
+    pub fn spawn(mut self) -> Instrumented<JoinHandle<Result<()>>> {
+        let span = info_span!("TopUp", network = self.network.as_str());
+
+        tokio::spawn(async move {
+            loop {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("!timestamp")
+                    .as_secs();
+
+                match self.provider.get_balance(self.address, None).await {
+                    Ok(balance) => {
+                        if let Err(e) = self.step(balance, now).await {
+                            error!(network = self.network.as_str(), error = %e, "Top-up failed");
+                        }
+                    }
+                    Err(e) => warn!(
+                        network = self.network.as_str(),
+                        error = %e,
+                        "Failed to query signer balance"
+                    ),
+                }
+
+                sleep(Duration::from_secs(self.interval)).await;
+            }
+        })
+        .instrument(span)
+    }
+}
+
+/// Spawn top-ups for every network of the agent that has one configured
+pub fn spawn_top_ups(
+    settings: Settings,
+    agent_name: &'static str,
+    metrics: Arc<CoreMetrics>,
+) -> Instrumented<JoinHandle<Result<()>>> {
+    let span = info_span!("TopUps");
+
+    tokio::spawn(async move {
+        let top_ups = metrics.new_int_counter(
+            "signer_top_ups",
+            "Number of testnet top-ups of agent signers",
+            &["network", "result", "agent"],
+        )?;
+
+        let mut tasks = vec![];
+        for (network, config) in settings.top_up.iter() {
+            let is_agent_network =
+                settings.home.name == *network || settings.replicas.contains_key(network);
+            if !is_agent_network {
+                continue;
+            }
+
+            if let Some(top_up) =
+                TopUp::from_settings(network, config, &settings, agent_name, top_ups.clone())
+                    .await?
+            {
+                info!(network = network.as_str(), "Starting signer top-up");
+                tasks.push(top_up.spawn());
+            }
+        }
+
+        if tasks.is_empty() {
+            return std::future::pending().await;
+        }
+
+        let (res, _, _) = select_all(tasks).await;
+        res?
+    })
+    .instrument(span)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use warp::Filter;
+
+    const COOLDOWN: u64 = 600;
+
+    fn faucet_top_up(faucet_url: String) -> (TopUp, IntCounterVec) {
+        let config: TopUpConfig = serde_json::from_value(serde_json::json!({
+            "isTestNetwork": true,
+            "floor": 1000,
+            "amount": 5000,
+            "cooldownSeconds": COOLDOWN,
+            "maxPerDay": 2,
+            "faucetUrl": faucet_url,
+        }))
+        .unwrap();
+
+        let metrics = CoreMetrics::new(
+            "top_up_test",
+            "home",
+            None,
+            Arc::new(prometheus::Registry::new()),
+        )
+        .unwrap();
+        let top_ups = metrics
+            .new_int_counter("signer_top_ups", "top-ups", &["network", "result", "agent"])
+            .unwrap();
+
+        let top_up = TopUp::new(
+            "goerli".to_owned(),
+            Address::repeat_byte(1),
+            &config,
+            // Balances are passed to `step` directly, the provider is unused
+            Arc::new(Provider::<Http>::try_from("http://127.0.0.1:1").unwrap()),
+            TopUpSource::Faucet {
+                client: reqwest::Client::new(),
+                url: faucet_url,
+            },
+            "test",
+            top_ups.clone(),
+        );
+
+        (top_up, top_ups)
+    }
+
+    #[tokio::test]
+    async fn it_respects_cooldown_and_daily_cap() {
+        // Mock faucet counting requests
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let route = warp::post()
+            .and(warp::path!("faucet"))
+            .and(warp::body::json())
+            .map(move |body: serde_json::Value| {
+                assert_eq!(body["amount"], "5000");
+                counter.fetch_add(1, Ordering::SeqCst);
+                warp::reply()
+            });
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let faucet_url = format!("http://{}/faucet", addr);
+        let (mut top_up, top_ups) = faucet_top_up(faucet_url);
+
+        let low = U256::from(10);
+        let high = U256::from(10_000);
+
+        assert_eq!(
+            top_up.step(high, 0).await.unwrap(),
+            TopUpDecision::Sufficient
+        );
+        assert_eq!(top_up.step(low, 0).await.unwrap(), TopUpDecision::TopUp);
+        assert_eq!(
+            top_up.step(low, COOLDOWN - 1).await.unwrap(),
+            TopUpDecision::Cooldown
+        );
+        assert_eq!(
+            top_up.step(low, COOLDOWN).await.unwrap(),
+            TopUpDecision::TopUp
+        );
+        assert_eq!(
+            top_up.step(low, 3 * COOLDOWN).await.unwrap(),
+            TopUpDecision::DailyCapReached
+        );
+
+        // The first top-up leaves the 24 hour window
+        assert_eq!(
+            top_up.step(low, DAY_SECONDS).await.unwrap(),
+            TopUpDecision::TopUp
+        );
+
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            top_ups
+                .with_label_values(&["goerli", "success", "test"])
+                .get(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_requests_count_against_the_cap() {
+        // Nothing listens here, so every faucet request fails
+        let (mut top_up, top_ups) = faucet_top_up("http://127.0.0.1:1/faucet".to_owned());
+
+        assert!(top_up.step(U256::zero(), 0).await.is_err());
+        assert_eq!(
+            top_up.step(U256::zero(), 1).await.unwrap(),
+            TopUpDecision::Cooldown
+        );
+        assert_eq!(
+            top_ups
+                .with_label_values(&["goerli", "failure", "test"])
+                .get(),
+            1
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use color_eyre::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{error, info_span, instrument::Instrumented, warn, Instrument};
+
+use nomad_core::{Common, Home};
+
+use crate::CachingHome;
+
+/// Read the home's `tree().count` and `count()` at one block. A consistent
+/// count is cached on the home. A mismatch is logged and counted.
+async fn check_tree_counts(home: &CachingHome, mismatches: &prometheus::IntGauge) -> Result<()> {
+    let (tree_count, count) = home.tree_counts().await?;
+    if tree_count != count {
+        mismatches.inc();
+        error!(
+            home = home.name(),
+            tree_count = tree_count,
+            count = count,
+            "Home tree().count does not match count(). Contract storage may be corrupt."
+        );
+        return Ok(());
+    }
+
+    home.cache_count(count);
+    Ok(())
+}
+
+/// Spawn a task which periodically asserts that the home's `tree().count`
+/// matches `count()`. A mismatch indicates a contract storage anomaly. A
+/// mismatch or a failed check is logged, but does not stop the agent.
+pub fn watch_tree_counts(
+    home: Arc<CachingHome>,
+    interval: u64,
+    mismatches: prometheus::IntGauge,
+) -> Instrumented<JoinHandle<Result<()>>> {
+    let span = info_span!("tree_count_watch");
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = check_tree_counts(&home, &mismatches).await {
+                warn!(
+                    home = home.name(),
+                    error = %e,
+                    "Failed to check home tree counts. Retrying at the next check."
+                );
+            }
+
+            sleep(Duration::from_secs(interval)).await;
+        }
+    })
+    .instrument(span)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        chains::PageSettings, ContractSync, ContractSyncMetrics, CoreMetrics, HomeIndexers,
+        IndexSettings, NomadDB,
+    };
+    use nomad_test::mocks::{MockHomeContract, MockIndexer};
+    use nomad_test::{test_utils, MockError};
+    use prometheus::IntGauge;
+
+    const INTERVAL: u64 = 60;
+
+    fn caching_home(db: NomadDB, mut mock_home: MockHomeContract) -> Arc<CachingHome> {
+        let metrics = Arc::new(
+            CoreMetrics::new(
+                "tree_counts_test",
+                "home",
+                None,
+                Arc::new(prometheus::Registry::new()),
+            )
+            .expect("could not make metrics"),
+        );
+
+        mock_home.expect__name().return_const("home_1".to_owned());
+
+        let home_indexer: Arc<HomeIndexers> = Arc::new(MockIndexer::new().into());
+        let home_sync = ContractSync::new(
+            "updater".to_owned(),
+            "home_1".to_owned(),
+            "".to_owned(),
+            db.clone(),
+            home_indexer,
+            IndexSettings::default(),
+            PageSettings::default(),
+            Default::default(),
+            ContractSyncMetrics::new(metrics),
+        );
+        Arc::new(CachingHome::new(mock_home.into(), home_sync, db))
+    }
+
+    #[tokio::test]
+    async fn it_counts_drift_and_caches_consistent_counts() {
+        test_utils::run_test_db(|db| async move {
+            let mut mock_home = MockHomeContract::new();
+            let mut seq = mockall::Sequence::new();
+            mock_home
+                .expect__tree_counts()
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_once(|| Ok((5, 5)));
+            mock_home
+                .expect__tree_counts()
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_once(|| Ok((6, 7)));
+            let home = caching_home(NomadDB::new("home_1", db), mock_home);
+            let mismatches = IntGauge::new("tree_count_mismatches", "test").unwrap();
+
+            assert_eq!(home.cached_count(), None);
+            check_tree_counts(&home, &mismatches).await.unwrap();
+            assert_eq!(home.cached_count(), Some(5));
+            assert_eq!(mismatches.get(), 0);
+
+            // A drifted count is flagged and never cached
+            check_tree_counts(&home, &mismatches).await.unwrap();
+            assert_eq!(home.cached_count(), Some(5));
+            assert_eq!(mismatches.get(), 1);
+        })
+        .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_retries_after_a_failed_check() {
+        test_utils::run_test_db(|db| async move {
+            let mut mock_home = MockHomeContract::new();
+            let mut seq = mockall::Sequence::new();
+            mock_home
+                .expect__tree_counts()
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_once(|| Err(MockError::Failed));
+            mock_home
+                .expect__tree_counts()
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_once(|| Ok((3, 3)));
+            mock_home
+                .expect__tree_counts()
+                .times(..)
+                .in_sequence(&mut seq)
+                .returning(|| Ok((3, 4)));
+            let home = caching_home(NomadDB::new("home_1", db), mock_home);
+            let mismatches = IntGauge::new("tree_count_mismatches", "test").unwrap();
+
+            let task = watch_tree_counts(home.clone(), INTERVAL, mismatches.clone());
+
+            // The failed RPC neither ends the task nor caches a count
+            sleep(Duration::from_secs(1)).await;
+            assert_eq!(home.cached_count(), None);
+
+            sleep(Duration::from_secs(INTERVAL)).await;
+            assert_eq!(home.cached_count(), Some(3));
+
+            sleep(Duration::from_secs(INTERVAL)).await;
+            assert_eq!(mismatches.get(), 1);
+
+            let task = task.into_inner();
+            assert!(!task.is_finished());
+            task.abort();
+        })
+        .await
+    }
+}
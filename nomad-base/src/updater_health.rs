@@ -0,0 +1,282 @@
+use color_eyre::Result;
+use ethers::core::types::H256;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{error, info, info_span, instrument::Instrumented, warn, Instrument};
+
+use nomad_core::{CanonicalH256, Common, Home};
+use nomad_types::CheckedCast;
+
+use crate::CachingHome;
+
+/// Seconds between updater health checks
+pub const UPDATER_HEALTH_CHECK_INTERVAL: u64 = 60;
+
+/// Seconds a root may wait on top of the updater's interval before it is
+/// considered overdue, for the update to be signed, submitted and confirmed
+pub const UPDATER_HEALTH_GRACE: u64 = 600;
+
+/// Health of the home's updater, judged from its queue and its dispatches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdaterHealth {
+    /// The queue is empty and nothing was dispatched recently. No update is
+    /// expected, so a quiet updater is not a fault.
+    Idle,
+    /// Every pending root is younger than the expected updater interval
+    Healthy,
+    /// A root has waited longer than the expected updater interval for an
+    /// update
+    Stalled,
+}
+
+/// Observation of the home's updater by `UpdaterHealthTracker`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdaterHealthReport {
+    /// Health of the updater
+    pub health: UpdaterHealth,
+    /// `queueLength()` on the home
+    pub queue_length: u64,
+    /// Seconds the oldest unattested root has been pending. `None` if the
+    /// queue is empty.
+    pub oldest_pending_age: Option<u64>,
+    /// Seconds since the home's leaf count last grew. `None` if no dispatch
+    /// was seen since the tracker started.
+    pub since_last_dispatch: Option<u64>,
+}
+
+/// Tracks the roots pending in the home's queue and the home's dispatches
+/// across checks, to tell an updater that stopped attesting from one with
+/// nothing to attest.
+///
+/// A root's age is measured from the first check that saw it pending, so
+/// ages run at most one check interval short, and a root pending before the
+/// tracker started is only aged from its first check.
+#[derive(Debug, Clone)]
+pub struct UpdaterHealthTracker {
+    expected_interval: u64,
+    /// Committed root the queue was pending on, and when it was first seen
+    /// pending
+    pending_since: Option<(H256, u64)>,
+    /// Leaf count at the last check
+    count: Option<u32>,
+    /// When the leaf count last grew
+    last_dispatch: Option<u64>,
+}
+
+impl UpdaterHealthTracker {
+    /// Track an updater expected to attest to pending roots within
+    /// `expected_interval` seconds
+    pub fn new(expected_interval: u64) -> Self {
+        Self {
+            expected_interval,
+            pending_since: None,
+            count: None,
+            last_dispatch: None,
+        }
+    }
+
+    /// Record the home's committed root, queue length and leaf count, read
+    /// at `now` in seconds, and judge the updater's health
+    pub fn observe(
+        &mut self,
+        now: u64,
+        committed_root: H256,
+        queue_length: u64,
+        count: u32,
+    ) -> UpdaterHealthReport {
+        if self.count.map_or(false, |previous| count > previous) {
+            self.last_dispatch = Some(now);
+        }
+        self.count = Some(count);
+
+        // An update moves the committed root, so roots still queued after
+        // it are newer than the ones it attested
+        self.pending_since = match self.pending_since {
+            _ if queue_length == 0 => None,
+            Some((root, since)) if root == committed_root => Some((root, since)),
+            _ => Some((committed_root, now)),
+        };
+
+        let oldest_pending_age = self
+            .pending_since
+            .map(|(_, since)| now.saturating_sub(since));
+        let since_last_dispatch = self.last_dispatch.map(|at| now.saturating_sub(at));
+        let dispatched_recently =
+            since_last_dispatch.map_or(false, |elapsed| elapsed <= self.expected_interval);
+
+        let health = if oldest_pending_age.map_or(false, |age| age > self.expected_interval) {
+            UpdaterHealth::Stalled
+        } else if queue_length == 0 && !dispatched_recently {
+            UpdaterHealth::Idle
+        } else {
+            UpdaterHealth::Healthy
+        };
+
+        UpdaterHealthReport {
+            health,
+            queue_length,
+            oldest_pending_age,
+            since_last_dispatch,
+        }
+    }
+}
+
+/// Read the home's committed root and queue length, and its leaf count from
+/// the messages its sync indexed, and judge the updater's health at `now`
+async fn check_updater_health(
+    home: &CachingHome,
+    tracker: &mut UpdaterHealthTracker,
+    now: u64,
+) -> Result<(H256, UpdaterHealthReport)> {
+    let committed_root = home.committed_root().await?;
+    let queue_length = home.queue_length().await?.try_u64()?;
+    let count = match home.db().retrieve_latest_leaf_index()? {
+        Some(index) => index.saturating_add(1),
+        None => 0,
+    };
+    Ok((
+        committed_root,
+        tracker.observe(now, committed_root, queue_length, count),
+    ))
+}
+
+/// Spawn a task which periodically judges the health of the home's updater.
+/// `stalled` is set only while an unattested root is older than
+/// `expected_interval` seconds, and `idle` while the queue is empty and
+/// nothing was dispatched recently. Dispatches are those the home's sync
+/// indexed. A stall or a failed check is logged, but does not stop the
+/// agent.
+pub fn watch_updater_health(
+    home: Arc<CachingHome>,
+    expected_interval: u64,
+    stalled: prometheus::IntGauge,
+    idle: prometheus::IntGauge,
+    pending_root_age: prometheus::IntGauge,
+) -> Instrumented<JoinHandle<Result<()>>> {
+    let span = info_span!("updater_health_watch");
+
+    tokio::spawn(async move {
+        let mut tracker = UpdaterHealthTracker::new(expected_interval);
+        let mut previous = None;
+        loop {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("!time")
+                .as_secs();
+            let (committed_root, report) =
+                match check_updater_health(&home, &mut tracker, now).await {
+                    Ok(checked) => checked,
+                    Err(e) => {
+                        warn!(
+                            home = home.name(),
+                            error = %e,
+                            "Failed to check updater health. Retrying at the next check."
+                        );
+                        sleep(Duration::from_secs(UPDATER_HEALTH_CHECK_INTERVAL)).await;
+                        continue;
+                    }
+                };
+
+            stalled.set((report.health == UpdaterHealth::Stalled) as i64);
+            idle.set((report.health == UpdaterHealth::Idle) as i64);
+            pending_root_age.set(
+                report
+                    .oldest_pending_age
+                    .map_or(0, |age| i64::try_from(age).unwrap_or(i64::MAX)),
+            );
+
+            if report.health == UpdaterHealth::Stalled {
+                error!(
+                    home = home.name(),
+                    committed_root = %CanonicalH256(committed_root),
+                    report = ?report,
+                    expected_interval,
+                    "Roots have been pending on the home longer than the expected updater interval. Check the updater."
+                );
+            } else if previous != Some(report.health) {
+                info!(
+                    home = home.name(),
+                    report = ?report,
+                    "Updater health is {:?}",
+                    report.health,
+                );
+            }
+            previous = Some(report.health);
+
+            sleep(Duration::from_secs(UPDATER_HEALTH_CHECK_INTERVAL)).await;
+        }
+    })
+    .instrument(span)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INTERVAL: u64 = 900;
+
+    #[test]
+    fn it_reports_an_idle_updater() {
+        let mut tracker = UpdaterHealthTracker::new(INTERVAL);
+        let root = H256::repeat_byte(1);
+
+        // Hours without updates or dispatches are not a stall
+        for hour in 0..4 {
+            let report = tracker.observe(hour * 3600, root, 0, 10);
+            assert_eq!(report.health, UpdaterHealth::Idle);
+            assert_eq!(report.oldest_pending_age, None);
+        }
+    }
+
+    #[test]
+    fn it_reports_a_healthy_busy_updater() {
+        let mut tracker = UpdaterHealthTracker::new(INTERVAL);
+        tracker.observe(0, H256::repeat_byte(1), 0, 10);
+
+        // A dispatch queues a root, attested within the interval
+        let report = tracker.observe(60, H256::repeat_byte(1), 1, 11);
+        assert_eq!(report.health, UpdaterHealth::Healthy);
+        assert_eq!(report.oldest_pending_age, Some(0));
+        assert_eq!(report.since_last_dispatch, Some(0));
+
+        let report = tracker.observe(600, H256::repeat_byte(1), 2, 12);
+        assert_eq!(report.health, UpdaterHealth::Healthy);
+        assert_eq!(report.oldest_pending_age, Some(540));
+
+        // The update lands but a new dispatch is already queued on top of it
+        let report = tracker.observe(1200, H256::repeat_byte(2), 1, 13);
+        assert_eq!(report.health, UpdaterHealth::Healthy);
+        assert_eq!(report.oldest_pending_age, Some(0));
+
+        // An empty queue right after dispatches is healthy, then idle
+        let report = tracker.observe(1500, H256::repeat_byte(3), 0, 13);
+        assert_eq!(report.health, UpdaterHealth::Healthy);
+        let report = tracker.observe(1200 + INTERVAL + 1, H256::repeat_byte(3), 0, 13);
+        assert_eq!(report.health, UpdaterHealth::Idle);
+    }
+
+    #[test]
+    fn it_reports_a_stalled_busy_updater() {
+        let mut tracker = UpdaterHealthTracker::new(INTERVAL);
+        let root = H256::repeat_byte(1);
+        tracker.observe(0, root, 0, 10);
+        tracker.observe(60, root, 1, 11);
+
+        // Dispatches keep coming but the committed root never moves
+        let report = tracker.observe(60 + INTERVAL, root, 3, 13);
+        assert_eq!(report.health, UpdaterHealth::Healthy);
+        let report = tracker.observe(61 + INTERVAL, root, 3, 13);
+        assert_eq!(report.health, UpdaterHealth::Stalled);
+        assert_eq!(report.oldest_pending_age, Some(INTERVAL + 1));
+
+        // Still stalled once dispatches stop
+        let report = tracker.observe(7200, root, 3, 13);
+        assert_eq!(report.health, UpdaterHealth::Stalled);
+        assert_eq!(report.since_last_dispatch, Some(7200 - 60 - INTERVAL));
+
+        // An update clears it
+        let report = tracker.observe(7260, H256::repeat_byte(2), 0, 13);
+        assert_eq!(report.health, UpdaterHealth::Idle);
+    }
+}
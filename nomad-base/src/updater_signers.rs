@@ -0,0 +1,197 @@
+use color_eyre::Result;
+use ethers::core::types::{Address, H256};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{error, info_span, instrument::Instrumented, Instrument};
+
+use nomad_core::{CanonicalH256, ChecksumAddress, Common, SignedUpdate};
+
+use crate::{CachingHome, NomadDB};
+
+/// Number of recent indexed updates whose signers are checked
+pub const UPDATER_SIGNER_SAMPLE: usize = 20;
+
+/// Seconds between updater signer checks
+pub const UPDATER_SIGNER_CHECK_INTERVAL: u64 = 300;
+
+/// Signers of recently indexed updates, alongside the updater reported by the
+/// home and the updater the agent was configured with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdaterSignerReport {
+    /// `updater()` on the home
+    pub on_chain: Address,
+    /// Updater expected by the config, if any
+    pub configured: Option<Address>,
+    /// New root and recovered signer of each sampled update, newest first
+    pub signers: Vec<(H256, Address)>,
+}
+
+impl UpdaterSignerReport {
+    /// Recover the signers of `updates`. Updates whose signature cannot be
+    /// recovered are reported with a zero signer.
+    pub fn new(on_chain: Address, configured: Option<Address>, updates: &[SignedUpdate]) -> Self {
+        let signers = updates
+            .iter()
+            .map(|update| (update.update.new_root, update.recover().unwrap_or_default()))
+            .collect();
+
+        Self {
+            on_chain,
+            configured,
+            signers,
+        }
+    }
+
+    /// Every disagreement between the configured updater, the on-chain
+    /// updater and the recent signers
+    pub fn disagreements(&self) -> Vec<String> {
+        let mut disagreements = vec![];
+
+        if let Some(configured) = self.configured {
+            if configured != self.on_chain {
+                disagreements.push(format!(
+                    "configured updater {} is not the home's updater {}",
+                    ChecksumAddress(configured),
+                    ChecksumAddress(self.on_chain),
+                ));
+            }
+
+            // The inverse: the config says X but every recent update is
+            // signed by Y
+            if let Some((_, first)) = self.signers.first() {
+                if *first != configured && self.signers.iter().all(|(_, s)| s == first) {
+                    disagreements.push(format!(
+                        "configured updater {} signed none of the last {} updates, all signed by {}",
+                        ChecksumAddress(configured),
+                        self.signers.len(),
+                        ChecksumAddress(*first),
+                    ));
+                }
+            }
+        }
+
+        for (new_root, signer) in self.signers.iter() {
+            if *signer != self.on_chain {
+                disagreements.push(format!(
+                    "update to root {} was signed by {}, not the home's updater {}",
+                    CanonicalH256(*new_root),
+                    ChecksumAddress(*signer),
+                    ChecksumAddress(self.on_chain),
+                ));
+            }
+        }
+
+        disagreements
+    }
+}
+
+/// Spawn a task which periodically compares the signers of the most recently
+/// indexed updates against the home's `updater()` and the configured
+/// updater. Disagreements are logged with the full evidence and counted. The
+/// check is read-only and only reads updates already in the DB.
+pub fn watch_updater_signers(
+    home: Arc<CachingHome>,
+    db: NomadDB,
+    configured: Option<Address>,
+    mismatches: prometheus::IntGauge,
+) -> Instrumented<JoinHandle<Result<()>>> {
+    let span = info_span!("updater_signer_watch");
+
+    tokio::spawn(async move {
+        loop {
+            let updates = db.recent_updates(UPDATER_SIGNER_SAMPLE)?;
+            if !updates.is_empty() {
+                let on_chain: Address = home.updater().await?.into();
+                let report = UpdaterSignerReport::new(on_chain, configured, &updates);
+
+                let disagreements = report.disagreements();
+                if !disagreements.is_empty() {
+                    mismatches.inc();
+                    error!(
+                        home = home.name(),
+                        disagreements = ?disagreements,
+                        report = ?report,
+                        "Updater does not match recent update signers. Check for an updater rotation."
+                    );
+                }
+            }
+
+            sleep(Duration::from_secs(UPDATER_SIGNER_CHECK_INTERVAL)).await;
+        }
+    })
+    .instrument(span)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::signers::{LocalWallet, Signer};
+    use nomad_core::Update;
+    use nomad_test::test_utils;
+
+    async fn signed_chain(signer: &LocalWallet, len: u8) -> Vec<SignedUpdate> {
+        let mut updates = vec![];
+        for i in 0..len {
+            updates.push(
+                Update {
+                    home_domain: 1,
+                    previous_root: H256::repeat_byte(i),
+                    new_root: H256::repeat_byte(i + 1),
+                }
+                .sign_with(signer)
+                .await
+                .unwrap(),
+            );
+        }
+        updates
+    }
+
+    fn wallet(key: &str) -> LocalWallet {
+        key.repeat(64).parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_reports_updater_disagreements() {
+        let x = wallet("1");
+        let y = wallet("2");
+        let by_y = signed_chain(&y, 3).await;
+
+        // Everything agrees
+        let report = UpdaterSignerReport::new(y.address(), Some(y.address()), &by_y);
+        assert!(report.disagreements().is_empty());
+
+        // Config says X, chain and all recent updates say Y
+        let report = UpdaterSignerReport::new(y.address(), Some(x.address()), &by_y);
+        assert_eq!(report.disagreements().len(), 2);
+
+        // Chain still says X but updates are signed by Y
+        let report = UpdaterSignerReport::new(x.address(), None, &by_y);
+        assert_eq!(report.disagreements().len(), 3);
+
+        // A single foreign signer is caught with its root
+        let mut mixed = by_y.clone();
+        mixed[1] = signed_chain(&x, 2).await.remove(1);
+        let report = UpdaterSignerReport::new(y.address(), Some(y.address()), &mixed);
+        let disagreements = report.disagreements();
+        assert_eq!(disagreements.len(), 1);
+        assert!(disagreements[0].contains(&CanonicalH256(H256::repeat_byte(2)).to_string()));
+    }
+
+    #[tokio::test]
+    async fn it_reads_recent_updates_newest_first() {
+        test_utils::run_test_db(|db| async move {
+            let db = NomadDB::new("home_1", db);
+            let updates = signed_chain(&wallet("1"), 5).await;
+            for update in updates.iter() {
+                db.store_latest_update(update).unwrap();
+            }
+
+            let recent = db.recent_updates(3).unwrap();
+            let expected: Vec<_> = updates.iter().rev().take(3).cloned().collect();
+            assert_eq!(recent, expected);
+            assert_eq!(db.recent_updates(10).unwrap().len(), 5);
+        })
+        .await
+    }
+}
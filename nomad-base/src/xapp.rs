@@ -1,5 +1,7 @@
 use async_trait::async_trait;
-use nomad_core::{ConnectionManager, NomadIdentifier, SignedFailureNotification, TxOutcome};
+use nomad_core::{
+    ConnectionManager, NomadIdentifier, ReplicaEnrollment, SignedFailureNotification, TxOutcome,
+};
 
 use nomad_ethereum::EthereumConnectionManager;
 use nomad_test::mocks::MockConnectionManagerContract;
@@ -66,6 +68,46 @@ impl ConnectionManager for ConnectionManagers {
         }
     }
 
+    async fn replica_to_domain(
+        &self,
+        address: NomadIdentifier,
+    ) -> Result<u32, ChainCommunicationError> {
+        match self {
+            ConnectionManagers::Ethereum(connection_manager) => {
+                Ok(connection_manager.replica_to_domain(address).await?)
+            }
+            ConnectionManagers::Mock(connection_manager) => {
+                Ok(connection_manager.replica_to_domain(address).await?)
+            }
+        }
+    }
+
+    async fn get_block_number(&self) -> Result<u32, ChainCommunicationError> {
+        match self {
+            ConnectionManagers::Ethereum(connection_manager) => {
+                Ok(connection_manager.get_block_number().await?)
+            }
+            ConnectionManagers::Mock(connection_manager) => {
+                Ok(connection_manager.get_block_number().await?)
+            }
+        }
+    }
+
+    async fn fetch_replica_enrollments(
+        &self,
+        from: u32,
+        to: u32,
+    ) -> Result<Vec<ReplicaEnrollment>, ChainCommunicationError> {
+        match self {
+            ConnectionManagers::Ethereum(connection_manager) => Ok(connection_manager
+                .fetch_replica_enrollments(from, to)
+                .await?),
+            ConnectionManagers::Mock(connection_manager) => Ok(connection_manager
+                .fetch_replica_enrollments(from, to)
+                .await?),
+        }
+    }
+
     async fn watcher_permission(
         &self,
         address: NomadIdentifier,
@@ -0,0 +1,102 @@
+use ethers::{signers::LocalWallet, types::H256};
+use nomad_base::NomadDB;
+use nomad_core::{
+    utils::abbreviated_hex, DoubleUpdate, FailureNotification, SignedUpdateWithMeta, Update,
+    UpdateEvent, UpdateMeta,
+};
+use nomad_test::{log_scan::LogCapture, test_utils::run_test_db};
+use nomad_xyz_configuration::{
+    agent::SignerConf,
+    chains::{ethereum, TxSubmitterConf},
+};
+use std::str::FromStr;
+use tracing::{debug, info};
+
+const KEY: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+
+#[tokio::test]
+async fn logs_do_not_leak_keys_or_signatures() {
+    run_test_db(|db| async move {
+        let (capture, _guard) = LogCapture::start();
+
+        let signer: LocalWallet = KEY.parse().unwrap();
+        let previous_root = H256::repeat_byte(1);
+        let new_root = H256::repeat_byte(2);
+        let signed = Update {
+            home_domain: 1000,
+            previous_root,
+            new_root,
+        }
+        .sign_with(&signer)
+        .await
+        .unwrap();
+        let conflicting = Update {
+            home_domain: 1000,
+            previous_root,
+            new_root: H256::repeat_byte(3),
+        }
+        .sign_with(&signer)
+        .await
+        .unwrap();
+        let failure = FailureNotification {
+            home_domain: 1000,
+            updater: H256::repeat_byte(4).into(),
+        }
+        .sign_with(&signer)
+        .await
+        .unwrap();
+
+        // Key-bearing configs
+        let key = SignerConf::HexKey(FromStr::from_str(KEY).unwrap());
+        let submitter = TxSubmitterConf::Ethereum(ethereum::TxSubmitterConf::Local(key.clone()));
+        debug!(signer = ?key, submitter = ?submitter, "Loaded signer");
+        info!(key = %key, "Loaded signer");
+
+        // Signatures
+        info!(update = %signed, "Signed update");
+        debug!(update = ?signed, "Signed update");
+        debug!(double = ?DoubleUpdate(signed.clone(), conflicting.clone()), "Double update");
+        debug!(failure = ?failure, "Signed failure notification");
+        let event = UpdateEvent {
+            home_domain: 1000,
+            old_root: previous_root,
+            new_root,
+            signature: signed.signature.to_vec(),
+        };
+        debug!(event = ?event, "Update event");
+
+        // Roots logged by the db are fine
+        NomadDB::new("home_1", db)
+            .store_updates_and_meta(&[SignedUpdateWithMeta {
+                signed_update: signed.clone(),
+                metadata: UpdateMeta {
+                    block_number: 1,
+                    timestamp: None,
+                },
+            }])
+            .unwrap();
+
+        capture.assert_no_secrets(&[
+            previous_root,
+            new_root,
+            H256::repeat_byte(3),
+            H256::repeat_byte(4),
+        ]);
+        assert!(capture
+            .logs()
+            .contains(&abbreviated_hex(&signed.signature.to_vec())));
+    })
+    .await
+}
+
+#[test]
+fn scan_catches_unredacted_secrets() {
+    let (capture, _guard) = LogCapture::start();
+    let root = H256::repeat_byte(0xab);
+
+    info!(root = ?root, "Root");
+    assert!(capture.violations(&[root]).is_empty());
+
+    info!(key = KEY, "Leaked key");
+    assert_eq!(capture.violations(&[root]), vec![KEY.to_owned()]);
+}
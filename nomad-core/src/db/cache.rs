@@ -0,0 +1,144 @@
+use prometheus::{IntCounter, IntCounterVec, Opts};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, RwLock},
+};
+
+/// Default number of cached keys
+pub const DEFAULT_CACHE_CAPACITY: usize = 4096;
+
+#[derive(Debug, Default)]
+struct Entries {
+    values: HashMap<Vec<u8>, Option<Vec<u8>>>,
+    order: VecDeque<Vec<u8>>,
+}
+
+/// Bounded write-through cache of raw values, shared by every handle to one
+/// rocksdb instance. Only keys under registered prefixes are cached. Misses
+/// are cached too, so a cursor that is not yet set is not re-read.
+///
+/// Reads that miss hold the entry lock while reading rocksdb, and writes hold
+/// it while writing, so a read never caches a value older than a completed
+/// write.
+#[derive(Debug)]
+pub struct DbCache {
+    capacity: usize,
+    prefixes: RwLock<Vec<Vec<u8>>>,
+    entries: Mutex<Entries>,
+    hits: IntCounter,
+    misses: IntCounter,
+    lookups: IntCounterVec,
+}
+
+impl Default for DbCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_CAPACITY)
+    }
+}
+
+impl DbCache {
+    /// Instantiate a cache holding at most `capacity` keys
+    pub fn new(capacity: usize) -> Self {
+        let lookups = IntCounterVec::new(
+            Opts::new(
+                "db_cache_lookups_total",
+                "Reads of cached db key spaces, by hit or miss",
+            )
+            .namespace("nomad"),
+            &["result"],
+        )
+        .expect("invalid db cache metric");
+
+        Self {
+            capacity,
+            prefixes: Default::default(),
+            entries: Default::default(),
+            hits: lookups.with_label_values(&["hit"]),
+            misses: lookups.with_label_values(&["miss"]),
+            lookups,
+        }
+    }
+
+    /// Cache keys starting with `prefix`
+    pub fn add_prefix(&self, prefix: impl AsRef<[u8]>) {
+        let mut prefixes = self.prefixes.write().expect("poisoned");
+        if !prefixes.iter().any(|p| p == prefix.as_ref()) {
+            prefixes.push(prefix.as_ref().to_vec());
+        }
+    }
+
+    /// Whether `key` is in a cached key space
+    pub fn is_cached(&self, key: &[u8]) -> bool {
+        self.prefixes
+            .read()
+            .expect("poisoned")
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+    }
+
+    /// Hit and miss counters, for registering with a metrics registry
+    pub fn lookups(&self) -> IntCounterVec {
+        self.lookups.clone()
+    }
+
+    /// Read `key`, calling `read` on a miss and caching its result
+    pub fn get_or_read<E>(
+        &self,
+        key: &[u8],
+        read: impl FnOnce() -> Result<Option<Vec<u8>>, E>,
+    ) -> Result<Option<Vec<u8>>, E> {
+        let mut entries = self.entries.lock().expect("poisoned");
+        if let Some(value) = entries.values.get(key) {
+            self.hits.inc();
+            return Ok(value.clone());
+        }
+
+        self.misses.inc();
+        let value = read()?;
+        Self::insert(&mut entries, self.capacity, key, value.clone());
+        Ok(value)
+    }
+
+    /// Write `value` under `key` with `write`, updating the cache if the
+    /// write succeeds. `None` is a delete.
+    pub fn write_through<E>(
+        &self,
+        key: &[u8],
+        value: Option<&[u8]>,
+        write: impl FnOnce() -> Result<(), E>,
+    ) -> Result<(), E> {
+        let mut entries = self.entries.lock().expect("poisoned");
+        match write() {
+            Ok(()) => {
+                Self::insert(&mut entries, self.capacity, key, value.map(<[u8]>::to_vec));
+                Ok(())
+            }
+            Err(e) => {
+                // The write may have partially applied. Drop the entry and
+                // re-read next time.
+                entries.values.remove(key);
+                Err(e)
+            }
+        }
+    }
+
+    fn insert(entries: &mut Entries, capacity: usize, key: &[u8], value: Option<Vec<u8>>) {
+        if let Some(cached) = entries.values.get_mut(key) {
+            *cached = value;
+            return;
+        }
+        if capacity == 0 {
+            return;
+        }
+        while entries.values.len() >= capacity {
+            match entries.order.pop_front() {
+                Some(oldest) => {
+                    entries.values.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        entries.order.push_back(key.to_vec());
+        entries.values.insert(key.to_vec(), value);
+    }
+}
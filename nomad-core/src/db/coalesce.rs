@@ -0,0 +1,220 @@
+use prometheus::{Histogram, HistogramOpts};
+
+use super::DbError;
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    iter::Peekable,
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+/// Staged value of a key. `None` is a delete.
+type StagedValue = Option<Vec<u8>>;
+
+#[derive(Debug, Default)]
+struct Staged {
+    /// Latest staged value of each key
+    values: BTreeMap<Vec<u8>, StagedValue>,
+    /// Writes staged since the last flush, counting rewrites of a key
+    records: usize,
+    /// When the oldest write was staged
+    since: Option<Instant>,
+}
+
+/// Writes staged by the coalescing handles of one rocksdb instance, to be
+/// flushed together as one atomic `WriteBatch` once enough writes are staged
+/// or the oldest is old enough.
+///
+/// Every handle reads staged values over rocksdb, so staging changes when
+/// writes become durable, not what is read. A flush applies every write
+/// staged so far at once. A crash therefore loses a tail of the staged
+/// writes and never a write without the writes staged before it: a cursor
+/// staged after the data it covers is never flushed without that data.
+#[derive(Debug)]
+pub struct WriteCoalescer {
+    limits: RwLock<(usize, Duration)>,
+    staged: Mutex<Staged>,
+    batch_sizes: Histogram,
+    flush_latencies: Histogram,
+}
+
+impl Default for WriteCoalescer {
+    fn default() -> Self {
+        let batch_sizes = Histogram::with_opts(
+            HistogramOpts::new(
+                "db_write_batch_size",
+                "Keys written by each flush of coalesced db writes",
+            )
+            .namespace("nomad")
+            .buckets(vec![
+                1.0, 10.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+            ]),
+        )
+        .expect("invalid db write batch metric");
+        let flush_latencies = Histogram::with_opts(
+            HistogramOpts::new(
+                "db_write_batch_flush_seconds",
+                "Duration of each flush of coalesced db writes",
+            )
+            .namespace("nomad")
+            .buckets(vec![
+                0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+            ]),
+        )
+        .expect("invalid db write batch metric");
+
+        Self {
+            limits: RwLock::new((1, Duration::ZERO)),
+            staged: Default::default(),
+            batch_sizes,
+            flush_latencies,
+        }
+    }
+}
+
+impl WriteCoalescer {
+    /// Flush once `max_records` writes are staged, or once the oldest staged
+    /// write is `max_delay` old
+    pub fn set_limits(&self, max_records: usize, max_delay: Duration) {
+        *self.limits.write().expect("poisoned") = (max_records.max(1), max_delay);
+    }
+
+    /// Stage `value` under `key`. `None` is a delete. Returns whether the
+    /// staged writes are due for a flush.
+    pub fn stage(&self, key: &[u8], value: Option<&[u8]>) -> bool {
+        let (max_records, max_delay) = *self.limits.read().expect("poisoned");
+        let mut staged = self.staged.lock().expect("poisoned");
+        staged
+            .values
+            .insert(key.to_vec(), value.map(<[u8]>::to_vec));
+        staged.records += 1;
+        let since = *staged.since.get_or_insert_with(Instant::now);
+        staged.records >= max_records || since.elapsed() >= max_delay
+    }
+
+    /// Staged value of `key`, if a write of it is staged
+    pub fn staged(&self, key: &[u8]) -> Option<StagedValue> {
+        self.staged
+            .lock()
+            .expect("poisoned")
+            .values
+            .get(key)
+            .cloned()
+    }
+
+    /// Whether a write of `key` is staged
+    pub fn is_staged(&self, key: &[u8]) -> bool {
+        self.staged
+            .lock()
+            .expect("poisoned")
+            .values
+            .contains_key(key)
+    }
+
+    /// Apply the staged writes with `write` and clear them if it succeeds.
+    /// Reads wait for the flush, so they never miss a write that left the
+    /// stage. Returns the number of keys written.
+    pub fn flush<E>(
+        &self,
+        write: impl FnOnce(&BTreeMap<Vec<u8>, StagedValue>) -> Result<(), E>,
+    ) -> Result<usize, E> {
+        let mut staged = self.staged.lock().expect("poisoned");
+        if staged.values.is_empty() {
+            return Ok(0);
+        }
+
+        let started = Instant::now();
+        write(&staged.values)?;
+        let keys = staged.values.len();
+        *staged = Default::default();
+
+        self.batch_sizes.observe(keys as f64);
+        self.flush_latencies
+            .observe(started.elapsed().as_secs_f64());
+        Ok(keys)
+    }
+
+    /// Drop the staged writes without applying them, as a crash would.
+    /// Returns the number of keys dropped.
+    pub fn discard(&self) -> usize {
+        let mut staged = self.staged.lock().expect("poisoned");
+        let keys = staged.values.len();
+        *staged = Default::default();
+        keys
+    }
+
+    /// Staged writes of keys from `start` on that start with `prefix`, in
+    /// key order
+    fn snapshot(&self, start: &[u8], prefix: &[u8]) -> Vec<(Vec<u8>, StagedValue)> {
+        self.staged
+            .lock()
+            .expect("poisoned")
+            .values
+            .range(start.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Iterate over `rocks` with the writes staged now for keys from `start`
+    /// on that start with `prefix` applied over it
+    pub fn merge<'a>(
+        &self,
+        rocks: impl Iterator<Item = Result<DbEntry, DbError>> + 'a,
+        start: &[u8],
+        prefix: &[u8],
+    ) -> MergedIterator<'a> {
+        let rocks: Box<dyn Iterator<Item = Result<DbEntry, DbError>> + 'a> = Box::new(rocks);
+        MergedIterator {
+            rocks: rocks.peekable(),
+            staged: self.snapshot(start, prefix).into_iter().peekable(),
+        }
+    }
+
+    /// Sizes of flushed batches, for registering with a metrics registry
+    pub fn batch_sizes(&self) -> Histogram {
+        self.batch_sizes.clone()
+    }
+
+    /// Durations of flushes, for registering with a metrics registry
+    pub fn flush_latencies(&self) -> Histogram {
+        self.flush_latencies.clone()
+    }
+}
+
+/// Key and value read from the db
+pub type DbEntry = (Box<[u8]>, Box<[u8]>);
+
+/// Iterator over rocksdb in key order, with the writes that were staged when
+/// it was created applied over it. Values rocksdb fails to read are yielded
+/// as errors where they sort.
+pub struct MergedIterator<'a> {
+    rocks: Peekable<Box<dyn Iterator<Item = Result<DbEntry, DbError>> + 'a>>,
+    staged: Peekable<std::vec::IntoIter<(Vec<u8>, StagedValue)>>,
+}
+
+impl<'a> Iterator for MergedIterator<'a> {
+    type Item = Result<DbEntry, DbError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let order = match (self.rocks.peek(), self.staged.peek()) {
+                (None, None) => return None,
+                (Some(_), None) | (Some(Err(_)), _) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(Ok((key, _))), Some((staged, _))) => key.as_ref().cmp(staged.as_slice()),
+            };
+            if order == Ordering::Less {
+                return self.rocks.next();
+            }
+            // a staged write of a key replaces its value in rocksdb
+            if order == Ordering::Equal {
+                self.rocks.next();
+            }
+            if let (key, Some(value)) = self.staged.next()? {
+                return Some(Ok((key.into_boxed_slice(), value.into_boxed_slice())));
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use aes_gcm::{
+    aead::{Aead, NewAead, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use rand::RngCore;
+use sha3::{Digest, Keccak256};
+use std::{collections::HashMap, fmt};
+
+/// Marks an encrypted value. Values without it were written before
+/// encryption was enabled, and are only read while migrating.
+const MAGIC: &[u8; 8] = b"NOMADENC";
+/// Length of the id of the key a value is encrypted under
+const KEY_ID_LENGTH: usize = 4;
+/// Length of the nonce drawn for each value
+const NONCE_LENGTH: usize = 12;
+/// Length of the header preceding the ciphertext
+const HEADER_LENGTH: usize = MAGIC.len() + KEY_ID_LENGTH + NONCE_LENGTH;
+/// Length of the authentication tag ending the ciphertext
+const TAG_LENGTH: usize = 16;
+
+/// Id of a db encryption key, derived from the key
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyId([u8; KEY_ID_LENGTH]);
+
+impl KeyId {
+    fn of(key: &[u8; 32]) -> Self {
+        let digest = Keccak256::new()
+            .chain(b"nomad-db-encryption-key")
+            .chain(key)
+            .finalize();
+        let mut id = [0u8; KEY_ID_LENGTH];
+        id.copy_from_slice(&digest[..KEY_ID_LENGTH]);
+        Self(id)
+    }
+}
+
+impl fmt::Display for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+/// Errors reading encrypted values
+#[derive(thiserror::Error, Debug)]
+pub enum EncryptionError {
+    /// The value is encrypted and the db has no encryption key
+    #[error("db value under key 0x{key} is encrypted and no db encryption key is configured")]
+    NoKey {
+        /// Hex db key of the value
+        key: String,
+    },
+    /// The value is encrypted under a key the db was not given
+    #[error("db value under key 0x{key} is encrypted with key id {found}, not a configured key id ({configured})")]
+    UnknownKey {
+        /// Hex db key of the value
+        key: String,
+        /// Id of the key the value is encrypted under
+        found: KeyId,
+        /// Ids of the configured keys
+        configured: String,
+    },
+    /// The value failed authentication: it was modified, or moved from
+    /// another db key
+    #[error("db value under key 0x{key} failed authentication")]
+    Authentication {
+        /// Hex db key of the value
+        key: String,
+    },
+    /// The value is not encrypted, and the cipher is not migrating the db
+    #[error("db value under key 0x{key} is not encrypted. Unencrypted values are only read while migrating the db to encryption")]
+    Unencrypted {
+        /// Hex db key of the value
+        key: String,
+    },
+}
+
+/// Encryption of db values with AES-256-GCM.
+///
+/// An encrypted value is `MAGIC || key id || nonce || ciphertext || tag`.
+/// Each value gets a random nonce, and is authenticated together with its
+/// db key, so a value copied under another key fails to decrypt. Values are
+/// written under the write key and read under any key the cipher holds, so
+/// a db can be rekeyed while some values are still under the old key.
+/// Unencrypted values are rejected, so a value written around the cipher is
+/// not trusted, unless the cipher is migrating a db to encryption.
+#[derive(Clone)]
+pub struct DbCipher {
+    write: KeyId,
+    keys: HashMap<KeyId, Aes256Gcm>,
+    migrating: bool,
+}
+
+impl fmt::Debug for DbCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DbCipher")
+            .field("write", &self.write)
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .field("migrating", &self.migrating)
+            .finish()
+    }
+}
+
+impl DbCipher {
+    /// Cipher writing and reading values under `key`
+    pub fn new(key: &[u8; 32]) -> Self {
+        let write = KeyId::of(key);
+        Self {
+            write,
+            keys: HashMap::from([(write, Aes256Gcm::new(Key::from_slice(key)))]),
+            migrating: false,
+        }
+    }
+
+    /// Also read unencrypted values as they are, while a db written before
+    /// encryption was enabled is migrated
+    pub fn migrating(mut self) -> Self {
+        self.migrating = true;
+        self
+    }
+
+    /// Also read values encrypted under `key`
+    pub fn with_read_key(mut self, key: &[u8; 32]) -> Self {
+        self.keys
+            .insert(KeyId::of(key), Aes256Gcm::new(Key::from_slice(key)));
+        self
+    }
+
+    /// Id of the key values are written under
+    pub fn key_id(&self) -> KeyId {
+        self.write
+    }
+
+    /// Encrypt `value` for storage under `db_key`
+    pub fn seal(&self, db_key: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = self.keys[&self.write]
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: value,
+                    aad: db_key,
+                },
+            )
+            .expect("!encrypt");
+
+        let mut sealed = Vec::with_capacity(HEADER_LENGTH + ciphertext.len());
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&self.write.0);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend(ciphertext);
+        sealed
+    }
+
+    /// Decrypt a value stored under `db_key`. Unencrypted values are
+    /// rejected unless the cipher is migrating.
+    pub fn open(&self, db_key: &[u8], stored: Vec<u8>) -> Result<Vec<u8>, EncryptionError> {
+        self.open_with(db_key, stored, self.migrating)
+    }
+
+    /// Decrypt a value stored under `db_key`, returning unencrypted values
+    /// as they are if `migrating`
+    pub(crate) fn open_with(
+        &self,
+        db_key: &[u8],
+        stored: Vec<u8>,
+        migrating: bool,
+    ) -> Result<Vec<u8>, EncryptionError> {
+        let key_id = match stored_key_id(&stored) {
+            Some(key_id) => key_id,
+            None if migrating => return Ok(stored),
+            None => {
+                return Err(EncryptionError::Unencrypted {
+                    key: hex::encode(db_key),
+                })
+            }
+        };
+        let cipher = self
+            .keys
+            .get(&key_id)
+            .ok_or_else(|| EncryptionError::UnknownKey {
+                key: hex::encode(db_key),
+                found: key_id,
+                configured: self
+                    .keys
+                    .keys()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })?;
+
+        let nonce = &stored[MAGIC.len() + KEY_ID_LENGTH..HEADER_LENGTH];
+        cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: &stored[HEADER_LENGTH..],
+                    aad: db_key,
+                },
+            )
+            .map_err(|_| EncryptionError::Authentication {
+                key: hex::encode(db_key),
+            })
+    }
+
+    /// Whether `stored` is encrypted under the write key
+    pub fn is_current(&self, stored: &[u8]) -> bool {
+        stored_key_id(stored) == Some(self.write)
+    }
+}
+
+/// Id of the key `stored` is encrypted under, if it is encrypted
+fn stored_key_id(stored: &[u8]) -> Option<KeyId> {
+    if stored.len() < HEADER_LENGTH + TAG_LENGTH || !stored.starts_with(MAGIC) {
+        return None;
+    }
+    let mut id = [0u8; KEY_ID_LENGTH];
+    id.copy_from_slice(&stored[MAGIC.len()..MAGIC.len() + KEY_ID_LENGTH]);
+    Some(KeyId(id))
+}
+
+/// Read a value stored under `db_key` of a db without encryption. Fails if
+/// the value is encrypted.
+pub(crate) fn open_unencrypted(db_key: &[u8], stored: Vec<u8>) -> Result<Vec<u8>, EncryptionError> {
+    match stored_key_id(&stored) {
+        Some(_) => Err(EncryptionError::NoKey {
+            key: hex::encode(db_key),
+        }),
+        None => Ok(stored),
+    }
+}
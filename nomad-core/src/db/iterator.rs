@@ -1,17 +1,18 @@
+use crate::db::{DbError, MergedIterator};
 use crate::{Decode, Encode};
-use rocksdb::DBIterator;
 use std::marker::PhantomData;
 
-/// An iterator over a prefix that deserializes values
+/// An iterator over a prefix that deserializes values. Values failing to
+/// read or decode are yielded as errors.
 pub struct PrefixIterator<'a, V> {
-    iter: DBIterator<'a>,
+    iter: MergedIterator<'a>,
     prefix: &'a [u8],
     _phantom: PhantomData<*const V>,
 }
 
 impl<'a, V> PrefixIterator<'a, V> {
     /// Return new prefix iterator
-    pub fn new(iter: DBIterator<'a>, prefix: &'a [u8]) -> Self {
+    pub fn new(iter: MergedIterator<'a>, prefix: &'a [u8]) -> Self {
         Self {
             iter,
             prefix,
@@ -24,13 +25,15 @@ impl<'a, V> Iterator for PrefixIterator<'a, V>
 where
     V: Encode + Decode,
 {
-    type Item = V;
+    type Item = Result<V, DbError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         let prefix = self.prefix;
-        self.iter
-            .find(|(k, _)| k.strip_prefix(prefix).is_some())
-            .map(|(_, v)| v.to_vec())
-            .map(|v| V::read_from(&mut v.as_slice()).expect("!corrupt"))
+        let entry = self.iter.find(|entry| {
+            entry
+                .as_ref()
+                .map_or(true, |(k, _)| k.strip_prefix(prefix).is_some())
+        })?;
+        Some(entry.and_then(|(_, v)| Ok(V::read_from(&mut v.as_ref())?)))
     }
 }
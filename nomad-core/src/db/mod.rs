@@ -1,6 +1,6 @@
 use color_eyre::eyre::WrapErr;
-use rocksdb::{DBIterator, Options, DB as Rocks};
-use std::{path::Path, sync::Arc};
+use rocksdb::{DBIterator, Direction, IteratorMode, Options, WriteBatch, DB as Rocks};
+use std::{borrow::Cow, path::Path, sync::Arc, time::Duration};
 use tracing::info;
 
 /// Shared functionality surrounding use of rocksdb
@@ -10,15 +10,76 @@ pub mod iterator;
 mod typed_db;
 pub use typed_db::*;
 
+/// Write-through cache of hot key spaces
+mod cache;
+pub use cache::*;
+
+/// Coalescing of writes into batches
+mod coalesce;
+pub use coalesce::*;
+
+/// Encryption of values at rest
+mod encryption;
+pub use encryption::*;
+
 use crate::{Decode, Encode, NomadError};
 
 #[derive(Debug, Clone)]
-/// A KV Store
-pub struct DB(Arc<Rocks>);
+/// A KV Store. Clones share the rocksdb handle and its cache.
+///
+/// # Cancellation safety
+///
+/// Db methods are synchronous, so a single write is never torn by a task
+/// being cancelled. A task can only be cancelled at an `.await`, and anything
+/// it wrote before that point stays written. Async code that makes several
+/// related writes must therefore do all of its awaiting first and then apply
+/// the writes together, with no `.await` between them:
+///
+/// ```ignore
+/// let status = replica.message_status(leaf).await?; // may be cancelled here
+/// db.set_processed_failed(&message)?;               // no await from here on
+/// db.set_previously_attempted(&message)?;
+/// ```
+///
+/// In-memory state that a write depends on (flags, counters) is updated in
+/// the same section, not before an await. Writes that must span an await are
+/// made idempotent, so that redoing the step after a restart converges to the
+/// same state.
+///
+/// Async functions that write to the db and have been reviewed against these
+/// rules say so in their docs with a `CancellationSafe:` line giving the
+/// reason.
+///
+/// # Write coalescing
+///
+/// Writes through a handle from [`DB::coalescing`] are staged and flushed in
+/// batches, see [`WriteCoalescer`]. Every handle reads staged writes, and a
+/// write through another handle to a staged key flushes the stage first.
+///
+/// # Encryption
+///
+/// A db opened with [`DB::with_cipher`] encrypts values as they are written
+/// to rocksdb and decrypts them as they are read, see [`DbCipher`]. The cache
+/// and staged writes hold plaintext. Values written before encryption was
+/// enabled are only readable through a migrating cipher, and [`DB::rekey`]
+/// rewrites them encrypted.
+pub struct DB {
+    rocks: Arc<Rocks>,
+    cache: Arc<DbCache>,
+    writes: Arc<WriteCoalescer>,
+    coalesce: bool,
+    cipher: Option<Arc<DbCipher>>,
+}
 
 impl From<Rocks> for DB {
     fn from(rocks: Rocks) -> Self {
-        Self(Arc::new(rocks))
+        Self {
+            rocks: Arc::new(rocks),
+            cache: Default::default(),
+            writes: Default::default(),
+            coalesce: false,
+            cipher: None,
+        }
     }
 }
 
@@ -31,6 +92,9 @@ pub enum DbError {
     /// Nomad Error
     #[error("{0}")]
     NomadError(#[from] NomadError),
+    /// Encryption Error
+    #[error("{0}")]
+    EncryptionError(#[from] EncryptionError),
 }
 
 type Result<T> = std::result::Result<T, DbError>;
@@ -62,14 +126,222 @@ impl DB {
             .map(Into::into)
     }
 
+    /// Opens the existing db at `db_path` read-only. Unlike `from_path`, this
+    /// works while an agent has the db open, and reads the db as of opening.
+    #[tracing::instrument(err)]
+    pub fn read_only_from_path(db_path: &str) -> color_eyre::Result<DB> {
+        let path = Path::new(db_path).canonicalize()?;
+        Rocks::open_for_read_only(&Options::default(), &path, false)
+            .wrap_err(format!(
+                "Failed to open db path {} read-only, canonicalized as {:?}",
+                db_path, path
+            ))
+            .map(Into::into)
+    }
+
+    /// Encrypt values written from now on with `cipher`, and decrypt values
+    /// read with it. Applies to clones made after this call, so set it
+    /// before handing the db out.
+    pub fn with_cipher(self, cipher: DbCipher) -> Self {
+        Self {
+            cipher: Some(Arc::new(cipher)),
+            ..self
+        }
+    }
+
+    /// Id of the key values are encrypted under, if encryption is enabled
+    pub fn encryption_key_id(&self) -> Option<KeyId> {
+        self.cipher.as_ref().map(|cipher| cipher.key_id())
+    }
+
+    /// Rewrite every value not encrypted under the write key of the db's
+    /// cipher, `batch_size` values per write batch, then compact the db so
+    /// the replaced values are dropped from its files. Rewriting values
+    /// under their own key requires no key for unencrypted values, which are
+    /// always migrated, and a read key of the cipher for encrypted ones. A
+    /// rekey that stops partway resumes where it stopped, as values already
+    /// under the write key are skipped. Returns the number of values
+    /// rewritten, none without a cipher.
+    ///
+    /// Run it with no agent using the db: rocksdb refuses to open a db an
+    /// agent holds.
+    pub fn rekey(&self, batch_size: usize) -> Result<usize> {
+        let cipher = match &self.cipher {
+            Some(cipher) => cipher,
+            None => return Ok(0),
+        };
+        self.flush_writes()?;
+
+        let mut rewritten = 0;
+        let mut batch = WriteBatch::default();
+        for (key, stored) in self.rocks.iterator(IteratorMode::Start) {
+            if cipher.is_current(&stored) {
+                continue;
+            }
+            let value = cipher.open_with(&key, stored.into_vec(), true)?;
+            batch.put(&key, cipher.seal(&key, &value));
+            rewritten += 1;
+            if batch.len() >= batch_size {
+                self.rocks.write(std::mem::take(&mut batch))?;
+                info!(rewritten, "Rekeyed db values");
+            }
+        }
+        self.rocks.write(batch)?;
+
+        // until compacted, replaced values linger in older files
+        self.rocks.compact_range::<&[u8], &[u8]>(None, None);
+        info!(rewritten, "Rekeyed and compacted db");
+        Ok(rewritten)
+    }
+
+    /// Value as written to rocksdb under `key`
+    fn seal<'a>(&self, key: &[u8], value: &'a [u8]) -> Cow<'a, [u8]> {
+        match &self.cipher {
+            Some(cipher) => Cow::Owned(cipher.seal(key, value)),
+            None => Cow::Borrowed(value),
+        }
+    }
+
+    /// Value read from rocksdb under `key`
+    fn open(&self, key: &[u8], stored: Vec<u8>) -> Result<Vec<u8>> {
+        Ok(match &self.cipher {
+            Some(cipher) => cipher.open(key, stored)?,
+            None => open_unencrypted(key, stored)?,
+        })
+    }
+
+    /// Read a value from rocksdb
+    fn rocks_get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.rocks
+            .get(key)?
+            .map(|stored| self.open(key, stored))
+            .transpose()
+    }
+
+    /// Iterate over rocksdb with values decrypted. Values failing to
+    /// decrypt are yielded as errors.
+    fn opened<'a>(&'a self, rocks: DBIterator<'a>) -> impl Iterator<Item = Result<DbEntry>> + 'a {
+        rocks.map(move |(key, stored)| {
+            let value = self.open(&key, stored.into_vec())?;
+            Ok((key, value.into_boxed_slice()))
+        })
+    }
+
+    /// Cache reads of keys starting with `prefix`. Applies to every clone
+    /// of this handle.
+    pub fn cache_prefix(&self, prefix: impl AsRef<[u8]>) {
+        self.cache.add_prefix(prefix)
+    }
+
+    /// Cache hit and miss counters
+    pub fn cache_lookups(&self) -> prometheus::IntCounterVec {
+        self.cache.lookups()
+    }
+
+    /// Handle whose writes are staged and flushed as one batch once
+    /// `max_records` writes are staged or the oldest is `max_delay` old. The
+    /// limits apply to every coalescing handle of this db.
+    pub fn coalescing(&self, max_records: usize, max_delay: Duration) -> DB {
+        self.writes.set_limits(max_records, max_delay);
+        Self {
+            coalesce: true,
+            ..self.clone()
+        }
+    }
+
+    /// Write every staged write in one batch. Returns the number of keys
+    /// written.
+    pub fn flush_writes(&self) -> Result<usize> {
+        self.writes.flush(|staged| {
+            let mut batch = WriteBatch::default();
+            for (key, value) in staged {
+                match value {
+                    Some(value) => batch.put(key, self.seal(key, value)),
+                    None => batch.delete(key),
+                }
+            }
+            self.rocks.write(batch)?;
+
+            for (key, value) in staged {
+                if self.cache.is_cached(key) {
+                    self.cache
+                        .write_through(key, value.as_deref(), || Ok::<_, DbError>(()))?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Drop the staged writes without writing them, as a crash would.
+    /// Returns the number of keys dropped.
+    pub fn discard_staged_writes(&self) -> usize {
+        self.writes.discard()
+    }
+
+    /// Sizes and durations of flushed write batches, for registering with a
+    /// metrics registry
+    pub fn write_batch_metrics(&self) -> (prometheus::Histogram, prometheus::Histogram) {
+        (self.writes.batch_sizes(), self.writes.flush_latencies())
+    }
+
+    /// Stage a write through a coalescing handle, flushing if the stage is
+    /// full. Writes through other handles flush the stage first if it holds
+    /// their key, so they are not overwritten by an older staged value.
+    /// Returns whether the write was staged.
+    fn stage(&self, key: &[u8], value: Option<&[u8]>) -> Result<bool> {
+        if self.coalesce {
+            if self.writes.stage(key, value) {
+                self.flush_writes()?;
+            }
+            return Ok(true);
+        }
+        if self.writes.is_staged(key) {
+            self.flush_writes()?;
+        }
+        Ok(false)
+    }
+
     /// Store a value in the DB
     fn _store(&self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) -> Result<()> {
-        Ok(self.0.put(key, value)?)
+        let key = key.as_ref();
+        let value = value.as_ref();
+        if self.stage(key, Some(value))? {
+            return Ok(());
+        }
+        let stored = self.seal(key, value);
+        if self.cache.is_cached(key) {
+            self.cache
+                .write_through(key, Some(value), || Ok(self.rocks.put(key, &stored)?))
+        } else {
+            Ok(self.rocks.put(key, &stored)?)
+        }
     }
 
     /// Retrieve a value from the DB
     fn _retrieve(&self, key: impl AsRef<[u8]>) -> Result<Option<Vec<u8>>> {
-        Ok(self.0.get(key)?)
+        let key = key.as_ref();
+        if let Some(staged) = self.writes.staged(key) {
+            return Ok(staged);
+        }
+        if self.cache.is_cached(key) {
+            self.cache.get_or_read(key, || self.rocks_get(key))
+        } else {
+            self.rocks_get(key)
+        }
+    }
+
+    /// Delete a value from the DB
+    fn _delete(&self, key: impl AsRef<[u8]>) -> Result<()> {
+        let key = key.as_ref();
+        if self.stage(key, None)? {
+            return Ok(());
+        }
+        if self.cache.is_cached(key) {
+            self.cache
+                .write_through(key, None, || Ok(self.rocks.delete(key)?))
+        } else {
+            Ok(self.rocks.delete(key)?)
+        }
     }
 
     /// Prefix a key and store in the DB
@@ -97,6 +369,33 @@ impl DB {
         self._retrieve(buf)
     }
 
+    /// Prefix the key and delete
+    fn prefix_delete(&self, prefix: impl AsRef<[u8]>, key: impl AsRef<[u8]>) -> Result<()> {
+        let mut buf = vec![];
+        buf.extend(prefix.as_ref());
+        buf.extend(key.as_ref());
+        self._delete(buf)
+    }
+
+    /// Move the raw value under `key` from one prefix to another without
+    /// decoding it. Returns false if there was no value.
+    pub fn move_keyed<K: Encode>(
+        &self,
+        from: impl AsRef<[u8]>,
+        to: impl AsRef<[u8]>,
+        key: &K,
+    ) -> Result<bool> {
+        let key = key.to_vec();
+        match self.prefix_retrieve(&from, &key)? {
+            Some(value) => {
+                self.prefix_store(to, &key, value)?;
+                self.prefix_delete(from, &key)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
     /// Store any encodeable
     pub fn store_encodable<V: Encode>(
         &self,
@@ -138,8 +437,57 @@ impl DB {
         self.retrieve_decodable(prefix, key.to_vec())
     }
 
+    /// Delete the value under an encodable key
+    pub fn delete_keyed<K: Encode>(&self, prefix: impl AsRef<[u8]>, key: &K) -> Result<()> {
+        self.prefix_delete(prefix, key.to_vec())
+    }
+
     /// Get prefix db iterator for `prefix`
-    pub fn prefix_iterator(&self, prefix: impl AsRef<[u8]>) -> DBIterator {
-        self.0.prefix_iterator(prefix)
+    pub fn prefix_iterator(&self, prefix: impl AsRef<[u8]>) -> MergedIterator {
+        let prefix = prefix.as_ref();
+        self.writes.merge(
+            self.opened(self.rocks.prefix_iterator(prefix)),
+            prefix,
+            prefix,
+        )
+    }
+
+    /// Get db iterator over the keys from `key` on, in key order
+    pub fn iterator_from(&self, key: impl AsRef<[u8]>) -> MergedIterator {
+        let key = key.as_ref();
+        self.writes.merge(
+            self.opened(
+                self.rocks
+                    .iterator(IteratorMode::From(key, Direction::Forward)),
+            ),
+            key,
+            b"",
+        )
+    }
+
+    /// Get db iterator over the keys starting with `prefix` from `key` on, in
+    /// key order
+    pub fn iterator_within(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        key: impl AsRef<[u8]>,
+    ) -> impl Iterator<Item = Result<DbEntry>> + '_ {
+        let prefix = prefix.as_ref().to_vec();
+        let mut start = prefix.clone();
+        start.extend(key.as_ref());
+        self.writes
+            .merge(
+                self.opened(
+                    self.rocks
+                        .iterator(IteratorMode::From(&start, Direction::Forward)),
+                ),
+                &start,
+                &prefix,
+            )
+            .take_while(move |entry| {
+                entry
+                    .as_ref()
+                    .map_or(true, |(key, _)| key.starts_with(&prefix))
+            })
     }
 }
@@ -25,6 +25,11 @@ impl TypedDB {
         Self { entity, db }
     }
 
+    /// Cache reads of `prefix` for this entity
+    pub fn cache_prefix(&self, prefix: impl AsRef<[u8]>) {
+        self.db.cache_prefix(self.full_prefix(prefix))
+    }
+
     fn full_prefix(&self, prefix: impl AsRef<[u8]>) -> Vec<u8> {
         let mut full_prefix = vec![];
         full_prefix.extend(self.entity.as_ref() as &[u8]);
@@ -64,6 +69,26 @@ impl TypedDB {
             .store_keyed_encodable(self.full_prefix(prefix), key, value)
     }
 
+    /// Move the raw value under `key` from one prefix to another
+    pub fn move_keyed<K: Encode>(
+        &self,
+        from: impl AsRef<[u8]>,
+        to: impl AsRef<[u8]>,
+        key: &K,
+    ) -> Result<bool, DbError> {
+        self.db
+            .move_keyed(self.full_prefix(from), self.full_prefix(to), key)
+    }
+
+    /// Delete the value under an encodable key
+    pub fn delete_keyed<K: Encode>(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        key: &K,
+    ) -> Result<(), DbError> {
+        self.db.delete_keyed(self.full_prefix(prefix), key)
+    }
+
     /// Retrieve decodable value given encodable key
     pub fn retrieve_keyed_decodable<K: Encode, V: Decode>(
         &self,
@@ -73,4 +98,33 @@ impl TypedDB {
         self.db
             .retrieve_keyed_decodable(self.full_prefix(prefix), key)
     }
+
+    /// Iterate over the values under `prefix` whose keys sort at or after
+    /// `from`, in key order
+    pub fn iterate_from<V: Decode>(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        from: impl AsRef<[u8]>,
+    ) -> impl Iterator<Item = Result<V, DbError>> + '_ {
+        self.db
+            .iterator_within(self.full_prefix(prefix), from)
+            .map(|entry| {
+                let (_, value) = entry?;
+                Ok(V::read_from(&mut value.as_ref())?)
+            })
+    }
+
+    /// Iterate over the raw keys and values under `prefix`, in key order.
+    /// Keys are stripped of the prefix.
+    pub fn raw_entries(
+        &self,
+        prefix: impl AsRef<[u8]>,
+    ) -> impl Iterator<Item = Result<(Vec<u8>, Vec<u8>), DbError>> + '_ {
+        let prefix = self.full_prefix(prefix);
+        let prefix_len = prefix.len();
+        self.db.iterator_within(prefix, b"").map(move |entry| {
+            let (key, value) = entry?;
+            Ok((key[prefix_len..].to_vec(), value.to_vec()))
+        })
+    }
 }
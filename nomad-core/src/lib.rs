@@ -48,7 +48,11 @@ pub mod test_output;
 mod chain;
 pub use chain::*;
 
-pub use nomad_types::NomadIdentifier;
+pub use nomad_types::{
+    evm_address_serde, format_evm_address, format_h256, h256_serde, parse_evm_address, parse_h256,
+    Address32, CanonicalH256, ChecksumAddress, MessageId, MessageRef, NomadIdentifier,
+    ReadableAddress32,
+};
 
 use ethers::core::types::{SignatureError, H256};
 
@@ -69,6 +73,9 @@ pub enum NomadError {
     /// Signature Error pasthrough
     #[error(transparent)]
     SignatureError(#[from] SignatureError),
+    /// Signature recovery byte is not one of 0, 1, 27 or 28
+    #[error("Signature v must be 0, 1, 27 or 28. Got: {0}")]
+    InvalidSignatureV(u64),
     /// Update does not build off the current root
     #[error("Update has wrong current root. Expected: {expected}. Got: {actual}.")]
     WrongCurrentRoot {
@@ -84,4 +91,31 @@ pub enum NomadError {
     /// IO error from Read/Write usage
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+    /// An update chain was built from no updates
+    #[error("Update chain is empty")]
+    EmptyUpdateChain,
+    /// An update in a chain does not build off the previous update's new root
+    #[error(
+        "Update chain broken at update {index}. Expected previous root: {expected}. Got: {actual}."
+    )]
+    BrokenUpdateChain {
+        /// Position of the offending update in the chain
+        index: usize,
+        /// The previous update's new root
+        expected: H256,
+        /// The offending update's previous root
+        actual: H256,
+    },
+    /// Governance message body has an unknown type byte
+    #[error("Unknown governance message type: {0}")]
+    UnknownGovernanceMessage(u8),
+    /// Bridge message body has an unknown action type byte
+    #[error("Unknown bridge action type: {0}")]
+    UnknownBridgeAction(u8),
+    /// Raw message is shorter than the message header
+    #[error("Message of {0} bytes is shorter than the message header")]
+    MessageTooShort(usize),
+    /// Encoded proof path is truncated
+    #[error(transparent)]
+    ProofLength(#[from] accumulator::ProofLengthError),
 }
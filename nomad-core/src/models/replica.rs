@@ -117,7 +117,7 @@ impl Replica<Waiting> {
             state: Pending {
                 root: self.state.root,
                 new_root: update.update.new_root,
-                timeout: now() + self.optimistic_wait,
+                timeout: now().saturating_add(self.optimistic_wait),
             },
         })
     }
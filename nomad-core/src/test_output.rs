@@ -38,16 +38,27 @@ pub mod output_functions {
             body: Vec::from_hex("1234").unwrap(),
         };
 
-        let message_json = json!({
-            "origin": nomad_message.origin,
-            "sender": nomad_message.sender,
-            "destination": nomad_message.destination,
-            "recipient": nomad_message.recipient,
-            "nonce": nomad_message.nonce,
-            "body": nomad_message.body,
-            "messageHash": nomad_message.to_leaf(),
-        });
-        let json = json!([message_json]).to_string();
+        // Pings dispatch zero-length bodies
+        let empty_body_message = NomadMessage {
+            body: vec![],
+            ..nomad_message.clone()
+        };
+
+        let messages: Vec<Value> = [nomad_message, empty_body_message]
+            .iter()
+            .map(|message| {
+                json!({
+                    "origin": message.origin,
+                    "sender": message.sender,
+                    "destination": message.destination,
+                    "recipient": message.recipient,
+                    "nonce": message.nonce,
+                    "body": message.body,
+                    "messageHash": message.to_leaf(),
+                })
+            })
+            .collect();
+        let json = json!(messages).to_string();
 
         let mut file = OpenOptions::new()
             .write(true)
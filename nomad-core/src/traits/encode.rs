@@ -143,8 +143,13 @@ impl<const N: usize> Decode for accumulator::Proof<N> {
 
         reader.read_exact(leaf.as_bytes_mut())?;
         reader.read_exact(&mut index_bytes)?;
-        for item in &mut path {
-            reader.read_exact(item.as_bytes_mut())?;
+        for (got, item) in path.iter_mut().enumerate() {
+            match reader.read_exact(item.as_bytes_mut()) {
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    return Err(accumulator::ProofLengthError { expected: N, got }.into())
+                }
+                result => result?,
+            }
         }
 
         let index = u64::from_be_bytes(index_bytes) as usize;
@@ -2,7 +2,7 @@ use crate::{
     db::DbError,
     traits::{Common, TxOutcome},
     utils::home_domain_hash,
-    Decode, Encode, Message, NomadError, NomadMessage, SignedUpdate, Update,
+    Decode, Encode, Message, MessageId, NomadError, NomadMessage, SignedUpdate, Update,
 };
 use async_trait::async_trait;
 use color_eyre::Result;
@@ -21,6 +21,10 @@ pub struct RawCommittedMessage {
     pub committed_root: H256,
     /// The fully detailed message that was committed
     pub message: Vec<u8>,
+    /// Timestamp of the block the message was dispatched in, if known. Not
+    /// part of the encoding: the db stores it under its own key, so messages
+    /// stored before it was indexed still decode.
+    pub timestamp: Option<u64>,
 }
 
 impl RawCommittedMessage {
@@ -64,6 +68,7 @@ impl Decode for RawCommittedMessage {
             leaf_index: u32::from_be_bytes(idx),
             committed_root: hash.into(),
             message,
+            timestamp: None,
         })
     }
 }
@@ -78,6 +83,8 @@ pub struct CommittedMessage {
     pub committed_root: H256,
     /// The fully detailed message that was committed
     pub message: NomadMessage,
+    /// Timestamp of the block the message was dispatched in, if known
+    pub timestamp: Option<u64>,
 }
 
 impl CommittedMessage {
@@ -85,6 +92,11 @@ impl CommittedMessage {
     pub fn to_leaf(&self) -> H256 {
         self.message.to_leaf()
     }
+
+    /// Return the id of the message, by origin domain and leaf index
+    pub fn message_id(&self) -> MessageId {
+        MessageId::new(self.message.origin, self.leaf_index)
+    }
 }
 
 impl AsRef<NomadMessage> for CommittedMessage {
@@ -100,11 +112,25 @@ impl TryFrom<RawCommittedMessage> for CommittedMessage {
         Ok(Self {
             leaf_index: raw.leaf_index,
             committed_root: raw.committed_root,
-            message: NomadMessage::read_from(&mut &raw.message[..])?,
+            message: NomadMessage::from_raw(&raw.message)?,
+            timestamp: raw.timestamp,
         })
     }
 }
 
+/// The result of a dispatch, locating the message in the home's tree
+#[derive(Debug, Clone, Copy)]
+pub struct DispatchOutcome {
+    /// The dispatch transaction
+    pub tx: TxOutcome,
+    /// The index at which the message was committed
+    pub leaf_index: u32,
+    /// The message hash, i.e. its leaf
+    pub message_hash: H256,
+    /// The encoded destination + nonce of the message
+    pub destination_and_nonce: u64,
+}
+
 /// Interface for the Home chain contract. Allows abstraction over different
 /// chains
 #[async_trait]
@@ -123,12 +149,32 @@ pub trait Home: Common + Send + Sync + std::fmt::Debug {
     /// Dispatch a message.
     async fn dispatch(&self, message: &Message) -> Result<TxOutcome, <Self as Common>::Error>;
 
+    /// Dispatch a message and locate it in the tree, read from the `Dispatch`
+    /// event of the transaction. Fails if the transaction emitted none.
+    async fn dispatch_tracked(
+        &self,
+        message: &Message,
+    ) -> Result<DispatchOutcome, <Self as Common>::Error>;
+
     /// Return length of queue.
     async fn queue_length(&self) -> Result<U256, <Self as Common>::Error>;
 
     /// Check if queue contains root.
     async fn queue_contains(&self, root: H256) -> Result<bool, <Self as Common>::Error>;
 
+    /// Return the latest root in the queue. Zero if the queue is empty.
+    async fn queue_end(&self) -> Result<H256, <Self as Common>::Error>;
+
+    /// Fetch the current tree root and leaf count. Both are read at the same
+    /// block, so the count always belongs to the root. Prefer this over
+    /// separate calls when both are needed.
+    async fn counted_root(&self) -> Result<(H256, u32), <Self as Common>::Error>;
+
+    /// Fetch the leaf count as reported by `tree().count` and by `count()`,
+    /// read at the same block. The two must be equal. A mismatch indicates a
+    /// contract storage anomaly.
+    async fn tree_counts(&self) -> Result<(u32, u32), <Self as Common>::Error>;
+
     /// Submit an improper update for slashing
     async fn improper_update(
         &self,
@@ -140,6 +186,14 @@ pub trait Home: Common + Send + Sync + std::fmt::Debug {
     /// update has been produced. The updater MUST take measures to prevent
     /// double-updating. If no messages are queued, this must produce Ok(None).
     async fn produce_update(&self) -> Result<Option<Update>, <Self as Common>::Error>;
+
+    /// Suggest an update from the chain's pending block. This is only a hint
+    /// that an update is available: never sign based on it alone, and
+    /// confirm it with `produce_update`. Chains without a pending block
+    /// suggest from the same state as `produce_update`.
+    async fn produce_pending_update(&self) -> Result<Option<Update>, <Self as Common>::Error> {
+        self.produce_update().await
+    }
 }
 
 /// Interface for retrieving event data emitted specifically by the home
@@ -11,7 +11,7 @@ use async_trait::async_trait;
 use color_eyre::Result;
 use std::error::Error as StdError;
 
-use crate::{RawCommittedMessage, SignedUpdateWithMeta};
+use crate::{RawCommittedMessage, RootAdvance, SignedUpdateWithMeta};
 
 /// Interface for Common contract indexer. Interface that allows for other
 /// entities to retrieve chain-specific data from a home or replica.
@@ -23,12 +23,29 @@ pub trait CommonIndexer: Send + Sync + std::fmt::Debug {
     /// Get chain's latest block number
     async fn get_block_number(&self) -> Result<u32, Self::Error>;
 
+    /// Get the timestamp of the chain's latest block. `None` if the chain
+    /// does not report one.
+    async fn get_latest_block_timestamp(&self) -> Result<Option<u64>, Self::Error> {
+        Ok(None)
+    }
+
     /// Fetch sequentially sorted list of updates between blocks `from` and `to`
     async fn fetch_sorted_updates(
         &self,
         from: u32,
         to: u32,
     ) -> Result<Vec<SignedUpdateWithMeta>, Self::Error>;
+
+    /// Fetch the roots a replica came to accept between blocks `from` and
+    /// `to`, through updates or governance confirmations, in the order they
+    /// were accepted. Indexers of other contracts find none.
+    async fn fetch_root_advances(
+        &self,
+        _from: u32,
+        _to: u32,
+    ) -> Result<Vec<RootAdvance>, Self::Error> {
+        Ok(vec![])
+    }
 }
 
 /// Interface for Home contract indexer. Interface for allowing other
@@ -7,7 +7,7 @@ mod xapp;
 
 use async_trait::async_trait;
 use color_eyre::Result;
-use ethers::core::types::H256;
+use ethers::core::types::{H256, U256};
 use std::{error::Error as StdError, fmt::Display};
 
 use crate::{db::DbError, SignedUpdate};
@@ -23,7 +23,7 @@ pub use xapp::*;
 pub type BoxStdError = Box<dyn std::error::Error + Send + Sync>;
 
 /// Contract states
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum State {
     /// Contract uninitialized
     Uninitialized,
@@ -51,6 +51,10 @@ impl Display for DoubleUpdate {
 pub struct TxOutcome {
     /// The txid
     pub txid: H256,
+    /// Gas used by the transaction, if its receipt was read
+    pub gas_used: Option<U256>,
+    /// Block the transaction was included in, if its receipt was read
+    pub block_number: Option<u64>,
     // TODO: more? What can be abstracted across all chains?
 }
 
@@ -5,24 +5,42 @@ use ethers::core::types::H256;
 use crate::{
     accumulator::NomadProof,
     traits::{Common, TxOutcome},
-    NomadMessage,
+    Decode, Encode, NomadError, NomadMessage,
 };
 
+/// Status word of a message unknown to the replica
+pub const LEGACY_STATUS_NONE: H256 = legacy_status(0);
+/// Status word of a message proven under a replica version before roots were
+/// stored. The replica still accepts it as proven.
+pub const LEGACY_STATUS_PROVEN: H256 = legacy_status(1);
+/// Status word of a processed message
+pub const LEGACY_STATUS_PROCESSED: H256 = legacy_status(2);
+
+const fn legacy_status(status: u8) -> H256 {
+    let mut word = [0u8; 32];
+    word[31] = status;
+    H256(word)
+}
+
 /// The status of a message in the replica
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MessageStatus {
     /// Message is unknown
     None,
-    /// Message has been proven but not processed
+    /// Message has been proven but not processed, under this root. Messages
+    /// proven under old replica versions have `LEGACY_STATUS_PROVEN` instead
     Proven(H256),
     /// Message has been processed
     Processed,
 }
+
+/// Maps the replica's `messages(leaf)` status word
 impl From<H256> for MessageStatus {
     fn from(status: H256) -> Self {
-        if status.is_zero() {
+        if status == LEGACY_STATUS_NONE {
             return MessageStatus::None;
         }
-        if status == H256::from_low_u64_be(2) {
+        if status == LEGACY_STATUS_PROCESSED {
             return MessageStatus::Processed;
         }
         MessageStatus::Proven(status)
@@ -36,6 +54,83 @@ impl From<[u8; 32]> for MessageStatus {
     }
 }
 
+/// Replica state a message is checked against before it is processed,
+/// read at a single block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessingState {
+    /// Whether the replica accepts the root of the message's proof
+    pub root_acceptable: bool,
+    /// Timestamp at which the root becomes acceptable. 0 if the replica has
+    /// not seen the root.
+    pub confirm_at: u64,
+    /// Status of the message
+    pub status: MessageStatus,
+}
+
+/// How a replica came to accept a root
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RootProvenance {
+    /// An `Update` signed by the updater
+    Update,
+    /// Governance `setConfirmation`, with no update attesting to the root
+    Governance,
+    /// The committed root the replica was initialized with
+    Genesis,
+}
+
+impl RootProvenance {
+    /// Whether the updater attested to the root
+    pub fn is_attested(&self) -> bool {
+        *self == RootProvenance::Update
+    }
+}
+
+impl std::fmt::Display for RootProvenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RootProvenance::Update => write!(f, "update"),
+            RootProvenance::Governance => write!(f, "governance"),
+            RootProvenance::Genesis => write!(f, "genesis"),
+        }
+    }
+}
+
+impl Encode for RootProvenance {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let tag: u8 = match self {
+            RootProvenance::Update => 0,
+            RootProvenance::Governance => 1,
+            RootProvenance::Genesis => 2,
+        };
+        writer.write_all(&[tag])?;
+        Ok(1)
+    }
+}
+
+impl Decode for RootProvenance {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let mut tag = [0u8; 1];
+        reader.read_exact(&mut tag)?;
+        match tag[0] {
+            0 => Ok(RootProvenance::Update),
+            1 => Ok(RootProvenance::Governance),
+            2 => Ok(RootProvenance::Genesis),
+            other => Err(NomadError::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unknown root provenance {}", other),
+            ))),
+        }
+    }
+}
+
 /// Interface for on-chain replicas
 #[async_trait]
 pub trait Replica: Common + Send + Sync + std::fmt::Debug {
@@ -65,6 +160,44 @@ pub trait Replica: Common + Send + Sync + std::fmt::Debug {
     /// Fetch the status of a message
     async fn message_status(&self, leaf: H256) -> Result<MessageStatus, <Self as Common>::Error>;
 
+    /// Fetch the `success` flag of the `Process` event emitted for a message,
+    /// in `block` if the block it was processed in is known. Returns `None`
+    /// if no `Process` event was found for the message.
+    async fn process_success(
+        &self,
+        leaf: H256,
+        block: Option<u64>,
+    ) -> Result<Option<bool>, <Self as Common>::Error>;
+
     /// Fetch the confirmation time for a specific root
     async fn acceptable_root(&self, root: H256) -> Result<bool, <Self as Common>::Error>;
+
+    /// Fetch the timestamp at which a root becomes acceptable. 0 if the
+    /// replica has not seen the root.
+    async fn confirm_at(&self, root: H256) -> Result<u64, <Self as Common>::Error>;
+
+    /// Fetch the acceptance of `root`, its confirmation time and the status
+    /// of the message with `leaf` together. Replicas reading from
+    /// load-balanced providers read them at the same block, so nodes at
+    /// different heights cannot answer them inconsistently.
+    async fn processing_state(
+        &self,
+        root: H256,
+        leaf: H256,
+    ) -> Result<ProcessingState, <Self as Common>::Error> {
+        Ok(ProcessingState {
+            root_acceptable: self.acceptable_root(root).await?,
+            confirm_at: self.confirm_at(root).await?,
+            status: self.message_status(leaf).await?,
+        })
+    }
+
+    /// Fetch how the replica came to accept a root. An update attesting to
+    /// the root takes precedence over governance confirming it. `None` if
+    /// the replica does not accept the root, or if the event confirming it
+    /// is older than implementations search.
+    async fn root_provenance(
+        &self,
+        root: H256,
+    ) -> Result<Option<RootProvenance>, <Self as Common>::Error>;
 }
@@ -1,7 +1,10 @@
 use async_trait::async_trait;
 use color_eyre::Result;
-use ethers::prelude::{Signature, Signer};
+use ethers::prelude::Signer;
 use nomad_xyz_configuration::agent::SignerConf;
+use std::convert::TryFrom;
+
+use crate::CanonicalSignature;
 
 /// Extension of ethers signer trait
 #[async_trait]
@@ -10,10 +13,10 @@ pub trait SignerExt: Signer {
     async fn sign_message_without_eip_155<S: Send + Sync + AsRef<[u8]>>(
         &self,
         message: S,
-    ) -> Result<Signature, <Self as Signer>::Error> {
+    ) -> Result<CanonicalSignature, <Self as Signer>::Error> {
         let mut signature = self.sign_message(message).await?;
         signature.v = 28 - (signature.v % 2);
-        Ok(signature)
+        Ok(CanonicalSignature::try_from(signature).expect("v is 27 or 28"))
     }
 }
 
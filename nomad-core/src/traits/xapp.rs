@@ -1,4 +1,4 @@
-use crate::{traits::TxOutcome, SignedFailureNotification};
+use crate::{traits::TxOutcome, ReplicaEnrollment, SignedFailureNotification};
 use async_trait::async_trait;
 use nomad_types::NomadIdentifier;
 use std::error::Error as StdError;
@@ -15,6 +15,21 @@ pub trait ConnectionManager: Send + Sync + std::fmt::Debug {
     /// Returns true if provided address is enrolled replica
     async fn is_replica(&self, address: NomadIdentifier) -> Result<bool, Self::Error>;
 
+    /// Returns the remote domain the replica at address is enrolled for, 0 if
+    /// none
+    async fn replica_to_domain(&self, address: NomadIdentifier) -> Result<u32, Self::Error>;
+
+    /// Get chain's latest block number
+    async fn get_block_number(&self) -> Result<u32, Self::Error>;
+
+    /// Fetch replica enrollments and unenrollments between blocks `from` and
+    /// `to`, sorted by block
+    async fn fetch_replica_enrollments(
+        &self,
+        from: u32,
+        to: u32,
+    ) -> Result<Vec<ReplicaEnrollment>, Self::Error>;
+
     /// Returns permission for address at given domain
     async fn watcher_permission(
         &self,
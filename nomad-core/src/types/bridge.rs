@@ -0,0 +1,123 @@
+use ethers::types::{H256, U256};
+use std::io::{Error, ErrorKind};
+
+use crate::{Decode, Encode, NomadError};
+
+const TRANSFER: u8 = 3;
+const FAST_TRANSFER: u8 = 4;
+
+/// Length of an encoded token id
+const TOKEN_ID_LENGTH: usize = 36;
+/// Length of an encoded transfer action
+const TRANSFER_LENGTH: usize = 97;
+
+/// A token, by the domain it originates on and its id there
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TokenId {
+    /// 4   Domain of the token's origin chain
+    pub domain: u32,
+    /// 32  Token address in its origin chain's convention
+    pub id: H256,
+}
+
+/// A transfer of tokens to a recipient
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Transfer {
+    /// 32  Recipient in destination convention
+    pub recipient: H256,
+    /// 32  Amount of tokens, in the token's smallest unit
+    pub amount: U256,
+    /// 32  Hash of the token's name, symbol and decimals
+    pub details_hash: H256,
+}
+
+/// The action of a bridge message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BridgeAction {
+    /// A transfer released once its message is processed
+    Transfer(Transfer),
+    /// A transfer a liquidity provider may front before its message is
+    /// processed
+    FastTransfer(Transfer),
+}
+
+impl BridgeAction {
+    /// The transfer of the action
+    pub fn transfer(&self) -> &Transfer {
+        match self {
+            Self::Transfer(transfer) | Self::FastTransfer(transfer) => transfer,
+        }
+    }
+}
+
+/// The body of a message between bridge routers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BridgeMessage {
+    /// The token the action moves
+    pub token: TokenId,
+    /// The action
+    pub action: BridgeAction,
+}
+
+impl Encode for BridgeMessage {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let (action_type, transfer) = match &self.action {
+            BridgeAction::Transfer(transfer) => (TRANSFER, transfer),
+            BridgeAction::FastTransfer(transfer) => (FAST_TRANSFER, transfer),
+        };
+        let mut amount = [0u8; 32];
+        transfer.amount.to_big_endian(&mut amount);
+
+        writer.write_all(&self.token.domain.to_be_bytes())?;
+        writer.write_all(self.token.id.as_ref())?;
+        writer.write_all(&[action_type])?;
+        writer.write_all(transfer.recipient.as_ref())?;
+        writer.write_all(&amount)?;
+        writer.write_all(transfer.details_hash.as_ref())?;
+        Ok(TOKEN_ID_LENGTH + TRANSFER_LENGTH)
+    }
+}
+
+impl Decode for BridgeMessage {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+    {
+        let mut token = [0u8; TOKEN_ID_LENGTH];
+        reader.read_exact(&mut token)?;
+
+        let mut action = vec![];
+        reader.read_to_end(&mut action)?;
+        let (action_type, rest) = action
+            .split_first()
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "bridge message has no action"))?;
+        if !matches!(*action_type, TRANSFER | FAST_TRANSFER) {
+            return Err(NomadError::UnknownBridgeAction(*action_type));
+        }
+        if action.len() != TRANSFER_LENGTH {
+            return Err(
+                Error::new(ErrorKind::InvalidData, "transfer action must be 97 bytes").into(),
+            );
+        }
+
+        let transfer = Transfer {
+            recipient: H256::from_slice(&rest[..32]),
+            amount: U256::from_big_endian(&rest[32..64]),
+            details_hash: H256::from_slice(&rest[64..]),
+        };
+        Ok(Self {
+            token: TokenId {
+                domain: u32::from_be_bytes(token[..4].try_into().expect("checked length")),
+                id: H256::from_slice(&token[4..]),
+            },
+            action: if *action_type == TRANSFER {
+                BridgeAction::Transfer(transfer)
+            } else {
+                BridgeAction::FastTransfer(transfer)
+            },
+        })
+    }
+}
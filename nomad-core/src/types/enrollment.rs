@@ -0,0 +1,52 @@
+use ethers::types::H256;
+use serde::{Deserialize, Serialize};
+
+use crate::{Decode, Encode, NomadError};
+
+/// A `ReplicaEnrolled` or `ReplicaUnenrolled` event emitted by an
+/// XAppConnectionManager, with the block it was emitted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicaEnrollment {
+    /// Remote domain the replica is (un)enrolled for
+    pub domain: u32,
+    /// Replica address
+    pub replica: H256,
+    /// Whether the replica was enrolled or unenrolled
+    pub enrolled: bool,
+    /// Block number
+    pub block_number: u64,
+    /// Block timestamp seconds (optional because fetching timestamp is
+    /// fallible)
+    pub timestamp: Option<u64>,
+}
+
+impl Encode for ReplicaEnrollment {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = self.domain.write_to(writer)?;
+        written += self.replica.write_to(writer)?;
+        written += self.enrolled.write_to(writer)?;
+        written += self.block_number.write_to(writer)?;
+        written += self.timestamp.unwrap_or_default().write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for ReplicaEnrollment {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        Ok(Self {
+            domain: u32::read_from(reader)?,
+            replica: H256::read_from(reader)?,
+            enrolled: bool::read_from(reader)?,
+            block_number: u64::read_from(reader)?,
+            timestamp: Some(u64::read_from(reader)?).filter(|timestamp| *timestamp != 0),
+        })
+    }
+}
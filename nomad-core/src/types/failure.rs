@@ -1,9 +1,8 @@
-use crate::{utils::home_domain_hash, NomadError, SignerExt};
-use ethers::{
-    prelude::{Address, Signature},
-    types::H256,
-    utils::hash_message,
+use crate::{
+    utils::{abbreviated_hex, home_domain_hash},
+    CanonicalSignature, NomadError, SignerExt,
 };
+use ethers::{prelude::Address, types::H256, utils::hash_message};
 use ethers_signers::Signer;
 use nomad_types::NomadIdentifier;
 use sha3::{Digest, Keccak256};
@@ -18,7 +17,9 @@ pub struct FailureNotification {
 }
 
 impl FailureNotification {
-    fn signing_hash(&self) -> H256 {
+    /// Digest the watcher signs, `keccak256(homeDomainHash, homeDomain,
+    /// updater)`. `unenrollReplica` rebuilds it from its arguments.
+    pub fn signing_hash(&self) -> H256 {
         H256::from_slice(
             Keccak256::new()
                 .chain(home_domain_hash(self.home_domain))
@@ -29,7 +30,9 @@ impl FailureNotification {
         )
     }
 
-    fn prepended_hash(&self) -> H256 {
+    /// Digest with the Ethereum signed message prefix, which the signature
+    /// recovers against
+    pub fn prepended_hash(&self) -> H256 {
         hash_message(self.signing_hash())
     }
 
@@ -48,13 +51,26 @@ impl FailureNotification {
     }
 }
 
-/// Signed failure notification produced by watcher
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Signed failure notification produced by watcher. `Debug` abbreviates the
+/// signature.
+#[derive(Clone, Copy, PartialEq)]
 pub struct SignedFailureNotification {
     /// Failure notification
     pub notification: FailureNotification,
     /// Signature
-    pub signature: Signature,
+    pub signature: CanonicalSignature,
+}
+
+impl std::fmt::Debug for SignedFailureNotification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignedFailureNotification")
+            .field("notification", &self.notification)
+            .field(
+                "signature",
+                &format_args!("{}", abbreviated_hex(&self.signature.to_vec())),
+            )
+            .finish()
+    }
 }
 
 impl SignedFailureNotification {
@@ -0,0 +1,61 @@
+use ethers::types::H256;
+use std::collections::HashMap;
+
+use crate::{DoubleUpdate, SignedUpdate};
+
+/// Detects double updates among the signed updates of one home: two updates
+/// off the same previous root to different new roots. A detected pair is
+/// ready for `Home::double_update`.
+///
+/// Signatures are not checked. Record only updates verified against the
+/// home's updater, as the contract rejects a pair not signed by it.
+#[derive(Debug, Default, Clone)]
+pub struct FraudDetector {
+    /// First update recorded off each previous root
+    by_previous_root: HashMap<H256, SignedUpdate>,
+}
+
+impl FraudDetector {
+    /// A detector with no updates recorded
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The double update `first` and `second` form, if they build off the
+    /// same previous root to different new roots. Repeats of an update,
+    /// with the same or another signature, are not fraud.
+    pub fn conflict(first: &SignedUpdate, second: &SignedUpdate) -> Option<DoubleUpdate> {
+        let same_previous_root = first.update.previous_root == second.update.previous_root;
+        let different_new_root = first.update.new_root != second.update.new_root;
+        if same_previous_root && different_new_root {
+            Some(DoubleUpdate(first.clone(), second.clone()))
+        } else {
+            None
+        }
+    }
+
+    /// Record `update`. Returns the double update it forms with the first
+    /// update recorded off its previous root, if any. Only the first update
+    /// off each root is kept, so every later conflicting update is reported
+    /// against it.
+    pub fn record(&mut self, update: SignedUpdate) -> Option<DoubleUpdate> {
+        match self.by_previous_root.get(&update.update.previous_root) {
+            Some(first) => Self::conflict(first, &update),
+            None => {
+                self.by_previous_root
+                    .insert(update.update.previous_root, update);
+                None
+            }
+        }
+    }
+
+    /// Number of previous roots with a recorded update
+    pub fn len(&self) -> usize {
+        self.by_previous_root.len()
+    }
+
+    /// Whether no update was recorded
+    pub fn is_empty(&self) -> bool {
+        self.by_previous_root.is_empty()
+    }
+}
@@ -0,0 +1,149 @@
+use ethers::{types::H256, utils::keccak256};
+use std::io::{Error, ErrorKind};
+
+use crate::{Decode, Encode, NomadError};
+
+const BATCH: u8 = 1;
+const TRANSFER_GOVERNOR: u8 = 2;
+
+/// A call in a governance batch
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GovernanceCall {
+    /// 32  Target address in destination convention
+    pub to: H256,
+    /// 0+  Calldata
+    pub data: Vec<u8>,
+}
+
+/// The body of a message between governance routers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GovernanceMessage {
+    /// A batch of calls carried in the message
+    Batch(Vec<GovernanceCall>),
+    /// A batch committed to by hash. The calls are supplied when the batch is
+    /// executed on the destination.
+    BatchHash(H256),
+    /// Transfer governorship to `governor` on `domain`
+    TransferGovernor {
+        /// Domain of the new governor
+        domain: u32,
+        /// New governor
+        governor: H256,
+    },
+}
+
+/// Serialize calls as the governance router does: a call count, then each
+/// call's target, data length and data
+pub fn serialize_calls(calls: &[GovernanceCall]) -> Vec<u8> {
+    let mut buf = vec![calls.len() as u8];
+    for call in calls {
+        buf.extend_from_slice(call.to.as_ref());
+        buf.extend_from_slice(&(call.data.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&call.data);
+    }
+    buf
+}
+
+/// Hash committing to a batch of calls
+pub fn batch_hash(calls: &[GovernanceCall]) -> H256 {
+    keccak256(serialize_calls(calls)).into()
+}
+
+fn read_calls(mut buf: &[u8]) -> Result<Vec<GovernanceCall>, NomadError> {
+    let truncated = || Error::new(ErrorKind::UnexpectedEof, "truncated governance batch");
+
+    let (count, rest) = buf.split_first().ok_or_else(truncated)?;
+    buf = rest;
+
+    let mut calls = Vec::with_capacity(*count as usize);
+    for _ in 0..*count {
+        if buf.len() < 36 {
+            return Err(truncated().into());
+        }
+        let to = H256::from_slice(&buf[..32]);
+        let len = u32::from_be_bytes(buf[32..36].try_into().expect("checked length")) as usize;
+        buf = &buf[36..];
+        if buf.len() < len {
+            return Err(truncated().into());
+        }
+        calls.push(GovernanceCall {
+            to,
+            data: buf[..len].to_vec(),
+        });
+        buf = &buf[len..];
+    }
+
+    if !buf.is_empty() {
+        return Err(
+            Error::new(ErrorKind::InvalidData, "trailing bytes in governance batch").into(),
+        );
+    }
+    Ok(calls)
+}
+
+impl GovernanceMessage {
+    /// Hash of the batch, for either batch form
+    pub fn batch_hash(&self) -> Option<H256> {
+        match self {
+            Self::Batch(calls) => Some(batch_hash(calls)),
+            Self::BatchHash(hash) => Some(*hash),
+            Self::TransferGovernor { .. } => None,
+        }
+    }
+}
+
+impl Encode for GovernanceMessage {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        match self {
+            Self::Batch(calls) => {
+                let serialized = serialize_calls(calls);
+                writer.write_all(&[BATCH])?;
+                writer.write_all(&serialized)?;
+                Ok(1 + serialized.len())
+            }
+            Self::BatchHash(hash) => {
+                writer.write_all(&[BATCH])?;
+                writer.write_all(hash.as_ref())?;
+                Ok(33)
+            }
+            Self::TransferGovernor { domain, governor } => {
+                writer.write_all(&[TRANSFER_GOVERNOR])?;
+                writer.write_all(&domain.to_be_bytes())?;
+                writer.write_all(governor.as_ref())?;
+                Ok(37)
+            }
+        }
+    }
+}
+
+impl Decode for GovernanceMessage {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+    {
+        let mut message_type = [0u8; 1];
+        reader.read_exact(&mut message_type)?;
+
+        let mut rest = vec![];
+        reader.read_to_end(&mut rest)?;
+
+        match message_type[0] {
+            // An inline batch is never 32 bytes: one call alone takes 37
+            BATCH if rest.len() == 32 => Ok(Self::BatchHash(H256::from_slice(&rest))),
+            BATCH => Ok(Self::Batch(read_calls(&rest)?)),
+            TRANSFER_GOVERNOR if rest.len() == 36 => Ok(Self::TransferGovernor {
+                domain: u32::from_be_bytes(rest[..4].try_into().expect("checked length")),
+                governor: H256::from_slice(&rest[4..]),
+            }),
+            TRANSFER_GOVERNOR => Err(Error::new(
+                ErrorKind::InvalidData,
+                "transfer governor message must be 37 bytes",
+            )
+            .into()),
+            other => Err(NomadError::UnknownGovernanceMessage(other)),
+        }
+    }
+}
@@ -5,7 +5,7 @@ use crate::{utils, Decode, Encode, NomadError};
 const NOMAD_MESSAGE_PREFIX_LEN: usize = 76;
 
 /// A full Nomad message between chains
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct NomadMessage {
     /// 4   SLIP-44 ID
     pub origin: u32,
@@ -17,7 +17,7 @@ pub struct NomadMessage {
     pub destination: u32,
     /// 32  Address in destination convention
     pub recipient: H256,
-    /// 0+  Message contents
+    /// 0+  Message contents. Zero-length bodies are valid
     pub body: Vec<u8>,
 }
 
@@ -82,7 +82,17 @@ impl Decode for NomadMessage {
 }
 
 impl NomadMessage {
-    /// Convert the message to a leaf
+    /// Parse a message from its raw encoding. The 76 byte header is required
+    /// and everything after it is the body, which may be empty.
+    pub fn from_raw(raw: &[u8]) -> Result<Self, NomadError> {
+        if raw.len() < NOMAD_MESSAGE_PREFIX_LEN {
+            return Err(NomadError::MessageTooShort(raw.len()));
+        }
+        Self::read_from(&mut &raw[..])
+    }
+
+    /// Convert the message to a leaf. Matches `keccak256(abi.encodePacked(..))`
+    /// in `Message.sol`, including for empty bodies.
     pub fn to_leaf(&self) -> H256 {
         keccak256(self.to_vec()).into()
     }
@@ -102,3 +112,52 @@ impl std::fmt::Display for NomadMessage {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn with_body(body: Vec<u8>) -> NomadMessage {
+        NomadMessage {
+            origin: 1000,
+            sender: H256::repeat_byte(1),
+            nonce: 7,
+            destination: 2000,
+            recipient: H256::repeat_byte(2),
+            body,
+        }
+    }
+
+    #[test]
+    fn it_roundtrips_messages() {
+        for body in [
+            vec![],
+            vec![0],
+            vec![0xff; 31],
+            vec![0xab; 32],
+            (0..=255).collect(),
+            vec![0; 4096],
+        ] {
+            let message = with_body(body);
+            let raw = message.to_vec();
+            assert_eq!(raw.len(), NOMAD_MESSAGE_PREFIX_LEN + message.body.len());
+            assert_eq!(NomadMessage::from_raw(&raw).unwrap(), message);
+            assert_eq!(
+                NomadMessage::read_from(&mut raw.as_slice()).unwrap(),
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn it_rejects_raw_messages_shorter_than_the_header() {
+        let raw = with_body(vec![]).to_vec();
+        for len in 0..NOMAD_MESSAGE_PREFIX_LEN {
+            assert!(matches!(
+                NomadMessage::from_raw(&raw[..len]),
+                Err(NomadError::MessageTooShort(short)) if short == len
+            ));
+        }
+        assert!(NomadMessage::from_raw(&raw).is_ok());
+    }
+}
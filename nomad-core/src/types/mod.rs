@@ -1,7 +1,23 @@
+mod bridge;
+mod enrollment;
 mod failure;
+mod fraud_detector;
+mod governance;
 mod messages;
+mod root_advance;
+mod signature;
+mod submission;
 mod update;
+mod update_chain;
 
+pub use bridge::*;
+pub use enrollment::*;
 pub use failure::*;
+pub use fraud_detector::*;
+pub use governance::*;
 pub use messages::*;
+pub use root_advance::*;
+pub use signature::*;
+pub use submission::*;
 pub use update::*;
+pub use update_chain::*;
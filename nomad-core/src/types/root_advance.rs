@@ -0,0 +1,59 @@
+use ethers::types::H256;
+use serde::{Deserialize, Serialize};
+
+use crate::{Decode, Encode, NomadError, RootProvenance};
+
+/// A root a replica came to accept on chain: through the `Update` moving its
+/// committed root to it, or a governance `setConfirmation` confirming it
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootAdvance {
+    /// The accepted root
+    pub root: H256,
+    /// How the replica came to accept the root
+    pub provenance: RootProvenance,
+    /// Block of the event
+    pub block_number: u64,
+    /// Timestamp seconds of the block (optional because fetching timestamp
+    /// is fallible)
+    pub timestamp: Option<u64>,
+    /// Transaction that emitted the event
+    pub tx_hash: H256,
+}
+
+impl Encode for RootAdvance {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = 0;
+        written += self.root.write_to(writer)?;
+        written += self.provenance.write_to(writer)?;
+        written += self.block_number.write_to(writer)?;
+        written += self.timestamp.unwrap_or_default().write_to(writer)?;
+        written += self.tx_hash.write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for RootAdvance {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let root = H256::read_from(reader)?;
+        let provenance = RootProvenance::read_from(reader)?;
+        let block_number = u64::read_from(reader)?;
+        let timestamp = u64::read_from(reader)?;
+        let tx_hash = H256::read_from(reader)?;
+
+        Ok(Self {
+            root,
+            provenance,
+            block_number,
+            timestamp: (timestamp != 0).then(|| timestamp),
+            tx_hash,
+        })
+    }
+}
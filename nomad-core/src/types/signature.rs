@@ -0,0 +1,173 @@
+use std::{convert::TryFrom, fmt::Display, ops::Deref, str::FromStr};
+
+use ethers::prelude::{Signature, SignatureError, U256};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Decode, Encode, NomadError};
+
+/// Length of an encoded signature: r || s || v
+pub const SIGNATURE_LENGTH: usize = 65;
+
+/// Length of an EIP-2098 compact signature: r || yParity and s, with the
+/// recovery bit in the top bit of s
+pub const COMPACT_SIGNATURE_LENGTH: usize = 64;
+
+/// A secp256k1 signature with v normalized to 27 or 28, as the contracts
+/// require. Signatures arrive with v in {0, 1, 27, 28}, as bytes or as hex
+/// strings with or without a leading `0x`, or as EIP-2098 compact bytes.
+/// Every form of the same signature converts to the same
+/// `CanonicalSignature`, so signatures compare and encode equal regardless of
+/// their source.
+///
+/// Derefs to the underlying [`Signature`] for recovery and verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanonicalSignature(Signature);
+
+impl CanonicalSignature {
+    /// The canonical v for a recovery byte, if it is one of 0, 1, 27 or 28
+    pub fn canonical_v(v: u64) -> Option<u64> {
+        match v {
+            0 | 1 => Some(v + 27),
+            27 | 28 => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Whether `bytes` is a signature already in canonical form
+    pub fn is_canonical(bytes: &[u8]) -> bool {
+        bytes.len() == SIGNATURE_LENGTH && matches!(bytes[64], 27 | 28)
+    }
+
+    /// Expand an EIP-2098 compact signature
+    pub fn from_compact(bytes: &[u8; COMPACT_SIGNATURE_LENGTH]) -> Self {
+        let mut y_parity_and_s = [0u8; 32];
+        y_parity_and_s.copy_from_slice(&bytes[32..]);
+        let y_parity = y_parity_and_s[0] >> 7;
+        y_parity_and_s[0] &= 0x7f;
+        Self(Signature {
+            r: U256::from_big_endian(&bytes[..32]),
+            s: U256::from_big_endian(&y_parity_and_s),
+            v: 27 + u64::from(y_parity),
+        })
+    }
+
+    /// The signature in its EIP-2098 compact form
+    pub fn to_compact(&self) -> [u8; COMPACT_SIGNATURE_LENGTH] {
+        let mut bytes = [0u8; COMPACT_SIGNATURE_LENGTH];
+        self.0.r.to_big_endian(&mut bytes[..32]);
+        self.0.s.to_big_endian(&mut bytes[32..]);
+        if self.0.v == 28 {
+            bytes[32] |= 0x80;
+        }
+        bytes
+    }
+
+    /// The signature as its 65 canonical bytes
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_LENGTH] {
+        let mut bytes = [0u8; SIGNATURE_LENGTH];
+        bytes.copy_from_slice(&self.0.to_vec());
+        bytes
+    }
+}
+
+impl Deref for CanonicalSignature {
+    type Target = Signature;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<CanonicalSignature> for Signature {
+    fn from(signature: CanonicalSignature) -> Self {
+        signature.0
+    }
+}
+
+impl TryFrom<Signature> for CanonicalSignature {
+    type Error = NomadError;
+
+    fn try_from(mut signature: Signature) -> Result<Self, Self::Error> {
+        signature.v =
+            Self::canonical_v(signature.v).ok_or(NomadError::InvalidSignatureV(signature.v))?;
+        Ok(Self(signature))
+    }
+}
+
+impl TryFrom<&[u8]> for CanonicalSignature {
+    type Error = NomadError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        match bytes.len() {
+            SIGNATURE_LENGTH => Self::try_from(Signature::try_from(bytes)?),
+            COMPACT_SIGNATURE_LENGTH => {
+                let mut compact = [0u8; COMPACT_SIGNATURE_LENGTH];
+                compact.copy_from_slice(bytes);
+                Ok(Self::from_compact(&compact))
+            }
+            len => Err(SignatureError::InvalidLength(len).into()),
+        }
+    }
+}
+
+impl FromStr for CanonicalSignature {
+    type Err = NomadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // `Signature` parses hex with or without a leading `0x`
+        Self::try_from(s.trim().parse::<Signature>()?)
+    }
+}
+
+impl Display for CanonicalSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x{}", hex::encode(self.to_bytes()))
+    }
+}
+
+impl Encode for CanonicalSignature {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        writer.write_all(&self.to_bytes())?;
+        Ok(SIGNATURE_LENGTH)
+    }
+}
+
+impl Decode for CanonicalSignature {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+    {
+        let mut bytes = [0u8; SIGNATURE_LENGTH];
+        reader.read_exact(&mut bytes)?;
+        Self::try_from(bytes.as_ref())
+    }
+}
+
+/// Serializes as the underlying [`Signature`], so existing JSON consumers see
+/// no change
+impl Serialize for CanonicalSignature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+/// Deserializes from a [`Signature`] or from a hex string, normalizing either
+impl<'de> Deserialize<'de> for CanonicalSignature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Hex(String),
+            Parts(Signature),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Hex(hex) => hex.parse(),
+            Repr::Parts(signature) => Self::try_from(signature),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
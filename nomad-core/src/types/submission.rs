@@ -0,0 +1,168 @@
+use ethers::core::types::{Bytes, H160, U256};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{cell::RefCell, future::Future, io::ErrorKind};
+
+use crate::{evm_address_serde, Decode, Encode, NomadError};
+
+/// Bytes of calldata kept in a payload. Longer calldata is truncated.
+pub const MAX_STORED_CALLDATA: usize = 16 * 1024;
+/// Characters of simulation output kept in a payload
+pub const MAX_STORED_SIMULATION: usize = 1024;
+
+tokio::task_local! {
+    static SUBMISSION: RefCell<Option<TxPayload>>;
+}
+
+/// Run `submission`, capturing the payload of the transaction it sends, if
+/// its submitter records one. Concurrent submissions in one task are
+/// captured separately.
+pub async fn capture_submission<F: Future>(submission: F) -> (F::Output, Option<TxPayload>) {
+    SUBMISSION
+        .scope(RefCell::new(None), async move {
+            let output = submission.await;
+            let payload = SUBMISSION.with(|payload| payload.borrow_mut().take());
+            (output, payload)
+        })
+        .await
+}
+
+/// Whether the current submission is captured. Submitters skip building a
+/// payload otherwise.
+pub fn capturing_submission() -> bool {
+    SUBMISSION.try_with(|_| ()).is_ok()
+}
+
+/// Record `payload` for the submission being captured. Does nothing outside
+/// [`capture_submission`].
+pub fn record_submission(payload: TxPayload) {
+    let _ = SUBMISSION.try_with(|captured| *captured.borrow_mut() = Some(payload));
+}
+
+/// Calldata, deflated and truncated to [`MAX_STORED_CALLDATA`] bytes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredCalldata {
+    /// Length of the full calldata
+    pub len: usize,
+    /// Deflated calldata
+    pub deflated: Bytes,
+    /// Whether the calldata was longer than what is kept
+    pub truncated: bool,
+}
+
+impl StoredCalldata {
+    /// Deflate `data`, truncated to [`MAX_STORED_CALLDATA`] bytes
+    pub fn new(data: &[u8]) -> Self {
+        let kept = &data[..data.len().min(MAX_STORED_CALLDATA)];
+        Self {
+            len: data.len(),
+            deflated: miniz_oxide::deflate::compress_to_vec(kept, 6).into(),
+            truncated: kept.len() < data.len(),
+        }
+    }
+
+    /// The kept calldata, or `None` if it does not inflate
+    pub fn inflate(&self) -> Option<Vec<u8>> {
+        miniz_oxide::inflate::decompress_to_vec_with_limit(&self.deflated, MAX_STORED_CALLDATA).ok()
+    }
+}
+
+/// Gas price decided on for a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeDecision {
+    /// Gas price the network quoted
+    pub network_gas_price: U256,
+    /// Gas price the fee policy set on the transaction
+    pub gas_price: Option<U256>,
+}
+
+/// Transaction request as submitted, without its signature, for post-mortems
+/// of submissions. Bounded in size: calldata is deflated and truncated, and
+/// simulation output is capped.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxPayload {
+    /// Recipient
+    #[serde(default, with = "evm_address_serde::option")]
+    pub to: Option<H160>,
+    /// Sender
+    #[serde(default, with = "evm_address_serde::option")]
+    pub from: Option<H160>,
+    /// Nonce
+    pub nonce: Option<U256>,
+    /// Gas limit
+    pub gas: Option<U256>,
+    /// Gas price of legacy transactions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_price: Option<U256>,
+    /// Fee cap of EIP-1559 transactions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<U256>,
+    /// Priority fee cap of EIP-1559 transactions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// Value sent
+    pub value: Option<U256>,
+    /// Chain id
+    pub chain_id: Option<u64>,
+    /// Calldata
+    pub calldata: StoredCalldata,
+    /// Result of simulating the transaction before it was sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simulation: Option<String>,
+    /// Gas price decision of the fee policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FeeDecision>,
+}
+
+impl TxPayload {
+    /// Set the simulation result, capped to [`MAX_STORED_SIMULATION`]
+    /// characters
+    pub fn with_simulation(mut self, simulation: &str) -> Self {
+        let mut capped: String = simulation.chars().take(MAX_STORED_SIMULATION).collect();
+        if capped.len() < simulation.len() {
+            capped.push_str("...");
+        }
+        self.simulation = Some(capped);
+        self
+    }
+
+    /// The payload for reports, with its calldata inflated
+    pub fn report(&self) -> Value {
+        let mut report = json!(self);
+        report["calldata"] = json!({
+            "len": self.calldata.len,
+            "data": self.calldata.inflate().map(Bytes::from),
+            "truncated": self.calldata.truncated,
+        });
+        report
+    }
+}
+
+impl Encode for TxPayload {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        writer.write_all(&json)?;
+        Ok(json.len())
+    }
+}
+
+impl Decode for TxPayload {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let mut json = vec![];
+        reader.read_to_end(&mut json)?;
+        serde_json::from_slice(&json).map_err(|e| {
+            NomadError::IoError(std::io::Error::new(ErrorKind::InvalidData, e.to_string()))
+        })
+    }
+}
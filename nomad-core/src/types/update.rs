@@ -1,15 +1,35 @@
-use std::fmt::Display;
+use std::{convert::TryFrom, fmt::Display};
 
-use crate::{utils::home_domain_hash, Decode, Encode, NomadError, SignerExt};
-use ethers::{
-    prelude::{Address, Signature},
-    types::H256,
-    utils::hash_message,
+use crate::{
+    utils::{abbreviated_hex, home_domain_hash},
+    CanonicalSignature, Decode, Encode, NomadError, SignerExt,
 };
+use ethers::{prelude::Address, types::H256, utils::hash_message};
 use ethers_signers::Signer;
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 
+/// The committed root of a home before its first update
+pub const GENESIS_ROOT: H256 = H256([0; 32]);
+
+/// Whether `root` is a genesis root: a committed root no update produced on
+/// the contract, where its history of updates begins.
+///
+/// - A home starts at [`GENESIS_ROOT`]. Its first update builds on it.
+/// - A replica starts at the root it was initialized with: the home's
+///   committed root when the replica was deployed. That is [`GENESIS_ROOT`]
+///   only for a replica deployed before the home's first update. The
+///   replica's first update builds on its initialization root, which the
+///   home reached through an earlier update.
+///
+/// Walks back through a contract's updates end at a genesis root, and an
+/// update building on it is never missing a predecessor.
+/// `initialization_root` is the root a replica was initialized with, if
+/// known, and `None` for homes.
+pub fn is_genesis_root(root: H256, initialization_root: Option<H256>) -> bool {
+    root == GENESIS_ROOT || Some(root) == initialization_root
+}
+
 /// An Nomad update message
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Update {
@@ -80,7 +100,9 @@ impl Update {
         )
     }
 
-    fn prepended_hash(&self) -> H256 {
+    /// Digest with the Ethereum signed message prefix, which the signature
+    /// recovers against
+    pub fn prepended_hash(&self) -> H256 {
         hash_message(self.signing_hash())
     }
 
@@ -157,24 +179,94 @@ pub struct SignedUpdateWithMeta {
     pub metadata: UpdateMeta,
 }
 
-/// A Signed Nomad Update
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+/// An `Update` event as emitted by a home or replica, with the signature
+/// still in its raw emitted form. Chain crates convert their generated event
+/// types into this, and only this parses and normalizes the signature.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct UpdateEvent {
+    /// The home chain
+    pub home_domain: u32,
+    /// The previous root
+    pub old_root: H256,
+    /// The new root
+    pub new_root: H256,
+    /// The emitted signature bytes
+    pub signature: Vec<u8>,
+}
+
+impl std::fmt::Debug for UpdateEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpdateEvent")
+            .field("home_domain", &self.home_domain)
+            .field("old_root", &self.old_root)
+            .field("new_root", &self.new_root)
+            .field(
+                "signature",
+                &format_args!("{}", abbreviated_hex(&self.signature)),
+            )
+            .finish()
+    }
+}
+
+impl UpdateEvent {
+    /// Attach block metadata to the event
+    pub fn with_meta(self, metadata: UpdateMeta) -> Result<SignedUpdateWithMeta, NomadError> {
+        Ok(SignedUpdateWithMeta {
+            signed_update: SignedUpdate::try_from(self)?,
+            metadata,
+        })
+    }
+}
+
+impl TryFrom<UpdateEvent> for SignedUpdate {
+    type Error = NomadError;
+
+    fn try_from(event: UpdateEvent) -> Result<Self, Self::Error> {
+        Ok(Self {
+            update: Update {
+                home_domain: event.home_domain,
+                previous_root: event.old_root,
+                new_root: event.new_root,
+            },
+            signature: CanonicalSignature::try_from(event.signature.as_ref())?,
+        })
+    }
+}
+
+/// A Signed Nomad Update. `Debug` and `Display` abbreviate the signature.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct SignedUpdate {
     /// The update
     pub update: Update,
     /// The signature
-    pub signature: Signature,
+    pub signature: CanonicalSignature,
 }
 
 impl Display for SignedUpdate {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "SignedUpdate {{ ")?;
         write!(f, "{:?} ", self.update)?;
-        write!(f, "Signature: 0x{} ", hex::encode(&self.signature.to_vec()))?;
+        write!(
+            f,
+            "Signature: {} ",
+            abbreviated_hex(&self.signature.to_vec())
+        )?;
         write!(f, " }}")
     }
 }
 
+impl std::fmt::Debug for SignedUpdate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignedUpdate")
+            .field("update", &self.update)
+            .field(
+                "signature",
+                &format_args!("{}", abbreviated_hex(&self.signature.to_vec())),
+            )
+            .finish()
+    }
+}
+
 impl Encode for SignedUpdate {
     fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
     where
@@ -194,7 +286,7 @@ impl Decode for SignedUpdate {
         Self: Sized,
     {
         let update = Update::read_from(reader)?;
-        let signature = Signature::read_from(reader)?;
+        let signature = CanonicalSignature::read_from(reader)?;
         Ok(Self { update, signature })
     }
 }
@@ -0,0 +1,205 @@
+use ethers::{prelude::Address, types::H256};
+
+use crate::{Decode, Encode, NomadError, SignedUpdate};
+
+/// A chain of signed updates, each building off the previous one's new root.
+/// Only constructed through `verify`, so it is never empty.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UpdateChain {
+    updater: Address,
+    intermediate: Vec<SignedUpdate>,
+    latest: SignedUpdate,
+}
+
+impl UpdateChain {
+    /// Verify that `updates` form an unbroken chain starting at `start` and
+    /// that each update is signed by `updater`
+    pub fn verify(
+        start: H256,
+        mut updates: Vec<SignedUpdate>,
+        updater: Address,
+    ) -> Result<Self, NomadError> {
+        let mut expected = start;
+        for (index, signed) in updates.iter().enumerate() {
+            if signed.update.previous_root != expected {
+                return Err(NomadError::BrokenUpdateChain {
+                    index,
+                    expected,
+                    actual: signed.update.previous_root,
+                });
+            }
+            signed.verify(updater)?;
+            expected = signed.update.new_root;
+        }
+
+        let latest = updates.pop().ok_or(NomadError::EmptyUpdateChain)?;
+        Ok(Self {
+            updater,
+            intermediate: updates,
+            latest,
+        })
+    }
+
+    /// The updater every update of the chain is signed by
+    pub fn updater(&self) -> Address {
+        self.updater
+    }
+
+    /// The root the chain builds off of
+    pub fn start(&self) -> H256 {
+        self.intermediate
+            .first()
+            .unwrap_or(&self.latest)
+            .update
+            .previous_root
+    }
+
+    /// The latest update of the chain
+    pub fn latest(&self) -> &SignedUpdate {
+        &self.latest
+    }
+
+    /// The updates preceding the latest one
+    pub fn intermediate(&self) -> &[SignedUpdate] {
+        &self.intermediate
+    }
+
+    /// All updates of the chain, in order
+    pub fn updates(&self) -> impl Iterator<Item = &SignedUpdate> {
+        self.intermediate
+            .iter()
+            .chain(std::iter::once(&self.latest))
+    }
+}
+
+impl Encode for UpdateChain {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        writer.write_all(self.updater.as_ref())?;
+        let mut written = 20;
+        written += (self.intermediate.len() as u32 + 1).write_to(writer)?;
+        for update in self.updates() {
+            written += update.write_to(writer)?;
+        }
+        Ok(written)
+    }
+}
+
+/// Decoding re-verifies the chain against its updater, so a record that was
+/// tampered with or truncated at rest is rejected
+impl Decode for UpdateChain {
+    fn read_from<R>(reader: &mut R) -> Result<Self, NomadError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let mut updater = Address::zero();
+        reader.read_exact(updater.as_mut())?;
+        let len = u32::read_from(reader)?;
+        let updates: Vec<SignedUpdate> = (0..len)
+            .map(|_| SignedUpdate::read_from(reader))
+            .collect::<Result<_, _>>()?;
+        let start = updates
+            .first()
+            .map(|signed| signed.update.previous_root)
+            .unwrap_or_default();
+        Self::verify(start, updates, updater)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Update;
+    use ethers::signers::{LocalWallet, Signer};
+
+    async fn signed_chain(signer: &LocalWallet, len: u8) -> Vec<SignedUpdate> {
+        let mut updates = vec![];
+        for i in 0..len {
+            updates.push(
+                Update {
+                    home_domain: 1,
+                    previous_root: H256::repeat_byte(i),
+                    new_root: H256::repeat_byte(i + 1),
+                }
+                .sign_with(signer)
+                .await
+                .unwrap(),
+            );
+        }
+        updates
+    }
+
+    fn wallet(key: &str) -> LocalWallet {
+        key.repeat(64).parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_rejects_empty_and_broken_chains() {
+        let signer = wallet("1");
+        let updates = signed_chain(&signer, 3).await;
+
+        assert!(matches!(
+            UpdateChain::verify(H256::zero(), vec![], signer.address()),
+            Err(NomadError::EmptyUpdateChain)
+        ));
+
+        let mut gapped = updates.clone();
+        gapped.remove(1);
+        assert!(matches!(
+            UpdateChain::verify(H256::zero(), gapped, signer.address()),
+            Err(NomadError::BrokenUpdateChain { index: 1, .. })
+        ));
+
+        let chain = UpdateChain::verify(H256::zero(), updates.clone(), signer.address()).unwrap();
+        assert_eq!(chain.start(), H256::zero());
+        assert_eq!(chain.latest(), &updates[2]);
+        assert_eq!(chain.intermediate(), &updates[..2]);
+        assert_eq!(chain.updates().cloned().collect::<Vec<_>>(), updates);
+    }
+
+    #[tokio::test]
+    async fn it_verifies_chains_on_decode() {
+        let signer = wallet("1");
+        let chain = UpdateChain::verify(
+            H256::zero(),
+            signed_chain(&signer, 3).await,
+            signer.address(),
+        )
+        .unwrap();
+
+        let encoded = chain.to_vec();
+        assert_eq!(
+            UpdateChain::read_from(&mut encoded.as_slice()).unwrap(),
+            chain
+        );
+
+        // An empty chain
+        let mut empty = chain.updater().as_bytes().to_vec();
+        empty.extend(0u32.to_vec());
+        assert!(matches!(
+            UpdateChain::read_from(&mut empty.as_slice()),
+            Err(NomadError::EmptyUpdateChain)
+        ));
+
+        // A chain recorded for another updater
+        let mut foreign = encoded.clone();
+        foreign[..20].copy_from_slice(wallet("2").address().as_bytes());
+        assert!(UpdateChain::read_from(&mut foreign.as_slice()).is_err());
+
+        // A chain with an update dropped
+        let mut updates = chain.updates().cloned().collect::<Vec<_>>();
+        updates.remove(1);
+        let mut gapped = chain.updater().as_bytes().to_vec();
+        gapped.extend(2u32.to_vec());
+        for update in updates.iter() {
+            gapped.extend(update.to_vec());
+        }
+        assert!(matches!(
+            UpdateChain::read_from(&mut gapped.as_slice()),
+            Err(NomadError::BrokenUpdateChain { index: 1, .. })
+        ));
+    }
+}
@@ -1,5 +1,7 @@
-use ethers::core::types::H256;
+use ethers::core::types::{H256, U256};
+use rand::Rng;
 use sha3::{Digest, Keccak256};
+use std::time::Duration;
 
 /// Computes hash of home domain concatenated with "NOMAD"
 pub fn home_domain_hash(home_domain: u32) -> H256 {
@@ -12,6 +14,31 @@ pub fn home_domain_hash(home_domain: u32) -> H256 {
     )
 }
 
+/// Convert a contract or chain value to `u64`, saturating at `u64::MAX`.
+/// Timestamps and durations read from chain are converted through this
+/// rather than `U256::as_u64`, which panics on values a misconfigured
+/// contract can hold.
+pub fn saturating_u64(value: U256) -> u64 {
+    if value > U256::from(u64::MAX) {
+        u64::MAX
+    } else {
+        value.low_u64()
+    }
+}
+
+/// Delay before retry number `retry`, counted from 1, doubling from `base`
+/// up to `max`. With `jitter`, the delay is drawn uniformly from its upper
+/// half, so retries failing together spread out.
+pub fn exponential_backoff(base: Duration, max: Duration, retry: u32, jitter: bool) -> Duration {
+    let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+    let delay = base.saturating_mul(factor).min(max);
+    if !jitter {
+        return delay;
+    }
+    let nanos = u64::try_from(delay.as_nanos()).unwrap_or(u64::MAX);
+    Duration::from_nanos(rand::thread_rng().gen_range(nanos / 2..=nanos))
+}
+
 /// Destination and destination-specific nonce combined in single field (
 /// (destination << 32) & nonce)
 pub fn destination_and_nonce(destination: u32, nonce: u32) -> u64 {
@@ -19,3 +46,18 @@ pub fn destination_and_nonce(destination: u32, nonce: u32) -> u64 {
     assert!(nonce < u32::MAX);
     ((destination as u64) << 32) | nonce as u64
 }
+
+/// Abbreviate bytes for logging to their first and last 4 bytes and their
+/// length, e.g. `0x12345678..9abcdef0 (65 bytes)`. Used for signatures and
+/// transaction payloads, which must not appear in full in logs.
+pub fn abbreviated_hex(bytes: &[u8]) -> String {
+    if bytes.len() <= 8 {
+        return format!("0x{}", hex::encode(bytes));
+    }
+    format!(
+        "0x{}..{} ({} bytes)",
+        hex::encode(&bytes[..4]),
+        hex::encode(&bytes[bytes.len() - 4..]),
+        bytes.len()
+    )
+}
@@ -13,3 +13,6 @@ pub use mocks::MockError;
 
 /// Testing utilities
 pub mod test_utils;
+
+/// Scans of captured logs for leaked keys and signatures
+pub mod log_scan;
@@ -0,0 +1,82 @@
+use ethers::types::H256;
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+use tracing::subscriber::DefaultGuard;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Hex runs at least this long are keys or signatures unless allowlisted
+pub const MIN_SECRET_HEX_LEN: usize = 64;
+
+/// Logs captured from the current thread
+#[derive(Debug, Clone, Default)]
+pub struct LogCapture(Arc<Mutex<Vec<u8>>>);
+
+impl Write for LogCapture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().expect("poisoned").extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogCapture {
+    type Writer = LogCapture;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+impl LogCapture {
+    /// Capture every log emitted on this thread, at all levels and with span
+    /// fields, until the guard is dropped
+    pub fn start() -> (Self, DefaultGuard) {
+        let capture = Self::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer(capture.clone())
+            .finish();
+        (capture, tracing::subscriber::set_default(subscriber))
+    }
+
+    /// Captured logs
+    pub fn logs(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().expect("poisoned")).into_owned()
+    }
+
+    /// Hex runs of at least [`MIN_SECRET_HEX_LEN`] characters that are not
+    /// one of the `allowed` roots or hashes
+    pub fn violations(&self, allowed: &[H256]) -> Vec<String> {
+        let allowed: Vec<String> = allowed.iter().map(|h| hex::encode(h.as_bytes())).collect();
+        hex_runs(&self.logs())
+            .into_iter()
+            .filter(|run| run.len() >= MIN_SECRET_HEX_LEN)
+            .filter(|run| !allowed.contains(&run.to_lowercase()))
+            .collect()
+    }
+
+    /// Fail if the captured logs contain a key or signature
+    pub fn assert_no_secrets(&self, allowed: &[H256]) {
+        let violations = self.violations(allowed);
+        assert!(
+            violations.is_empty(),
+            "logs contain unredacted hex: {:?}\n{}",
+            violations,
+            self.logs()
+        );
+    }
+}
+
+/// Maximal runs of hex digits in `text`
+fn hex_runs(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_ascii_hexdigit())
+        .filter(|run| !run.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
@@ -6,6 +6,7 @@ use mockall::*;
 
 use ethers::core::types::{H256, U256};
 
+use crate::test_utils::yield_point;
 use nomad_core::*;
 
 mock! {
@@ -36,10 +37,18 @@ mock! {
 
         pub fn _dispatch(&self, message: &Message) -> Result<TxOutcome, MockError> {}
 
+        pub fn _dispatch_tracked(&self, message: &Message) -> Result<DispatchOutcome, MockError> {}
+
         pub fn _queue_length(&self) -> Result<U256, MockError> {}
 
         pub fn _queue_contains(&self, root: H256) -> Result<bool, MockError> {}
 
+        pub fn _queue_end(&self) -> Result<H256, MockError> {}
+
+        pub fn _counted_root(&self) -> Result<(H256, u32), MockError> {}
+
+        pub fn _tree_counts(&self) -> Result<(u32, u32), MockError> {}
+
         pub fn _improper_update(
             &self,
             update: &SignedUpdate,
@@ -47,6 +56,8 @@ mock! {
 
         pub fn _produce_update(&self) -> Result<Option<Update>, MockError> {}
 
+        pub fn _produce_pending_update(&self) -> Result<Option<Update>, MockError> {}
+
         // Common
         pub fn _name(&self) -> &str {}
 
@@ -90,31 +101,65 @@ impl Home for MockHomeContract {
     }
 
     async fn nonces(&self, destination: u32) -> Result<u32, <Self as Common>::Error> {
+        yield_point().await;
         self._nonces(destination)
     }
 
     async fn dispatch(&self, message: &Message) -> Result<TxOutcome, <Self as Common>::Error> {
+        yield_point().await;
         self._dispatch(message)
     }
 
+    async fn dispatch_tracked(
+        &self,
+        message: &Message,
+    ) -> Result<DispatchOutcome, <Self as Common>::Error> {
+        yield_point().await;
+        self._dispatch_tracked(message)
+    }
+
     async fn queue_length(&self) -> Result<U256, <Self as Common>::Error> {
+        yield_point().await;
         self._queue_length()
     }
 
     async fn queue_contains(&self, root: H256) -> Result<bool, <Self as Common>::Error> {
+        yield_point().await;
         self._queue_contains(root)
     }
 
+    async fn queue_end(&self) -> Result<H256, <Self as Common>::Error> {
+        yield_point().await;
+        self._queue_end()
+    }
+
+    async fn counted_root(&self) -> Result<(H256, u32), <Self as Common>::Error> {
+        yield_point().await;
+        self._counted_root()
+    }
+
+    async fn tree_counts(&self) -> Result<(u32, u32), <Self as Common>::Error> {
+        yield_point().await;
+        self._tree_counts()
+    }
+
     async fn improper_update(
         &self,
         update: &SignedUpdate,
     ) -> Result<TxOutcome, <Self as Common>::Error> {
+        yield_point().await;
         self._improper_update(update)
     }
 
     async fn produce_update(&self) -> Result<Option<Update>, <Self as Common>::Error> {
+        yield_point().await;
         self._produce_update()
     }
+
+    async fn produce_pending_update(&self) -> Result<Option<Update>, <Self as Common>::Error> {
+        yield_point().await;
+        self._produce_pending_update()
+    }
 }
 
 #[async_trait]
@@ -126,26 +171,32 @@ impl Common for MockHomeContract {
     }
 
     async fn status(&self, txid: H256) -> Result<Option<TxOutcome>, Self::Error> {
+        yield_point().await;
         self._status(txid)
     }
 
     async fn updater(&self) -> Result<H256, Self::Error> {
+        yield_point().await;
         self._updater()
     }
 
     async fn state(&self) -> Result<State, Self::Error> {
+        yield_point().await;
         self._state()
     }
 
     async fn committed_root(&self) -> Result<H256, Self::Error> {
+        yield_point().await;
         self._committed_root()
     }
 
     async fn update(&self, update: &SignedUpdate) -> Result<TxOutcome, Self::Error> {
+        yield_point().await;
         self._update(update)
     }
 
     async fn double_update(&self, double: &DoubleUpdate) -> Result<TxOutcome, Self::Error> {
+        yield_point().await;
         self._double_update(double)
     }
 }
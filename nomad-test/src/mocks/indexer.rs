@@ -5,12 +5,15 @@ use async_trait::async_trait;
 use color_eyre::Result;
 use mockall::*;
 
+use crate::test_utils::yield_point;
 use nomad_core::*;
 
 mock! {
     pub Indexer {
         pub fn _get_block_number(&self) -> Result<u32, MockError> {}
 
+        pub fn _get_latest_block_timestamp(&self) -> Result<Option<u64>, MockError> {}
+
         pub fn _fetch_sorted_updates(&self, from: u32, to: u32) -> Result<Vec<SignedUpdateWithMeta>, MockError> {}
 
         pub fn _fetch_sorted_messages(&self, from: u32, to: u32) -> Result<Vec<RawCommittedMessage>, MockError> {}
@@ -28,14 +31,21 @@ impl CommonIndexer for MockIndexer {
     type Error = MockError;
 
     async fn get_block_number(&self) -> Result<u32, Self::Error> {
+        yield_point().await;
         self._get_block_number()
     }
 
+    async fn get_latest_block_timestamp(&self) -> Result<Option<u64>, Self::Error> {
+        yield_point().await;
+        self._get_latest_block_timestamp()
+    }
+
     async fn fetch_sorted_updates(
         &self,
         from: u32,
         to: u32,
     ) -> Result<Vec<SignedUpdateWithMeta>, Self::Error> {
+        yield_point().await;
         self._fetch_sorted_updates(from, to)
     }
 }
@@ -47,6 +57,7 @@ impl HomeIndexer for MockIndexer {
         from: u32,
         to: u32,
     ) -> Result<Vec<RawCommittedMessage>, <Self as CommonIndexer>::Error> {
+        yield_point().await;
         self._fetch_sorted_messages(from, to)
     }
 }
@@ -15,6 +15,24 @@ pub use indexer::MockIndexer;
 pub use replica::MockReplicaContract;
 pub use xapp::MockConnectionManagerContract;
 
+/// Error returned by mocks
 #[derive(Debug, thiserror::Error)]
-#[error("Mock error")]
-pub struct MockError;
+pub enum MockError {
+    /// Any failure
+    #[error("Mock error")]
+    Failed,
+    /// A submitted transaction reverted
+    #[error("Mock transaction was not executed successfully {0:?}")]
+    TxNotExecuted(ethers::prelude::H256),
+    /// A transaction needed more gas than the cap
+    #[error("Mock gas limit {limit} exceeds the cap of {cap}")]
+    GasLimitExceeded {
+        /// Gas limit from the estimate
+        limit: u64,
+        /// Configured cap
+        cap: u64,
+    },
+    /// A classified JSON-RPC error
+    #[error("Mock RPC error")]
+    Rpc(#[source] nomad_ethereum::ClassifiedRpcError),
+}
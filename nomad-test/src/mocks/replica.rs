@@ -5,6 +5,7 @@ use mockall::*;
 
 use ethers::core::types::H256;
 
+use crate::test_utils::yield_point;
 use nomad_core::{accumulator::NomadProof, *};
 
 use super::MockError;
@@ -45,7 +46,17 @@ mock! {
 
         pub fn _message_status(&self, leaf: H256) -> Result<MessageStatus, MockError> {}
 
+        pub fn _process_success(
+            &self,
+            leaf: H256,
+            block: Option<u64>,
+        ) -> Result<Option<bool>, MockError> {}
+
         pub fn _acceptable_root(&self, root: H256) -> Result<bool, MockError> {}
+
+        pub fn _confirm_at(&self, root: H256) -> Result<u64, MockError> {}
+
+        pub fn _root_provenance(&self, root: H256) -> Result<Option<RootProvenance>, MockError> {}
     }
 }
 
@@ -68,14 +79,17 @@ impl Replica for MockReplicaContract {
     }
 
     async fn remote_domain(&self) -> Result<u32, <Self as Common>::Error> {
+        yield_point().await;
         self._remote_domain()
     }
 
     async fn prove(&self, proof: &NomadProof) -> Result<TxOutcome, <Self as Common>::Error> {
+        yield_point().await;
         self._prove(proof)
     }
 
     async fn process(&self, message: &NomadMessage) -> Result<TxOutcome, <Self as Common>::Error> {
+        yield_point().await;
         self._process(message)
     }
 
@@ -84,16 +98,41 @@ impl Replica for MockReplicaContract {
         message: &NomadMessage,
         proof: &NomadProof,
     ) -> Result<TxOutcome, <Self as Common>::Error> {
+        yield_point().await;
         self._prove_and_process(message, proof)
     }
 
     async fn message_status(&self, leaf: H256) -> Result<MessageStatus, <Self as Common>::Error> {
+        yield_point().await;
         self._message_status(leaf)
     }
 
+    async fn process_success(
+        &self,
+        leaf: H256,
+        block: Option<u64>,
+    ) -> Result<Option<bool>, <Self as Common>::Error> {
+        yield_point().await;
+        self._process_success(leaf, block)
+    }
+
     async fn acceptable_root(&self, root: H256) -> Result<bool, <Self as Common>::Error> {
+        yield_point().await;
         self._acceptable_root(root)
     }
+
+    async fn confirm_at(&self, root: H256) -> Result<u64, <Self as Common>::Error> {
+        yield_point().await;
+        self._confirm_at(root)
+    }
+
+    async fn root_provenance(
+        &self,
+        root: H256,
+    ) -> Result<Option<RootProvenance>, <Self as Common>::Error> {
+        yield_point().await;
+        self._root_provenance(root)
+    }
 }
 
 #[async_trait]
@@ -105,26 +144,32 @@ impl Common for MockReplicaContract {
     }
 
     async fn status(&self, txid: H256) -> Result<Option<TxOutcome>, Self::Error> {
+        yield_point().await;
         self._status(txid)
     }
 
     async fn updater(&self) -> Result<H256, Self::Error> {
+        yield_point().await;
         self._updater()
     }
 
     async fn state(&self) -> Result<State, Self::Error> {
+        yield_point().await;
         self._state()
     }
 
     async fn committed_root(&self) -> Result<H256, Self::Error> {
+        yield_point().await;
         self._committed_root()
     }
 
     async fn update(&self, update: &SignedUpdate) -> Result<TxOutcome, Self::Error> {
+        yield_point().await;
         self._update(update)
     }
 
     async fn double_update(&self, double: &DoubleUpdate) -> Result<TxOutcome, Self::Error> {
+        yield_point().await;
         self._double_update(double)
     }
 }
@@ -13,6 +13,16 @@ mock! {
 
         pub fn _is_replica(&self, address: NomadIdentifier) -> Result<bool, MockError> {}
 
+        pub fn _replica_to_domain(&self, address: NomadIdentifier) -> Result<u32, MockError> {}
+
+        pub fn _get_block_number(&self) -> Result<u32, MockError> {}
+
+        pub fn _fetch_replica_enrollments(
+            &self,
+            from: u32,
+            to: u32,
+        ) -> Result<Vec<ReplicaEnrollment>, MockError> {}
+
         pub fn _watcher_permission(
             &self,
             address: NomadIdentifier,
@@ -64,6 +74,22 @@ impl ConnectionManager for MockConnectionManagerContract {
         self._is_replica(address)
     }
 
+    async fn replica_to_domain(&self, address: NomadIdentifier) -> Result<u32, Self::Error> {
+        self._replica_to_domain(address)
+    }
+
+    async fn get_block_number(&self) -> Result<u32, Self::Error> {
+        self._get_block_number()
+    }
+
+    async fn fetch_replica_enrollments(
+        &self,
+        from: u32,
+        to: u32,
+    ) -> Result<Vec<ReplicaEnrollment>, Self::Error> {
+        self._fetch_replica_enrollments(from, to)
+    }
+
     async fn watcher_permission(
         &self,
         address: NomadIdentifier,
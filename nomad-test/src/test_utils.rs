@@ -1,10 +1,16 @@
-use futures_util::FutureExt;
+use futures_util::{task::noop_waker_ref, FutureExt};
 use mockito;
 use nomad_core::db::DB;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use std::path::Path;
-use std::{env, future::Future, panic};
+use std::{
+    env,
+    future::Future,
+    panic,
+    pin::Pin,
+    task::{Context, Poll},
+};
 
 use rocksdb::Options;
 
@@ -69,6 +75,53 @@ where
     assert!(result.is_ok())
 }
 
+/// Future that is pending exactly once. Mock contracts and indexers await
+/// one at the start of every async call, so each call is a point at which
+/// the calling task can be cancelled.
+pub fn yield_point() -> impl Future<Output = ()> {
+    YieldPoint(false)
+}
+
+struct YieldPoint(bool);
+
+impl Future for YieldPoint {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Cancel futures made by `make` at each of their await points in turn: the
+/// first is dropped before it is polled, the next after one poll, and so on
+/// until one completes. `check` is called after each cancellation with the
+/// number of polls, and should assert the invariants that must survive a
+/// cancellation. Returns the number of polls the completing future took.
+pub fn cancel_at_each_await<M, Fut, C>(mut make: M, mut check: C) -> usize
+where
+    M: FnMut() -> Fut,
+    Fut: Future,
+    C: FnMut(usize),
+{
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for polls in 0.. {
+        let mut fut = Box::pin(make());
+        for _ in 0..polls {
+            if fut.as_mut().poll(&mut cx).is_ready() {
+                return polls;
+            }
+        }
+        drop(fut);
+        check(polls);
+    }
+    unreachable!()
+}
+
 /// Run test with a mock http server response
 pub async fn run_test_with_http_response<T, Fut>(response_body: impl AsRef<[u8]>, test: T)
 where
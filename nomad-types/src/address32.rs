@@ -0,0 +1,224 @@
+//! 32-byte message recipients and senders. EVM addresses are stored
+//! left-padded with 12 zero bytes, as `TypeCasts.addressToBytes32` does
+//! on-chain.
+
+use ethers::prelude::{H160, H256};
+use std::{fmt, str::FromStr};
+
+use crate::{format_h256, parse_h256, ChecksumAddress, NomadTypeError};
+
+/// A 32-byte address as used for message recipients and senders
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Address32(pub H256);
+
+impl Address32 {
+    /// Canonical 32-byte form of an EVM address: 12 zero bytes followed by
+    /// the address
+    pub fn from_evm(address: H160) -> Self {
+        let mut bytes = [0u8; 32];
+        bytes[12..].copy_from_slice(address.as_bytes());
+        Self(bytes.into())
+    }
+
+    /// The EVM address, if the first 12 bytes are zero
+    pub fn to_evm(&self) -> Option<H160> {
+        let bytes = self.0.as_bytes();
+        bytes[..12]
+            .iter()
+            .all(|b| *b == 0)
+            .then(|| H160::from_slice(&bytes[12..]))
+    }
+
+    /// Whether this looks like an EVM address padded on the wrong side: the
+    /// last 12 bytes are zero and the first 12 are not. Such a recipient is
+    /// not the address it was meant to be.
+    pub fn looks_right_padded(&self) -> bool {
+        let bytes = self.0.as_bytes();
+        bytes[20..].iter().all(|b| *b == 0) && bytes[..12].iter().any(|b| *b != 0)
+    }
+
+    /// Form of the address for logs and reports. `Display` gives the
+    /// canonical 32-byte form, which serialization relies on.
+    pub fn readable(&self) -> ReadableAddress32 {
+        ReadableAddress32(*self)
+    }
+
+    /// The EVM address intended by a right-padded value
+    pub fn right_padded_evm(&self) -> Option<H160> {
+        self.looks_right_padded()
+            .then(|| H160::from_slice(&self.0.as_bytes()[..20]))
+    }
+}
+
+/// Form of an `Address32` for logs and reports: a left-padded EVM address
+/// shows as the checksummed address, noted as padded, and anything else in
+/// canonical 32-byte form
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadableAddress32(pub Address32);
+
+impl fmt::Display for ReadableAddress32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.to_evm() {
+            Some(address) => write!(f, "{} (padded EVM address)", ChecksumAddress(address)),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl fmt::Display for Address32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_h256(&self.0))
+    }
+}
+
+/// Parses a 32-byte value, or a 20-byte EVM address which is left-padded
+impl FromStr for Address32 {
+    type Err = NomadTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let digits = trimmed
+            .strip_prefix("0x")
+            .or_else(|| trimmed.strip_prefix("0X"))
+            .unwrap_or(trimmed);
+
+        if digits.len() == 40 {
+            let mut bytes = [0u8; 20];
+            hex::decode_to_slice(digits, &mut bytes)
+                .map_err(|_| NomadTypeError::InvalidAddress32(s.to_owned()))?;
+            return Ok(Self::from_evm(bytes.into()));
+        }
+        parse_h256(s)
+            .map(Self)
+            .map_err(|_| NomadTypeError::InvalidAddress32(s.to_owned()))
+    }
+}
+
+impl serde::Serialize for Address32 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Address32 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl From<H160> for Address32 {
+    fn from(address: H160) -> Self {
+        Self::from_evm(address)
+    }
+}
+
+impl From<H256> for Address32 {
+    fn from(value: H256) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Address32> for H256 {
+    fn from(address: Address32) -> Self {
+        address.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    /// Ethereum BridgeRouter proxy, and the recipient of messages to it as
+    /// dispatched by the remote BridgeRouters
+    const BRIDGE_ROUTER: &str = "0x88A69B4E698A4B090DF6CF5Bd7B2D47325Ad30A3";
+    const BRIDGE_ROUTER_32: &str =
+        "0x00000000000000000000000088a69b4e698a4b090df6cf5bd7b2d47325ad30a3";
+
+    #[test]
+    fn it_left_pads_evm_addresses() {
+        let address: H160 = BRIDGE_ROUTER.parse().unwrap();
+        let padded = Address32::from_evm(address);
+
+        assert_eq!(padded.to_string(), BRIDGE_ROUTER_32);
+        assert_eq!(padded.0, parse_h256(BRIDGE_ROUTER_32).unwrap());
+        // Same as ethers' own conversion
+        assert_eq!(padded.0, H256::from(address));
+        assert_eq!(padded.to_evm(), Some(address));
+        assert!(!padded.looks_right_padded());
+    }
+
+    #[test]
+    fn it_parses_either_form() {
+        let address: H160 = BRIDGE_ROUTER.parse().unwrap();
+        let expected = Address32::from_evm(address);
+
+        assert_eq!(BRIDGE_ROUTER.parse::<Address32>().unwrap(), expected);
+        assert_eq!(BRIDGE_ROUTER_32.parse::<Address32>().unwrap(), expected);
+        assert_eq!(
+            BRIDGE_ROUTER_32
+                .trim_start_matches("0x")
+                .to_uppercase()
+                .parse::<Address32>()
+                .unwrap(),
+            expected
+        );
+        assert!("0x1234".parse::<Address32>().is_err());
+        assert!(format!("{}zz", &BRIDGE_ROUTER[..40])
+            .parse::<Address32>()
+            .is_err());
+
+        let json = serde_json::to_value(expected).unwrap();
+        assert_eq!(json, json!(BRIDGE_ROUTER_32));
+        assert_eq!(
+            serde_json::from_value::<Address32>(json!(BRIDGE_ROUTER)).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn it_detects_wrong_side_padding() {
+        let address: H160 = BRIDGE_ROUTER.parse().unwrap();
+        let right_padded: Address32 = format!("{}{}", BRIDGE_ROUTER, "0".repeat(24))
+            .parse()
+            .unwrap();
+
+        assert!(right_padded.looks_right_padded());
+        assert_eq!(right_padded.right_padded_evm(), Some(address));
+        assert_eq!(right_padded.to_evm(), None);
+
+        // Full 32-byte values are neither
+        let full = Address32(H256::repeat_byte(0xab));
+        assert!(!full.looks_right_padded());
+        assert_eq!(full.to_evm(), None);
+        assert!(!Address32::default().looks_right_padded());
+    }
+
+    #[test]
+    fn it_shows_padded_evm_addresses_checksummed() {
+        let padded: Address32 = BRIDGE_ROUTER_32.parse().unwrap();
+        assert_eq!(
+            padded.readable().to_string(),
+            format!("{} (padded EVM address)", BRIDGE_ROUTER)
+        );
+        // the canonical form is unchanged
+        assert_eq!(padded.to_string(), BRIDGE_ROUTER_32);
+
+        let full = Address32(H256::repeat_byte(0xab));
+        assert_eq!(full.readable().to_string(), format_h256(&full.0));
+        let right_padded: Address32 = format!("{}{}", BRIDGE_ROUTER, "0".repeat(24))
+            .parse()
+            .unwrap();
+        assert_eq!(
+            right_padded.readable().to_string(),
+            right_padded.to_string()
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Canonical text form of 32-byte values (roots, message hashes and 32-byte
+//! addresses): lowercase, `0x`-prefixed, 64 hex characters.
+
+use ethers::prelude::H256;
+use std::fmt;
+
+use crate::NomadTypeError;
+
+/// Format a 32-byte value in canonical form
+pub fn format_h256(value: &H256) -> String {
+    format!("{:#x}", value)
+}
+
+/// Parse a 32-byte value. Accepts the canonical form as well as unprefixed
+/// and mixed or upper case hex.
+pub fn parse_h256(s: &str) -> Result<H256, NomadTypeError> {
+    let trimmed = s.trim();
+    let digits = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed);
+
+    if digits.len() != 64 {
+        return Err(NomadTypeError::InvalidH256(s.to_owned()));
+    }
+
+    let mut bytes = [0u8; 32];
+    hex::decode_to_slice(digits, &mut bytes)
+        .map_err(|_| NomadTypeError::InvalidH256(s.to_owned()))?;
+    Ok(bytes.into())
+}
+
+/// Displays a 32-byte value in canonical form. `H256`'s own `Display`
+/// abbreviates the value, so use this for anything user-visible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanonicalH256(pub H256);
+
+impl fmt::Display for CanonicalH256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
+impl From<H256> for CanonicalH256 {
+    fn from(value: H256) -> Self {
+        Self(value)
+    }
+}
+
+/// Serde adapter writing 32-byte values in canonical form and reading any
+/// form accepted by [`parse_h256`]. Use with `#[serde(with = "...")]`.
+pub mod h256_serde {
+    use ethers::prelude::H256;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    /// Serialize in canonical form
+    pub fn serialize<S>(value: &H256, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&super::format_h256(value))
+    }
+
+    /// Deserialize any accepted form
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<H256, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        super::parse_h256(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    const CANONICAL: &str = "0xabcdef0000000000000000000000000000000000000000000000000000000001";
+
+    #[test]
+    fn it_formats_canonically() {
+        let value = parse_h256(CANONICAL).unwrap();
+
+        assert_eq!(format_h256(&value), CANONICAL);
+        assert_eq!(CanonicalH256(value).to_string(), CANONICAL);
+        // `Display` for `H256` is abbreviated and must not be used
+        assert_ne!(value.to_string(), CANONICAL);
+    }
+
+    #[test]
+    fn it_parses_all_variants() {
+        let expected = parse_h256(CANONICAL).unwrap();
+        let unprefixed = CANONICAL.trim_start_matches("0x");
+
+        for variant in [
+            unprefixed.to_owned(),
+            unprefixed.to_uppercase(),
+            format!("0x{}", unprefixed.to_uppercase()),
+            format!("0X{}", unprefixed),
+            "0xAbCdEf0000000000000000000000000000000000000000000000000000000001".to_owned(),
+        ] {
+            assert_eq!(parse_h256(&variant).unwrap(), expected, "{}", variant);
+        }
+
+        assert!(parse_h256("0x1234").is_err());
+        assert!(parse_h256(&format!("{}zz", &CANONICAL[..64])).is_err());
+    }
+
+    #[test]
+    fn it_serdes_canonically() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Wrapper(#[serde(with = "h256_serde")] H256);
+
+        let value = parse_h256(CANONICAL).unwrap();
+        assert_eq!(
+            serde_json::to_value(Wrapper(value)).unwrap(),
+            json!(CANONICAL)
+        );
+
+        let upper = json!(CANONICAL.trim_start_matches("0x").to_uppercase());
+        let Wrapper(parsed) = serde_json::from_value(upper).unwrap();
+        assert_eq!(parsed, value);
+
+        // Default serde for `H256` is also canonical
+        assert_eq!(serde_json::to_value(value).unwrap(), json!(CANONICAL));
+    }
+}
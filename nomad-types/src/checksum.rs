@@ -0,0 +1,196 @@
+//! EIP-55 checksummed text form of EVM addresses, for logs, `/status`, CLI
+//! output and reports, so addresses compare at a glance against block
+//! explorers. Parsing stays case-insensitive: the checksum is not enforced.
+
+use ethers::{prelude::H160, utils::to_checksum};
+use std::fmt;
+
+use crate::NomadTypeError;
+
+/// Format an EVM address with its EIP-55 checksum
+pub fn format_evm_address(address: &H160) -> String {
+    to_checksum(address, None)
+}
+
+/// Parse a 20-byte EVM address. Accepts checksummed, lowercase and
+/// uppercase hex, with or without `0x`.
+pub fn parse_evm_address(s: &str) -> Result<H160, NomadTypeError> {
+    let trimmed = s.trim();
+    let digits = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed);
+
+    if digits.len() != 40 {
+        return Err(NomadTypeError::InvalidEvmAddress(s.to_owned()));
+    }
+
+    let mut bytes = [0u8; 20];
+    hex::decode_to_slice(digits, &mut bytes)
+        .map_err(|_| NomadTypeError::InvalidEvmAddress(s.to_owned()))?;
+    Ok(bytes.into())
+}
+
+/// Displays an EVM address with its EIP-55 checksum. `H160`'s own `Debug`
+/// is lowercase and its `Display` abbreviates, so use this for anything
+/// user-visible. `Debug` is the same as `Display`, for `?` fields in logs.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ChecksumAddress(pub H160);
+
+impl fmt::Display for ChecksumAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_evm_address(&self.0))
+    }
+}
+
+impl fmt::Debug for ChecksumAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl From<H160> for ChecksumAddress {
+    fn from(address: H160) -> Self {
+        Self(address)
+    }
+}
+
+/// Serde adapter writing EVM addresses checksummed and reading any form
+/// accepted by [`parse_evm_address`]. Use with `#[serde(with = "...")]`.
+pub mod evm_address_serde {
+    use ethers::prelude::H160;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    /// Serialize checksummed
+    pub fn serialize<S>(address: &H160, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&super::format_evm_address(address))
+    }
+
+    /// Deserialize any case
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<H160, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        super::parse_evm_address(&s).map_err(serde::de::Error::custom)
+    }
+
+    /// The same, for optional addresses
+    pub mod option {
+        use ethers::prelude::H160;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        /// Serialize checksummed
+        pub fn serialize<S>(address: &Option<H160>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match address {
+                Some(address) => super::serialize(address, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        /// Deserialize any case
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<H160>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Option::<String>::deserialize(deserializer)?
+                .map(|s| super::super::parse_evm_address(&s).map_err(serde::de::Error::custom))
+                .transpose()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    /// Test vectors of EIP-55: all caps, all lowercase and mixed case
+    const EIP55_VECTORS: [&str; 8] = [
+        "0x52908400098527886E0F7030069857D2E4169EE7",
+        "0x8617E340B3D01FA5F11F306F4090FD50E238070D",
+        "0xde709f2102306220921060314715629080e2fb77",
+        "0x27b1fdb04752bbc536007a920d24acb045561c26",
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn it_checksums_the_eip55_vectors() {
+        for vector in EIP55_VECTORS {
+            let lower = parse_evm_address(&vector.to_lowercase()).unwrap();
+            assert_eq!(format_evm_address(&lower), vector);
+            assert_eq!(ChecksumAddress(lower).to_string(), vector);
+            assert_eq!(format!("{:?}", ChecksumAddress(lower)), vector);
+        }
+    }
+
+    #[test]
+    fn it_parses_any_case() {
+        for vector in EIP55_VECTORS {
+            let expected = parse_evm_address(vector).unwrap();
+            let digits = &vector[2..];
+            for input in [
+                vector.to_lowercase(),
+                digits.to_uppercase(),
+                format!("0X{}", digits),
+                format!(" {}\n", vector),
+            ] {
+                assert_eq!(parse_evm_address(&input).unwrap(), expected, "{}", input);
+            }
+        }
+
+        for invalid in [
+            "",
+            "0x",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAe",
+            "0xzz",
+        ] {
+            assert!(matches!(
+                parse_evm_address(invalid),
+                Err(NomadTypeError::InvalidEvmAddress(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn it_sers_checksummed_and_desers_any_case() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Report {
+            #[serde(with = "evm_address_serde")]
+            address: H160,
+            #[serde(with = "evm_address_serde::option")]
+            implementation: Option<H160>,
+        }
+
+        let report = Report {
+            address: parse_evm_address(EIP55_VECTORS[4]).unwrap(),
+            implementation: None,
+        };
+        let value = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            value,
+            json!({ "address": EIP55_VECTORS[4], "implementation": null })
+        );
+
+        let lower = json!({
+            "address": EIP55_VECTORS[4].to_lowercase(),
+            "implementation": EIP55_VECTORS[5].to_lowercase(),
+        });
+        let parsed: Report = serde_json::from_value(lower).unwrap();
+        assert_eq!(parsed.address, report.address);
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap()["implementation"],
+            json!(EIP55_VECTORS[5])
+        );
+    }
+}
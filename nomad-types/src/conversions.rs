@@ -0,0 +1,169 @@
+//! Checked conversions at the boundary between ethers integers and the
+//! integers of our domain types. Contracts and providers report leaf
+//! indexes, leaf counts and block numbers as `U256` and `U64`, while the
+//! agents keep them as `u32`.
+//!
+//! `U256::as_u32` panics on overflow and `low_u32` and `as` casts truncate
+//! silently. Convert through this module instead, so a value out of range
+//! is an error.
+
+#![deny(clippy::cast_possible_truncation)]
+
+use ethers::core::types::U256;
+use std::{any::type_name, fmt};
+
+use crate::NomadTypeError;
+
+/// Convert `value` to `T`, erroring if it does not fit
+pub fn checked_cast<T, V>(value: V) -> Result<T, NomadTypeError>
+where
+    V: Copy + fmt::Display,
+    T: TryFrom<V>,
+{
+    T::try_from(value).map_err(|_| NomadTypeError::IntegerOverflow {
+        value: value.to_string(),
+        target: type_name::<T>(),
+    })
+}
+
+/// Checked narrowing of `U256`, `U64` and primitive integers to the widths
+/// of domain types
+pub trait CheckedCast: Copy + fmt::Display {
+    /// Convert to `u32`, erroring if the value does not fit
+    fn try_u32(self) -> Result<u32, NomadTypeError>;
+
+    /// Convert to `u64`, erroring if the value does not fit
+    fn try_u64(self) -> Result<u64, NomadTypeError>;
+
+    /// Convert to `usize`, erroring if the value does not fit
+    fn try_usize(self) -> Result<usize, NomadTypeError>;
+
+    /// Convert an unsigned value to the `i64` of a prometheus gauge,
+    /// saturating at `i64::MAX`
+    fn saturating_i64(self) -> i64 {
+        self.try_u64()
+            .ok()
+            .and_then(|value| i64::try_from(value).ok())
+            .unwrap_or(i64::MAX)
+    }
+}
+
+impl<V> CheckedCast for V
+where
+    V: Copy + fmt::Display,
+    u32: TryFrom<V>,
+    u64: TryFrom<V>,
+    usize: TryFrom<V>,
+{
+    fn try_u32(self) -> Result<u32, NomadTypeError> {
+        checked_cast(self)
+    }
+
+    fn try_u64(self) -> Result<u64, NomadTypeError> {
+        checked_cast(self)
+    }
+
+    fn try_usize(self) -> Result<usize, NomadTypeError> {
+        checked_cast(self)
+    }
+}
+
+/// Index of a leaf in a home's message tree, or a count of its leaves.
+/// Contracts report both as `U256`. The tree holds at most `2^32 - 1`
+/// leaves, so either fits a `u32`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LeafIndex(u32);
+
+impl fmt::Display for LeafIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<u32> for LeafIndex {
+    fn from(index: u32) -> Self {
+        Self(index)
+    }
+}
+
+impl From<LeafIndex> for u32 {
+    fn from(index: LeafIndex) -> Self {
+        index.0
+    }
+}
+
+impl From<LeafIndex> for U256 {
+    fn from(index: LeafIndex) -> Self {
+        index.0.into()
+    }
+}
+
+impl TryFrom<U256> for LeafIndex {
+    type Error = NomadTypeError;
+
+    fn try_from(value: U256) -> Result<Self, Self::Error> {
+        value.try_u32().map(Self)
+    }
+}
+
+impl TryFrom<u64> for LeafIndex {
+    type Error = NomadTypeError;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        value.try_u32().map(Self)
+    }
+}
+
+impl TryFrom<usize> for LeafIndex {
+    type Error = NomadTypeError;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        value.try_u32().map(Self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::core::types::U64;
+
+    #[test]
+    fn it_converts_values_in_range() {
+        assert_eq!(U256::from(u32::MAX).try_u32().unwrap(), u32::MAX);
+        assert_eq!(U64::from(17u64).try_u32().unwrap(), 17);
+        assert_eq!(U256::from(u64::MAX).try_u64().unwrap(), u64::MAX);
+        assert_eq!(U256::from(96u64).try_usize().unwrap(), 96);
+
+        let index = LeafIndex::try_from(U256::from(u32::MAX)).unwrap();
+        assert_eq!(u32::from(index), u32::MAX);
+        assert_eq!(U256::from(index), U256::from(u32::MAX));
+        assert_eq!(LeafIndex::try_from(5usize).unwrap(), LeafIndex::from(5));
+        assert_eq!(U256::from(96u64).saturating_i64(), 96);
+    }
+
+    #[test]
+    fn it_errors_instead_of_truncating() {
+        let over = u64::from(u32::MAX) + 1;
+
+        // `low_u32` would read these as 0
+        assert!(U256::from(over).try_u32().is_err());
+        assert!(U64::from(over).try_u32().is_err());
+        assert!(over.try_u32().is_err());
+        assert!(LeafIndex::try_from(U256::from(over)).is_err());
+        assert!(LeafIndex::try_from(over).is_err());
+        assert!((U256::from(u64::MAX) + 1).try_u64().is_err());
+        assert!(U256::MAX.try_usize().is_err());
+
+        // Gauges saturate rather than wrap negative
+        assert_eq!(U256::from(u64::MAX).saturating_i64(), i64::MAX);
+        assert_eq!(U256::MAX.saturating_i64(), i64::MAX);
+
+        match LeafIndex::try_from(U256::from(over) + 7) {
+            Err(NomadTypeError::IntegerOverflow { value, target }) => {
+                assert_eq!(value, "4294967303");
+                assert_eq!(target, "u32");
+            }
+            other => panic!("expected an overflow, got {:?}", other),
+        }
+    }
+}
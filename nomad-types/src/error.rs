@@ -6,4 +6,29 @@ pub enum NomadTypeError {
     /// Failed to perform conversion to 20 byte address
     #[error("Failed to convert 32 byte address into 20 byte address: {0}")]
     AddressConversionError(NomadIdentifier),
+    /// Failed to parse a 32-byte hex value
+    #[error("Invalid 32 byte hex value: {0}")]
+    InvalidH256(String),
+    /// Failed to parse a 32-byte address or a 20-byte EVM address
+    #[error("Invalid 32 byte or EVM address: {0}")]
+    InvalidAddress32(String),
+    /// Failed to parse a 20-byte EVM address
+    #[error("Invalid EVM address: {0}")]
+    InvalidEvmAddress(String),
+    /// An integer did not fit the type it was converted to
+    #[error("Integer {value} does not fit into {target}")]
+    IntegerOverflow {
+        /// The integer
+        value: String,
+        /// The type it was converted to
+        target: &'static str,
+    },
+    /// Failed to parse a message id or a message leaf hash
+    #[error("Invalid message {input:?}: {reason}. Expected a leaf hash or <origin domain>-<leaf index>, e.g. 2000-12345")]
+    InvalidMessageId {
+        /// The input
+        input: String,
+        /// What is wrong with it
+        reason: &'static str,
+    },
 }
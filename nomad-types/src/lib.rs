@@ -1,20 +1,45 @@
 //! Common Nomad data structures used across various parts of the stack (configuration, SDK, agents)
 
+mod address32;
+pub use address32::*;
+
+mod canonical;
+pub use canonical::*;
+
+mod checksum;
+pub use checksum::*;
+
+mod conversions;
+pub use conversions::*;
+
 mod error;
 pub use error::*;
 
 mod macros;
 pub use macros::*;
 
+mod message_id;
+pub use message_id::*;
+
+mod schema;
+pub use schema::*;
+
 use color_eyre::{eyre::bail, Report, Result};
 use ethers::prelude::{Address, H160, H256};
 use serde::{de, Deserializer};
 use std::{fmt, ops::DerefMut, str::FromStr};
 
-/// A Hex String of length `N` representing bytes of length `N / 2`
-#[derive(Debug, Clone, PartialEq)]
+/// A Hex String of length `N` representing bytes of length `N / 2`. Used
+/// for private keys, so `Debug` does not show the value.
+#[derive(Clone, PartialEq)]
 pub struct HexString<const N: usize>(String);
 
+impl<const N: usize> fmt::Debug for HexString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HexString<{}>(..)", N)
+    }
+}
+
 impl<const N: usize> AsRef<String> for HexString<N> {
     fn as_ref(&self) -> &String {
         &self.0
@@ -66,7 +91,7 @@ pub struct NomadIdentifier(H256);
 
 impl std::fmt::Display for NomadIdentifier {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:x}", self.0)
+        write!(f, "{}", format_h256(&self.0))
     }
 }
 
@@ -189,7 +214,7 @@ impl<'de> de::Visitor<'de> for NomadIdentifierVisitor {
 impl_deser_nomad_number!(u128, u64, u32, u16, u8);
 
 /// An abstraction for allowing domains to be referenced by name or number
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(untagged)]
 pub enum NameOrDomain {
     /// Domain name
@@ -212,7 +237,16 @@ impl From<u32> for NameOrDomain {
 
 /// Domain/Address pair
 #[derive(
-    Default, Debug, Clone, Copy, Hash, Eq, PartialEq, serde::Serialize, serde::Deserialize,
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    Hash,
+    Eq,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
 )]
 #[serde(rename_all = "camelCase")]
 pub struct NomadLocator {
@@ -224,7 +258,16 @@ pub struct NomadLocator {
 
 /// An EVM beacon proxy
 #[derive(
-    Default, Debug, Clone, Copy, serde::Serialize, serde::Deserialize, Eq, PartialEq, Hash,
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    Eq,
+    PartialEq,
+    Hash,
 )]
 #[serde(rename_all = "camelCase")]
 pub struct Proxy {
@@ -256,6 +299,12 @@ mod test {
         assert_eq!(a, expected);
     }
 
+    #[test]
+    fn it_displays_identifiers_canonically() {
+        let id: NomadIdentifier = H256::repeat_byte(0xab).into();
+        assert_eq!(id.to_string(), format!("0x{}", "ab".repeat(32)));
+    }
+
     #[test]
     fn it_sers_and_desers_numbers() {
         // u64
@@ -0,0 +1,193 @@
+//! Identifiers of messages. A message is identified by its origin domain and
+//! its index in the origin home's tree, written `<origin>-<leaf index>` as
+//! the TS SDK does, or by its leaf hash.
+
+use ethers::prelude::H256;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+use crate::{format_h256, parse_h256, NomadTypeError};
+
+/// A message by origin domain and leaf index, e.g. `2000-12345`. Unlike the
+/// leaf hash, it cannot be ambiguous across domains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MessageId {
+    /// Domain of the home the message was dispatched on
+    pub origin: u32,
+    /// Index of the message in the origin home's tree
+    pub leaf_index: u32,
+}
+
+impl MessageId {
+    /// Id of the message at `leaf_index` of the home of `origin`
+    pub fn new(origin: u32, leaf_index: u32) -> Self {
+        Self { origin, leaf_index }
+    }
+}
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.origin, self.leaf_index)
+    }
+}
+
+impl FromStr for MessageId {
+    type Err = NomadTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason| NomadTypeError::InvalidMessageId {
+            input: s.to_owned(),
+            reason,
+        };
+
+        let (origin, leaf_index) = s
+            .trim()
+            .split_once('-')
+            .ok_or_else(|| invalid("missing `-` between origin and leaf index"))?;
+        let number = |part: &str, what| {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid(what));
+            }
+            part.parse::<u32>().map_err(|_| invalid("number too large"))
+        };
+
+        Ok(Self {
+            origin: number(origin, "origin is not a domain number")?,
+            leaf_index: number(leaf_index, "leaf index is not a number")?,
+        })
+    }
+}
+
+impl Serialize for MessageId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MessageId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A message as users refer to it: by leaf hash or by `MessageId`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageRef {
+    /// Leaf hash of the message
+    Leaf(H256),
+    /// Origin domain and leaf index of the message
+    Id(MessageId),
+}
+
+impl fmt::Display for MessageRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageRef::Leaf(leaf) => write!(f, "{}", format_h256(leaf)),
+            MessageRef::Id(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+impl FromStr for MessageRef {
+    type Err = NomadTypeError;
+
+    /// Parse a leaf hash in any form `parse_h256` accepts, or a `MessageId`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains('-') {
+            return s.parse().map(MessageRef::Id);
+        }
+        parse_h256(s)
+            .map(MessageRef::Leaf)
+            .map_err(|_| NomadTypeError::InvalidMessageId {
+                input: s.to_owned(),
+                reason: "neither a leaf hash nor an id",
+            })
+    }
+}
+
+impl From<H256> for MessageRef {
+    fn from(leaf: H256) -> Self {
+        MessageRef::Leaf(leaf)
+    }
+}
+
+impl From<MessageId> for MessageRef {
+    fn from(id: MessageId) -> Self {
+        MessageRef::Id(id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_round_trips_message_ids() {
+        let id = MessageId::new(2000, 12345);
+        assert_eq!(id.to_string(), "2000-12345");
+        assert_eq!("2000-12345".parse::<MessageId>().unwrap(), id);
+        assert_eq!(" 2000-12345\n".parse::<MessageId>().unwrap(), id);
+
+        let json = serde_json::to_value(&id).unwrap();
+        assert_eq!(json, serde_json::json!("2000-12345"));
+        assert_eq!(serde_json::from_value::<MessageId>(json).unwrap(), id);
+
+        let max = MessageId::new(u32::MAX, u32::MAX);
+        assert_eq!(max.to_string().parse::<MessageId>().unwrap(), max);
+    }
+
+    #[test]
+    fn it_explains_malformed_message_ids() {
+        let cases = [
+            ("2000", "missing `-` between origin and leaf index"),
+            ("2000_12345", "missing `-` between origin and leaf index"),
+            ("-12345", "origin is not a domain number"),
+            ("ethereum-12345", "origin is not a domain number"),
+            ("2000-", "leaf index is not a number"),
+            ("2000-+5", "leaf index is not a number"),
+            ("2000-1-2", "leaf index is not a number"),
+            ("2000-0x10", "leaf index is not a number"),
+            ("2000-4294967296", "number too large"),
+        ];
+        for (input, expected) in cases {
+            match input.parse::<MessageId>() {
+                Err(NomadTypeError::InvalidMessageId { input: got, reason }) => {
+                    assert_eq!(got, input);
+                    assert_eq!(reason, expected, "{}", input);
+                }
+                other => panic!("{} parsed as {:?}", input, other),
+            }
+        }
+
+        let error = "ethereum-1".parse::<MessageId>().unwrap_err().to_string();
+        assert!(error.contains("ethereum-1"), "{}", error);
+        assert!(error.contains("<origin domain>-<leaf index>"), "{}", error);
+    }
+
+    #[test]
+    fn it_parses_leaves_and_ids_as_message_refs() {
+        let leaf = H256::repeat_byte(0xab);
+        for input in [format!("{:#x}", leaf), "ab".repeat(32), "AB".repeat(32)] {
+            assert_eq!(input.parse::<MessageRef>().unwrap(), MessageRef::Leaf(leaf));
+        }
+        assert_eq!(
+            "1650811245-7".parse::<MessageRef>().unwrap(),
+            MessageRef::Id(MessageId::new(1650811245, 7))
+        );
+
+        for message in [MessageRef::Leaf(leaf), MessageId::new(6648936, 0).into()] {
+            assert_eq!(message.to_string().parse::<MessageRef>().unwrap(), message);
+        }
+
+        assert!("0xabc".parse::<MessageRef>().is_err());
+        assert!("2000-x".parse::<MessageRef>().is_err());
+        assert!("".parse::<MessageRef>().is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! JSON schemas for types with custom serde implementations
+
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{
+        InstanceType, Metadata, NumberValidation, Schema, SchemaObject, StringValidation,
+        SubschemaValidation,
+    },
+    JsonSchema,
+};
+
+use crate::{HexString, NomadIdentifier};
+
+fn string_schema(pattern: String, description: &str) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        string: Some(Box::new(StringValidation {
+            pattern: Some(pattern),
+            ..Default::default()
+        })),
+        metadata: Some(Box::new(Metadata {
+            description: Some(description.to_owned()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Schema for fields deserialized with the `deser_nomad_*` functions. Allows
+/// numbers, hex strings, and decimal strings.
+pub fn nomad_number_schema(_: &mut SchemaGenerator) -> Schema {
+    let integer = SchemaObject {
+        instance_type: Some(InstanceType::Integer.into()),
+        number: Some(Box::new(NumberValidation {
+            minimum: Some(0.0),
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    let string = string_schema(
+        "^([0-9]+|0x[0-9a-fA-F]*)$".to_owned(),
+        "A decimal string or a 0x-prepended hexadecimal string",
+    );
+
+    SchemaObject {
+        subschemas: Some(Box::new(SubschemaValidation {
+            any_of: Some(vec![integer.into(), string]),
+            ..Default::default()
+        })),
+        metadata: Some(Box::new(Metadata {
+            description: Some(
+                "An integer, a decimal string, or a 0x-prepended hexadecimal string".to_owned(),
+            ),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+impl<const N: usize> JsonSchema for HexString<N> {
+    fn schema_name() -> String {
+        format!("HexString{}", N)
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        string_schema(
+            format!("^(0x)?[0-9a-fA-F]{{{}}}$", N),
+            &format!("A hex string of length {}, with or without 0x prefix", N),
+        )
+    }
+}
+
+impl JsonSchema for NomadIdentifier {
+    fn schema_name() -> String {
+        "NomadIdentifier".to_owned()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        string_schema(
+            "^(0x)?([0-9a-fA-F]{40}|[0-9a-fA-F]{64})$".to_owned(),
+            "A 20-byte address or a 32-byte identifier as a hex string",
+        )
+    }
+}
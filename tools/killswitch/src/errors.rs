@@ -42,4 +42,10 @@ pub enum Error {
     /// Unenrollment failure
     #[error("UnenrollmentFailed: Unenrollment failed: {0}")]
     UnenrollmentFailed(#[source] ChainCommunicationError),
+    /// Chain id or connection manager missing for a Safe batch
+    #[error("MissingSafeTarget: No EVM chain id or connection manager in config for: {0}")]
+    MissingSafeTarget(String),
+    /// Safe batch not written
+    #[error("SafeBatchExport: Unable to write Safe batch: {0}")]
+    SafeBatchExport(String),
 }
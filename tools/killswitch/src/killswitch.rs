@@ -1,13 +1,23 @@
 use crate::{
-    errors::Error, output::build_output_message, settings::Settings, Args, Message, Result,
+    errors::Error,
+    output::{build_export_message, build_output_message},
+    settings::Settings,
+    Args, Message, Result,
 };
+use ethers::prelude::H160;
 use futures_util::future::join_all;
 use nomad_base::{AttestationSigner, ChainSetup, ChainSetupType, ConnectionManagers, Homes};
 use nomad_core::{
     Common, ConnectionManager, FailureNotification, FromSignerConf, Home,
     SignedFailureNotification, TxOutcome,
 };
-use nomad_xyz_configuration::AgentSecrets;
+use nomad_ethereum::{encode_unenroll_replica_calldata, SafeBatch, SafeTransaction};
+use nomad_xyz_configuration::{core::CoreDeploymentInfo, AgentSecrets};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
 /// Main `KillSwitch` struct
 #[derive(Debug)]
@@ -25,6 +35,16 @@ pub(crate) struct Channel {
     pub(crate) replica: String,
 }
 
+/// Chain and connection manager a channel is killed on when exported to a
+/// Safe batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SafeTarget {
+    /// EVM chain id of the replica network
+    chain_id: u64,
+    /// Connection manager on the replica network
+    connection_manager: H160,
+}
+
 /// The channel and contracts required or errors encountered
 #[derive(Debug)]
 struct ChannelKiller {
@@ -32,8 +52,10 @@ struct ChannelKiller {
     channel: Channel,
     /// Home contract
     home_contract: Option<Homes>,
-    /// Connection manager
+    /// Connection manager. Not made when exporting to Safe batches
     connection_manager: Option<ConnectionManagers>,
+    /// Safe batch target. Only made when exporting to Safe batches
+    safe_target: Option<SafeTarget>,
     /// Attestation signer
     attestation_signer: Option<AttestationSigner>,
     /// Contract init errors we've encountered
@@ -114,8 +136,7 @@ impl KillSwitch {
         // We just need the rpc here
         let secrets = AgentSecrets {
             rpcs: settings.rpcs.clone(),
-            tx_submitters: Default::default(),
-            attestation_signer: None,
+            ..Default::default()
         };
         Ok(ChainSetup::from_config_and_secrets(
             setup_type,
@@ -140,6 +161,44 @@ impl KillSwitch {
             .map_err(|report| Error::HomeInit(format!("{:#}", report)))
     }
 
+    /// Make `Homes` without a transaction submitter, for reading the updater
+    /// only, or return error
+    async fn make_read_only_home(channel: &Channel, settings: &Settings) -> Result<Homes> {
+        let setup_type = ChainSetupType::Home {
+            home_network: &channel.home,
+        };
+        let chain_setup = Self::make_chain_setup(&channel.home, setup_type, settings)?;
+        chain_setup
+            .try_into_home(None, None, None)
+            .await
+            .map_err(|report| Error::HomeInit(format!("{:#}", report)))
+    }
+
+    /// Make `SafeTarget` of the channel's replica network from config or
+    /// return error
+    fn make_safe_target(channel: &Channel, settings: &Settings) -> Result<SafeTarget> {
+        let missing = || Error::MissingSafeTarget(channel.replica.clone());
+        let chain_id = settings
+            .config
+            .protocol()
+            .networks
+            .get(&channel.replica)
+            .map(|domain| domain.specs.chain_id)
+            .filter(|chain_id| *chain_id != 0)
+            .ok_or_else(missing)?;
+        let connection_manager = match settings.config.core().get(&channel.replica) {
+            Some(CoreDeploymentInfo::Ethereum(core)) => core
+                .x_app_connection_manager
+                .as_ethereum_address()
+                .map_err(|_| missing())?,
+            _ => return Err(missing()),
+        };
+        Ok(SafeTarget {
+            chain_id,
+            connection_manager,
+        })
+    }
+
     /// Make `ConnectionManagers` or return error
     async fn make_connection_manager(
         channel: &Channel,
@@ -186,14 +245,20 @@ impl KillSwitch {
             return Err(Error::NoNetworks);
         }
 
+        // Exported kills are sent by a Safe, so need no submitters
+        let exporting = args.safe_json.is_some();
         let futs = channels.into_iter().map(|channel| async {
-            let home_contract = Self::make_home(&channel, &settings).await;
-            let connection_manager = Self::make_connection_manager(&channel, &settings).await;
+            let home_contract = if exporting {
+                Self::make_read_only_home(&channel, &settings).await
+            } else {
+                Self::make_home(&channel, &settings).await
+            };
             let attestation_signer = Self::make_signer(&channel, &settings).await;
             let mut killer = ChannelKiller {
                 channel,
                 home_contract: None,
                 connection_manager: None,
+                safe_target: None,
                 attestation_signer: None,
                 errors: vec![],
             };
@@ -204,10 +269,16 @@ impl KillSwitch {
                 killer.home_contract = home_contract.ok();
             }
 
-            if let Err(err) = connection_manager {
-                killer.errors.push(err);
+            if exporting {
+                match Self::make_safe_target(&killer.channel, &settings) {
+                    Ok(target) => killer.safe_target = Some(target),
+                    Err(err) => killer.errors.push(err),
+                }
             } else {
-                killer.connection_manager = connection_manager.ok();
+                match Self::make_connection_manager(&killer.channel, &settings).await {
+                    Ok(connection_manager) => killer.connection_manager = Some(connection_manager),
+                    Err(err) => killer.errors.push(err),
+                }
             }
 
             if let Err(err) = attestation_signer {
@@ -283,12 +354,95 @@ impl KillSwitch {
 
         build_output_message(bad, good)
     }
+
+    /// Sign failure notifications for the remaining channels and write their
+    /// `unenrollReplica` calls to `dir`, one Safe transaction batch per
+    /// replica network, instead of sending them
+    pub(crate) async fn export_safe_batches(mut self, dir: &Path) -> Message {
+        let futs = self
+            .channel_killers
+            .iter_mut()
+            .map(|killer| async {
+                let target = killer.safe_target.expect("made when exporting");
+                let signed_failure = killer.create_signed_failure().await;
+                (killer.channel.clone(), target, signed_failure)
+            })
+            .collect::<Vec<_>>();
+
+        let results = join_all(futs).await;
+
+        // Calls by replica network
+        let mut bad = vec![];
+        let mut networks: BTreeMap<String, (SafeTarget, Vec<(Channel, Vec<u8>)>)> = BTreeMap::new();
+        for (channel, target, signed_failure) in results {
+            match signed_failure {
+                Ok(signed_failure) => networks
+                    .entry(channel.replica.clone())
+                    .or_insert_with(|| (target, vec![]))
+                    .1
+                    .push((channel, encode_unenroll_replica_calldata(&signed_failure))),
+                Err(error) => bad.push((channel, vec![error])),
+            }
+        }
+
+        let mut good = vec![];
+        for (network, (target, calls)) in networks {
+            match write_safe_batch(dir, &network, target, &calls) {
+                Ok(path) => good.extend(
+                    calls
+                        .into_iter()
+                        .map(|(channel, _)| (channel, path.clone())),
+                ),
+                Err(error) => bad.extend(calls.into_iter().map(|(channel, _)| {
+                    (channel, vec![Error::SafeBatchExport(error.to_string())])
+                })),
+            }
+        }
+
+        build_export_message(bad, good)
+    }
+}
+
+/// Write the `unenrollReplica` calls killing channels into `network` to
+/// `<dir>/<network>.json` as one Safe transaction batch. Returns the path
+fn write_safe_batch(
+    dir: &Path,
+    network: &str,
+    target: SafeTarget,
+    calls: &[(Channel, Vec<u8>)],
+) -> std::io::Result<PathBuf> {
+    let homes = calls
+        .iter()
+        .map(|(channel, _)| channel.home.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let batch = SafeBatch::new(
+        target.chain_id,
+        format!("Killswitch {}", network),
+        format!(
+            "Unenroll the replicas of {} on {} with signed failure notifications",
+            homes, network
+        ),
+        calls
+            .iter()
+            .map(|(_, calldata)| SafeTransaction::new(target.connection_manager, calldata))
+            .collect(),
+    );
+
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.json", network));
+    fs::write(
+        &path,
+        serde_json::to_string_pretty(&batch).expect("Serialization error. Should never happen"),
+    )?;
+    Ok(path)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::App;
+    use ethers::{signers::LocalWallet, types::Bytes};
     use nomad_test::test_utils;
     use nomad_xyz_configuration::{ChainConf, Connection};
     use std::collections::HashMap;
@@ -448,6 +602,7 @@ mod test {
                 app: App::TokenBridge,
                 all: false,
                 all_inbound: Some("avalanche".into()), // Unused network
+                safe_json: None,
                 pretty: false,
             };
             let settings = Settings::new().await;
@@ -460,6 +615,87 @@ mod test {
         .await
     }
 
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn it_makes_safe_targets() {
+        test_utils::run_test_with_env("../../fixtures/env.test-killswitch", || async move {
+            let settings = Settings::new().await.unwrap();
+            let channel = Channel {
+                home: "rinkeby".into(),
+                replica: "goerli".into(),
+            };
+
+            let target = KillSwitch::make_safe_target(&channel, &settings).unwrap();
+            assert_eq!(target.chain_id, 5);
+            let core = match settings.config.core().get("goerli").unwrap() {
+                CoreDeploymentInfo::Ethereum(core) => core,
+                _ => panic!("goerli is an EVM network"),
+            };
+            assert_eq!(
+                target.connection_manager,
+                core.x_app_connection_manager.as_ethereum_address().unwrap()
+            );
+
+            let unknown = Channel {
+                home: "rinkeby".into(),
+                replica: "avalanche".into(),
+            };
+            assert_matches!(
+                KillSwitch::make_safe_target(&unknown, &settings).unwrap_err(),
+                Error::MissingSafeTarget(n) if n == "avalanche"
+            );
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_writes_one_safe_batch_per_network() {
+        let wallet: LocalWallet =
+            "1111111111111111111111111111111111111111111111111111111111111111"
+                .parse()
+                .unwrap();
+        let mut calls = vec![];
+        for (home, home_domain) in [("rinkeby", 2000), ("kovan", 3000)] {
+            let signed = FailureNotification {
+                home_domain,
+                updater: H160::repeat_byte(7).into(),
+            }
+            .sign_with(&wallet)
+            .await
+            .unwrap();
+            let channel = Channel {
+                home: home.into(),
+                replica: "goerli".into(),
+            };
+            calls.push((channel, encode_unenroll_replica_calldata(&signed)));
+        }
+        let target = SafeTarget {
+            chain_id: 5,
+            connection_manager: H160::repeat_byte(1),
+        };
+
+        let dir = std::env::temp_dir().join(format!("killswitch-safe-{}", std::process::id()));
+        let path = write_safe_batch(&dir, "goerli", target, &calls).unwrap();
+        assert_eq!(path, dir.join("goerli.json"));
+
+        let batch: SafeBatch = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(batch.chain_id, "5");
+        assert_eq!(batch.transactions.len(), 2);
+        for (tx, (_, calldata)) in batch.transactions.iter().zip(calls.iter()) {
+            assert_eq!(tx.to.parse::<H160>().unwrap(), H160::repeat_byte(1));
+            assert_eq!(&tx.data.parse::<Bytes>().unwrap().to_vec(), calldata);
+            assert_eq!(tx.contract_method.as_ref().unwrap().name, "unenrollReplica");
+        }
+        assert_eq!(
+            batch.transactions[1]
+                .contract_inputs_values
+                .as_ref()
+                .unwrap()["_domain"],
+            "3000"
+        );
+    }
+
     /// `ChannelKiller` with errors
     fn make_bad_channel_killer() -> ChannelKiller {
         let channel = Channel {
@@ -470,6 +706,7 @@ mod test {
             channel: channel.clone(),
             home_contract: None,
             connection_manager: None,
+            safe_target: None,
             attestation_signer: None,
             errors: vec![
                 Error::MissingTxSubmitterConf(channel.home.clone()),
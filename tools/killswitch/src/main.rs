@@ -14,6 +14,7 @@ use clap::{ArgGroup, Parser, ValueEnum};
 use std::{
     env,
     io::{stdout, Write},
+    path::PathBuf,
     process::exit,
 };
 
@@ -48,6 +49,11 @@ struct Args {
     #[clap(long, value_name = "NETWORK")]
     all_inbound: Option<String>,
 
+    /// Write the kills to DIR as one Safe transaction batch per network
+    /// instead of sending them
+    #[clap(long, value_name = "DIR")]
+    safe_json: Option<PathBuf>,
+
     // The most common form of streaming JSON is line delimited
     // hide this behind a (hidden) flag so it's not abused
     #[clap(long, hide = true)]
@@ -81,6 +87,7 @@ fn report(message: Message, pretty: bool) {
 async fn main() {
     let args = Args::parse();
     let pretty = args.pretty;
+    let safe_json = args.safe_json.clone();
 
     // Try to build `NomadConfig`, exiting immediately if we can't
     let settings = Settings::new().await;
@@ -106,8 +113,11 @@ async fn main() {
         report(errors, pretty);
     }
 
-    // Run all channels that *could* succeed
-    let results = killswitch.run().await;
+    // Run all channels that *could* succeed, or export them for a Safe
+    let results = match safe_json {
+        Some(dir) => killswitch.export_safe_batches(&dir).await,
+        None => killswitch.run().await,
+    };
 
     // Give users final results, exit ok
     report(results, pretty);
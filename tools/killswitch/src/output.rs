@@ -2,7 +2,7 @@ use crate::{errors::Error, killswitch::Channel};
 use ethers::prelude::H256;
 use nomad_core::TxOutcome;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 
 /// KillSwitch response showing success / failure of configuration
 /// and tx submission. Gets serialized to json
@@ -82,6 +82,10 @@ enum ReplicaOutput {
         tx_hash: Option<H256>,
         /// Will be populated with errors on failure
         message: Option<Vec<String>>,
+        /// Will be populated with the Safe batch file holding the kill, if
+        /// exported instead of sent
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        safe_batch: Option<String>,
     },
 }
 
@@ -102,6 +106,45 @@ pub(crate) fn build_output_message(
     bad: Vec<(Channel, Vec<Error>)>,
     good: Vec<(Channel, TxOutcome)>,
 ) -> Message {
+    let good = good
+        .into_iter()
+        .map(|(channel, tx)| {
+            let replica = ReplicaOutput::Result {
+                status: Status::Success,
+                tx_hash: Some(tx.txid),
+                message: None,
+                safe_batch: None,
+            };
+            (channel, replica)
+        })
+        .collect();
+    build_message(bad, good)
+}
+
+/// Build output `Message::FullMessage(Homes)` accepting a set
+/// of errored channels as well as channels exported to Safe batch files
+#[allow(clippy::type_complexity)]
+pub(crate) fn build_export_message(
+    bad: Vec<(Channel, Vec<Error>)>,
+    good: Vec<(Channel, PathBuf)>,
+) -> Message {
+    let good = good
+        .into_iter()
+        .map(|(channel, path)| {
+            let replica = ReplicaOutput::Result {
+                status: Status::Success,
+                tx_hash: None,
+                message: None,
+                safe_batch: Some(path.display().to_string()),
+            };
+            (channel, replica)
+        })
+        .collect();
+    build_message(bad, good)
+}
+
+#[allow(clippy::type_complexity)]
+fn build_message(bad: Vec<(Channel, Vec<Error>)>, good: Vec<(Channel, ReplicaOutput)>) -> Message {
     // Failed channels
     let mut replicas = bad
         .into_iter()
@@ -117,20 +160,17 @@ pub(crate) fn build_output_message(
                         .map(|e| format!("{}", e))
                         .collect::<Vec<String>>(),
                 ),
+                safe_batch: None,
             };
             (channel.clone(), (false, (channel.replica, replica)))
         })
         .collect::<Vec<(_, (_, _))>>();
 
     // Successful channels
-    replicas.extend(good.into_iter().map(|(channel, tx)| {
-        let replica = ReplicaOutput::Result {
-            status: Status::Success,
-            tx_hash: Some(tx.txid),
-            message: None,
-        };
-        (channel.clone(), (true, (channel.replica, replica)))
-    }));
+    replicas.extend(
+        good.into_iter()
+            .map(|(channel, replica)| (channel.clone(), (true, (channel.replica, replica)))),
+    );
 
     // Map replicas to homes
     let mut homes: HashMap<String, Vec<(bool, (String, ReplicaOutput))>> = HashMap::new();
@@ -247,12 +287,16 @@ mod test {
                 "0x1111111111111111111111111111111111111111111111111111111111111111",
             )
             .unwrap(),
+            gas_used: None,
+            block_number: None,
         };
         let tx2 = TxOutcome {
             txid: H256::from_str(
                 "0x2222222222222222222222222222222222222222222222222222222222222222",
             )
             .unwrap(),
+            gas_used: None,
+            block_number: None,
         };
         let good = vec![(channel1, tx1), (channel2, tx2)];
         let homes = match build_output_message(vec![], good) {
@@ -272,6 +316,39 @@ mod test {
         );
     }
 
+    #[test]
+    fn it_produces_correct_export_output() {
+        let channel1 = Channel {
+            home: "ethereum".into(),
+            replica: "avalanche".into(),
+        };
+        let channel2 = Channel {
+            home: "avalanche".into(),
+            replica: "ethereum".into(),
+        };
+        let error = Error::MissingSafeTarget(channel1.replica.clone());
+        let bad = vec![(channel1, vec![error])];
+        let good = vec![(channel2, PathBuf::from("batches/ethereum.json"))];
+        let homes = match build_export_message(bad, good) {
+            Message::FullMessage(homes) => homes,
+            _ => panic!("Match error. Should never happen"),
+        };
+        let json = serde_json::to_string(&homes).unwrap();
+        assert!(json.contains("\"safe_batch\":\"batches/ethereum.json\""));
+        assert!(!json.contains("\"safe_batch\":null"));
+
+        let result: HomesOutput = serde_json::from_str(&json).unwrap();
+        let ethereum = result.homes.get("ethereum").unwrap();
+        let avalanche = result.homes.get("avalanche").unwrap();
+        assert_matches!(ethereum.status, Status::Error);
+        assert_matches!(avalanche.status, Status::Success);
+        assert_matches!(
+            avalanche.message.replicas.get("ethereum").unwrap(),
+            ReplicaOutput::Result { tx_hash: None, safe_batch: Some(path), .. }
+                if path == "batches/ethereum.json"
+        );
+    }
+
     #[test]
     fn it_produces_correct_mixed_output() {
         let channel1 = Channel {
@@ -287,6 +364,8 @@ mod test {
                 "0x1111111111111111111111111111111111111111111111111111111111111111",
             )
             .unwrap(),
+            gas_used: None,
+            block_number: None,
         };
         let error = Error::MissingTxSubmitterConf(channel1.replica.clone());
         let bad = vec![(channel1.clone(), vec![error])];
@@ -1,6 +1,27 @@
 use structopt::StructOpt;
 
-use crate::subcommands::{db_state::DbStateCommand, prove::ProveCommand};
+use crate::subcommands::{
+    audit::AuditCommand,
+    compute_root::ComputeRootCommand,
+    db_rekey::DbRekeyCommand,
+    db_snapshot::DbSnapshotCommand,
+    db_state::DbStateCommand,
+    db_verify::DbVerifyCommand,
+    decode_governance::DecodeGovernanceCommand,
+    dispatch::DispatchCommand,
+    duplicates::DuplicatesCommand,
+    evidence::{ExportEvidenceCommand, VerifyEvidenceCommand},
+    failure_notification::{SignFailureNotificationCommand, VerifyFailureNotificationCommand},
+    held_messages::HeldMessagesCommand,
+    ownership::{RenounceOwnershipCommand, TransferOwnershipCommand},
+    processed_failed::ProcessedFailedCommand,
+    prove::ProveCommand,
+    recipient_gas::RecipientGasCommand,
+    simulate_backlog::SimulateBacklogCommand,
+    trace::TraceCommand,
+    updater_handoff::UpdaterHandoffCommand,
+    watch::WatchCommand,
+};
 
 #[derive(StructOpt)]
 pub enum Commands {
@@ -8,4 +29,61 @@ pub enum Commands {
     Prove(ProveCommand),
     /// Print the processor's db state
     DbState(DbStateCommand),
+    /// Export a snapshot of the message tree for `--bootstrap-from`
+    DbSnapshot(DbSnapshotCommand),
+    /// Encrypt every db value under a new key, from an old key or from no
+    /// encryption. Run with the agent stopped
+    DbRekey(DbRekeyCommand),
+    /// Verify stored messages and proofs against each other. Fails if any
+    /// violation is left unresolved
+    DbVerify(DbVerifyCommand),
+    /// Export messages whose handler reverted at process time
+    ProcessedFailed(ProcessedFailedCommand),
+    /// Ask the active updater to hand its lease off to the standby
+    UpdaterHandoff(UpdaterHandoffCommand),
+    /// Compute the tree root of a file of leaves and compare it to the home
+    ComputeRoot(ComputeRootCommand),
+    /// Decode a governance message body and the calls it will execute
+    DecodeGovernance(DecodeGovernanceCommand),
+    /// Dispatch a message from a home, or print its calldata. Takes the
+    /// recipient as an EVM address or a 32-byte value
+    Dispatch(DispatchCommand),
+    /// Transfer ownership of a core contract to a known governance address
+    TransferOwnership(TransferOwnershipCommand),
+    /// Renounce ownership of a core contract. Irreversible
+    RenounceOwnership(RenounceOwnershipCommand),
+    /// Simulate processing a destination's pending messages against a fork.
+    /// Sends no transactions
+    SimulateBacklog(SimulateBacklogCommand),
+    /// Sign a failure notification with a watcher key, for governance to
+    /// submit `unenrollReplica` with
+    SignFailureNotification(SignFailureNotificationCommand),
+    /// Verify a signed failure notification artifact
+    VerifyFailureNotification(VerifyFailureNotificationCommand),
+    /// Read the audit trail of decisions leading to on-chain actions
+    Audit(AuditCommand),
+    /// Export the evidence bundle of a watcher's fraud incident, or list
+    /// the incidents
+    ExportEvidence(ExportEvidenceCommand),
+    /// Verify an evidence bundle's signatures, and its state reads and
+    /// transactions against public RPCs
+    VerifyEvidence(VerifyEvidenceCommand),
+    /// List the messages a processor holds for being older than its maximum
+    /// message age, or release one for processing
+    HeldMessages(HeldMessagesCommand),
+    /// Trace a message through the processor db: its proof, processing and
+    /// the provenance of the root it was processed under
+    Trace(TraceCommand),
+    /// List leaves indexed at more than one leaf index, with the blocks
+    /// each was indexed from
+    Duplicates(DuplicatesCommand),
+    /// List the recipients the processor spent the most gas on, with their
+    /// average gas per message
+    RecipientGas(RecipientGasCommand),
+    /// Generate the daily digest of messages, gas, incidents and signer
+    /// balances from processor dbs and agent metrics, once or every day
+    Report(ReportCommand),
+    /// Stream dispatches, updates and processes of the watched corridors
+    /// live, with their latencies, as lines of text or JSON
+    Watch(WatchCommand),
 }
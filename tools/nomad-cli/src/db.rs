@@ -0,0 +1,46 @@
+use color_eyre::{eyre::WrapErr, Result};
+use ethers::types::H256;
+use nomad_base::SecretResolver;
+use nomad_core::db::{DbCipher, DB};
+
+/// Environment variable holding the db encryption key, as for agents
+pub(crate) const DB_ENCRYPTION_KEY: &str = "DB_ENCRYPTION_KEY";
+
+/// Open the db at `db_path`, decrypting its values with the key in
+/// `DB_ENCRYPTION_KEY` if it is set
+pub(crate) async fn open(db_path: &str) -> Result<DB> {
+    let db = DB::from_path(db_path)?;
+    Ok(match env_key(DB_ENCRYPTION_KEY).await? {
+        Some(key) => db.with_cipher(DbCipher::new(&key)),
+        None => db,
+    })
+}
+
+/// Open the existing db at `db_path` read-only, so it can be read while an
+/// agent runs on it. Values are decrypted as by `open`.
+pub(crate) async fn open_read_only(db_path: &str) -> Result<DB> {
+    let db = DB::read_only_from_path(db_path)?;
+    Ok(match env_key(DB_ENCRYPTION_KEY).await? {
+        Some(key) => db.with_cipher(DbCipher::new(&key)),
+        None => db,
+    })
+}
+
+/// 32-byte hex key in the environment variable `var`, if it is set. The key
+/// may be a secret reference.
+pub(crate) async fn env_key(var: &str) -> Result<Option<[u8; 32]>> {
+    let value = match std::env::var(var) {
+        Ok(value) => value,
+        Err(_) => return Ok(None),
+    };
+    let value = SecretResolver::for_agent()
+        .await
+        .resolve(var, &value)
+        .await?
+        .unwrap_or(value);
+
+    let key: H256 = value
+        .parse()
+        .wrap_err_with(|| format!("{} is not a 32-byte hex key", var))?;
+    Ok(Some(key.to_fixed_bytes()))
+}
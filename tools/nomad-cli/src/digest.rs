@@ -0,0 +1,885 @@
+//! Daily digests for operators: messages per corridor, gas spent, incidents,
+//! signer balances and the gaps in what the digest could observe.
+
+use color_eyre::{eyre::eyre, Report, Result};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Gaps in metrics coverage shorter than this are not reported
+const MIN_GAP: u64 = 60;
+
+/// Counters of events operators must look into
+const INCIDENT_METRICS: [&str; 4] = [
+    "nomad_home_failure_observations",
+    "nomad_tree_count_mismatches",
+    "nomad_updater_signer_mismatches",
+    "nomad_channel_faults",
+];
+
+/// Gauges that are 1 while something needs attention
+const WARNING_METRICS: [&str; 1] = ["nomad_updater_stalled"];
+
+const WALLET_BALANCE_METRIC: &str = "nomad_wallet_balance_total";
+const START_TIME_METRIC: &str = "nomad_start_time_seconds";
+
+/// Fixed offset from UTC of the timezone digests are written in. Daylight
+/// saving changes are not followed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct UtcOffset {
+    seconds: i64,
+}
+
+impl FromStr for UtcOffset {
+    type Err = Report;
+
+    /// Parse `Z`, `UTC` or `+HH:MM`, `-HH:MM`, `+HH`
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "Z" || s.eq_ignore_ascii_case("utc") {
+            return Ok(Self { seconds: 0 });
+        }
+
+        let invalid = || eyre!("invalid UTC offset {}, expected e.g. +02:00", s);
+        let (sign, rest) = if let Some(rest) = s.strip_prefix('+') {
+            (1, rest)
+        } else if let Some(rest) = s.strip_prefix('-') {
+            (-1, rest)
+        } else {
+            return Err(invalid());
+        };
+        let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+        let hours: i64 = hours.parse().map_err(|_| invalid())?;
+        let minutes: i64 = minutes.parse().map_err(|_| invalid())?;
+        if hours > 14 || minutes >= 60 {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            seconds: sign * (hours * 3600 + minutes * 60),
+        })
+    }
+}
+
+impl fmt::Display for UtcOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.seconds < 0 { '-' } else { '+' };
+        let minutes = self.seconds.abs() / 60;
+        write!(f, "UTC{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+    }
+}
+
+/// A calendar date
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LocalDate {
+    year: i64,
+    month: u32,
+    day: u32,
+}
+
+impl LocalDate {
+    /// The date `days` days after 1970-01-01
+    fn from_days(days: i64) -> Self {
+        // Howard Hinnant's civil_from_days
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        Self { year, month, day }
+    }
+
+    /// Days from 1970-01-01 to the date
+    fn days(&self) -> i64 {
+        // Howard Hinnant's days_from_civil
+        let year = self.year - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let month = i64::from(self.month);
+        let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5
+            + i64::from(self.day)
+            - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
+    }
+}
+
+impl FromStr for LocalDate {
+    type Err = Report;
+
+    /// Parse `YYYY-MM-DD`
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || eyre!("invalid date {}, expected YYYY-MM-DD", s);
+        let mut parts = s.splitn(3, '-');
+        let mut next = || parts.next().ok_or_else(invalid);
+        let (year, month, day) = (next()?, next()?, next()?);
+        let date = Self {
+            year: year.parse().map_err(|_| invalid())?,
+            month: month.parse().map_err(|_| invalid())?,
+            day: day.parse().map_err(|_| invalid())?,
+        };
+
+        // Reject dates that do not round trip, such as 2023-02-30
+        if LocalDate::from_days(date.days()) != date {
+            return Err(invalid());
+        }
+        Ok(date)
+    }
+}
+
+impl fmt::Display for LocalDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// A local day, as unix times
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Window {
+    pub(crate) date: LocalDate,
+    pub(crate) offset: UtcOffset,
+    /// First second of the day
+    pub(crate) start: u64,
+    /// First second of the next day
+    pub(crate) end: u64,
+}
+
+impl Window {
+    /// The local day `date`
+    pub(crate) fn of(date: LocalDate, offset: UtcOffset) -> Self {
+        let start = date.days() * SECONDS_PER_DAY - offset.seconds;
+        Self {
+            date,
+            offset,
+            start: start.max(0) as u64,
+            end: (start + SECONDS_PER_DAY).max(0) as u64,
+        }
+    }
+
+    /// The local day before the one `now` is in
+    pub(crate) fn previous(now: u64, offset: UtcOffset) -> Self {
+        Self::of(LocalDate::from_days(local_days(now, offset) - 1), offset)
+    }
+
+    /// The local day after this one
+    pub(crate) fn next(&self) -> Self {
+        Self::of(LocalDate::from_days(self.date.days() + 1), self.offset)
+    }
+
+    /// Local time of `timestamp`, as `YYYY-MM-DD HH:MM`
+    pub(crate) fn format_time(&self, timestamp: u64) -> String {
+        let local = timestamp as i64 + self.offset.seconds;
+        let minutes = local.rem_euclid(SECONDS_PER_DAY) / 60;
+        format!(
+            "{} {:02}:{:02}",
+            LocalDate::from_days(local.div_euclid(SECONDS_PER_DAY)),
+            minutes / 60,
+            minutes % 60
+        )
+    }
+}
+
+/// Days from 1970-01-01 to the local date of `timestamp`
+fn local_days(timestamp: u64, offset: UtcOffset) -> i64 {
+    (timestamp as i64 + offset.seconds).div_euclid(SECONDS_PER_DAY)
+}
+
+/// One sample of a scraped metric
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Sample {
+    pub(crate) name: String,
+    pub(crate) labels: BTreeMap<String, String>,
+    pub(crate) value: f64,
+}
+
+impl Sample {
+    /// Labels as `name=value`, without the agent version
+    fn label_text(&self) -> String {
+        self.labels
+            .iter()
+            .filter(|(name, _)| name.as_str() != "VERSION")
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// The metrics of an agent at some point in time
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Snapshot {
+    /// Metrics endpoint scraped
+    pub(crate) endpoint: String,
+    /// Unix time of the scrape
+    pub(crate) taken_at: u64,
+    pub(crate) samples: Vec<Sample>,
+}
+
+impl Snapshot {
+    /// Parse the Prometheus text exposition of a scrape. Lines that are not
+    /// samples are skipped.
+    pub(crate) fn parse(endpoint: &str, taken_at: u64, text: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_owned(),
+            taken_at,
+            samples: text.lines().filter_map(parse_sample).collect(),
+        }
+    }
+
+    /// Unix time the agent started at, if it reports it
+    fn start_time(&self) -> Option<u64> {
+        self.samples
+            .iter()
+            .find(|sample| sample.name == START_TIME_METRIC)
+            .map(|sample| sample.value as u64)
+    }
+}
+
+/// Parse a `name{label="value",...} value [timestamp]` line
+fn parse_sample(line: &str) -> Option<Sample> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let name_end = line.find(|c: char| c == '{' || c.is_whitespace())?;
+    let (name, mut rest) = line.split_at(name_end);
+    let mut labels = BTreeMap::new();
+    if let Some(body) = rest.strip_prefix('{') {
+        let mut chars = body.char_indices();
+        let mut label = String::new();
+        let mut value = String::new();
+        let mut in_value = false;
+        let mut escaped = false;
+        let close = loop {
+            let (i, c) = chars.next()?;
+            match (in_value, c) {
+                (false, '}') => break i,
+                (false, '"') => in_value = true,
+                (false, '=') | (false, ',') => {}
+                (false, c) => label.push(c),
+                (true, _) if escaped => {
+                    value.push(if c == 'n' { '\n' } else { c });
+                    escaped = false;
+                }
+                (true, '\\') => escaped = true,
+                (true, '"') => {
+                    labels.insert(
+                        std::mem::take(&mut label).trim().to_owned(),
+                        std::mem::take(&mut value),
+                    );
+                    in_value = false;
+                }
+                (true, c) => value.push(c),
+            }
+        };
+        rest = &body[close + 1..];
+    }
+
+    let value = rest.split_whitespace().next()?.parse().ok()?;
+    Some(Sample {
+        name: name.to_owned(),
+        labels,
+        value,
+    })
+}
+
+/// Messages of a corridor over the window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CorridorTotals {
+    /// Name of the home
+    pub(crate) origin: String,
+    /// Destination domain
+    pub(crate) destination: u32,
+    pub(crate) dispatched: u64,
+    pub(crate) processed: u64,
+}
+
+impl CorridorTotals {
+    /// Growth of the corridor's backlog over the window
+    pub(crate) fn backlog_delta(&self) -> i64 {
+        self.dispatched as i64 - self.processed as i64
+    }
+}
+
+/// Gas spent processing messages on a chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ChainGas {
+    pub(crate) domain: u32,
+    pub(crate) gas_used: u64,
+    pub(crate) transactions: u64,
+}
+
+/// How urgent an alert is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Severity {
+    /// An event operators must look into
+    Incident,
+    /// A condition seen while the agent ran
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Incident => write!(f, "incident"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Incidents raised, or snapshots showing a warning, of one metric series
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Alert {
+    pub(crate) severity: Severity,
+    pub(crate) endpoint: String,
+    pub(crate) metric: String,
+    pub(crate) labels: String,
+    pub(crate) count: u64,
+}
+
+/// Latest balance of a signer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Balance {
+    pub(crate) chain: String,
+    pub(crate) wallet: String,
+    pub(crate) agent: String,
+    pub(crate) balance: i64,
+}
+
+/// A time range the digest has no data from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CoverageGap {
+    /// What is missing, e.g. the metrics of an endpoint
+    pub(crate) source: String,
+    pub(crate) from: u64,
+    pub(crate) to: u64,
+}
+
+/// A daily digest
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Digest {
+    pub(crate) window: Window,
+    pub(crate) corridors: Vec<CorridorTotals>,
+    pub(crate) gas: Vec<ChainGas>,
+    pub(crate) alerts: Vec<Alert>,
+    pub(crate) balances: Vec<Balance>,
+    pub(crate) gaps: Vec<CoverageGap>,
+}
+
+impl Digest {
+    /// Digest of `window` from db totals, with the gaps in them, and
+    /// metrics snapshots of `endpoints` taken every `sample_interval` seconds
+    pub(crate) fn new(
+        window: Window,
+        corridors: Vec<CorridorTotals>,
+        gas: Vec<ChainGas>,
+        db_gaps: Vec<CoverageGap>,
+        endpoints: &[String],
+        snapshots: &[Snapshot],
+        sample_interval: u64,
+    ) -> Self {
+        let mut gaps = coverage_gaps(&window, endpoints, snapshots, sample_interval);
+        gaps.extend(db_gaps);
+        Self {
+            window,
+            corridors,
+            gas,
+            alerts: alerts(&window, snapshots),
+            balances: balances(snapshots),
+            gaps,
+        }
+    }
+
+    /// Render the digest as Markdown
+    pub(crate) fn to_markdown(&self) -> String {
+        let window = &self.window;
+        let mut md = format!("# Nomad daily digest: {}\n\n", window.date);
+        md += &format!(
+            "Window: {} to {} ({})\n\n",
+            window.format_time(window.start),
+            window.format_time(window.end),
+            window.offset
+        );
+
+        md += "## Coverage\n\n";
+        if self.gaps.is_empty() {
+            md += "Data covers the whole window.\n";
+        }
+        for gap in self.gaps.iter() {
+            md += &format!(
+                "- {}: no data between {} and {}\n",
+                gap.source,
+                window.format_time(gap.from),
+                window.format_time(gap.to)
+            );
+        }
+
+        md += "\n## Messages per corridor\n\n";
+        if self.corridors.is_empty() {
+            md += "No messages dispatched or processed.\n";
+        } else {
+            md += "| Corridor | Dispatched | Processed | Backlog delta |\n";
+            md += "| --- | ---: | ---: | ---: |\n";
+            for corridor in self.corridors.iter() {
+                md += &format!(
+                    "| {} -> {} | {} | {} | {:+} |\n",
+                    corridor.origin,
+                    corridor.destination,
+                    corridor.dispatched,
+                    corridor.processed,
+                    corridor.backlog_delta()
+                );
+            }
+        }
+
+        md += "\n## Gas spent per chain\n\n";
+        if self.gas.is_empty() {
+            md += "No gas spent processing messages.\n";
+        } else {
+            md += "| Chain | Gas used | Transactions |\n";
+            md += "| --- | ---: | ---: |\n";
+            for chain in self.gas.iter() {
+                md += &format!(
+                    "| {} | {} | {} |\n",
+                    chain.domain, chain.gas_used, chain.transactions
+                );
+            }
+        }
+
+        md += "\n## Incidents and warnings\n\n";
+        if self.alerts.is_empty() {
+            md += "None raised.\n";
+        } else {
+            md += "| Severity | Source | Metric | Labels | Count |\n";
+            md += "| --- | --- | --- | --- | ---: |\n";
+            for alert in self.alerts.iter() {
+                md += &format!(
+                    "| {} | {} | {} | {} | {} |\n",
+                    alert.severity, alert.endpoint, alert.metric, alert.labels, alert.count
+                );
+            }
+        }
+
+        md += "\n## Signer balances\n\n";
+        if self.balances.is_empty() {
+            md += "No balances reported.\n";
+        } else {
+            md += "| Chain | Wallet | Agent | Balance (gwei) |\n";
+            md += "| --- | --- | --- | ---: |\n";
+            for balance in self.balances.iter() {
+                md += &format!(
+                    "| {} | {} | {} | {} |\n",
+                    balance.chain, balance.wallet, balance.agent, balance.balance
+                );
+            }
+        }
+
+        md
+    }
+
+    /// Render the digest as JSON
+    pub(crate) fn to_json(&self) -> Value {
+        let window = &self.window;
+        json!({
+            "date": window.date.to_string(),
+            "utcOffset": window.offset.to_string(),
+            "start": window.start,
+            "end": window.end,
+            "coverageGaps": self.gaps.iter().map(|gap| json!({
+                "source": gap.source,
+                "from": gap.from,
+                "to": gap.to,
+            })).collect::<Vec<_>>(),
+            "corridors": self.corridors.iter().map(|corridor| json!({
+                "origin": corridor.origin,
+                "destination": corridor.destination,
+                "dispatched": corridor.dispatched,
+                "processed": corridor.processed,
+                "backlogDelta": corridor.backlog_delta(),
+            })).collect::<Vec<_>>(),
+            "gas": self.gas.iter().map(|chain| json!({
+                "domain": chain.domain,
+                "gasUsed": chain.gas_used,
+                "transactions": chain.transactions,
+            })).collect::<Vec<_>>(),
+            "alerts": self.alerts.iter().map(|alert| json!({
+                "severity": alert.severity.to_string(),
+                "source": alert.endpoint,
+                "metric": alert.metric,
+                "labels": alert.labels,
+                "count": alert.count,
+            })).collect::<Vec<_>>(),
+            "balances": self.balances.iter().map(|balance| json!({
+                "chain": balance.chain,
+                "wallet": balance.wallet,
+                "agent": balance.agent,
+                "balance": balance.balance,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Snapshots of each endpoint, in the order they were taken
+fn by_endpoint(snapshots: &[Snapshot]) -> BTreeMap<&str, Vec<&Snapshot>> {
+    let mut endpoints: BTreeMap<&str, Vec<&Snapshot>> = BTreeMap::new();
+    for snapshot in snapshots {
+        endpoints
+            .entry(snapshot.endpoint.as_str())
+            .or_default()
+            .push(snapshot);
+    }
+    for taken in endpoints.values_mut() {
+        taken.sort_by_key(|snapshot| snapshot.taken_at);
+    }
+    endpoints
+}
+
+/// Incidents raised over the window, and warnings seen in its snapshots.
+///
+/// Incident metrics count since the agent started. Each run of an agent
+/// contributes its count at the first snapshot after the window, or its last
+/// count, less its count at the start of the window if it was running by
+/// then. Increments between the window start and the first snapshot of a run
+/// that started earlier are missed, and increments up to a snapshot taken
+/// after the window are included.
+fn alerts(window: &Window, snapshots: &[Snapshot]) -> Vec<Alert> {
+    let mut alerts = BTreeMap::new();
+    for (endpoint, taken) in by_endpoint(snapshots) {
+        // Runs by start time, then snapshots of each series
+        let mut runs: BTreeMap<Option<u64>, BTreeMap<(String, String), Vec<(u64, f64)>>> =
+            BTreeMap::new();
+        for snapshot in taken.iter() {
+            let run = runs.entry(snapshot.start_time()).or_default();
+            for sample in snapshot.samples.iter() {
+                if INCIDENT_METRICS.contains(&sample.name.as_str()) {
+                    run.entry((sample.name.clone(), sample.label_text()))
+                        .or_default()
+                        .push((snapshot.taken_at, sample.value));
+                }
+            }
+        }
+
+        for (start_time, series) in runs {
+            if start_time.map_or(false, |start| start >= window.end) {
+                continue;
+            }
+            for ((metric, labels), values) in series {
+                let baseline = match values.iter().rev().find(|(at, _)| *at <= window.start) {
+                    Some((_, value)) => *value,
+                    None if start_time.map_or(false, |start| start >= window.start) => 0.0,
+                    None => values[0].1,
+                };
+                let last = values
+                    .iter()
+                    .find(|(at, _)| *at >= window.end)
+                    .or_else(|| values.last())
+                    .map_or(0.0, |(_, value)| *value);
+                let raised = (last - baseline).max(0.0) as u64;
+                if raised > 0 {
+                    *alerts
+                        .entry((Severity::Incident, endpoint.to_owned(), metric, labels))
+                        .or_default() += raised;
+                }
+            }
+        }
+
+        for snapshot in taken.iter() {
+            if snapshot.taken_at < window.start || snapshot.taken_at >= window.end {
+                continue;
+            }
+            for sample in snapshot.samples.iter() {
+                if WARNING_METRICS.contains(&sample.name.as_str()) && sample.value > 0.0 {
+                    *alerts
+                        .entry((
+                            Severity::Warning,
+                            endpoint.to_owned(),
+                            sample.name.clone(),
+                            sample.label_text(),
+                        ))
+                        .or_default() += 1;
+                }
+            }
+        }
+    }
+
+    alerts
+        .into_iter()
+        .map(|((severity, endpoint, metric, labels), count)| Alert {
+            severity,
+            endpoint,
+            metric,
+            labels,
+            count,
+        })
+        .collect()
+}
+
+/// Balances in the latest snapshot of each endpoint
+fn balances(snapshots: &[Snapshot]) -> Vec<Balance> {
+    let mut balances = vec![];
+    for taken in by_endpoint(snapshots).values() {
+        let latest = match taken.last() {
+            Some(latest) => latest,
+            None => continue,
+        };
+        for sample in latest.samples.iter() {
+            if sample.name == WALLET_BALANCE_METRIC {
+                let label = |name: &str| sample.labels.get(name).cloned().unwrap_or_default();
+                balances.push(Balance {
+                    chain: label("chain"),
+                    wallet: label("wallet"),
+                    agent: label("agent"),
+                    balance: sample.value as i64,
+                });
+            }
+        }
+    }
+    balances.sort_by(|a, b| (&a.chain, &a.agent, &a.wallet).cmp(&(&b.chain, &b.agent, &b.wallet)));
+    balances
+}
+
+/// Ranges of the window without data from each of `endpoints`. A snapshot
+/// covers the time since the agent started, or the preceding
+/// `sample_interval` if the agent does not report its start time.
+fn coverage_gaps(
+    window: &Window,
+    endpoints: &[String],
+    snapshots: &[Snapshot],
+    sample_interval: u64,
+) -> Vec<CoverageGap> {
+    let taken = by_endpoint(snapshots);
+    let mut gaps = vec![];
+    for endpoint in endpoints {
+        let mut covered: Vec<(u64, u64)> = taken
+            .get(endpoint.as_str())
+            .map(|taken| {
+                taken
+                    .iter()
+                    .map(|snapshot| {
+                        let from = snapshot
+                            .start_time()
+                            .unwrap_or_else(|| snapshot.taken_at.saturating_sub(sample_interval));
+                        (from, snapshot.taken_at)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        covered.sort_unstable();
+
+        let mut cursor = window.start;
+        for (from, to) in covered
+            .into_iter()
+            .chain(std::iter::once((window.end, window.end)))
+        {
+            let from = from.min(window.end);
+            if from > cursor && from - cursor >= MIN_GAP {
+                gaps.push(CoverageGap {
+                    source: format!("metrics from {}", endpoint),
+                    from: cursor,
+                    to: from,
+                });
+            }
+            cursor = cursor.max(to);
+        }
+    }
+    gaps
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GOLDEN_DIGEST: &str = "../../fixtures/daily_digest.md";
+
+    fn sample(name: &str, labels: &[(&str, &str)], value: f64) -> Sample {
+        Sample {
+            name: name.to_owned(),
+            labels: labels
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            value,
+        }
+    }
+
+    fn snapshot(endpoint: &str, taken_at: u64, samples: Vec<Sample>) -> Snapshot {
+        Snapshot {
+            endpoint: endpoint.to_owned(),
+            taken_at,
+            samples,
+        }
+    }
+
+    /// A day on which the processor restarted and the updater was down
+    /// for the afternoon
+    fn fixture_digest() -> Digest {
+        let window = Window::of("2023-03-14".parse().unwrap(), "+02:00".parse().unwrap());
+        let (start, end) = (window.start, window.end);
+        let processor = "http://processor:9090/metrics".to_owned();
+        let updater = "http://updater:9090/metrics".to_owned();
+
+        let processor_sample = |taken_at: u64, started: u64, faults: f64| {
+            snapshot(
+                &processor,
+                taken_at,
+                vec![
+                    sample(START_TIME_METRIC, &[], started as f64),
+                    sample(
+                        "nomad_channel_faults",
+                        &[
+                            ("VERSION", "1.6.0"),
+                            ("agent", "processor"),
+                            ("home", "ethereum"),
+                            ("replica", "moonbeam"),
+                        ],
+                        faults,
+                    ),
+                    sample(
+                        WALLET_BALANCE_METRIC,
+                        &[
+                            ("agent", "processor"),
+                            ("chain", "moonbeam"),
+                            ("wallet", "0x1111"),
+                        ],
+                        1_500_000_000.0,
+                    ),
+                ],
+            )
+        };
+        let updater_sample = |taken_at: u64, stalled: f64| {
+            snapshot(
+                &updater,
+                taken_at,
+                vec![
+                    sample(START_TIME_METRIC, &[], (start - 3600) as f64),
+                    sample(
+                        "nomad_updater_stalled",
+                        &[("agent", "updater"), ("home", "ethereum")],
+                        stalled,
+                    ),
+                    sample(
+                        "nomad_home_failure_observations",
+                        &[("agent", "updater"), ("home", "ethereum")],
+                        0.0,
+                    ),
+                ],
+            )
+        };
+
+        let snapshots = vec![
+            // Up since the day before, restarted at 06:00 after 2 faults
+            processor_sample(start - 60, start - 7200, 1.0),
+            processor_sample(start + 5 * 3600, start - 7200, 3.0),
+            processor_sample(start + 6 * 3600 + 300, start + 6 * 3600, 0.0),
+            processor_sample(end + 900, start + 6 * 3600, 4.0),
+            // Stalled at noon, then down until the evening
+            updater_sample(start + 11 * 3600, 0.0),
+            updater_sample(start + 12 * 3600, 1.0),
+            snapshot(
+                &updater,
+                end + 900,
+                vec![sample(START_TIME_METRIC, &[], (start + 20 * 3600) as f64)],
+            ),
+        ];
+
+        Digest::new(
+            window,
+            vec![
+                CorridorTotals {
+                    origin: "ethereum".into(),
+                    destination: 1650811245,
+                    dispatched: 42,
+                    processed: 40,
+                },
+                CorridorTotals {
+                    origin: "moonbeam".into(),
+                    destination: 6648936,
+                    dispatched: 3,
+                    processed: 5,
+                },
+            ],
+            vec![ChainGas {
+                domain: 1650811245,
+                gas_used: 8_400_000,
+                transactions: 41,
+            }],
+            // Messages before 02:00 were indexed without dispatch times
+            vec![CoverageGap {
+                source: "ethereum dispatches".into(),
+                from: start,
+                to: start + 2 * 3600,
+            }],
+            &[processor, updater],
+            &snapshots,
+            300,
+        )
+    }
+
+    #[test]
+    fn it_renders_the_golden_digest() {
+        let rendered = fixture_digest().to_markdown();
+        let golden = std::fs::read_to_string(GOLDEN_DIGEST).unwrap();
+        assert_eq!(
+            rendered, golden,
+            "rendered digest differs from {}",
+            GOLDEN_DIGEST
+        );
+    }
+
+    #[test]
+    fn it_renders_json_digests() {
+        let json = fixture_digest().to_json();
+        assert_eq!(json["date"], "2023-03-14");
+        assert_eq!(json["corridors"][1]["backlogDelta"], -2);
+        assert_eq!(json["alerts"].as_array().unwrap().len(), 2);
+        assert_eq!(json["coverageGaps"].as_array().unwrap().len(), 3);
+        assert_eq!(json["coverageGaps"][2]["source"], "ethereum dispatches");
+    }
+
+    #[test]
+    fn it_computes_local_day_windows() {
+        let date: LocalDate = "2023-03-14".parse().unwrap();
+        assert_eq!(date.days(), 19430);
+        assert_eq!(LocalDate::from_days(19430), date);
+
+        let utc = Window::of(date, "Z".parse().unwrap());
+        assert_eq!((utc.start, utc.end), (1678752000, 1678838400));
+        let east = Window::of(date, "+05:30".parse().unwrap());
+        assert_eq!(east.start, 1678752000 - 5 * 3600 - 1800);
+        let west = Window::of(date, "-08".parse().unwrap());
+        assert_eq!(west.start, 1678752000 + 8 * 3600);
+        assert_eq!(west.format_time(west.start), "2023-03-14 00:00");
+        assert_eq!(west.next().date.to_string(), "2023-03-15");
+
+        // Just after local midnight, the previous day is reported
+        assert_eq!(
+            Window::previous(east.start + 60, east.offset),
+            Window::of("2023-03-13".parse().unwrap(), east.offset)
+        );
+
+        assert!("2023-02-29".parse::<LocalDate>().is_err());
+        assert!("2024-02-29".parse::<LocalDate>().is_ok());
+        assert!("+25:00".parse::<UtcOffset>().is_err());
+        assert!("02:00".parse::<UtcOffset>().is_err());
+    }
+
+    #[test]
+    fn it_parses_metric_snapshots() {
+        let text = r#"# HELP nomad_channel_faults Number of faults
+# TYPE nomad_channel_faults gauge
+nomad_channel_faults{VERSION="1.6.0",agent="processor",home="a \"b\"",replica="c,d"} 3
+nomad_start_time_seconds 1678752000
+malformed{ 1
+"#;
+        let snapshot = Snapshot::parse("http://agent", 10, text);
+        assert_eq!(snapshot.samples.len(), 2);
+        assert_eq!(snapshot.samples[0].labels["home"], "a \"b\"");
+        assert_eq!(snapshot.samples[0].labels["replica"], "c,d");
+        assert_eq!(snapshot.samples[0].value, 3.0);
+        assert_eq!(snapshot.start_time(), Some(1678752000));
+    }
+}
@@ -2,8 +2,11 @@ use color_eyre::Result;
 use structopt::StructOpt;
 
 mod commands;
+mod db;
+mod digest;
 mod replicas;
 mod rpc;
+mod signer;
 mod subcommands;
 
 use commands::Commands;
@@ -15,5 +18,27 @@ async fn main() -> Result<()> {
     match command {
         Commands::Prove(prove) => prove.run().await,
         Commands::DbState(db_state) => db_state.run().await,
+        Commands::DbSnapshot(db_snapshot) => db_snapshot.run().await,
+        Commands::DbRekey(db_rekey) => db_rekey.run().await,
+        Commands::DbVerify(db_verify) => db_verify.run().await,
+        Commands::ProcessedFailed(processed_failed) => processed_failed.run().await,
+        Commands::UpdaterHandoff(handoff) => handoff.run().await,
+        Commands::ComputeRoot(compute_root) => compute_root.run().await,
+        Commands::DecodeGovernance(decode) => decode.run().await,
+        Commands::Dispatch(dispatch) => dispatch.run().await,
+        Commands::TransferOwnership(transfer) => transfer.run().await,
+        Commands::RenounceOwnership(renounce) => renounce.run().await,
+        Commands::SimulateBacklog(simulate) => simulate.run().await,
+        Commands::SignFailureNotification(sign) => sign.run().await,
+        Commands::VerifyFailureNotification(verify) => verify.run().await,
+        Commands::Audit(audit) => audit.run().await,
+        Commands::ExportEvidence(export) => export.run().await,
+        Commands::VerifyEvidence(verify) => verify.run().await,
+        Commands::HeldMessages(held) => held.run().await,
+        Commands::Trace(trace) => trace.run().await,
+        Commands::Duplicates(duplicates) => duplicates.run().await,
+        Commands::RecipientGas(gas) => gas.run().await,
+        Commands::Report(report) => report.run().await,
+        Commands::Watch(watch) => watch.run().await,
     }
 }
@@ -4,6 +4,16 @@ const CELO: u32 = 1667591279;
 const ETH: u32 = 6648936;
 const POLY: u32 = 1886350457;
 
+/// Name of a known domain
+pub(crate) fn domain_name(domain: u32) -> Option<&'static str> {
+    match domain {
+        ETH => Some("ethereum"),
+        CELO => Some("celo"),
+        POLY => Some("polygon"),
+        _ => None,
+    }
+}
+
 pub(crate) fn address_by_domain_pair(origin: u32, destination: u32) -> Option<H160> {
     let addr = match (origin, destination) {
         (ETH, CELO) => "0xf25C5932bb6EFc7afA4895D9916F2abD7151BF97",
@@ -0,0 +1,74 @@
+use color_eyre::{
+    eyre::{bail, WrapErr},
+    Result,
+};
+use ethers_signers::{AwsSigner, LocalWallet};
+use nomad_ethereum::EthereumSigners;
+use once_cell::sync::OnceCell;
+use rusoto_core::{credential::EnvironmentProvider, HttpClient};
+use rusoto_kms::KmsClient;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+static KMS_CLIENT: OnceCell<KmsClient> = OnceCell::new();
+
+/// Signer flags shared by commands that submit transactions
+#[derive(StructOpt, Debug)]
+pub struct SignerArgs {
+    /// HexKey to use (please be careful)
+    #[structopt(long)]
+    key: Option<String>,
+
+    /// If using AWS signer, the key ID
+    #[structopt(long)]
+    key_id: Option<String>,
+
+    /// If using AWS signer, the region
+    #[structopt(long)]
+    aws_region: Option<String>,
+
+    /// Encrypted JSON keystore to sign with
+    #[structopt(long)]
+    keystore: Option<PathBuf>,
+
+    /// Environment variable holding the keystore password
+    #[structopt(long, default_value = "KEYSTORE_PASSWORD")]
+    keystore_password_env: String,
+}
+
+impl SignerArgs {
+    // mostly copied from nomad-base settings
+    pub(crate) async fn signer(&self) -> Result<EthereumSigners> {
+        if let Some(key) = &self.key {
+            Ok(EthereumSigners::Local(key.parse()?))
+        } else if let Some(keystore) = &self.keystore {
+            let password = std::env::var(&self.keystore_password_env).wrap_err_with(|| {
+                format!(
+                    "keystore password not set in {}",
+                    self.keystore_password_env
+                )
+            })?;
+            Ok(EthereumSigners::Local(LocalWallet::decrypt_keystore(
+                keystore, password,
+            )?))
+        } else {
+            match (&self.key_id, &self.aws_region) {
+                (Some(id), Some(region)) => {
+                    let client = KMS_CLIENT.get_or_init(|| {
+                        KmsClient::new_with_client(
+                            rusoto_core::Client::new_with(
+                                EnvironmentProvider::default(),
+                                HttpClient::new().unwrap(),
+                            ),
+                            region.parse().expect("invalid region"),
+                        )
+                    });
+                    let signer = AwsSigner::new(client, id, 0).await?;
+                    Ok(EthereumSigners::Aws(signer))
+                }
+
+                _ => bail!("missing signer information"),
+            }
+        }
+    }
+}
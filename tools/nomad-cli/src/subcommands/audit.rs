@@ -0,0 +1,60 @@
+use color_eyre::Result;
+use std::io::Write;
+use structopt::StructOpt;
+
+use nomad_base::{AuditId, NomadDB};
+use nomad_core::Encode;
+
+#[derive(StructOpt, Debug)]
+pub enum AuditCommand {
+    /// Print audit records as NDJSON, oldest first. Submission records carry
+    /// the payload of their transaction, if it was captured and not pruned
+    Export(AuditExportCommand),
+}
+
+impl AuditCommand {
+    pub async fn run(&self) -> Result<()> {
+        match self {
+            AuditCommand::Export(export) => export.run().await,
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct AuditExportCommand {
+    /// Path to agent db
+    #[structopt(long)]
+    db_path: String,
+
+    /// Entity the records were written under: the home name for the updater,
+    /// relayer and processor, `<home>_watcher` for the watcher
+    #[structopt(long)]
+    home_name: String,
+
+    /// Only export records written at or after this unix timestamp, in
+    /// seconds
+    #[structopt(long, default_value = "0")]
+    since: u64,
+}
+
+impl AuditExportCommand {
+    pub async fn run(&self) -> Result<()> {
+        let db = NomadDB::new(&self.home_name, crate::db::open(&self.db_path).await?);
+
+        let stdout = std::io::stdout();
+        let mut out = stdout.lock();
+        for record in db.audit_records(AuditId::since(self.since)) {
+            let record = record?;
+            match db.submission_payload(record.id)? {
+                Some(payload) => {
+                    let mut json = serde_json::to_value(&record)?;
+                    json["payload"] = payload.report();
+                    serde_json::to_writer(&mut out, &json)?;
+                }
+                None => out.write_all(&record.to_vec())?,
+            }
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
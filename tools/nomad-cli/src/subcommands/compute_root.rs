@@ -0,0 +1,295 @@
+use color_eyre::{
+    eyre::{bail, eyre, WrapErr},
+    Result,
+};
+use serde_json::Value;
+use std::{
+    collections::{HashSet, VecDeque},
+    convert::{TryFrom, TryInto},
+    fs::File,
+    io::{BufRead, BufReader},
+    sync::Arc,
+};
+use structopt::StructOpt;
+
+use nomad_core::{
+    accumulator::{Merkle, NomadLightMerkle},
+    parse_h256, CanonicalH256, CommittedMessage, CommonIndexer, ContractLocator, HomeIndexer,
+};
+use nomad_ethereum::EthereumHomeIndexer;
+
+use ethers::{
+    prelude::{Http, Provider, H160},
+    types::H256,
+};
+
+#[derive(StructOpt, Debug)]
+pub struct ComputeRootCommand {
+    /// File of leaves, one per line. Either hex leaves or NDJSON objects with
+    /// a `leaf` field, as exported by `processed-failed`
+    #[structopt(long)]
+    leaves: String,
+
+    /// File of leaf counts, one per line, at which to print the root. If
+    /// unset, only the root of all leaves is printed
+    #[structopt(long)]
+    boundaries: Option<String>,
+
+    /// RPC endpoint of the home chain. If set, roots and leaves are compared
+    /// against the home contract
+    #[structopt(long, requires = "home_address")]
+    rpc: Option<String>,
+
+    /// Home contract address
+    #[structopt(long)]
+    home_address: Option<H160>,
+
+    /// Block the home contract was deployed at
+    #[structopt(long, default_value = "0")]
+    from_block: u32,
+
+    /// Number of blocks to query logs for at once
+    #[structopt(long, default_value = "2000")]
+    page_size: u32,
+}
+
+impl ComputeRootCommand {
+    pub async fn run(&self) -> Result<()> {
+        let leaves = BufReader::new(
+            File::open(&self.leaves).wrap_err_with(|| format!("opening {}", self.leaves))?,
+        );
+        let boundaries = match &self.boundaries {
+            Some(path) => read_boundaries(BufReader::new(File::open(path)?))?,
+            None => vec![],
+        };
+
+        let mut chain = match &self.rpc {
+            Some(rpc) => Some(self.chain_home(rpc).await?),
+            None => None,
+        };
+        let committed_roots = match chain.as_ref() {
+            Some(chain) => Some(chain.committed_roots().await?),
+            None => None,
+        };
+
+        let mut tree = NomadLightMerkle::default();
+        let mut boundaries = boundaries.into_iter().peekable();
+        let mut first_divergent: Option<usize> = None;
+
+        for (index, line) in leaves.lines().enumerate() {
+            let leaf = parse_leaf(&line?, index)?;
+
+            if let (Some(chain), None) = (chain.as_mut(), first_divergent) {
+                if chain.next_leaf().await? != Some(leaf) {
+                    first_divergent = Some(index);
+                }
+            }
+
+            tree.ingest(leaf)?;
+
+            while boundaries.peek() == Some(&tree.count()) {
+                boundaries.next();
+                print_root(tree.count(), tree.root(), committed_roots.as_ref());
+            }
+        }
+
+        if let Some(count) = boundaries.next() {
+            bail!(
+                "Boundary {} is past the last leaf. File has {} leaves",
+                count,
+                tree.count()
+            );
+        }
+        if self.boundaries.is_none() {
+            print_root(tree.count(), tree.root(), committed_roots.as_ref());
+        }
+
+        if let Some(chain) = chain.as_mut() {
+            if first_divergent.is_none() && chain.next_leaf().await?.is_some() {
+                println!(
+                    "Home has dispatched more than the {} leaves in the file",
+                    tree.count()
+                );
+            }
+        }
+        match first_divergent {
+            Some(index) => bail!("First divergent leaf index: {}", index),
+            None if chain.is_some() => println!("All leaves match the home"),
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    async fn chain_home(&self, rpc: &str) -> Result<ChainHome> {
+        let provider = Arc::new(Provider::<Http>::try_from(rpc)?);
+        let address = self.home_address.expect("required by structopt");
+        let indexer = EthereumHomeIndexer::new(
+            provider,
+            &ContractLocator {
+                name: "".into(),
+                domain: 0,
+                address: address.into(),
+            },
+            None,
+        );
+        let tip = indexer.get_block_number().await?;
+
+        Ok(ChainHome {
+            indexer,
+            page_size: self.page_size.max(1),
+            from_block: self.from_block,
+            next_block: self.from_block,
+            tip,
+            next_index: 0,
+            buffered: VecDeque::new(),
+        })
+    }
+}
+
+fn print_root(count: usize, root: H256, committed_roots: Option<&HashSet<H256>>) {
+    match committed_roots {
+        Some(roots) if roots.contains(&root) => {
+            println!("{} {} committed", count, CanonicalH256(root))
+        }
+        Some(_) => println!("{} {} NOT committed", count, CanonicalH256(root)),
+        None => println!("{} {}", count, CanonicalH256(root)),
+    }
+}
+
+/// Parse a leaf from a line of hex or an NDJSON export entry. Entries with a
+/// `leafIndex` must be in order.
+fn parse_leaf(line: &str, index: usize) -> Result<H256> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return parse_h256(line).wrap_err_with(|| format!("invalid leaf at line {}", index));
+    }
+
+    let entry: Value = serde_json::from_str(line)?;
+    if let Some(leaf_index) = entry.get("leafIndex").and_then(Value::as_u64) {
+        if leaf_index != index as u64 {
+            bail!("Expected leaf index {}, found {}", index, leaf_index);
+        }
+    }
+    entry
+        .get("leaf")
+        .and_then(Value::as_str)
+        .ok_or_else(|| eyre!("missing leaf at line {}", index))
+        .and_then(|leaf| {
+            parse_h256(leaf).wrap_err_with(|| format!("invalid leaf at line {}", index))
+        })
+}
+
+/// Read ascending leaf counts, one per line
+fn read_boundaries<R: BufRead>(reader: R) -> Result<Vec<usize>> {
+    let mut boundaries: Vec<usize> = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let count = line.parse()?;
+        if boundaries.last().map_or(false, |last| *last >= count) {
+            bail!(
+                "Boundaries must be ascending. {} follows {:?}",
+                count,
+                boundaries.last()
+            );
+        }
+        boundaries.push(count);
+    }
+    Ok(boundaries)
+}
+
+/// Home contract leaves and committed roots, fetched a page of blocks at a
+/// time so only one page of messages is held in memory
+struct ChainHome {
+    indexer: EthereumHomeIndexer<Provider<Http>>,
+    page_size: u32,
+    from_block: u32,
+    next_block: u32,
+    tip: u32,
+    next_index: u32,
+    buffered: VecDeque<H256>,
+}
+
+impl ChainHome {
+    /// New roots of all updates on the home
+    async fn committed_roots(&self) -> Result<HashSet<H256>> {
+        let mut roots = HashSet::new();
+        let mut from = self.from_block;
+        while from <= self.tip {
+            let to = self.tip.min(from.saturating_add(self.page_size - 1));
+            for update in self.indexer.fetch_sorted_updates(from, to).await? {
+                roots.insert(update.signed_update.update.new_root);
+            }
+            from = to + 1;
+        }
+        Ok(roots)
+    }
+
+    /// Next dispatched leaf, or `None` once all leaves up to the tip are read
+    async fn next_leaf(&mut self) -> Result<Option<H256>> {
+        while self.buffered.is_empty() && self.next_block <= self.tip {
+            let to = self
+                .tip
+                .min(self.next_block.saturating_add(self.page_size - 1));
+            for raw in self
+                .indexer
+                .fetch_sorted_messages(self.next_block, to)
+                .await?
+            {
+                let message: CommittedMessage = raw.try_into()?;
+                if message.leaf_index != self.next_index {
+                    bail!(
+                        "Home is missing leaf index {}. Found {}",
+                        self.next_index,
+                        message.leaf_index
+                    );
+                }
+                self.next_index += 1;
+                self.buffered.push_back(message.to_leaf());
+            }
+            self.next_block = to + 1;
+        }
+        Ok(self.buffered.pop_front())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_parses_hex_and_export_leaves() {
+        let leaf = H256::repeat_byte(1);
+
+        assert_eq!(parse_leaf(&format!("{:?}", leaf), 0).unwrap(), leaf);
+        assert_eq!(
+            parse_leaf(&hex::encode(leaf).to_uppercase(), 0).unwrap(),
+            leaf
+        );
+        assert_eq!(
+            parse_leaf(&format!(r#"{{"leaf": "{:?}", "leafIndex": 3}}"#, leaf), 3).unwrap(),
+            leaf
+        );
+        assert!(parse_leaf(&format!(r#"{{"leaf": "{:?}", "leafIndex": 3}}"#, leaf), 4).is_err());
+        assert!(parse_leaf("0x1234", 0).is_err());
+    }
+
+    #[test]
+    fn it_streams_to_the_batch_root() {
+        let leaves: Vec<H256> = (0..100).map(H256::from_low_u64_be).collect();
+
+        let mut tree = NomadLightMerkle::default();
+        for leaf in leaves.iter() {
+            tree.ingest(*leaf).unwrap();
+        }
+        assert_eq!(tree.root(), NomadLightMerkle::from_leaves(&leaves).root());
+
+        assert_eq!(read_boundaries("1\n\n5\n".as_bytes()).unwrap(), vec![1, 5]);
+        assert!(read_boundaries("5\n5\n".as_bytes()).is_err());
+    }
+}
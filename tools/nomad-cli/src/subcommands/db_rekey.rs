@@ -0,0 +1,53 @@
+use color_eyre::{eyre::bail, Result};
+use serde_json::json;
+use structopt::StructOpt;
+
+use nomad_core::db::{DbCipher, DB};
+
+use crate::db::env_key;
+
+#[derive(StructOpt, Debug)]
+pub struct DbRekeyCommand {
+    /// Path to agent db. Stop the agent using it first
+    #[structopt(long)]
+    db_path: String,
+
+    /// Environment variable holding the key values are encrypted under now.
+    /// Leave it unset for a db that is not encrypted yet
+    #[structopt(long, default_value = "DB_ENCRYPTION_KEY")]
+    old_key_env: String,
+
+    /// Environment variable holding the key to encrypt values under
+    #[structopt(long, default_value = "NEW_DB_ENCRYPTION_KEY")]
+    new_key_env: String,
+
+    /// Values rewritten per write batch
+    #[structopt(long, default_value = "10000")]
+    batch_size: usize,
+}
+
+impl DbRekeyCommand {
+    pub async fn run(&self) -> Result<()> {
+        let new_key = match env_key(&self.new_key_env).await? {
+            Some(key) => key,
+            None => bail!("No new key in {}", self.new_key_env),
+        };
+        let mut cipher = DbCipher::new(&new_key);
+        if let Some(old_key) = env_key(&self.old_key_env).await? {
+            cipher = cipher.with_read_key(&old_key);
+        }
+        let key_id = cipher.key_id();
+
+        let db = DB::from_path(&self.db_path)?.with_cipher(cipher);
+        let rewritten = db.rekey(self.batch_size)?;
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({
+                "keyId": key_id.to_string(),
+                "rewritten": rewritten,
+            }))?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use color_eyre::{eyre::bail, Result};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+use structopt::StructOpt;
+
+use nomad_base::{NomadDB, TreeSnapshot};
+use nomad_core::CanonicalH256;
+
+#[derive(StructOpt, Debug)]
+pub struct DbSnapshotCommand {
+    /// Path to agent db
+    #[structopt(long)]
+    db_path: String,
+
+    /// Name of associated home
+    #[structopt(long)]
+    home_name: String,
+
+    /// File to write the snapshot to
+    #[structopt(long)]
+    output: String,
+}
+
+impl DbSnapshotCommand {
+    pub async fn run(&self) -> Result<()> {
+        let db = NomadDB::new(&self.home_name, crate::db::open(&self.db_path).await?);
+
+        let snapshot = match TreeSnapshot::from_db(&db)? {
+            Some(snapshot) => snapshot,
+            None => bail!("No updates for {} in db", self.home_name),
+        };
+        snapshot.verify_root()?;
+
+        let mut writer = BufWriter::new(File::create(&self.output)?);
+        snapshot.write_to(&mut writer)?;
+        writer.flush()?;
+
+        println!("Domain: {}", snapshot.domain);
+        println!("Block number: {}", snapshot.block_number);
+        println!("Message block number: {}", snapshot.message_block_number);
+        println!("Leaves: {}", snapshot.messages.len());
+        println!("Committed leaves: {}", snapshot.committed);
+        println!("Root: {}", CanonicalH256(snapshot.root));
+
+        Ok(())
+    }
+}
@@ -4,7 +4,7 @@ use std::{collections::HashMap, convert::TryInto, fs::OpenOptions, io::Write};
 use structopt::StructOpt;
 
 use nomad_base::NomadDB;
-use nomad_core::{db::DB, CommittedMessage};
+use nomad_core::{CanonicalH256, CommittedMessage};
 
 use ethers::types::H256;
 
@@ -27,7 +27,7 @@ type OutputVec = Vec<((H256, u64), Vec<CommittedMessage>)>;
 
 impl DbStateCommand {
     pub async fn run(&self) -> Result<()> {
-        let db = NomadDB::new(&self.home_name, DB::from_path(&self.db_path)?);
+        let db = NomadDB::new(&self.home_name, crate::db::open(&self.db_path).await?);
 
         let messages_by_committed_roots = self.create_comitted_root_to_message_map(&db)?;
 
@@ -114,7 +114,7 @@ impl DbStateCommand {
 
     fn print_output(output_vec: OutputVec) {
         for ((update_root, block_number), mut bucket) in output_vec {
-            println!("Update root: {:?}", update_root);
+            println!("Update root: {}", CanonicalH256(update_root));
             println!("Block number: {}", block_number);
 
             bucket.sort_by(|x, y| x.leaf_index.cmp(&y.leaf_index));
@@ -0,0 +1,59 @@
+use color_eyre::{eyre::bail, Result};
+use structopt::StructOpt;
+
+use nomad_base::NomadDB;
+
+#[derive(StructOpt, Debug)]
+pub struct DbVerifyCommand {
+    /// Path to agent db
+    #[structopt(long)]
+    db_path: String,
+
+    /// Name of associated home
+    #[structopt(long)]
+    home_name: String,
+
+    /// Quarantine proofs failing verification, for the processor to
+    /// regenerate
+    #[structopt(long)]
+    quarantine: bool,
+
+    /// Start over rather than resume an interrupted verification
+    #[structopt(long)]
+    restart: bool,
+
+    /// Stop after verifying this many leaves. A later run resumes from there
+    #[structopt(long)]
+    max_leaves: Option<u32>,
+}
+
+impl DbVerifyCommand {
+    pub async fn run(&self) -> Result<()> {
+        let db = NomadDB::new(&self.home_name, crate::db::open(&self.db_path).await?);
+        let report = db.verify(self.quarantine, self.restart, self.max_leaves)?;
+
+        for (violation, quarantined) in &report.violations {
+            let action = if *quarantined { " (quarantined)" } else { "" };
+            println!("{}{}", violation, action);
+        }
+        println!(
+            "Verified {} leaves from leaf index {}. {} violations, {} unresolved.",
+            report.leaves,
+            report.from,
+            report.violations.len(),
+            report.unresolved().count()
+        );
+        if report.is_partial() {
+            println!(
+                "Stopped before leaf index {}. Run again to resume.",
+                report.next
+            );
+        }
+
+        let unresolved = report.unresolved().count();
+        if unresolved > 0 {
+            bail!("{} violations left unresolved", unresolved);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use serde_json::Value;
+use std::fs::File;
+use structopt::StructOpt;
+
+use nomad_core::{
+    batch_hash, parse_h256, CanonicalH256, Decode, GovernanceCall, GovernanceMessage,
+};
+use nomad_ethereum::describe_governance_call;
+
+#[derive(StructOpt, Debug)]
+pub struct DecodeGovernanceCommand {
+    /// Hex body of a message to the governance router
+    #[structopt(long)]
+    body: String,
+
+    /// JSON file of the batch's calls, as `[{"to": "0x..", "data": "0x.."}]`.
+    /// Checked against the batch hash of the body
+    #[structopt(long)]
+    calls: Option<String>,
+}
+
+impl DecodeGovernanceCommand {
+    pub async fn run(&self) -> Result<()> {
+        let body = hex::decode(self.body.trim_start_matches("0x"))?;
+        let message = GovernanceMessage::read_from(&mut body.as_slice())?;
+
+        let calls = match (&message, &self.calls) {
+            (GovernanceMessage::TransferGovernor { domain, governor }, _) => {
+                println!(
+                    "Transfer governor to {} on domain {}",
+                    CanonicalH256(*governor),
+                    domain
+                );
+                return Ok(());
+            }
+            (GovernanceMessage::Batch(calls), _) => calls.clone(),
+            (GovernanceMessage::BatchHash(hash), Some(path)) => {
+                let calls = read_calls(path)?;
+                if batch_hash(&calls) != *hash {
+                    bail!(
+                        "Calls hash to {}, message commits to {}",
+                        CanonicalH256(batch_hash(&calls)),
+                        CanonicalH256(*hash)
+                    );
+                }
+                calls
+            }
+            (GovernanceMessage::BatchHash(hash), None) => {
+                println!("Batch hash: {}", CanonicalH256(*hash));
+                println!("Pass the batch's calls with --calls to decode them");
+                return Ok(());
+            }
+        };
+
+        println!("Batch hash: {}", CanonicalH256(batch_hash(&calls)));
+        for (i, call) in calls.iter().enumerate() {
+            println!("Call {} {}", i, describe_governance_call(call));
+        }
+
+        Ok(())
+    }
+}
+
+/// Read calls from a JSON array of `{"to", "data"}` objects
+fn read_calls(path: &str) -> Result<Vec<GovernanceCall>> {
+    let entries: Value = serde_json::from_reader(File::open(path)?)?;
+    entries
+        .as_array()
+        .ok_or_else(|| eyre!("{} is not a JSON array", path))?
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let field = |name: &str| {
+                entry
+                    .get(name)
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| eyre!("missing {} in call {}", name, i))
+            };
+            Ok(GovernanceCall {
+                to: parse_h256(field("to")?)?,
+                data: hex::decode(field("data")?.trim_start_matches("0x"))?,
+            })
+        })
+        .collect()
+}
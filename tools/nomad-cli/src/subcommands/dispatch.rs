@@ -0,0 +1,191 @@
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use std::{convert::TryFrom, sync::Arc};
+use structopt::StructOpt;
+
+use ethers::prelude::{
+    Http, Middleware, Provider, SignerMiddleware, TransactionRequest, H160, H256,
+};
+use ethers_signers::Signer;
+use nomad_core::{Address32, CanonicalH256, Message};
+use nomad_ethereum::{encode_dispatch_calldata, HomeState, SafeBatch, SafeTransaction};
+use nomad_ethereum_bindings::home::Home;
+
+use crate::signer::SignerArgs;
+
+#[derive(StructOpt, Debug)]
+pub struct DispatchCommand {
+    /// Home address
+    #[structopt(long)]
+    home: H160,
+
+    /// Destination domain
+    #[structopt(long)]
+    destination: u32,
+
+    /// Recipient, as an EVM address or a 32-byte value. EVM addresses are
+    /// left-padded to 32 bytes
+    #[structopt(long)]
+    recipient: Address32,
+
+    /// Message body as hex
+    #[structopt(long, parse(try_from_str = parse_body), default_value = "0x")]
+    body: Vec<u8>,
+
+    /// Print the calldata instead of sending the transaction
+    #[structopt(long)]
+    calldata_only: bool,
+
+    /// Print the call as a Safe transaction builder batch instead of sending
+    /// the transaction
+    #[structopt(long, conflicts_with = "calldata-only")]
+    safe_json: bool,
+
+    /// Chain id of the batch printed with --safe-json. Read from --rpc if
+    /// not given
+    #[structopt(long)]
+    chain_id: Option<u64>,
+
+    /// RPC connection details. Required unless printing calldata
+    #[structopt(long)]
+    rpc: Option<String>,
+
+    #[structopt(flatten)]
+    signer: SignerArgs,
+}
+
+impl DispatchCommand {
+    pub async fn run(&self) -> Result<()> {
+        let message = Message {
+            destination: self.destination,
+            recipient: checked_recipient(self.recipient),
+            body: self.body.clone(),
+        };
+        let calldata = encode_dispatch_calldata(&message);
+
+        if self.safe_json {
+            let chain_id = match (self.chain_id, &self.rpc) {
+                (Some(chain_id), _) => chain_id,
+                (None, Some(rpc)) => Provider::<Http>::try_from(rpc.as_str())?
+                    .get_chainid()
+                    .await?
+                    .low_u64(),
+                (None, None) => bail!("--safe-json needs --chain-id or --rpc"),
+            };
+            let batch = SafeBatch::new(
+                chain_id,
+                format!("Dispatch to domain {}", self.destination),
+                format!(
+                    "Dispatch to {} on domain {}",
+                    self.recipient, self.destination
+                ),
+                vec![SafeTransaction::new(self.home, &calldata)],
+            );
+            println!("{}", serde_json::to_string_pretty(&batch)?);
+            return Ok(());
+        }
+
+        println!("Recipient: {}", self.recipient);
+        if self.calldata_only {
+            println!("To:        {:?}", self.home);
+            println!("Calldata:  0x{}", hex::encode(&calldata));
+            return Ok(());
+        }
+
+        let rpc = self.rpc.as_deref().ok_or_else(|| {
+            eyre!("--rpc is required unless --calldata-only or --safe-json is set")
+        })?;
+        let provider = Provider::<Http>::try_from(rpc)?;
+        let home = Home::new(self.home, Arc::new(provider.clone()));
+        check_dispatchable(self.home, HomeState::try_from(home.state().call().await?)?)?;
+
+        let chain_id = provider.get_chainid().await?.low_u64();
+        let signer = self.signer.signer().await?.with_chain_id(chain_id);
+        let client = SignerMiddleware::new(provider, signer);
+
+        let tx = TransactionRequest::new().to(self.home).data(calldata);
+        let tx_hash = *client.send_transaction(tx, None).await?;
+        println!("Dispatched in {}", CanonicalH256(tx_hash));
+        Ok(())
+    }
+}
+
+/// Refuse to dispatch through a home that would revert the dispatch
+fn check_dispatchable(home: H160, state: HomeState) -> Result<()> {
+    match state {
+        HomeState::Active => Ok(()),
+        HomeState::Failed => bail!(
+            "home {:?} is in failed state and rejects every dispatch. Not sending",
+            home
+        ),
+        HomeState::Uninitialized => bail!(
+            "home {:?} is not initialized and cannot dispatch yet. Not sending",
+            home
+        ),
+    }
+}
+
+/// The recipient, warning if it looks like an EVM address padded on the
+/// wrong side
+fn checked_recipient(recipient: Address32) -> H256 {
+    if let Some(address) = recipient.right_padded_evm() {
+        eprintln!(
+            "Warning: recipient {} looks like {:?} padded on the wrong side. Its left-padded form is {}",
+            recipient,
+            address,
+            Address32::from_evm(address),
+        );
+    }
+    recipient.into()
+}
+
+fn parse_body(s: &str) -> Result<Vec<u8>> {
+    Ok(hex::decode(s.trim_start_matches("0x"))?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_normalizes_recipients() {
+        let address: H160 = "0x88A69B4E698A4B090DF6CF5Bd7B2D47325Ad30A3"
+            .parse()
+            .unwrap();
+        let padded = "0x00000000000000000000000088a69b4e698a4b090df6cf5bd7b2d47325ad30a3";
+
+        let from_address: Address32 = "0x88A69B4E698A4B090DF6CF5Bd7B2D47325Ad30A3"
+            .parse()
+            .unwrap();
+        let from_padded: Address32 = padded.parse().unwrap();
+        assert_eq!(checked_recipient(from_address), H256::from(address));
+        assert_eq!(checked_recipient(from_padded), H256::from(address));
+
+        // Wrong-side padding is kept as given
+        let right_padded: Address32 =
+            "0x88a69b4e698a4b090df6cf5bd7b2d47325ad30a3000000000000000000000000"
+                .parse()
+                .unwrap();
+        assert_ne!(checked_recipient(right_padded), H256::from(address));
+    }
+
+    #[test]
+    fn it_refuses_homes_that_cannot_dispatch() {
+        let home = H160::repeat_byte(1);
+        assert!(check_dispatchable(home, HomeState::Active).is_ok());
+
+        let failed = check_dispatchable(home, HomeState::Failed).unwrap_err();
+        assert!(failed.to_string().contains("failed state"));
+        assert!(check_dispatchable(home, HomeState::Uninitialized).is_err());
+    }
+
+    #[test]
+    fn it_parses_bodies() {
+        assert_eq!(parse_body("0x").unwrap(), Vec::<u8>::new());
+        assert_eq!(parse_body("0x0102").unwrap(), vec![1u8, 2]);
+        assert_eq!(parse_body("0102").unwrap(), vec![1u8, 2]);
+        assert!(parse_body("0x0g").is_err());
+    }
+}
@@ -0,0 +1,45 @@
+use color_eyre::Result;
+use serde_json::json;
+use structopt::StructOpt;
+
+use nomad_base::NomadDB;
+
+#[derive(StructOpt, Debug)]
+pub struct DuplicatesCommand {
+    /// Path to agent db
+    #[structopt(long)]
+    db_path: String,
+
+    /// Name of associated home
+    #[structopt(long)]
+    home_name: String,
+}
+
+impl DuplicatesCommand {
+    pub async fn run(&self) -> Result<()> {
+        let db = NomadDB::new(&self.home_name, crate::db::open(&self.db_path).await?);
+
+        let mut duplicates = vec![];
+        for entry in db.duplicate_leaves() {
+            let (leaf, _) = entry?;
+            let occurrences: Vec<_> = db
+                .leaf_occurrences(leaf)?
+                .into_iter()
+                .map(|occurrence| {
+                    json!({
+                        "leafIndex": occurrence.leaf_index,
+                        "fromBlock": occurrence.from_block,
+                        "toBlock": occurrence.to_block,
+                    })
+                })
+                .collect();
+            duplicates.push(json!({
+                "leaf": leaf,
+                "occurrences": occurrences,
+            }));
+        }
+
+        println!("{}", serde_json::to_string_pretty(&duplicates)?);
+        Ok(())
+    }
+}
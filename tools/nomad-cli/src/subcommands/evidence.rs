@@ -0,0 +1,231 @@
+use color_eyre::{
+    eyre::{bail, eyre, WrapErr},
+    Result,
+};
+use std::{collections::HashMap, convert::TryFrom, fs::File};
+use structopt::StructOpt;
+
+use ethers::prelude::{Address, Http, Middleware, Provider, TransactionRequest, H256, U64};
+use nomad_base::{AuditId, EvidenceBundle, Fraud, NomadDB};
+use nomad_ethereum::is_history_unavailable;
+
+#[derive(StructOpt, Debug)]
+pub struct ExportEvidenceCommand {
+    /// Path to the watcher db
+    #[structopt(long)]
+    db_path: String,
+
+    /// Entity the watcher wrote its records under, `<home>_watcher`
+    #[structopt(long)]
+    home_name: String,
+
+    /// Incident id. Lists the incidents if not set
+    #[structopt(long)]
+    incident: Option<u64>,
+}
+
+impl ExportEvidenceCommand {
+    pub async fn run(&self) -> Result<()> {
+        let db = NomadDB::new(&self.home_name, crate::db::open(&self.db_path).await?);
+
+        let id = match self.incident {
+            Some(id) => AuditId(id),
+            None => {
+                for incident in db.incidents() {
+                    let incident = incident?;
+                    let kind = match incident.fraud {
+                        Fraud::DoubleUpdate { .. } => "double update",
+                        Fraud::ImproperUpdate => "improper update",
+                    };
+                    println!(
+                        "{}: {} on home domain {}",
+                        incident.id.0, kind, incident.home_domain
+                    );
+                }
+                return Ok(());
+            }
+        };
+
+        let bundle = EvidenceBundle::export(&db, id)?
+            .ok_or_else(|| eyre!("No incident {} in {}", id.0, self.home_name))?;
+        println!("{}", serde_json::to_string_pretty(&bundle)?);
+        Ok(())
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct VerifyEvidenceCommand {
+    /// Bundle produced by `export-evidence`
+    bundle: String,
+
+    /// Public RPC of a domain, as `<domain>=<url>`. Repeat for each chain.
+    /// State reads and transactions on domains without one are skipped
+    #[structopt(long = "rpc", parse(try_from_str = parse_rpc))]
+    rpcs: Vec<(u32, String)>,
+}
+
+impl VerifyEvidenceCommand {
+    pub async fn run(&self) -> Result<()> {
+        let bundle: EvidenceBundle = serde_json::from_reader(
+            File::open(&self.bundle).wrap_err_with(|| format!("opening {}", self.bundle))?,
+        )
+        .wrap_err_with(|| format!("parsing {}", self.bundle))?;
+
+        bundle.verify_signatures()?;
+        println!(
+            "Signatures valid: failure notification by watcher {:?}",
+            bundle.failure_notification.watcher
+        );
+        if let Some(signer) = bundle.signer {
+            println!(
+                "Signatures valid: conflicting updates by updater {:?}",
+                signer
+            );
+        }
+
+        let rpcs: HashMap<u32, &str> = self
+            .rpcs
+            .iter()
+            .map(|(domain, url)| (*domain, url.as_str()))
+            .collect();
+        let mut failures = 0;
+        let mut unavailable = 0;
+        for contract in bundle.contracts.iter() {
+            let provider = match rpcs.get(&contract.domain) {
+                Some(url) => Provider::<Http>::try_from(*url)?,
+                None => {
+                    println!(
+                        "Skipped {}: no RPC for domain {}",
+                        contract.name, contract.domain
+                    );
+                    continue;
+                }
+            };
+            let address = match contract.address {
+                Some(address) => Address::from(address),
+                None => {
+                    println!("FAILED {}: no address in the bundle", contract.name);
+                    failures += 1;
+                    continue;
+                }
+            };
+
+            for read in bundle
+                .state_reads
+                .iter()
+                .filter(|read| read.contract == contract.name)
+            {
+                let tx = TransactionRequest::new()
+                    .to(address)
+                    .data(ethers::utils::id(&read.function).to_vec());
+                let check = format!(
+                    "{}.{} at block {}",
+                    contract.name,
+                    read.function,
+                    read.block_number
+                        .map_or_else(|| "latest".to_owned(), |n| n.to_string())
+                );
+                let returned = match provider
+                    .call(&tx.into(), read.block_number.map(|n| U64::from(n).into()))
+                    .await
+                {
+                    Ok(returned) => returned,
+                    // Non-archive nodes prune the state of old blocks
+                    Err(e) if is_history_unavailable(&e) => {
+                        println!(
+                            "Unavailable {}: state pruned on this provider, use an archive node",
+                            check
+                        );
+                        unavailable += 1;
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                match abi_word(&returned) {
+                    Some(value) if value == read.value => println!("Ok {}", check),
+                    found => {
+                        println!(
+                            "FAILED {}: expected {:?}, found {:?}",
+                            check, read.value, found
+                        );
+                        failures += 1;
+                    }
+                }
+            }
+
+            for tx in bundle
+                .transactions
+                .iter()
+                .filter(|tx| tx.contract == contract.name)
+            {
+                let receipt = provider.get_transaction_receipt(tx.txid).await?;
+                let succeeded = receipt.as_ref().and_then(|r| r.status) == Some(1.into());
+                let to_contract = receipt.as_ref().and_then(|r| r.to) == Some(address);
+                if succeeded && to_contract {
+                    println!("Ok {} transaction {:?}", contract.name, tx.txid);
+                } else {
+                    println!(
+                        "FAILED {} transaction {:?}: {}",
+                        contract.name,
+                        tx.txid,
+                        if receipt.is_none() {
+                            "not found"
+                        } else if !succeeded {
+                            "reverted"
+                        } else {
+                            "sent to another address"
+                        }
+                    );
+                    failures += 1;
+                }
+            }
+        }
+
+        if failures > 0 {
+            bail!("{} evidence checks failed", failures);
+        }
+        if unavailable > 0 {
+            println!(
+                "{} state reads unavailable on this provider and not verified",
+                unavailable
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Parse `<domain>=<url>`
+fn parse_rpc(s: &str) -> Result<(u32, String)> {
+    let (domain, url) = s
+        .split_once('=')
+        .ok_or_else(|| eyre!("Expected <domain>=<url>, got {}", s))?;
+    Ok((domain.trim().parse()?, url.trim().to_owned()))
+}
+
+/// The single ABI word a view function returned
+fn abi_word(returned: &[u8]) -> Option<H256> {
+    (returned.len() == 32).then(|| H256::from_slice(returned))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_parses_rpcs() {
+        assert_eq!(
+            parse_rpc("6648936=https://rpc.example").unwrap(),
+            (6648936, "https://rpc.example".to_owned())
+        );
+        assert!(parse_rpc("https://rpc.example").is_err());
+        assert!(parse_rpc("ethereum=https://rpc.example").is_err());
+    }
+
+    #[test]
+    fn it_reads_single_abi_words() {
+        let word = H256::from_low_u64_be(2);
+        assert_eq!(abi_word(word.as_bytes()), Some(word));
+        assert_eq!(abi_word(&[]), None);
+        assert_eq!(abi_word(&[0u8; 64]), None);
+    }
+}
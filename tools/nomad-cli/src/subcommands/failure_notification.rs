@@ -0,0 +1,289 @@
+use color_eyre::{
+    eyre::{bail, eyre, WrapErr},
+    Result,
+};
+use ethers::{
+    prelude::{Address, Signature, H160},
+    types::H256,
+};
+use serde_json::{json, Value};
+use std::{convert::TryFrom, fs::File};
+use structopt::StructOpt;
+
+use nomad_core::{
+    parse_h256, utils::home_domain_hash, Address32, CanonicalSignature, FailureNotification,
+    SignedFailureNotification,
+};
+
+use crate::signer::SignerArgs;
+
+#[derive(StructOpt, Debug)]
+pub struct SignFailureNotificationCommand {
+    /// Domain of the failed home
+    #[structopt(long)]
+    home_domain: u32,
+
+    /// Updater of the failed home, as an address or 32-byte identifier
+    #[structopt(long, parse(try_from_str = parse_updater))]
+    updater: H256,
+
+    #[structopt(flatten)]
+    signer: SignerArgs,
+}
+
+impl SignFailureNotificationCommand {
+    pub async fn run(&self) -> Result<()> {
+        let signer = self.signer.signer().await?;
+        let signed = FailureNotification {
+            home_domain: self.home_domain,
+            updater: self.updater.into(),
+        }
+        .sign_with(&signer)
+        .await?;
+
+        // Never hand out a signature the contracts would not recover to us
+        let signer = signed.recover()?;
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&artifact(&signed, signer))?
+        );
+        Ok(())
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct VerifyFailureNotificationCommand {
+    /// Artifact produced by `sign-failure-notification`
+    artifact: String,
+
+    /// Watcher address the signature must recover to
+    #[structopt(long)]
+    expected_signer: H160,
+}
+
+impl VerifyFailureNotificationCommand {
+    pub async fn run(&self) -> Result<()> {
+        let artifact: Value = serde_json::from_reader(
+            File::open(&self.artifact).wrap_err_with(|| format!("opening {}", self.artifact))?,
+        )
+        .wrap_err_with(|| format!("parsing {}", self.artifact))?;
+
+        let signer = verify_artifact(&artifact, self.expected_signer)?;
+        println!(
+            "Valid failure notification for home domain {} signed by {:?}",
+            artifact["homeDomain"], signer
+        );
+        Ok(())
+    }
+}
+
+/// Parse an updater given as an address or a 32-byte identifier
+fn parse_updater(s: &str) -> Result<H256> {
+    s.parse::<Address32>()
+        .map(Into::into)
+        .map_err(|_| eyre!("{} is not an address or 32-byte identifier", s))
+}
+
+/// JSON artifact for a signed failure notification. `digest` is what
+/// `unenrollReplica` hashes from its arguments, and `ethSignedDigest` what it
+/// recovers the signature against.
+fn artifact(signed: &SignedFailureNotification, signer: Address) -> Value {
+    let notification = &signed.notification;
+    json!({
+        "homeDomain": notification.home_domain,
+        "updater": H256::from(notification.updater),
+        "homeDomainHash": home_domain_hash(notification.home_domain),
+        "digest": notification.signing_hash(),
+        "ethSignedDigest": notification.prepended_hash(),
+        "signature": format!("0x{}", hex::encode(signed.signature.to_vec())),
+        "signer": signer,
+    })
+}
+
+/// Check an artifact's hashes against its notification and its signature
+/// against `expected`. Returns the recovered signer.
+fn verify_artifact(artifact: &Value, expected: Address) -> Result<Address> {
+    let field = |name: &str| {
+        artifact
+            .get(name)
+            .ok_or_else(|| eyre!("artifact is missing {}", name))
+    };
+    let hash = |name: &str| -> Result<H256> {
+        let value = field(name)?
+            .as_str()
+            .ok_or_else(|| eyre!("{} is not a string", name))?;
+        Ok(parse_h256(value)?)
+    };
+
+    let home_domain = field("homeDomain")?
+        .as_u64()
+        .and_then(|domain| u32::try_from(domain).ok())
+        .ok_or_else(|| eyre!("homeDomain is not a domain"))?;
+    let notification = FailureNotification {
+        home_domain,
+        updater: hash("updater")?.into(),
+    };
+
+    if hash("homeDomainHash")? != home_domain_hash(home_domain) {
+        bail!("homeDomainHash does not match homeDomain");
+    }
+    if hash("digest")? != notification.signing_hash() {
+        bail!("digest does not match homeDomain and updater");
+    }
+    if hash("ethSignedDigest")? != notification.prepended_hash() {
+        bail!("ethSignedDigest does not match digest");
+    }
+
+    let signature = field("signature")?
+        .as_str()
+        .ok_or_else(|| eyre!("signature is not a string"))?;
+    let signature = hex::decode(signature.trim_start_matches("0x"))?;
+    // Checked before normalizing: governance submits the artifact's bytes
+    if !CanonicalSignature::is_canonical(&signature) {
+        bail!("signature must be 65 bytes with v 27 or 28, as the contracts require");
+    }
+    let signed = SignedFailureNotification {
+        notification,
+        signature: CanonicalSignature::try_from(signature.as_slice())
+            .map_err(|e| eyre!("invalid signature: {}", e))?,
+    };
+
+    let recovered = signed.recover()?;
+    if let Some(signer) = field("signer")?.as_str() {
+        if signer.parse::<Address>().ok() != Some(recovered) {
+            bail!(
+                "signature recovers to {:?}, not to signer {}",
+                recovered,
+                signer
+            );
+        }
+    }
+    if recovered != expected {
+        bail!(
+            "signature recovers to {:?}, not to the expected signer {:?}",
+            recovered,
+            expected
+        );
+    }
+    Ok(recovered)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::{
+        signers::{LocalWallet, Signer},
+        types::U256,
+        utils::{hash_message, keccak256},
+    };
+
+    const KEY: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+
+    /// `XAppConnectionManager._recoverWatcherFromSig`, written against the
+    /// Solidity rather than the agents' digest code
+    fn solidity_recover(domain: u32, updater: H256, signature: &[u8]) -> Option<Address> {
+        // Replica.homeDomainHash: keccak256(abi.encodePacked(_homeDomain, "NOMAD"))
+        let home_domain_hash = keccak256([&domain.to_be_bytes()[..], b"NOMAD"].concat());
+        // keccak256(abi.encodePacked(_homeDomainHash, _domain, _updater))
+        let digest = keccak256(
+            [
+                &home_domain_hash[..],
+                &domain.to_be_bytes(),
+                updater.as_bytes(),
+            ]
+            .concat(),
+        );
+        // ECDSA.toEthSignedMessageHash, then ECDSA.recover
+        let digest = hash_message(digest);
+        if signature.len() != 65 || ![27, 28].contains(&signature[64]) {
+            return None;
+        }
+        let half_order = U256::from_str_radix(
+            "7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0",
+            16,
+        )
+        .unwrap();
+        if U256::from_big_endian(&signature[32..64]) > half_order {
+            return None;
+        }
+        Signature::try_from(signature).ok()?.recover(digest).ok()
+    }
+
+    async fn signed(home_domain: u32, updater: H256) -> (SignedFailureNotification, Address) {
+        let wallet: LocalWallet = KEY.parse().unwrap();
+        let signed = FailureNotification {
+            home_domain,
+            updater: updater.into(),
+        }
+        .sign_with(&wallet)
+        .await
+        .unwrap();
+        (signed, wallet.address())
+    }
+
+    #[tokio::test]
+    async fn it_matches_the_contract_recovery() {
+        let updater: H256 = Address::repeat_byte(7).into();
+        for home_domain in [1, 1000, 6648936, u32::MAX] {
+            let (signed, address) = signed(home_domain, updater).await;
+            assert_eq!(
+                solidity_recover(home_domain, updater, &signed.signature.to_vec()),
+                Some(address)
+            );
+
+            // Signed for another domain or updater, it recovers elsewhere
+            assert_ne!(
+                solidity_recover(home_domain ^ 1, updater, &signed.signature.to_vec()),
+                Some(address)
+            );
+            assert_ne!(
+                solidity_recover(
+                    home_domain,
+                    H256::repeat_byte(8),
+                    &signed.signature.to_vec()
+                ),
+                Some(address)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn it_verifies_artifacts() {
+        let updater = parse_updater("0x0707070707070707070707070707070707070707").unwrap();
+        let (signed, address) = signed(1000, updater).await;
+        let artifact = artifact(&signed, address);
+
+        assert_eq!(
+            artifact["digest"],
+            json!(H256::from(keccak256(
+                [
+                    &keccak256([&1000u32.to_be_bytes()[..], b"NOMAD"].concat())[..],
+                    &1000u32.to_be_bytes(),
+                    updater.as_bytes(),
+                ]
+                .concat()
+            )))
+        );
+        assert_eq!(verify_artifact(&artifact, address).unwrap(), address);
+
+        // Wrong counterparty expectation
+        assert!(verify_artifact(&artifact, Address::repeat_byte(1)).is_err());
+
+        // Tampered notification
+        let mut tampered = artifact.clone();
+        tampered["homeDomain"] = json!(1001);
+        assert!(verify_artifact(&tampered, address).is_err());
+
+        // Tampered signature
+        let mut tampered = artifact.clone();
+        let mut signature = Signature::from(signed.signature);
+        signature.v = 27 + 28 - signature.v;
+        tampered["signature"] = json!(format!("0x{}", hex::encode(signature.to_vec())));
+        assert!(verify_artifact(&tampered, address).is_err());
+
+        // Hashes are recomputed, not trusted
+        let mut tampered = artifact;
+        tampered["digest"] = json!(H256::repeat_byte(1));
+        assert!(verify_artifact(&tampered, address).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use color_eyre::{eyre::bail, Result};
+use structopt::StructOpt;
+
+use nomad_core::MessageRef;
+
+#[derive(StructOpt, Debug)]
+pub struct HeldMessagesCommand {
+    /// Admin endpoint of the processor
+    #[structopt(long, default_value = "http://localhost:9092")]
+    admin_url: String,
+
+    /// Token the processor's admin endpoint requires
+    #[structopt(long, env = "ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: String,
+
+    /// Held message to release, by leaf hash or id (`<origin>-<leaf index>`).
+    /// Lists the held messages if not set
+    #[structopt(long)]
+    release: Option<MessageRef>,
+}
+
+impl HeldMessagesCommand {
+    pub async fn run(&self) -> Result<()> {
+        let admin_url = self.admin_url.trim_end_matches('/');
+        let client = reqwest::Client::new();
+
+        let response = match &self.release {
+            Some(message) => {
+                client
+                    .post(&format!("{}/messages/{}/release", admin_url, message))
+                    .bearer_auth(&self.admin_token)
+                    .send()
+                    .await?
+            }
+            None => {
+                client
+                    .get(&format!("{}/messages/held", admin_url))
+                    .bearer_auth(&self.admin_token)
+                    .send()
+                    .await?
+            }
+        };
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            bail!(
+                "Processor at {} answered {}: {}",
+                self.admin_url,
+                status,
+                body
+            );
+        }
+
+        let body: serde_json::Value = serde_json::from_str(&body)?;
+        println!("{}", serde_json::to_string_pretty(&body)?);
+        if self.release.is_some() {
+            println!("Released. The processor processes the message on its next pass.");
+        }
+        Ok(())
+    }
+}
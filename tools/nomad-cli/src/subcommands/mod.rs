@@ -1,5 +1,43 @@
+pub mod audit;
+pub mod compute_root;
+pub mod db_rekey;
+pub mod db_snapshot;
 pub mod db_state;
+pub mod db_verify;
+pub mod decode_governance;
+pub mod dispatch;
+pub mod duplicates;
+pub mod evidence;
+pub mod failure_notification;
+pub mod held_messages;
+pub mod ownership;
+pub mod processed_failed;
 pub mod prove;
+pub mod recipient_gas;
+pub mod report;
+pub mod simulate_backlog;
+pub mod trace;
+pub mod updater_handoff;
+pub mod watch;
 
+pub use audit::*;
+pub use compute_root::*;
+pub use db_rekey::*;
+pub use db_snapshot::*;
 pub use db_state::*;
+pub use db_verify::*;
+pub use decode_governance::*;
+pub use dispatch::*;
+pub use duplicates::*;
+pub use evidence::*;
+pub use failure_notification::*;
+pub use held_messages::*;
+pub use ownership::*;
+pub use processed_failed::*;
 pub use prove::*;
+pub use recipient_gas::*;
+pub use report::*;
+pub use simulate_backlog::*;
+pub use trace::*;
+pub use updater_handoff::*;
+pub use watch::*;
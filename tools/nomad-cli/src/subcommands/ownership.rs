@@ -0,0 +1,426 @@
+use color_eyre::{eyre::bail, Result};
+use std::{
+    convert::TryFrom,
+    io::{BufRead, Write},
+    sync::Arc,
+};
+use structopt::StructOpt;
+
+use ethers::prelude::{Http, Middleware, Provider, SignerMiddleware, H160};
+use ethers_signers::Signer;
+use nomad_core::{CanonicalH256, ChecksumAddress};
+use nomad_ethereum::{EthereumSigners, OwnableContract, SafeBatch, SafeTransaction};
+
+use crate::{replicas, signer::SignerArgs};
+
+type ConcreteOwnable = OwnableContract<SignerMiddleware<Provider<Http>, EthereumSigners>>;
+
+/// Word that must be typed to confirm a renounce
+const RENOUNCE_WORD: &str = "RENOUNCE";
+
+#[derive(StructOpt, Debug)]
+pub struct OwnershipArgs {
+    /// Home, Replica or XAppConnectionManager address
+    #[structopt(long)]
+    address: H160,
+
+    /// RPC connection details
+    #[structopt(long)]
+    rpc: String,
+
+    /// Print the call as a Safe transaction builder batch for the owner
+    /// instead of sending it. Needs no signer
+    #[structopt(long)]
+    safe_json: bool,
+
+    #[structopt(flatten)]
+    signer: SignerArgs,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct TransferOwnershipCommand {
+    #[structopt(flatten)]
+    args: OwnershipArgs,
+
+    /// New owner
+    #[structopt(long)]
+    new_owner: H160,
+
+    /// Known governance addresses. The new owner must be one of them
+    #[structopt(long, env = "NOMAD_KNOWN_OWNERS", use_delimiter = true)]
+    known_owners: Vec<H160>,
+
+    /// Allow a new owner that is not in the known owners
+    #[structopt(long)]
+    allow_unknown_owner: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct RenounceOwnershipCommand {
+    #[structopt(flatten)]
+    args: OwnershipArgs,
+}
+
+/// Contract, chain and current owner shown before any ownership change
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OwnershipSummary {
+    contract: H160,
+    chain: String,
+    owner: H160,
+}
+
+impl std::fmt::Display for OwnershipSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Contract:      {}", ChecksumAddress(self.contract))?;
+        writeln!(f, "Chain:         {}", self.chain)?;
+        write!(f, "Current owner: {}", ChecksumAddress(self.owner))
+    }
+}
+
+impl OwnershipArgs {
+    async fn contract(&self) -> Result<(ConcreteOwnable, OwnershipSummary)> {
+        let provider = Provider::<Http>::try_from(self.rpc.as_str())?;
+        let chain_id = provider.get_chainid().await?.low_u64();
+        let signer = self.signer.signer().await?.with_chain_id(chain_id);
+        let signer_address = signer.address();
+        let contract = OwnableContract::new(
+            self.address,
+            Arc::new(SignerMiddleware::new(provider, signer)),
+        );
+
+        let summary = self.summary(&contract, chain_id).await?;
+        if summary.owner != signer_address {
+            bail!(
+                "Signer {} is not the owner {}",
+                ChecksumAddress(signer_address),
+                ChecksumAddress(summary.owner)
+            );
+        }
+        Ok((contract, summary))
+    }
+
+    /// Contract without a signer, for exporting calls for the owner, with
+    /// its chain id
+    async fn read_only_contract(
+        &self,
+    ) -> Result<(OwnableContract<Provider<Http>>, OwnershipSummary, u64)> {
+        let provider = Provider::<Http>::try_from(self.rpc.as_str())?;
+        let chain_id = provider.get_chainid().await?.low_u64();
+        let contract = OwnableContract::new(self.address, Arc::new(provider));
+
+        let summary = self.summary(&contract, chain_id).await?;
+        Ok((contract, summary, chain_id))
+    }
+
+    async fn summary<M: Middleware + 'static>(
+        &self,
+        contract: &OwnableContract<M>,
+        chain_id: u64,
+    ) -> Result<OwnershipSummary> {
+        let owner = contract.owner().await?;
+        if owner.is_zero() {
+            bail!(
+                "{} has no owner. Ownership was already renounced",
+                ChecksumAddress(self.address)
+            );
+        }
+
+        Ok(OwnershipSummary {
+            contract: self.address,
+            chain: chain_label(contract.local_domain().await.ok(), chain_id),
+            owner,
+        })
+    }
+}
+
+impl TransferOwnershipCommand {
+    pub async fn run(&self) -> Result<()> {
+        if self.args.safe_json {
+            let (contract, summary, chain_id) = self.args.read_only_contract().await?;
+            check_transfer_target(
+                summary.owner,
+                self.new_owner,
+                &self.known_owners,
+                self.allow_unknown_owner,
+            )?;
+
+            eprintln!("{}", summary);
+            eprintln!("New owner:     {}", ChecksumAddress(self.new_owner));
+            let batch = safe_batch(
+                &summary,
+                chain_id,
+                "Transfer ownership",
+                format!("to {}", ChecksumAddress(self.new_owner)),
+                &contract.transfer_ownership_calldata(self.new_owner),
+            );
+            println!("{}", serde_json::to_string_pretty(&batch)?);
+            return Ok(());
+        }
+
+        let (contract, summary) = self.args.contract().await?;
+        check_transfer_target(
+            summary.owner,
+            self.new_owner,
+            &self.known_owners,
+            self.allow_unknown_owner,
+        )?;
+
+        println!("{}", summary);
+        println!("New owner:     {}", ChecksumAddress(self.new_owner));
+        confirm_transfer(std::io::stdin().lock(), std::io::stdout())?;
+
+        let tx_hash = contract.transfer_ownership(self.new_owner).await?;
+        println!("Ownership transferred in {}", CanonicalH256(tx_hash));
+        Ok(())
+    }
+}
+
+impl RenounceOwnershipCommand {
+    pub async fn run(&self) -> Result<()> {
+        if self.args.safe_json {
+            let (contract, summary, chain_id) = self.args.read_only_contract().await?;
+
+            eprintln!("{}", summary);
+            eprintln!("Renouncing ownership is irreversible. The contract will have no owner.");
+            let batch = safe_batch(
+                &summary,
+                chain_id,
+                "Renounce ownership",
+                "irreversibly, leaving no owner".to_owned(),
+                &contract.renounce_ownership_calldata(),
+            );
+            println!("{}", serde_json::to_string_pretty(&batch)?);
+            return Ok(());
+        }
+
+        let (contract, summary) = self.args.contract().await?;
+
+        println!("{}", summary);
+        println!("Renouncing ownership is irreversible. The contract will have no owner.");
+        confirm_renounce(std::io::stdin().lock(), std::io::stdout(), summary.contract)?;
+
+        let tx_hash = contract.renounce_ownership().await?;
+        println!("Ownership renounced in {}", CanonicalH256(tx_hash));
+        Ok(())
+    }
+}
+
+/// Safe batch of an ownership call on the summarized contract, for its
+/// current owner to execute
+fn safe_batch(
+    summary: &OwnershipSummary,
+    chain_id: u64,
+    action: &str,
+    detail: String,
+    calldata: &[u8],
+) -> SafeBatch {
+    SafeBatch::new(
+        chain_id,
+        action,
+        format!(
+            "{} of {} on {} {}",
+            action,
+            ChecksumAddress(summary.contract),
+            summary.chain,
+            detail
+        ),
+        vec![SafeTransaction::new(summary.contract, calldata)],
+    )
+    .for_safe(summary.owner)
+}
+
+/// Chain name from the domain registry, with the domain and chain id
+fn chain_label(domain: Option<u32>, chain_id: u64) -> String {
+    match domain {
+        Some(domain) => match replicas::domain_name(domain) {
+            Some(name) => format!("{} (domain {}, chain id {})", name, domain, chain_id),
+            None => format!("unknown domain {} (chain id {})", domain, chain_id),
+        },
+        None => format!("unknown domain (chain id {})", chain_id),
+    }
+}
+
+/// Refuse transfers to the zero address, to the current owner, and to
+/// addresses outside `known_owners` unless `allow_unknown` is set
+fn check_transfer_target(
+    owner: H160,
+    new_owner: H160,
+    known_owners: &[H160],
+    allow_unknown: bool,
+) -> Result<()> {
+    if new_owner.is_zero() {
+        bail!("Refusing to transfer ownership to the zero address. Use renounce-ownership");
+    }
+    if new_owner == owner {
+        bail!("{} is already the owner", ChecksumAddress(new_owner));
+    }
+    if !allow_unknown && !known_owners.contains(&new_owner) {
+        bail!(
+            "{} is not a known governance address. Add it to --known-owners or pass --allow-unknown-owner",
+            ChecksumAddress(new_owner)
+        );
+    }
+    Ok(())
+}
+
+fn prompt<R: BufRead, W: Write>(input: &mut R, output: &mut W, message: &str) -> Result<String> {
+    write!(output, "{}: ", message)?;
+    output.flush()?;
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    Ok(line.trim().to_owned())
+}
+
+/// Require `yes` before a transfer
+fn confirm_transfer<R: BufRead, W: Write>(mut input: R, mut output: W) -> Result<()> {
+    if prompt(&mut input, &mut output, "Type yes to transfer ownership")? != "yes" {
+        bail!("Transfer not confirmed");
+    }
+    Ok(())
+}
+
+/// Require the full contract address and the word RENOUNCE before a
+/// renounce
+fn confirm_renounce<R: BufRead, W: Write>(
+    mut input: R,
+    mut output: W,
+    contract: H160,
+) -> Result<()> {
+    let typed_address = prompt(&mut input, &mut output, "Type the full contract address")?;
+    if typed_address.parse::<H160>().ok() != Some(contract) {
+        bail!("Typed address does not match the contract. Renounce not confirmed");
+    }
+
+    let typed_word = prompt(
+        &mut input,
+        &mut output,
+        &format!("Type {} to renounce ownership", RENOUNCE_WORD),
+    )?;
+    if typed_word != RENOUNCE_WORD {
+        bail!("Renounce not confirmed");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn addr(byte: u8) -> H160 {
+        H160::repeat_byte(byte)
+    }
+
+    #[test]
+    fn it_refuses_unknown_owners_by_default() {
+        let owner = addr(1);
+        let known = [addr(2)];
+
+        assert!(check_transfer_target(owner, addr(2), &known, false).is_ok());
+        assert!(check_transfer_target(owner, addr(3), &known, false).is_err());
+        assert!(check_transfer_target(owner, addr(3), &[], false).is_err());
+        assert!(check_transfer_target(owner, addr(3), &known, true).is_ok());
+
+        // never to zero or to the current owner, even when allowed
+        assert!(check_transfer_target(owner, H160::zero(), &[H160::zero()], true).is_err());
+        assert!(check_transfer_target(owner, owner, &[owner], true).is_err());
+    }
+
+    #[test]
+    fn it_requires_typed_confirmation() {
+        assert!(confirm_transfer("yes\n".as_bytes(), vec![]).is_ok());
+        assert!(confirm_transfer("y\n".as_bytes(), vec![]).is_err());
+        assert!(confirm_transfer("".as_bytes(), vec![]).is_err());
+
+        let contract: H160 = "0xf25C5932bb6EFc7afA4895D9916F2abD7151BF97"
+            .parse()
+            .unwrap();
+        let full = "0xf25C5932bb6EFc7afA4895D9916F2abD7151BF97";
+
+        let confirm = |input: String| confirm_renounce(input.as_bytes(), vec![], contract);
+        assert!(confirm(format!("{}\nRENOUNCE\n", full)).is_ok());
+        assert!(confirm(format!("{}\nRENOUNCE\n", full.to_lowercase())).is_ok());
+
+        // the default answer and partial input never renounce
+        assert!(confirm("".to_owned()).is_err());
+        assert!(confirm("\n\n".to_owned()).is_err());
+        assert!(confirm(format!("{}\nrenounce\n", full)).is_err());
+        assert!(confirm(format!("{}\nyes\n", full)).is_err());
+        assert!(confirm(format!("{}\n", full)).is_err());
+        assert!(confirm("0xf25C5932\nRENOUNCE\n".to_owned()).is_err());
+        assert!(confirm(format!("{:?}\nRENOUNCE\n", addr(1))).is_err());
+    }
+
+    #[test]
+    fn it_exports_ownership_calls_for_the_owner() {
+        let provider = Provider::<Http>::try_from("http://localhost:8545").unwrap();
+        let contract = OwnableContract::new(addr(1), Arc::new(provider));
+        let summary = OwnershipSummary {
+            contract: addr(1),
+            chain: chain_label(Some(6648936), 1),
+            owner: addr(2),
+        };
+
+        let batch = safe_batch(
+            &summary,
+            1,
+            "Transfer ownership",
+            format!("to {}", ChecksumAddress(addr(3))),
+            &contract.transfer_ownership_calldata(addr(3)),
+        );
+        assert_eq!(batch.chain_id, "1");
+        assert_eq!(
+            batch
+                .meta
+                .created_from_safe_address
+                .parse::<H160>()
+                .unwrap(),
+            addr(2)
+        );
+        assert!(batch
+            .meta
+            .description
+            .contains("ethereum (domain 6648936, chain id 1)"));
+
+        let tx = &batch.transactions[0];
+        assert_eq!(tx.to.parse::<H160>().unwrap(), addr(1));
+        assert_eq!(
+            tx.contract_method.as_ref().unwrap().name,
+            "transferOwnership"
+        );
+        assert_eq!(
+            tx.contract_inputs_values.as_ref().unwrap()["newOwner"]
+                .parse::<H160>()
+                .unwrap(),
+            addr(3)
+        );
+
+        let renounce = safe_batch(
+            &summary,
+            1,
+            "Renounce ownership",
+            String::new(),
+            &contract.renounce_ownership_calldata(),
+        );
+        let tx = &renounce.transactions[0];
+        assert_eq!(
+            tx.contract_method.as_ref().unwrap().name,
+            "renounceOwnership"
+        );
+        assert!(tx.contract_inputs_values.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_names_the_chain_in_the_confirmation() {
+        let summary = OwnershipSummary {
+            contract: addr(1),
+            chain: chain_label(Some(6648936), 1),
+            owner: addr(2),
+        };
+        let shown = summary.to_string();
+        assert!(shown.contains("ethereum (domain 6648936, chain id 1)"));
+        assert!(shown.contains(&ChecksumAddress(addr(2)).to_string()));
+
+        assert_eq!(chain_label(Some(5), 5), "unknown domain 5 (chain id 5)");
+        assert_eq!(chain_label(None, 5), "unknown domain (chain id 5)");
+    }
+}
@@ -0,0 +1,123 @@
+use color_eyre::Result;
+use serde_json::{json, Value};
+use std::convert::TryInto;
+use structopt::StructOpt;
+
+use nomad_base::NomadDB;
+use nomad_core::CommittedMessage;
+
+use ethers::types::{H256, U256};
+
+/// Length of a bridge message token id: 4 byte domain + 32 byte id
+const TOKEN_ID_LEN: usize = 36;
+/// Length of a bridge transfer action: type + recipient + amount + details hash
+const TRANSFER_LEN: usize = 97;
+/// Bridge message action types for transfers
+const TRANSFER_TYPES: [u8; 2] = [3, 4];
+
+#[derive(StructOpt, Debug)]
+pub struct ProcessedFailedCommand {
+    /// Path to processor db
+    #[structopt(long)]
+    db_path: String,
+
+    /// Name of associated home
+    #[structopt(long)]
+    home_name: String,
+
+    /// Only export messages to this destination domain
+    #[structopt(long)]
+    destination: Option<u32>,
+}
+
+impl ProcessedFailedCommand {
+    pub async fn run(&self) -> Result<()> {
+        let db = NomadDB::new(&self.home_name, crate::db::open(&self.db_path).await?);
+
+        let mut entries: Vec<Value> = Vec::new();
+        for index in 0.. {
+            let message: CommittedMessage = match db.message_by_leaf_index(index)? {
+                Some(message) => message.try_into()?,
+                None => break,
+            };
+
+            if let Some(destination) = self.destination {
+                if message.message.destination != destination {
+                    continue;
+                }
+            }
+
+            if db.processed_failed(message.to_leaf())? {
+                entries.push(ProcessedFailedCommand::entry(&message));
+            }
+        }
+
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        Ok(())
+    }
+
+    fn entry(message: &CommittedMessage) -> Value {
+        json!({
+            "leaf": message.to_leaf(),
+            "leafIndex": message.leaf_index,
+            "origin": message.message.origin,
+            "sender": message.message.sender,
+            "nonce": message.message.nonce,
+            "destination": message.message.destination,
+            "recipient": message.message.recipient,
+            "body": format!("0x{}", hex::encode(&message.message.body)),
+            "bridgeMessage": decode_bridge_transfer(&message.message.body),
+        })
+    }
+}
+
+/// Decode a bridge router transfer message. Returns `null` for bodies that
+/// are not token transfers.
+fn decode_bridge_transfer(body: &[u8]) -> Value {
+    if body.len() != TOKEN_ID_LEN + TRANSFER_LEN {
+        return Value::Null;
+    }
+
+    let (token, action) = body.split_at(TOKEN_ID_LEN);
+    if !TRANSFER_TYPES.contains(&action[0]) {
+        return Value::Null;
+    }
+
+    let token_domain = u32::from_be_bytes(token[..4].try_into().unwrap());
+    json!({
+        "tokenDomain": token_domain,
+        "tokenId": H256::from_slice(&token[4..]),
+        "recipient": H256::from_slice(&action[1..33]),
+        "amount": U256::from_big_endian(&action[33..65]).to_string(),
+        "detailsHash": H256::from_slice(&action[65..97]),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_decodes_bridge_transfers() {
+        let mut body = vec![];
+        body.extend(1000u32.to_be_bytes());
+        body.extend(H256::repeat_byte(1).as_bytes());
+        body.push(3);
+        body.extend(H256::repeat_byte(2).as_bytes());
+        body.extend(H256::from_low_u64_be(500).as_bytes());
+        body.extend(H256::repeat_byte(3).as_bytes());
+
+        let decoded = decode_bridge_transfer(&body);
+        assert_eq!(decoded["tokenDomain"], 1000);
+        assert_eq!(decoded["amount"], "500");
+        assert_eq!(decoded["recipient"], json!(H256::repeat_byte(2)));
+
+        // Not a transfer
+        body[TOKEN_ID_LEN] = 1;
+        assert!(decode_bridge_transfer(&body).is_null());
+        assert!(decode_bridge_transfer(&body[1..]).is_null());
+
+        // Empty pings are not transfers
+        assert!(decode_bridge_transfer(&[]).is_null());
+    }
+}
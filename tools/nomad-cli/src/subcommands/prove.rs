@@ -1,28 +1,20 @@
 use std::{convert::TryFrom, sync::Arc};
 use structopt::StructOpt;
 
-use crate::{replicas, rpc};
+use crate::{replicas, rpc, signer::SignerArgs};
 
 use nomad_core::{
-    accumulator::NomadProof, db::DB, ContractLocator, Decode, MessageStatus, NomadMessage, Replica,
+    accumulator::NomadProof, ContractLocator, Decode, MessageRef, MessageStatus, NomadMessage,
+    Replica,
 };
 
 use nomad_base::NomadDB;
 use nomad_ethereum::{EthereumReplica, EthereumSigners, TxSubmitter};
 
-use ethers::{
-    prelude::{Http, Middleware, Provider, SignerMiddleware, H160},
-    types::H256,
-};
+use ethers::prelude::{Http, Middleware, Provider, SignerMiddleware, H160};
 
 use color_eyre::{eyre::bail, Result};
-use ethers_signers::{AwsSigner, Signer};
-
-use once_cell::sync::OnceCell;
-use rusoto_core::{credential::EnvironmentProvider, HttpClient};
-use rusoto_kms::KmsClient;
-
-static KMS_CLIENT: OnceCell<KmsClient> = OnceCell::new();
+use ethers_signers::Signer;
 
 type ConcreteReplica = EthereumReplica<
     SignerMiddleware<Provider<Http>, EthereumSigners>,
@@ -31,12 +23,12 @@ type ConcreteReplica = EthereumReplica<
 
 #[derive(StructOpt, Debug)]
 pub struct ProveCommand {
-    /// Leaf to prove
-    #[structopt(long, required_unless = "leaf_index")]
-    leaf: Option<H256>,
+    /// Message to prove, by leaf hash or id (`<origin>-<leaf index>`)
+    #[structopt(long, alias = "leaf", required_unless = "leaf_index")]
+    message: Option<MessageRef>,
 
     /// Leaf index to prove
-    #[structopt(long, required_unless = "leaf")]
+    #[structopt(long, required_unless = "message")]
     leaf_index: Option<u32>,
 
     /// The name of the home chain, used to lookup keys in the db
@@ -47,17 +39,8 @@ pub struct ProveCommand {
     #[structopt(long)]
     db_path: String,
 
-    /// HexKey to use (please be careful)
-    #[structopt(long)]
-    key: Option<String>,
-
-    /// If using AWS signer, the key ID
-    #[structopt(long)]
-    key_id: Option<String>,
-
-    /// If using AWS signer, the region
-    #[structopt(long)]
-    aws_region: Option<String>,
+    #[structopt(flatten)]
+    signer: SignerArgs,
 
     /// replica contract address
     #[structopt(long)]
@@ -70,7 +53,7 @@ pub struct ProveCommand {
 
 impl ProveCommand {
     pub async fn run(&self) -> Result<()> {
-        let db = NomadDB::new(&self.home_name, DB::from_path(&self.db_path)?);
+        let db = NomadDB::new(&self.home_name, crate::db::open(&self.db_path).await?);
         let (message, proof) = self.fetch_proof(db)?;
         let replica = self.replica(message.origin, message.destination).await?;
 
@@ -88,43 +71,24 @@ impl ProveCommand {
         Ok(())
     }
 
-    // mostly copied from nomad-base settings
-    async fn signer(&self) -> Result<EthereumSigners> {
-        if let Some(key) = &self.key {
-            Ok(EthereumSigners::Local(key.parse()?))
-        } else {
-            match (&self.key_id, &self.aws_region) {
-                (Some(id), Some(region)) => {
-                    let client = KMS_CLIENT.get_or_init(|| {
-                        KmsClient::new_with_client(
-                            rusoto_core::Client::new_with(
-                                EnvironmentProvider::default(),
-                                HttpClient::new().unwrap(),
-                            ),
-                            region.parse().expect("invalid region"),
-                        )
-                    });
-                    let signer = AwsSigner::new(client, id, 0).await?;
-                    Ok(EthereumSigners::Aws(signer))
-                }
-
-                _ => bail!("missing signer information"),
-            }
-        }
-    }
-
     fn fetch_proof(&self, db: NomadDB) -> Result<(NomadMessage, NomadProof)> {
-        let idx = match (self.leaf_index, self.leaf) {
+        let idx = match (self.leaf_index, self.message) {
             (Some(idx), _) => idx,
-            (None, Some(digest)) => match db.message_by_leaf(digest)? {
-                Some(leaf) => leaf.leaf_index,
-                None => bail!("No leaf index or "),
+            (None, Some(message)) => match db.message_by_ref(message)? {
+                Some(found) => found.leaf_index,
+                None => bail!("No message {} in db", message),
             },
-            (None, None) => bail!("Must provide leaf index or leaf hash"),
+            (None, None) => bail!("Must provide leaf index or message"),
         };
 
-        let proof = db.proof_by_leaf_index(idx)?.expect("no proof");
-        let message = db.message_by_leaf_index(idx)?.expect("no message");
+        let proof = match db.proof_by_leaf_index(idx)? {
+            Some(proof) => proof,
+            None => bail!("No proof for leaf index {}", idx),
+        };
+        let message = match db.message_by_leaf_index(idx)? {
+            Some(message) => message,
+            None => bail!("No message for leaf index {}", idx),
+        };
         let message = NomadMessage::read_from(&mut message.message.as_slice())?;
 
         Ok((message, proof))
@@ -141,7 +105,11 @@ impl ProveCommand {
             .unwrap_or_else(|| rpc::fetch_rpc_connection(destination).unwrap());
 
         let chain_id = provider.get_chainid().await?;
-        let signer = self.signer().await?.with_chain_id(chain_id.low_u64());
+        let signer = self
+            .signer
+            .signer()
+            .await?
+            .with_chain_id(chain_id.low_u64());
         let middleware = Arc::new(SignerMiddleware::new(provider, signer));
 
         // bit ugly. Tries passed-in address first, then defaults to lookup by
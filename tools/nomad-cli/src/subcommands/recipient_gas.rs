@@ -0,0 +1,52 @@
+use color_eyre::Result;
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+use structopt::StructOpt;
+
+use nomad_base::NomadDB;
+
+#[derive(StructOpt, Debug)]
+pub struct RecipientGasCommand {
+    /// Path to processor db
+    #[structopt(long)]
+    db_path: String,
+
+    /// Name of associated home
+    #[structopt(long)]
+    home_name: String,
+
+    /// Days of totals to sum, the current day included
+    #[structopt(long, default_value = "7")]
+    days: u64,
+
+    /// Number of recipients to list
+    #[structopt(long, default_value = "20")]
+    top: usize,
+}
+
+impl RecipientGasCommand {
+    pub async fn run(&self) -> Result<()> {
+        let db = NomadDB::new(&self.home_name, crate::db::open(&self.db_path).await?);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let since = now.saturating_sub(self.days.saturating_sub(1) * 24 * 60 * 60);
+        let recipients: Vec<_> = db
+            .recipient_gas_since(since)?
+            .into_iter()
+            .take(self.top)
+            .map(|(destination, recipient, totals)| {
+                json!({
+                    "destination": destination,
+                    "recipient": recipient,
+                    "gasUsed": totals.gas_used,
+                    "transactions": totals.transactions,
+                    "messages": totals.messages,
+                    "averageGasPerMessage": totals.average_per_message(),
+                })
+            })
+            .collect();
+
+        println!("{}", serde_json::to_string_pretty(&recipients)?);
+        Ok(())
+    }
+}
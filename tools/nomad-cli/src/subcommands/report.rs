@@ -0,0 +1,264 @@
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use serde_json::json;
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use structopt::StructOpt;
+use tokio::time::sleep;
+
+use nomad_base::NomadDB;
+use nomad_core::NomadMessage;
+
+use crate::digest::{
+    ChainGas, CorridorTotals, CoverageGap, Digest, LocalDate, Snapshot, UtcOffset, Window,
+};
+
+#[derive(StructOpt, Debug)]
+pub struct ReportCommand {
+    /// Processor db of a home, as `home_name=db_path`. Repeat for each home.
+    #[structopt(long = "db", required = true)]
+    dbs: Vec<String>,
+
+    /// Metrics endpoint of an agent to snapshot. Repeat for each agent.
+    #[structopt(long = "metrics-url")]
+    metrics_urls: Vec<String>,
+
+    /// Offset from UTC of the timezone days are reported in, e.g. `+02:00`
+    #[structopt(long, default_value = "+00:00")]
+    utc_offset: UtcOffset,
+
+    /// Local date to report, as `YYYY-MM-DD`. Defaults to the previous day.
+    #[structopt(long)]
+    date: Option<LocalDate>,
+
+    /// Directory to write `digest-<date>.md` and `digest-<date>.json` to
+    #[structopt(long)]
+    out_dir: Option<PathBuf>,
+
+    /// Webhook to post the digest to, e.g. of the operators' Slack channel
+    #[structopt(long)]
+    webhook_url: Option<String>,
+
+    /// Keep running, snapshotting metrics and reporting each day once it
+    /// has ended
+    #[structopt(long)]
+    daily: bool,
+
+    /// Seconds between metrics snapshots when running daily
+    #[structopt(long, default_value = "300")]
+    sample_interval: u64,
+
+    /// Seconds to wait after a day ends before reporting it, for agents to
+    /// index the day's last messages
+    #[structopt(long, default_value = "900")]
+    report_delay: u64,
+}
+
+fn now() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+impl ReportCommand {
+    pub async fn run(&self) -> Result<()> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+
+        if !self.daily {
+            let window = match self.date {
+                Some(date) => Window::of(date, self.utc_offset),
+                None => Window::previous(now()?, self.utc_offset),
+            };
+            let snapshots = self.snapshot(&client).await?;
+            let digest = self.digest(window, &snapshots).await?;
+            return self.deliver(&client, &digest).await;
+        }
+
+        // Start with the current day
+        let mut window = Window::previous(now()?, self.utc_offset).next();
+        let mut snapshots = vec![];
+        loop {
+            let report_at = window.end + self.report_delay;
+            loop {
+                snapshots.extend(self.snapshot(&client).await?);
+                let now = now()?;
+                if now >= report_at {
+                    break;
+                }
+                sleep(Duration::from_secs(
+                    self.sample_interval.min(report_at - now),
+                ))
+                .await;
+            }
+
+            // Delivery failures must not stop the next days' digests
+            let delivered = match self.digest(window, &snapshots).await {
+                Ok(digest) => self.deliver(&client, &digest).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = delivered {
+                eprintln!("Failed to report {}: {:?}", window.date, e);
+            }
+
+            // Keep the snapshots before the next day, as baselines
+            snapshots.retain(|snapshot| snapshot.taken_at >= window.start);
+            window = window.next();
+        }
+    }
+
+    /// Snapshot the metrics of every endpoint. Endpoints that cannot be
+    /// scraped have no snapshot, and show as coverage gaps.
+    async fn snapshot(&self, client: &reqwest::Client) -> Result<Vec<Snapshot>> {
+        let mut snapshots = vec![];
+        for url in self.metrics_urls.iter() {
+            let scraped = async {
+                client
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await
+            };
+            match scraped.await {
+                Ok(text) => snapshots.push(Snapshot::parse(url, now()?, &text)),
+                Err(e) => eprintln!("Failed to snapshot metrics of {}: {}", url, e),
+            }
+        }
+        Ok(snapshots)
+    }
+
+    /// Digest of `window` from the dbs and `snapshots`
+    async fn digest(&self, window: Window, snapshots: &[Snapshot]) -> Result<Digest> {
+        let mut corridors = vec![];
+        let mut gas: BTreeMap<u32, ChainGas> = BTreeMap::new();
+        let mut gaps = vec![];
+        for entry in self.dbs.iter() {
+            let (home_name, db_path) = entry
+                .split_once('=')
+                .ok_or_else(|| eyre!("invalid --db {}, expected home_name=db_path", entry))?;
+            let db = NomadDB::new(home_name, crate::db::open_read_only(db_path).await?);
+
+            let (dispatched, gap) = dispatched_in(home_name, &db, &window)?;
+            gaps.extend(gap);
+
+            let processed = db.destination_gas_between(window.start, window.end)?;
+            let mut destinations: Vec<u32> = dispatched.keys().copied().collect();
+            destinations.extend(processed.keys().copied());
+            destinations.sort_unstable();
+            destinations.dedup();
+            for destination in destinations {
+                corridors.push(CorridorTotals {
+                    origin: home_name.to_owned(),
+                    destination,
+                    dispatched: dispatched.get(&destination).copied().unwrap_or_default(),
+                    processed: processed
+                        .get(&destination)
+                        .map_or(0, |totals| totals.messages),
+                });
+            }
+
+            for (destination, totals) in processed {
+                let chain = gas.entry(destination).or_insert(ChainGas {
+                    domain: destination,
+                    gas_used: 0,
+                    transactions: 0,
+                });
+                chain.gas_used = chain.gas_used.saturating_add(totals.gas_used);
+                chain.transactions += totals.transactions;
+            }
+        }
+
+        Ok(Digest::new(
+            window,
+            corridors,
+            gas.into_values().collect(),
+            gaps,
+            &self.metrics_urls,
+            snapshots,
+            self.sample_interval,
+        ))
+    }
+
+    /// Write the digest to the output directory and post it to the webhook,
+    /// or print it if neither is set
+    async fn deliver(&self, client: &reqwest::Client, digest: &Digest) -> Result<()> {
+        let markdown = digest.to_markdown();
+        let json = digest.to_json();
+
+        if let Some(out_dir) = self.out_dir.as_ref() {
+            std::fs::create_dir_all(out_dir)?;
+            let name = format!("digest-{}", digest.window.date);
+            std::fs::write(out_dir.join(format!("{}.md", name)), &markdown)?;
+            std::fs::write(
+                out_dir.join(format!("{}.json", name)),
+                serde_json::to_string_pretty(&json)?,
+            )?;
+        }
+
+        if let Some(webhook_url) = self.webhook_url.as_ref() {
+            let response = client
+                .post(webhook_url)
+                .json(&json!({ "text": markdown, "digest": json }))
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                bail!("Webhook rejected digest: {}", response.status());
+            }
+        }
+
+        if self.out_dir.is_none() && self.webhook_url.is_none() {
+            print!("{}", markdown);
+        }
+        Ok(())
+    }
+}
+
+/// Messages dispatched by the home in the window, by destination. Walks back
+/// from the latest message until one dispatched before the window. Messages
+/// indexed before dispatch times were stop the walk, and the part of the
+/// window they may fall in is returned as a gap.
+fn dispatched_in(
+    home_name: &str,
+    db: &NomadDB,
+    window: &Window,
+) -> Result<(BTreeMap<u32, u64>, Option<CoverageGap>)> {
+    let mut dispatched = BTreeMap::new();
+    let mut earliest = window.end;
+    let mut leaf_index = db.retrieve_latest_leaf_index()?;
+    while let Some(index) = leaf_index {
+        leaf_index = index.checked_sub(1);
+        let message = match db.message_by_leaf_index(index)? {
+            Some(message) => message,
+            None => continue,
+        };
+        let timestamp = match message.timestamp {
+            Some(timestamp) => timestamp,
+            None if earliest > window.start => {
+                return Ok((
+                    dispatched,
+                    Some(CoverageGap {
+                        source: format!("{} dispatches", home_name),
+                        from: window.start,
+                        to: earliest,
+                    }),
+                ))
+            }
+            None => break,
+        };
+        if timestamp < window.start {
+            break;
+        }
+        earliest = earliest.min(timestamp);
+        if timestamp < window.end {
+            let destination = NomadMessage::from_raw(&message.message)?.destination;
+            *dispatched.entry(destination).or_default() += 1;
+        }
+    }
+    Ok((dispatched, None))
+}
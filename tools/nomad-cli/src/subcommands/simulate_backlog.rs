@@ -0,0 +1,464 @@
+use color_eyre::{eyre::bail, Result};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    convert::{TryFrom, TryInto},
+    process::{Child, Command, Stdio},
+    sync::Arc,
+    time::Duration,
+};
+use structopt::StructOpt;
+
+use ethers::{
+    abi::AbiDecode,
+    prelude::{Http, Middleware, Provider, H160},
+    types::H256,
+};
+use nomad_base::NomadDB;
+use nomad_core::{CommittedMessage, ContractLocator, MessageStatus, Replica};
+use nomad_ethereum::{EthereumReplica, Simulation, StateOverrides, SubmitterClient, TxSubmitter};
+
+use crate::replicas;
+
+/// Key of the processor's next nonce by destination
+const PROCESSOR_NONCE_CURSOR: &str = "current_nonce_";
+/// Polls of a spawned anvil before giving up
+const ANVIL_READY_POLLS: usize = 50;
+
+type ReadOnlyReplica = EthereumReplica<Provider<Http>, Provider<Http>>;
+
+#[derive(StructOpt, Debug)]
+pub struct SimulateBacklogCommand {
+    /// Path to processor db
+    #[structopt(long)]
+    db_path: String,
+
+    /// Name of associated home
+    #[structopt(long)]
+    home_name: String,
+
+    /// Destination domain whose backlog to simulate
+    #[structopt(long)]
+    destination: u32,
+
+    /// RPC of a running fork of the destination
+    #[structopt(long, required_unless = "fork-url", conflicts_with = "fork-url")]
+    rpc: Option<String>,
+
+    /// Destination RPC to fork with a spawned anvil
+    #[structopt(long)]
+    fork_url: Option<String>,
+
+    /// Block to fork at. Defaults to the latest
+    #[structopt(long, requires = "fork-url")]
+    fork_block: Option<u64>,
+
+    /// Port of the spawned anvil
+    #[structopt(long, default_value = "8545")]
+    anvil_port: u16,
+
+    /// Replica address. Defaults to the known replica of the corridor
+    #[structopt(long)]
+    address: Option<H160>,
+
+    /// First nonce to simulate. Defaults to the processor's cursor
+    #[structopt(long)]
+    from_nonce: Option<u32>,
+
+    /// Simulate at most this many messages
+    #[structopt(long)]
+    limit: Option<usize>,
+
+    /// Storage slot of the replica's `confirmAt` mapping. When set, roots not
+    /// yet acceptable on the fork are overridden as confirmed
+    #[structopt(long)]
+    confirm_at_slot: Option<u64>,
+}
+
+/// Call at which a simulated message fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Prove,
+    Process,
+    Handle,
+}
+
+impl Stage {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Prove => "prove",
+            Stage::Process => "process",
+            Stage::Handle => "handle",
+        }
+    }
+}
+
+/// Simulated outcome of processing a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome {
+    AlreadyProcessed,
+    NoProof,
+    WouldSucceed,
+    WouldRevert { stage: Stage, reason: String },
+}
+
+impl Outcome {
+    /// `None` if `simulation` passed, or the revert it implies. `prove` and
+    /// `process` return false instead of reverting in some cases.
+    fn from_simulation(stage: Stage, simulation: Simulation) -> Option<Outcome> {
+        let reason = match simulation {
+            Simulation::Revert(reason) => reason,
+            Simulation::Success(data) => match stage {
+                Stage::Handle => return None,
+                Stage::Prove | Stage::Process => match bool::decode(&data) {
+                    Ok(true) => return None,
+                    Ok(false) => "returned false".to_owned(),
+                    Err(_) => format!("undecodable return data 0x{}", hex::encode(&data)),
+                },
+            },
+        };
+        Some(Outcome::WouldRevert { stage, reason })
+    }
+
+    fn status(&self) -> &'static str {
+        match self {
+            Outcome::AlreadyProcessed => "alreadyProcessed",
+            Outcome::NoProof => "noProof",
+            Outcome::WouldSucceed => "wouldSucceed",
+            Outcome::WouldRevert { .. } => "wouldRevert",
+        }
+    }
+}
+
+/// Spawned anvil fork, killed on drop
+struct Anvil(Child);
+
+impl Drop for Anvil {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn anvil_args(fork_url: &str, fork_block: Option<u64>, port: u16) -> Vec<String> {
+    let mut args = vec![
+        "--fork-url".to_owned(),
+        fork_url.to_owned(),
+        "--port".to_owned(),
+        port.to_string(),
+    ];
+    if let Some(block) = fork_block {
+        args.push("--fork-block-number".to_owned());
+        args.push(block.to_string());
+    }
+    args
+}
+
+impl SimulateBacklogCommand {
+    pub async fn run(&self) -> Result<()> {
+        let db = NomadDB::new(&self.home_name, crate::db::open(&self.db_path).await?);
+        let messages = self.pending_messages(&db)?;
+        if messages.is_empty() {
+            println!("[]");
+            return Ok(());
+        }
+
+        let (_anvil, provider) = self.fork().await?;
+        let address = match self.address {
+            Some(address) => address,
+            None => {
+                match replicas::address_by_domain_pair(messages[0].message.origin, self.destination)
+                {
+                    Some(address) => address,
+                    None => bail!("No known replica for this corridor. Pass --address"),
+                }
+            }
+        };
+        let provider = Arc::new(provider);
+        // No signer, and a submitter that refuses to submit
+        let replica: ReadOnlyReplica = EthereumReplica::new(
+            TxSubmitter::new(SubmitterClient::ReadOnly),
+            provider,
+            &ContractLocator {
+                name: "".into(),
+                domain: self.destination,
+                address: address.into(),
+            },
+            None,
+        );
+
+        let averages = recipient_averages(&db)?;
+        let mut entries = Vec::with_capacity(messages.len());
+        let (mut predicted_gas, mut unpredicted) = (0u64, 0usize);
+        for message in messages {
+            let (outcome, confirm_overridden) =
+                self.simulate(&db, &replica, address, &message).await?;
+            let average_gas = averages
+                .get(&(message.message.destination, message.message.recipient))
+                .copied();
+            if matches!(outcome, Outcome::WouldSucceed | Outcome::WouldRevert { .. }) {
+                match average_gas {
+                    Some(gas) => predicted_gas = predicted_gas.saturating_add(gas),
+                    None => unpredicted += 1,
+                }
+            }
+            entries.push(entry(&message, &outcome, confirm_overridden, average_gas));
+        }
+
+        let count = |status: &str| entries.iter().filter(|e| e["status"] == status).count();
+        eprintln!(
+            "{} would succeed, {} would revert, {} without proof, {} already processed",
+            count("wouldSucceed"),
+            count("wouldRevert"),
+            count("noProof"),
+            count("alreadyProcessed"),
+        );
+        eprintln!(
+            "predicted gas to process: {} ({} messages to recipients without recorded gas)",
+            predicted_gas, unpredicted,
+        );
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        Ok(())
+    }
+
+    /// Messages to the destination from the processor's cursor on
+    fn pending_messages(&self, db: &NomadDB) -> Result<Vec<CommittedMessage>> {
+        let from_nonce = match self.from_nonce {
+            Some(nonce) => nonce,
+            None => db
+                .retrieve_keyed_decodable(PROCESSOR_NONCE_CURSOR, &self.destination)?
+                .unwrap_or_default(),
+        };
+
+        let mut messages = vec![];
+        for nonce in from_nonce.. {
+            if self.limit.map_or(false, |limit| messages.len() >= limit) {
+                break;
+            }
+            match db.message_by_nonce(self.destination, nonce)? {
+                Some(raw) => messages.push(raw.try_into()?),
+                None => break,
+            }
+        }
+        Ok(messages)
+    }
+
+    async fn fork(&self) -> Result<(Option<Anvil>, Provider<Http>)> {
+        if let Some(rpc) = &self.rpc {
+            return Ok((None, Provider::<Http>::try_from(rpc.as_str())?));
+        }
+
+        let fork_url = self.fork_url.as_deref().expect("required by structopt");
+        let anvil = Anvil(
+            Command::new("anvil")
+                .args(anvil_args(fork_url, self.fork_block, self.anvil_port))
+                .stdout(Stdio::null())
+                .spawn()?,
+        );
+        let provider =
+            Provider::<Http>::try_from(format!("http://127.0.0.1:{}", self.anvil_port).as_str())?;
+
+        for _ in 0..ANVIL_READY_POLLS {
+            if provider.get_chainid().await.is_ok() {
+                return Ok((Some(anvil), provider));
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        bail!("anvil did not start on port {}", self.anvil_port)
+    }
+
+    /// Overrides making `root` acceptable, if it is not and the `confirmAt`
+    /// slot is known
+    async fn overrides_for(
+        &self,
+        replica: &ReadOnlyReplica,
+        address: H160,
+        root: H256,
+    ) -> Result<StateOverrides> {
+        let mut overrides = StateOverrides::default();
+        if let Some(slot) = self.confirm_at_slot {
+            if !replica.acceptable_root(root).await? {
+                overrides.set_mapping_entry(address, slot, root, H256::from_low_u64_be(1));
+            }
+        }
+        Ok(overrides)
+    }
+
+    async fn simulate(
+        &self,
+        db: &NomadDB,
+        replica: &ReadOnlyReplica,
+        address: H160,
+        message: &CommittedMessage,
+    ) -> Result<(Outcome, bool)> {
+        let leaf = message.to_leaf();
+
+        let overrides = match replica.message_status(leaf).await? {
+            MessageStatus::Processed => return Ok((Outcome::AlreadyProcessed, false)),
+            MessageStatus::None => {
+                let proof = match db.proof_by_leaf_index(message.leaf_index)? {
+                    Some(proof) => proof,
+                    None => return Ok((Outcome::NoProof, false)),
+                };
+                let overrides = self.overrides_for(replica, address, proof.root()).await?;
+                let prove = replica.simulate_prove(&proof, &overrides).await?;
+                if let Some(outcome) = Outcome::from_simulation(Stage::Prove, prove) {
+                    return Ok((outcome, !overrides.is_empty()));
+                }
+                overrides
+            }
+            MessageStatus::Proven(root) => {
+                let overrides = self.overrides_for(replica, address, root).await?;
+                match replica
+                    .simulate_process(&message.message, &overrides)
+                    .await?
+                {
+                    // process returns false when the handler reverts.
+                    // Simulate the handler for its reason
+                    Simulation::Success(data) if bool::decode(&data).ok() == Some(false) => {}
+                    process => {
+                        let outcome = Outcome::from_simulation(Stage::Process, process)
+                            .unwrap_or(Outcome::WouldSucceed);
+                        return Ok((outcome, !overrides.is_empty()));
+                    }
+                }
+                overrides
+            }
+        };
+
+        let handle = replica
+            .simulate_handle(&message.message, &overrides)
+            .await?;
+        let outcome =
+            Outcome::from_simulation(Stage::Handle, handle).unwrap_or(Outcome::WouldSucceed);
+        Ok((outcome, !overrides.is_empty()))
+    }
+}
+
+/// Average gas per message of each recipient over the retained days, as the
+/// processor recorded it
+fn recipient_averages(db: &NomadDB) -> Result<HashMap<(u32, H256), u64>> {
+    Ok(db
+        .recipient_gas_since(0)?
+        .into_iter()
+        .filter_map(|(destination, recipient, totals)| {
+            Some(((destination, recipient), totals.average_per_message()?))
+        })
+        .collect())
+}
+
+fn entry(
+    message: &CommittedMessage,
+    outcome: &Outcome,
+    confirm_overridden: bool,
+    average_gas: Option<u64>,
+) -> Value {
+    let (stage, reason) = match outcome {
+        Outcome::WouldRevert { stage, reason } => (Some(stage.as_str()), Some(reason.as_str())),
+        _ => (None, None),
+    };
+    json!({
+        "leaf": message.to_leaf(),
+        "leafIndex": message.leaf_index,
+        "origin": message.message.origin,
+        "sender": message.message.sender,
+        "nonce": message.message.nonce,
+        "destination": message.message.destination,
+        "recipient": message.message.recipient,
+        "status": outcome.status(),
+        "stage": stage,
+        "reason": reason,
+        "confirmOverridden": confirm_overridden,
+        "recipientAverageGas": average_gas,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::{abi::AbiEncode, types::Bytes};
+    use nomad_core::NomadMessage;
+
+    #[test]
+    fn it_classifies_simulations() {
+        let returned = |b: bool| Simulation::Success(Bytes::from(b.encode()));
+
+        assert_eq!(Outcome::from_simulation(Stage::Prove, returned(true)), None);
+        assert_eq!(
+            Outcome::from_simulation(Stage::Prove, returned(false)),
+            Some(Outcome::WouldRevert {
+                stage: Stage::Prove,
+                reason: "returned false".to_owned()
+            })
+        );
+        assert_eq!(
+            Outcome::from_simulation(Stage::Process, Simulation::Revert("!proven".to_owned())),
+            Some(Outcome::WouldRevert {
+                stage: Stage::Process,
+                reason: "!proven".to_owned()
+            })
+        );
+        // handlers may return nothing
+        assert_eq!(
+            Outcome::from_simulation(Stage::Handle, Simulation::Success(Bytes::default())),
+            None
+        );
+        assert!(matches!(
+            Outcome::from_simulation(Stage::Process, Simulation::Success(Bytes::default())),
+            Some(Outcome::WouldRevert {
+                stage: Stage::Process,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn it_reports_reasons_per_message() {
+        let message = CommittedMessage {
+            leaf_index: 4,
+            committed_root: H256::zero(),
+            message: NomadMessage {
+                origin: 1000,
+                sender: H256::repeat_byte(1),
+                nonce: 3,
+                destination: 2000,
+                recipient: H256::repeat_byte(2),
+                body: vec![],
+            },
+            timestamp: None,
+        };
+
+        let reverted = entry(
+            &message,
+            &Outcome::WouldRevert {
+                stage: Stage::Handle,
+                reason: "!valid".to_owned(),
+            },
+            true,
+            Some(120_000),
+        );
+        assert_eq!(reverted["status"], "wouldRevert");
+        assert_eq!(reverted["stage"], "handle");
+        assert_eq!(reverted["reason"], "!valid");
+        assert_eq!(reverted["confirmOverridden"], true);
+        assert_eq!(reverted["nonce"], 3);
+        assert_eq!(reverted["recipientAverageGas"], 120_000);
+
+        let succeeded = entry(&message, &Outcome::WouldSucceed, false, None);
+        assert_eq!(succeeded["status"], "wouldSucceed");
+        assert!(succeeded["reason"].is_null());
+        assert!(succeeded["recipientAverageGas"].is_null());
+    }
+
+    #[test]
+    fn it_forks_at_the_requested_block() {
+        assert_eq!(
+            anvil_args("http://rpc", None, 8545),
+            vec!["--fork-url", "http://rpc", "--port", "8545"]
+        );
+        assert_eq!(
+            anvil_args("http://rpc", Some(100), 9000)[4..],
+            ["--fork-block-number", "100"]
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use color_eyre::{eyre::bail, Result};
+use ethers::core::types::H256;
+use serde_json::{json, Value};
+use std::{collections::HashSet, convert::TryInto};
+use structopt::StructOpt;
+
+use nomad_base::{AuditEvent, AuditId, NomadDB};
+use nomad_core::{CommittedMessage, MessageRef};
+
+#[derive(StructOpt, Debug)]
+pub struct TraceCommand {
+    /// Path to processor db
+    #[structopt(long)]
+    db_path: String,
+
+    /// Name of associated home
+    #[structopt(long)]
+    home_name: String,
+
+    /// Message to trace, by leaf hash or id (`<origin>-<leaf index>`)
+    #[structopt(long, alias = "leaf", conflicts_with_all = &["destination", "nonce"])]
+    message: Option<MessageRef>,
+
+    /// Destination domain of the message to trace, with `--nonce`
+    #[structopt(long, requires = "nonce")]
+    destination: Option<u32>,
+
+    /// Destination nonce of the message to trace, with `--destination`
+    #[structopt(long, requires = "destination")]
+    nonce: Option<u32>,
+
+    /// Name of the destination replica, to report where its committed root
+    /// advanced
+    #[structopt(long)]
+    replica_name: Option<String>,
+}
+
+impl TraceCommand {
+    pub async fn run(&self) -> Result<()> {
+        let rocks = crate::db::open(&self.db_path).await?;
+        let db = NomadDB::new(&self.home_name, rocks.clone());
+
+        let raw = match (self.message, self.destination, self.nonce) {
+            (Some(message), _, _) => db.message_by_ref(message)?,
+            (None, Some(destination), Some(nonce)) => db.message_by_nonce(destination, nonce)?,
+            _ => bail!("Pass --message, or --destination and --nonce"),
+        };
+        let message: CommittedMessage = match raw {
+            Some(raw) => raw.try_into()?,
+            None => bail!("Message not found in db"),
+        };
+
+        let mut report = trace(&db, &message)?;
+        if let Some(replica_name) = &self.replica_name {
+            let replica_db = NomadDB::new(replica_name, rocks);
+            let processing_root = db.processing_root(message.to_leaf())?;
+            report["rootAdvance"] = json!(processing_root
+                .map(|record| replica_db.root_advance(record.root))
+                .transpose()?
+                .flatten());
+            report["latestRootAdvance"] = json!(replica_db.latest_root_advance()?);
+        }
+
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        Ok(())
+    }
+}
+
+/// What the processor db records about a message: its proof, whether it was
+/// submitted, the root it was submitted under with how the replica came to
+/// accept that root, and its submissions with their payloads
+fn trace(db: &NomadDB, message: &CommittedMessage) -> Result<Value> {
+    let leaf = message.to_leaf();
+    let proof_root = db
+        .proof_by_leaf_index(message.leaf_index)?
+        .map(|proof| proof.root());
+    let processing = db.processing_root(leaf)?.map(|record| {
+        json!({
+            "root": record.root,
+            "provenance": record.provenance,
+        })
+    });
+
+    Ok(json!({
+        "id": message.message_id(),
+        "leaf": leaf,
+        "leafIndex": message.leaf_index,
+        "origin": message.message.origin,
+        "sender": message.message.sender,
+        "nonce": message.message.nonce,
+        "destination": message.message.destination,
+        "recipient": message.message.recipient,
+        "proofRoot": proof_root,
+        "attempted": db.previously_attempted(message)?,
+        "processedFailed": db.processed_failed(leaf)?,
+        "processing": processing,
+        "submissions": submissions(db, leaf)?,
+    }))
+}
+
+/// Submission records of the processing decisions on `leaf`, oldest first,
+/// with the payloads of their transactions. Scans the whole audit trail.
+fn submissions(db: &NomadDB, leaf: H256) -> Result<Vec<Value>> {
+    let mut decisions = HashSet::new();
+    let mut submissions = vec![];
+    for record in db.audit_records(AuditId(0)) {
+        let record = record?;
+        match &record.event {
+            AuditEvent::ProcessMessage { leaf: decided, .. } if *decided == leaf => {
+                decisions.insert(record.id);
+            }
+            AuditEvent::Submitted { decision, .. }
+            | AuditEvent::SubmissionFailed { decision, .. }
+                if decisions.contains(decision) =>
+            {
+                let mut submission = serde_json::to_value(&record)?;
+                submission["payload"] = json!(db
+                    .submission_payload(record.id)?
+                    .map(|payload| payload.report()));
+                submissions.push(submission);
+            }
+            _ => {}
+        }
+    }
+    Ok(submissions)
+}
@@ -0,0 +1,35 @@
+use color_eyre::{eyre::bail, Result};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub struct UpdaterHandoffCommand {
+    /// Admin endpoint of the active updater instance
+    #[structopt(long, default_value = "http://localhost:9091")]
+    admin_url: String,
+
+    /// Token the updater's admin endpoint requires
+    #[structopt(long, env = "ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: String,
+}
+
+impl UpdaterHandoffCommand {
+    pub async fn run(&self) -> Result<()> {
+        let url = format!("{}/handoff", self.admin_url.trim_end_matches('/'));
+        let response = reqwest::Client::new()
+            .post(&url)
+            .bearer_auth(&self.admin_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            bail!(
+                "Updater at {} rejected handoff request: {}",
+                self.admin_url,
+                response.status()
+            );
+        }
+
+        println!("Handoff requested. The active updater releases its lease on its next heartbeat.");
+        Ok(())
+    }
+}
@@ -0,0 +1,957 @@
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use ethers::prelude::{Http, Middleware, Provider, H256};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap, convert::TryFrom, path::PathBuf, str::FromStr, sync::Arc, time::Duration,
+};
+use structopt::StructOpt;
+use tokio::time::{sleep, Instant};
+
+use nomad_base::NomadDB;
+use nomad_core::{Address32, BridgeAction, BridgeMessage, Decode, NomadMessage};
+use nomad_ethereum_bindings::{home::Home, replica::Replica};
+use nomad_xyz_configuration::{
+    bridge::BridgeDeploymentInfo, core::CoreDeploymentInfo, NomadConfig,
+};
+
+/// Most blocks read from a network in one poll
+const MAX_BLOCK_RANGE: u64 = 2000;
+
+/// Longest wait before polling a failing network again
+const MAX_BACKOFF: Duration = Duration::from_secs(120);
+
+#[derive(StructOpt, Debug)]
+pub struct WatchCommand {
+    /// Nomad config file. Defaults to the builtin config of --env
+    #[structopt(long)]
+    config_path: Option<PathBuf>,
+
+    /// Environment of the builtin config
+    #[structopt(long, default_value = "production")]
+    env: String,
+
+    /// RPC of a network, as `network=url`, in place of the config's. Repeat
+    /// for each network
+    #[structopt(long = "rpc")]
+    rpcs: Vec<String>,
+
+    /// Corridor to watch, as `origin:destination` network names. Repeat for
+    /// each corridor. Defaults to every corridor of the config
+    #[structopt(long = "corridor")]
+    corridors: Vec<Corridor>,
+
+    /// Only show messages from this sender, as an EVM address or a 32-byte
+    /// value. Hides updates
+    #[structopt(long)]
+    sender: Option<Address32>,
+
+    /// Only show messages to this recipient, as an EVM address or a 32-byte
+    /// value. Hides updates
+    #[structopt(long)]
+    recipient: Option<Address32>,
+
+    /// Processor db of a home, as `home_name=db_path`, for the dispatch of
+    /// messages processed but dispatched before the stream started. Repeat
+    /// for each home
+    #[structopt(long = "db")]
+    dbs: Vec<String>,
+
+    /// Print one JSON object per event instead of a line of text
+    #[structopt(long)]
+    json: bool,
+
+    /// Print lines without colors. Also set by the NO_COLOR environment
+    /// variable
+    #[structopt(long)]
+    no_color: bool,
+
+    /// Seconds between polls of each network
+    #[structopt(long, default_value = "5")]
+    interval: u64,
+
+    /// Blocks before each network's head to start streaming from
+    #[structopt(long, default_value = "0")]
+    backfill: u64,
+}
+
+/// Messages from an origin network to a destination network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Corridor {
+    origin: String,
+    destination: String,
+}
+
+impl FromStr for Corridor {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some((origin, destination)) if !origin.is_empty() && !destination.is_empty() => {
+                Ok(Self {
+                    origin: origin.to_owned(),
+                    destination: destination.to_owned(),
+                })
+            }
+            _ => bail!("invalid corridor {}, expected origin:destination", s),
+        }
+    }
+}
+
+/// Which events are shown
+#[derive(Debug, Default)]
+struct Filters {
+    corridors: Vec<Corridor>,
+    sender: Option<Address32>,
+    recipient: Option<Address32>,
+}
+
+impl Filters {
+    fn watches_origin(&self, origin: &str) -> bool {
+        self.corridors.iter().any(|c| c.origin == origin)
+    }
+
+    fn watches(&self, origin: &str, destination: &str) -> bool {
+        self.corridors
+            .iter()
+            .any(|c| c.origin == origin && c.destination == destination)
+    }
+
+    fn shows_updates(&self) -> bool {
+        self.sender.is_none() && self.recipient.is_none()
+    }
+
+    fn shows_event(&self, event: &Event, registry: &Registry) -> bool {
+        match &event.activity {
+            Activity::Dispatch { message, .. } => self.shows(
+                &registry.name(message.origin),
+                &registry.name(message.destination),
+                Some(message),
+            ),
+            Activity::Update { .. } => self.shows_updates() && self.watches_origin(&event.network),
+            Activity::Process {
+                origin, message, ..
+            } => self.shows(origin, &event.network, message.as_ref()),
+        }
+    }
+
+    /// Whether to show a message of the corridor. Messages not known, as
+    /// when processed but neither dispatched while watching nor in a db,
+    /// are only shown without sender and recipient filters.
+    fn shows(&self, origin: &str, destination: &str, message: Option<&NomadMessage>) -> bool {
+        if !self.watches(origin, destination) {
+            return false;
+        }
+        match message {
+            Some(message) => {
+                self.sender.map_or(true, |s| s.0 == message.sender)
+                    && self.recipient.map_or(true, |r| r.0 == message.recipient)
+            }
+            None => self.shows_updates(),
+        }
+    }
+}
+
+/// Names of domains and bridge routers of the config
+#[derive(Debug, Default)]
+struct Registry {
+    names: HashMap<u32, String>,
+    bridge_routers: HashMap<u32, H256>,
+}
+
+impl Registry {
+    fn new(config: &NomadConfig) -> Self {
+        let mut registry = Self::default();
+        for (name, network) in config.protocol().networks.iter() {
+            registry.names.insert(network.domain, name.clone());
+            if let Some(BridgeDeploymentInfo::Ethereum(bridge)) = config.bridge().get(name) {
+                registry
+                    .bridge_routers
+                    .insert(network.domain, *bridge.bridge_router.proxy);
+            }
+        }
+        registry
+    }
+
+    fn name(&self, domain: u32) -> String {
+        self.names
+            .get(&domain)
+            .cloned()
+            .unwrap_or_else(|| domain.to_string())
+    }
+
+    /// Bridge message of `message`, if sent by its origin's bridge router
+    fn bridge_message(&self, message: &NomadMessage) -> Option<BridgeMessage> {
+        if self.bridge_routers.get(&message.origin) != Some(&message.sender) {
+            return None;
+        }
+        BridgeMessage::read_from(&mut message.body.as_slice()).ok()
+    }
+}
+
+/// Activity read from a network
+#[derive(Debug, Clone, PartialEq)]
+enum Activity {
+    /// A message dispatched from the network's home
+    Dispatch {
+        message: NomadMessage,
+        leaf_index: u32,
+        committed_root: H256,
+    },
+    /// An update of the network's home
+    Update { old_root: H256, new_root: H256 },
+    /// A message from `origin` processed by its replica on the network
+    Process {
+        origin: String,
+        leaf: H256,
+        success: bool,
+        message: Option<NomadMessage>,
+    },
+}
+
+/// Activity with where and when it was emitted
+#[derive(Debug, Clone, PartialEq)]
+struct Event {
+    network: String,
+    block: u64,
+    transaction: H256,
+    timestamp: u64,
+    activity: Activity,
+    /// Seconds since the dispatch of the message, or of the earliest
+    /// message an update commits to, if known
+    latency: Option<u64>,
+}
+
+/// Human form of a number of seconds
+fn format_latency(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+fn paint(text: &str, color: &str, colored: bool) -> String {
+    if colored {
+        format!("\x1b[{}m{}\x1b[0m", color, text)
+    } else {
+        text.to_owned()
+    }
+}
+
+impl Event {
+    /// One line describing the event
+    fn line(&self, registry: &Registry, colored: bool) -> String {
+        let mut line = match &self.activity {
+            Activity::Dispatch { message, .. } => {
+                let mut line = format!(
+                    "{} {} {}→{} nonce {} from {} to {}",
+                    paint("DISPATCH", "36", colored),
+                    message.to_leaf(),
+                    registry.name(message.origin),
+                    registry.name(message.destination),
+                    message.nonce,
+                    Address32(message.sender).readable(),
+                    Address32(message.recipient).readable(),
+                );
+                if let Some(bridge) = registry.bridge_message(message) {
+                    line.push_str(&format!(" | {}", describe_bridge(registry, &bridge)));
+                }
+                line
+            }
+            Activity::Update { old_root, new_root } => format!(
+                "{} {} {}→{}",
+                paint("UPDATE  ", "33", colored),
+                self.network,
+                old_root,
+                new_root,
+            ),
+            Activity::Process {
+                origin,
+                leaf,
+                success,
+                ..
+            } => format!(
+                "{} {} {}→{} {}",
+                paint("PROCESS ", "32", colored),
+                leaf,
+                origin,
+                self.network,
+                if *success {
+                    paint("ok", "32", colored)
+                } else {
+                    paint("failed", "31", colored)
+                },
+            ),
+        };
+        line.push_str(&format!(
+            " {}",
+            paint(&format!("[{} #{}]", self.network, self.block), "2", colored)
+        ));
+        if let Some(latency) = self.latency {
+            line.push_str(&format!(" +{}", format_latency(latency)));
+        }
+        line
+    }
+
+    /// JSON form of the event
+    fn to_json(&self, registry: &Registry) -> Value {
+        let mut value = json!({
+            "network": self.network,
+            "block": self.block,
+            "transaction": format!("{:?}", self.transaction),
+            "timestamp": self.timestamp,
+            "latency_secs": self.latency,
+        });
+        let fields = match &self.activity {
+            Activity::Dispatch {
+                message,
+                leaf_index,
+                committed_root,
+            } => {
+                let mut fields = json!({
+                    "event": "dispatch",
+                    "leaf_index": leaf_index,
+                    "committed_root": format!("{:?}", committed_root),
+                    "message": message_json(registry, message),
+                });
+                if let Some(bridge) = registry.bridge_message(message) {
+                    fields["bridge"] = bridge_json(registry, &bridge);
+                }
+                fields
+            }
+            Activity::Update { old_root, new_root } => json!({
+                "event": "update",
+                "old_root": format!("{:?}", old_root),
+                "new_root": format!("{:?}", new_root),
+            }),
+            Activity::Process {
+                origin,
+                leaf,
+                success,
+                message,
+            } => json!({
+                "event": "process",
+                "origin": origin,
+                "leaf": format!("{:?}", leaf),
+                "success": success,
+                "message": message.as_ref().map(|m| message_json(registry, m)),
+            }),
+        };
+        if let (Value::Object(value), Value::Object(fields)) = (&mut value, fields) {
+            value.extend(fields);
+        }
+        value
+    }
+}
+
+fn message_json(registry: &Registry, message: &NomadMessage) -> Value {
+    json!({
+        "leaf": format!("{:?}", message.to_leaf()),
+        "origin": registry.name(message.origin),
+        "destination": registry.name(message.destination),
+        "nonce": message.nonce,
+        "sender": Address32(message.sender).readable().to_string(),
+        "recipient": Address32(message.recipient).readable().to_string(),
+    })
+}
+
+fn describe_bridge(registry: &Registry, bridge: &BridgeMessage) -> String {
+    let (action, transfer) = match &bridge.action {
+        BridgeAction::Transfer(transfer) => ("transfer", transfer),
+        BridgeAction::FastTransfer(transfer) => ("fast transfer", transfer),
+    };
+    format!(
+        "{} {} of {}:{} to {}",
+        action,
+        transfer.amount,
+        registry.name(bridge.token.domain),
+        Address32(bridge.token.id).readable(),
+        Address32(transfer.recipient).readable(),
+    )
+}
+
+fn bridge_json(registry: &Registry, bridge: &BridgeMessage) -> Value {
+    let transfer = bridge.action.transfer();
+    json!({
+        "action": match bridge.action {
+            BridgeAction::Transfer(_) => "transfer",
+            BridgeAction::FastTransfer(_) => "fast_transfer",
+        },
+        "token_domain": registry.name(bridge.token.domain),
+        "token": Address32(bridge.token.id).readable().to_string(),
+        "amount": transfer.amount.to_string(),
+        "recipient": Address32(transfer.recipient).readable().to_string(),
+    })
+}
+
+/// A watched network and how far it has been read
+struct Network {
+    name: String,
+    provider: Arc<Provider<Http>>,
+    /// The network's home, if messages from it are watched
+    home: Option<Home<Provider<Http>>>,
+    /// Replicas on the network of watched origins, by origin
+    replicas: Vec<(String, Replica<Provider<Http>>)>,
+    /// Next block to read. Only advanced once its logs were all read, so
+    /// failed polls read the same blocks again.
+    cursor: Option<u64>,
+    /// Head of the network at the last poll
+    head: u64,
+    /// Consecutive failed polls
+    failures: u32,
+    /// When to poll next
+    poll_at: Instant,
+}
+
+impl Network {
+    /// Read the events of the blocks after the cursor, up to the head or
+    /// the block range
+    async fn poll(&mut self, backfill: u64) -> Result<Vec<Event>> {
+        let head = self.provider.get_block_number().await?.as_u64();
+        self.head = head;
+        let from = *self
+            .cursor
+            .get_or_insert_with(|| head.saturating_sub(backfill));
+        if from > head {
+            return Ok(vec![]);
+        }
+        let to = head.min(from + MAX_BLOCK_RANGE - 1);
+
+        let mut activity = vec![];
+        if let Some(home) = self.home.as_ref() {
+            for (dispatch, meta) in home
+                .dispatch_filter()
+                .from_block(from)
+                .to_block(to)
+                .query_with_meta()
+                .await?
+            {
+                let message = NomadMessage::from_raw(dispatch.message.as_ref())?;
+                let leaf_index = u32::try_from(dispatch.leaf_index)
+                    .map_err(|_| eyre!("leaf index {} out of range", dispatch.leaf_index))?;
+                activity.push((
+                    Activity::Dispatch {
+                        message,
+                        leaf_index,
+                        committed_root: dispatch.committed_root.into(),
+                    },
+                    meta,
+                ));
+            }
+            for (update, meta) in home
+                .update_filter()
+                .from_block(from)
+                .to_block(to)
+                .query_with_meta()
+                .await?
+            {
+                activity.push((
+                    Activity::Update {
+                        old_root: update.old_root.into(),
+                        new_root: update.new_root.into(),
+                    },
+                    meta,
+                ));
+            }
+        }
+        for (origin, replica) in self.replicas.iter() {
+            for (process, meta) in replica
+                .process_filter()
+                .from_block(from)
+                .to_block(to)
+                .query_with_meta()
+                .await?
+            {
+                activity.push((
+                    Activity::Process {
+                        origin: origin.clone(),
+                        leaf: process.message_hash.into(),
+                        success: process.success,
+                        message: None,
+                    },
+                    meta,
+                ));
+            }
+        }
+        activity.sort_by_key(|(_, meta)| (meta.block_number, meta.log_index));
+
+        let mut timestamps: HashMap<u64, u64> = HashMap::new();
+        let mut events = Vec::with_capacity(activity.len());
+        for (activity, meta) in activity {
+            let block = meta.block_number.as_u64();
+            let timestamp = match timestamps.get(&block) {
+                Some(timestamp) => *timestamp,
+                None => {
+                    let timestamp = self
+                        .provider
+                        .get_block(block)
+                        .await?
+                        .ok_or_else(|| eyre!("block {} of {} not found", block, self.name))?
+                        .timestamp
+                        .as_u64();
+                    timestamps.insert(block, timestamp);
+                    timestamp
+                }
+            };
+            events.push(Event {
+                network: self.name.clone(),
+                block,
+                transaction: meta.transaction_hash,
+                timestamp,
+                activity,
+                latency: None,
+            });
+        }
+
+        self.cursor = Some(to + 1);
+        Ok(events)
+    }
+
+    /// Wait longer after each consecutive failure, up to the maximum
+    fn back_off(&mut self) -> Duration {
+        self.failures += 1;
+        let wait = Duration::from_secs(1 << self.failures.min(7)).min(MAX_BACKOFF);
+        self.poll_at = Instant::now() + wait;
+        wait
+    }
+}
+
+/// Dispatches seen, for annotating the updates and processes after them
+#[derive(Debug, Default)]
+struct Correlations {
+    /// Dispatched messages and their timestamps, by leaf
+    dispatched: HashMap<H256, (NomadMessage, u64)>,
+    /// Earliest dispatch under each committed root, by home network
+    earliest_under_root: HashMap<(String, H256), u64>,
+    /// Processor dbs, by home network
+    dbs: HashMap<String, NomadDB>,
+}
+
+impl Correlations {
+    /// Record the dispatch, update and process correlations of `event`,
+    /// and fill in its latency and, for processes, its message
+    fn correlate(&mut self, event: &mut Event) {
+        match &mut event.activity {
+            Activity::Dispatch {
+                message,
+                committed_root,
+                ..
+            } => {
+                self.dispatched
+                    .insert(message.to_leaf(), (message.clone(), event.timestamp));
+                self.earliest_under_root
+                    .entry((event.network.clone(), *committed_root))
+                    .or_insert(event.timestamp);
+            }
+            Activity::Update { old_root, .. } => {
+                event.latency = self
+                    .earliest_under_root
+                    .remove(&(event.network.clone(), *old_root))
+                    .map(|dispatched| event.timestamp.saturating_sub(dispatched));
+            }
+            Activity::Process {
+                origin,
+                leaf,
+                message,
+                ..
+            } => {
+                let dispatched = self.dispatched.remove(leaf).or_else(|| {
+                    let db = self.dbs.get(origin.as_str())?;
+                    let raw = db.message_by_leaf(*leaf).ok()??;
+                    Some((NomadMessage::from_raw(&raw.message).ok()?, raw.timestamp?))
+                });
+                if let Some((dispatched, timestamp)) = dispatched {
+                    event.latency = Some(event.timestamp.saturating_sub(timestamp));
+                    *message = Some(dispatched);
+                }
+            }
+        }
+    }
+}
+
+impl WatchCommand {
+    pub async fn run(&self) -> Result<()> {
+        let config = match self.config_path.as_ref() {
+            Some(path) => NomadConfig::from_file(path)?,
+            None => nomad_xyz_configuration::get_builtin(&self.env)
+                .ok_or_else(|| eyre!("no builtin config for {}", self.env))?
+                .to_owned(),
+        };
+        let registry = Registry::new(&config);
+        let filters = Filters {
+            corridors: self.corridors(&config),
+            sender: self.sender,
+            recipient: self.recipient,
+        };
+        if filters.corridors.is_empty() {
+            bail!("no corridors to watch");
+        }
+        let mut networks = self.networks(&config, &filters)?;
+
+        let mut correlations = Correlations::default();
+        for entry in self.dbs.iter() {
+            let (home_name, db_path) = entry
+                .split_once('=')
+                .ok_or_else(|| eyre!("invalid --db {}, expected home_name=db_path", entry))?;
+            let db = NomadDB::new(home_name, crate::db::open_read_only(db_path).await?);
+            correlations.dbs.insert(home_name.to_owned(), db);
+        }
+
+        let colored = !self.json && !self.no_color && std::env::var_os("NO_COLOR").is_none();
+        let interval = Duration::from_secs(self.interval);
+        loop {
+            for network in networks.iter_mut() {
+                if Instant::now() < network.poll_at {
+                    continue;
+                }
+                match network.poll(self.backfill).await {
+                    Ok(events) => {
+                        network.failures = 0;
+                        // Catch up on blocks past the range without waiting
+                        network.poll_at = if network.cursor.map_or(false, |c| c <= network.head) {
+                            Instant::now()
+                        } else {
+                            Instant::now() + interval
+                        };
+                        for mut event in events {
+                            correlations.correlate(&mut event);
+                            if !filters.shows_event(&event, &registry) {
+                                continue;
+                            }
+                            if self.json {
+                                println!("{}", event.to_json(&registry));
+                            } else {
+                                println!("{}", event.line(&registry, colored));
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        // The cursor stays, so the blocks are read again
+                        let wait = network.back_off();
+                        eprintln!(
+                            "Failed to read {} from block {}, retrying in {}s: {}",
+                            network.name,
+                            network.cursor.map_or("head".to_owned(), |c| c.to_string()),
+                            wait.as_secs(),
+                            e
+                        );
+                    }
+                }
+            }
+
+            let next = networks
+                .iter()
+                .map(|network| network.poll_at)
+                .min()
+                .expect("networks are watched");
+            let now = Instant::now();
+            if next > now {
+                sleep(next - now).await;
+            }
+        }
+    }
+
+    /// The corridors given, or every corridor of the config
+    fn corridors(&self, config: &NomadConfig) -> Vec<Corridor> {
+        if !self.corridors.is_empty() {
+            return self.corridors.clone();
+        }
+        let mut corridors = vec![];
+        for (destination, core) in config.core().iter() {
+            if let CoreDeploymentInfo::Ethereum(core) = core {
+                for origin in core.replicas.keys() {
+                    corridors.push(Corridor {
+                        origin: origin.clone(),
+                        destination: destination.clone(),
+                    });
+                }
+            }
+        }
+        corridors.sort_by(|a, b| (&a.origin, &a.destination).cmp(&(&b.origin, &b.destination)));
+        corridors
+    }
+
+    fn rpc(&self, config: &NomadConfig, network: &str) -> Result<String> {
+        for entry in self.rpcs.iter() {
+            let (name, url) = entry
+                .split_once('=')
+                .ok_or_else(|| eyre!("invalid --rpc {}, expected network=url", entry))?;
+            if name == network {
+                return Ok(url.to_owned());
+            }
+        }
+        config
+            .rpcs
+            .get(network)
+            .and_then(|rpcs| rpcs.iter().next())
+            .cloned()
+            .ok_or_else(|| eyre!("no RPC for {}, pass --rpc {}=<url>", network, network))
+    }
+
+    /// The networks with homes or replicas of the watched corridors
+    fn networks(&self, config: &NomadConfig, filters: &Filters) -> Result<Vec<Network>> {
+        let mut names: Vec<&String> = filters
+            .corridors
+            .iter()
+            .flat_map(|c| [&c.origin, &c.destination])
+            .collect();
+        names.sort();
+        names.dedup();
+
+        let mut networks = vec![];
+        for name in names {
+            let core = match config.core().get(name.as_str()) {
+                Some(CoreDeploymentInfo::Ethereum(core)) => core,
+                _ => bail!("no EVM core deployment of {}", name),
+            };
+            let provider = Arc::new(Provider::<Http>::try_from(self.rpc(config, name)?)?);
+
+            let home = if filters.watches_origin(name) {
+                Some(Home::new(
+                    core.home.proxy.as_ethereum_address()?,
+                    provider.clone(),
+                ))
+            } else {
+                None
+            };
+            let mut replicas = vec![];
+            for (origin, replica) in core.replicas.iter() {
+                if filters.watches(origin, name) {
+                    replicas.push((
+                        origin.clone(),
+                        Replica::new(replica.proxy.as_ethereum_address()?, provider.clone()),
+                    ));
+                }
+            }
+
+            networks.push(Network {
+                name: name.clone(),
+                provider,
+                home,
+                replicas,
+                cursor: None,
+                head: 0,
+                failures: 0,
+                poll_at: Instant::now(),
+            });
+        }
+        Ok(networks)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::prelude::U256;
+    use nomad_core::{Encode, TokenId, Transfer};
+
+    const ETHEREUM: u32 = 6648936;
+    const MOONBEAM: u32 = 1650811245;
+
+    fn registry() -> Registry {
+        Registry {
+            names: [
+                (ETHEREUM, "ethereum".to_owned()),
+                (MOONBEAM, "moonbeam".to_owned()),
+            ]
+            .into_iter()
+            .collect(),
+            bridge_routers: [(ETHEREUM, H256::repeat_byte(0xbb))].into_iter().collect(),
+        }
+    }
+
+    fn transfer() -> BridgeMessage {
+        BridgeMessage {
+            token: TokenId {
+                domain: ETHEREUM,
+                id: Address32::from_evm([0xcc; 20].into()).0,
+            },
+            action: BridgeAction::Transfer(Transfer {
+                recipient: Address32::from_evm([0xdd; 20].into()).0,
+                amount: U256::from(1_000_000u64),
+                details_hash: H256::repeat_byte(0xee),
+            }),
+        }
+    }
+
+    fn message(sender: H256, body: Vec<u8>) -> NomadMessage {
+        NomadMessage {
+            origin: ETHEREUM,
+            sender,
+            nonce: 4,
+            destination: MOONBEAM,
+            recipient: H256::repeat_byte(0xbb),
+            body,
+        }
+    }
+
+    fn corridor(s: &str) -> Corridor {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn it_round_trips_bridge_messages() {
+        let transfer = transfer();
+        let encoded = transfer.to_vec();
+        assert_eq!(encoded.len(), 133);
+        assert_eq!(encoded[36], 3);
+        assert_eq!(
+            BridgeMessage::read_from(&mut encoded.as_slice()).unwrap(),
+            transfer
+        );
+
+        let mut unknown = encoded.clone();
+        unknown[36] = 9;
+        assert!(BridgeMessage::read_from(&mut unknown.as_slice()).is_err());
+        assert!(BridgeMessage::read_from(&mut &encoded[..100]).is_err());
+    }
+
+    #[test]
+    fn it_decodes_bridge_messages_of_bridge_routers_only() {
+        let registry = registry();
+        let body = transfer().to_vec();
+        assert_eq!(
+            registry.bridge_message(&message(H256::repeat_byte(0xbb), body.clone())),
+            Some(transfer())
+        );
+        assert_eq!(
+            registry.bridge_message(&message(H256::repeat_byte(0xaa), body)),
+            None
+        );
+    }
+
+    #[test]
+    fn it_parses_corridors() {
+        assert_eq!(
+            corridor("ethereum:moonbeam"),
+            Corridor {
+                origin: "ethereum".to_owned(),
+                destination: "moonbeam".to_owned()
+            }
+        );
+        assert!("ethereum".parse::<Corridor>().is_err());
+        assert!("ethereum:".parse::<Corridor>().is_err());
+    }
+
+    #[test]
+    fn it_filters_by_corridor_sender_and_recipient() {
+        let sent = message(H256::repeat_byte(0xaa), vec![]);
+        let mut filters = Filters {
+            corridors: vec![corridor("ethereum:moonbeam")],
+            ..Default::default()
+        };
+        assert!(filters.shows("ethereum", "moonbeam", Some(&sent)));
+        assert!(!filters.shows("moonbeam", "ethereum", Some(&sent)));
+        assert!(filters.shows("ethereum", "moonbeam", None));
+
+        filters.sender = Some(Address32(H256::repeat_byte(0xaa)));
+        assert!(filters.shows("ethereum", "moonbeam", Some(&sent)));
+        assert!(!filters.shows("ethereum", "moonbeam", None));
+        assert!(!filters.shows_updates());
+
+        filters.recipient = Some(Address32(H256::repeat_byte(0xaa)));
+        assert!(!filters.shows("ethereum", "moonbeam", Some(&sent)));
+    }
+
+    #[test]
+    fn it_annotates_latencies_from_dispatches() {
+        let sent = message(H256::repeat_byte(0xaa), vec![]);
+        let leaf = sent.to_leaf();
+        let event = |network: &str, timestamp, activity| Event {
+            network: network.to_owned(),
+            block: 1,
+            transaction: H256::zero(),
+            timestamp,
+            activity,
+            latency: None,
+        };
+        let mut correlations = Correlations::default();
+
+        let mut dispatch = event(
+            "ethereum",
+            1000,
+            Activity::Dispatch {
+                message: sent.clone(),
+                leaf_index: 0,
+                committed_root: H256::repeat_byte(1),
+            },
+        );
+        correlations.correlate(&mut dispatch);
+        assert_eq!(dispatch.latency, None);
+
+        let mut update = event(
+            "ethereum",
+            1090,
+            Activity::Update {
+                old_root: H256::repeat_byte(1),
+                new_root: H256::repeat_byte(2),
+            },
+        );
+        correlations.correlate(&mut update);
+        assert_eq!(update.latency, Some(90));
+
+        let mut process = event(
+            "moonbeam",
+            3000,
+            Activity::Process {
+                origin: "ethereum".to_owned(),
+                leaf,
+                success: true,
+                message: None,
+            },
+        );
+        correlations.correlate(&mut process);
+        assert_eq!(process.latency, Some(2000));
+        assert!(matches!(
+            &process.activity,
+            Activity::Process { message: Some(m), .. } if *m == sent
+        ));
+        assert_eq!(
+            process.line(&registry(), false),
+            format!(
+                "PROCESS  {} ethereum→moonbeam ok [moonbeam #1] +33m20s",
+                leaf
+            )
+        );
+        assert_eq!(process.to_json(&registry())["latency_secs"], 2000);
+    }
+
+    #[test]
+    fn it_formats_dispatch_lines() {
+        let sent = message(H256::repeat_byte(0xbb), transfer().to_vec());
+        let dispatch = Event {
+            network: "ethereum".to_owned(),
+            block: 15_000_000,
+            transaction: H256::zero(),
+            timestamp: 0,
+            activity: Activity::Dispatch {
+                message: sent.clone(),
+                leaf_index: 7,
+                committed_root: H256::zero(),
+            },
+            latency: None,
+        };
+        let line = dispatch.line(&registry(), false);
+        assert!(line.starts_with(&format!(
+            "DISPATCH {} ethereum→moonbeam nonce 4",
+            sent.to_leaf()
+        )));
+        assert!(line.contains("| transfer 1000000 of ethereum:"));
+        assert!(line.ends_with("[ethereum #15000000]"));
+
+        let json = dispatch.to_json(&registry());
+        assert_eq!(json["event"], "dispatch");
+        assert_eq!(json["message"]["destination"], "moonbeam");
+        assert_eq!(json["bridge"]["amount"], "1000000");
+    }
+
+    #[test]
+    fn it_formats_latencies() {
+        assert_eq!(format_latency(42), "42s");
+        assert_eq!(format_latency(125), "2m05s");
+        assert_eq!(format_latency(3 * 3600 + 4 * 60), "3h04m");
+    }
+}